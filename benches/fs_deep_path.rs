@@ -0,0 +1,66 @@
+/* Benchmarks for path resolution on deep paths, with and without the dentry
+ * cache.  As elsewhere, results aren't checked to avoid biasing them.  */
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use rustposix::interface;
+
+use rustposix::safeposix::cage::*;
+use rustposix::safeposix::filesystem::dentry_cache_set_enabled;
+
+// Using this to include my criterion settings from a single shared file.
+mod global_criterion_settings;
+
+// How many directories deep the file being looked up is
+const DEPTH: usize = 16;
+
+pub fn run_benchmark(c: &mut Criterion) {
+    rustposix::safeposix::dispatcher::lindrustinit(0);
+
+    let cage = interface::cagetable_getref(1);
+
+    // Build /deep/d1/d2/.../dN/file
+    let mut path = String::from("/deep");
+    cage.mkdir_syscall(&path, S_IRWXA);
+    for level in 1..DEPTH {
+        path.push_str(&format!("/d{}", level));
+        cage.mkdir_syscall(&path, S_IRWXA);
+    }
+    path.push_str("/file");
+    let fd = cage.open_syscall(&path, O_CREAT | O_WRONLY, S_IRWXA);
+    cage.close_syscall(fd);
+
+    // --- COMPARING stat / open+close ON A DEEP PATH WITH AND WITHOUT THE CACHE ---
+    let mut group = c.benchmark_group("Compare fs:deep path resolution");
+
+    group.plot_config(
+        criterion::PlotConfiguration::default().summary_scale(criterion::AxisScale::Linear),
+    );
+
+    for (name, enabled) in [("uncached", false), ("cached", true)] {
+        dentry_cache_set_enabled(enabled);
+
+        group.bench_function(format!("TF10: Lind stat, {}", name), |b| {
+            let mut statdata = StatData::default();
+            b.iter(|| {
+                cage.stat_syscall(&path, &mut statdata);
+            })
+        });
+
+        group.bench_function(format!("TF10: Lind open+close, {}", name), |b| {
+            b.iter(|| {
+                let fd = cage.open_syscall(&path, O_RDONLY, 0);
+                cage.close_syscall(fd);
+            })
+        });
+    }
+    group.finish();
+
+    rustposix::safeposix::dispatcher::lindrustfinalize();
+}
+
+criterion_group!(name=benches;
+                 // Add the global settings here so we don't type it everywhere
+                 config=global_criterion_settings::get_criterion();
+                 targets=run_benchmark);
+criterion_main!(benches);
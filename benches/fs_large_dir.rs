@@ -0,0 +1,83 @@
+/* Benchmarks for listing and looking up entries in a directory with a very
+ * large number of entries.  As elsewhere, results aren't checked to avoid
+ * biasing them.  */
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use rustposix::interface;
+
+use rustposix::safeposix::cage::*;
+
+// Using this to include my criterion settings from a single shared file.
+mod global_criterion_settings;
+
+// How many entries the benchmarked directory holds
+const ENTRIES: usize = 100000;
+
+// The size of the buffer getdents fills on each call
+const DENTBUFSIZE: usize = 32768;
+
+pub fn run_benchmark(c: &mut Criterion) {
+    rustposix::safeposix::dispatcher::lindrustinit(0);
+
+    let cage = interface::cagetable_getref(1);
+
+    // Fill /bigdir with hard links to a single file.  It's a tmpfs so that
+    // building it doesn't log the whole directory once per entry.
+    cage.mkdir_syscall("/bigdir", S_IRWXA);
+    cage.mount_syscall("none", "/bigdir", "tmpfs", 0, None);
+    let fd = cage.open_syscall("/bigdir/target", O_CREAT | O_WRONLY, S_IRWXA);
+    cage.close_syscall(fd);
+    for entry in 0..ENTRIES {
+        cage.link_syscall("/bigdir/target", &format!("/bigdir/link{}", entry));
+    }
+
+    // --- LISTING AND LOOKING UP ENTRIES OF A LARGE DIRECTORY ---
+    let mut group = c.benchmark_group("Compare fs:large directory");
+    group.sample_size(10);
+
+    group.plot_config(
+        criterion::PlotConfiguration::default().summary_scale(criterion::AxisScale::Linear),
+    );
+
+    group.bench_function("TF12: Lind getdents whole directory", |b| {
+        let mut dentbuf = vec![0u8; DENTBUFSIZE];
+        b.iter(|| {
+            let fd = cage.open_syscall("/bigdir", O_RDONLY, 0);
+            while cage.getdents_syscall(fd, dentbuf.as_mut_ptr(), DENTBUFSIZE as u32) > 0 {}
+            cage.close_syscall(fd);
+        })
+    });
+
+    group.bench_function("TF12: Lind stat entry", |b| {
+        let mut statdata = StatData::default();
+        let path = format!("/bigdir/link{}", ENTRIES / 2);
+        b.iter(|| {
+            cage.stat_syscall(&path, &mut statdata);
+        })
+    });
+
+    group.bench_function("TF12: Lind rename entry back and forth", |b| {
+        let path = format!("/bigdir/link{}", ENTRIES / 2);
+        b.iter(|| {
+            cage.rename_syscall(&path, "/bigdir/renamed");
+            cage.rename_syscall("/bigdir/renamed", &path);
+        })
+    });
+    group.finish();
+
+    for entry in 0..ENTRIES {
+        cage.unlink_syscall(&format!("/bigdir/link{}", entry));
+    }
+    cage.unlink_syscall("/bigdir/target");
+    cage.umount_syscall("/bigdir");
+    cage.rmdir_syscall("/bigdir");
+
+    rustposix::safeposix::dispatcher::lindrustfinalize();
+}
+
+criterion_group!(name=benches;
+                 // Add the global settings here so we don't type it everywhere
+                 config=global_criterion_settings::get_criterion();
+                 targets=run_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+/* Benchmarks for serializing and deserializing a large metadata snapshot in
+ * each of the supported formats.  As elsewhere, results aren't checked to
+ * avoid biasing them.  */
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use rustposix::interface;
+
+use rustposix::safeposix::filesystem::*;
+use rustposix::safeposix::syscalls::fs_constants::*;
+use rustposix::safeposix::syscalls::sys_constants::{DEFAULT_GID, DEFAULT_UID};
+
+// Using this to include my criterion settings from a single shared file.
+mod global_criterion_settings;
+
+// How many files the root directory of the benchmarked filesystem holds
+const FILES: usize = 10000;
+
+fn large_metadata() -> FilesystemMetadata {
+    let metadata = FilesystemMetadata::blank_fs_init();
+    let firstinode = metadata
+        .nextinode
+        .fetch_add(FILES, interface::RustAtomicOrdering::Relaxed);
+
+    for inodenum in firstinode..firstinode + FILES {
+        let inode = Inode::File(GenericInode {
+            size: inodenum,
+            uid: DEFAULT_UID,
+            gid: DEFAULT_GID,
+            mode: (S_IFREG | 0o644) as u32,
+            linkcount: 1,
+            refcount: 0,
+            atime: 0,
+            ctime: 0,
+            btime: 0,
+            mtime: 0,
+        });
+        metadata.inodetable.insert(inodenum, inode);
+        if let Inode::Dir(ref mut rootinode) =
+            *metadata.inodetable.get_mut(&ROOTDIRECTORYINODE).unwrap()
+        {
+            rootinode
+                .filename_to_inode_dict
+                .insert(format!("file{}", inodenum), inodenum);
+        }
+    }
+
+    metadata
+}
+
+pub fn run_benchmark(c: &mut Criterion) {
+    let metadata = large_metadata();
+
+    // --- COMPARING CBOR AND THE BINARY FORMAT ON A LARGE SNAPSHOT ---
+    let mut group = c.benchmark_group("Compare fs:metadata serialization");
+
+    group.plot_config(
+        criterion::PlotConfiguration::default().summary_scale(criterion::AxisScale::Linear),
+    );
+
+    for (name, format) in [
+        ("cbor", interface::MetadataFormat::Cbor),
+        ("binary", interface::MetadataFormat::Binary),
+    ] {
+        group.bench_function(BenchmarkId::new("TF11: serialize", name), |b| {
+            b.iter(|| interface::serialize_metadata_as(format, &metadata).unwrap())
+        });
+
+        let bytes = interface::serialize_metadata_as(format, &metadata).unwrap();
+        group.bench_function(BenchmarkId::new("TF11: deserialize", name), |b| {
+            b.iter(|| {
+                let _: FilesystemMetadata = interface::deserialize_metadata(&bytes).unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(name=benches;
+                 // Add the global settings here so we don't type it everywhere
+                 config=global_criterion_settings::get_criterion();
+                 targets=run_benchmark);
+criterion_main!(benches);
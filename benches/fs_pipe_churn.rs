@@ -0,0 +1,88 @@
+/* Benchmarks for the microvisor implementation.  Pipes that are opened, used
+ * once, and closed again, which is where reusing ring buffers instead of
+ * allocating a fresh one for each pipe pays off.  */
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use rustposix::interface;
+
+use std::time::Duration;
+
+use rustposix::safeposix::cage::*;
+
+// Using this to include my criterion settings from a single shared file.
+// I did not use "use" or "mod" because benches/ isn't in the crate's usual
+// namespace and I didn't want to either make a separate crate with a single,
+// tiny file or add this file to the rustposix crate.
+mod global_criterion_settings;
+
+pub fn run_benchmark(c: &mut Criterion) {
+    // I'm following the initialization workflow from the unit tests here.
+    //
+    // I'm using the lindrustinit to set up cages and the file system.
+    rustposix::safeposix::dispatcher::lindrustinit(0);
+
+    // Since all system calls are a method of a cage object, I also need this
+    // reference.
+    let cage = interface::cagetable_getref(1);
+
+    let mut group = c.benchmark_group("Compare fs:pipe churn");
+
+    // Should be similar.  Use a linear scale...
+    group.plot_config(
+        criterion::PlotConfiguration::default().summary_scale(criterion::AxisScale::Linear),
+    );
+    group.measurement_time(Duration::from_secs(2));
+    group.warm_up_time(Duration::from_secs(1));
+
+    // Iterate for different message sizes...
+    for buflen in [1, 1024, 65536].iter() {
+        let msg = vec![b'X'; *buflen];
+        let mut reply = vec![0u8; *buflen];
+
+        // First do this for Lind
+        group.bench_with_input(
+            BenchmarkId::new("TF13:Lind pipe+write+read+close", buflen),
+            buflen,
+            |b, buflen| {
+                b.iter(|| {
+                    let mut pipefds = PipeArray::default();
+                    cage.pipe_syscall(&mut pipefds);
+                    cage.write_syscall(pipefds.writefd, msg.as_ptr(), *buflen);
+                    cage.read_syscall(pipefds.readfd, reply.as_mut_ptr(), *buflen);
+                    cage.close_syscall(pipefds.writefd);
+                    cage.close_syscall(pipefds.readfd);
+                })
+            },
+        );
+
+        // For comparison let's time the native OS...
+        group.bench_with_input(
+            BenchmarkId::new("TF13:Native pipe+write+read+close", buflen),
+            buflen,
+            |b, buflen| {
+                b.iter(|| {
+                    let mut pipefds = [0 as libc::c_int; 2];
+                    unsafe {
+                        libc::pipe(pipefds.as_mut_ptr());
+                        libc::write(pipefds[1], msg.as_ptr().cast(), *buflen);
+                        libc::read(pipefds[0], reply.as_mut_ptr().cast(), *buflen);
+                        libc::close(pipefds[1]);
+                        libc::close(pipefds[0]);
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+
+    // This cleans up in ways I do not fully understand.  I think it ensures
+    // the file system is cleaned up
+    rustposix::safeposix::dispatcher::lindrustfinalize();
+}
+
+criterion_group!(name=benches;
+                 // Add the global settings here so we don't type it everywhere
+                 config=global_criterion_settings::get_criterion();
+                 targets=run_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,147 @@
+/* Benchmarks for the microvisor implementation.  Echo throughput over
+ * socketpairs, with several threads each using their own pair so that the
+ * cost of sharing the cage's fd table shows up.  */
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use rustposix::interface;
+
+use std::time::{Duration, Instant};
+
+use rustposix::safeposix::cage::*;
+
+// Using this to include my criterion settings from a single shared file.
+// I did not use "use" or "mod" because benches/ isn't in the crate's usual
+// namespace and I didn't want to either make a separate crate with a single,
+// tiny file or add this file to the rustposix crate.
+mod global_criterion_settings;
+
+// The size of every message echoed
+const ECHO_LEN: usize = 64;
+
+pub fn run_benchmark(c: &mut Criterion) {
+    // I'm following the initialization workflow from the unit tests here.
+    //
+    // I'm using the lindrustinit to set up cages and the file system.
+    rustposix::safeposix::dispatcher::lindrustinit(0);
+
+    // Since all system calls are a method of a cage object, I also need this
+    // reference.
+    let cage = interface::cagetable_getref(1);
+
+    let mut group = c.benchmark_group("Compare net:socketpair echo");
+
+    // Should be similar.  Use a linear scale...
+    group.plot_config(
+        criterion::PlotConfiguration::default().summary_scale(criterion::AxisScale::Linear),
+    );
+    group.measurement_time(Duration::from_secs(2));
+    group.warm_up_time(Duration::from_secs(1));
+
+    // First do this for Lind
+    for threads in [1, 4, 8].iter() {
+        let mut pairs = Vec::new();
+        for _ in 0..*threads {
+            let mut sv = interface::SockPair::default();
+            Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut sv);
+            pairs.push((sv.sock1, sv.sock2));
+        }
+
+        group.bench_with_input(
+            BenchmarkId::new("TN01:Lind echo", threads),
+            threads,
+            |b, _threads| {
+                b.iter_custom(|iters| {
+                    let start = Instant::now();
+                    let workers: Vec<_> = pairs
+                        .iter()
+                        .map(|&(sock1, sock2)| {
+                            let cage = cage.clone();
+                            std::thread::spawn(move || {
+                                let msg = vec![b'X'; ECHO_LEN];
+                                let mut reply = vec![0u8; ECHO_LEN];
+                                for _ in 0..iters {
+                                    cage.send_syscall(sock1, msg.as_ptr(), ECHO_LEN, 0);
+                                    cage.recv_syscall(sock2, reply.as_mut_ptr(), ECHO_LEN, 0);
+                                    cage.send_syscall(sock2, reply.as_ptr(), ECHO_LEN, 0);
+                                    cage.recv_syscall(sock1, reply.as_mut_ptr(), ECHO_LEN, 0);
+                                }
+                            })
+                        })
+                        .collect();
+                    for worker in workers {
+                        worker.join().unwrap();
+                    }
+                    start.elapsed()
+                })
+            },
+        );
+
+        for (sock1, sock2) in pairs {
+            cage.close_syscall(sock1);
+            cage.close_syscall(sock2);
+        }
+    }
+
+    // Now do this for Native
+    for threads in [1, 4, 8].iter() {
+        let mut pairs = Vec::new();
+        for _ in 0..*threads {
+            let mut sv = [0 as libc::c_int; 2];
+            unsafe {
+                libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, sv.as_mut_ptr());
+            }
+            pairs.push((sv[0], sv[1]));
+        }
+
+        // For comparison let's time the native OS...
+        group.bench_with_input(
+            BenchmarkId::new("TN01:Native echo", threads),
+            threads,
+            |b, _threads| {
+                b.iter_custom(|iters| {
+                    let start = Instant::now();
+                    let workers: Vec<_> = pairs
+                        .iter()
+                        .map(|&(sock1, sock2)| {
+                            std::thread::spawn(move || {
+                                let msg = vec![b'X'; ECHO_LEN];
+                                let mut reply = vec![0u8; ECHO_LEN];
+                                for _ in 0..iters {
+                                    unsafe {
+                                        libc::send(sock1, msg.as_ptr().cast(), ECHO_LEN, 0);
+                                        libc::recv(sock2, reply.as_mut_ptr().cast(), ECHO_LEN, 0);
+                                        libc::send(sock2, reply.as_ptr().cast(), ECHO_LEN, 0);
+                                        libc::recv(sock1, reply.as_mut_ptr().cast(), ECHO_LEN, 0);
+                                    }
+                                }
+                            })
+                        })
+                        .collect();
+                    for worker in workers {
+                        worker.join().unwrap();
+                    }
+                    start.elapsed()
+                })
+            },
+        );
+
+        for (sock1, sock2) in pairs {
+            unsafe {
+                libc::close(sock1);
+                libc::close(sock2);
+            }
+        }
+    }
+    group.finish();
+
+    // This cleans up in ways I do not fully understand.  I think it ensures
+    // the file system is cleaned up
+    rustposix::safeposix::dispatcher::lindrustfinalize();
+}
+
+criterion_group!(name=benches;
+                 // Add the global settings here so we don't type it everywhere
+                 config=global_criterion_settings::get_criterion();
+                 targets=run_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,184 @@
+/* Benchmarks for the microvisor implementation.  Packet rate over UDP on the
+ * loopback, both with send / recv on connected sockets and with sendto /
+ * recvfrom naming the address on every packet.  */
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use rustposix::interface;
+
+use std::mem::size_of;
+use std::time::Duration;
+
+use rustposix::safeposix::cage::*;
+
+// Using this to include my criterion settings from a single shared file.
+// I did not use "use" or "mod" because benches/ isn't in the crate's usual
+// namespace and I didn't want to either make a separate crate with a single,
+// tiny file or add this file to the rustposix crate.
+mod global_criterion_settings;
+
+// The size of every datagram sent
+const PACKET_LEN: usize = 64;
+
+fn lind_loopback(port: u16) -> interface::GenSockaddr {
+    interface::GenSockaddr::V4(interface::SockaddrV4 {
+        sin_family: AF_INET as u16,
+        sin_port: port.to_be(),
+        sin_addr: interface::V4Addr {
+            s_addr: u32::from_ne_bytes([127, 0, 0, 1]),
+        },
+        padding: 0,
+    })
+}
+
+fn native_loopback(port: u16) -> libc::sockaddr_in {
+    let mut addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+    addr.sin_family = libc::AF_INET as u16;
+    addr.sin_port = port.to_be();
+    addr.sin_addr.s_addr = u32::from_ne_bytes([127, 0, 0, 1]);
+    addr
+}
+
+fn native_bound_socket(port: u16) -> i32 {
+    let addr = native_loopback(port);
+    unsafe {
+        let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
+        libc::bind(
+            fd,
+            (&addr as *const libc::sockaddr_in).cast(),
+            size_of::<libc::sockaddr_in>() as u32,
+        );
+        fd
+    }
+}
+
+pub fn run_benchmark(c: &mut Criterion) {
+    // I'm following the initialization workflow from the unit tests here.
+    //
+    // I'm using the lindrustinit to set up cages and the file system.
+    rustposix::safeposix::dispatcher::lindrustinit(0);
+
+    // Since all system calls are a method of a cage object, I also need this
+    // reference.
+    let cage = interface::cagetable_getref(1);
+
+    let mut group = c.benchmark_group("Compare net:udp packets");
+
+    // Should be similar.  Use a linear scale...
+    group.plot_config(
+        criterion::PlotConfiguration::default().summary_scale(criterion::AxisScale::Linear),
+    );
+    group.measurement_time(Duration::from_secs(2));
+    group.warm_up_time(Duration::from_secs(1));
+
+    let msg = vec![b'X'; PACKET_LEN];
+    let mut reply = vec![0u8; PACKET_LEN];
+
+    // First do this for Lind
+    let sock1 = cage.socket_syscall(AF_INET, SOCK_DGRAM, 0);
+    let sock2 = cage.socket_syscall(AF_INET, SOCK_DGRAM, 0);
+    cage.bind_syscall(sock1, &lind_loopback(50161));
+    cage.bind_syscall(sock2, &lind_loopback(50162));
+    cage.connect_syscall(sock1, &lind_loopback(50162));
+    cage.connect_syscall(sock2, &lind_loopback(50161));
+
+    group.bench_function("TN02:Lind send/recv", |b| {
+        b.iter(|| {
+            cage.send_syscall(sock1, msg.as_ptr(), PACKET_LEN, 0);
+            cage.recv_syscall(sock2, reply.as_mut_ptr(), PACKET_LEN, 0);
+        })
+    });
+
+    let sock3 = cage.socket_syscall(AF_INET, SOCK_DGRAM, 0);
+    let sock4 = cage.socket_syscall(AF_INET, SOCK_DGRAM, 0);
+    cage.bind_syscall(sock4, &lind_loopback(50164));
+    let dest = lind_loopback(50164);
+    let mut from = lind_loopback(0);
+
+    group.bench_function("TN02:Lind sendto/recvfrom", |b| {
+        b.iter(|| {
+            cage.sendto_syscall(sock3, msg.as_ptr(), PACKET_LEN, 0, &dest);
+            cage.recvfrom_syscall(
+                sock4,
+                reply.as_mut_ptr(),
+                PACKET_LEN,
+                0,
+                &mut Some(&mut from),
+            );
+        })
+    });
+
+    for sock in [sock1, sock2, sock3, sock4].iter() {
+        cage.close_syscall(*sock);
+    }
+
+    // Now do this for Native
+    let sock1 = native_bound_socket(50171);
+    let sock2 = native_bound_socket(50172);
+    let addr1 = native_loopback(50171);
+    let addr2 = native_loopback(50172);
+    unsafe {
+        libc::connect(
+            sock1,
+            (&addr2 as *const libc::sockaddr_in).cast(),
+            size_of::<libc::sockaddr_in>() as u32,
+        );
+        libc::connect(
+            sock2,
+            (&addr1 as *const libc::sockaddr_in).cast(),
+            size_of::<libc::sockaddr_in>() as u32,
+        );
+    }
+
+    // For comparison let's time the native OS...
+    group.bench_function("TN02:Native send/recv", |b| {
+        b.iter(|| unsafe {
+            libc::send(sock1, msg.as_ptr().cast(), PACKET_LEN, 0);
+            libc::recv(sock2, reply.as_mut_ptr().cast(), PACKET_LEN, 0);
+        })
+    });
+
+    let sock3 = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    let sock4 = native_bound_socket(50174);
+    let dest = native_loopback(50174);
+    let mut from = native_loopback(0);
+
+    group.bench_function("TN02:Native sendto/recvfrom", |b| {
+        b.iter(|| unsafe {
+            libc::sendto(
+                sock3,
+                msg.as_ptr().cast(),
+                PACKET_LEN,
+                0,
+                (&dest as *const libc::sockaddr_in).cast(),
+                size_of::<libc::sockaddr_in>() as u32,
+            );
+            let mut fromlen = size_of::<libc::sockaddr_in>() as u32;
+            libc::recvfrom(
+                sock4,
+                reply.as_mut_ptr().cast(),
+                PACKET_LEN,
+                0,
+                (&mut from as *mut libc::sockaddr_in).cast(),
+                &mut fromlen,
+            );
+        })
+    });
+
+    for sock in [sock1, sock2, sock3, sock4].iter() {
+        unsafe {
+            libc::close(*sock);
+        }
+    }
+    group.finish();
+
+    // This cleans up in ways I do not fully understand.  I think it ensures
+    // the file system is cleaned up
+    rustposix::safeposix::dispatcher::lindrustfinalize();
+}
+
+criterion_group!(name=benches;
+                 // Add the global settings here so we don't type it everywhere
+                 config=global_criterion_settings::get_criterion();
+                 targets=run_benchmark);
+criterion_main!(benches);
@@ -13,6 +13,8 @@ extern crate libc;
 static NET_DEV_FILENAME: &str = "net_devices";
 
 static mut UD_ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
+// bumped whenever a host socket is closed, so a KernelEpollCache knows its registrations may be stale
+static SOCKET_CLOSES: AtomicUsize = AtomicUsize::new(0);
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
 pub enum GenSockaddr {
@@ -224,11 +226,55 @@ pub struct SockaddrV6 {
     pub sin6_scope_id: u32,
 }
 
+// A socket address laid out the way the host's socket calls take it, worked out once so that a
+// socket sending to the same peer packet after packet needn't convert the address every time
+#[derive(Debug, Clone, Copy)]
+pub struct HostSockaddr {
+    raw: SockaddrV6, // big enough for either inet address
+    len: u32,
+}
+
+impl HostSockaddr {
+    pub fn new(addr: &GenSockaddr) -> Self {
+        let mut raw = SockaddrV6::default();
+        let len = match addr {
+            GenSockaddr::V4(v4addr) => {
+                unsafe {
+                    std::ptr::write_unaligned(
+                        (&mut raw as *mut SockaddrV6).cast::<SockaddrV4>(),
+                        *v4addr,
+                    )
+                };
+                size_of::<SockaddrV4>()
+            }
+            GenSockaddr::V6(v6addr) => {
+                raw = *v6addr;
+                size_of::<SockaddrV6>()
+            }
+            GenSockaddr::Unix(_) => unreachable!(),
+        };
+        HostSockaddr {
+            raw,
+            len: len as u32,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Socket {
     pub raw_sys_fd: i32,
 }
 
+// a result from the io_uring backend, which gives back -errno, as the libc call's would be
+#[cfg(feature = "io_uring")]
+fn uring_syscall_result(result: i32) -> Result<i32, i32> {
+    if result < 0 {
+        Err(-result)
+    } else {
+        Ok(result)
+    }
+}
+
 impl Socket {
     pub fn new(domain: i32, socktype: i32, protocol: i32) -> Socket {
         let fd = unsafe { libc::socket(domain, socktype, protocol) };
@@ -255,7 +301,9 @@ impl Socket {
         Self { raw_sys_fd: fd }
     }
 
-    pub fn bind(&self, addr: &GenSockaddr) -> i32 {
+    //These wrap the host calls on the socket, giving back what the call returned or the errno it
+    //failed with, captured as it returned; see interface::host_ret.
+    pub fn bind(&self, addr: &GenSockaddr) -> Result<i32, i32> {
         let (finalsockaddr, addrlen) = match addr {
             GenSockaddr::V6(addrref6) => (
                 (addrref6 as *const SockaddrV6).cast::<libc::sockaddr>(),
@@ -269,10 +317,10 @@ impl Socket {
                 unreachable!()
             }
         };
-        unsafe { libc::bind(self.raw_sys_fd, finalsockaddr, addrlen as u32) }
+        interface::host_ret(unsafe { libc::bind(self.raw_sys_fd, finalsockaddr, addrlen as u32) })
     }
 
-    pub fn connect(&self, addr: &GenSockaddr) -> i32 {
+    pub fn connect(&self, addr: &GenSockaddr) -> Result<i32, i32> {
         let (finalsockaddr, addrlen) = match addr {
             GenSockaddr::V6(addrref6) => (
                 (addrref6 as *const SockaddrV6).cast::<libc::sockaddr>(),
@@ -286,10 +334,33 @@ impl Socket {
                 unreachable!()
             }
         };
-        unsafe { libc::connect(self.raw_sys_fd, finalsockaddr, addrlen as u32) }
+        interface::host_ret(unsafe {
+            libc::connect(self.raw_sys_fd, finalsockaddr, addrlen as u32)
+        })
+    }
+
+    pub fn connect_nonblocking(&self, addr: &GenSockaddr) -> Result<i32, i32> {
+        self.set_nonblocking();
+        let retval = self.connect(addr);
+        self.set_blocking();
+        retval
     }
 
-    pub fn sendto(&self, buf: *const u8, len: usize, addr: Option<&GenSockaddr>) -> i32 {
+    pub fn sendto(
+        &self,
+        buf: *const u8,
+        len: usize,
+        addr: Option<&GenSockaddr>,
+        flags: i32,
+    ) -> Result<i32, i32> {
+        #[cfg(feature = "io_uring")]
+        {
+            if addr.is_none() {
+                if let Some(result) = interface::uring_send(self.raw_sys_fd, buf, len, flags) {
+                    return uring_syscall_result(result);
+                }
+            }
+        }
         let (finalsockaddr, addrlen) = match addr {
             Some(GenSockaddr::V6(addrref6)) => (
                 (addrref6 as *const SockaddrV6).cast::<libc::sockaddr>(),
@@ -307,23 +378,51 @@ impl Socket {
                 0,
             ),
         };
-        unsafe {
+        interface::host_ret(unsafe {
             libc::sendto(
                 self.raw_sys_fd,
                 buf as *const libc::c_void,
                 len,
-                0,
+                flags,
                 finalsockaddr,
                 addrlen as u32,
             ) as i32
-        }
+        })
     }
 
-    pub fn writev(&self, iov: *const interface::IovecStruct, iovcnt: i32) -> i32 {
-        unsafe { libc::writev(self.raw_sys_fd, iov as *const libc::iovec, iovcnt) as i32 }
+    pub fn sendto_host(&self, buf: *const u8, len: usize, addr: &HostSockaddr) -> Result<i32, i32> {
+        interface::host_ret(unsafe {
+            libc::sendto(
+                self.raw_sys_fd,
+                buf as *const libc::c_void,
+                len,
+                0,
+                (&addr.raw as *const SockaddrV6).cast::<libc::sockaddr>(),
+                addr.len,
+            ) as i32
+        })
+    }
+
+    pub fn writev(&self, iov: *const interface::IovecStruct, iovcnt: i32) -> Result<i32, i32> {
+        interface::host_ret(unsafe {
+            libc::writev(self.raw_sys_fd, iov as *const libc::iovec, iovcnt) as i32
+        })
     }
 
-    pub fn recvfrom(&self, buf: *mut u8, len: usize, addr: &mut Option<&mut GenSockaddr>) -> i32 {
+    pub fn recvfrom(
+        &self,
+        buf: *mut u8,
+        len: usize,
+        addr: &mut Option<&mut GenSockaddr>,
+    ) -> Result<i32, i32> {
+        #[cfg(feature = "io_uring")]
+        {
+            if addr.is_none() {
+                if let Some(result) = interface::uring_recv(self.raw_sys_fd, buf, len) {
+                    return uring_syscall_result(result);
+                }
+            }
+        }
         let (finalsockaddr, mut addrlen) = match addr {
             Some(GenSockaddr::V6(ref mut addrref6)) => (
                 (addrref6 as *mut SockaddrV6).cast::<libc::sockaddr>(),
@@ -338,7 +437,7 @@ impl Socket {
             }
             None => (std::ptr::null::<libc::sockaddr>() as *mut libc::sockaddr, 0),
         };
-        unsafe {
+        interface::host_ret(unsafe {
             libc::recvfrom(
                 self.raw_sys_fd,
                 buf as *mut libc::c_void,
@@ -347,7 +446,7 @@ impl Socket {
                 finalsockaddr,
                 &mut addrlen as *mut u32,
             ) as i32
-        }
+        })
     }
 
     pub fn recvfrom_nonblocking(
@@ -355,7 +454,7 @@ impl Socket {
         buf: *mut u8,
         len: usize,
         addr: &mut Option<&mut GenSockaddr>,
-    ) -> i32 {
+    ) -> Result<i32, i32> {
         let (finalsockaddr, mut addrlen) = match addr {
             Some(GenSockaddr::V6(ref mut addrref6)) => (
                 (addrref6 as *mut SockaddrV6).cast::<libc::sockaddr>(),
@@ -371,7 +470,7 @@ impl Socket {
             None => (std::ptr::null::<libc::sockaddr>() as *mut libc::sockaddr, 0),
         };
         self.set_nonblocking();
-        let retval = unsafe {
+        let retval = interface::host_ret(unsafe {
             libc::recvfrom(
                 self.raw_sys_fd,
                 buf as *mut libc::c_void,
@@ -380,13 +479,24 @@ impl Socket {
                 finalsockaddr,
                 &mut addrlen as *mut u32,
             ) as i32
-        };
+        });
         self.set_blocking();
         retval
     }
 
-    pub fn listen(&self, backlog: i32) -> i32 {
-        unsafe { libc::listen(self.raw_sys_fd, backlog) }
+    //sleep until there is room to send, or timeout passes, saying whether the socket woke up.
+    //A connect that is under way wakes it once it completes or fails
+    pub fn wait_writable(&self, timeout: interface::RustDuration) -> bool {
+        let mut pollfd = libc::pollfd {
+            fd: self.raw_sys_fd,
+            events: libc::POLLOUT,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut pollfd, 1, timeout.as_millis() as i32) > 0 }
+    }
+
+    pub fn listen(&self, backlog: i32) -> Result<i32, i32> {
+        interface::host_ret(unsafe { libc::listen(self.raw_sys_fd, backlog) })
     }
 
     pub fn set_blocking(&self) -> i32 {
@@ -401,41 +511,33 @@ impl Socket {
         return if isv4 {
             let mut inneraddrbuf = SockaddrV4::default();
             let mut sadlen = size_of::<SockaddrV4>() as u32;
-            let newfd = unsafe {
+            let newfd = interface::host_ret(unsafe {
                 libc::accept(
                     self.raw_sys_fd,
                     (&mut inneraddrbuf as *mut SockaddrV4).cast::<libc::sockaddr>(),
                     &mut sadlen as *mut u32,
                 )
-            };
-
-            if newfd < 0 {
-                (Err(newfd), GenSockaddr::V4(inneraddrbuf))
-            } else {
-                (
-                    Ok(Self { raw_sys_fd: newfd }),
-                    GenSockaddr::V4(inneraddrbuf),
-                )
-            }
+            });
+
+            (
+                newfd.map(|newfd| Self { raw_sys_fd: newfd }),
+                GenSockaddr::V4(inneraddrbuf),
+            )
         } else {
             let mut inneraddrbuf = SockaddrV6::default();
             let mut sadlen = size_of::<SockaddrV6>() as u32;
-            let newfd = unsafe {
+            let newfd = interface::host_ret(unsafe {
                 libc::accept(
                     self.raw_sys_fd,
                     (&mut inneraddrbuf as *mut SockaddrV6).cast::<libc::sockaddr>(),
                     &mut sadlen as *mut u32,
                 )
-            };
-
-            if newfd < 0 {
-                (Err(newfd), GenSockaddr::V6(inneraddrbuf))
-            } else {
-                (
-                    Ok(Self { raw_sys_fd: newfd }),
-                    GenSockaddr::V6(inneraddrbuf),
-                )
-            }
+            });
+
+            (
+                newfd.map(|newfd| Self { raw_sys_fd: newfd }),
+                GenSockaddr::V6(inneraddrbuf),
+            )
         };
     }
 
@@ -444,50 +546,71 @@ impl Socket {
             let mut inneraddrbuf = SockaddrV4::default();
             let mut sadlen = size_of::<SockaddrV4>() as u32;
             self.set_nonblocking();
-            let newfd = unsafe {
+            let newfd = interface::host_ret(unsafe {
                 libc::accept(
                     self.raw_sys_fd,
                     (&mut inneraddrbuf as *mut SockaddrV4).cast::<libc::sockaddr>(),
                     &mut sadlen as *mut u32,
                 )
-            };
+            });
             self.set_blocking();
 
-            if newfd < 0 {
-                (Err(newfd), GenSockaddr::V4(inneraddrbuf))
-            } else {
-                (
-                    Ok(Self { raw_sys_fd: newfd }),
-                    GenSockaddr::V4(inneraddrbuf),
-                )
-            }
+            (
+                newfd.map(|newfd| Self { raw_sys_fd: newfd }),
+                GenSockaddr::V4(inneraddrbuf),
+            )
         } else {
             let mut inneraddrbuf = SockaddrV6::default();
             let mut sadlen = size_of::<SockaddrV6>() as u32;
             self.set_nonblocking();
-            let newfd = unsafe {
+            let newfd = interface::host_ret(unsafe {
                 libc::accept(
                     self.raw_sys_fd,
                     (&mut inneraddrbuf as *mut SockaddrV6).cast::<libc::sockaddr>(),
                     &mut sadlen as *mut u32,
                 )
-            };
+            });
             self.set_blocking();
 
-            if newfd < 0 {
-                (Err(newfd), GenSockaddr::V6(inneraddrbuf))
-            } else {
-                (
-                    Ok(Self { raw_sys_fd: newfd }),
-                    GenSockaddr::V6(inneraddrbuf),
+            (
+                newfd.map(|newfd| Self { raw_sys_fd: newfd }),
+                GenSockaddr::V6(inneraddrbuf),
+            )
+        };
+    }
+
+    //the address the host socket is bound to, which for an accepted socket is the one the peer
+    //connected to even when the listener is bound to a wildcard address
+    pub fn getsockname(&self, isv4: bool) -> Result<GenSockaddr, i32> {
+        let (ret, addr) = if isv4 {
+            let mut inneraddrbuf = SockaddrV4::default();
+            let mut sadlen = size_of::<SockaddrV4>() as u32;
+            let ret = interface::host_ret(unsafe {
+                libc::getsockname(
+                    self.raw_sys_fd,
+                    (&mut inneraddrbuf as *mut SockaddrV4).cast::<libc::sockaddr>(),
+                    &mut sadlen as *mut u32,
                 )
-            }
+            });
+            (ret, GenSockaddr::V4(inneraddrbuf))
+        } else {
+            let mut inneraddrbuf = SockaddrV6::default();
+            let mut sadlen = size_of::<SockaddrV6>() as u32;
+            let ret = interface::host_ret(unsafe {
+                libc::getsockname(
+                    self.raw_sys_fd,
+                    (&mut inneraddrbuf as *mut SockaddrV6).cast::<libc::sockaddr>(),
+                    &mut sadlen as *mut u32,
+                )
+            });
+            (ret, GenSockaddr::V6(inneraddrbuf))
         };
+        ret.map(|_| addr)
     }
 
-    pub fn setsockopt(&self, level: i32, optname: i32, optval: i32) -> i32 {
+    pub fn setsockopt(&self, level: i32, optname: i32, optval: i32) -> Result<i32, i32> {
         let valbuf = optval;
-        let ret = unsafe {
+        interface::host_ret(unsafe {
             libc::setsockopt(
                 self.raw_sys_fd,
                 level,
@@ -495,13 +618,75 @@ impl Socket {
                 (&valbuf as *const i32).cast::<libc::c_void>(),
                 size_of::<i32>() as u32,
             )
+        })
+    }
+
+    //close the socket with a reset rather than the usual orderly shutdown
+    pub fn reset(self) {
+        let linger = libc::linger {
+            l_onoff: 1,
+            l_linger: 0,
         };
-        ret
+        unsafe {
+            libc::setsockopt(
+                self.raw_sys_fd,
+                libc::SOL_SOCKET,
+                libc::SO_LINGER,
+                (&linger as *const libc::linger).cast::<libc::c_void>(),
+                size_of::<libc::linger>() as u32,
+            )
+        };
+    }
+
+    pub fn shutdown(&self, how: i32) -> Result<i32, i32> {
+        interface::host_ret(unsafe { libc::shutdown(self.raw_sys_fd, how) })
     }
 
-    pub fn shutdown(&self, how: i32) -> i32 {
-        let ret = unsafe { libc::shutdown(self.raw_sys_fd, how) };
-        ret
+    //the bytes queued on the socket for reading, or the size of the next datagram
+    pub fn readable_bytes(&self) -> Result<i32, i32> {
+        let mut count: libc::c_int = 0;
+        interface::host_ret(unsafe { libc::ioctl(self.raw_sys_fd, libc::FIONREAD, &mut count) })?;
+        Ok(count)
+    }
+
+    //how a nonblocking connect that has finished turned out, as connect would have returned it
+    pub fn connect_result(&self) -> Result<i32, i32> {
+        let mut valbuf = 0;
+        let mut len = size_of::<i32>() as u32;
+        interface::host_ret(unsafe {
+            libc::getsockopt(
+                self.raw_sys_fd,
+                libc::SOL_SOCKET,
+                libc::SO_ERROR,
+                (&mut valbuf as *mut i32).cast::<libc::c_void>(),
+                &mut len as *mut u32,
+            )
+        })?;
+        if valbuf != 0 {
+            Err(valbuf)
+        } else {
+            Ok(0)
+        }
+    }
+
+    //whether the peer closed the connection before this side did, in which case it is the peer
+    //and not this side that goes through TIME_WAIT
+    pub fn peer_closed_first(&self) -> bool {
+        //the linux tcp states of a connection closed by the peer: CLOSE, CLOSE_WAIT and LAST_ACK
+        const TCP_CLOSE: u8 = 7;
+        const TCP_LAST_ACK: u8 = 9;
+        let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+        let mut len = size_of::<libc::tcp_info>() as u32;
+        let ret = unsafe {
+            libc::getsockopt(
+                self.raw_sys_fd,
+                libc::IPPROTO_TCP,
+                libc::TCP_INFO,
+                (&mut info as *mut libc::tcp_info).cast::<libc::c_void>(),
+                &mut len as *mut u32,
+            )
+        };
+        ret == 0 && (TCP_CLOSE..=TCP_LAST_ACK).contains(&info.tcpi_state)
     }
 
     pub fn check_rawconnection(&self) -> bool {
@@ -525,6 +710,7 @@ impl Drop for Socket {
         unsafe {
             libc::close(self.raw_sys_fd);
         }
+        SOCKET_CLOSES.fetch_add(1, Ordering::Release);
     }
 }
 
@@ -534,54 +720,79 @@ pub fn getifaddrs_from_file() -> String {
         .to_owned()
 }
 
-// Implementations of select related FD_SET structure
-pub struct FdSet(libc::fd_set);
+// The fd sets of select, and the sets poll gathers its fds into. A set grows as higher fds are
+// turned on, so it isn't bound to the FD_SETSIZE of libc's fd_set. Its bits are laid out the way
+// fd_set's are, a word per 64 fds, so that the sets a cage passes in can be read as they are.
+#[derive(Clone, Default, Debug)]
+pub struct FdSet(Vec<u64>);
 
 impl FdSet {
     pub fn new() -> FdSet {
-        unsafe {
-            let mut raw_fd_set = std::mem::MaybeUninit::<libc::fd_set>::uninit();
-            libc::FD_ZERO(raw_fd_set.as_mut_ptr());
-            FdSet(raw_fd_set.assume_init())
-        }
+        FdSet::default()
     }
 
-    pub fn new_from_ptr(raw_fdset_ptr: *const libc::fd_set) -> &'static mut FdSet {
-        unsafe { &mut *(raw_fdset_ptr as *mut FdSet) }
+    pub fn with_capacity(nfds: i32) -> FdSet {
+        FdSet(vec![0; FdSet::words_for(nfds)])
+    }
+
+    // how many words hold the bits of fds below nfds
+    pub fn words_for(nfds: i32) -> usize {
+        (nfds.max(0) as usize + 63) / 64
+    }
+
+    // read a set from the words_for(nfds) words of it in the cage's memory
+    pub fn new_from_words(words: &[u64]) -> FdSet {
+        FdSet(words.to_vec())
+    }
+
+    // write the set back to the words_for(nfds) words of it in the cage's memory, as the kernel does
+    pub fn copy_to_words(&self, words: &mut [u64]) {
+        for (index, word) in words.iter_mut().enumerate() {
+            *word = self.0.get(index).copied().unwrap_or(0);
+        }
     }
 
     // copy the src FdSet into self
     pub fn copy_from(&mut self, src_fds: &FdSet) {
-        unsafe {
-            std::ptr::copy_nonoverlapping(
-                &src_fds.0 as *const libc::fd_set,
-                &mut self.0 as *mut libc::fd_set,
-                1,
-            );
-        }
+        self.0.clone_from(&src_fds.0);
     }
 
-    // turn off the fd bit in fd_set (currently only used by the tests)
+    // turn off the fd bit in the set (currently only used by the tests)
     #[allow(dead_code)]
     pub fn clear(&mut self, fd: i32) {
-        unsafe { libc::FD_CLR(fd, &mut self.0) }
+        if let Some(word) = self.0.get_mut(fd as usize / 64) {
+            *word &= !(1 << (fd % 64));
+        }
     }
 
-    // turn on the fd bit in fd_set
+    // turn on the fd bit in the set
     pub fn set(&mut self, fd: i32) {
-        unsafe { libc::FD_SET(fd, &mut self.0) }
+        let word = fd as usize / 64;
+        if word >= self.0.len() {
+            self.0.resize(word + 1, 0);
+        }
+        self.0[word] |= 1 << (fd % 64);
     }
 
     // return true if the bit for fd is set, false otherwise
     pub fn is_set(&self, fd: i32) -> bool {
-        unsafe { libc::FD_ISSET(fd, &self.0) }
+        match self.0.get(fd as usize / 64) {
+            Some(word) => word & (1 << (fd % 64)) != 0,
+            None => false,
+        }
     }
 
     pub fn is_empty(&self) -> bool {
-        let fd_array: &[u8] = unsafe {
-            std::slice::from_raw_parts(&self.0 as *const _ as *const u8, size_of::<libc::fd_set>())
-        };
-        fd_array.iter().all(|&byte| byte == 0)
+        self.0.iter().all(|&word| word == 0)
+    }
+
+    // the fds turned on, lowest first
+    pub fn iter(&self) -> impl Iterator<Item = i32> + '_ {
+        self.0.iter().enumerate().flat_map(|(index, &word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| (index * 64 + bit) as i32)
+        })
     }
 
     // for each fd, if kernel_fds turned it on, then self will turn the corresponding tranlated fd on
@@ -591,10 +802,7 @@ impl FdSet {
         nfds: i32,
         rawfd_lindfd_tuples: &Vec<(i32, i32)>,
     ) {
-        for fd in 0..nfds {
-            if !kernel_fds.is_set(fd) {
-                continue;
-            }
+        for fd in kernel_fds.iter().take_while(|fd| *fd < nfds) {
             // translate and set
             if let Some((_, lindfd)) = rawfd_lindfd_tuples.iter().find(|(rawfd, _)| *rawfd == fd) {
                 self.set(*lindfd);
@@ -603,37 +811,179 @@ impl FdSet {
     }
 }
 
-// for unwrapping in kernel_select
-fn to_fdset_ptr(opt: Option<&mut FdSet>) -> *mut libc::fd_set {
-    match opt {
-        None => std::ptr::null_mut(),
-        Some(&mut FdSet(ref mut raw_fd_set)) => raw_fd_set,
-    }
-}
-
+// Returns how many fds are ready, or -errno. The host fds are looked at with poll, which unlike the
+// host's select has no ceiling on how high they may be
 pub fn kernel_select(
     nfds: libc::c_int,
     readfds: Option<&mut FdSet>,
     writefds: Option<&mut FdSet>,
     errorfds: Option<&mut FdSet>,
 ) -> i32 {
-    // Call libc::select and store the result
-    let result = unsafe {
-        // Create a timeval struct with zero timeout
+    let sets = [
+        (readfds, libc::POLLIN),
+        (writefds, libc::POLLOUT),
+        (errorfds, libc::POLLPRI),
+    ];
+    let mut pollfds: Vec<libc::pollfd> = vec![];
+    for (set, events) in sets.iter() {
+        if let Some(set) = set {
+            for fd in set.iter().take_while(|fd| *fd < nfds) {
+                match pollfds.iter_mut().find(|pollfd| pollfd.fd == fd) {
+                    Some(pollfd) => pollfd.events |= events,
+                    None => pollfds.push(libc::pollfd {
+                        fd: fd,
+                        events: *events,
+                        revents: 0,
+                    }),
+                }
+            }
+        }
+    }
 
-        let mut kselect_timeout = libc::timeval {
-            tv_sec: 0,  // 0 seconds
-            tv_usec: 0, // 0 microseconds
+    // a zero timeout, so that this never blocks
+    let result = interface::host_ret(unsafe {
+        libc::poll(pollfds.as_mut_ptr(), pollfds.len() as libc::nfds_t, 0)
+    });
+    if let Err(errno) = result {
+        return -errno;
+    }
+    if pollfds
+        .iter()
+        .any(|pollfd| pollfd.revents & libc::POLLNVAL != 0)
+    {
+        return -(libc::EBADF);
+    }
+
+    // as with select, a closed or failed fd counts as readable and writable
+    let mut count = 0;
+    for (set, events) in sets {
+        if let Some(set) = set {
+            let mut ready = FdSet::new();
+            for pollfd in pollfds.iter() {
+                if set.is_set(pollfd.fd)
+                    && pollfd.revents & (events | libc::POLLHUP | libc::POLLERR) != 0
+                    && (events != libc::POLLPRI || pollfd.revents & libc::POLLPRI != 0)
+                {
+                    ready.set(pollfd.fd);
+                    count += 1;
+                }
+            }
+            set.copy_from(&ready);
+        }
+    }
+    count
+}
+
+// A kernel epoll instance watching a set of host fds for readability, so that select can poll
+// them with one epoll_wait rather than building an fd_set and calling select every time round
+// its loop. Registrations are only touched when the set of fds asked about changes.
+#[derive(Debug)]
+pub struct KernelEpollCache {
+    epfd: i32,          // -1 until first used
+    rawfds: Vec<i32>,   // registered, sorted
+    closes_seen: usize, // SOCKET_CLOSES when the registrations were last checked
+}
+
+impl KernelEpollCache {
+    pub fn new() -> Self {
+        KernelEpollCache {
+            epfd: -1,
+            rawfds: vec![],
+            closes_seen: 0,
+        }
+    }
+
+    fn register(&self, rawfd: i32) -> i32 {
+        let mut event = libc::epoll_event {
+            events: libc::EPOLLIN as u32,
+            u64: rawfd as u64,
         };
+        match interface::host_ret(unsafe {
+            libc::epoll_ctl(self.epfd, libc::EPOLL_CTL_ADD, rawfd, &mut event)
+        }) {
+            Err(errno) if errno != libc::EEXIST => -errno,
+            _ => 0,
+        }
+    }
 
-        libc::select(
-            nfds,
-            to_fdset_ptr(readfds),
-            to_fdset_ptr(writefds),
-            to_fdset_ptr(errorfds),
-            &mut kselect_timeout as *mut libc::timeval,
-        )
-    };
+    fn reset(&mut self) {
+        if self.epfd >= 0 {
+            unsafe { libc::close(self.epfd) };
+        }
+        self.epfd = -1;
+        self.rawfds.clear();
+    }
+
+    // Watch exactly rawfds, which must be sorted and free of duplicates, and add the ones that
+    // are readable right now to ready. Returns how many there were or -errno.
+    pub fn ready_to_read(&mut self, rawfds: &[i32], ready: &mut Vec<i32>) -> i32 {
+        if self.epfd < 0 {
+            self.epfd =
+                match interface::host_ret(unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) }) {
+                    Ok(epfd) => epfd,
+                    Err(errno) => return -errno,
+                };
+            self.closes_seen = SOCKET_CLOSES.load(Ordering::Acquire);
+        }
+
+        let closes = SOCKET_CLOSES.load(Ordering::Acquire);
+        if closes != self.closes_seen {
+            // a closed fd leaves the instance by itself and its number may since have gone to
+            // another socket, so whatever we think is registered is added again
+            self.closes_seen = closes;
+            for rawfd in self.rawfds.clone() {
+                if self.register(rawfd) < 0 {
+                    self.rawfds.retain(|fd| *fd != rawfd);
+                }
+            }
+        }
+
+        if self.rawfds != rawfds {
+            for rawfd in self.rawfds.iter() {
+                if rawfds.binary_search(rawfd).is_err() {
+                    unsafe {
+                        libc::epoll_ctl(
+                            self.epfd,
+                            libc::EPOLL_CTL_DEL,
+                            *rawfd,
+                            std::ptr::null_mut(),
+                        )
+                    };
+                }
+            }
+            for rawfd in rawfds {
+                if self.rawfds.binary_search(rawfd).is_err() {
+                    let ret = self.register(*rawfd);
+                    if ret < 0 {
+                        self.reset();
+                        return ret;
+                    }
+                }
+            }
+            self.rawfds = rawfds.to_vec();
+        }
+
+        if rawfds.is_empty() {
+            return 0;
+        }
+        let mut events = vec![libc::epoll_event { events: 0, u64: 0 }; rawfds.len()];
+        let count = match interface::host_ret(unsafe {
+            libc::epoll_wait(self.epfd, events.as_mut_ptr(), events.len() as i32, 0)
+        }) {
+            Ok(count) => count,
+            Err(errno) => return -errno,
+        };
+        ready.extend(
+            events[..count as usize]
+                .iter()
+                .map(|event| event.u64 as i32),
+        );
+        count
+    }
+}
 
-    return result;
+impl Drop for KernelEpollCache {
+    fn drop(&mut self) {
+        self.reset();
+    }
 }
@@ -172,3 +172,46 @@ pub fn syscall_error(e: Errno, syscall: &str, message: &str) -> i32 {
     }
     -(e as i32)
 }
+
+impl Errno {
+    // Host calls can report errnos we have no variant for (or leave none at all); rather than
+    // panicking on those, a positive unknown errno becomes EIO and anything else EINVAL.
+    pub fn from_host(v: i32) -> Errno {
+        match Errno::from_discriminant(v) {
+            Ok(e) => e,
+            Err(()) if v > 0 => Errno::EIO,
+            Err(()) => Errno::EINVAL,
+        }
+    }
+}
+
+// errno as a host call wrapper captured it, handed to the cage
+pub fn host_syscall_error(errno: i32, syscall: &str, message: &str) -> i32 {
+    syscall_error(Errno::from_host(errno), syscall, message)
+}
+
+//Inconsistent state, such as an fd whose inode is of the wrong type or metadata that can't be
+//read back, is recorded here rather than panicking, since a panic takes down every cage along
+//with the one that ran into it. Embedders can read the reports back to decide what to do.
+static CORRUPTION_REPORTS: interface::RustLazyGlobal<interface::Mutex<Vec<String>>> =
+    interface::RustLazyGlobal::new(|| interface::Mutex::new(vec![]));
+
+pub fn report_corruption(message: &str) {
+    interface::log_to_stderr(&format!("Corrupted state: {}", message));
+    CORRUPTION_REPORTS.lock().push(message.to_string());
+}
+
+//every inconsistency reported so far, oldest first
+pub fn corruption_reports() -> Vec<String> {
+    CORRUPTION_REPORTS.lock().clone()
+}
+
+pub fn clear_corruption_reports() {
+    CORRUPTION_REPORTS.lock().clear();
+}
+
+//report an inconsistency found by a syscall, which then fails with EIO
+pub fn corruption_error(syscall: &str, message: &str) -> i32 {
+    report_corruption(&format!("{}: {}", syscall, message));
+    syscall_error(Errno::EIO, syscall, message)
+}
@@ -4,13 +4,16 @@
 #![allow(dead_code)]
 
 use dashmap::DashSet;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::env;
 pub use std::ffi::CStr as RustCStr;
 use std::fs::{self, canonicalize, File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 pub use std::path::{Component as RustPathComponent, Path as RustPath, PathBuf as RustPathBuf};
 use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 pub use std::sync::LazyLock as RustLazyGlobal;
 
@@ -18,11 +21,50 @@ use crate::interface::errnos::{syscall_error, Errno};
 use libc::{mmap, mremap, munmap, off64_t, MAP_SHARED, MREMAP_MAYMOVE, PROT_READ, PROT_WRITE};
 use std::convert::TryInto;
 use std::ffi::c_void;
-use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
 use std::os::unix::fs::{FileExt};
 
+// The host directory that the metadata, its logs, file data and shm backings are kept in, so that
+// instances run from the same working directory can each have their own. It comes from LIND_ROOT
+// when that is set and is otherwise the working directory. Changing it only affects files opened
+// afterwards, so it should be set before lindrustinit.
+pub const LINDROOT_ENV: &str = "LIND_ROOT";
+static LIND_ROOT: RustLazyGlobal<RwLock<RustPathBuf>> = RustLazyGlobal::new(|| {
+    let root = env::var_os(LINDROOT_ENV)
+        .map(RustPathBuf::from)
+        .unwrap_or_else(|| RustPathBuf::from("."));
+    let _ = fs::create_dir_all(&root);
+    RwLock::new(root)
+});
+
+thread_local! {
+    // a root for the calling thread alone, such as that of the machine it works for
+    static THREAD_LIND_ROOT: RefCell<Option<RustPathBuf>> = RefCell::new(None);
+}
+
+pub fn set_lind_root(root: &RustPath) -> std::io::Result<()> {
+    fs::create_dir_all(root)?;
+    *LIND_ROOT.write() = root.to_path_buf();
+    Ok(())
+}
+
+pub fn set_thread_lind_root(root: Option<RustPathBuf>) {
+    THREAD_LIND_ROOT.with(|threadroot| *threadroot.borrow_mut() = root);
+}
+
+pub fn lind_root() -> RustPathBuf {
+    THREAD_LIND_ROOT
+        .with(|threadroot| threadroot.borrow().clone())
+        .unwrap_or_else(|| LIND_ROOT.read().clone())
+}
+
+// Where a file lind keeps on the host lives. Absolute names are left as they are
+fn hostpath(filename: &str) -> RustPathBuf {
+    lind_root().join(filename)
+}
+
 pub fn removefile(filename: String) -> std::io::Result<()> {
-    let path: RustPathBuf = [".".to_string(), filename].iter().collect();
+    let path = hostpath(&filename);
 
     let absolute_filename = canonicalize(&path)?; //will return an error if the file does not exist
 
@@ -31,24 +73,278 @@ pub fn removefile(filename: String) -> std::io::Result<()> {
     Ok(())
 }
 
+// Lists the files in the lind root whose names start with prefix, along with their sizes
+pub fn listfiles(prefix: &str) -> std::io::Result<Vec<(String, u64)>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(lind_root())? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        if let Some(name) = entry.file_name().to_str() {
+            if name.starts_with(prefix) {
+                files.push((name.to_string(), metadata.len()));
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+// Atomically replaces newname with oldname, so readers see either the old file or the new one
+pub fn renamefile(oldname: String, newname: String) -> std::io::Result<()> {
+    fs::rename(hostpath(&oldname), hostpath(&newname))
+}
+
 pub fn openfile(filename: String, filesize: usize) -> std::io::Result<EmulatedFile> {
     EmulatedFile::new(filename, filesize)
 }
 
+// Opens a file whose contents live only in memory, for filesystems that are never persisted
+pub fn openmemfile(filename: String) -> std::io::Result<EmulatedFile> {
+    EmulatedFile::new_memory(filename)
+}
+
 pub fn openmetadata(filename: String) -> std::io::Result<EmulatedFile> {
     EmulatedFile::new_metadata(filename)
 }
 
+// How many pages of its data each file keeps in memory. Writes land in the cache and reach the
+// host on fsync, close or when their page is evicted, so a crash can lose them; zero sends every
+// read and write straight to the host. Files keep the setting they were opened with.
+pub const DEFAULT_PAGE_CACHE_PAGES: usize = 256;
+static PAGE_CACHE_PAGES: RustLazyGlobal<AtomicUsize> =
+    RustLazyGlobal::new(|| AtomicUsize::new(DEFAULT_PAGE_CACHE_PAGES));
+const CACHEPAGESIZE: usize = 4096;
+
+pub fn set_page_cache_pages(pages: usize) {
+    PAGE_CACHE_PAGES.store(pages, Ordering::Relaxed);
+}
+
+pub fn page_cache_pages() -> usize {
+    PAGE_CACHE_PAGES.load(Ordering::Relaxed)
+}
+
+// Reads, writes and syncs of host files, through io_uring when it is built in and available
+fn host_read_at(fobj: &File, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+    #[cfg(feature = "io_uring")]
+    {
+        if let Some(result) = super::uring_read_at(fobj.as_raw_fd(), buf, offset) {
+            return uring_io_result(result);
+        }
+    }
+    fobj.read_at(buf, offset)
+}
+
+fn host_write_at(fobj: &File, buf: &[u8], offset: u64) -> std::io::Result<usize> {
+    #[cfg(feature = "io_uring")]
+    {
+        if let Some(result) = super::uring_write_at(fobj.as_raw_fd(), buf, offset) {
+            return uring_io_result(result);
+        }
+    }
+    fobj.write_at(buf, offset)
+}
+
+fn host_write_all_at(fobj: &File, mut buf: &[u8], mut offset: u64) -> std::io::Result<()> {
+    while !buf.is_empty() {
+        match host_write_at(fobj, buf, offset)? {
+            0 => return Err(std::io::ErrorKind::WriteZero.into()),
+            written => {
+                buf = &buf[written..];
+                offset += written as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn host_sync(fobj: &File, datasync: bool) -> std::io::Result<()> {
+    #[cfg(feature = "io_uring")]
+    {
+        if let Some(result) = super::uring_fsync(fobj.as_raw_fd(), datasync) {
+            return uring_io_result(result).map(|_| ());
+        }
+    }
+    if datasync {
+        fobj.sync_data()
+    } else {
+        fobj.sync_all()
+    }
+}
+
+#[cfg(feature = "io_uring")]
+fn uring_io_result(result: i32) -> std::io::Result<usize> {
+    if result < 0 {
+        Err(std::io::Error::from_raw_os_error(-result))
+    } else {
+        Ok(result as usize)
+    }
+}
+
+#[derive(Debug)]
+struct CachedPage {
+    data: Box<[u8]>,
+    dirty: bool,
+    lastuse: u64,
+}
+
+// Pages of a file by page number. Bytes of a page past the end of the file are always zero, so
+// a page only ever needs writing back up to the end of the file.
+#[derive(Debug)]
+struct PageCache {
+    pages: HashMap<usize, CachedPage>,
+    capacity: usize,
+    clock: u64,
+}
+
+impl PageCache {
+    fn new(capacity: usize) -> PageCache {
+        PageCache {
+            pages: HashMap::new(),
+            capacity,
+            clock: 0,
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    // The cached copy of a page, read in from the host first if fill is set and it isn't cached
+    fn page(
+        &mut self,
+        fobj: &File,
+        pageno: usize,
+        filesize: usize,
+        fill: bool,
+    ) -> std::io::Result<&mut CachedPage> {
+        self.clock += 1;
+        if !self.pages.contains_key(&pageno) {
+            if self.pages.len() >= self.capacity {
+                self.evict(fobj, filesize)?;
+            }
+            let mut data = vec![0u8; CACHEPAGESIZE].into_boxed_slice();
+            let start = pageno * CACHEPAGESIZE;
+            if fill && start < filesize {
+                let len = std::cmp::min(CACHEPAGESIZE, filesize - start);
+                let mut filled = 0;
+                while filled < len {
+                    let bytes_read =
+                        host_read_at(fobj, &mut data[filled..len], (start + filled) as u64)?;
+                    if bytes_read == 0 {
+                        break;
+                    }
+                    filled += bytes_read;
+                }
+            }
+            self.pages.insert(
+                pageno,
+                CachedPage {
+                    data,
+                    dirty: false,
+                    lastuse: 0,
+                },
+            );
+        }
+        let page = self.pages.get_mut(&pageno).unwrap();
+        page.lastuse = self.clock;
+        Ok(page)
+    }
+
+    // drop the least recently used page, writing it back first if it is dirty
+    fn evict(&mut self, fobj: &File, filesize: usize) -> std::io::Result<()> {
+        let victim = match self.pages.iter().min_by_key(|(_, page)| page.lastuse) {
+            Some((pageno, _)) => *pageno,
+            None => return Ok(()),
+        };
+        if self.pages[&victim].dirty {
+            Self::write_back(fobj, victim, &self.pages[&victim].data, filesize)?;
+        }
+        self.pages.remove(&victim);
+        Ok(())
+    }
+
+    fn write_back(fobj: &File, pageno: usize, data: &[u8], filesize: usize) -> std::io::Result<()> {
+        let start = pageno * CACHEPAGESIZE;
+        if start >= filesize {
+            return Ok(());
+        }
+        let len = std::cmp::min(CACHEPAGESIZE, filesize - start);
+        host_write_all_at(fobj, &data[..len], start as u64)
+    }
+
+    fn flush(&mut self, fobj: &File, filesize: usize) -> std::io::Result<()> {
+        let mut dirty: Vec<usize> = self
+            .pages
+            .iter()
+            .filter(|(_, page)| page.dirty)
+            .map(|(pageno, _)| *pageno)
+            .collect();
+        dirty.sort_unstable();
+        for pageno in dirty {
+            let page = self.pages.get_mut(&pageno).unwrap();
+            Self::write_back(fobj, pageno, &page.data, filesize)?;
+            page.dirty = false;
+        }
+        Ok(())
+    }
+
+    fn read(
+        &mut self,
+        fobj: &File,
+        buf: &mut [u8],
+        offset: usize,
+        filesize: usize,
+    ) -> std::io::Result<usize> {
+        let end = std::cmp::min(offset + buf.len(), filesize);
+        let mut pos = offset;
+        while pos < end {
+            let inpage = pos % CACHEPAGESIZE;
+            let count = std::cmp::min(CACHEPAGESIZE - inpage, end - pos);
+            let page = self.page(fobj, pos / CACHEPAGESIZE, filesize, true)?;
+            buf[pos - offset..pos - offset + count]
+                .copy_from_slice(&page.data[inpage..inpage + count]);
+            pos += count;
+        }
+        Ok(end - offset)
+    }
+
+    // filesize is the size of the file once the write is done
+    fn write(
+        &mut self,
+        fobj: &File,
+        buf: &[u8],
+        offset: usize,
+        filesize: usize,
+    ) -> std::io::Result<usize> {
+        let mut pos = offset;
+        while pos < offset + buf.len() {
+            let inpage = pos % CACHEPAGESIZE;
+            let count = std::cmp::min(CACHEPAGESIZE - inpage, offset + buf.len() - pos);
+            // a page that is overwritten whole needn't be read in first
+            let fill = count != CACHEPAGESIZE;
+            let page = self.page(fobj, pos / CACHEPAGESIZE, filesize, fill)?;
+            page.data[inpage..inpage + count]
+                .copy_from_slice(&buf[pos - offset..pos - offset + count]);
+            page.dirty = true;
+            pos += count;
+        }
+        Ok(buf.len())
+    }
+}
+
 #[derive(Debug)]
 pub struct EmulatedFile {
     filename: String,
     fobj: Option<Arc<Mutex<File>>>,
     filesize: usize,
+    cache: Option<Mutex<PageCache>>,
 }
 
 pub fn pathexists(filename: String) -> bool {
-    let path: RustPathBuf = [".".to_string(), filename.clone()].iter().collect();
-    path.exists()
+    hostpath(&filename).exists()
 }
 
 impl EmulatedFile {
@@ -57,12 +353,33 @@ impl EmulatedFile {
             .read(true)
             .write(true)
             .create(true)
-            .open(filename.clone())
+            .open(hostpath(&filename))
             .unwrap();
+        let cachepages = page_cache_pages();
         Ok(EmulatedFile {
             filename,
             fobj: Some(Arc::new(Mutex::new(f))),
             filesize,
+            cache: if cachepages > 0 {
+                Some(Mutex::new(PageCache::new(cachepages)))
+            } else {
+                None
+            },
+        })
+    }
+
+    fn new_memory(filename: String) -> std::io::Result<EmulatedFile> {
+        let cname = std::ffi::CString::new(filename.clone()).unwrap();
+        let memfd = unsafe { libc::memfd_create(cname.as_ptr(), libc::MFD_CLOEXEC) };
+        if memfd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let f = unsafe { File::from_raw_fd(memfd) };
+        Ok(EmulatedFile {
+            filename,
+            fobj: Some(Arc::new(Mutex::new(f))),
+            filesize: 0,
+            cache: None, // memory files gain nothing from a cache
         })
     }
 
@@ -71,7 +388,7 @@ impl EmulatedFile {
             .read(true)
             .write(true)
             .create(true)
-            .open(filename.clone())
+            .open(hostpath(&filename))
             .unwrap();
 
         let filesize = f.metadata()?.len();
@@ -80,10 +397,24 @@ impl EmulatedFile {
             filename,
             fobj: Some(Arc::new(Mutex::new(f))),
             filesize: filesize as usize,
+            cache: None,
         })
     }
 
     pub fn close(&self) -> std::io::Result<()> {
+        self.flush_cache(false)
+    }
+
+    // Write back every dirty cached page, and forget the cached pages too if invalidate is set
+    fn flush_cache(&self, invalidate: bool) -> std::io::Result<()> {
+        if let (Some(cache), Some(f)) = (&self.cache, &self.fobj) {
+            let mut cache = cache.lock();
+            let fobj = f.lock();
+            cache.flush(&fobj, self.filesize)?;
+            if invalidate {
+                cache.pages.clear();
+            }
+        }
         Ok(())
     }
 
@@ -94,6 +425,7 @@ impl EmulatedFile {
                 self.filename
             );
         }
+        self.flush_cache(true)?;
         match &self.fobj {
             None => panic!("{} is already closed.", self.filename),
             Some(f) => {
@@ -106,29 +438,38 @@ impl EmulatedFile {
     }
 
     pub fn fdatasync(&self) -> std::io::Result<()> {
+        self.flush_cache(false)?;
         match &self.fobj {
             None => panic!("{} is already closed.", self.filename),
             Some(f) => {
                 let fobj = f.lock();
-                fobj.sync_data()?;
+                host_sync(&fobj, true)?;
                 Ok(())
             }
         }
     }
 
     pub fn fsync(&self) -> std::io::Result<()> {
+        self.flush_cache(false)?;
         match &self.fobj {
             None => panic!("{} is already closed.", self.filename),
             Some(f) => {
                 let fobj = f.lock();
-                fobj.sync_all()?;
+                host_sync(&fobj, false)?;
                 Ok(())
             }
         }
     }
 
     pub fn sync_file_range(&self, offset: isize, nbytes: isize, flags: u32) -> i32 {
-        let fd = &self.as_fd_handle_raw_int();
+        if self.flush_cache(false).is_err() {
+            return syscall_error(
+                Errno::EIO,
+                "sync_file_range",
+                "could not write back the file",
+            );
+        }
+        let fd = &self.raw_fd();
         let valid_flags = libc::SYNC_FILE_RANGE_WAIT_BEFORE
             | libc::SYNC_FILE_RANGE_WRITE
             | libc::SYNC_FILE_RANGE_WAIT_AFTER;
@@ -139,7 +480,16 @@ impl EmulatedFile {
                 "flags specifies an invalid bit",
             );
         }
-        unsafe { libc::sync_file_range(*fd, offset as off64_t, nbytes as off64_t, flags) }
+        match crate::interface::host_ret(unsafe {
+            libc::sync_file_range(*fd, offset as off64_t, nbytes as off64_t, flags)
+        }) {
+            Ok(ret) => ret,
+            Err(errno) => syscall_error(
+                Errno::from_host(errno),
+                "sync_file_range",
+                "The libc call to sync_file_range failed!",
+            ),
+        }
     }
 
     // Wrapper around Rust's file object read_at function
@@ -154,11 +504,17 @@ impl EmulatedFile {
         match &self.fobj {
             None => panic!("{} is already closed.", self.filename),
             Some(f) => {
-                let fobj = f.lock();
                 if offset > self.filesize {
                     panic!("Seek offset extends past the EOF!");
                 }
-                let bytes_read = fobj.read_at(buf, offset as u64)?;
+                if let Some(cache) = &self.cache {
+                    let mut cache = cache.lock();
+                    if cache.enabled() {
+                        return cache.read(&f.lock(), buf, offset, self.filesize);
+                    }
+                }
+                let fobj = f.lock();
+                let bytes_read = host_read_at(&fobj, buf, offset as u64)?;
                 Ok(bytes_read)
             }
         }
@@ -183,11 +539,17 @@ impl EmulatedFile {
         match &self.fobj {
             None => panic!("{} is already closed.", self.filename),
             Some(f) => {
-                let fobj = f.lock();
                 if offset > self.filesize {
                     panic!("Seek offset extends past the EOF!");
                 }
-                bytes_written = fobj.write_at(buf, offset as u64)?;
+                let newsize = std::cmp::max(self.filesize, offset + length);
+                let mut cache = self.cache.as_ref().map(|cache| cache.lock());
+                bytes_written = match cache.as_mut() {
+                    Some(cache) if cache.enabled() => {
+                        cache.write(&f.lock(), buf, offset, newsize)?
+                    }
+                    _ => host_write_at(&f.lock(), buf, offset as u64)?,
+                };
             }
         }
 
@@ -201,6 +563,7 @@ impl EmulatedFile {
 
     // Reads entire file into bytes
     pub fn readfile_to_new_bytes(&self) -> std::io::Result<Vec<u8>> {
+        self.flush_cache(false)?;
         match &self.fobj {
             None => panic!("{} is already closed.", self.filename),
             Some(f) => {
@@ -217,6 +580,7 @@ impl EmulatedFile {
         let length = buf.len();
         let offset = self.filesize;
 
+        self.flush_cache(true)?;
         match &self.fobj {
             None => panic!("{} is already closed.", self.filename),
             Some(f) => {
@@ -240,6 +604,7 @@ impl EmulatedFile {
         let bytes_written;
         let buf = vec![0; count];
 
+        self.flush_cache(true)?;
         match &self.fobj {
             None => panic!("{} is already closed.", self.filename),
             Some(f) => {
@@ -260,7 +625,16 @@ impl EmulatedFile {
     }
 
     //gets the raw fd handle (integer) from a rust fileobject
+    //whoever has it can reach the data without going through us, so from then on nothing is cached
     pub fn as_fd_handle_raw_int(&self) -> i32 {
+        if let Some(cache) = &self.cache {
+            let _ = self.flush_cache(true);
+            cache.lock().capacity = 0;
+        }
+        self.raw_fd()
+    }
+
+    fn raw_fd(&self) -> i32 {
         if let Some(wrapped_barefile) = &self.fobj {
             wrapped_barefile.lock().as_raw_fd() as i32
         } else {
@@ -269,8 +643,101 @@ impl EmulatedFile {
     }
 }
 
+// anything still dirty when the file goes away is written back on a best effort basis
+impl Drop for EmulatedFile {
+    fn drop(&mut self) {
+        let _ = self.flush_cache(false);
+    }
+}
+
 pub const COUNTMAPSIZE: usize = 8;
 pub const MAP_1MB: usize = usize::pow(2, 20);
+// each log entry is preceded by its length and a CRC32 of its bytes, both big endian u32s, so a
+// torn write at the end of the log can be told apart from a complete entry
+pub const LOGENTRYHEADERSIZE: usize = 8;
+// set in the length of a record holding a batch of entries, each preceded by its length as a big
+// endian u32. The checksum covers the whole batch, so its entries are replayed all or not at all.
+pub const LOGBATCHFLAG: u32 = 1 << 31;
+const LOGBATCHLENSIZE: usize = 4;
+
+// CRC-32 as used by zlib and ethernet (reflected polynomial 0xEDB88320)
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
+
+// Splits a log file read back from disk into its entries, stopping at the first one that is cut
+// short or fails its checksum. Returns the intact entries and whether the whole log was intact.
+pub fn read_log_entries(logbytes: &[u8]) -> (Vec<&[u8]>, bool) {
+    let mut entries = Vec::new();
+    if logbytes.len() < COUNTMAPSIZE {
+        return (entries, logbytes.is_empty());
+    }
+
+    let count = convert_bytes_to_size(&logbytes[0..COUNTMAPSIZE]);
+    let available = logbytes.len() - COUNTMAPSIZE;
+    let mut intact = count <= available;
+    let log = &logbytes[COUNTMAPSIZE..(COUNTMAPSIZE + usize::min(count, available))];
+
+    let mut offset = 0;
+    while offset < log.len() {
+        if log.len() - offset < LOGENTRYHEADERSIZE {
+            intact = false;
+            break;
+        }
+        let header = u32::from_be_bytes(log[offset..offset + 4].try_into().unwrap());
+        let entrylen = (header & !LOGBATCHFLAG) as usize;
+        let entrycrc = u32::from_be_bytes(log[offset + 4..offset + 8].try_into().unwrap());
+        let start = offset + LOGENTRYHEADERSIZE;
+        if log.len() - start < entrylen || crc32(&log[start..start + entrylen]) != entrycrc {
+            intact = false;
+            break;
+        }
+        let record = &log[start..start + entrylen];
+        if header & LOGBATCHFLAG == 0 {
+            entries.push(record);
+        } else {
+            match split_log_batch(record) {
+                Some(batch) => entries.extend(batch),
+                None => {
+                    intact = false;
+                    break;
+                }
+            }
+        }
+        offset = start + entrylen;
+    }
+
+    (entries, intact)
+}
+
+// the entries of a batch record, or None if their lengths don't add up to the record
+fn split_log_batch(record: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut batch = Vec::new();
+    let mut offset = 0;
+    while offset < record.len() {
+        if record.len() - offset < LOGBATCHLENSIZE {
+            return None;
+        }
+        let entrylen =
+            u32::from_be_bytes(record[offset..offset + LOGBATCHLENSIZE].try_into().unwrap())
+                as usize;
+        let start = offset + LOGBATCHLENSIZE;
+        if record.len() - start < entrylen {
+            return None;
+        }
+        batch.push(&record[start..start + entrylen]);
+        offset = start + entrylen;
+    }
+    Some(batch)
+}
 
 #[derive(Debug)]
 pub struct EmulatedFileMap {
@@ -292,7 +759,7 @@ impl EmulatedFileMap {
             .read(true)
             .write(true)
             .create(true)
-            .open(filename.clone())
+            .open(hostpath(&filename))
             .unwrap();
 
         let mapsize = MAP_1MB - COUNTMAPSIZE;
@@ -329,18 +796,43 @@ impl EmulatedFileMap {
     }
 
     pub fn write_to_map(&mut self, bytes_to_write: &[u8]) -> std::io::Result<()> {
-        let writelen = bytes_to_write.len();
+        self.write_record(bytes_to_write.len() as u32, bytes_to_write)
+    }
+
+    // Write several entries as a single record, which replay applies all together or not at all
+    pub fn write_batch_to_map(&mut self, entries: &[Vec<u8>]) -> std::io::Result<()> {
+        if entries.len() == 1 {
+            return self.write_to_map(&entries[0]);
+        }
+
+        let batchlen = entries
+            .iter()
+            .map(|entry| LOGBATCHLENSIZE + entry.len())
+            .sum();
+        let mut batch = Vec::with_capacity(batchlen);
+        for entry in entries {
+            batch.extend_from_slice(&(entry.len() as u32).to_be_bytes());
+            batch.extend_from_slice(entry);
+        }
+        self.write_record(batch.len() as u32 | LOGBATCHFLAG, &batch)
+    }
+
+    fn write_record(&mut self, header: u32, bytes_to_write: &[u8]) -> std::io::Result<()> {
+        let writelen = LOGENTRYHEADERSIZE + bytes_to_write.len();
 
         // if we're writing past the current map, increase the map another 1MB
-        if writelen + self.count > self.mapsize {
+        while writelen + self.count > self.mapsize {
             self.extend_map();
         }
 
         let mut mapopt = self.map.lock();
         let map = mapopt.as_deref_mut().unwrap();
 
+        // frame the entry with its length and checksum
         let mapslice = &mut map[self.count..(self.count + writelen)];
-        mapslice.copy_from_slice(bytes_to_write);
+        mapslice[0..4].copy_from_slice(&header.to_be_bytes());
+        mapslice[4..8].copy_from_slice(&crc32(bytes_to_write).to_be_bytes());
+        mapslice[LOGENTRYHEADERSIZE..].copy_from_slice(bytes_to_write);
         self.count += writelen;
 
         // update the bytes written in the map portion
@@ -351,6 +843,11 @@ impl EmulatedFileMap {
         Ok(())
     }
 
+    // bytes of log entries written so far
+    pub fn bytes_logged(&self) -> usize {
+        self.count
+    }
+
     fn extend_map(&mut self) {
         // open count and map to resize mmap, and file to increase file size
         let mut mapopt = self.map.lock();
@@ -437,12 +934,12 @@ impl ShmFile {
             .read(true)
             .write(true)
             .create(true)
-            .open(filename.clone())
+            .open(hostpath(&filename))
             .unwrap();
         // truncate file to size
         f.set_len(size as u64)?;
         // unlink file
-        fs::remove_file(filename)?;
+        fs::remove_file(hostpath(&filename))?;
         let shmfile = ShmFile {
             fobj: Arc::new(Mutex::new(f)),
             key,
@@ -482,6 +979,13 @@ mod tests {
         let non_existent_file = "/tmp/non_existent_file.txt";
         assert!(!pathexists(non_existent_file.to_string()));
     }
+    #[test]
+    fn test_hostpath_in_lind_root() {
+        assert_eq!(hostpath("lind.metadata"), lind_root().join("lind.metadata"));
+        //names that are already absolute are left alone
+        assert_eq!(hostpath("/tmp/lind.metadata"), RustPathBuf::from("/tmp/lind.metadata"));
+    }
+
     #[test]
     fn test_new_emulated_file() {
         let filename = "test_file.txt";
@@ -538,4 +1042,104 @@ mod tests {
         emulated_file.readat(buffer.as_mut_ptr(), buffer.len(), 0).unwrap();
         assert_eq!(buffer, new_content);
     }
+
+    #[test]
+    fn test_page_cache_write_back() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let file_path = temp_file.path().to_str().unwrap().to_string();
+        temp_file.as_file().write_all(&[1u8; 6000]).unwrap();
+
+        let mut emulated_file = EmulatedFile::new(file_path.clone(), 6000).unwrap();
+        if emulated_file.cache.is_none() {
+            return; // caching has been turned off
+        }
+
+        // a write straddling two pages reads back at once but stays off the host
+        let new_content = [2u8; 3000];
+        emulated_file
+            .writeat(new_content.as_ptr(), new_content.len(), 3000)
+            .unwrap();
+        let mut buffer = vec![0; 7000];
+        let bytes_read = emulated_file
+            .readat(buffer.as_mut_ptr(), buffer.len(), 0)
+            .unwrap();
+        assert_eq!(bytes_read, 6000);
+        assert!(buffer[..3000].iter().all(|b| *b == 1));
+        assert!(buffer[3000..6000].iter().all(|b| *b == 2));
+        let ondisk = fs::read(&file_path).unwrap();
+        assert!(ondisk[3000..].iter().all(|b| *b == 1));
+
+        // extending the file and fsyncing puts everything on the host
+        emulated_file
+            .writeat(new_content.as_ptr(), new_content.len(), 6000)
+            .unwrap();
+        emulated_file.fsync().unwrap();
+        let ondisk = fs::read(&file_path).unwrap();
+        assert_eq!(ondisk.len(), 9000);
+        assert!(ondisk[3000..].iter().all(|b| *b == 2));
+
+        // once the raw fd is handed out, writes go straight to the host
+        emulated_file.as_fd_handle_raw_int();
+        emulated_file.writeat([3u8].as_ptr(), 1, 0).unwrap();
+        assert_eq!(fs::read(&file_path).unwrap()[0], 3);
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn test_read_log_entries() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let file_path = temp_file.path().to_str().unwrap().to_string();
+
+        let mut logmap = EmulatedFileMap::new(file_path.clone()).unwrap();
+        logmap.write_to_map(b"first").unwrap();
+        logmap.write_to_map(b"second").unwrap();
+        logmap.close().unwrap();
+        let mut logbytes = fs::read(&file_path).unwrap();
+
+        let (entries, intact) = read_log_entries(&logbytes);
+        assert!(intact);
+        assert_eq!(entries, vec![&b"first"[..], &b"second"[..]]);
+
+        // a torn second entry is dropped, the first survives
+        logbytes[COUNTMAPSIZE + 2 * LOGENTRYHEADERSIZE + 6] ^= 0xFF;
+        let (entries, intact) = read_log_entries(&logbytes);
+        assert!(!intact);
+        assert_eq!(entries, vec![&b"first"[..]]);
+
+        // a count claiming more than the file holds stops at what is there
+        logbytes.truncate(COUNTMAPSIZE + LOGENTRYHEADERSIZE + 5 + 3);
+        let (entries, intact) = read_log_entries(&logbytes);
+        assert!(!intact);
+        assert_eq!(entries, vec![&b"first"[..]]);
+    }
+
+    #[test]
+    fn test_read_log_batch() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let file_path = temp_file.path().to_str().unwrap().to_string();
+
+        let mut logmap = EmulatedFileMap::new(file_path.clone()).unwrap();
+        logmap.write_to_map(b"first").unwrap();
+        logmap
+            .write_batch_to_map(&[b"second".to_vec(), b"third".to_vec()])
+            .unwrap();
+        logmap.close().unwrap();
+        let mut logbytes = fs::read(&file_path).unwrap();
+
+        // a batch reads back as the entries it holds
+        let (entries, intact) = read_log_entries(&logbytes);
+        assert!(intact);
+        assert_eq!(entries, vec![&b"first"[..], &b"second"[..], &b"third"[..]]);
+
+        // a batch torn in its last entry loses the ones before it too
+        logbytes[COUNTMAPSIZE + 2 * LOGENTRYHEADERSIZE + 5 + 4 + 6 + 4 + 2] ^= 0xFF;
+        let (entries, intact) = read_log_entries(&logbytes);
+        assert!(!intact);
+        assert_eq!(entries, vec![&b"first"[..]]);
+    }
 }
@@ -0,0 +1,149 @@
+// Futex wait queues for SafePOSIX
+//
+// Waiters are queued by a key derived from the futex word's address, so the queues never touch
+// the word itself beyond the value check done when a waiter goes to sleep.
+#![allow(dead_code)]
+
+use crate::interface;
+use crate::interface::errnos::Errno;
+use std::collections::HashMap;
+
+// A word inside a shared memory segment is named by the segment and its offset, so every cage
+// that has the segment attached finds the same queue no matter where it mapped it. Any other word
+// is private to the cage it lives in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FutexKey {
+    Private(u64, usize), // cage id, address within the cage
+    Shared(i32, usize),  // shmid, offset within the segment
+}
+
+struct FutexWaiter {
+    woken: interface::Mutex<bool>,
+    cv: interface::Condvar,
+}
+
+// a single lock over every queue keeps requeueing between two keys atomic
+static FUTEX_TABLE: interface::RustLazyGlobal<
+    interface::Mutex<HashMap<FutexKey, interface::RustDeque<interface::RustRfc<FutexWaiter>>>>,
+> = interface::RustLazyGlobal::new(|| interface::Mutex::new(HashMap::new()));
+
+// Sleep on key as long as the word at uaddr still holds val, until woken, until timeout runs
+// out, or until interrupted reports a signal for the caller. The value is checked under the table lock, so a waker that changes the word first and
+// then wakes the queue can never slip in between the check and the wait.
+pub fn futex_wait(
+    key: FutexKey,
+    uaddr: *const u32,
+    val: u32,
+    timeout: Option<interface::RustDuration>,
+    interrupted: &dyn Fn() -> bool,
+) -> Result<(), Errno> {
+    let deadline = timeout.map(|duration| interface::RustInstant::now() + duration);
+    let waiter = interface::RustRfc::new(FutexWaiter {
+        woken: interface::Mutex::new(false),
+        cv: interface::Condvar::new(),
+    });
+
+    {
+        let mut table = FUTEX_TABLE.lock();
+        let word = unsafe { &*(uaddr as *const interface::RustAtomicU32) };
+        if word.load(interface::RustAtomicOrdering::SeqCst) != val {
+            return Err(Errno::EAGAIN);
+        }
+        table
+            .entry(key)
+            .or_insert_with(interface::RustDeque::new)
+            .push_back(waiter.clone());
+    }
+
+    let mut woken = waiter.woken.lock();
+    let mut failure = Errno::ETIMEDOUT;
+    while !*woken {
+        if interrupted() {
+            failure = Errno::EINTR;
+            break;
+        }
+        // wake up every so often to look for signals
+        let nextcheck = interface::RustInstant::now() + interface::SIGNAL_CHECK_INTERVAL;
+        match deadline {
+            Some(deadline) if deadline <= nextcheck => {
+                if waiter.cv.wait_until(&mut woken, deadline).timed_out() {
+                    break;
+                }
+            }
+            _ => {
+                waiter.cv.wait_until(&mut woken, nextcheck);
+            }
+        }
+    }
+    if *woken {
+        return Ok(());
+    }
+    drop(woken);
+
+    // Timed out or interrupted, but a wake may still have raced in before we got the table lock back. Wakers
+    // dequeue under the table lock before flagging, so an unflagged waiter is still queued,
+    // though a requeue may have moved it off the key it started on.
+    let mut table = FUTEX_TABLE.lock();
+    if *waiter.woken.lock() {
+        return Ok(());
+    }
+    for queue in table.values_mut() {
+        queue.retain(|queued| !interface::RustRfc::ptr_eq(queued, &waiter));
+    }
+    table.retain(|_, queue| !queue.is_empty());
+    Err(failure)
+}
+
+fn wake_waiter(waiter: interface::RustRfc<FutexWaiter>) {
+    *waiter.woken.lock() = true;
+    waiter.cv.notify_one();
+}
+
+// wake up to count waiters on key in the order they went to sleep, returning how many were woken
+pub fn futex_wake(key: FutexKey, count: usize) -> usize {
+    futex_requeue(key, count, key, 0)
+}
+
+// Wake up to wakecount waiters on key, then move up to requeuecount of those left over onto
+// newkey without waking them. Returns how many were woken.
+pub fn futex_requeue(
+    key: FutexKey,
+    wakecount: usize,
+    newkey: FutexKey,
+    requeuecount: usize,
+) -> usize {
+    let mut table = FUTEX_TABLE.lock();
+    let woken = {
+        let mut woken = 0;
+        if let Some(queue) = table.get_mut(&key) {
+            while woken < wakecount {
+                match queue.pop_front() {
+                    Some(waiter) => wake_waiter(waiter),
+                    None => break,
+                }
+                woken += 1;
+            }
+        }
+        woken
+    };
+
+    if newkey != key {
+        let moved: Vec<interface::RustRfc<FutexWaiter>> = match table.get_mut(&key) {
+            Some(queue) => {
+                let movecount = interface::rust_min(requeuecount, queue.len());
+                queue.drain(..movecount).collect()
+            }
+            None => vec![],
+        };
+        if !moved.is_empty() {
+            table
+                .entry(newkey)
+                .or_insert_with(interface::RustDeque::new)
+                .extend(moved);
+        }
+    }
+    if table.get(&key).map_or(false, |queue| queue.is_empty()) {
+        table.remove(&key);
+    }
+    woken
+}
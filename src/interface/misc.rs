@@ -8,11 +8,12 @@ pub use dashmap::{
     mapref::entry::Entry as RustHashEntry, DashMap as RustHashMap, DashSet as RustHashSet,
 };
 pub use parking_lot::{
-    Condvar, Mutex, RwLock as RustLock, RwLockReadGuard as RustLockReadGuard,
+    Condvar, Mutex, MutexGuard, RwLock as RustLock, RwLockReadGuard as RustLockReadGuard,
     RwLockWriteGuard as RustLockWriteGuard,
 };
 use std::cell::RefCell;
 pub use std::cmp::{max as rust_max, min as rust_min};
+pub use std::collections::BTreeMap as RustBTreeMap;
 pub use std::collections::VecDeque as RustDeque;
 use std::fs::File;
 use std::io::{self, Read, Write};
@@ -23,7 +24,7 @@ pub use std::sync::atomic::{
     Ordering as RustAtomicOrdering,
 };
 pub use std::sync::Arc as RustRfc;
-pub use std::thread::spawn as helper_thread;
+pub use std::thread::JoinHandle as RustJoinHandle;
 
 use libc::{mmap, pthread_exit, pthread_kill, pthread_self, sched_yield};
 use std::ffi::c_void;
@@ -34,13 +35,14 @@ pub use serde_cbor::{
 };
 
 use crate::interface;
-use crate::interface::errnos::VERBOSE;
-use crate::interface::types::SigsetType;
+use crate::interface::errnos::{Errno, VERBOSE};
+use crate::interface::types::{SiginfoStruct, SigsetType};
 use crate::safeposix::syscalls::fs_constants::SEM_VALUE_MAX;
 use std::sync::LazyLock;
 use std::time::Duration;
 
 pub const MAXCAGEID: i32 = 1024;
+const NICE_BACKOFF_MICROS: u64 = 50;
 const EXIT_SUCCESS: i32 = 0;
 
 pub static RUSTPOSIX_TESTSUITE: LazyLock<RustAtomicBool> =
@@ -93,6 +95,12 @@ pub fn cagetable_getref_opt(cageid: u64) -> Option<RustRfc<Cage>> {
     }
 }
 
+pub fn cagetable_getrefs() -> Vec<RustRfc<Cage>> {
+    (0..MAXCAGEID as u64)
+        .filter_map(cagetable_getref_opt)
+        .collect()
+}
+
 pub fn cagetable_clear() {
     let mut exitvec = Vec::new();
     unsafe {
@@ -140,6 +148,54 @@ pub fn get_errno() -> i32 {
     (unsafe { *libc::__errno_location() }) as i32
 }
 
+// The return value of a host call that signals failure with -1, or the errno it failed with.
+// errno belongs to the host thread and whatever runs next on it (logging, taking a lock, another
+// host call) can overwrite it, so wrappers capture it here as the call returns rather than leave
+// the syscall to read it after the fact.
+pub fn host_ret(ret: i32) -> Result<i32, i32> {
+    if ret < 0 {
+        Err(get_errno())
+    } else {
+        Ok(ret)
+    }
+}
+
+//Read whatever the host's standard input has to offer, waiting at most timeout for it to have
+//anything. Returns the bytes read, 0 at end of file, or -EAGAIN if nothing arrived in time.
+pub fn read_host_stdin(buf: &mut [u8], timeout: interface::RustDuration) -> i32 {
+    let mut pollfd = libc::pollfd {
+        fd: 0,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let ready = unsafe { libc::poll(&mut pollfd, 1, timeout.as_millis() as i32) };
+    if ready == 0 {
+        return -(Errno::EAGAIN as i32);
+    }
+    if ready < 0 {
+        return -get_errno();
+    }
+    let ret = unsafe { libc::read(0, buf.as_mut_ptr() as *mut c_void, buf.len()) };
+    if ret < 0 {
+        return -get_errno();
+    }
+    ret as i32
+}
+
+//the size of the terminal the host's standard output goes to, if it goes to one
+pub fn host_winsize() -> Option<interface::WinSize> {
+    let mut hostsize: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(1, libc::TIOCGWINSZ, &mut hostsize) } < 0 || hostsize.ws_row == 0 {
+        return None;
+    }
+    Some(interface::WinSize {
+        ws_row: hostsize.ws_row,
+        ws_col: hostsize.ws_col,
+        ws_xpixel: hostsize.ws_xpixel,
+        ws_ypixel: hostsize.ws_ypixel,
+    })
+}
+
 // Cancellation functions
 
 pub fn lind_threadexit() {
@@ -152,6 +208,36 @@ pub fn lind_threadkill(thread_id: u64, sig: i32) -> i32 {
     unsafe { pthread_kill(thread_id, sig) as i32 }
 }
 
+// Cpu masks are kept as bytes, cpu n being bit n % 8 of byte n / 8, as sched_getaffinity lays
+// them out, and cover as many cpus as a host cpu_set_t does.
+pub const CPU_MASK_BYTES: usize = std::mem::size_of::<libc::cpu_set_t>();
+
+// the host cpus this process may run on
+pub fn lind_process_cpumask() -> Vec<u8> {
+    let mut cpuset: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    let mut mask = vec![0u8; CPU_MASK_BYTES];
+    if unsafe { libc::sched_getaffinity(0, CPU_MASK_BYTES, &mut cpuset) } != 0 {
+        return mask;
+    }
+    for cpu in 0..CPU_MASK_BYTES * 8 {
+        if unsafe { libc::CPU_ISSET(cpu, &cpuset) } {
+            mask[cpu / 8] |= 1 << (cpu % 8);
+        }
+    }
+    mask
+}
+
+// pins a host thread to the cpus set in mask
+pub fn lind_setaffinity(thread_id: u64, mask: &[u8]) -> i32 {
+    let mut cpuset: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for cpu in 0..rust_min(mask.len(), CPU_MASK_BYTES) * 8 {
+        if mask[cpu / 8] & (1 << (cpu % 8)) != 0 {
+            unsafe { libc::CPU_SET(cpu, &mut cpuset) };
+        }
+    }
+    unsafe { libc::pthread_setaffinity_np(thread_id, CPU_MASK_BYTES, &cpuset) }
+}
+
 pub fn get_pthreadid() -> u64 {
     unsafe { pthread_self() as u64 }
 }
@@ -162,6 +248,17 @@ pub fn lind_yield() {
     }
 }
 
+// Spin-wait loops yield according to the cage's nice value. A cage that has been made nicer
+// backs off for a little longer each time round instead of yielding straight back into the
+// run queue, leaving the CPU to cages with a higher priority.
+pub fn lind_yield_nice(nice: i32) {
+    if nice > 0 {
+        std::thread::sleep(Duration::from_micros(nice as u64 * NICE_BACKOFF_MICROS));
+    } else {
+        lind_yield();
+    }
+}
+
 // this function checks if a thread is killable and returns that state
 pub fn check_thread(cageid: u64, tid: u64) -> bool {
     let cage = cagetable_getref(cageid);
@@ -169,21 +266,6 @@ pub fn check_thread(cageid: u64, tid: u64) -> bool {
     killable
 }
 
-// in-rustposix cancelpoints checks if the thread is killable,
-// and if sets killable back to false and kills the thread
-pub fn cancelpoint(cageid: u64) {
-    if RUSTPOSIX_TESTSUITE.load(RustAtomicOrdering::Relaxed) {
-        return;
-    } // we don't use this when testing rustposix standalone
-
-    let pthread_id = get_pthreadid();
-    if check_thread(cageid, pthread_id) {
-        let cage = cagetable_getref(cageid);
-        cage.thread_table.insert(pthread_id, false);
-        lind_threadexit();
-    }
-}
-
 pub fn signalflag_set(value: u64) {
     TRUSTED_SIGNAL_FLAG.with(|v| *v.borrow_mut() = value);
 }
@@ -266,9 +348,24 @@ pub unsafe fn charstar_to_ruststr<'a>(cstr: CharPtr) -> Result<&'a str, Utf8Erro
     std::ffi::CStr::from_ptr(cstr as *const _).to_str() //returns a result to be unwrapped later
 }
 
-pub fn libc_mmap(addr: *mut u8, len: usize, prot: i32, flags: i32, fildes: i32, off: i64) -> i32 {
-    return ((unsafe { mmap(addr as *mut c_void, len, prot, flags, fildes, off) } as i64)
-        & 0xffffffff) as i32;
+// the full host address, syscalls hand the cage only its low 32 bits
+pub fn libc_mmap(
+    addr: *mut u8,
+    len: usize,
+    prot: i32,
+    flags: i32,
+    fildes: i32,
+    off: i64,
+) -> Result<usize, i32> {
+    let mapped = unsafe { mmap(addr as *mut c_void, len, prot, flags, fildes, off) };
+    if mapped == libc::MAP_FAILED {
+        return Err(get_errno());
+    }
+    Ok(mapped as usize)
+}
+
+pub fn libc_msync(addr: *mut u8, len: usize, flags: i32) -> Result<i32, i32> {
+    host_ret(unsafe { libc::msync(addr as *mut c_void, len, flags) })
 }
 
 // Sigset Operations
@@ -297,12 +394,31 @@ pub fn lind_sigismember(set: SigsetType, signum: i32) -> bool {
     set & (1 << (signum - 1)) != 0
 }
 
+//spawn a thread that works in the lind machine of the thread that spawned it, see context.rs
+pub fn helper_thread<F, T>(f: F) -> RustJoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let context = crate::safeposix::context::current_context();
+    std::thread::spawn(move || {
+        crate::safeposix::context::set_thread_context(context);
+        f()
+    })
+}
+
 // Signals
 pub fn lind_kill_from_id(cage_id: u64, sig: i32) {
     if let Some(cage) = cagetable_getref_opt(cage_id as u64) {
-        let cage_main_thread_id = cage.main_threadid.load(RustAtomicOrdering::Relaxed);
-        assert!(cage_main_thread_id != 0);
-        lind_threadkill(cage_main_thread_id, sig);
+        cage.post_signal(sig);
+    }
+}
+
+// like lind_kill_from_id, for a signal that carries a siginfo of its own
+pub fn lind_sigqueue_from_id(cage_id: u64, info: SiginfoStruct) -> i32 {
+    match cagetable_getref_opt(cage_id as u64) {
+        Some(cage) => cage.queue_signal(info),
+        None => -(Errno::ESRCH as i32),
     }
 }
 
@@ -405,6 +521,15 @@ impl AdvisoryLock {
     }
 }
 
+// pthread calls give back the error number itself, rather than setting errno and returning -1
+fn pthread_ret(ret: i32) -> Result<i32, i32> {
+    if ret == 0 {
+        Ok(0)
+    } else {
+        Err(ret)
+    }
+}
+
 pub struct RawMutex {
     inner: libc::pthread_mutex_t,
 }
@@ -421,35 +546,31 @@ impl RawMutex {
                 std::ptr::null(),
             );
         }
-        if libcret < 0 {
-            Err(libcret)
-        } else {
-            Ok(retval)
-        }
+        pthread_ret(libcret).map(|_| retval)
     }
 
-    pub fn lock(&self) -> i32 {
-        unsafe {
+    pub fn lock(&self) -> Result<i32, i32> {
+        pthread_ret(unsafe {
             libc::pthread_mutex_lock(
                 (&self.inner) as *const libc::pthread_mutex_t as *mut libc::pthread_mutex_t,
             )
-        }
+        })
     }
 
-    pub fn trylock(&self) -> i32 {
-        unsafe {
+    pub fn trylock(&self) -> Result<i32, i32> {
+        pthread_ret(unsafe {
             libc::pthread_mutex_trylock(
                 (&self.inner) as *const libc::pthread_mutex_t as *mut libc::pthread_mutex_t,
             )
-        }
+        })
     }
 
-    pub fn unlock(&self) -> i32 {
-        unsafe {
+    pub fn unlock(&self) -> Result<i32, i32> {
+        pthread_ret(unsafe {
             libc::pthread_mutex_unlock(
                 (&self.inner) as *const libc::pthread_mutex_t as *mut libc::pthread_mutex_t,
             )
-        }
+        })
     }
 }
 
@@ -483,50 +604,46 @@ impl RawCondvar {
                 std::ptr::null(),
             );
         }
-        if libcret < 0 {
-            Err(libcret)
-        } else {
-            Ok(retval)
-        }
+        pthread_ret(libcret).map(|_| retval)
     }
 
-    pub fn signal(&self) -> i32 {
-        unsafe {
+    pub fn signal(&self) -> Result<i32, i32> {
+        pthread_ret(unsafe {
             libc::pthread_cond_signal(
                 (&self.inner) as *const libc::pthread_cond_t as *mut libc::pthread_cond_t,
             )
-        }
+        })
     }
 
-    pub fn broadcast(&self) -> i32 {
-        unsafe {
+    pub fn broadcast(&self) -> Result<i32, i32> {
+        pthread_ret(unsafe {
             libc::pthread_cond_broadcast(
                 (&self.inner) as *const libc::pthread_cond_t as *mut libc::pthread_cond_t,
             )
-        }
+        })
     }
 
-    pub fn wait(&self, mutex: &RawMutex) -> i32 {
-        unsafe {
+    pub fn wait(&self, mutex: &RawMutex) -> Result<i32, i32> {
+        pthread_ret(unsafe {
             libc::pthread_cond_wait(
                 (&self.inner) as *const libc::pthread_cond_t as *mut libc::pthread_cond_t,
                 (&mutex.inner) as *const libc::pthread_mutex_t as *mut libc::pthread_mutex_t,
             )
-        }
+        })
     }
 
-    pub fn timedwait(&self, mutex: &RawMutex, abs_duration: Duration) -> i32 {
+    pub fn timedwait(&self, mutex: &RawMutex, abs_duration: Duration) -> Result<i32, i32> {
         let abstime = libc::timespec {
             tv_sec: abs_duration.as_secs() as i64,
             tv_nsec: (abs_duration.as_nanos() % 1000000000) as i64,
         };
-        unsafe {
+        pthread_ret(unsafe {
             libc::pthread_cond_timedwait(
                 (&self.inner) as *const libc::pthread_cond_t as *mut libc::pthread_cond_t,
                 (&mutex.inner) as *const libc::pthread_mutex_t as *mut libc::pthread_mutex_t,
                 (&abstime) as *const libc::timespec,
             )
-        }
+        })
     }
 }
 
@@ -6,14 +6,24 @@
 mod comm;
 pub mod errnos;
 mod file;
+mod futex;
 mod misc;
 mod pipe;
+mod readiness;
+mod serial;
 mod timer;
 pub mod types;
+#[cfg(feature = "io_uring")]
+mod uring;
 pub use comm::*;
 pub use errnos::*;
 pub use file::*;
+pub use futex::*;
 pub use misc::*;
 pub use pipe::*;
+pub use readiness::*;
+pub use serial::*;
 pub use timer::*;
 pub use types::*;
+#[cfg(feature = "io_uring")]
+pub use uring::*;
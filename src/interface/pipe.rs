@@ -6,12 +6,13 @@
 use crate::interface;
 use crate::interface::errnos::{syscall_error, Errno};
 
-use parking_lot::Mutex;
-use ringbuf::{Consumer, Producer, RingBuffer};
+use parking_lot::{Condvar, Mutex, RwLock};
 use std::cmp::min;
+use std::collections::HashMap;
 use std::fmt;
+use std::ptr;
 use std::slice;
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 const O_RDONLY: i32 = 0o0;
@@ -19,38 +20,211 @@ const O_WRONLY: i32 = 0o1;
 const O_RDWRFLAGS: i32 = 0o3;
 const PAGE_SIZE: usize = 4096;
 
-const CANCEL_CHECK_INTERVAL: usize = 1048576; // check to cancel pipe reads every 2^20 iterations
+const BUFFER_POOL_DEPTH: usize = 32; // ring buffers of each capacity kept around for reuse
 
 pub fn new_pipe(size: usize) -> EmulatedPipe {
     EmulatedPipe::new_with_capacity(size)
 }
 
+// Storage of pipes that have gone away, by capacity. Every domain socket connection makes a
+// pair of pipes, so a new one can usually take over a buffer rather than allocate its own.
+static BUFFER_POOL: interface::RustLazyGlobal<Mutex<HashMap<usize, Vec<Box<[u8]>>>>> =
+    interface::RustLazyGlobal::new(|| Mutex::new(HashMap::new()));
+
+// How many buffers of the capacity the pool holds
+pub fn pooled_buffers(capacity: usize) -> usize {
+    BUFFER_POOL
+        .lock()
+        .get(&capacity)
+        .map_or(0, |buffers| buffers.len())
+}
+
+// The ring buffer backing a pipe. head and tail count every byte ever read and written, so the
+// bytes in the pipe are the ones between them. Only one reader pops and only one writer pushes at
+// a time (the pipe's reader and writer locks see to that), so each cursor has a single owner and
+// the other side just loads it. The buffer is swapped out whole when the pipe is resized, so
+// readers and writers only hold it for as long as a single copy in or out takes.
+struct PipeBuffer {
+    data: *mut u8,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    capacity: usize,
+}
+
+// data is only touched between the cursors, by the one reader or writer that owns that side
+unsafe impl Send for PipeBuffer {}
+unsafe impl Sync for PipeBuffer {}
+
+impl PipeBuffer {
+    fn new(capacity: usize) -> PipeBuffer {
+        let pooled = BUFFER_POOL
+            .lock()
+            .get_mut(&capacity)
+            .and_then(|buffers| buffers.pop());
+        let storage = match pooled {
+            Some(storage) => storage,
+            None => vec![0u8; capacity].into_boxed_slice(),
+        };
+        PipeBuffer {
+            data: Box::into_raw(storage) as *mut u8,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            capacity: capacity,
+        }
+    }
+
+    fn len(&self) -> usize {
+        let tail = self.tail.load(Ordering::SeqCst);
+        tail.wrapping_sub(self.head.load(Ordering::SeqCst))
+    }
+
+    fn remaining(&self) -> usize {
+        self.capacity - self.len()
+    }
+
+    // Copy as much of src as fits in after tail. Must only be called by the pipe's current writer.
+    fn push_slice(&self, src: &[u8]) -> usize {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::SeqCst);
+        let count = min(src.len(), self.capacity - tail.wrapping_sub(head));
+        if count == 0 {
+            return 0;
+        }
+
+        let start = tail % self.capacity;
+        let first = min(count, self.capacity - start);
+        unsafe {
+            ptr::copy_nonoverlapping(src.as_ptr(), self.data.add(start), first);
+            ptr::copy_nonoverlapping(src.as_ptr().add(first), self.data, count - first);
+        }
+        self.tail.store(tail.wrapping_add(count), Ordering::SeqCst);
+        count
+    }
+
+    // Copy as much as dst can take from head onwards. Must only be called by the pipe's current reader.
+    fn pop_slice(&self, dst: &mut [u8]) -> usize {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::SeqCst);
+        let count = min(dst.len(), tail.wrapping_sub(head));
+        if count == 0 {
+            return 0;
+        }
+
+        let start = head % self.capacity;
+        let first = min(count, self.capacity - start);
+        unsafe {
+            ptr::copy_nonoverlapping(self.data.add(start), dst.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(self.data, dst.as_mut_ptr().add(first), count - first);
+        }
+        self.head.store(head.wrapping_add(count), Ordering::SeqCst);
+        count
+    }
+
+    // Drop everything in the buffer. Must only be called by the pipe's current reader.
+    fn clear(&self) {
+        self.head
+            .store(self.tail.load(Ordering::SeqCst), Ordering::SeqCst);
+    }
+}
+
+// Hand the storage back to the pool, unless enough of its size are pooled already
+impl Drop for PipeBuffer {
+    fn drop(&mut self) {
+        let storage = unsafe { Box::from_raw(slice::from_raw_parts_mut(self.data, self.capacity)) };
+        let mut pool = BUFFER_POOL.lock();
+        let buffers = pool.entry(self.capacity).or_insert_with(Vec::new);
+        if buffers.len() < BUFFER_POOL_DEPTH {
+            buffers.push(storage);
+        }
+    }
+}
+
+// Where readers of an empty pipe and writers of a full one sleep. parked is only bumped while
+// someone is (about to be) asleep, so the common case of a copy with nobody waiting never takes
+// the lock.
+struct PipeParking {
+    parked: AtomicUsize,
+    lock: Mutex<()>,
+    cv: Condvar,
+}
+
 #[derive(Clone)]
 pub struct EmulatedPipe {
-    write_end: Arc<Mutex<Producer<u8>>>,
-    read_end: Arc<Mutex<Consumer<u8>>>,
+    buffer: Arc<RwLock<PipeBuffer>>,
+    writer: Arc<Mutex<()>>, // held for a whole write so concurrent writes don't interleave
+    reader: Arc<Mutex<()>>,
+    parking: Arc<PipeParking>,
     pub refcount_write: Arc<AtomicU32>,
     pub refcount_read: Arc<AtomicU32>,
     eof: Arc<AtomicBool>,
-    size: usize,
+    read_shut: Arc<AtomicBool>, // a socket shut down for reading, which refuses what is written
 }
 
 impl EmulatedPipe {
     pub fn new_with_capacity(size: usize) -> EmulatedPipe {
-        let rb = RingBuffer::<u8>::new(size);
-        let (prod, cons) = rb.split();
         EmulatedPipe {
-            write_end: Arc::new(Mutex::new(prod)),
-            read_end: Arc::new(Mutex::new(cons)),
+            buffer: Arc::new(RwLock::new(PipeBuffer::new(size))),
+            writer: Arc::new(Mutex::new(())),
+            reader: Arc::new(Mutex::new(())),
+            parking: Arc::new(PipeParking {
+                parked: AtomicUsize::new(0),
+                lock: Mutex::new(()),
+                cv: Condvar::new(),
+            }),
             refcount_write: Arc::new(AtomicU32::new(1)),
             refcount_read: Arc::new(AtomicU32::new(1)),
             eof: Arc::new(AtomicBool::new(false)),
-            size: size,
+            read_shut: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    pub fn capacity(&self) -> usize {
+        self.buffer.read().capacity
+    }
+
+    // Move the pipe's contents into a buffer of the new capacity. Writers blocked on a full pipe
+    // pick up the new capacity as soon as they wake.
+    pub fn set_capacity(&self, capacity: usize) -> i32 {
+        let mut buffer = self.buffer.write();
+        let pending = buffer.len();
+        if pending > capacity {
+            return syscall_error(
+                Errno::EBUSY,
+                "fcntl",
+                "the pipe holds more data than the requested capacity",
+            );
+        }
+
+        let newbuffer = PipeBuffer::new(capacity);
+        let mut contents = vec![0u8; pending];
+        buffer.pop_slice(&mut contents);
+        newbuffer.push_slice(&contents);
+        *buffer = newbuffer;
+        drop(buffer);
+        self.wake_parked();
+        interface::notify_readiness();
+        capacity as i32
+    }
+
     pub fn set_eof(&self) {
-        self.eof.store(true, Ordering::Relaxed);
+        self.eof.store(true, Ordering::SeqCst);
+        self.wake_parked();
+        interface::notify_readiness();
+    }
+
+    // For shutdown(SHUT_RD) on the socket reading from the pipe: what is in it is thrown away,
+    // reads find the end of the stream and writes fail with EPIPE from here on
+    pub fn shutdown_read(&self) {
+        self.read_shut.store(true, Ordering::SeqCst);
+        let _reader = self.reader.lock();
+        self.buffer.read().clear();
+        drop(_reader);
+        self.set_eof();
+    }
+
+    // whether nothing is left to read what is written
+    fn readers_gone(&self) -> bool {
+        self.get_read_ref() == 0 || self.read_shut.load(Ordering::SeqCst)
     }
 
     pub fn get_write_ref(&self) -> u32 {
@@ -72,7 +246,9 @@ impl EmulatedPipe {
 
     pub fn decr_ref(&self, flags: i32) {
         if (flags & O_RDWRFLAGS) == O_RDONLY {
-            self.refcount_read.fetch_sub(1, Ordering::Relaxed);
+            self.refcount_read.fetch_sub(1, Ordering::SeqCst);
+            // a writer parked on a full pipe has to find out if that was the last reader
+            self.wake_parked();
         }
         if (flags & O_RDWRFLAGS) == O_WRONLY {
             self.refcount_write.fetch_sub(1, Ordering::Relaxed);
@@ -80,8 +256,7 @@ impl EmulatedPipe {
     }
 
     pub fn check_select_read(&self) -> bool {
-        let read_end = self.read_end.lock();
-        let pipe_space = read_end.len();
+        let pipe_space = self.readable_bytes();
 
         if (pipe_space > 0) || self.eof.load(Ordering::SeqCst) {
             return true;
@@ -89,13 +264,51 @@ impl EmulatedPipe {
             return false;
         }
     }
+    //how many bytes a read could take from the pipe right now
+    pub fn readable_bytes(&self) -> usize {
+        self.buffer.read().len()
+    }
+
     pub fn check_select_write(&self) -> bool {
-        let write_end = self.write_end.lock();
-        let pipe_space = write_end.remaining();
+        let pipe_space = self.buffer.read().remaining();
 
         return pipe_space != 0;
     }
 
+    // we write if the pipe is empty, otherwise we try to limit writes to 4096 bytes (unless whats
+    // leftover of this write is < 4096)
+    fn has_room(&self, left: usize) -> bool {
+        let buffer = self.buffer.read();
+        let remaining = buffer.remaining();
+        remaining != 0
+            && (remaining == buffer.capacity || left <= PAGE_SIZE || remaining >= PAGE_SIZE)
+    }
+
+    // Sleep until woken or SIGNAL_CHECK_INTERVAL passes, unless ready already holds. Anything
+    // that could make ready hold calls wake_parked after making it so, and ready is checked
+    // under the lock, so the wakeup can't slip in between the check and the sleep.
+    fn park<F: Fn() -> bool>(&self, ready: F) {
+        let parking = &*self.parking;
+        parking.parked.fetch_add(1, Ordering::SeqCst);
+        let mut guard = parking.lock.lock();
+        if !ready() {
+            parking
+                .cv
+                .wait_for(&mut guard, interface::SIGNAL_CHECK_INTERVAL);
+        }
+        drop(guard);
+        parking.parked.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    // wake the readers and writers waiting on the pipe, so they look at it again
+    pub fn wake_parked(&self) {
+        let parking = &*self.parking;
+        if parking.parked.load(Ordering::SeqCst) > 0 {
+            let _guard = parking.lock.lock();
+            parking.cv.notify_all();
+        }
+    }
+
     // Write length bytes from pointer into pipe
     pub fn write_to_pipe(&self, ptr: *const u8, length: usize, nonblocking: bool) -> i32 {
         let mut bytes_written = 0;
@@ -105,10 +318,9 @@ impl EmulatedPipe {
             slice::from_raw_parts(ptr, length)
         };
 
-        let mut write_end = self.write_end.lock();
+        let _writer = self.writer.lock();
 
-        let pipe_space = write_end.remaining();
-        if nonblocking && (pipe_space == 0) {
+        if nonblocking && !self.check_select_write() {
             return syscall_error(
                 Errno::EAGAIN,
                 "write",
@@ -117,31 +329,66 @@ impl EmulatedPipe {
         }
 
         while bytes_written < length {
-            if self.get_read_ref() == 0 {
+            if self.readers_gone() {
                 return syscall_error(Errno::EPIPE, "write", "broken pipe");
             } // EPIPE, all read ends are closed
 
-            let remaining = write_end.remaining();
-
-            if remaining == 0 {
-                interface::lind_yield(); //yield on a full pipe
+            if !self.has_room(length - bytes_written) {
+                // sleep on a full pipe until a read makes room or the last reader goes away
+                self.park(|| self.has_room(length - bytes_written) || self.readers_gone());
                 continue;
             }
-            // we write if the pipe is empty, otherwise we try to limit writes to 4096 bytes (unless whats leftover of this write is < 4096)
-            if remaining != self.size
-                && (length - bytes_written) > PAGE_SIZE
-                && remaining < PAGE_SIZE
-            {
-                continue;
-            };
-            let bytes_to_write = min(length, bytes_written as usize + remaining);
-            write_end.push_slice(&buf[bytes_written..bytes_to_write]);
-            bytes_written = bytes_to_write;
+
+            // the buffer is let go of on every pass so the pipe can be resized under a blocked write
+            bytes_written += self.buffer.read().push_slice(&buf[bytes_written..]);
+            self.wake_parked();
+            interface::notify_readiness();
         }
 
         bytes_written as i32
     }
 
+    // Write as much of length bytes as there is room for, waiting on a full pipe for a single
+    // SIGNAL_CHECK_INTERVAL at most unless nonblocking. Returns how many bytes went in, EAGAIN if
+    // none did, or EPIPE once all read ends are closed. Socket sends go through this so they can
+    // hand back a short count or check for signals between chunks.
+    pub fn write_some_to_pipe(&self, buf: &[u8], nonblocking: bool) -> i32 {
+        let length = buf.len();
+
+        let _writer = self.writer.lock();
+
+        let mut parked = false;
+        loop {
+            if self.readers_gone() {
+                return syscall_error(Errno::EPIPE, "write", "broken pipe");
+            }
+
+            // a nonblocking write takes whatever room there is rather than waiting for more
+            let room = if nonblocking {
+                self.check_select_write()
+            } else {
+                self.has_room(length)
+            };
+            if room {
+                let bytes_written = self.buffer.read().push_slice(buf);
+                self.wake_parked();
+                interface::notify_readiness();
+                return bytes_written as i32;
+            }
+
+            if nonblocking || parked {
+                return syscall_error(
+                    Errno::EAGAIN,
+                    "write",
+                    "there is no room in the pipe right now, try again later",
+                );
+            }
+
+            self.park(|| self.has_room(length) || self.readers_gone());
+            parked = true;
+        }
+    }
+
     // Read length bytes from the pipe into pointer
     // Will wait for bytes unless pipe is empty and eof is set.
     pub fn read_from_pipe(&self, ptr: *mut u8, length: usize, nonblocking: bool) -> i32 {
@@ -150,42 +397,40 @@ impl EmulatedPipe {
             slice::from_raw_parts_mut(ptr, length)
         };
 
-        let mut read_end = self.read_end.lock();
-        let mut pipe_space = read_end.len();
-        if nonblocking && (pipe_space == 0) {
-            if self.eof.load(Ordering::SeqCst) {
-                return 0;
-            }
-            return syscall_error(
-                Errno::EAGAIN,
-                "read",
-                "there is no data available right now, try again later",
-            );
+        if length == 0 {
+            return 0;
         }
 
+        let _reader = self.reader.lock();
+
         // wait for something to be in the pipe, but break on eof
-        // check cancel point after 2^20 cycles just in case
-        let mut count = 0;
-        while pipe_space == 0 {
+        // go back to the caller after a while asleep so it can check for cancellation and signals
+        let mut parked = false;
+        loop {
+            let bytes_read = self.buffer.read().pop_slice(buf);
+            if bytes_read > 0 {
+                self.wake_parked();
+                interface::notify_readiness();
+                return bytes_read as i32;
+            }
+
             if self.eof.load(Ordering::SeqCst) {
                 return 0;
             }
-
-            if count == CANCEL_CHECK_INTERVAL {
-                return -(Errno::EAGAIN as i32); // we've tried enough, return to pipe
+            if nonblocking {
+                return syscall_error(
+                    Errno::EAGAIN,
+                    "read",
+                    "there is no data available right now, try again later",
+                );
+            }
+            if parked {
+                return -(Errno::EAGAIN as i32); // we've waited long enough, return to pipe
             }
 
-            pipe_space = read_end.len();
-            count = count + 1;
-            if pipe_space == 0 {
-                interface::lind_yield();
-            } // yield on an empty pipe
+            self.park(|| self.readable_bytes() > 0 || self.eof.load(Ordering::SeqCst));
+            parked = true;
         }
-
-        let bytes_to_read = min(length, pipe_space);
-        read_end.pop_slice(&mut buf[0..bytes_to_read]);
-
-        bytes_to_read as i32
     }
 }
 
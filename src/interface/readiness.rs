@@ -0,0 +1,168 @@
+// Readiness notification for select and poll
+//
+// Rather than spinning, a select or poll that finds nothing ready sleeps on a single condvar
+// until something that may have made a descriptor ready happens. Emulated objects (pipes, the
+// domain socket accept table, message queues, ttys...) call notify_readiness whenever they
+// change; kernel sockets are handed to a helper thread which waits on them in the kernel and
+// notifies once one of them is readable.
+
+#![allow(dead_code)]
+use crate::interface;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+struct Readiness {
+    generation: AtomicU64,
+    waiters: AtomicUsize,
+    lock: interface::Mutex<()>,
+    cv: interface::Condvar,
+}
+
+static READINESS: interface::RustLazyGlobal<Readiness> =
+    interface::RustLazyGlobal::new(|| Readiness {
+        generation: AtomicU64::new(0),
+        waiters: AtomicUsize::new(0),
+        lock: interface::Mutex::new(()),
+        cv: interface::Condvar::new(),
+    });
+
+// The rawfds of every waiter that has kernel sockets among its descriptors, keyed by a per-wait
+// id, along with the pipe the helper thread is woken through when they change.
+struct KernelWatch {
+    waits: interface::Mutex<HashMap<u64, Vec<i32>>>,
+    nextid: AtomicU64,
+    wakepipe: [i32; 2],
+}
+
+static KERNEL_WATCH: interface::RustLazyGlobal<KernelWatch> =
+    interface::RustLazyGlobal::new(|| {
+        let mut wakepipe = [-1; 2];
+        unsafe {
+            libc::pipe2(wakepipe.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC);
+        }
+        interface::helper_thread(kernel_watch_loop);
+        KernelWatch {
+            waits: interface::Mutex::new(HashMap::new()),
+            nextid: AtomicU64::new(0),
+            wakepipe: wakepipe,
+        }
+    });
+
+// Take a reading before checking descriptors; a wait given it returns as soon as anything
+// changes after the reading was taken
+pub fn readiness_generation() -> u64 {
+    READINESS.generation.load(Ordering::SeqCst)
+}
+
+// Wake every select or poll waiting for a descriptor to become ready
+pub fn notify_readiness() {
+    READINESS.generation.fetch_add(1, Ordering::SeqCst);
+    if READINESS.waiters.load(Ordering::SeqCst) > 0 {
+        let _guard = READINESS.lock.lock();
+        READINESS.cv.notify_all();
+    }
+}
+
+// Sleep for at most timeout, or until notify_readiness is called after generation was read, or
+// until one of rawfds becomes readable
+pub fn wait_for_readiness(generation: u64, rawfds: &[i32], timeout: interface::RustDuration) {
+    let watchid = if rawfds.is_empty() {
+        None
+    } else {
+        Some(kernel_watch_add(rawfds))
+    };
+
+    let mut guard = READINESS.lock.lock();
+    READINESS.waiters.fetch_add(1, Ordering::SeqCst);
+    if READINESS.generation.load(Ordering::SeqCst) == generation {
+        READINESS.cv.wait_for(&mut guard, timeout);
+    }
+    READINESS.waiters.fetch_sub(1, Ordering::SeqCst);
+    drop(guard);
+
+    if let Some(id) = watchid {
+        kernel_watch_remove(id);
+    }
+}
+
+fn kernel_watch_add(rawfds: &[i32]) -> u64 {
+    let watch = &*KERNEL_WATCH;
+    let id = watch.nextid.fetch_add(1, Ordering::Relaxed);
+    watch.waits.lock().insert(id, rawfds.to_vec());
+    kernel_watch_wake(watch);
+    id
+}
+
+fn kernel_watch_remove(id: u64) {
+    let watch = &*KERNEL_WATCH;
+    if watch.waits.lock().remove(&id).is_some() {
+        kernel_watch_wake(watch);
+    }
+}
+
+fn kernel_watch_wake(watch: &KernelWatch) {
+    let byte = 0u8;
+    unsafe {
+        libc::write(
+            watch.wakepipe[1],
+            &byte as *const u8 as *const libc::c_void,
+            1,
+        );
+    }
+}
+
+// The helper thread polls the wake pipe along with every watched rawfd. A wait is dropped from
+// the watch as soon as any of its rawfds is readable, since its waiter is about to look for
+// itself, so a socket that stays readable doesn't keep the helper spinning.
+fn kernel_watch_loop() {
+    let watch = &*KERNEL_WATCH;
+    loop {
+        let mut pollfds = vec![libc::pollfd {
+            fd: watch.wakepipe[0],
+            events: libc::POLLIN,
+            revents: 0,
+        }];
+        for rawfds in watch.waits.lock().values() {
+            for &fd in rawfds {
+                if !pollfds.iter().any(|pollfd| pollfd.fd == fd) {
+                    pollfds.push(libc::pollfd {
+                        fd: fd,
+                        events: libc::POLLIN,
+                        revents: 0,
+                    });
+                }
+            }
+        }
+
+        let ret = unsafe { libc::poll(pollfds.as_mut_ptr(), pollfds.len() as libc::nfds_t, -1) };
+        if ret <= 0 {
+            continue;
+        }
+
+        if pollfds[0].revents != 0 {
+            let mut drain = [0u8; 64];
+            while unsafe {
+                libc::read(
+                    watch.wakepipe[0],
+                    drain.as_mut_ptr() as *mut libc::c_void,
+                    drain.len(),
+                )
+            } > 0
+            {}
+        }
+
+        let ready: Vec<i32> = pollfds[1..]
+            .iter()
+            .filter(|pollfd| pollfd.revents != 0)
+            .map(|pollfd| pollfd.fd)
+            .collect();
+        if !ready.is_empty() {
+            watch
+                .waits
+                .lock()
+                .retain(|_, rawfds| !rawfds.iter().any(|fd| ready.contains(fd)));
+            notify_readiness();
+        }
+    }
+}
@@ -0,0 +1,828 @@
+// Serialization of filesystem metadata, in either CBOR or a faster binary format
+//
+// The binary format is not self-describing: integers are fixed width little endian, strings,
+// sequences and maps are prefixed by their length as a u64, options by a tag byte and enum
+// variants by their index as a u32. Structs and tuples are just their fields in order.
+//
+// Since fields are only known by their place, a field may only be added to the end of a struct,
+// with #[serde(default)], and it is listed below under the version that added it. Metadata of an
+// earlier version is read without those fields, which are given their defaults.
+#![allow(dead_code)]
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+use serde::Deserialize;
+use std::fmt;
+
+use crate::interface::{RustLazyGlobal, RustLock};
+
+// Binary metadata starts with these bytes, a tag and then the version of the format. 0xFF can
+// never start a CBOR item, so anything else is read as CBOR and metadata written before the
+// binary format existed stays readable.
+const BINARYTAG: u8 = 0xFF;
+pub const BINARYVERSION: u8 = 2;
+pub const BINARYMAGIC: [u8; 2] = [BINARYTAG, BINARYVERSION];
+
+// the fields appended to structs in each version after the first, by the version adding them
+const ADDED_FIELDS: &[(u8, &str)] = &[(2, "btime")];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataFormat {
+    Cbor,
+    Binary,
+}
+
+static METADATA_FORMAT: RustLazyGlobal<RustLock<MetadataFormat>> = RustLazyGlobal::new(|| {
+    RustLock::new(if cfg!(feature = "binary_metadata") {
+        MetadataFormat::Binary
+    } else {
+        MetadataFormat::Cbor
+    })
+});
+
+// the format new snapshots and log entries are written in, either is always readable
+pub fn set_metadata_format(format: MetadataFormat) {
+    *METADATA_FORMAT.write() = format;
+}
+
+pub fn metadata_format() -> MetadataFormat {
+    *METADATA_FORMAT.read()
+}
+
+#[derive(Debug)]
+pub struct SerialError(String);
+
+impl fmt::Display for SerialError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SerialError {}
+
+impl ser::Error for SerialError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerialError(msg.to_string())
+    }
+}
+
+impl de::Error for SerialError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerialError(msg.to_string())
+    }
+}
+
+impl From<serde_cbor::Error> for SerialError {
+    fn from(err: serde_cbor::Error) -> Self {
+        SerialError(err.to_string())
+    }
+}
+
+pub fn serialize_metadata<T: Serialize>(value: &T) -> Result<Vec<u8>, SerialError> {
+    serialize_metadata_as(metadata_format(), value)
+}
+
+pub fn serialize_metadata_as<T: Serialize>(
+    format: MetadataFormat,
+    value: &T,
+) -> Result<Vec<u8>, SerialError> {
+    match format {
+        MetadataFormat::Cbor => Ok(serde_cbor::ser::to_vec_packed(value)?),
+        MetadataFormat::Binary => {
+            let mut serializer = BinarySerializer {
+                output: BINARYMAGIC.to_vec(),
+            };
+            value.serialize(&mut serializer)?;
+            Ok(serializer.output)
+        }
+    }
+}
+
+// reads metadata written in either format
+pub fn deserialize_metadata<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, SerialError> {
+    if metadata_format_of(bytes) == MetadataFormat::Binary {
+        let version = match bytes.get(1) {
+            Some(&version) if version >= 1 && version <= BINARYVERSION => version,
+            _ => {
+                return Err(SerialError(
+                    "unknown version of binary metadata".to_string(),
+                ))
+            }
+        };
+        let mut deserializer = BinaryDeserializer {
+            input: &bytes[BINARYMAGIC.len()..],
+            version,
+        };
+        let value = T::deserialize(&mut deserializer)?;
+        if !deserializer.input.is_empty() {
+            return Err(SerialError(
+                "trailing bytes after binary metadata".to_string(),
+            ));
+        }
+        Ok(value)
+    } else {
+        Ok(serde_cbor::from_slice(bytes)?)
+    }
+}
+
+pub fn metadata_format_of(bytes: &[u8]) -> MetadataFormat {
+    if bytes.first() == Some(&BINARYTAG) {
+        MetadataFormat::Binary
+    } else {
+        MetadataFormat::Cbor
+    }
+}
+
+struct BinarySerializer {
+    output: Vec<u8>,
+}
+
+impl BinarySerializer {
+    fn write_len(&mut self, len: Option<usize>) -> Result<(), SerialError> {
+        match len {
+            Some(len) => {
+                self.output.extend_from_slice(&(len as u64).to_le_bytes());
+                Ok(())
+            }
+            None => Err(SerialError(
+                "binary metadata needs the length of every sequence up front".to_string(),
+            )),
+        }
+    }
+
+    fn write_variant(&mut self, variant_index: u32) {
+        self.output.extend_from_slice(&variant_index.to_le_bytes());
+    }
+}
+
+macro_rules! serialize_le {
+    ($($method:ident: $ty:ty),*) => {
+        $(fn $method(self, v: $ty) -> Result<(), SerialError> {
+            self.output.extend_from_slice(&v.to_le_bytes());
+            Ok(())
+        })*
+    };
+}
+
+impl ser::Serializer for &mut BinarySerializer {
+    type Ok = ();
+    type Error = SerialError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    serialize_le!(
+        serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+        serialize_i128: i128, serialize_u8: u8, serialize_u16: u16, serialize_u32: u32,
+        serialize_u64: u64, serialize_u128: u128, serialize_f32: f32, serialize_f64: f64
+    );
+
+    fn serialize_bool(self, v: bool) -> Result<(), SerialError> {
+        self.output.push(v as u8);
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), SerialError> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), SerialError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), SerialError> {
+        self.write_len(Some(v.len()))?;
+        self.output.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), SerialError> {
+        self.output.push(0);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerialError> {
+        self.output.push(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerialError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerialError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), SerialError> {
+        self.write_variant(variant_index);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SerialError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerialError> {
+        self.write_variant(variant_index);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, SerialError> {
+        self.write_len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, SerialError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, SerialError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, SerialError> {
+        self.write_variant(variant_index);
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, SerialError> {
+        self.write_len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, SerialError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, SerialError> {
+        self.write_variant(variant_index);
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeSeq for &mut BinarySerializer {
+    type Ok = ();
+    type Error = SerialError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerialError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SerialError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut BinarySerializer {
+    type Ok = ();
+    type Error = SerialError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerialError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SerialError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut BinarySerializer {
+    type Ok = ();
+    type Error = SerialError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerialError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SerialError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut BinarySerializer {
+    type Ok = ();
+    type Error = SerialError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerialError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SerialError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut BinarySerializer {
+    type Ok = ();
+    type Error = SerialError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerialError> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerialError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SerialError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut BinarySerializer {
+    type Ok = ();
+    type Error = SerialError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), SerialError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SerialError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut BinarySerializer {
+    type Ok = ();
+    type Error = SerialError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), SerialError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SerialError> {
+        Ok(())
+    }
+}
+
+struct BinaryDeserializer<'de> {
+    input: &'de [u8],
+    version: u8, // of the metadata being read
+}
+
+impl<'de> BinaryDeserializer<'de> {
+    // how many of a struct's fields are in metadata of this version, those added since are left
+    // off its end
+    fn fields_present(&self, fields: &'static [&'static str]) -> usize {
+        let mut present = fields.len();
+        while present > 0
+            && ADDED_FIELDS
+                .iter()
+                .any(|&(version, field)| version > self.version && field == fields[present - 1])
+        {
+            present -= 1;
+        }
+        present
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'de [u8], SerialError> {
+        if self.input.len() < len {
+            return Err(SerialError("binary metadata ended early".to_string()));
+        }
+        let (taken, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(taken)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], SerialError> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn read_len(&mut self) -> Result<usize, SerialError> {
+        let len = u64::from_le_bytes(self.take_array()?) as usize;
+        // every element takes at least a byte, so a length longer than the rest of the input is
+        // corrupt and must not be used to size an allocation
+        if len > self.input.len() {
+            return Err(SerialError(
+                "binary metadata length out of range".to_string(),
+            ));
+        }
+        Ok(len)
+    }
+}
+
+macro_rules! deserialize_le {
+    ($($method:ident: $ty:ty => $visit:ident),*) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+            visitor.$visit(<$ty>::from_le_bytes(self.take_array()?))
+        })*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut BinaryDeserializer<'de> {
+    type Error = SerialError;
+
+    deserialize_le!(
+        deserialize_i8: i8 => visit_i8, deserialize_i16: i16 => visit_i16,
+        deserialize_i32: i32 => visit_i32, deserialize_i64: i64 => visit_i64,
+        deserialize_i128: i128 => visit_i128, deserialize_u8: u8 => visit_u8,
+        deserialize_u16: u16 => visit_u16, deserialize_u32: u32 => visit_u32,
+        deserialize_u64: u64 => visit_u64, deserialize_u128: u128 => visit_u128,
+        deserialize_f32: f32 => visit_f32, deserialize_f64: f64 => visit_f64
+    );
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, SerialError> {
+        Err(SerialError(
+            "binary metadata is not self-describing".to_string(),
+        ))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+        match self.take(1)?[0] {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            _ => Err(SerialError("invalid bool in binary metadata".to_string())),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+        match char::from_u32(u32::from_le_bytes(self.take_array()?)) {
+            Some(c) => visitor.visit_char(c),
+            None => Err(SerialError("invalid char in binary metadata".to_string())),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+        let len = self.read_len()?;
+        match std::str::from_utf8(self.take(len)?) {
+            Ok(s) => visitor.visit_borrowed_str(s),
+            Err(_) => Err(SerialError("invalid utf8 in binary metadata".to_string())),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+        let len = self.read_len()?;
+        visitor.visit_borrowed_bytes(self.take(len)?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+        match self.take(1)?[0] {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            _ => Err(SerialError(
+                "invalid option tag in binary metadata".to_string(),
+            )),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerialError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerialError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+        let len = self.read_len()?;
+        visitor.visit_seq(BinaryAccess {
+            de: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerialError> {
+        visitor.visit_seq(BinaryAccess {
+            de: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerialError> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+        let len = self.read_len()?;
+        visitor.visit_map(BinaryAccess {
+            de: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerialError> {
+        let present = self.fields_present(fields);
+        self.deserialize_tuple(present, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerialError> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerialError> {
+        self.deserialize_any(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+struct BinaryAccess<'a, 'de: 'a> {
+    de: &'a mut BinaryDeserializer<'de>,
+    remaining: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for BinaryAccess<'_, 'de> {
+    type Error = SerialError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, SerialError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for BinaryAccess<'_, 'de> {
+    type Error = SerialError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SerialError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SerialError> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut BinaryDeserializer<'de> {
+    type Error = SerialError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), SerialError> {
+        let variant_index = u32::from_le_bytes(self.take_array()?);
+        let variant_deserializer: de::value::U32Deserializer<SerialError> =
+            variant_index.into_deserializer();
+        let variant = seed.deserialize(variant_deserializer)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut BinaryDeserializer<'de> {
+    type Error = SerialError;
+
+    fn unit_variant(self) -> Result<(), SerialError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, SerialError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerialError> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerialError> {
+        let present = self.fields_present(fields);
+        de::Deserializer::deserialize_tuple(self, present, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Empty,
+        Named(String),
+        Sized { width: u32, height: u32 },
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Entry {
+        id: usize,
+        flag: bool,
+        parent: Option<usize>,
+        shapes: Vec<Shape>,
+        children: BTreeMap<String, usize>,
+    }
+
+    fn sample() -> Entry {
+        let mut children = BTreeMap::new();
+        children.insert(".".to_string(), 7);
+        children.insert("file".to_string(), 12);
+        Entry {
+            id: 7,
+            flag: true,
+            parent: None,
+            shapes: vec![
+                Shape::Empty,
+                Shape::Named("x".to_string()),
+                Shape::Sized {
+                    width: 3,
+                    height: 4,
+                },
+            ],
+            children,
+        }
+    }
+
+    #[test]
+    fn test_binary_roundtrip() {
+        let bytes = serialize_metadata_as(MetadataFormat::Binary, &sample()).unwrap();
+        assert_eq!(metadata_format_of(&bytes), MetadataFormat::Binary);
+        let entry: Entry = deserialize_metadata(&bytes).unwrap();
+        assert_eq!(entry, sample());
+    }
+
+    #[test]
+    fn test_cbor_still_readable() {
+        let bytes = serialize_metadata_as(MetadataFormat::Cbor, &sample()).unwrap();
+        assert_eq!(metadata_format_of(&bytes), MetadataFormat::Cbor);
+        let entry: Entry = deserialize_metadata(&bytes).unwrap();
+        assert_eq!(entry, sample());
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct OldInode {
+        size: usize,
+        mtime: u64,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct NewInode {
+        size: usize,
+        mtime: u64,
+        #[serde(default)]
+        btime: u64,
+    }
+
+    #[test]
+    fn test_added_field_defaults() {
+        let old = vec![
+            OldInode { size: 1, mtime: 2 },
+            OldInode { size: 3, mtime: 4 },
+        ];
+        let expected = vec![
+            NewInode {
+                size: 1,
+                mtime: 2,
+                btime: 0,
+            },
+            NewInode {
+                size: 3,
+                mtime: 4,
+                btime: 0,
+            },
+        ];
+
+        //packed CBOR knows fields by their index, so a missing last one takes its default
+        let bytes = serialize_metadata_as(MetadataFormat::Cbor, &old).unwrap();
+        let new: Vec<NewInode> = deserialize_metadata(&bytes).unwrap();
+        assert_eq!(new, expected);
+
+        //binary metadata from before the field was added says so by its version
+        let mut bytes = serialize_metadata_as(MetadataFormat::Binary, &old).unwrap();
+        bytes[1] = 1;
+        let new: Vec<NewInode> = deserialize_metadata(&bytes).unwrap();
+        assert_eq!(new, expected);
+
+        let bytes = serialize_metadata_as(MetadataFormat::Binary, &expected).unwrap();
+        let new: Vec<NewInode> = deserialize_metadata(&bytes).unwrap();
+        assert_eq!(new, expected);
+    }
+
+    #[test]
+    fn test_binary_unknown_version() {
+        let mut bytes = serialize_metadata_as(MetadataFormat::Binary, &sample()).unwrap();
+        bytes[1] = BINARYVERSION + 1;
+        assert!(deserialize_metadata::<Entry>(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_binary_truncated() {
+        let bytes = serialize_metadata_as(MetadataFormat::Binary, &sample()).unwrap();
+        for len in BINARYMAGIC.len()..bytes.len() {
+            assert!(deserialize_metadata::<Entry>(&bytes[..len]).is_err());
+        }
+    }
+}
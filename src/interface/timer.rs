@@ -3,13 +3,17 @@
 // Timer functions for Rust interface.
 #![allow(dead_code)]
 
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::cell::Cell;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
 use std::thread;
 pub use std::time::Duration as RustDuration;
 pub use std::time::Instant as RustInstant;
 use std::time::SystemTime;
 
-use crate::interface::lind_kill_from_id;
+use crate::interface::{helper_thread, lind_kill_from_id, lind_sigqueue_from_id, SiginfoStruct};
+
+// how long a syscall blocked on a condition variable sleeps between looks for pending signals
+pub const SIGNAL_CHECK_INTERVAL: RustDuration = RustDuration::from_millis(10);
 
 pub fn timestamp() -> u64 {
     SystemTime::now()
@@ -88,7 +92,7 @@ impl IntervalTimer {
                 guard.is_ticking = true;
 
                 let self_dup = self.clone();
-                thread::spawn(move || {
+                helper_thread(move || {
                     // There is a chance that there'll be two ticking threads running
                     // at the same time
                     self_dup.tick();
@@ -139,3 +143,255 @@ impl IntervalTimer {
         self.clone()
     }
 }
+
+thread_local! {
+    // the calling thread's cpu time as of the last time it was charged to a cage
+    static CPU_CHECKPOINT: Cell<RustDuration> = Cell::new(RustDuration::ZERO);
+}
+
+// the host's reading of one of its clocks
+pub fn clock_time(clockid: libc::clockid_t) -> RustDuration {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(clockid, &mut ts) };
+    RustDuration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+// cpu time the calling host thread has used since it started
+pub fn thread_cputime() -> RustDuration {
+    clock_time(libc::CLOCK_THREAD_CPUTIME_ID)
+}
+
+// the cpu time the calling thread has used since the last checkpoint, moving the checkpoint up
+pub fn thread_cputime_since_checkpoint() -> RustDuration {
+    let now = thread_cputime();
+    let last = CPU_CHECKPOINT.with(|checkpoint| checkpoint.replace(now));
+    now.saturating_sub(last)
+}
+
+// A timer counting down cpu time rather than real time, re-armed with its interval each time it
+// runs out and disarmed while its value is zero.
+#[derive(Clone, Copy, Debug, Default)]
+struct _CpuTimer {
+    value: RustDuration,
+    interval: RustDuration,
+}
+
+impl _CpuTimer {
+    // count used against the timer, returning whether it ran out
+    fn charge(&mut self, used: RustDuration) -> bool {
+        if self.value.is_zero() {
+            return false;
+        }
+        if used < self.value {
+            self.value -= used;
+            return false;
+        }
+        self.value = self.interval;
+        true
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct _CpuUsage {
+    user: RustDuration,       // spent running guest code
+    system: RustDuration,     // spent inside syscalls
+    virtual_timer: _CpuTimer, // counts user time
+    prof_timer: _CpuTimer,    // counts user and system time
+}
+
+// The cpu time a cage has used, along with its ITIMER_VIRTUAL and ITIMER_PROF timers. Time is
+// charged as the cage's threads cross into and out of syscalls, so that is when the timers run out.
+#[derive(Debug, Default)]
+pub struct CpuUsage {
+    _usage: Mutex<_CpuUsage>,
+}
+
+impl CpuUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // an exec'd image keeps both the time used so far and the timers
+    pub fn carry_over(&self) -> Self {
+        Self {
+            _usage: Mutex::new(*self._usage.lock().unwrap()),
+        }
+    }
+
+    pub fn user_time(&self) -> RustDuration {
+        self._usage.lock().unwrap().user
+    }
+
+    pub fn system_time(&self) -> RustDuration {
+        self._usage.lock().unwrap().system
+    }
+
+    // Charge user and system time, returning whether the virtual and the profiling timer ran out.
+    pub fn charge(&self, user: RustDuration, system: RustDuration) -> (bool, bool) {
+        let mut usage = self._usage.lock().unwrap();
+        usage.user += user;
+        usage.system += system;
+        let virtual_expired = usage.virtual_timer.charge(user);
+        let prof_expired = usage.prof_timer.charge(user + system);
+        (virtual_expired, prof_expired)
+    }
+
+    // Like get_itimer on IntervalTimer, for the virtual timer when vtimer is set and the
+    // profiling timer otherwise.
+    pub fn get_cputimer(&self, vtimer: bool) -> (RustDuration, RustDuration) {
+        let usage = self._usage.lock().unwrap();
+        let timer = if vtimer {
+            usage.virtual_timer
+        } else {
+            usage.prof_timer
+        };
+        (timer.value, timer.interval)
+    }
+
+    pub fn set_cputimer(&self, vtimer: bool, value: RustDuration, interval: RustDuration) {
+        let mut usage = self._usage.lock().unwrap();
+        let timer = if vtimer {
+            &mut usage.virtual_timer
+        } else {
+            &mut usage.prof_timer
+        };
+        timer.value = value;
+        timer.interval = interval;
+    }
+}
+
+#[derive(Debug)]
+struct _PosixTimer {
+    deadline: Option<RustInstant>, // None while disarmed
+    interval: RustDuration,
+    overrun: i32, // of the expiration whose signal was delivered last
+    deleted: bool,
+}
+
+// A timer made by timer_create. Each one has a thread of its own that sleeps until the timer
+// next expires, sends the timer's signal, if it has one, and re-arms it with its interval.
+// Expirations the thread only wakes for late are counted as overruns of the one it signals.
+#[derive(Clone, Debug)]
+pub struct PosixTimer {
+    cageid: u64,
+    clockid: i32,                // the clock an absolute expiry time is read against
+    info: Option<SiginfoStruct>, // sent on each expiry, None for SIGEV_NONE
+    _pt: Arc<(Mutex<_PosixTimer>, Condvar)>,
+}
+
+impl PosixTimer {
+    pub fn new(cageid: u64, clockid: i32, info: Option<SiginfoStruct>) -> Self {
+        let timer = Self {
+            cageid: cageid,
+            clockid: clockid,
+            info: info,
+            _pt: Arc::new((
+                Mutex::new(_PosixTimer {
+                    deadline: None,
+                    interval: RustDuration::ZERO,
+                    overrun: 0,
+                    deleted: false,
+                }),
+                Condvar::new(),
+            )),
+        };
+        let timer_dup = timer.clone();
+        helper_thread(move || timer_dup.run());
+        timer
+    }
+
+    // Similar to timer_gettime. Returns (time until the next expiry, interval), zero for a
+    // disarmed timer.
+    pub fn get_time(&self) -> (RustDuration, RustDuration) {
+        Self::_get_time(&self._pt.0.lock().unwrap())
+    }
+
+    fn _get_time(guard: &MutexGuard<_PosixTimer>) -> (RustDuration, RustDuration) {
+        let remaining = guard.deadline.map_or(RustDuration::ZERO, |deadline| {
+            deadline.saturating_duration_since(RustInstant::now())
+        });
+        (remaining, guard.interval)
+    }
+
+    // Arm the timer to expire after value and every interval from then on, or disarm it for a
+    // zero value, giving back what get_time would have before.
+    pub fn set_time(
+        &self,
+        value: RustDuration,
+        interval: RustDuration,
+    ) -> (RustDuration, RustDuration) {
+        let (lock, cv) = &*self._pt;
+        let mut guard = lock.lock().unwrap();
+        let old = Self::_get_time(&guard);
+        if value.is_zero() {
+            guard.deadline = None;
+            guard.interval = RustDuration::ZERO;
+        } else {
+            guard.deadline = Some(RustInstant::now() + value);
+            guard.interval = interval;
+        }
+        cv.notify_all();
+        old
+    }
+
+    pub fn clockid(&self) -> i32 {
+        self.clockid
+    }
+
+    pub fn overrun(&self) -> i32 {
+        self._pt.0.lock().unwrap().overrun
+    }
+
+    pub fn set_overrun(&self, overrun: i32) {
+        self._pt.0.lock().unwrap().overrun = overrun;
+    }
+
+    // stop the timer for good, letting its thread finish
+    pub fn delete(&self) {
+        let (lock, cv) = &*self._pt;
+        lock.lock().unwrap().deleted = true;
+        cv.notify_all();
+    }
+
+    fn run(&self) {
+        let (lock, cv) = &*self._pt;
+        let mut guard = lock.lock().unwrap();
+        loop {
+            if guard.deleted {
+                return;
+            }
+            let deadline = match guard.deadline {
+                Some(deadline) => deadline,
+                None => {
+                    guard = cv.wait(guard).unwrap();
+                    continue;
+                }
+            };
+            let now = RustInstant::now();
+            if now < deadline {
+                guard = cv.wait_timeout(guard, deadline - now).unwrap().0;
+                continue;
+            }
+
+            let mut missed = 0;
+            if guard.interval.is_zero() {
+                guard.deadline = None;
+            } else {
+                missed = (now - deadline).as_nanos() / guard.interval.as_nanos();
+                let next = guard.interval.as_nanos() * (missed + 1);
+                guard.deadline = Some(deadline + RustDuration::from_nanos(next as u64));
+            }
+            if let Some(mut info) = self.info {
+                info.si_overrun = missed.min(i32::MAX as u128) as i32;
+                // the signal is queued without holding the timer, since delivering it reads the
+                // timer's overrun
+                drop(guard);
+                lind_sigqueue_from_id(self.cageid, info);
+                guard = lock.lock().unwrap();
+            }
+        }
+    }
+}
@@ -1,8 +1,11 @@
 #![allow(dead_code)]
 use crate::interface;
 use crate::interface::errnos::{syscall_error, Errno};
+use crate::safeposix::syscalls::fs_constants::{makedev, DevNo, NCCS};
+use crate::safeposix::syscalls::net_constants::{AF_INET, AF_INET6, AF_UNIX, IFNAMSIZ, MSG_CTRUNC};
 
 const SIZEOF_SOCKADDR: u32 = 16;
+pub const IOV_MAX: usize = 1024; // iovecs a msghdr may hold
 
 //redefining the FSData struct in this file so that we maintain flow of program
 //derive eq attributes for testing whether the structs equal other fsdata structs from stat/fstat
@@ -47,6 +50,139 @@ pub struct StatData {
     pub st_ctim: (u64, u64),
 }
 
+#[derive(Eq, PartialEq, Default, Copy, Clone, Debug)]
+#[repr(C)]
+pub struct StatxTimestamp {
+    pub tv_sec: i64,
+    pub tv_nsec: u32,
+    pub __reserved: i32,
+}
+
+//the structure statx fills; stx_mask says which of the fields hold real values
+#[derive(Eq, PartialEq, Default, Debug)]
+#[repr(C)]
+pub struct StatxStruct {
+    pub stx_mask: u32,
+    pub stx_blksize: u32,
+    pub stx_attributes: u64,
+    pub stx_nlink: u32,
+    pub stx_uid: u32,
+    pub stx_gid: u32,
+    pub stx_mode: u16,
+    pub __spare0: u16,
+    pub stx_ino: u64,
+    pub stx_size: u64,
+    pub stx_blocks: u64,
+    pub stx_attributes_mask: u64,
+    pub stx_atime: StatxTimestamp,
+    pub stx_btime: StatxTimestamp,
+    pub stx_ctime: StatxTimestamp,
+    pub stx_mtime: StatxTimestamp,
+    pub stx_rdev_major: u32,
+    pub stx_rdev_minor: u32,
+    pub stx_dev_major: u32,
+    pub stx_dev_minor: u32,
+    pub stx_mnt_id: u64,
+    pub __spare3: [u64; 13],
+}
+
+pub const UTSNAME_LENGTH: usize = 65;
+
+//struct utsname, each field a NUL terminated string
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct UtsName {
+    pub sysname: [u8; UTSNAME_LENGTH],
+    pub nodename: [u8; UTSNAME_LENGTH],
+    pub release: [u8; UTSNAME_LENGTH],
+    pub version: [u8; UTSNAME_LENGTH],
+    pub machine: [u8; UTSNAME_LENGTH],
+    pub domainname: [u8; UTSNAME_LENGTH],
+}
+
+//a utsname field holding as much of value as fits before its NUL
+pub fn uts_field(value: &str) -> [u8; UTSNAME_LENGTH] {
+    let mut field = [0u8; UTSNAME_LENGTH];
+    let len = interface::rust_min(value.len(), UTSNAME_LENGTH - 1);
+    field[..len].copy_from_slice(&value.as_bytes()[..len]);
+    field
+}
+
+//struct sysinfo, with sizes in units of mem_unit bytes
+#[derive(Default, Debug)]
+#[repr(C)]
+pub struct SysInfo {
+    pub uptime: i64,
+    pub loads: [u64; 3],
+    pub totalram: u64,
+    pub freeram: u64,
+    pub sharedram: u64,
+    pub bufferram: u64,
+    pub totalswap: u64,
+    pub freeswap: u64,
+    pub procs: u16,
+    pub pad: u16,
+    pub totalhigh: u64,
+    pub freehigh: u64,
+    pub mem_unit: u32,
+}
+
+//struct stat64 as 32-bit guests lay it out, where 8 byte fields are only 4 byte aligned
+#[derive(Default, Copy, Clone)]
+#[repr(C, packed(4))]
+pub struct Stat64Struct {
+    pub st_dev: u64,
+    pub __pad0: u32,
+    pub __st_ino: u32, // the inode number truncated to 32 bits
+    pub st_mode: u32,
+    pub st_nlink: u32,
+    pub st_uid: u32,
+    pub st_gid: u32,
+    pub st_rdev: u64,
+    pub __pad3: u32,
+    pub st_size: i64,
+    pub st_blksize: u32,
+    pub st_blocks: u64,
+    pub st_atime: u32,
+    pub st_atime_nsec: u32,
+    pub st_mtime: u32,
+    pub st_mtime_nsec: u32,
+    pub st_ctime: u32,
+    pub st_ctime_nsec: u32,
+    pub st_ino: u64,
+}
+
+impl Stat64Struct {
+    pub fn from_statx(statx: &StatxStruct) -> Stat64Struct {
+        Stat64Struct {
+            st_dev: makedev(&DevNo {
+                major: statx.stx_dev_major,
+                minor: statx.stx_dev_minor,
+            }),
+            __st_ino: statx.stx_ino as u32,
+            st_mode: statx.stx_mode as u32,
+            st_nlink: statx.stx_nlink,
+            st_uid: statx.stx_uid,
+            st_gid: statx.stx_gid,
+            st_rdev: makedev(&DevNo {
+                major: statx.stx_rdev_major,
+                minor: statx.stx_rdev_minor,
+            }),
+            st_size: statx.stx_size as i64,
+            st_blksize: statx.stx_blksize,
+            st_blocks: statx.stx_blocks,
+            st_atime: statx.stx_atime.tv_sec as u32,
+            st_atime_nsec: statx.stx_atime.tv_nsec,
+            st_mtime: statx.stx_mtime.tv_sec as u32,
+            st_mtime_nsec: statx.stx_mtime.tv_nsec,
+            st_ctime: statx.stx_ctime.tv_sec as u32,
+            st_ctime_nsec: statx.stx_ctime.tv_nsec,
+            st_ino: statx.stx_ino,
+            ..Default::default()
+        }
+    }
+}
+
 //R Limit for getrlimit system call
 #[repr(C)]
 pub struct Rlimit {
@@ -91,29 +227,198 @@ pub struct SockaddrDummy {
     pub _sa_data: [u16; 14],
 }
 
+//big enough and aligned for any sockaddr, as on Linux
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct SockaddrStorage {
+    pub ss_family: u16,
+    pub __ss_padding: [u8; 118],
+    pub __ss_align: u64,
+}
+
+impl Default for SockaddrStorage {
+    fn default() -> Self {
+        SockaddrStorage {
+            ss_family: 0,
+            __ss_padding: [0; 118],
+            __ss_align: 0,
+        }
+    }
+}
+
+//the struct msghdr sendmsg and recvmsg take
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct MsghdrStruct {
+    pub msg_name: *mut u8,
+    pub msg_namelen: u32,
+    pub msg_iov: *mut IovecStruct,
+    pub msg_iovlen: usize,
+    pub msg_control: *mut u8,
+    pub msg_controllen: usize,
+    pub msg_flags: i32,
+}
+
+//the header each control message in msg_control starts with, its data following at
+//cmsg_align(size_of::<CmsghdrStruct>())
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct CmsghdrStruct {
+    pub cmsg_len: usize, // the header and data together, without the padding after them
+    pub cmsg_level: i32,
+    pub cmsg_type: i32,
+}
+
+// CMSG_ALIGN, CMSG_LEN and CMSG_SPACE
+pub fn cmsg_align(len: usize) -> usize {
+    (len + size_of::<usize>() - 1) & !(size_of::<usize>() - 1)
+}
+
+pub fn cmsg_len(datalen: usize) -> usize {
+    cmsg_align(size_of::<CmsghdrStruct>()) + datalen
+}
+
+pub fn cmsg_space(datalen: usize) -> usize {
+    cmsg_align(size_of::<CmsghdrStruct>()) + cmsg_align(datalen)
+}
+
+#[derive(Copy, Clone, Default)]
 #[repr(C)]
 pub struct TimeVal {
     pub tv_sec: i64,
     pub tv_usec: i64,
 }
 
+//lind does not account for resource usage, so this is only ever handed back zeroed
+#[derive(Copy, Clone, Default)]
+#[repr(C)]
+pub struct Rusage {
+    pub ru_utime: TimeVal,
+    pub ru_stime: TimeVal,
+    pub ru_maxrss: i64,
+    pub ru_ixrss: i64,
+    pub ru_idrss: i64,
+    pub ru_isrss: i64,
+    pub ru_minflt: i64,
+    pub ru_majflt: i64,
+    pub ru_nswap: i64,
+    pub ru_inblock: i64,
+    pub ru_oublock: i64,
+    pub ru_msgsnd: i64,
+    pub ru_msgrcv: i64,
+    pub ru_nsignals: i64,
+    pub ru_nvcsw: i64,
+    pub ru_nivcsw: i64,
+}
+
+//the credentials of a unix domain socket's peer, as SO_PEERCRED reports them
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+pub struct UCred {
+    pub pid: i32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+//the header and per-32-bit data words capget and capset exchange
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+pub struct CapUserHeader {
+    pub version: u32,
+    pub pid: i32,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+pub struct CapUserData {
+    pub effective: u32,
+    pub permitted: u32,
+    pub inheritable: u32,
+}
+
+#[derive(Copy, Clone, Default)]
 #[repr(C)]
 pub struct ITimerVal {
     pub it_interval: TimeVal,
     pub it_value: TimeVal,
 }
 
+#[derive(Copy, Clone, Default)]
 #[repr(C)]
 pub struct TimeSpec {
     pub tv_sec: i64,
     pub tv_nsec: i64,
 }
 
+#[derive(Copy, Clone, Default)]
+#[repr(C)]
+pub struct ITimerSpec {
+    pub it_interval: TimeSpec,
+    pub it_value: TimeSpec,
+}
+
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub union IoctlPtrUnion {
     pub int_ptr: *mut i32,
-    pub c_char_ptr: *mut u8, //Right now, we do not support passing struct pointers to ioctl as the related call are not implemented
+    pub c_char_ptr: *mut u8,
+    pub termios_ptr: *mut Termios,
+    pub winsize_ptr: *mut WinSize,
+    pub ifreq_ptr: *mut IfreqStruct,
+}
+
+//struct ifreq as the SIOCGIF ioctls pass it: an interface name, then what is asked about it
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct IfreqStruct {
+    pub ifr_name: [u8; IFNAMSIZ],
+    pub ifr_ifru: IfreqUnion,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub union IfreqUnion {
+    pub ifru_addr: interface::SockaddrV4,
+    pub ifru_flags: i16,
+    pub ifru_ivalue: i32, // the index for SIOCGIFINDEX and SIOCGIFNAME
+    pub ifru_mtu: i32,
+    pub ifru_pad: [u8; 24],
+}
+
+impl IfreqStruct {
+    //the name up to its terminator, None if it has none or is not utf-8
+    pub fn name(&self) -> Option<&str> {
+        let len = self.ifr_name.iter().position(|&c| c == 0)?;
+        std::str::from_utf8(&self.ifr_name[..len]).ok()
+    }
+
+    //names that don't fit are cut short to leave room for the terminator
+    pub fn set_name(&mut self, name: &str) {
+        let len = interface::rust_min(name.len(), IFNAMSIZ - 1);
+        self.ifr_name = [0; IFNAMSIZ];
+        self.ifr_name[..len].copy_from_slice(&name.as_bytes()[..len]);
+    }
+}
+
+//struct termios as the TCGETS family of ioctls passes it
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Termios {
+    pub c_iflag: u32,
+    pub c_oflag: u32,
+    pub c_cflag: u32,
+    pub c_lflag: u32,
+    pub c_line: u8,
+    pub c_cc: [u8; NCCS],
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct WinSize {
+    pub ws_row: u16,
+    pub ws_col: u16,
+    pub ws_xpixel: u16,
+    pub ws_ypixel: u16,
 }
 
 #[derive(Copy, Clone, Default)]
@@ -145,6 +450,47 @@ pub struct ShmidsStruct {
     pub shm_nattch: u32,
 }
 
+#[derive(Copy, Clone, Default)]
+#[repr(C)]
+pub struct MsqidsStruct {
+    pub msg_perm: IpcPermStruct,
+    pub msg_stime: isize,
+    pub msg_rtime: isize,
+    pub msg_ctime: isize,
+    pub msg_cbytes: u64,
+    pub msg_qnum: u64,
+    pub msg_qbytes: u64,
+    pub msg_lspid: u32,
+    pub msg_lrpid: u32,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct SembufStruct {
+    pub sem_num: u16,
+    pub sem_op: i16,
+    pub sem_flg: i16,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct MqAttrStruct {
+    pub mq_flags: i64,
+    pub mq_maxmsg: i64,
+    pub mq_msgsize: i64,
+    pub mq_curmsgs: i64,
+    pub __reserved: [i64; 4],
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct SigeventStruct {
+    pub sigev_value: u64,
+    pub sigev_signo: i32,
+    pub sigev_notify: i32,
+    pub __pad: [i32; 12],
+}
+
 pub type SigsetType = u64;
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -155,6 +501,21 @@ pub struct SigactionStruct {
     pub sa_flags: i32,
 }
 
+//what a signal was sent with, as handed to an SA_SIGINFO handler
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct SiginfoStruct {
+    pub si_signo: i32,
+    pub si_errno: i32,
+    pub si_code: i32,
+    pub si_pid: i32,
+    pub si_uid: u32,
+    pub si_status: i32, // for SIGCHLD, the exit status or the signal that ended the child
+    pub si_value: u64,  // the sigval given to sigqueue or in a sigevent
+    pub si_timerid: i32, // for SI_TIMER, the timer that expired
+    pub si_overrun: i32, // and how many more times it expired before the signal was delivered
+}
+
 pub type IovecStruct = libc::iovec;
 
 //redefining the Arg union to maintain the flow of the program
@@ -172,9 +533,23 @@ pub union Arg {
     pub dispatch_cstr: *const i8, //Typically corresponds to a passed in string of type char*, as in open
     pub dispatch_cstrarr: *const *const i8, //Typically corresponds to a passed in string array of type char* const[] as in execve
     pub dispatch_rlimitstruct: *mut Rlimit,
+    pub dispatch_rusagestruct: *mut Rusage,
+    pub dispatch_ucredstruct: *mut UCred,
+    pub dispatch_capheaderstruct: *mut CapUserHeader,
+    pub dispatch_capdatastructarray: *mut CapUserData,
     pub dispatch_statdatastruct: *mut StatData,
     pub dispatch_fsdatastruct: *mut FSData,
+    pub dispatch_statxstruct: *mut StatxStruct,
+    pub dispatch_stat64struct: *mut Stat64Struct,
+    pub dispatch_utsnamestruct: *mut UtsName,
+    pub dispatch_sysinfostruct: *mut SysInfo,
     pub dispatch_shmidstruct: *mut ShmidsStruct,
+    pub dispatch_sembufstructarray: *mut SembufStruct,
+    pub dispatch_msqidstruct: *mut MsqidsStruct,
+    pub dispatch_mqattrstruct: *mut MqAttrStruct,
+    pub dispatch_constmqattrstruct: *const MqAttrStruct,
+    pub dispatch_constsigeventstruct: *const SigeventStruct,
+    pub dispatch_uintptr: *mut u32,
     pub dispatch_constsockaddrstruct: *const SockaddrDummy,
     pub dispatch_sockaddrstruct: *mut SockaddrDummy,
     pub dispatch_socklen_t_ptr: *mut u32,
@@ -183,6 +558,7 @@ pub union Arg {
     pub dispatch_epollevent: *mut EpollEvent,
     pub dispatch_structtimeval: *mut TimeVal,
     pub dispatch_structtimespec: *mut TimeSpec,
+    pub dispatch_conststructtimespec: *const TimeSpec,
     pub dispatch_pipearray: *mut PipeArray,
     pub dispatch_sockpair: *mut SockPair,
     pub dispatch_ioctlptrunion: IoctlPtrUnion,
@@ -192,8 +568,11 @@ pub union Arg {
     pub dispatch_constsigsett: *const SigsetType,
     pub dispatch_structitimerval: *mut ITimerVal,
     pub dispatch_conststructitimerval: *const ITimerVal,
+    pub dispatch_structitimerspec: *mut ITimerSpec,
+    pub dispatch_conststructitimerspec: *const ITimerSpec,
     pub dispatch_fdset: *mut libc::fd_set,
     pub dispatch_constiovecstruct: *const interface::IovecStruct,
+    pub dispatch_msghdrstruct: *mut MsghdrStruct,
 }
 
 use std::mem::size_of;
@@ -257,6 +636,249 @@ pub fn get_usize(union_argument: Arg) -> Result<usize, i32> {
     return Ok(unsafe { union_argument.dispatch_usize });
 }
 
+//the span of host memory a cage's own memory occupies, set by the embedder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub base: usize,
+    pub len: usize,
+}
+
+impl MemoryRegion {
+    pub fn contains(&self, addr: usize, len: usize) -> bool {
+        addr >= self.base && len <= self.len && addr - self.base <= self.len - len
+    }
+}
+
+// A buffer a cage passed to a syscall, checked to lie wholly within the cage's memory region, or
+// when it has none set, at least to be non-null and not to wrap around the address space.
+#[derive(Debug, Clone, Copy)]
+pub struct UserBuffer {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl UserBuffer {
+    pub fn new(region: Option<MemoryRegion>, ptr: *mut u8, len: usize) -> Result<UserBuffer, i32> {
+        let addr = ptr as usize;
+        let valid = !ptr.is_null()
+            && addr.checked_add(len).is_some()
+            && region.map_or(true, |region| region.contains(addr, len));
+        if !valid {
+            return Err(syscall_error(
+                Errno::EFAULT,
+                "dispatcher",
+                "buffer lies outside the cage's memory",
+            ));
+        }
+        Ok(UserBuffer { ptr: ptr, len: len })
+    }
+
+    pub fn as_ptr(&self) -> *const u8 {
+        self.ptr
+    }
+
+    pub fn as_mut_ptr(&self) -> *mut u8 {
+        self.ptr
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    // The caller picks the lifetime, so it has to make sure the cage's memory outlives the slice
+    // and that nothing else touches the buffer while a mutable slice of it is live.
+    pub unsafe fn as_slice<'a>(&self) -> &'a [u8] {
+        std::slice::from_raw_parts(self.ptr, self.len)
+    }
+
+    pub unsafe fn as_mut_slice<'a>(&self) -> &'a mut [u8] {
+        std::slice::from_raw_parts_mut(self.ptr, self.len)
+    }
+}
+
+pub fn get_userbuf(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+    len: usize,
+) -> Result<UserBuffer, i32> {
+    UserBuffer::new(region, unsafe { union_argument.dispatch_mutcbuf }, len)
+}
+
+// like get_cbuf and get_mutcbuf, for a buffer of len bytes checked by get_userbuf
+pub fn get_usercbuf(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+    len: usize,
+) -> Result<*const u8, i32> {
+    get_userbuf(region, union_argument, len).map(|buf| buf.as_ptr())
+}
+
+pub fn get_usermutcbuf(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+    len: usize,
+) -> Result<*mut u8, i32> {
+    get_userbuf(region, union_argument, len).map(|buf| buf.as_mut_ptr())
+}
+
+// for the case where the buffer pointer being Null is normal
+pub fn get_usermutcbuf_null(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+    len: usize,
+) -> Result<Option<*mut u8>, i32> {
+    if arg_nullity(&union_argument) {
+        return Ok(None);
+    }
+    get_usermutcbuf(region, union_argument, len).map(Some)
+}
+
+// the buffer as a slice, for syscalls that take one rather than a pointer and a length
+pub fn get_userslice<'a>(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+    len: usize,
+) -> Result<&'a [u8], i32> {
+    let buf = get_userbuf(region, union_argument, len)?;
+    Ok(unsafe { std::slice::from_raw_parts(buf.as_ptr(), len) })
+}
+
+pub fn get_usermutslice<'a>(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+    len: usize,
+) -> Result<&'a mut [u8], i32> {
+    let buf = get_userbuf(region, union_argument, len)?;
+    Ok(unsafe { std::slice::from_raw_parts_mut(buf.as_mut_ptr(), len) })
+}
+
+// a struct the cage passed, checked by get_userbuf to lie wholly within its memory
+fn get_userstruct<'a, T>(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+) -> Result<&'a mut T, i32> {
+    let buf = get_userbuf(region, union_argument, size_of::<T>())?;
+    Ok(unsafe { &mut *(buf.as_mut_ptr() as *mut T) })
+}
+
+// like get_cstr, but the string has to end before the cage's memory does
+pub fn get_usercstr<'a>(region: Option<MemoryRegion>, union_argument: Arg) -> Result<&'a str, i32> {
+    let region = match region {
+        Some(region) => region,
+        None => return get_cstr(union_argument),
+    };
+    let pointer = unsafe { union_argument.dispatch_cstr } as *const u8;
+    let addr = pointer as usize;
+    if pointer.is_null() || !region.contains(addr, 0) {
+        return Err(syscall_error(
+            Errno::EFAULT,
+            "dispatcher",
+            "input data not valid",
+        ));
+    }
+    let maxlen = region.base + region.len - addr;
+    let len = match (0..maxlen).position(|i| unsafe { *pointer.add(i) } == 0) {
+        Some(len) => len,
+        None => {
+            return Err(syscall_error(
+                Errno::EFAULT,
+                "dispatcher",
+                "string runs past the cage's memory",
+            ))
+        }
+    };
+    match std::str::from_utf8(unsafe { std::slice::from_raw_parts(pointer, len) }) {
+        Ok(ret_data) => Ok(ret_data),
+        Err(_) => Err(syscall_error(
+            Errno::EILSEQ,
+            "dispatcher",
+            "could not parse input data to a string",
+        )),
+    }
+}
+
+// for the case where the string pointer being Null is normal
+pub fn get_usercstr_null<'a>(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+) -> Result<Option<&'a str>, i32> {
+    if arg_nullity(&union_argument) {
+        return Ok(None);
+    }
+    get_usercstr(region, union_argument).map(Some)
+}
+
+pub fn get_userstatdatastruct<'a>(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+) -> Result<&'a mut StatData, i32> {
+    get_userstruct(region, union_argument)
+}
+
+pub fn get_userfsdatastruct<'a>(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+) -> Result<&'a mut FSData, i32> {
+    get_userstruct(region, union_argument)
+}
+
+pub fn get_userpipearray<'a>(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+) -> Result<&'a mut PipeArray, i32> {
+    get_userstruct(region, union_argument)
+}
+
+// like get_sockaddr, for an address of addrlen bytes checked by get_userbuf
+pub fn get_usersockaddr(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+    addrlen: u32,
+) -> Result<interface::GenSockaddr, i32> {
+    if (addrlen as usize) < size_of::<u16>() {
+        return Err(syscall_error(
+            Errno::EINVAL,
+            "dispatcher",
+            "input length too small for a sockaddr",
+        ));
+    }
+    get_userbuf(region, union_argument, addrlen as usize)?;
+    get_sockaddr(union_argument, addrlen)
+}
+
+// An iovec array along with every buffer it points to, each checked by get_userbuf. A count
+// that is negative or more than IOV_MAX is EINVAL.
+pub fn get_useriovecs(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+    iovcnt: i32,
+) -> Result<*const IovecStruct, i32> {
+    if iovcnt < 0 || iovcnt as usize > IOV_MAX {
+        return Err(syscall_error(
+            Errno::EINVAL,
+            "dispatcher",
+            "iovec count is negative or more than IOV_MAX",
+        ));
+    }
+    if iovcnt == 0 {
+        return Ok(unsafe { union_argument.dispatch_constiovecstruct });
+    }
+    let array = get_userbuf(
+        region,
+        union_argument,
+        iovcnt as usize * size_of::<IovecStruct>(),
+    )?;
+    let iovecs = unsafe {
+        std::slice::from_raw_parts(array.as_ptr() as *const IovecStruct, iovcnt as usize)
+    };
+    for iovec in iovecs {
+        if iovec.iov_len > 0 {
+            UserBuffer::new(region, iovec.iov_base as *mut u8, iovec.iov_len)?;
+        }
+    }
+    Ok(array.as_ptr() as *const IovecStruct)
+}
+
 pub fn get_cbuf(union_argument: Arg) -> Result<*const u8, i32> {
     let data = unsafe { union_argument.dispatch_cbuf };
     if !data.is_null() {
@@ -290,15 +912,27 @@ pub fn get_mutcbuf_null(union_argument: Arg) -> Result<Option<*mut u8>, i32> {
     return Ok(None);
 }
 
-pub fn get_fdset(union_argument: Arg) -> Result<Option<&'static mut interface::FdSet>, i32> {
+//the first nfds bits of an fd set the cage passed, which may be larger than libc's fd_set
+pub fn get_fdset(union_argument: Arg, nfds: i32) -> Result<Option<interface::FdSet>, i32> {
     let data: *mut libc::fd_set = unsafe { union_argument.dispatch_fdset };
     if !data.is_null() {
-        let internal_fds: &mut interface::FdSet = interface::FdSet::new_from_ptr(data);
-        return Ok(Some(internal_fds));
+        let words = interface::FdSet::words_for(nfds);
+        let words = unsafe { std::slice::from_raw_parts(data as *const u64, words) };
+        return Ok(Some(interface::FdSet::new_from_words(words)));
     }
     return Ok(None);
 }
 
+pub fn copy_out_fdset(union_argument: Arg, fds: Option<&interface::FdSet>, nfds: i32) {
+    let data: *mut libc::fd_set = unsafe { union_argument.dispatch_fdset };
+    if let Some(fds) = fds {
+        if !data.is_null() {
+            let words = interface::FdSet::words_for(nfds);
+            fds.copy_to_words(unsafe { std::slice::from_raw_parts_mut(data as *mut u64, words) });
+        }
+    }
+}
+
 pub fn get_cstr<'a>(union_argument: Arg) -> Result<&'a str, i32> {
     //first we check that the pointer is not null
     //and then we check so that we can get data from the memory
@@ -322,6 +956,15 @@ pub fn get_cstr<'a>(union_argument: Arg) -> Result<&'a str, i32> {
     ));
 }
 
+// for the case where the string pointer being Null is normal
+pub fn get_cstr_null<'a>(union_argument: Arg) -> Result<Option<&'a str>, i32> {
+    let pointer = unsafe { union_argument.dispatch_cstr };
+    if pointer.is_null() {
+        return Ok(None);
+    }
+    get_cstr(union_argument).map(Some)
+}
+
 pub fn get_cstrarr<'a>(union_argument: Arg) -> Result<Vec<&'a str>, i32> {
     //iterate though the pointers in a function and:
     //  1: check that the pointer is not null
@@ -353,6 +996,15 @@ pub fn get_cstrarr<'a>(union_argument: Arg) -> Result<Vec<&'a str>, i32> {
     ));
 }
 
+// for the case where the array pointer being Null is normal
+pub fn get_cstrarr_null<'a>(union_argument: Arg) -> Result<Option<Vec<&'a str>>, i32> {
+    let pointer = unsafe { union_argument.dispatch_cstrarr };
+    if pointer.is_null() {
+        return Ok(None);
+    }
+    get_cstrarr(union_argument).map(Some)
+}
+
 pub fn get_iovecstruct(union_argument: Arg) -> Result<*const interface::IovecStruct, i32> {
     let data = unsafe { union_argument.dispatch_constiovecstruct };
     if !data.is_null() {
@@ -377,6 +1029,30 @@ pub fn get_statdatastruct<'a>(union_argument: Arg) -> Result<&'a mut StatData, i
     ));
 }
 
+pub fn get_statxstruct<'a>(union_argument: Arg) -> Result<&'a mut StatxStruct, i32> {
+    let pointer = unsafe { union_argument.dispatch_statxstruct };
+    if !pointer.is_null() {
+        return Ok(unsafe { &mut *pointer });
+    }
+    return Err(syscall_error(
+        Errno::EFAULT,
+        "dispatcher",
+        "input data not valid",
+    ));
+}
+
+pub fn get_stat64struct<'a>(union_argument: Arg) -> Result<&'a mut Stat64Struct, i32> {
+    let pointer = unsafe { union_argument.dispatch_stat64struct };
+    if !pointer.is_null() {
+        return Ok(unsafe { &mut *pointer });
+    }
+    return Err(syscall_error(
+        Errno::EFAULT,
+        "dispatcher",
+        "input data not valid",
+    ));
+}
+
 pub fn get_fsdatastruct<'a>(union_argument: Arg) -> Result<&'a mut FSData, i32> {
     let pointer = unsafe { union_argument.dispatch_fsdatastruct };
     if !pointer.is_null() {
@@ -389,6 +1065,30 @@ pub fn get_fsdatastruct<'a>(union_argument: Arg) -> Result<&'a mut FSData, i32>
     ));
 }
 
+pub fn get_utsnamestruct<'a>(union_argument: Arg) -> Result<&'a mut UtsName, i32> {
+    let pointer = unsafe { union_argument.dispatch_utsnamestruct };
+    if !pointer.is_null() {
+        return Ok(unsafe { &mut *pointer });
+    }
+    return Err(syscall_error(
+        Errno::EFAULT,
+        "dispatcher",
+        "input data not valid",
+    ));
+}
+
+pub fn get_sysinfostruct<'a>(union_argument: Arg) -> Result<&'a mut SysInfo, i32> {
+    let pointer = unsafe { union_argument.dispatch_sysinfostruct };
+    if !pointer.is_null() {
+        return Ok(unsafe { &mut *pointer });
+    }
+    return Err(syscall_error(
+        Errno::EFAULT,
+        "dispatcher",
+        "input data not valid",
+    ));
+}
+
 pub fn get_shmidstruct<'a>(union_argument: Arg) -> Result<&'a mut ShmidsStruct, i32> {
     let pointer = unsafe { union_argument.dispatch_shmidstruct };
     if !pointer.is_null() {
@@ -401,6 +1101,18 @@ pub fn get_shmidstruct<'a>(union_argument: Arg) -> Result<&'a mut ShmidsStruct,
     ));
 }
 
+pub fn get_msqidstruct<'a>(union_argument: Arg) -> Result<&'a mut MsqidsStruct, i32> {
+    let pointer = unsafe { union_argument.dispatch_msqidstruct };
+    if !pointer.is_null() {
+        return Ok(unsafe { &mut *pointer });
+    }
+    return Err(syscall_error(
+        Errno::EFAULT,
+        "dispatcher",
+        "input data not valid",
+    ));
+}
+
 pub fn get_ioctlptrunion(union_argument: Arg) -> Result<IoctlPtrUnion, i32> {
     return Ok(unsafe { union_argument.dispatch_ioctlptrunion });
 }
@@ -413,6 +1125,39 @@ pub fn get_ioctl_int<'a>(ptrunion: IoctlPtrUnion) -> Result<i32, i32> {
     return Err(syscall_error(Errno::EFAULT, "ioctl", "argp is not valid"));
 }
 
+pub fn set_ioctl_int(ptrunion: IoctlPtrUnion, value: i32) -> Result<(), i32> {
+    let pointer = unsafe { ptrunion.int_ptr };
+    if !pointer.is_null() {
+        unsafe { *pointer = value };
+        return Ok(());
+    }
+    return Err(syscall_error(Errno::EFAULT, "ioctl", "argp is not valid"));
+}
+
+pub fn get_ioctl_termios<'a>(ptrunion: IoctlPtrUnion) -> Result<&'a mut Termios, i32> {
+    let pointer = unsafe { ptrunion.termios_ptr };
+    if !pointer.is_null() {
+        return Ok(unsafe { &mut *pointer });
+    }
+    return Err(syscall_error(Errno::EFAULT, "ioctl", "argp is not valid"));
+}
+
+pub fn get_ioctl_winsize<'a>(ptrunion: IoctlPtrUnion) -> Result<&'a mut WinSize, i32> {
+    let pointer = unsafe { ptrunion.winsize_ptr };
+    if !pointer.is_null() {
+        return Ok(unsafe { &mut *pointer });
+    }
+    return Err(syscall_error(Errno::EFAULT, "ioctl", "argp is not valid"));
+}
+
+pub fn get_ioctl_ifreq<'a>(ptrunion: IoctlPtrUnion) -> Result<&'a mut IfreqStruct, i32> {
+    let pointer = unsafe { ptrunion.ifreq_ptr };
+    if !pointer.is_null() {
+        return Ok(unsafe { &mut *pointer });
+    }
+    return Err(syscall_error(Errno::EFAULT, "ioctl", "argp is not valid"));
+}
+
 pub fn get_ioctl_char<'a>(ptrunion: IoctlPtrUnion) -> Result<u8, i32> {
     let pointer = unsafe { ptrunion.c_char_ptr };
     if !pointer.is_null() {
@@ -486,13 +1231,84 @@ pub fn get_sockpair<'a>(union_argument: Arg) -> Result<&'a mut SockPair, i32> {
     ));
 }
 
+// The numbering of address families cages are built against, which differs between platforms:
+// AF_INET6 is 10 on Linux but 30 on macOS. Sockaddrs are kept with the Linux numbers here, and
+// get_sockaddr and copy_out_sockaddr translate the family of those passing into and out of
+// cages. It starts out as the host's own numbering, and the embedder may set it otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SockaddrAbi {
+    Linux,
+    Darwin,
+}
+
+#[cfg(target_os = "macos")]
+pub const HOST_SOCKADDR_ABI: SockaddrAbi = SockaddrAbi::Darwin;
+#[cfg(not(target_os = "macos"))]
+pub const HOST_SOCKADDR_ABI: SockaddrAbi = SockaddrAbi::Linux;
+
+static SOCKADDR_ABI: interface::RustAtomicBool =
+    interface::RustAtomicBool::new(matches!(HOST_SOCKADDR_ABI, SockaddrAbi::Darwin));
+
+pub fn set_sockaddr_abi(abi: SockaddrAbi) {
+    SOCKADDR_ABI.store(
+        abi == SockaddrAbi::Darwin,
+        interface::RustAtomicOrdering::Relaxed,
+    );
+}
+
+pub fn sockaddr_abi() -> SockaddrAbi {
+    if SOCKADDR_ABI.load(interface::RustAtomicOrdering::Relaxed) {
+        SockaddrAbi::Darwin
+    } else {
+        SockaddrAbi::Linux
+    }
+}
+
+//each supported family as numbered here, and as numbered by the cages' ABI
+fn family_table() -> [(u16, u16); 3] {
+    let inet6 = match sockaddr_abi() {
+        SockaddrAbi::Linux => 10,
+        SockaddrAbi::Darwin => 30,
+    };
+    [
+        (AF_UNIX as u16, 1),
+        (AF_INET as u16, 2),
+        (AF_INET6 as u16, inet6),
+    ]
+}
+
+//the family a cage gave, renumbered for use here, or None for one that isn't supported
+pub fn family_from_abi(family: u16) -> Option<u16> {
+    family_table()
+        .iter()
+        .find(|(_, abifamily)| *abifamily == family)
+        .map(|(ourfamily, _)| *ourfamily)
+}
+
+//a family as a cage numbers it; one with no mapping is passed along as it is
+pub fn family_to_abi(family: u16) -> u16 {
+    family_table()
+        .iter()
+        .find(|(ourfamily, _)| *ourfamily == family)
+        .map_or(family, |(_, abifamily)| *abifamily)
+}
+
 pub fn get_sockaddr(union_argument: Arg, addrlen: u32) -> Result<interface::GenSockaddr, i32> {
     let pointer = unsafe { union_argument.dispatch_constsockaddrstruct };
     if !pointer.is_null() {
         let tmpsock = unsafe { &*pointer };
-        match tmpsock.sa_family {
-            /*AF_UNIX*/
-            1 => {
+        let family = match family_from_abi(tmpsock.sa_family) {
+            Some(family) => family as i32,
+            None => {
+                return Err(syscall_error(
+                    Errno::EOPNOTSUPP,
+                    "dispatcher",
+                    "sockaddr family not supported",
+                ))
+            }
+        };
+        let mut gensock = match family {
+            AF_UNIX => {
                 if addrlen < SIZEOF_SOCKADDR
                     || addrlen > size_of::<interface::SockaddrUnix>() as u32
                 {
@@ -502,11 +1318,18 @@ pub fn get_sockaddr(union_argument: Arg, addrlen: u32) -> Result<interface::GenS
                         "input length incorrect for family of sockaddr",
                     ));
                 }
-                let unix_ptr = pointer as *const interface::SockaddrUnix;
-                return Ok(interface::GenSockaddr::Unix(unsafe { *unix_ptr }));
+                //only the addrlen bytes passed are read, the rest of the path is zeroed
+                let mut unixaddr: interface::SockaddrUnix = unsafe { std::mem::zeroed() };
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        pointer as *const u8,
+                        (&mut unixaddr as *mut interface::SockaddrUnix).cast::<u8>(),
+                        addrlen as usize,
+                    )
+                };
+                interface::GenSockaddr::Unix(unixaddr)
             }
-            /*AF_INET*/
-            2 => {
+            AF_INET => {
                 if addrlen < size_of::<interface::SockaddrV4>() as u32 {
                     return Err(syscall_error(
                         Errno::EINVAL,
@@ -515,10 +1338,9 @@ pub fn get_sockaddr(union_argument: Arg, addrlen: u32) -> Result<interface::GenS
                     ));
                 }
                 let v4_ptr = pointer as *const interface::SockaddrV4;
-                return Ok(interface::GenSockaddr::V4(unsafe { *v4_ptr }));
+                interface::GenSockaddr::V4(unsafe { *v4_ptr })
             }
-            /*AF_INET6*/
-            30 => {
+            AF_INET6 => {
                 if addrlen < size_of::<interface::SockaddrV6>() as u32 {
                     return Err(syscall_error(
                         Errno::EINVAL,
@@ -527,16 +1349,12 @@ pub fn get_sockaddr(union_argument: Arg, addrlen: u32) -> Result<interface::GenS
                     ));
                 }
                 let v6_ptr = pointer as *const interface::SockaddrV6;
-                return Ok(interface::GenSockaddr::V6(unsafe { *v6_ptr }));
-            }
-            _ => {
-                return Err(syscall_error(
-                    Errno::EOPNOTSUPP,
-                    "dispatcher",
-                    "sockaddr family not supported",
-                ))
+                interface::GenSockaddr::V6(unsafe { *v6_ptr })
             }
-        }
+            _ => unreachable!(),
+        };
+        gensock.set_family(family as u16);
+        return Ok(gensock);
     }
     return Err(syscall_error(
         Errno::EFAULT,
@@ -552,9 +1370,19 @@ pub fn copy_out_sockaddr(union_argument: Arg, len_argument: Arg, gensock: interf
     assert!(!addrlen.is_null());
     let initaddrlen = unsafe { *addrlen };
     let mut mutgensock = gensock;
+    mutgensock.set_family(family_to_abi(gensock.get_family()));
     match mutgensock {
         interface::GenSockaddr::Unix(ref mut unixa) => {
-            let unixlen = size_of::<interface::SockaddrUnix>() as u32;
+            //like linux, the length covers the path and its null, or just the family if unnamed
+            let pathlen = gensock.path().len() as u32;
+            let unixlen = if pathlen == 0 {
+                size_of::<u16>() as u32
+            } else {
+                interface::rust_min(
+                    size_of::<u16>() as u32 + pathlen + 1,
+                    size_of::<interface::SockaddrUnix>() as u32,
+                )
+            };
 
             let fullcopylen = interface::rust_min(initaddrlen, unixlen);
             unsafe {
@@ -603,6 +1431,132 @@ pub fn copy_out_sockaddr(union_argument: Arg, len_argument: Arg, gensock: interf
     }
 }
 
+// The msghdr of a sendmsg or recvmsg, checked so that its buffers can be walked: each buffer it
+// gives a length for must be there, and it may hold at most IOV_MAX iovecs.
+pub fn get_msghdr<'a>(union_argument: Arg) -> Result<&'a mut MsghdrStruct, i32> {
+    let pointer = unsafe { union_argument.dispatch_msghdrstruct };
+    if pointer.is_null() {
+        return Err(syscall_error(
+            Errno::EFAULT,
+            "dispatcher",
+            "input data not valid",
+        ));
+    }
+    let msghdr = unsafe { &mut *pointer };
+    if msghdr.msg_iovlen > IOV_MAX {
+        return Err(syscall_error(
+            Errno::EMSGSIZE,
+            "dispatcher",
+            "msghdr holds more than IOV_MAX iovecs",
+        ));
+    }
+    if (msghdr.msg_iov.is_null() && msghdr.msg_iovlen > 0)
+        || (msghdr.msg_control.is_null() && msghdr.msg_controllen > 0)
+    {
+        return Err(syscall_error(
+            Errno::EFAULT,
+            "dispatcher",
+            "msghdr buffer not valid",
+        ));
+    }
+    Ok(msghdr)
+}
+
+//the address a msghdr names, if it names one, read as get_sockaddr reads one
+pub fn get_msghdr_sockaddr(msghdr: &MsghdrStruct) -> Result<Option<interface::GenSockaddr>, i32> {
+    if msghdr.msg_name.is_null() || msghdr.msg_namelen == 0 {
+        return Ok(None);
+    }
+    let name = Arg {
+        dispatch_constsockaddrstruct: msghdr.msg_name as *const SockaddrDummy,
+    };
+    get_sockaddr(name, msghdr.msg_namelen).map(Some)
+}
+
+pub fn get_msghdr_iovecs<'a>(msghdr: &MsghdrStruct) -> &'a [IovecStruct] {
+    if msghdr.msg_iovlen == 0 {
+        return &[];
+    }
+    unsafe { std::slice::from_raw_parts(msghdr.msg_iov, msghdr.msg_iovlen) }
+}
+
+// Walk the control messages of a msghdr, the way CMSG_FIRSTHDR and CMSG_NXTHDR do, giving each
+// header along with its data. A header whose cmsg_len is too small for it or runs past the end of
+// msg_control is EINVAL.
+pub fn iterate_cmsgs<'a>(msghdr: &MsghdrStruct) -> Result<Vec<(CmsghdrStruct, &'a [u8])>, i32> {
+    let mut cmsgs = vec![];
+    if msghdr.msg_controllen == 0 {
+        return Ok(cmsgs);
+    }
+    let control = unsafe { std::slice::from_raw_parts(msghdr.msg_control, msghdr.msg_controllen) };
+    let hdrlen = size_of::<CmsghdrStruct>();
+    let mut offset = 0;
+    while offset + hdrlen <= control.len() {
+        let cmsg =
+            unsafe { std::ptr::read_unaligned(control[offset..].as_ptr() as *const CmsghdrStruct) };
+        if cmsg.cmsg_len < hdrlen || cmsg.cmsg_len > control.len() - offset {
+            return Err(syscall_error(
+                Errno::EINVAL,
+                "dispatcher",
+                "control message length not valid",
+            ));
+        }
+        let data = &control[offset + cmsg_align(hdrlen)..offset + cmsg.cmsg_len];
+        cmsgs.push((cmsg, data));
+        offset += cmsg_align(cmsg.cmsg_len);
+    }
+    Ok(cmsgs)
+}
+
+// Fill in what recvmsg hands back through its msghdr: the sender's address, written like
+// copy_out_sockaddr does, and the control messages, each as (level, type, data). Messages that
+// don't fit in msg_control are dropped and MSG_CTRUNC is set in msg_flags along with flags.
+pub fn copy_out_msghdr(
+    msghdr: &mut MsghdrStruct,
+    name: Option<interface::GenSockaddr>,
+    cmsgs: &[(i32, i32, Vec<u8>)],
+    flags: i32,
+) {
+    match name {
+        Some(gensock) if !msghdr.msg_name.is_null() => {
+            let nameptr = Arg {
+                dispatch_sockaddrstruct: msghdr.msg_name as *mut SockaddrDummy,
+            };
+            let lenptr = Arg {
+                dispatch_socklen_t_ptr: &mut msghdr.msg_namelen as *mut u32,
+            };
+            copy_out_sockaddr(nameptr, lenptr, gensock);
+        }
+        _ => msghdr.msg_namelen = 0,
+    }
+
+    msghdr.msg_flags = flags;
+    let mut offset = 0;
+    for (level, cmsgtype, data) in cmsgs {
+        // the last message needs no padding after it
+        if offset + cmsg_len(data.len()) > msghdr.msg_controllen {
+            msghdr.msg_flags |= MSG_CTRUNC;
+            break;
+        }
+        let cmsg = CmsghdrStruct {
+            cmsg_len: cmsg_len(data.len()),
+            cmsg_level: *level,
+            cmsg_type: *cmsgtype,
+        };
+        unsafe {
+            let hdrptr = msghdr.msg_control.add(offset);
+            std::ptr::write_unaligned(hdrptr as *mut CmsghdrStruct, cmsg);
+            std::ptr::copy(
+                data.as_ptr(),
+                hdrptr.add(cmsg_align(size_of::<CmsghdrStruct>())),
+                data.len(),
+            );
+        }
+        offset = interface::rust_min(offset + cmsg_space(data.len()), msghdr.msg_controllen);
+    }
+    msghdr.msg_controllen = offset;
+}
+
 pub fn get_pollstruct_slice<'a>(
     union_argument: Arg,
     nfds: usize,
@@ -633,9 +1587,36 @@ pub fn get_epollevent_slice<'a>(
     ));
 }
 
+pub fn get_sembuf_slice<'a>(union_argument: Arg, nsops: usize) -> Result<&'a [SembufStruct], i32> {
+    let sembufptr = unsafe { union_argument.dispatch_sembufstructarray };
+    if !sembufptr.is_null() {
+        return Ok(unsafe { std::slice::from_raw_parts(sembufptr, nsops) });
+    }
+    return Err(syscall_error(
+        Errno::EFAULT,
+        "dispatcher",
+        "input data not valid",
+    ));
+}
+
+//an empty list may be passed as null, as getgroups and setgroups allow
+pub fn get_uint_slice<'a>(
+    region: Option<MemoryRegion>,
+    union_argument: Arg,
+    len: usize,
+) -> Result<&'a mut [u32], i32> {
+    if len == 0 {
+        return Ok(&mut []);
+    }
+    // a length too big to count in bytes can't fit anywhere
+    let bytes = len.checked_mul(size_of::<u32>()).unwrap_or(usize::MAX);
+    let buf = get_userbuf(region, union_argument, bytes)?;
+    Ok(unsafe { std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u32, len) })
+}
+
 pub fn get_slice_from_string<'a>(union_argument: Arg, len: usize) -> Result<&'a mut [u8], i32> {
     let bufptr = unsafe { union_argument.dispatch_mutcbuf };
-    if bufptr.is_null() {
+    if !bufptr.is_null() {
         return Ok(unsafe { std::slice::from_raw_parts_mut(bufptr, len as usize) });
     }
     return Err(syscall_error(
@@ -711,6 +1692,42 @@ pub fn get_constitimerval<'a>(union_argument: Arg) -> Result<Option<&'a ITimerVa
     }
 }
 
+pub fn get_timespec<'a>(union_argument: Arg) -> Result<Option<&'a mut TimeSpec>, i32> {
+    let pointer = unsafe { union_argument.dispatch_structtimespec };
+    if !pointer.is_null() {
+        Ok(Some(unsafe { &mut *pointer }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn get_consttimespec<'a>(union_argument: Arg) -> Result<Option<&'a TimeSpec>, i32> {
+    let pointer = unsafe { union_argument.dispatch_conststructtimespec };
+    if !pointer.is_null() {
+        Ok(Some(unsafe { &*pointer }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn get_itimerspec<'a>(union_argument: Arg) -> Result<Option<&'a mut ITimerSpec>, i32> {
+    let pointer = unsafe { union_argument.dispatch_structitimerspec };
+    if !pointer.is_null() {
+        Ok(Some(unsafe { &mut *pointer }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn get_constitimerspec<'a>(union_argument: Arg) -> Result<Option<&'a ITimerSpec>, i32> {
+    let pointer = unsafe { union_argument.dispatch_conststructitimerspec };
+    if !pointer.is_null() {
+        Ok(Some(unsafe { &*pointer }))
+    } else {
+        Ok(None)
+    }
+}
+
 pub fn duration_fromtimespec(union_argument: Arg) -> Result<interface::RustDuration, i32> {
     let pointer = unsafe { union_argument.dispatch_structtimespec };
     if !pointer.is_null() {
@@ -735,6 +1752,28 @@ pub fn duration_fromtimespec(union_argument: Arg) -> Result<interface::RustDurat
     }
 }
 
+// for timeouts where a null timespec means waiting forever
+pub fn duration_fromtimespec_null(
+    union_argument: Arg,
+) -> Result<Option<interface::RustDuration>, i32> {
+    let pointer = unsafe { union_argument.dispatch_structtimespec };
+    if pointer.is_null() {
+        return Ok(None);
+    }
+    let times = unsafe { &*pointer };
+    if times.tv_sec < 0 || times.tv_nsec < 0 || times.tv_nsec >= 1000000000 {
+        return Err(syscall_error(
+            Errno::EINVAL,
+            "timedwait",
+            "timespec was negative or its nanosecond count was more than 1 billion",
+        ));
+    }
+    Ok(Some(interface::RustDuration::new(
+        times.tv_sec as u64,
+        times.tv_nsec as u32,
+    )))
+}
+
 pub fn get_duration_from_millis(
     union_argument: Arg,
 ) -> Result<Option<interface::RustDuration>, i32> {
@@ -781,6 +1820,127 @@ pub fn get_constsigactionstruct<'a>(
     }
 }
 
+pub fn get_mqattrstruct<'a>(union_argument: Arg) -> Result<Option<&'a mut MqAttrStruct>, i32> {
+    let pointer = unsafe { union_argument.dispatch_mqattrstruct };
+
+    if !pointer.is_null() {
+        Ok(Some(unsafe { &mut *pointer }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn get_constmqattrstruct<'a>(union_argument: Arg) -> Result<Option<&'a MqAttrStruct>, i32> {
+    let pointer = unsafe { union_argument.dispatch_constmqattrstruct };
+
+    if !pointer.is_null() {
+        Ok(Some(unsafe { &*pointer }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn get_constsigeventstruct<'a>(union_argument: Arg) -> Result<Option<&'a SigeventStruct>, i32> {
+    let pointer = unsafe { union_argument.dispatch_constsigeventstruct };
+
+    if !pointer.is_null() {
+        Ok(Some(unsafe { &*pointer }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn get_uintptr<'a>(union_argument: Arg) -> Result<Option<&'a mut u32>, i32> {
+    let pointer = unsafe { union_argument.dispatch_uintptr };
+
+    if !pointer.is_null() {
+        Ok(Some(unsafe { &mut *pointer }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn get_intptr<'a>(union_argument: Arg) -> Result<Option<&'a mut i32>, i32> {
+    let pointer = unsafe { union_argument.dispatch_intptr };
+
+    if !pointer.is_null() {
+        Ok(Some(unsafe { &mut *pointer }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn get_rusagestruct<'a>(union_argument: Arg) -> Result<Option<&'a mut Rusage>, i32> {
+    let pointer = unsafe { union_argument.dispatch_rusagestruct };
+
+    if !pointer.is_null() {
+        Ok(Some(unsafe { &mut *pointer }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn get_timevalstruct<'a>(union_argument: Arg) -> Result<&'a mut TimeVal, i32> {
+    let pointer = unsafe { union_argument.dispatch_structtimeval };
+    if !pointer.is_null() {
+        return Ok(unsafe { &mut *pointer });
+    }
+    return Err(syscall_error(
+        Errno::EFAULT,
+        "dispatcher",
+        "input data not valid",
+    ));
+}
+
+pub fn get_ucredstruct<'a>(union_argument: Arg) -> Result<&'a mut UCred, i32> {
+    let pointer = unsafe { union_argument.dispatch_ucredstruct };
+    if !pointer.is_null() {
+        return Ok(unsafe { &mut *pointer });
+    }
+    return Err(syscall_error(
+        Errno::EFAULT,
+        "dispatcher",
+        "input data not valid",
+    ));
+}
+
+pub fn get_capheaderstruct<'a>(union_argument: Arg) -> Result<&'a mut CapUserHeader, i32> {
+    let pointer = unsafe { union_argument.dispatch_capheaderstruct };
+    if !pointer.is_null() {
+        return Ok(unsafe { &mut *pointer });
+    }
+    return Err(syscall_error(
+        Errno::EFAULT,
+        "dispatcher",
+        "input data not valid",
+    ));
+}
+
+pub fn get_capdata_slice<'a>(union_argument: Arg, len: usize) -> Result<&'a [CapUserData], i32> {
+    let pointer = unsafe { union_argument.dispatch_capdatastructarray };
+    if !pointer.is_null() {
+        return Ok(unsafe { std::slice::from_raw_parts(pointer, len) });
+    }
+    return Err(syscall_error(
+        Errno::EFAULT,
+        "dispatcher",
+        "input data not valid",
+    ));
+}
+
+//capget may be passed a null data pointer just to learn the supported version
+pub fn get_capdata_slice_null<'a>(
+    union_argument: Arg,
+    len: usize,
+) -> Result<Option<&'a mut [CapUserData]>, i32> {
+    let pointer = unsafe { union_argument.dispatch_capdatastructarray };
+    if !pointer.is_null() {
+        let data = unsafe { std::slice::from_raw_parts_mut(pointer, len) };
+        return Ok(Some(data));
+    }
+    Ok(None)
+}
+
 pub fn get_sigsett<'a>(union_argument: Arg) -> Result<Option<&'a mut SigsetType>, i32> {
     let pointer = unsafe { union_argument.dispatch_sigsett };
 
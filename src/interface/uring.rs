@@ -0,0 +1,274 @@
+// io_uring backend for the host I/O primitives
+//
+// Built with the io_uring feature, reads, writes and syncs of host files and sends and receives
+// on host sockets are submitted through a small ring kept by each thread, rather than made as
+// plain syscalls. Every function here returns None when the operation can't go through a ring,
+// because the kernel is too old, io_uring is blocked, the opcode is unsupported or the backend
+// has been switched off, and the caller then falls back to the plain syscall. Otherwise it
+// returns what the syscall would: the byte count, or -errno on failure.
+#![allow(dead_code)]
+
+use io_uring::{opcode, squeue, types, IoUring, Probe};
+use std::cell::RefCell;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::interface;
+
+const RING_ENTRIES: u32 = 8;
+
+static URING_ENABLED: interface::RustLazyGlobal<AtomicBool> =
+    interface::RustLazyGlobal::new(|| AtomicBool::new(true));
+// cleared the first time a ring can't be set up or stops working, since no other thread will
+// manage either
+static URING_AVAILABLE: interface::RustLazyGlobal<AtomicBool> =
+    interface::RustLazyGlobal::new(|| AtomicBool::new(true));
+
+pub fn set_io_uring_enabled(enabled: bool) {
+    URING_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+// whether I/O is going through io_uring right now
+pub fn io_uring_active() -> bool {
+    URING_ENABLED.load(Ordering::Relaxed) && URING_AVAILABLE.load(Ordering::Relaxed)
+}
+
+struct Ring {
+    ring: IoUring,
+    probe: Probe,
+}
+
+thread_local! {
+    static RING: RefCell<Option<Ring>> = RefCell::new(None);
+}
+
+fn new_ring() -> Option<Ring> {
+    let ring = IoUring::new(RING_ENTRIES).ok()?;
+    let mut probe = Probe::new();
+    ring.submitter().register_probe(&mut probe).ok()?;
+    Some(Ring { ring, probe })
+}
+
+// Submit entries, the first of which is the operation, and wait for all of them to complete.
+// Returns the operation's result.
+fn submit(codes: &[u8], entries: &[squeue::Entry]) -> Option<i32> {
+    if !io_uring_active() {
+        return None;
+    }
+
+    RING.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        if cell.is_none() {
+            match new_ring() {
+                Some(ring) => *cell = Some(ring),
+                None => {
+                    URING_AVAILABLE.store(false, Ordering::Relaxed);
+                    return None;
+                }
+            }
+        }
+        let ring = cell.as_mut().unwrap();
+        if !codes.iter().all(|code| ring.probe.is_supported(*code)) {
+            return None;
+        }
+
+        for (index, entry) in entries.iter().enumerate() {
+            let entry = entry.clone().user_data(index as u64);
+            if unsafe { ring.ring.submission().push(&entry) }.is_err() {
+                return None;
+            }
+        }
+
+        // the kernel may still be using the buffers, so we can't leave before every entry is done
+        let mut broken = false;
+        loop {
+            match ring.ring.submit_and_wait(entries.len()) {
+                Ok(_) => {}
+                Err(e)
+                    if e.raw_os_error() == Some(libc::EINTR)
+                        || e.raw_os_error() == Some(libc::EAGAIN)
+                        || e.raw_os_error() == Some(libc::EBUSY) => {}
+                Err(_) => {
+                    broken = true;
+                    break;
+                }
+            }
+            if ring.ring.completion().len() >= entries.len() {
+                break;
+            }
+        }
+        if broken {
+            // Dropping the ring cancels whatever it still had in flight. The plain syscalls take
+            // over from here on, starting with this one.
+            *cell = None;
+            URING_AVAILABLE.store(false, Ordering::Relaxed);
+            return None;
+        }
+
+        let mut result = None;
+        for cqe in ring.ring.completion() {
+            if cqe.user_data() == 0 {
+                result = Some(cqe.result());
+            }
+        }
+        result
+    })
+}
+
+pub fn uring_read_at(fd: i32, buf: &mut [u8], offset: u64) -> Option<i32> {
+    let entry = opcode::Read::new(types::Fd(fd), buf.as_mut_ptr(), buf.len() as u32)
+        .offset(offset)
+        .build();
+    submit(&[opcode::Read::CODE], &[entry])
+}
+
+pub fn uring_write_at(fd: i32, buf: &[u8], offset: u64) -> Option<i32> {
+    let entry = opcode::Write::new(types::Fd(fd), buf.as_ptr(), buf.len() as u32)
+        .offset(offset)
+        .build();
+    submit(&[opcode::Write::CODE], &[entry])
+}
+
+pub fn uring_fsync(fd: i32, datasync: bool) -> Option<i32> {
+    let flags = if datasync {
+        types::FsyncFlags::DATASYNC
+    } else {
+        types::FsyncFlags::empty()
+    };
+    let entry = opcode::Fsync::new(types::Fd(fd)).flags(flags).build();
+    submit(&[opcode::Fsync::CODE], &[entry])
+}
+
+pub fn uring_send(fd: i32, buf: *const u8, len: usize, flags: i32) -> Option<i32> {
+    let entry = opcode::Send::new(types::Fd(fd), buf, len as u32)
+        .flags(flags)
+        .build();
+    submit(&[opcode::Send::CODE], &[entry])
+}
+
+// the SO_RCVTIMEO of a host socket, or None if its receives wait for as long as they take
+fn recv_timeout(fd: i32) -> Option<types::Timespec> {
+    let mut timeval = libc::timeval {
+        tv_sec: 0,
+        tv_usec: 0,
+    };
+    let mut optlen = size_of::<libc::timeval>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_RCVTIMEO,
+            (&mut timeval as *mut libc::timeval).cast::<libc::c_void>(),
+            &mut optlen,
+        )
+    };
+    if ret < 0 || (timeval.tv_sec == 0 && timeval.tv_usec == 0) {
+        return None;
+    }
+    Some(
+        types::Timespec::new()
+            .sec(timeval.tv_sec as u64)
+            .nsec(timeval.tv_usec as u32 * 1000),
+    )
+}
+
+// A receive through the ring doesn't honour SO_RCVTIMEO, so on a socket that has one it is linked
+// to a timeout of the same length and reports EAGAIN when that runs out, just as the plain
+// syscall would.
+pub fn uring_recv(fd: i32, buf: *mut u8, len: usize) -> Option<i32> {
+    let recv = opcode::Recv::new(types::Fd(fd), buf, len as u32).build();
+    let timeout = match recv_timeout(fd) {
+        Some(timeout) => timeout,
+        None => return submit(&[opcode::Recv::CODE], &[recv]),
+    };
+    let entries = [
+        recv.flags(squeue::Flags::IO_LINK),
+        opcode::LinkTimeout::new(&timeout).build(),
+    ];
+    match submit(&[opcode::Recv::CODE, opcode::LinkTimeout::CODE], &entries) {
+        Some(result) if result == -libc::ECANCELED => Some(-libc::EAGAIN),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // the backend is switched on and off for the whole process, so the tests take turns
+    static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+    fn socketpair() -> (interface::Socket, interface::Socket) {
+        let mut fds = [-1; 2];
+        assert_eq!(
+            unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) },
+            0
+        );
+        (
+            interface::Socket { raw_sys_fd: fds[0] },
+            interface::Socket { raw_sys_fd: fds[1] },
+        )
+    }
+
+    #[test]
+    fn test_uring_send_recv() {
+        let _turn = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_io_uring_enabled(true);
+        let (sock1, sock2) = socketpair();
+        let mut buf = [0u8; 8];
+
+        let sent = uring_send(sock1.raw_sys_fd, b"ping".as_ptr(), 4, 0);
+        if sent.is_none() {
+            //this kernel can't give us a ring, and what the callers do then is tested below
+            assert!(!io_uring_active());
+            return;
+        }
+        assert_eq!(sent, Some(4));
+        assert_eq!(uring_recv(sock2.raw_sys_fd, buf.as_mut_ptr(), 8), Some(4));
+        assert_eq!(&buf[..4], b"ping");
+
+        //a socket's receive timeout holds for a receive through the ring
+        let timeval = libc::timeval {
+            tv_sec: 0,
+            tv_usec: 50_000,
+        };
+        assert_eq!(
+            unsafe {
+                libc::setsockopt(
+                    sock2.raw_sys_fd,
+                    libc::SOL_SOCKET,
+                    libc::SO_RCVTIMEO,
+                    (&timeval as *const libc::timeval).cast::<libc::c_void>(),
+                    size_of::<libc::timeval>() as libc::socklen_t,
+                )
+            },
+            0
+        );
+        let start = interface::starttimer();
+        assert_eq!(
+            uring_recv(sock2.raw_sys_fd, buf.as_mut_ptr(), 8),
+            Some(-libc::EAGAIN)
+        );
+        assert!(interface::readtimer(start) >= interface::RustDuration::from_millis(50));
+    }
+
+    #[test]
+    fn test_uring_fallback() {
+        let _turn = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let (sock1, sock2) = socketpair();
+        let mut buf = [0u8; 8];
+
+        //switched off, the backend turns everything down
+        set_io_uring_enabled(false);
+        assert!(!io_uring_active());
+        assert_eq!(uring_send(sock1.raw_sys_fd, b"pong".as_ptr(), 4, 0), None);
+        assert_eq!(uring_recv(sock2.raw_sys_fd, buf.as_mut_ptr(), 8), None);
+
+        //and the socket calls are made as plain syscalls instead
+        assert_eq!(sock1.sendto(b"pong".as_ptr(), 4, None, 0), Ok(4));
+        assert_eq!(sock2.recvfrom(buf.as_mut_ptr(), 8, &mut None), Ok(4));
+        assert_eq!(&buf[..4], b"pong");
+        set_io_uring_enabled(true);
+    }
+}
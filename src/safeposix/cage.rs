@@ -1,13 +1,20 @@
 #![allow(dead_code)]
 use crate::interface;
 //going to get the datatypes and errnos from the cage file from now on
-pub use crate::interface::errnos::{syscall_error, Errno};
+pub use crate::interface::errnos::{corruption_error, syscall_error, Errno};
 pub use crate::interface::types::{
-    Arg, EpollEvent, FSData, IoctlPtrUnion, PipeArray, PollStruct, Rlimit, ShmidsStruct, StatData,
+    Arg, EpollEvent, FSData, IoctlPtrUnion, MqAttrStruct, MsqidsStruct, PipeArray, PollStruct,
+    Rlimit, Rusage, SembufStruct, ShmidsStruct, Stat64Struct, StatData, StatxStruct,
+    StatxTimestamp, UCred,
 };
 
+pub use super::cancel::CancelRegistry;
+use super::context::LindContext;
 use super::filesystem::normpath;
+use super::inotify::InotifyInstance;
+use super::mqueue::MqueueInstance;
 use super::net::SocketHandle;
+use super::quota::DiskQuota;
 pub use super::syscalls::fs_constants::*;
 pub use super::syscalls::net_constants::*;
 pub use super::syscalls::sys_constants::*;
@@ -21,6 +28,57 @@ pub enum FileDescriptor {
     Socket(SocketDesc),
     Pipe(PipeDesc),
     Epoll(EpollDesc),
+    Inotify(InotifyDesc),
+    Mqueue(MqueueDesc),
+}
+
+//Status flags such as O_NONBLOCK and O_APPEND belong to the open file description, so every
+//descriptor dup'd or inherited from the same open shares them. The flags of a descriptor itself
+//only hold what is its own: the mode it was opened with and O_CLOEXEC. So does the owner set by
+//F_SETOWN and the signal set by F_SETSIG, which O_ASYNC readiness signals are sent with.
+#[derive(Debug)]
+pub struct StatusFlags {
+    flags: interface::RustAtomicI32,
+    owner: interface::RustAtomicI32, // a cage id, or a process group id if negative
+    sig: interface::RustAtomicI32,   // 0 for SIGIO
+}
+
+impl StatusFlags {
+    pub fn new(flags: i32) -> interface::RustRfc<StatusFlags> {
+        interface::RustRfc::new(StatusFlags {
+            flags: interface::RustAtomicI32::new(flags & O_STATUSFLAGS),
+            owner: interface::RustAtomicI32::new(0),
+            sig: interface::RustAtomicI32::new(0),
+        })
+    }
+
+    pub fn get(&self) -> i32 {
+        self.flags.load(interface::RustAtomicOrdering::Relaxed)
+    }
+
+    pub fn set(&self, flags: i32) {
+        self.flags.store(
+            flags & O_STATUSFLAGS,
+            interface::RustAtomicOrdering::Relaxed,
+        );
+    }
+
+    pub fn owner(&self) -> i32 {
+        self.owner.load(interface::RustAtomicOrdering::Relaxed)
+    }
+
+    pub fn set_owner(&self, owner: i32) {
+        self.owner
+            .store(owner, interface::RustAtomicOrdering::Relaxed);
+    }
+
+    pub fn sig(&self) -> i32 {
+        self.sig.load(interface::RustAtomicOrdering::Relaxed)
+    }
+
+    pub fn set_sig(&self, sig: i32) {
+        self.sig.store(sig, interface::RustAtomicOrdering::Relaxed);
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -28,6 +86,7 @@ pub struct FileDesc {
     pub position: usize,
     pub inode: usize,
     pub flags: i32,
+    pub statusflags: interface::RustRfc<StatusFlags>,
     pub advlock: interface::RustRfc<interface::AdvisoryLock>,
 }
 
@@ -36,12 +95,14 @@ pub struct StreamDesc {
     pub position: usize,
     pub stream: i32, //0 for stdin, 1 for stdout, 2 for stderr
     pub flags: i32,
+    pub statusflags: interface::RustRfc<StatusFlags>,
     pub advlock: interface::RustRfc<interface::AdvisoryLock>,
 }
 
 #[derive(Debug, Clone)]
 pub struct SocketDesc {
     pub flags: i32,
+    pub statusflags: interface::RustRfc<StatusFlags>,
     pub domain: i32,
     pub rawfd: i32,
     pub handle: interface::RustRfc<interface::RustLock<SocketHandle>>,
@@ -52,6 +113,7 @@ pub struct SocketDesc {
 pub struct PipeDesc {
     pub pipe: interface::RustRfc<interface::EmulatedPipe>,
     pub flags: i32,
+    pub statusflags: interface::RustRfc<StatusFlags>,
     pub advlock: interface::RustRfc<interface::AdvisoryLock>,
 }
 
@@ -62,31 +124,324 @@ pub struct EpollDesc {
     pub advlock: interface::RustRfc<interface::AdvisoryLock>,
     pub errno: i32,
     pub flags: i32,
+    pub statusflags: interface::RustRfc<StatusFlags>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InotifyDesc {
+    pub instance: interface::RustRfc<InotifyInstance>,
+    pub flags: i32,
+    pub statusflags: interface::RustRfc<StatusFlags>,
+    pub advlock: interface::RustRfc<interface::AdvisoryLock>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MqueueDesc {
+    pub mqueue: interface::RustRfc<MqueueInstance>,
+    pub flags: i32,
+    pub statusflags: interface::RustRfc<StatusFlags>,
+    pub advlock: interface::RustRfc<interface::AdvisoryLock>,
+}
+
+//a MAP_SHARED mapping of a lind file, tracked so msync and munmap can find the backing inode
+#[derive(Debug, Clone, Copy)]
+pub struct FileMapping {
+    pub addr: usize,
+    pub len: usize,
+    pub inode: usize,
+    pub prot: i32,
+}
+
+//an exited child, held by its parent until the parent waits for it
+#[derive(Debug, Clone, Copy)]
+pub struct Zombie {
+    pub cageid: u64,
+    pub pgid: u64,
+    pub wstatus: i32,
+}
+
+//the real, effective and saved user and group ids of a cage, its supplementary groups, and
+//its capability sets, one bit per CAP_* constant
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub ruid: u32,
+    pub euid: u32,
+    pub suid: u32,
+    pub rgid: u32,
+    pub egid: u32,
+    pub sgid: u32,
+    pub groups: Vec<u32>,
+    pub cap_effective: u64,
+    pub cap_permitted: u64,
+    pub cap_inheritable: u64,
+    pub cap_bounding: u64,
+    pub keepcaps: bool, // keep the permitted set when leaving root, set through prctl
+}
+
+impl Credentials {
+    //root starts out with every capability and anyone else with none
+    pub fn new(uid: u32, gid: u32) -> Credentials {
+        let caps = if uid == 0 { CAP_FULL_SET } else { 0 };
+        Credentials {
+            ruid: uid,
+            euid: uid,
+            suid: uid,
+            rgid: gid,
+            egid: gid,
+            sgid: gid,
+            groups: vec![],
+            cap_effective: caps,
+            cap_permitted: caps,
+            cap_inheritable: 0,
+            cap_bounding: CAP_FULL_SET,
+            keepcaps: false,
+        }
+    }
+
+    pub fn capable(&self, cap: u32) -> bool {
+        self.cap_effective & cap_to_mask(cap) != 0
+    }
+
+    //Called after any change of uids, with the uids from before it. As on Linux, leaving root
+    //entirely drops every permitted capability unless keepcaps is set, leaving an effective uid
+    //of 0 drops the effective set, and regaining one restores it from the permitted set.
+    pub fn fix_caps_after_setuid(&mut self, oldruid: u32, oldeuid: u32, oldsuid: u32) {
+        let wasroot = oldruid == 0 || oldeuid == 0 || oldsuid == 0;
+        if wasroot && self.ruid != 0 && self.euid != 0 && self.suid != 0 && !self.keepcaps {
+            self.cap_permitted = 0;
+            self.cap_effective = 0;
+        }
+        if oldeuid == 0 && self.euid != 0 {
+            self.cap_effective = 0;
+        } else if oldeuid != 0 && self.euid == 0 {
+            self.cap_effective = self.cap_permitted;
+        }
+    }
+
+    //There are no file capabilities, so a new image run by root gets its inheritable set plus the
+    //bounding set, and one run by anyone else gets nothing. keepcaps never survives exec.
+    pub fn exec_caps(&mut self) {
+        if self.ruid == 0 || self.euid == 0 {
+            self.cap_permitted = self.cap_inheritable | self.cap_bounding;
+        } else {
+            self.cap_permitted = 0;
+        }
+        self.cap_effective = if self.euid == 0 {
+            self.cap_permitted
+        } else {
+            0
+        };
+        self.keepcaps = false;
+    }
+
+    //whether these credentials grant the R_OK, W_OK and X_OK bits of want on a file with the
+    //given mode and owner, judged by the effective ids and the supplementary groups
+    pub fn permits(&self, mode: u32, fileuid: u32, filegid: u32, want: u32) -> bool {
+        if self.capable(CAP_DAC_OVERRIDE) {
+            //this may read and write anything, but only execute what someone may, though it can
+            //always search a directory
+            return want & X_OK == 0 || is_dir(mode) || mode & (S_IXUSR | S_IXGRP | S_IXOTH) != 0;
+        }
+        //this may read anything and search any directory
+        if self.capable(CAP_DAC_READ_SEARCH)
+            && want & W_OK == 0
+            && (want & X_OK == 0 || is_dir(mode))
+        {
+            return true;
+        }
+        let shift = if self.euid == fileuid {
+            6
+        } else if self.egid == filegid || self.groups.contains(&filegid) {
+            3
+        } else {
+            0
+        };
+        (mode >> shift) & want == want
+    }
 }
 
-pub type FdTable = Vec<interface::RustRfc<interface::RustLock<Option<FileDescriptor>>>>;
+//what the dispatcher does with a call a cage's syscall filter matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyscallAction {
+    Allow,
+    Log,        // run it, but report it on stderr
+    Errno(i32), // fail it with this errno without running it
+    Kill,       // end the cage as if SIGSYS had killed it
+}
+
+//A syscall policy the embedder sets up for a cage before letting it run. Like a seccomp filter
+//it is handed down across fork, clone and exec, so a cage can't shed it by starting a new one.
+#[derive(Debug, Clone)]
+pub struct SyscallFilter {
+    pub rules: interface::RustBTreeMap<i32, SyscallAction>,
+    pub default: SyscallAction, // for any call without a rule of its own
+}
+
+impl SyscallFilter {
+    pub fn new() -> SyscallFilter {
+        SyscallFilter {
+            rules: interface::RustBTreeMap::new(),
+            default: SyscallAction::Allow,
+        }
+    }
+
+    pub fn action(&self, callnum: i32) -> SyscallAction {
+        match self.rules.get(&callnum) {
+            Some(action) => *action,
+            None => self.default,
+        }
+    }
+}
+
+//A descriptor slot, aligned to its own cache line so that threads busy with neighbouring
+//descriptors don't fight over one line
+#[derive(Debug)]
+#[repr(align(64))]
+pub struct FdSlot(interface::RustLock<Option<FileDescriptor>>);
+
+impl std::ops::Deref for FdSlot {
+    type Target = interface::RustLock<Option<FileDescriptor>>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+//slots are allocated this many at a time, as the table grows
+const FDTABLE_CHUNK: usize = 1024;
+
+//Up to MAXFD slots with a lock each and none over the table as a whole. Slots are allocated a
+//chunk at a time as higher fds are opened, lower chunks first, and stay put once allocated.
+//Cages sharing their descriptors (CLONE_FILES) hold the same table, so a lookup is an index and
+//never a refcount bump.
+#[derive(Debug)]
+pub struct FdTableSlots {
+    chunks: Vec<std::sync::OnceLock<Box<[FdSlot]>>>,
+    limit: interface::RustAtomicI32, // RLIMIT_NOFILE, the lowest fd that is never handed out
+}
+
+impl FdTableSlots {
+    fn new(limit: i32) -> FdTableSlots {
+        FdTableSlots {
+            chunks: (0..(MAXFD as usize + FDTABLE_CHUNK - 1) / FDTABLE_CHUNK)
+                .map(|_| std::sync::OnceLock::new())
+                .collect(),
+            limit: interface::RustAtomicI32::new(limit),
+        }
+    }
+
+    //the slot of fd if it has been allocated, for lookups that don't open an fd
+    pub fn lookup(&self, fd: usize) -> Option<&FdSlot> {
+        self.chunks[fd / FDTABLE_CHUNK]
+            .get()
+            .map(|chunk| &chunk[fd % FDTABLE_CHUNK])
+    }
+
+    //the slot of fd, which must be below MAXFD, allocating it and the slots below if need be
+    pub fn slot(&self, fd: usize) -> &FdSlot {
+        let chunkidx = fd / FDTABLE_CHUNK;
+        for chunk in &self.chunks[..chunkidx] {
+            Self::allocate(chunk);
+        }
+        &Self::allocate(&self.chunks[chunkidx])[fd % FDTABLE_CHUNK]
+    }
+
+    fn allocate(chunk: &std::sync::OnceLock<Box<[FdSlot]>>) -> &[FdSlot] {
+        chunk.get_or_init(|| {
+            (0..FDTABLE_CHUNK)
+                .map(|_| FdSlot(interface::RustLock::new(None)))
+                .collect()
+        })
+    }
+
+    //how many slots have been allocated; every fd at or past this is closed
+    pub fn allocated(&self) -> i32 {
+        (self
+            .chunks
+            .iter()
+            .take_while(|chunk| chunk.get().is_some())
+            .count()
+            * FDTABLE_CHUNK) as i32
+    }
+
+    //the allocated slots, lowest fd first
+    pub fn iter(&self) -> impl Iterator<Item = &FdSlot> {
+        self.chunks
+            .iter()
+            .map_while(|chunk| chunk.get())
+            .flat_map(|chunk| chunk.iter())
+    }
+
+    pub fn limit(&self) -> i32 {
+        self.limit.load(interface::RustAtomicOrdering::Relaxed)
+    }
+
+    pub fn set_limit(&self, limit: i32) {
+        self.limit
+            .store(limit, interface::RustAtomicOrdering::Relaxed);
+    }
+}
+
+impl std::ops::Index<usize> for FdTableSlots {
+    type Output = FdSlot;
+    fn index(&self, fd: usize) -> &FdSlot {
+        self.slot(fd)
+    }
+}
+
+//what a lookup of an fd past the allocated slots gets; no lookup opens an fd, so it stays closed
+static CLOSED_FDSLOT: FdSlot = FdSlot(interface::RustLock::new(None));
+
+pub type FdTable = interface::RustRfc<FdTableSlots>;
+//the epoll fds each fd is registered with, so that closing an fd can take it out of them all
+pub type EpollRegistrations = interface::RustRfc<interface::RustHashMap<i32, Vec<i32>>>;
 
 #[derive(Debug)]
 pub struct Cage {
     pub cageid: u64,
-    pub cwd: interface::RustLock<interface::RustRfc<interface::RustPathBuf>>,
+    pub cwd: interface::RustRfc<interface::RustLock<interface::RustRfc<interface::RustPathBuf>>>,
     pub parent: u64,
+    pub pgid: interface::RustAtomicU64, // process group
+    pub sid: interface::RustAtomicU64,  // session
+    pub nice: interface::RustAtomicI32,
+    pub cpu_affinity: interface::RustHashMap<u64, Vec<u8>>, // by thread, 0 for an unstarted main thread
     pub filedescriptortable: FdTable,
-    pub cancelstatus: interface::RustAtomicBool,
+    pub fdtable_users: interface::RustRfc<interface::RustAtomicUsize>, // cages sharing the fd table
+    pub epoll_registrations: EpollRegistrations, // shared along with the fd table
+    pub cancel_registry: CancelRegistry,         // the cage's blocked syscalls, for lindcancelinit
     pub getgid: interface::RustAtomicI32,
     pub getuid: interface::RustAtomicI32,
     pub getegid: interface::RustAtomicI32,
     pub geteuid: interface::RustAtomicI32,
+    pub cred: interface::RustLock<Credentials>,
     pub rev_shm: interface::Mutex<Vec<(u32, i32)>>, //maps addr within cage to shmid
     pub mutex_table: interface::RustLock<Vec<Option<interface::RustRfc<interface::RawMutex>>>>,
     pub cv_table: interface::RustLock<Vec<Option<interface::RustRfc<interface::RawCondvar>>>>,
     pub sem_table: interface::RustHashMap<u32, interface::RustRfc<interface::RustSemaphore>>,
     pub thread_table: interface::RustHashMap<u64, bool>,
-    pub signalhandler: interface::RustHashMap<i32, interface::SigactionStruct>,
+    pub signalhandler: interface::RustRfc<interface::RustHashMap<i32, interface::SigactionStruct>>,
     pub sigset: interface::RustHashMap<u64, interface::RustAtomicU64>,
     pub pendingsigset: interface::RustHashMap<u64, interface::RustAtomicU64>,
+    pub sharedpendingsigset: interface::RustAtomicU64, // signals sent to the cage as a whole
+    pub sigqueue: interface::Mutex<
+        interface::RustBTreeMap<i32, interface::RustDeque<interface::SiginfoStruct>>,
+    >, // the siginfo of each signal in sharedpendingsigset, oldest first
     pub main_threadid: interface::RustAtomicU64,
     pub interval_timer: interface::IntervalTimer,
+    pub cpu_usage: interface::CpuUsage,
+    pub memory_region: interface::RustLock<Option<interface::MemoryRegion>>, // None until the embedder sets it
+    pub posix_timers: interface::RustLock<Vec<Option<interface::PosixTimer>>>, // by timer id
+    pub file_mappings: interface::Mutex<Vec<FileMapping>>,
+    pub rootdir:
+        interface::RustRfc<interface::RustLock<interface::RustRfc<interface::RustPathBuf>>>,
+    pub fs_users: interface::RustRfc<interface::RustAtomicUsize>, // cages sharing cwd and rootdir
+    pub environ: interface::RustLock<Vec<String>>,
+    pub syscall_filter: interface::RustLock<SyscallFilter>,
+    pub quota: interface::RustLock<Option<interface::RustRfc<DiskQuota>>>,
+    pub zombies: interface::Mutex<Vec<Zombie>>,
+    pub zombie_cv: interface::Condvar, // signalled whenever a child of this cage exits
+    pub select_epoll: interface::Mutex<interface::KernelEpollCache>, // the inet sockets select last polled
+    pub max_watched_fds: interface::RustAtomicI32, // past which select, poll and epoll_wait give EINVAL
+    pub context: &'static LindContext, // the machine whose filesystem and network the cage uses
 }
 
 impl Cage {
@@ -103,7 +458,7 @@ impl Cage {
         };
 
         // let's get the next available fd number. The standard says we need to return the lowest open fd number.
-        for fd in start..MAXFD {
+        for fd in start..self.filedescriptortable.limit() {
             let fdguard = self.filedescriptortable[fd as usize].try_write();
             if let Some(ref fdopt) = fdguard {
                 // we grab the lock here and if there is no occupied cage, we return the fdno and guard while keeping the fd slot locked
@@ -114,7 +469,7 @@ impl Cage {
         }
         return (
             syscall_error(
-                Errno::ENFILE,
+                Errno::EMFILE,
                 "get_next_fd",
                 "no available file descriptor number could be found",
             ),
@@ -122,6 +477,21 @@ impl Cage {
         );
     }
 
+    //where the buffers the cage passes to syscalls must lie
+    pub fn memory_region(&self) -> Option<interface::MemoryRegion> {
+        *self.memory_region.read()
+    }
+
+    //what a unix domain socket's peer sees of this cage through SO_PEERCRED
+    pub fn ucred(&self) -> UCred {
+        let cred = self.cred.read();
+        UCred {
+            pid: self.cageid as i32,
+            uid: cred.euid,
+            gid: cred.egid,
+        }
+    }
+
     pub fn changedir(&self, newdir: interface::RustPathBuf) {
         let newwd = interface::RustRfc::new(normpath(newdir, self));
         let mut cwdbox = self.cwd.write();
@@ -129,80 +499,375 @@ impl Cage {
     }
 
     // function to signal all cvs in a cage when forcing exit
-    pub fn signalcvs(&self) {
-        let cvtable = self.cv_table.read();
+    // A host signal pulls the thread out of guest code so the runtime comes back for what is
+    // pending; the signals themselves are handed over by next_signal.
+    pub fn send_pending_signals(&self, sigset: interface::SigsetType, pthreadid: u64) {
+        if pthreadid == 0
+            || interface::RUSTPOSIX_TESTSUITE.load(interface::RustAtomicOrdering::Relaxed)
+        {
+            return;
+        }
+        for signo in 1..=SIGNAL_MAX {
+            if interface::lind_sigismember(sigset, signo) {
+                interface::lind_threadkill(pthreadid, signo);
+            }
+        }
+    }
 
-        for cv_handle in 0..cvtable.len() {
-            if cvtable[cv_handle as usize].is_some() {
-                let clonedcv = cvtable[cv_handle as usize].as_ref().unwrap().clone();
-                clonedcv.broadcast();
+    //whether sig would be thrown away on delivery, going by how it is disposed of right now
+    pub fn signal_ignored(&self, sig: i32) -> bool {
+        if sig == SIGKILL {
+            return false;
+        }
+        match self.signalhandler.get(&sig) {
+            Some(action) if action.sa_handler == SIG_IGN => true,
+            Some(action) if action.sa_handler != SIG_DFL => false,
+            _ => signal_default_ignored(sig),
+        }
+    }
+
+    //post a signal the kernel raised on its own, such as SIGALRM from the interval timer
+    pub fn post_signal(&self, sig: i32) {
+        self.queue_signal(interface::SiginfoStruct {
+            si_signo: sig,
+            si_code: SI_KERNEL,
+            ..Default::default()
+        });
+    }
+
+    // Make a signal pending for the cage as a whole, for whichever thread doesn't block it to pick
+    // up, either when a blocking syscall notices it or when the runtime next asks for signals.
+    // Each real-time signal sent is queued with its siginfo and delivered in the order sent, while
+    // a standard signal already pending is not queued again. Returns EAGAIN when SIGQUEUE_MAX
+    // real-time signals are queued already. Signal 0 only checks that the cage exists, and is
+    // never posted.
+    pub fn queue_signal(&self, info: interface::SiginfoStruct) -> i32 {
+        let sig = info.si_signo;
+        if sig < 1 || sig > SIGNAL_MAX || self.signal_ignored(sig) {
+            return 0;
+        }
+        let sigbit = interface::lind_sigaddset(0, sig);
+        {
+            let mut sigqueue = self.sigqueue.lock();
+            // a timer whose last signal is still waiting counts each further expiry as an overrun
+            if info.si_code == SI_TIMER {
+                let waiting = sigqueue.get_mut(&sig).and_then(|infos| {
+                    infos.iter_mut().find(|queued| {
+                        queued.si_code == SI_TIMER && queued.si_timerid == info.si_timerid
+                    })
+                });
+                if let Some(queued) = waiting {
+                    queued.si_overrun = queued
+                        .si_overrun
+                        .saturating_add(info.si_overrun)
+                        .saturating_add(1);
+                    return 0;
+                }
             }
+            if sig >= SIGRTMIN {
+                let queued: usize = sigqueue
+                    .iter()
+                    .filter(|(queuedsig, _)| **queuedsig >= SIGRTMIN)
+                    .map(|(_, infos)| infos.len())
+                    .sum();
+                if queued >= SIGQUEUE_MAX {
+                    return syscall_error(
+                        Errno::EAGAIN,
+                        "sigqueue",
+                        "the limit of queued real-time signals has been reached",
+                    );
+                }
+            } else if sigqueue.contains_key(&sig) {
+                return 0;
+            }
+            sigqueue
+                .entry(sig)
+                .or_insert_with(interface::RustDeque::new)
+                .push_back(info);
+            // the bit is only ever changed with the queue locked, so it is set exactly while
+            // something is queued
+            self.sharedpendingsigset
+                .fetch_or(sigbit, interface::RustAtomicOrdering::SeqCst);
         }
+        self.send_pending_signals(
+            sigbit,
+            self.main_threadid
+                .load(interface::RustAtomicOrdering::Relaxed),
+        );
+        0
     }
 
-    pub fn send_pending_signals(&self, sigset: interface::SigsetType, pthreadid: u64) {
-        for signo in 1..SIGNAL_MAX {
-            if interface::lind_sigismember(sigset, signo) {
-                interface::lind_threadkill(pthreadid, signo);
+    pub fn blocked_signals(&self, pthreadid: u64) -> interface::SigsetType {
+        self.sigset.get(&pthreadid).map_or(0, |sigset| {
+            sigset.load(interface::RustAtomicOrdering::Relaxed)
+        })
+    }
+
+    fn set_blocked_signals(&self, pthreadid: u64, mask: interface::SigsetType) {
+        // SIGKILL and SIGSTOP can never be blocked
+        let mask = interface::lind_sigdelset(interface::lind_sigdelset(mask, SIGKILL), SIGSTOP);
+        self.sigset
+            .entry(pthreadid)
+            .or_insert_with(|| interface::RustAtomicU64::new(0))
+            .store(mask, interface::RustAtomicOrdering::Relaxed);
+    }
+
+    //every signal pending for the thread, whether sent to it or to the whole cage
+    pub fn pending_signals(&self, pthreadid: u64) -> interface::SigsetType {
+        let threadpending = self.pendingsigset.get(&pthreadid).map_or(0, |pending| {
+            pending.load(interface::RustAtomicOrdering::SeqCst)
+        });
+        threadpending
+            | self
+                .sharedpendingsigset
+                .load(interface::RustAtomicOrdering::SeqCst)
+    }
+
+    //the pending signals the thread doesn't block, the lowest numbered of which goes first
+    pub fn deliverable_signals(&self, pthreadid: u64) -> interface::SigsetType {
+        self.pending_signals(pthreadid) & !self.blocked_signals(pthreadid)
+    }
+
+    // Take sig off the thread's own pending set if it is there, otherwise take the oldest instance
+    // queued for the cage, giving back its siginfo. A signal sent to the thread alone carries
+    // nothing beyond its number.
+    fn take_pending_signal(&self, pthreadid: u64, sig: i32) -> Option<interface::SiginfoStruct> {
+        let sigbit = interface::lind_sigaddset(0, sig);
+        if let Some(pending) = self.pendingsigset.get(&pthreadid) {
+            if pending.fetch_and(!sigbit, interface::RustAtomicOrdering::SeqCst) & sigbit != 0 {
+                return Some(interface::SiginfoStruct {
+                    si_signo: sig,
+                    si_code: SI_KERNEL,
+                    ..Default::default()
+                });
+            }
+        }
+        let mut sigqueue = self.sigqueue.lock();
+        let infos = sigqueue.get_mut(&sig)?;
+        let info = infos.pop_front();
+        if infos.is_empty() {
+            sigqueue.remove(&sig);
+            self.sharedpendingsigset
+                .fetch_and(!sigbit, interface::RustAtomicOrdering::SeqCst);
+        }
+        info
+    }
+
+    //drop the signal of a deleted timer if it is still waiting to be delivered
+    pub fn discard_timer_signal(&self, timerid: i32) {
+        let mut sigqueue = self.sigqueue.lock();
+        sigqueue.retain(|sig, infos| {
+            infos.retain(|info| !(info.si_code == SI_TIMER && info.si_timerid == timerid));
+            if infos.is_empty() {
+                self.sharedpendingsigset.fetch_and(
+                    !interface::lind_sigaddset(0, *sig),
+                    interface::RustAtomicOrdering::SeqCst,
+                );
+            }
+            !infos.is_empty()
+        });
+    }
+
+    //timers go away along with the cage, and don't survive exec either
+    pub fn delete_posix_timers(&self) {
+        for timer in self.posix_timers.write().drain(..).flatten() {
+            timer.delete();
+        }
+    }
+
+    //a signal that comes to be ignored is dropped from everywhere it was pending
+    pub fn discard_pending_signal(&self, sig: i32) {
+        let sigbit = interface::lind_sigaddset(0, sig);
+        for pending in self.pendingsigset.iter() {
+            pending.fetch_and(!sigbit, interface::RustAtomicOrdering::SeqCst);
+        }
+        let mut sigqueue = self.sigqueue.lock();
+        sigqueue.remove(&sig);
+        self.sharedpendingsigset
+            .fetch_and(!sigbit, interface::RustAtomicOrdering::SeqCst);
+    }
+
+    // Checked wherever a blocking syscall would go back to sleep. If a signal has arrived that the
+    // calling thread doesn't block, this gives the error the call should bail out with, leaving the
+    // signal pending for the runtime to deliver. A call that may be restarted returns ERESTART
+    // when the signal's handler was installed with SA_RESTART, telling the runtime to issue the
+    // call again once the handler has run; everything else gets EINTR. As on Linux, calls that
+    // wait with a timeout (select, poll, epoll_wait, the sleeps, semop and the SysV message
+    // calls) and connect are never restarted.
+    pub fn signal_interrupt(&self, callname: &str, restartable: bool) -> Option<i32> {
+        if interface::sigcheck() {
+            return Some(syscall_error(
+                Errno::EINTR,
+                callname,
+                "interrupted function call",
+            ));
+        }
+        let deliverable = self.deliverable_signals(interface::get_pthreadid());
+        if deliverable == 0 {
+            return None;
+        }
+        let sig = deliverable.trailing_zeros() as i32 + 1;
+        let restart = restartable
+            && self
+                .signalhandler
+                .get(&sig)
+                .map_or(false, |action| action.sa_flags & SA_RESTART != 0);
+        if restart {
+            Some(syscall_error(
+                Errno::ERESTART,
+                callname,
+                "interrupted function call, restart it after the handler runs",
+            ))
+        } else {
+            Some(syscall_error(
+                Errno::EINTR,
+                callname,
+                "interrupted function call",
+            ))
+        }
+    }
+
+    // Hand the calling thread its next deliverable signal so the runtime can run the handler.
+    // Returns the signal number, or 0 once nothing is left to deliver. For a caught signal, action
+    // gets the handler as registered, info what the signal was sent with, for an SA_SIGINFO
+    // handler to be passed, and oldmask the thread's signal mask, which signal_return
+    // puts back after the handler returns; meanwhile the handler's sa_mask is blocked, and so is
+    // the signal itself unless SA_NODEFER was given. A signal whose default action ends the cage
+    // is acted on here, and is returned with SIG_DFL in action so the runtime stops running it.
+    pub fn next_signal(
+        &self,
+        action: &mut interface::SigactionStruct,
+        info: &mut interface::SiginfoStruct,
+        oldmask: &mut interface::SigsetType,
+    ) -> i32 {
+        let pthreadid = interface::get_pthreadid();
+        loop {
+            let deliverable = self.deliverable_signals(pthreadid);
+            if deliverable == 0 {
+                return 0;
+            }
+            let sig = deliverable.trailing_zeros() as i32 + 1;
+            // another thread may have taken a signal sent to the whole cage first
+            let siginfo = match self.take_pending_signal(pthreadid, sig) {
+                Some(siginfo) => siginfo,
+                None => continue,
+            };
+            if self.signal_ignored(sig) {
+                continue;
             }
+            if siginfo.si_code == SI_TIMER {
+                if let Some(Some(timer)) = self.posix_timers.read().get(siginfo.si_timerid as usize)
+                {
+                    timer.set_overrun(siginfo.si_overrun);
+                }
+            }
+            *info = siginfo;
+
+            let handler = match self.signalhandler.get(&sig) {
+                Some(entry) => *entry.value(),
+                None => interface::SigactionStruct::default(),
+            };
+            if sig == SIGKILL || handler.sa_handler == SIG_DFL {
+                *action = interface::SigactionStruct::default();
+                self.exit_by_signal(sig);
+                return sig;
+            }
+
+            let mut handlermask = handler.sa_mask;
+            if handler.sa_flags & SA_NODEFER == 0 {
+                handlermask = interface::lind_sigaddset(handlermask, sig);
+            }
+            *oldmask = self.blocked_signals(pthreadid);
+            self.set_blocked_signals(pthreadid, *oldmask | handlermask);
+            if handler.sa_flags & SA_RESETHAND != 0 {
+                self.signalhandler.remove(&sig);
+            }
+            *action = handler;
+            return sig;
+        }
+    }
+
+    //count cpu time against the cage, raising SIGVTALRM and SIGPROF as their timers run out
+    pub fn charge_cputime(&self, user: interface::RustDuration, system: interface::RustDuration) {
+        let (virtual_expired, prof_expired) = self.cpu_usage.charge(user, system);
+        if virtual_expired {
+            self.post_signal(SIGVTALRM);
+        }
+        if prof_expired {
+            self.post_signal(SIGPROF);
         }
     }
 
+    //restore the mask a handler ran under, once it returns
+    pub fn signal_return(&self, oldmask: interface::SigsetType) {
+        self.set_blocked_signals(interface::get_pthreadid(), oldmask);
+    }
+
     pub fn get_filedescriptor(
         &self,
         fd: i32,
-    ) -> Result<interface::RustRfc<interface::RustLock<Option<FileDescriptor>>>, ()> {
+    ) -> Result<&interface::RustLock<Option<FileDescriptor>>, ()> {
         if (fd < 0) || (fd >= MAXFD) {
             Err(())
         } else {
-            Ok(self.filedescriptortable[fd as usize].clone())
+            let table = &self.filedescriptortable;
+            Ok(&*table.lookup(fd as usize).unwrap_or(&CLOSED_FDSLOT))
         }
     }
 }
 
+//signals whose default action leaves the cage running; cages never stop, so the stop signals are too
+pub fn signal_default_ignored(sig: i32) -> bool {
+    matches!(
+        sig,
+        SIGCHLD | SIGCONT | SIGURG | SIGWINCH | SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU
+    )
+}
+
 pub fn init_fdtable() -> FdTable {
-    let mut fdtable = Vec::new();
+    let stream = |stream: i32, flags: i32| {
+        FdSlot(interface::RustLock::new(Some(FileDescriptor::Stream(
+            StreamDesc {
+                position: 0,
+                stream,
+                flags,
+                statusflags: StatusFlags::new(0),
+                advlock: interface::RustRfc::new(interface::AdvisoryLock::new()),
+            },
+        ))))
+    };
+    let fdtable = FdTableSlots::new(NOFILE_CUR as i32);
     // load lower handle stubs
-    let stdin = interface::RustRfc::new(interface::RustLock::new(Some(FileDescriptor::Stream(
-        StreamDesc {
-            position: 0,
-            stream: 0,
-            flags: O_RDONLY,
-            advlock: interface::RustRfc::new(interface::AdvisoryLock::new()),
-        },
-    ))));
-    let stdout = interface::RustRfc::new(interface::RustLock::new(Some(FileDescriptor::Stream(
-        StreamDesc {
-            position: 0,
-            stream: 1,
-            flags: O_WRONLY,
-            advlock: interface::RustRfc::new(interface::AdvisoryLock::new()),
-        },
-    ))));
-    let stderr = interface::RustRfc::new(interface::RustLock::new(Some(FileDescriptor::Stream(
-        StreamDesc {
-            position: 0,
-            stream: 2,
-            flags: O_WRONLY,
-            advlock: interface::RustRfc::new(interface::AdvisoryLock::new()),
-        },
-    ))));
-    fdtable.push(stdin);
-    fdtable.push(stdout);
-    fdtable.push(stderr);
-
-    for _fd in 3..MAXFD as usize {
-        fdtable.push(interface::RustRfc::new(interface::RustLock::new(None)));
-    }
-    fdtable
-}
-
-pub fn create_unix_sockpipes() -> (
+    let _ = fdtable.chunks[0].set(
+        vec![
+            stream(0, O_RDONLY),
+            stream(1, O_WRONLY),
+            stream(2, O_WRONLY),
+        ]
+        .into_iter()
+        .chain((3..FDTABLE_CHUNK).map(|_| FdSlot(interface::RustLock::new(None))))
+        .collect(),
+    );
+    interface::RustRfc::new(fdtable)
+}
+
+//the capacity of a unix socket pipe for a SO_SNDBUF or SO_RCVBUF, never below the default and, as
+//linux caps the option, at most twice it
+pub fn unix_sockpipe_capacity(bufsize: i32) -> usize {
+    (bufsize.max(0) as usize).clamp(UDSOCK_CAPACITY, 2 * UDSOCK_CAPACITY)
+}
+
+//the pipes a unix socket sends and receives through, sized by its SO_SNDBUF and SO_RCVBUF
+pub fn create_unix_sockpipes(
+    sockhandle: &SocketHandle,
+) -> (
     interface::RustRfc<interface::EmulatedPipe>,
     interface::RustRfc<interface::EmulatedPipe>,
 ) {
-    let pipe1 = interface::RustRfc::new(interface::new_pipe(UDSOCK_CAPACITY));
-    let pipe2 = interface::RustRfc::new(interface::new_pipe(UDSOCK_CAPACITY));
+    let sendcapacity = unix_sockpipe_capacity(sockhandle.sndbuf);
+    let receivecapacity = unix_sockpipe_capacity(sockhandle.rcvbuf);
+    let pipe1 = interface::RustRfc::new(interface::new_pipe(sendcapacity));
+    let pipe2 = interface::RustRfc::new(interface::new_pipe(receivecapacity));
 
     (pipe1, pipe2)
 }
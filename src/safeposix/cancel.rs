@@ -0,0 +1,164 @@
+// Cancellation of the threads blocked in a cage
+//
+// When the embedder tears a cage down it calls lindcancelinit, and every thread of the cage that
+// is blocked in a syscall has to be got out. A blocking syscall holds a CancelPoint, which
+// registers a token with the cage's CancelRegistry the first time the syscall is about to wait,
+// along with a way of waking what it waits on. Cancelling the cage triggers every token,
+// including any registered afterwards, and wakes the waits they belong to. The syscalls then give
+// up with ECANCELED, which tells the embedder the thread is to exit rather than carry on.
+
+use crate::interface;
+use crate::interface::errnos::{syscall_error, Errno};
+
+use std::collections::HashMap;
+
+type Waker = Box<dyn Fn() + Send + Sync>;
+
+struct CancelToken {
+    cancelled: interface::RustAtomicBool,
+    lock: interface::Mutex<()>,
+    cv: interface::Condvar, // what CancelPoint::sleep waits on
+    waker: Option<Waker>,   // wakes whatever else the wait is blocked on
+}
+
+impl CancelToken {
+    fn trigger(&self) {
+        self.cancelled
+            .store(true, interface::RustAtomicOrdering::SeqCst);
+        let guard = self.lock.lock();
+        self.cv.notify_all();
+        drop(guard);
+        if let Some(waker) = &self.waker {
+            waker();
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CancelRegistry {
+    cancelled: interface::RustAtomicBool,
+    tokens: interface::Mutex<HashMap<u64, interface::RustRfc<CancelToken>>>,
+    nextid: interface::RustAtomicU64,
+}
+
+impl std::fmt::Debug for CancelRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancelRegistry")
+            .field("cancelled", &self.is_cancelled())
+            .field("waits", &self.tokens.lock().len())
+            .finish()
+    }
+}
+
+impl CancelRegistry {
+    pub fn new() -> CancelRegistry {
+        CancelRegistry::default()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(interface::RustAtomicOrdering::SeqCst)
+    }
+
+    //trigger every wait registered now or from now on
+    pub fn cancel(&self) {
+        let tokens = self.tokens.lock();
+        self.cancelled
+            .store(true, interface::RustAtomicOrdering::SeqCst);
+        for token in tokens.values() {
+            token.trigger();
+        }
+        drop(tokens);
+        //select, poll and the other waits on readiness look again once woken
+        interface::notify_readiness();
+    }
+
+    //a cancel point for a wait that checks for cancellation often enough by itself
+    pub fn point(&self) -> CancelPoint<'_> {
+        CancelPoint {
+            registry: self,
+            waker: None,
+            token: None,
+        }
+    }
+
+    //a cancel point for a wait that is woken by calling waker
+    pub fn point_waking<F: Fn() + Send + Sync + 'static>(&self, waker: F) -> CancelPoint<'_> {
+        CancelPoint {
+            registry: self,
+            waker: Some(Box::new(waker)),
+            token: None,
+        }
+    }
+
+    fn register(&self, waker: Option<Waker>) -> (u64, interface::RustRfc<CancelToken>) {
+        let id = self
+            .nextid
+            .fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+        let token = interface::RustRfc::new(CancelToken {
+            cancelled: interface::RustAtomicBool::new(false),
+            lock: interface::Mutex::new(()),
+            cv: interface::Condvar::new(),
+            waker: waker,
+        });
+        let mut tokens = self.tokens.lock();
+        if self.is_cancelled() {
+            token
+                .cancelled
+                .store(true, interface::RustAtomicOrdering::SeqCst);
+        } else {
+            tokens.insert(id, token.clone());
+        }
+        (id, token)
+    }
+}
+
+//A blocking syscall's hold on cancellation. Nothing is registered until the syscall first checks,
+//so calls that never have to wait cost nothing, and the token is dropped along with the point.
+pub struct CancelPoint<'a> {
+    registry: &'a CancelRegistry,
+    waker: Option<Waker>,
+    token: Option<(u64, interface::RustRfc<CancelToken>)>,
+}
+
+impl<'a> CancelPoint<'a> {
+    fn token(&mut self) -> &CancelToken {
+        if self.token.is_none() {
+            self.token = Some(self.registry.register(self.waker.take()));
+        }
+        &self.token.as_ref().unwrap().1
+    }
+
+    //ECANCELED once the cage has been cancelled, registering the wait if it isn't yet
+    pub fn check(&mut self, callname: &str) -> Option<i32> {
+        if self
+            .token()
+            .cancelled
+            .load(interface::RustAtomicOrdering::SeqCst)
+        {
+            Some(syscall_error(
+                Errno::ECANCELED,
+                callname,
+                "the cage was cancelled while the call was blocked",
+            ))
+        } else {
+            None
+        }
+    }
+
+    //sleep for duration, or until the cage is cancelled
+    pub fn sleep(&mut self, duration: interface::RustDuration) {
+        let token = self.token();
+        let mut guard = token.lock.lock();
+        if !token.cancelled.load(interface::RustAtomicOrdering::SeqCst) {
+            token.cv.wait_for(&mut guard, duration);
+        }
+    }
+}
+
+impl<'a> Drop for CancelPoint<'a> {
+    fn drop(&mut self) {
+        if let Some((id, _)) = self.token.take() {
+            self.registry.tokens.lock().remove(&id);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// The filesystem and network state that makes up one lind machine
+//
+// The inode table, the metadata log, the open host files, the mounts and the network tables all
+// live in a LindContext. The statics that name them, such as FS_METADATA, LOGMAP,
+// FILEOBJECTTABLE and NET_METADATA, stand for the part of whichever context the calling thread is
+// working in, which is the default context unless the thread has entered another. Each cage
+// belongs to a context and passes it on to the cages it forks, and syscalls dispatched for a cage
+// run in its context, as do the helper threads started on its behalf. Cage ids, IPC objects and the
+// remaining tables are shared by every context.
+
+use super::filesystem::FsContext;
+use super::net::{new_net_metadata, NetMetadata};
+use crate::interface;
+
+use std::cell::Cell;
+
+pub struct LindContext {
+    pub root: Option<interface::RustPathBuf>, // host directory of its files, if not the lind root
+    pub fs: FsContext,
+    pub net: interface::RustLazyGlobal<interface::RustRfc<NetMetadata>>,
+}
+
+impl std::fmt::Debug for LindContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LindContext")
+            .field("root", &self.root)
+            .finish()
+    }
+}
+
+impl LindContext {
+    fn new(root: Option<interface::RustPathBuf>) -> LindContext {
+        LindContext {
+            root: root,
+            fs: FsContext::new(),
+            net: interface::RustLazyGlobal::new(new_net_metadata),
+        }
+    }
+}
+
+static DEFAULT_CONTEXT: interface::RustLazyGlobal<LindContext> =
+    interface::RustLazyGlobal::new(|| LindContext::new(None));
+
+thread_local! {
+    static CURRENT_CONTEXT: Cell<Option<&'static LindContext>> = Cell::new(None);
+}
+
+pub fn default_context() -> &'static LindContext {
+    &DEFAULT_CONTEXT
+}
+
+//A new machine with a filesystem and network of its own, kept in root on the host if given. Its
+//filesystem is loaded the first time it is used, so load_fs should be called from within it
+//first. Cages and statics hand out references to contexts freely, so they are never freed.
+pub fn new_context(root: Option<&interface::RustPath>) -> &'static LindContext {
+    if let Some(root) = root {
+        let _ = std::fs::create_dir_all(root);
+    }
+    Box::leak(Box::new(LindContext::new(
+        root.map(|root| root.to_path_buf()),
+    )))
+}
+
+pub fn current_context() -> &'static LindContext {
+    CURRENT_CONTEXT
+        .with(|current| current.get())
+        .unwrap_or_else(default_context)
+}
+
+//switch the calling thread to context, giving back the one it was in
+fn swap_context(context: &'static LindContext) -> &'static LindContext {
+    let previous = current_context();
+    CURRENT_CONTEXT.with(|current| current.set(Some(context)));
+    interface::set_thread_lind_root(context.root.clone());
+    previous
+}
+
+//work in context on the calling thread from now on, as a cage's threads do
+pub fn set_thread_context(context: &'static LindContext) {
+    swap_context(context);
+}
+
+//work in context on the calling thread until the guard is dropped
+pub fn enter_context(context: &'static LindContext) -> ContextGuard {
+    ContextGuard {
+        previous: swap_context(context),
+    }
+}
+
+pub struct ContextGuard {
+    previous: &'static LindContext,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        swap_context(self.previous);
+    }
+}
+
+//a static that stands for one part of the calling thread's context
+pub struct ContextGlobal<T: 'static> {
+    part: fn(&'static LindContext) -> &'static T,
+}
+
+impl<T> ContextGlobal<T> {
+    pub const fn new(part: fn(&'static LindContext) -> &'static T) -> ContextGlobal<T> {
+        ContextGlobal { part: part }
+    }
+}
+
+impl<T> std::ops::Deref for ContextGlobal<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        (self.part)(current_context())
+    }
+}
@@ -0,0 +1,129 @@
+// Character device drivers, looked up by device number whenever a device file is used
+#![allow(dead_code)]
+
+use super::filesystem::*;
+use super::syscalls::fs_constants::*;
+use super::syscalls::net_constants::{POLLIN, POLLOUT};
+use super::tty::tty_of_inode;
+use crate::interface;
+use crate::interface::errnos::{syscall_error, Errno};
+
+//The operations a character device file supports once it has been opened. Embedders can plug in
+//their own devices by registering an implementation under the device number of a mknod'd file.
+pub trait CharDevice: Send + Sync {
+    fn read(&self, buf: *mut u8, count: usize) -> i32;
+
+    fn write(&self, buf: *const u8, count: usize) -> i32;
+
+    fn ioctl(&self, _request: u32, _ptrunion: interface::IoctlPtrUnion) -> i32 {
+        syscall_error(
+            Errno::ENOTTY,
+            "ioctl",
+            "The specified request does not apply to the kind of object that the file descriptor fd references.",
+        )
+    }
+
+    //returns the subset of POLLIN and POLLOUT in events that would not block right now
+    fn poll(&self, events: i16) -> i16 {
+        events & (POLLIN | POLLOUT)
+    }
+}
+
+pub static CHAR_DEVICES: interface::RustLazyGlobal<
+    interface::RustHashMap<DevNo, interface::RustRfc<dyn CharDevice>>,
+> = interface::RustLazyGlobal::new(|| {
+    let devices: interface::RustHashMap<DevNo, interface::RustRfc<dyn CharDevice>> =
+        interface::RustHashMap::new();
+    devices.insert(NULLDEVNO, interface::RustRfc::new(NullDevice));
+    devices.insert(ZERODEVNO, interface::RustRfc::new(ZeroDevice));
+    devices.insert(FULLDEVNO, interface::RustRfc::new(FullDevice));
+    devices.insert(RANDOMDEVNO, interface::RustRfc::new(RandomDevice));
+    devices.insert(URANDOMDEVNO, interface::RustRfc::new(RandomDevice));
+    devices
+});
+
+//install device as the driver for dev, returning the driver it replaced if there was one
+pub fn register_char_device(
+    dev: DevNo,
+    device: interface::RustRfc<dyn CharDevice>,
+) -> Option<interface::RustRfc<dyn CharDevice>> {
+    CHAR_DEVICES.insert(dev, device)
+}
+
+pub fn unregister_char_device(dev: &DevNo) -> Option<interface::RustRfc<dyn CharDevice>> {
+    CHAR_DEVICES.remove(dev).map(|(_, device)| device)
+}
+
+pub fn char_device(dev: &DevNo) -> Option<interface::RustRfc<dyn CharDevice>> {
+    CHAR_DEVICES.get(dev).map(|device| device.clone())
+}
+
+//the driver behind inodenum if it is a character device with one registered
+pub fn char_device_of_inode(inodenum: usize) -> Option<interface::RustRfc<dyn CharDevice>> {
+    match &*FS_METADATA.inodetable.get(&inodenum)? {
+        Inode::CharDev(devinode) => char_device(&devinode.dev),
+        _ => None,
+    }
+}
+
+//readiness of a regular file descriptor's inode for select and poll, files never block
+pub fn file_poll(inodenum: usize, events: i16) -> i16 {
+    if let Some((tty, end)) = tty_of_inode(inodenum) {
+        return tty.poll(end, events);
+    }
+    match char_device_of_inode(inodenum) {
+        Some(device) => device.poll(events),
+        None => events & (POLLIN | POLLOUT),
+    }
+}
+
+//reading from /dev/null always reads 0 bytes, writes transparently succeed while doing nothing
+pub struct NullDevice;
+
+impl CharDevice for NullDevice {
+    fn read(&self, _buf: *mut u8, _count: usize) -> i32 {
+        0
+    }
+
+    fn write(&self, _buf: *const u8, count: usize) -> i32 {
+        count as i32
+    }
+}
+
+pub struct ZeroDevice;
+
+impl CharDevice for ZeroDevice {
+    fn read(&self, buf: *mut u8, count: usize) -> i32 {
+        interface::fillzero(buf, count)
+    }
+
+    fn write(&self, _buf: *const u8, count: usize) -> i32 {
+        count as i32
+    }
+}
+
+//reads like /dev/zero, but there is never room to write anything
+pub struct FullDevice;
+
+impl CharDevice for FullDevice {
+    fn read(&self, buf: *mut u8, count: usize) -> i32 {
+        interface::fillzero(buf, count)
+    }
+
+    fn write(&self, _buf: *const u8, _count: usize) -> i32 {
+        syscall_error(Errno::ENOSPC, "write or pwrite", "/dev/full is always full")
+    }
+}
+
+//serves both /dev/random and /dev/urandom
+pub struct RandomDevice;
+
+impl CharDevice for RandomDevice {
+    fn read(&self, buf: *mut u8, count: usize) -> i32 {
+        interface::fillrandom(buf, count)
+    }
+
+    fn write(&self, _buf: *const u8, count: usize) -> i32 {
+        count as i32
+    }
+}
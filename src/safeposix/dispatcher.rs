@@ -112,14 +112,115 @@ const SYNC_FILE_RANGE: i32 = 164;
 
 const WRITEV_SYSCALL: i32 = 170;
 
+const INOTIFY_INIT1_SYSCALL: i32 = 171;
+const INOTIFY_ADD_WATCH_SYSCALL: i32 = 172;
+const INOTIFY_RM_WATCH_SYSCALL: i32 = 173;
+
+const MSYNC_SYSCALL: i32 = 174;
+
+const MOUNT_SYSCALL: i32 = 175;
+const UMOUNT_SYSCALL: i32 = 176;
+const CHROOT_SYSCALL: i32 = 177;
+
+const OPENAT_SYSCALL: i32 = 178;
+const UNLINKAT_SYSCALL: i32 = 179;
+const MKDIRAT_SYSCALL: i32 = 180;
+const FSTATAT_SYSCALL: i32 = 181;
+const RENAMEAT_SYSCALL: i32 = 182;
+const LINKAT_SYSCALL: i32 = 183;
+const SYNC_SYSCALL: i32 = 184;
+const SYNCFS_SYSCALL: i32 = 185;
+const DUP3_SYSCALL: i32 = 186;
+const EPOLL_CREATE1_SYSCALL: i32 = 187;
+const SEMGET_SYSCALL: i32 = 188;
+const SEMOP_SYSCALL: i32 = 189;
+const SEMCTL_SYSCALL: i32 = 190;
+const MSGGET_SYSCALL: i32 = 191;
+const MSGSND_SYSCALL: i32 = 192;
+const MSGRCV_SYSCALL: i32 = 193;
+const MSGCTL_SYSCALL: i32 = 194;
+const MQ_OPEN_SYSCALL: i32 = 195;
+const MQ_UNLINK_SYSCALL: i32 = 196;
+const MQ_SEND_SYSCALL: i32 = 197;
+const MQ_RECEIVE_SYSCALL: i32 = 198;
+const MQ_GETSETATTR_SYSCALL: i32 = 199;
+const MQ_NOTIFY_SYSCALL: i32 = 200;
+const SHM_OPEN_SYSCALL: i32 = 201;
+const SHM_UNLINK_SYSCALL: i32 = 202;
+const MEMFD_CREATE_SYSCALL: i32 = 203;
+const FUTEX_SYSCALL: i32 = 204;
+const WAIT_SYSCALL: i32 = 205;
+const WAITPID_SYSCALL: i32 = 206;
+const WAIT4_SYSCALL: i32 = 207;
+const SETPGID_SYSCALL: i32 = 208;
+const GETPGID_SYSCALL: i32 = 209;
+const SETSID_SYSCALL: i32 = 210;
+const GETSID_SYSCALL: i32 = 211;
+const NICE_SYSCALL: i32 = 212;
+const GETPRIORITY_SYSCALL: i32 = 213;
+const SETPRIORITY_SYSCALL: i32 = 214;
+const CLONE_SYSCALL: i32 = 215;
+
+const GETENV_SYSCALL: i32 = 216;
+const SETENV_SYSCALL: i32 = 217;
+const UNSETENV_SYSCALL: i32 = 218;
+const GETENVIRON_SYSCALL: i32 = 219;
+
+const SETUID_SYSCALL: i32 = 220;
+const SETEUID_SYSCALL: i32 = 221;
+const SETGID_SYSCALL: i32 = 222;
+const SETEGID_SYSCALL: i32 = 223;
+const GETGROUPS_SYSCALL: i32 = 224;
+const SETGROUPS_SYSCALL: i32 = 225;
+
+const PRCTL_SYSCALL: i32 = 226;
+const CAPGET_SYSCALL: i32 = 227;
+const CAPSET_SYSCALL: i32 = 228;
+const CHOWN_SYSCALL: i32 = 229;
+const FCHOWN_SYSCALL: i32 = 230;
+
+const SCHED_SETAFFINITY_SYSCALL: i32 = 231;
+const SCHED_GETAFFINITY_SYSCALL: i32 = 232;
+const SIGQUEUE_SYSCALL: i32 = 233;
+const TIMER_CREATE_SYSCALL: i32 = 234;
+const TIMER_SETTIME_SYSCALL: i32 = 235;
+const TIMER_GETTIME_SYSCALL: i32 = 236;
+const TIMER_GETOVERRUN_SYSCALL: i32 = 237;
+const TIMER_DELETE_SYSCALL: i32 = 238;
+const CLOCK_GETTIME_SYSCALL: i32 = 239;
+const CLOCK_GETRES_SYSCALL: i32 = 240;
+const CLOCK_NANOSLEEP_SYSCALL: i32 = 241;
+const NANOSLEEP_SYSCALL: i32 = 242;
+const STATX_SYSCALL: i32 = 243;
+const STAT64_SYSCALL: i32 = 244;
+const FSTAT64_SYSCALL: i32 = 245;
+const ISATTY_SYSCALL: i32 = 246;
+const POSIX_OPENPT_SYSCALL: i32 = 247;
+const GRANTPT_SYSCALL: i32 = 248;
+const UNLOCKPT_SYSCALL: i32 = 249;
+const PTSNAME_SYSCALL: i32 = 250;
+const UNAME_SYSCALL: i32 = 251;
+const SYSINFO_SYSCALL: i32 = 252;
+const READLINK_SYSCALL: i32 = 253;
+
 use super::cage::*;
+use super::context;
 use super::filesystem::{
-    incref_root, load_fs, persist_metadata, remove_domain_sock, FilesystemMetadata, FS_METADATA,
-    LOGFILENAME, LOGMAP,
+    incref_root, load_fs, persist_metadata, remove_domain_sock, start_metadata_flusher,
+    stop_metadata_flusher, umount_all, FilesystemMetadata, FS_METADATA, LOGFILENAME, LOGMAP,
+    LOG_COMPACT_LOCK,
 };
+use super::lifecycle::*;
+use super::metrics;
 use super::net::NET_METADATA;
+use super::procfs::PROCMOUNTPOINT;
 use super::shm::SHM_METADATA;
-use super::syscalls::{fs_constants::IPC_STAT, sys_constants::*};
+use super::syscalls::{
+    fs_constants::{IPC_STAT, MS_NODEV, MS_NOEXEC, MS_NOSUID},
+    net_constants::is_ifreq_request,
+    sys_calls::set_uts_field,
+    sys_constants::*,
+};
 use crate::interface;
 use crate::interface::errnos::*;
 use crate::lib_fs_utils::{lind_deltree, visit_children};
@@ -153,34 +254,82 @@ macro_rules! check_and_dispatch_socketpair {
     };
 }
 
+// Applies the cage's syscall filter, giving the return value for a call it stops, or None when
+// the call should go ahead.
+fn filter_syscall(cage: &Cage, callnum: i32) -> Option<i32> {
+    let action = cage.syscall_filter.read().action(callnum);
+    match action {
+        SyscallAction::Allow => None,
+        SyscallAction::Log => {
+            interface::log_to_stderr(&format!(
+                "cage {} made filtered syscall {}",
+                cage.cageid, callnum
+            ));
+            None
+        }
+        SyscallAction::Errno(errno) => Some(-errno),
+        SyscallAction::Kill => {
+            interface::log_to_stderr(&format!(
+                "cage {} killed by its syscall filter on syscall {}",
+                cage.cageid, callnum
+            ));
+            cage.exit_by_signal(SIGSYS);
+            Some(-(Errno::ENOSYS as i32))
+        }
+    }
+}
+
 // the following "quick" functions are implemented for research purposes
 // to increase I/O performance by bypassing the dispatcher and type checker
 #[no_mangle]
 pub extern "C" fn quick_write(fd: i32, buf: *const u8, count: usize, cageid: u64) -> i32 {
     interface::check_cageid(cageid);
-    unsafe {
+    let cage = interface::cagetable_getref(cageid);
+    let _context = context::enter_context(cage.context);
+    if let Some(rv) = filter_syscall(&cage, WRITE_SYSCALL) {
+        return rv;
+    }
+    if let Err(e) = interface::UserBuffer::new(cage.memory_region(), buf as *mut u8, count) {
+        return e;
+    }
+    let start = metrics::syscall_started();
+    let retval = unsafe {
         CAGE_TABLE[cageid as usize]
             .as_ref()
             .unwrap()
             .write_syscall(fd, buf, count)
-    }
+    };
+    metrics::syscall_finished(cageid, WRITE_SYSCALL, start, retval);
+    retval
 }
 
 #[no_mangle]
 pub extern "C" fn quick_read(fd: i32, buf: *mut u8, size: usize, cageid: u64) -> i32 {
     interface::check_cageid(cageid);
-    unsafe {
+    let cage = interface::cagetable_getref(cageid);
+    let _context = context::enter_context(cage.context);
+    if let Some(rv) = filter_syscall(&cage, READ_SYSCALL) {
+        return rv;
+    }
+    if let Err(e) = interface::UserBuffer::new(cage.memory_region(), buf, size) {
+        return e;
+    }
+    let start = metrics::syscall_started();
+    let retval = unsafe {
         CAGE_TABLE[cageid as usize]
             .as_ref()
             .unwrap()
             .read_syscall(fd, buf, size)
-    }
+    };
+    metrics::syscall_finished(cageid, READ_SYSCALL, start, retval);
+    retval
 }
 
 #[no_mangle]
 pub extern "C" fn rustposix_thread_init(cageid: u64, signalflag: u64) {
     let cage = interface::cagetable_getref(cageid);
     let pthreadid = interface::get_pthreadid();
+    context::set_thread_context(cage.context);
     cage.main_threadid
         .store(pthreadid, interface::RustAtomicOrdering::Relaxed);
     let inheritedsigset = cage.sigset.remove(&0); // in cases of a forked cage, we've stored the inherited sigset at entry 0
@@ -193,9 +342,36 @@ pub extern "C" fn rustposix_thread_init(cageid: u64, signalflag: u64) {
 
     cage.pendingsigset
         .insert(pthreadid, interface::RustAtomicU64::new(0));
+    // likewise the cpus a forked or exec'd cage's main thread was left to run on
+    if let Some((_, mask)) = cage.cpu_affinity.remove(&0) {
+        interface::lind_setaffinity(pthreadid, &mask);
+        cage.cpu_affinity.insert(pthreadid, mask);
+    }
     interface::signalflag_set(signalflag);
 }
 
+// Charges the cage for the cpu time its thread spent in guest code since its last syscall when
+// the syscall starts, and for the time spent in the syscall once it returns.
+struct CpuCharge {
+    cage: interface::RustRfc<Cage>,
+}
+
+impl CpuCharge {
+    fn enter(cage: &interface::RustRfc<Cage>) -> CpuCharge {
+        let user = interface::thread_cputime_since_checkpoint();
+        cage.charge_cputime(user, interface::RustDuration::ZERO);
+        CpuCharge { cage: cage.clone() }
+    }
+}
+
+impl Drop for CpuCharge {
+    fn drop(&mut self) {
+        let system = interface::thread_cputime_since_checkpoint();
+        self.cage
+            .charge_cputime(interface::RustDuration::ZERO, system);
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn dispatcher(
     cageid: u64,
@@ -209,27 +385,54 @@ pub extern "C" fn dispatcher(
 ) -> i32 {
     // need to match based on if cage exists
     let cage = interface::cagetable_getref(cageid);
+    let _cpucharge = CpuCharge::enter(&cage);
+    // the call works on the filesystem and network of the cage's machine
+    let _context = context::enter_context(cage.context);
+
+    if let Some(rv) = filter_syscall(&cage, callnum) {
+        return rv;
+    }
+
+    let start = metrics::syscall_started();
+    let retval = dispatch_call(cage, cageid, callnum, arg1, arg2, arg3, arg4, arg5, arg6);
+    metrics::syscall_finished(cageid, callnum, start, retval);
+    retval
+}
+
+fn dispatch_call(
+    cage: interface::RustRfc<Cage>,
+    cageid: u64,
+    callnum: i32,
+    arg1: Arg,
+    arg2: Arg,
+    arg3: Arg,
+    arg4: Arg,
+    arg5: Arg,
+    arg6: Arg,
+) -> i32 {
+    // buffers the cage passes are checked against its memory before a syscall gets them
+    let region = cage.memory_region();
 
     match callnum {
         ACCESS_SYSCALL => {
             check_and_dispatch!(
                 cage.access_syscall,
-                interface::get_cstr(arg1),
+                interface::get_usercstr(region, arg1),
                 interface::get_uint(arg2)
             )
         }
         UNLINK_SYSCALL => {
-            check_and_dispatch!(cage.unlink_syscall, interface::get_cstr(arg1))
+            check_and_dispatch!(cage.unlink_syscall, interface::get_usercstr(region, arg1))
         }
         LINK_SYSCALL => {
             check_and_dispatch!(
                 cage.link_syscall,
-                interface::get_cstr(arg1),
-                interface::get_cstr(arg2)
+                interface::get_usercstr(region, arg1),
+                interface::get_usercstr(region, arg2)
             )
         }
         CHDIR_SYSCALL => {
-            check_and_dispatch!(cage.chdir_syscall, interface::get_cstr(arg1))
+            check_and_dispatch!(cage.chdir_syscall, interface::get_usercstr(region, arg1))
         }
         FSYNC_SYSCALL => {
             check_and_dispatch!(cage.fsync_syscall, interface::get_int(arg1))
@@ -252,32 +455,34 @@ pub extern "C" fn dispatcher(
         XSTAT_SYSCALL => {
             check_and_dispatch!(
                 cage.stat_syscall,
-                interface::get_cstr(arg1),
-                interface::get_statdatastruct(arg2)
+                interface::get_usercstr(region, arg1),
+                interface::get_userstatdatastruct(region, arg2)
             )
         }
         OPEN_SYSCALL => {
             check_and_dispatch!(
                 cage.open_syscall,
-                interface::get_cstr(arg1),
+                interface::get_usercstr(region, arg1),
                 interface::get_int(arg2),
                 interface::get_uint(arg3)
             )
         }
         READ_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg3));
             check_and_dispatch!(
                 cage.read_syscall,
                 interface::get_int(arg1),
-                interface::get_mutcbuf(arg2),
-                interface::get_usize(arg3)
+                interface::get_usermutcbuf(region, arg2, len),
+                Ok::<usize, i32>(len)
             )
         }
         WRITE_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg3));
             check_and_dispatch!(
                 cage.write_syscall,
                 interface::get_int(arg1),
-                interface::get_cbuf(arg2),
-                interface::get_usize(arg3)
+                interface::get_usercbuf(region, arg2, len),
+                Ok::<usize, i32>(len)
             )
         }
         CLOSE_SYSCALL => {
@@ -295,21 +500,22 @@ pub extern "C" fn dispatcher(
             check_and_dispatch!(
                 cage.fstat_syscall,
                 interface::get_int(arg1),
-                interface::get_statdatastruct(arg2)
+                interface::get_userstatdatastruct(region, arg2)
             )
         }
         FSTATFS_SYSCALL => {
             check_and_dispatch!(
                 cage.fstatfs_syscall,
                 interface::get_int(arg1),
-                interface::get_fsdatastruct(arg2)
+                interface::get_userfsdatastruct(region, arg2)
             )
         }
         MMAP_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg2));
             check_and_dispatch!(
                 cage.mmap_syscall,
-                interface::get_mutcbuf(arg1),
-                interface::get_usize(arg2),
+                interface::get_usermutcbuf(region, arg1, len),
+                Ok::<usize, i32>(len),
                 interface::get_int(arg3),
                 interface::get_int(arg4),
                 interface::get_int(arg5),
@@ -317,10 +523,20 @@ pub extern "C" fn dispatcher(
             )
         }
         MUNMAP_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg2));
             check_and_dispatch!(
                 cage.munmap_syscall,
-                interface::get_mutcbuf(arg1),
-                interface::get_usize(arg2)
+                interface::get_usermutcbuf(region, arg1, len),
+                Ok::<usize, i32>(len)
+            )
+        }
+        MSYNC_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg2));
+            check_and_dispatch!(
+                cage.msync_syscall,
+                interface::get_usermutcbuf(region, arg1, len),
+                Ok::<usize, i32>(len),
+                interface::get_int(arg3)
             )
         }
         DUP_SYSCALL => {
@@ -337,11 +553,19 @@ pub extern "C" fn dispatcher(
                 interface::get_int(arg2)
             )
         }
+        DUP3_SYSCALL => {
+            check_and_dispatch!(
+                cage.dup3_syscall,
+                interface::get_int(arg1),
+                interface::get_int(arg2),
+                interface::get_int(arg3)
+            )
+        }
         STATFS_SYSCALL => {
             check_and_dispatch!(
                 cage.statfs_syscall,
-                interface::get_cstr(arg1),
-                interface::get_fsdatastruct(arg2)
+                interface::get_usercstr(region, arg1),
+                interface::get_userfsdatastruct(region, arg2)
             )
         }
         FCNTL_SYSCALL => {
@@ -353,6 +577,15 @@ pub extern "C" fn dispatcher(
             )
         }
         IOCTL_SYSCALL => {
+            //a struct ifreq has to lie within the cage's memory like any other buffer
+            let request = get_onearg!(interface::get_uint(arg2));
+            if is_ifreq_request(request) {
+                get_onearg!(interface::get_userbuf(
+                    region,
+                    arg3,
+                    std::mem::size_of::<interface::IfreqStruct>()
+                ));
+            }
             check_and_dispatch!(
                 cage.ioctl_syscall,
                 interface::get_int(arg1),
@@ -366,6 +599,64 @@ pub extern "C" fn dispatcher(
         GETPID_SYSCALL => {
             check_and_dispatch!(cage.getpid_syscall,)
         }
+        SETPGID_SYSCALL => {
+            check_and_dispatch!(
+                cage.setpgid_syscall,
+                interface::get_int(arg1),
+                interface::get_int(arg2)
+            )
+        }
+        GETPGID_SYSCALL => {
+            check_and_dispatch!(cage.getpgid_syscall, interface::get_int(arg1))
+        }
+        SETSID_SYSCALL => {
+            check_and_dispatch!(cage.setsid_syscall,)
+        }
+        GETSID_SYSCALL => {
+            check_and_dispatch!(cage.getsid_syscall, interface::get_int(arg1))
+        }
+        NICE_SYSCALL => {
+            check_and_dispatch!(cage.nice_syscall, interface::get_int(arg1))
+        }
+        GETPRIORITY_SYSCALL => {
+            check_and_dispatch!(
+                cage.getpriority_syscall,
+                interface::get_int(arg1),
+                interface::get_int(arg2)
+            )
+        }
+        SETPRIORITY_SYSCALL => {
+            check_and_dispatch!(
+                cage.setpriority_syscall,
+                interface::get_int(arg1),
+                interface::get_int(arg2),
+                interface::get_int(arg3)
+            )
+        }
+        SCHED_SETAFFINITY_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg2));
+            check_and_dispatch!(
+                cage.sched_setaffinity_syscall,
+                interface::get_int(arg1),
+                interface::get_userslice(region, arg3, len)
+            )
+        }
+        SCHED_GETAFFINITY_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg2));
+            check_and_dispatch!(
+                cage.sched_getaffinity_syscall,
+                interface::get_int(arg1),
+                interface::get_usermutslice(region, arg3, len)
+            )
+        }
+        SIGQUEUE_SYSCALL => {
+            check_and_dispatch!(
+                cage.sigqueue_syscall,
+                interface::get_int(arg1),
+                interface::get_int(arg2),
+                interface::get_ulong(arg3)
+            )
+        }
         SOCKET_SYSCALL => {
             check_and_dispatch!(
                 cage.socket_syscall,
@@ -376,7 +667,7 @@ pub extern "C" fn dispatcher(
         }
         BIND_SYSCALL => {
             let addrlen = get_onearg!(interface::get_uint(arg3));
-            let addr = get_onearg!(interface::get_sockaddr(arg2, addrlen));
+            let addr = get_onearg!(interface::get_usersockaddr(region, arg2, addrlen));
             check_and_dispatch!(
                 cage.bind_syscall,
                 interface::get_int(arg1),
@@ -384,36 +675,40 @@ pub extern "C" fn dispatcher(
             )
         }
         SEND_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg3));
             check_and_dispatch!(
                 cage.send_syscall,
                 interface::get_int(arg1),
-                interface::get_cbuf(arg2),
-                interface::get_usize(arg3),
+                interface::get_usercbuf(region, arg2, len),
+                Ok::<usize, i32>(len),
                 interface::get_int(arg4)
             )
         }
         SENDTO_SYSCALL => {
             let addrlen = get_onearg!(interface::get_uint(arg6));
-            let addr = get_onearg!(interface::get_sockaddr(arg5, addrlen));
+            let addr = get_onearg!(interface::get_usersockaddr(region, arg5, addrlen));
+            let len = get_onearg!(interface::get_usize(arg3));
             check_and_dispatch!(
                 cage.sendto_syscall,
                 interface::get_int(arg1),
-                interface::get_cbuf(arg2),
-                interface::get_usize(arg3),
+                interface::get_usercbuf(region, arg2, len),
+                Ok::<usize, i32>(len),
                 interface::get_int(arg4),
                 Ok::<&interface::GenSockaddr, i32>(&addr)
             )
         }
         RECV_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg3));
             check_and_dispatch!(
                 cage.recv_syscall,
                 interface::get_int(arg1),
-                interface::get_mutcbuf(arg2),
-                interface::get_usize(arg3),
+                interface::get_usermutcbuf(region, arg2, len),
+                Ok::<usize, i32>(len),
                 interface::get_int(arg4)
             )
         }
         RECVFROM_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg3));
             let nullity1 = interface::arg_nullity(&arg5);
             let nullity2 = interface::arg_nullity(&arg6);
 
@@ -421,8 +716,8 @@ pub extern "C" fn dispatcher(
                 check_and_dispatch!(
                     cage.recvfrom_syscall,
                     interface::get_int(arg1),
-                    interface::get_mutcbuf(arg2),
-                    interface::get_usize(arg3),
+                    interface::get_usermutcbuf(region, arg2, len),
+                    Ok::<usize, i32>(len),
                     interface::get_int(arg4),
                     Ok::<&mut Option<&mut interface::GenSockaddr>, i32>(&mut None)
                 )
@@ -432,8 +727,8 @@ pub extern "C" fn dispatcher(
                 let rv = check_and_dispatch!(
                     cage.recvfrom_syscall,
                     interface::get_int(arg1),
-                    interface::get_mutcbuf(arg2),
-                    interface::get_usize(arg3),
+                    interface::get_usermutcbuf(region, arg2, len),
+                    Ok::<usize, i32>(len),
                     interface::get_int(arg4),
                     Ok::<&mut Option<&mut interface::GenSockaddr>, i32>(&mut Some(
                         &mut newsockaddr
@@ -454,7 +749,7 @@ pub extern "C" fn dispatcher(
         }
         CONNECT_SYSCALL => {
             let addrlen = get_onearg!(interface::get_uint(arg3));
-            let addr = get_onearg!(interface::get_sockaddr(arg2, addrlen));
+            let addr = get_onearg!(interface::get_usersockaddr(region, arg2, addrlen));
             check_and_dispatch!(
                 cage.connect_syscall,
                 interface::get_int(arg1),
@@ -538,10 +833,11 @@ pub extern "C" fn dispatcher(
             rv
         }
         GETIFADDRS_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg2));
             check_and_dispatch!(
                 cage.getifaddrs_syscall,
-                interface::get_mutcbuf(arg1),
-                interface::get_usize(arg2)
+                interface::get_usermutcbuf(region, arg1, len),
+                Ok::<usize, i32>(len)
             )
         }
         GETSOCKOPT_SYSCALL => {
@@ -553,6 +849,38 @@ pub extern "C" fn dispatcher(
                     "Optval or optlen passed as null",
                 );
             }
+            let level = get_onearg!(interface::get_int(arg2));
+            let optname = get_onearg!(interface::get_int(arg3));
+            if level == SOL_SOCKET && optname == SO_PEERCRED {
+                let credsize = std::mem::size_of::<interface::UCred>();
+                if (get_onearg!(interface::get_socklen_t_ptr(arg5)) as usize) < credsize {
+                    return syscall_error(Errno::EINVAL, "getsockopt", "Invalid optlen passed");
+                }
+                let rv = check_and_dispatch!(
+                    cage.getpeercred_syscall,
+                    interface::get_int(arg1),
+                    interface::get_ucredstruct(arg4)
+                );
+                if rv >= 0 {
+                    interface::copy_out_intptr(arg5, credsize as i32);
+                }
+                return rv;
+            }
+            if level == SOL_SOCKET && optname == SO_SNDTIMEO {
+                let timevalsize = std::mem::size_of::<interface::TimeVal>();
+                if (get_onearg!(interface::get_socklen_t_ptr(arg5)) as usize) < timevalsize {
+                    return syscall_error(Errno::EINVAL, "getsockopt", "Invalid optlen passed");
+                }
+                let rv = check_and_dispatch!(
+                    cage.getsndtimeo_syscall,
+                    interface::get_int(arg1),
+                    interface::get_timevalstruct(arg4)
+                );
+                if rv >= 0 {
+                    interface::copy_out_intptr(arg5, timevalsize as i32);
+                }
+                return rv;
+            }
             if get_onearg!(interface::get_socklen_t_ptr(arg5)) != 4 {
                 return syscall_error(Errno::EINVAL, "setsockopt", "Invalid optlen passed");
             }
@@ -571,6 +899,20 @@ pub extern "C" fn dispatcher(
             rv
         }
         SETSOCKOPT_SYSCALL => {
+            if get_onearg!(interface::get_int(arg2)) == SOL_SOCKET
+                && get_onearg!(interface::get_int(arg3)) == SO_SNDTIMEO
+            {
+                if get_onearg!(interface::get_uint(arg5)) as usize
+                    != std::mem::size_of::<interface::TimeVal>()
+                {
+                    return syscall_error(Errno::EINVAL, "setsockopt", "Invalid optlen passed");
+                }
+                return check_and_dispatch!(
+                    cage.setsndtimeo_syscall,
+                    interface::get_int(arg1),
+                    interface::get_timevalstruct(arg4)
+                );
+            }
             let sockval;
             if !interface::arg_nullity(&arg4) {
                 if get_onearg!(interface::get_uint(arg5)) != 4 {
@@ -597,22 +939,31 @@ pub extern "C" fn dispatcher(
         }
         SELECT_SYSCALL => {
             let nfds = get_onearg!(interface::get_int(arg1));
-            if nfds < 0 {
-                //RLIMIT_NOFILE check as well?
+            //checked before the sets are read, since nfds says how much of them to read
+            if nfds < 0 || nfds > cage.max_watched_fds() {
                 return syscall_error(
                     Errno::EINVAL,
                     "select",
                     "The number of fds passed was invalid",
                 );
             }
-            check_and_dispatch!(
+            let mut readfds = get_onearg!(interface::get_fdset(arg2, nfds));
+            let mut writefds = get_onearg!(interface::get_fdset(arg3, nfds));
+            let mut exceptfds = get_onearg!(interface::get_fdset(arg4, nfds));
+            let rv = check_and_dispatch!(
                 cage.select_syscall,
                 Ok::<i32, i32>(nfds),
-                interface::get_fdset(arg2),
-                interface::get_fdset(arg3),
-                interface::get_fdset(arg4),
+                Ok::<_, i32>(readfds.as_mut()),
+                Ok::<_, i32>(writefds.as_mut()),
+                Ok::<_, i32>(exceptfds.as_mut()),
                 interface::duration_fromtimeval(arg5)
-            )
+            );
+            if rv >= 0 {
+                interface::copy_out_fdset(arg2, readfds.as_ref(), nfds);
+                interface::copy_out_fdset(arg3, writefds.as_ref(), nfds);
+                interface::copy_out_fdset(arg4, exceptfds.as_ref(), nfds);
+            }
+            rv
         }
         POLL_SYSCALL => {
             let nfds = get_onearg!(interface::get_usize(arg2));
@@ -646,7 +997,66 @@ pub extern "C" fn dispatcher(
             check_and_dispatch!(cage.fork_syscall, interface::get_ulong(arg1))
         }
         EXEC_SYSCALL => {
-            check_and_dispatch!(cage.exec_syscall, interface::get_ulong(arg1))
+            check_and_dispatch!(
+                cage.exec_syscall,
+                interface::get_ulong(arg1),
+                interface::get_cstrarr_null(arg2)
+            )
+        }
+        GETENV_SYSCALL => {
+            let len = get_onearg!(interface::get_uint(arg3));
+            check_and_dispatch!(
+                cage.getenv_syscall,
+                interface::get_usercstr(region, arg1),
+                interface::get_usermutcbuf(region, arg2, len as usize),
+                Ok::<u32, i32>(len)
+            )
+        }
+        SETENV_SYSCALL => {
+            check_and_dispatch!(
+                cage.setenv_syscall,
+                interface::get_usercstr(region, arg1),
+                interface::get_usercstr(region, arg2),
+                interface::get_int(arg3)
+            )
+        }
+        UNSETENV_SYSCALL => {
+            check_and_dispatch!(cage.unsetenv_syscall, interface::get_usercstr(region, arg1))
+        }
+        GETENVIRON_SYSCALL => {
+            let len = get_onearg!(interface::get_uint(arg2));
+            check_and_dispatch!(
+                cage.getenviron_syscall,
+                interface::get_usermutcbuf_null(region, arg1, len as usize),
+                Ok::<u32, i32>(len)
+            )
+        }
+        CLONE_SYSCALL => {
+            check_and_dispatch!(
+                cage.clone_syscall,
+                interface::get_int(arg1),
+                interface::get_ulong(arg2)
+            )
+        }
+        WAIT_SYSCALL => {
+            check_and_dispatch!(cage.wait_syscall, interface::get_intptr(arg1))
+        }
+        WAITPID_SYSCALL => {
+            check_and_dispatch!(
+                cage.waitpid_syscall,
+                interface::get_int(arg1),
+                interface::get_intptr(arg2),
+                interface::get_int(arg3)
+            )
+        }
+        WAIT4_SYSCALL => {
+            check_and_dispatch!(
+                cage.wait4_syscall,
+                interface::get_int(arg1),
+                interface::get_intptr(arg2),
+                interface::get_int(arg3),
+                interface::get_rusagestruct(arg4)
+            )
         }
         GETUID_SYSCALL => {
             check_and_dispatch!(cage.getuid_syscall,)
@@ -660,28 +1070,83 @@ pub extern "C" fn dispatcher(
         GETEGID_SYSCALL => {
             check_and_dispatch!(cage.getegid_syscall,)
         }
+        SETUID_SYSCALL => {
+            check_and_dispatch!(cage.setuid_syscall, interface::get_uint(arg1))
+        }
+        SETEUID_SYSCALL => {
+            check_and_dispatch!(cage.seteuid_syscall, interface::get_uint(arg1))
+        }
+        SETGID_SYSCALL => {
+            check_and_dispatch!(cage.setgid_syscall, interface::get_uint(arg1))
+        }
+        SETEGID_SYSCALL => {
+            check_and_dispatch!(cage.setegid_syscall, interface::get_uint(arg1))
+        }
+        GETGROUPS_SYSCALL => {
+            let size = get_onearg!(interface::get_int(arg1));
+            if size < 0 {
+                return syscall_error(Errno::EINVAL, "getgroups", "Invalid size passed");
+            }
+            check_and_dispatch!(
+                cage.getgroups_syscall,
+                interface::get_uint_slice(region, arg2, size as usize)
+            )
+        }
+        SETGROUPS_SYSCALL => {
+            let size = get_onearg!(interface::get_int(arg1));
+            if size < 0 {
+                return syscall_error(Errno::EINVAL, "setgroups", "Invalid size passed");
+            }
+            check_and_dispatch!(
+                cage.setgroups_syscall,
+                interface::get_uint_slice(region, arg2, size as usize)
+            )
+        }
+        PRCTL_SYSCALL => {
+            check_and_dispatch!(
+                cage.prctl_syscall,
+                interface::get_int(arg1),
+                interface::get_ulong(arg2)
+            )
+        }
+        CAPGET_SYSCALL => {
+            check_and_dispatch!(
+                cage.capget_syscall,
+                interface::get_capheaderstruct(arg1),
+                interface::get_capdata_slice_null(arg2, _LINUX_CAPABILITY_U32S_3)
+            )
+        }
+        CAPSET_SYSCALL => {
+            check_and_dispatch!(
+                cage.capset_syscall,
+                interface::get_capheaderstruct(arg1),
+                interface::get_capdata_slice(arg2, _LINUX_CAPABILITY_U32S_3)
+            )
+        }
         PREAD_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg3));
             check_and_dispatch!(
                 cage.pread_syscall,
                 interface::get_int(arg1),
-                interface::get_mutcbuf(arg2),
-                interface::get_usize(arg3),
+                interface::get_usermutcbuf(region, arg2, len),
+                Ok::<usize, i32>(len),
                 interface::get_isize(arg4)
             )
         }
         PWRITE_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg3));
             check_and_dispatch!(
                 cage.pwrite_syscall,
                 interface::get_int(arg1),
-                interface::get_mutcbuf(arg2),
-                interface::get_usize(arg3),
+                interface::get_usermutcbuf(region, arg2, len),
+                Ok::<usize, i32>(len),
                 interface::get_isize(arg4)
             )
         }
         CHMOD_SYSCALL => {
             check_and_dispatch!(
                 cage.chmod_syscall,
-                interface::get_cstr(arg1),
+                interface::get_usercstr(region, arg1),
                 interface::get_uint(arg2)
             )
         }
@@ -692,19 +1157,38 @@ pub extern "C" fn dispatcher(
                 interface::get_uint(arg2)
             )
         }
+        CHOWN_SYSCALL => {
+            check_and_dispatch!(
+                cage.chown_syscall,
+                interface::get_usercstr(region, arg1),
+                interface::get_uint(arg2),
+                interface::get_uint(arg3)
+            )
+        }
+        FCHOWN_SYSCALL => {
+            check_and_dispatch!(
+                cage.fchown_syscall,
+                interface::get_int(arg1),
+                interface::get_uint(arg2),
+                interface::get_uint(arg3)
+            )
+        }
         RMDIR_SYSCALL => {
-            check_and_dispatch!(cage.rmdir_syscall, interface::get_cstr(arg1))
+            check_and_dispatch!(cage.rmdir_syscall, interface::get_usercstr(region, arg1))
         }
         RENAME_SYSCALL => {
             check_and_dispatch!(
                 cage.rename_syscall,
-                interface::get_cstr(arg1),
-                interface::get_cstr(arg2)
+                interface::get_usercstr(region, arg1),
+                interface::get_usercstr(region, arg2)
             )
         }
         EPOLL_CREATE_SYSCALL => {
             check_and_dispatch!(cage.epoll_create_syscall, interface::get_int(arg1))
         }
+        EPOLL_CREATE1_SYSCALL => {
+            check_and_dispatch!(cage.epoll_create1_syscall, interface::get_int(arg1))
+        }
         EPOLL_CTL_SYSCALL => {
             check_and_dispatch!(
                 cage.epoll_ctl_syscall,
@@ -735,41 +1219,47 @@ pub extern "C" fn dispatcher(
             )
         }
         GETDENTS_SYSCALL => {
+            let len = get_onearg!(interface::get_uint(arg3));
             check_and_dispatch!(
                 cage.getdents_syscall,
                 interface::get_int(arg1),
-                interface::get_mutcbuf(arg2),
-                interface::get_uint(arg3)
+                interface::get_usermutcbuf(region, arg2, len as usize),
+                Ok::<u32, i32>(len)
             )
         }
         PIPE_SYSCALL => {
-            check_and_dispatch!(cage.pipe_syscall, interface::get_pipearray(arg1))
+            check_and_dispatch!(
+                cage.pipe_syscall,
+                interface::get_userpipearray(region, arg1)
+            )
         }
         PIPE2_SYSCALL => {
             check_and_dispatch!(
                 cage.pipe2_syscall,
-                interface::get_pipearray(arg1),
+                interface::get_userpipearray(region, arg1),
                 interface::get_int(arg2)
             )
         }
         GETCWD_SYSCALL => {
+            let len = get_onearg!(interface::get_uint(arg2));
             check_and_dispatch!(
                 cage.getcwd_syscall,
-                interface::get_mutcbuf(arg1),
-                interface::get_uint(arg2)
+                interface::get_usermutcbuf(region, arg1, len as usize),
+                Ok::<u32, i32>(len)
             )
         }
         GETHOSTNAME_SYSCALL => {
+            let len = get_onearg!(interface::get_isize(arg2));
             check_and_dispatch!(
                 cage.gethostname_syscall,
-                interface::get_mutcbuf(arg1),
-                interface::get_isize(arg2)
+                interface::get_usermutcbuf(region, arg1, len.max(0) as usize),
+                Ok::<isize, i32>(len)
             )
         }
         MKDIR_SYSCALL => {
             check_and_dispatch!(
                 cage.mkdir_syscall,
-                interface::get_cstr(arg1),
+                interface::get_usercstr(region, arg1),
                 interface::get_uint(arg2)
             )
         }
@@ -782,19 +1272,24 @@ pub extern "C" fn dispatcher(
             )
         }
         SHMAT_SYSCALL => {
+            //the segment's size isn't known here, so only the address is checked against the
+            //cage's memory, as it is for shmdt
             check_and_dispatch!(
                 cage.shmat_syscall,
                 interface::get_int(arg1),
-                interface::get_mutcbuf(arg2),
+                interface::get_usermutcbuf(region, arg2, 0),
                 interface::get_int(arg3)
             )
         }
         SHMDT_SYSCALL => {
-            check_and_dispatch!(cage.shmdt_syscall, interface::get_mutcbuf(arg1))
+            check_and_dispatch!(
+                cage.shmdt_syscall,
+                interface::get_usermutcbuf(region, arg1, 0)
+            )
         }
         SHMCTL_SYSCALL => {
             let cmd = get_onearg!(interface::get_int(arg2));
-            let buf = if cmd == IPC_STAT {
+            let buf = if cmd == IPC_STAT || cmd == IPC_SET {
                 Some(get_onearg!(interface::get_shmidstruct(arg3)))
             } else {
                 None
@@ -806,6 +1301,161 @@ pub extern "C" fn dispatcher(
                 Ok::<Option<&mut interface::ShmidsStruct>, i32>(buf)
             )
         }
+        SEMGET_SYSCALL => {
+            check_and_dispatch!(
+                cage.semget_syscall,
+                interface::get_int(arg1),
+                interface::get_int(arg2),
+                interface::get_int(arg3)
+            )
+        }
+        SEMOP_SYSCALL => {
+            let nsops = get_onearg!(interface::get_usize(arg3));
+            check_and_dispatch!(
+                cage.semop_syscall,
+                interface::get_int(arg1),
+                interface::get_sembuf_slice(arg2, nsops)
+            )
+        }
+        SEMCTL_SYSCALL => {
+            check_and_dispatch!(
+                cage.semctl_syscall,
+                interface::get_int(arg1),
+                interface::get_int(arg2),
+                interface::get_int(arg3),
+                interface::get_int(arg4)
+            )
+        }
+        MSGGET_SYSCALL => {
+            check_and_dispatch!(
+                cage.msgget_syscall,
+                interface::get_int(arg1),
+                interface::get_int(arg2)
+            )
+        }
+        MSGSND_SYSCALL => {
+            // the message follows a long holding its type
+            let len = get_onearg!(interface::get_usize(arg3));
+            check_and_dispatch!(
+                cage.msgsnd_syscall,
+                interface::get_int(arg1),
+                interface::get_userslice(region, arg2, len.saturating_add(8)),
+                interface::get_int(arg4)
+            )
+        }
+        MSGRCV_SYSCALL => {
+            // the message follows a long holding its type
+            let len = get_onearg!(interface::get_usize(arg3));
+            check_and_dispatch!(
+                cage.msgrcv_syscall,
+                interface::get_int(arg1),
+                interface::get_usermutslice(region, arg2, len.saturating_add(8)),
+                interface::get_long(arg4),
+                interface::get_int(arg5)
+            )
+        }
+        MSGCTL_SYSCALL => {
+            let cmd = get_onearg!(interface::get_int(arg2));
+            let buf = if cmd == IPC_STAT || cmd == IPC_SET {
+                Some(get_onearg!(interface::get_msqidstruct(arg3)))
+            } else {
+                None
+            };
+            check_and_dispatch!(
+                cage.msgctl_syscall,
+                interface::get_int(arg1),
+                Ok::<i32, i32>(cmd),
+                Ok::<Option<&mut interface::MsqidsStruct>, i32>(buf)
+            )
+        }
+        MQ_OPEN_SYSCALL => {
+            check_and_dispatch!(
+                cage.mq_open_syscall,
+                interface::get_usercstr(region, arg1),
+                interface::get_int(arg2),
+                interface::get_uint(arg3),
+                interface::get_constmqattrstruct(arg4)
+            )
+        }
+        MQ_UNLINK_SYSCALL => {
+            check_and_dispatch!(
+                cage.mq_unlink_syscall,
+                interface::get_usercstr(region, arg1)
+            )
+        }
+        MQ_SEND_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg3));
+            check_and_dispatch!(
+                cage.mq_send_syscall,
+                interface::get_int(arg1),
+                interface::get_userslice(region, arg2, len),
+                interface::get_uint(arg4)
+            )
+        }
+        MQ_RECEIVE_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg3));
+            check_and_dispatch!(
+                cage.mq_receive_syscall,
+                interface::get_int(arg1),
+                interface::get_usermutslice(region, arg2, len),
+                interface::get_uintptr(arg4)
+            )
+        }
+        MQ_GETSETATTR_SYSCALL => {
+            check_and_dispatch!(
+                cage.mq_getsetattr_syscall,
+                interface::get_int(arg1),
+                interface::get_constmqattrstruct(arg2),
+                interface::get_mqattrstruct(arg3)
+            )
+        }
+        MQ_NOTIFY_SYSCALL => {
+            check_and_dispatch!(
+                cage.mq_notify_syscall,
+                interface::get_int(arg1),
+                interface::get_constsigeventstruct(arg2)
+            )
+        }
+        SHM_OPEN_SYSCALL => {
+            check_and_dispatch!(
+                cage.shm_open_syscall,
+                interface::get_usercstr(region, arg1),
+                interface::get_int(arg2),
+                interface::get_uint(arg3)
+            )
+        }
+        SHM_UNLINK_SYSCALL => {
+            check_and_dispatch!(
+                cage.shm_unlink_syscall,
+                interface::get_usercstr(region, arg1)
+            )
+        }
+        MEMFD_CREATE_SYSCALL => {
+            check_and_dispatch!(
+                cage.memfd_create_syscall,
+                interface::get_usercstr(region, arg1),
+                interface::get_uint(arg2)
+            )
+        }
+        FUTEX_SYSCALL => {
+            //FUTEX_REQUEUE passes its requeue count where FUTEX_WAIT passes its timeout
+            let futex_op = get_onearg!(interface::get_int(arg2));
+            let (val2, timeout) = if futex_op & FUTEX_CMD_MASK == FUTEX_WAIT {
+                (0, get_onearg!(interface::duration_fromtimespec_null(arg4)))
+            } else {
+                (get_onearg!(interface::get_usize(arg4)), None)
+            };
+            check_and_dispatch!(
+                cage.futex_syscall,
+                interface::get_usermutcbuf(region, arg1, 4).map(|uaddr| uaddr as *mut u32),
+                Ok::<i32, i32>(futex_op),
+                interface::get_uint(arg3),
+                Ok::<usize, i32>(val2),
+                Ok::<Option<interface::RustDuration>, i32>(timeout),
+                interface::get_usermutcbuf_null(region, arg5, 4)
+                    .map(|uaddr2| uaddr2.map(|uaddr2| uaddr2 as *mut u32))
+            )
+        }
 
         MUTEX_CREATE_SYSCALL => {
             check_and_dispatch!(cage.mutex_create_syscall,)
@@ -852,7 +1502,7 @@ pub extern "C" fn dispatcher(
         TRUNCATE_SYSCALL => {
             check_and_dispatch!(
                 cage.truncate_syscall,
-                interface::get_cstr(arg1),
+                interface::get_usercstr(region, arg1),
                 interface::get_isize(arg2)
             )
         }
@@ -894,6 +1544,66 @@ pub extern "C" fn dispatcher(
                 interface::get_itimerval(arg3)
             )
         }
+        CLOCK_GETTIME_SYSCALL => {
+            check_and_dispatch!(
+                cage.clock_gettime_syscall,
+                interface::get_int(arg1),
+                interface::get_timespec(arg2)
+            )
+        }
+        CLOCK_GETRES_SYSCALL => {
+            check_and_dispatch!(
+                cage.clock_getres_syscall,
+                interface::get_int(arg1),
+                interface::get_timespec(arg2)
+            )
+        }
+        CLOCK_NANOSLEEP_SYSCALL => {
+            check_and_dispatch!(
+                cage.clock_nanosleep_syscall,
+                interface::get_int(arg1),
+                interface::get_int(arg2),
+                interface::get_consttimespec(arg3),
+                interface::get_timespec(arg4)
+            )
+        }
+        NANOSLEEP_SYSCALL => {
+            check_and_dispatch!(
+                cage.nanosleep_syscall,
+                interface::get_consttimespec(arg1),
+                interface::get_timespec(arg2)
+            )
+        }
+        TIMER_CREATE_SYSCALL => {
+            check_and_dispatch!(
+                cage.timer_create_syscall,
+                interface::get_int(arg1),
+                interface::get_constsigeventstruct(arg2),
+                interface::get_intptr(arg3)
+            )
+        }
+        TIMER_SETTIME_SYSCALL => {
+            check_and_dispatch!(
+                cage.timer_settime_syscall,
+                interface::get_int(arg1),
+                interface::get_int(arg2),
+                interface::get_constitimerspec(arg3),
+                interface::get_itimerspec(arg4)
+            )
+        }
+        TIMER_GETTIME_SYSCALL => {
+            check_and_dispatch!(
+                cage.timer_gettime_syscall,
+                interface::get_int(arg1),
+                interface::get_itimerspec(arg2)
+            )
+        }
+        TIMER_GETOVERRUN_SYSCALL => {
+            check_and_dispatch!(cage.timer_getoverrun_syscall, interface::get_int(arg1))
+        }
+        TIMER_DELETE_SYSCALL => {
+            check_and_dispatch!(cage.timer_delete_syscall, interface::get_int(arg1))
+        }
         SEM_INIT_SYSCALL => {
             check_and_dispatch!(
                 cage.sem_init_syscall,
@@ -925,13 +1635,177 @@ pub extern "C" fn dispatcher(
             )
         }
         WRITEV_SYSCALL => {
+            let iovcnt = get_onearg!(interface::get_int(arg3));
             check_and_dispatch!(
                 cage.writev_syscall,
                 interface::get_int(arg1),
-                interface::get_iovecstruct(arg2),
+                interface::get_useriovecs(region, arg2, iovcnt),
+                Ok::<i32, i32>(iovcnt)
+            )
+        }
+        INOTIFY_INIT1_SYSCALL => {
+            check_and_dispatch!(cage.inotify_init1_syscall, interface::get_int(arg1))
+        }
+        INOTIFY_ADD_WATCH_SYSCALL => {
+            check_and_dispatch!(
+                cage.inotify_add_watch_syscall,
+                interface::get_int(arg1),
+                interface::get_usercstr(region, arg2),
+                interface::get_uint(arg3)
+            )
+        }
+        INOTIFY_RM_WATCH_SYSCALL => {
+            check_and_dispatch!(
+                cage.inotify_rm_watch_syscall,
+                interface::get_int(arg1),
+                interface::get_int(arg2)
+            )
+        }
+        MOUNT_SYSCALL => {
+            check_and_dispatch!(
+                cage.mount_syscall,
+                interface::get_usercstr(region, arg1),
+                interface::get_usercstr(region, arg2),
+                interface::get_usercstr(region, arg3),
+                interface::get_ulong(arg4),
+                interface::get_usercstr_null(region, arg5)
+            )
+        }
+        UMOUNT_SYSCALL => {
+            check_and_dispatch!(cage.umount_syscall, interface::get_usercstr(region, arg1))
+        }
+        CHROOT_SYSCALL => {
+            check_and_dispatch!(cage.chroot_syscall, interface::get_usercstr(region, arg1))
+        }
+        OPENAT_SYSCALL => {
+            check_and_dispatch!(
+                cage.openat_syscall,
+                interface::get_int(arg1),
+                interface::get_usercstr(region, arg2),
+                interface::get_int(arg3),
+                interface::get_uint(arg4)
+            )
+        }
+        UNLINKAT_SYSCALL => {
+            check_and_dispatch!(
+                cage.unlinkat_syscall,
+                interface::get_int(arg1),
+                interface::get_usercstr(region, arg2),
                 interface::get_int(arg3)
             )
         }
+        MKDIRAT_SYSCALL => {
+            check_and_dispatch!(
+                cage.mkdirat_syscall,
+                interface::get_int(arg1),
+                interface::get_usercstr(region, arg2),
+                interface::get_uint(arg3)
+            )
+        }
+        FSTATAT_SYSCALL => {
+            check_and_dispatch!(
+                cage.fstatat_syscall,
+                interface::get_int(arg1),
+                interface::get_usercstr(region, arg2),
+                interface::get_userstatdatastruct(region, arg3),
+                interface::get_int(arg4)
+            )
+        }
+        STATX_SYSCALL => {
+            check_and_dispatch!(
+                cage.statx_syscall,
+                interface::get_int(arg1),
+                interface::get_usercstr(region, arg2),
+                interface::get_int(arg3),
+                interface::get_uint(arg4),
+                interface::get_statxstruct(arg5)
+            )
+        }
+        STAT64_SYSCALL => {
+            check_and_dispatch!(
+                cage.stat64_syscall,
+                interface::get_usercstr(region, arg1),
+                interface::get_stat64struct(arg2)
+            )
+        }
+        FSTAT64_SYSCALL => {
+            check_and_dispatch!(
+                cage.fstat64_syscall,
+                interface::get_int(arg1),
+                interface::get_stat64struct(arg2)
+            )
+        }
+        ISATTY_SYSCALL => {
+            check_and_dispatch!(cage.isatty_syscall, interface::get_int(arg1))
+        }
+        POSIX_OPENPT_SYSCALL => {
+            check_and_dispatch!(cage.posix_openpt_syscall, interface::get_int(arg1))
+        }
+        GRANTPT_SYSCALL => {
+            check_and_dispatch!(cage.grantpt_syscall, interface::get_int(arg1))
+        }
+        UNLOCKPT_SYSCALL => {
+            check_and_dispatch!(cage.unlockpt_syscall, interface::get_int(arg1))
+        }
+        PTSNAME_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg3));
+            check_and_dispatch!(
+                cage.ptsname_syscall,
+                interface::get_int(arg1),
+                interface::get_usermutcbuf(region, arg2, len),
+                Ok::<usize, i32>(len)
+            )
+        }
+        UNAME_SYSCALL => {
+            get_onearg!(interface::get_usermutcbuf(
+                region,
+                arg1,
+                std::mem::size_of::<interface::UtsName>()
+            ));
+            check_and_dispatch!(cage.uname_syscall, interface::get_utsnamestruct(arg1))
+        }
+        SYSINFO_SYSCALL => {
+            get_onearg!(interface::get_usermutcbuf(
+                region,
+                arg1,
+                std::mem::size_of::<interface::SysInfo>()
+            ));
+            check_and_dispatch!(cage.sysinfo_syscall, interface::get_sysinfostruct(arg1))
+        }
+        READLINK_SYSCALL => {
+            let len = get_onearg!(interface::get_usize(arg3));
+            check_and_dispatch!(
+                cage.readlink_syscall,
+                interface::get_usercstr(region, arg1),
+                interface::get_usermutcbuf(region, arg2, len),
+                Ok::<usize, i32>(len)
+            )
+        }
+        RENAMEAT_SYSCALL => {
+            check_and_dispatch!(
+                cage.renameat_syscall,
+                interface::get_int(arg1),
+                interface::get_usercstr(region, arg2),
+                interface::get_int(arg3),
+                interface::get_usercstr(region, arg4)
+            )
+        }
+        LINKAT_SYSCALL => {
+            check_and_dispatch!(
+                cage.linkat_syscall,
+                interface::get_int(arg1),
+                interface::get_usercstr(region, arg2),
+                interface::get_int(arg3),
+                interface::get_usercstr(region, arg4),
+                interface::get_int(arg5)
+            )
+        }
+        SYNC_SYSCALL => {
+            check_and_dispatch!(cage.sync_syscall,)
+        }
+        SYNCFS_SYSCALL => {
+            check_and_dispatch!(cage.syncfs_syscall, interface::get_int(arg1))
+        }
         _ => {
             //unknown syscall
             -1
@@ -939,12 +1813,145 @@ pub extern "C" fn dispatcher(
     }
 }
 
+fn filter_action(action: i32, errno: i32) -> Option<SyscallAction> {
+    match action {
+        FILTER_ALLOW => Some(SyscallAction::Allow),
+        FILTER_LOG => Some(SyscallAction::Log),
+        FILTER_DENY => Some(SyscallAction::Errno(Errno::EPERM as i32)),
+        FILTER_ERRNO => match Errno::from_discriminant(errno) {
+            Ok(_) => Some(SyscallAction::Errno(errno)),
+            Err(()) => None,
+        },
+        FILTER_KILL => Some(SyscallAction::Kill),
+        _ => None,
+    }
+}
+
+// Sets what happens when the cage makes syscall callnum, for the embedder to call before the cage
+// starts running. errno is only looked at for FILTER_ERRNO.
+#[no_mangle]
+pub extern "C" fn lindsetsyscallfilter(cageid: u64, callnum: i32, action: i32, errno: i32) -> i32 {
+    let cage = match interface::cagetable_getref_opt(cageid) {
+        Some(cage) => cage,
+        None => return -(Errno::ESRCH as i32),
+    };
+    match filter_action(action, errno) {
+        Some(action) => {
+            cage.syscall_filter.write().rules.insert(callnum, action);
+            0
+        }
+        None => -(Errno::EINVAL as i32),
+    }
+}
+
+// Sets what happens for any syscall the cage's filter has no rule for, so that an embedder can
+// list the calls it allows and refuse the rest.
+#[no_mangle]
+pub extern "C" fn lindsetsyscallfilterdefault(cageid: u64, action: i32, errno: i32) -> i32 {
+    let cage = match interface::cagetable_getref_opt(cageid) {
+        Some(cage) => cage,
+        None => return -(Errno::ESRCH as i32),
+    };
+    match filter_action(action, errno) {
+        Some(action) => {
+            cage.syscall_filter.write().default = action;
+            0
+        }
+        None => -(Errno::EINVAL as i32),
+    }
+}
+
+// Turns the counting of calls, errors and latencies of every cage's syscalls on or off. What has
+// been counted so far is kept either way, and is read through safeposix::metrics or /proc/lind/stats.
+#[no_mangle]
+pub extern "C" fn lindsetsyscallmetrics(enabled: i32) {
+    metrics::set_syscall_metrics_enabled(enabled != 0);
+}
+
+// Tells rustposix where the cage's memory lies, so that any buffer it passes to a syscall from
+// then on is checked to be within it and refused with EFAULT otherwise. A len of 0 stops checking.
+#[no_mangle]
+pub extern "C" fn lindsetmemoryregion(cageid: u64, base: usize, len: usize) -> i32 {
+    let cage = match interface::cagetable_getref_opt(cageid) {
+        Some(cage) => cage,
+        None => return -(Errno::ESRCH as i32),
+    };
+    if base.checked_add(len).is_none() {
+        return -(Errno::EINVAL as i32);
+    }
+    *cage.memory_region.write() = if len == 0 {
+        None
+    } else {
+        Some(interface::MemoryRegion {
+            base: base,
+            len: len,
+        })
+    };
+    0
+}
+
+// Sets how many fds the cage's select, poll and epoll_wait may be asked about, beyond which they
+// fail with EINVAL. Forked cages start with the limit of their parent.
+#[no_mangle]
+pub extern "C" fn lindsetmaxwatchedfds(cageid: u64, max: i32) -> i32 {
+    let cage = match interface::cagetable_getref_opt(cageid) {
+        Some(cage) => cage,
+        None => return -(Errno::ESRCH as i32),
+    };
+    if max < 1 {
+        return -(Errno::EINVAL as i32);
+    }
+    cage.max_watched_fds
+        .store(max, interface::RustAtomicOrdering::Relaxed);
+    0
+}
+
+// Sets which platform's numbering of address families the sockaddrs cages pass in and get back
+// use: 0 for Linux and 1 for macOS. The host's own is used until this is called.
+#[no_mangle]
+pub extern "C" fn lindsetsockaddrabi(abi: i32) -> i32 {
+    match abi {
+        0 => interface::set_sockaddr_abi(interface::SockaddrAbi::Linux),
+        1 => interface::set_sockaddr_abi(interface::SockaddrAbi::Darwin),
+        _ => return -(Errno::EINVAL as i32),
+    }
+    0
+}
+
+// Sets what uname reports for one of its fields: 0 for sysname, 1 for release, 2 for version and
+// 3 for machine. Values longer than 64 bytes are cut short.
+#[no_mangle]
+pub extern "C" fn lindsetutsname(field: i32, value: *const i8) -> i32 {
+    match interface::get_cstr(Arg {
+        dispatch_cstr: value,
+    }) {
+        Ok(value) => set_uts_field(field, value),
+        Err(e) => e,
+    }
+}
+
+// Registers a hook to be told of every cage created, forked, exec'd or exiting from now on,
+// returning a handle to unregister it with.
+#[no_mangle]
+pub extern "C" fn lindregisterlifecyclehook(hook: LifecycleHook) -> u64 {
+    register_lifecycle_hook(hook)
+}
+
+#[no_mangle]
+pub extern "C" fn lindunregisterlifecyclehook(handle: u64) -> i32 {
+    if unregister_lifecycle_hook(handle) {
+        0
+    } else {
+        -(Errno::EINVAL as i32)
+    }
+}
+
+// Wakes every syscall of the cage that is blocked, and any that would block from now on, and has
+// them return ECANCELED so that the threads that made them can exit
 #[no_mangle]
 pub extern "C" fn lindcancelinit(cageid: u64) {
     let cage = interface::cagetable_getref(cageid);
-    cage.cancelstatus
-        .store(true, interface::RustAtomicOrdering::Relaxed);
-    cage.signalcvs();
+    cage.cancel_registry.cancel();
 }
 
 #[no_mangle]
@@ -995,12 +2002,44 @@ fn cleartmp(init: bool) {
     }
 }
 
+// Hands the runtime the calling thread's next signal to deliver, see Cage::next_signal. The
+// runtime calls this when a syscall comes back with EINTR or ERESTART and whenever it is pulled
+// out of guest code by a host signal, runs the handler in action for each signal returned, passing
+// it info under SA_SIGINFO, and then calls lindsigreturn with oldmask. Returns 0 when nothing is
+// left to deliver. Each pointer has to be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn lindnextsignal(
+    cageid: u64,
+    action: *mut interface::SigactionStruct,
+    info: *mut interface::SiginfoStruct,
+    oldmask: *mut interface::SigsetType,
+) -> i32 {
+    let cage = match interface::cagetable_getref_opt(cageid) {
+        Some(cage) => cage,
+        None => return -(Errno::ESRCH as i32),
+    };
+    if action.is_null() || info.is_null() || oldmask.is_null() {
+        return -(Errno::EFAULT as i32);
+    }
+    cage.next_signal(&mut *action, &mut *info, &mut *oldmask)
+}
+
+#[no_mangle]
+pub extern "C" fn lindsigreturn(cageid: u64, oldmask: interface::SigsetType) -> i32 {
+    let cage = match interface::cagetable_getref_opt(cageid) {
+        Some(cage) => cage,
+        None => return -(Errno::ESRCH as i32),
+    };
+    cage.signal_return(oldmask);
+    0
+}
+
 #[no_mangle]
 pub extern "C" fn lindgetsighandler(cageid: u64, signo: i32) -> u32 {
     let cage = interface::cagetable_getref(cageid);
     let pthreadid = interface::get_pthreadid();
     let sigset = cage.sigset.get(&pthreadid).unwrap(); // these lock sigset dashmaps for concurrency
-    let pendingset = cage.sigset.get(&pthreadid).unwrap();
+    let pendingset = cage.pendingsigset.get(&pthreadid).unwrap();
 
     if !interface::lind_sigismember(sigset.load(interface::RustAtomicOrdering::Relaxed), signo) {
         return match cage.signalhandler.get(&signo) {
@@ -1010,8 +2049,8 @@ pub extern "C" fn lindgetsighandler(cageid: u64, signo: i32) -> u32 {
             None => 0, // if we dont have a handler return 0
         };
     } else {
-        let mutpendingset = sigset.load(interface::RustAtomicOrdering::Relaxed);
-        sigset.store(
+        let mutpendingset = pendingset.load(interface::RustAtomicOrdering::Relaxed);
+        pendingset.store(
             interface::lind_sigaddset(mutpendingset, signo),
             interface::RustAtomicOrdering::Relaxed,
         );
@@ -1025,71 +2064,149 @@ pub extern "C" fn lindrustinit(verbosity: isize) {
     let _ = interface::VERBOSE.set(verbosity); //assigned to suppress unused result warning
     interface::cagetable_init();
     load_fs();
+    start_metadata_flusher();
     incref_root();
     incref_root();
 
     let utilcage = Cage {
         cageid: 0,
-        cwd: interface::RustLock::new(interface::RustRfc::new(interface::RustPathBuf::from("/"))),
+        cwd: interface::RustRfc::new(interface::RustLock::new(interface::RustRfc::new(
+            interface::RustPathBuf::from("/"),
+        ))),
         parent: 0,
+        pgid: interface::RustAtomicU64::new(0),
+        sid: interface::RustAtomicU64::new(0),
+        nice: interface::RustAtomicI32::new(0),
+        cpu_affinity: interface::RustHashMap::new(),
         filedescriptortable: init_fdtable(),
-        cancelstatus: interface::RustAtomicBool::new(false),
+        fdtable_users: interface::RustRfc::new(interface::RustAtomicUsize::new(1)),
+        epoll_registrations: interface::RustRfc::new(interface::RustHashMap::new()),
+        cancel_registry: CancelRegistry::new(),
         getgid: interface::RustAtomicI32::new(-1),
         getuid: interface::RustAtomicI32::new(-1),
         getegid: interface::RustAtomicI32::new(-1),
         geteuid: interface::RustAtomicI32::new(-1),
+        cred: interface::RustLock::new(Credentials::new(DEFAULT_UID, DEFAULT_GID)),
         rev_shm: interface::Mutex::new(vec![]),
         mutex_table: interface::RustLock::new(vec![]),
         cv_table: interface::RustLock::new(vec![]),
         sem_table: interface::RustHashMap::new(),
         thread_table: interface::RustHashMap::new(),
-        signalhandler: interface::RustHashMap::new(),
+        signalhandler: interface::RustRfc::new(interface::RustHashMap::new()),
         sigset: interface::RustHashMap::new(),
         pendingsigset: interface::RustHashMap::new(),
+        sharedpendingsigset: interface::RustAtomicU64::new(0),
+        sigqueue: interface::Mutex::new(interface::RustBTreeMap::new()),
         main_threadid: interface::RustAtomicU64::new(0),
         interval_timer: interface::IntervalTimer::new(0),
+        cpu_usage: interface::CpuUsage::new(),
+        memory_region: interface::RustLock::new(None),
+        posix_timers: interface::RustLock::new(vec![]),
+        file_mappings: interface::Mutex::new(vec![]),
+        rootdir: interface::RustRfc::new(interface::RustLock::new(interface::RustRfc::new(
+            interface::RustPathBuf::from("/"),
+        ))),
+        fs_users: interface::RustRfc::new(interface::RustAtomicUsize::new(1)),
+        environ: interface::RustLock::new(vec![]),
+        syscall_filter: interface::RustLock::new(SyscallFilter::new()),
+        quota: interface::RustLock::new(None),
+        zombies: interface::Mutex::new(vec![]),
+        zombie_cv: interface::Condvar::new(),
+        select_epoll: interface::Mutex::new(interface::KernelEpollCache::new()),
+        max_watched_fds: interface::RustAtomicI32::new(MAX_WATCHED_FDS),
+        context: context::current_context(),
     };
 
     interface::cagetable_insert(0, utilcage);
+    fire_lifecycle_hooks(LIFECYCLE_CREATE, 0, 0, 0);
 
     //init cage is its own parent
     let initcage = Cage {
         cageid: 1,
-        cwd: interface::RustLock::new(interface::RustRfc::new(interface::RustPathBuf::from("/"))),
+        cwd: interface::RustRfc::new(interface::RustLock::new(interface::RustRfc::new(
+            interface::RustPathBuf::from("/"),
+        ))),
         parent: 1,
+        pgid: interface::RustAtomicU64::new(1),
+        sid: interface::RustAtomicU64::new(1),
+        nice: interface::RustAtomicI32::new(0),
+        cpu_affinity: interface::RustHashMap::new(),
         filedescriptortable: init_fdtable(),
-        cancelstatus: interface::RustAtomicBool::new(false),
+        fdtable_users: interface::RustRfc::new(interface::RustAtomicUsize::new(1)),
+        epoll_registrations: interface::RustRfc::new(interface::RustHashMap::new()),
+        cancel_registry: CancelRegistry::new(),
         getgid: interface::RustAtomicI32::new(-1),
         getuid: interface::RustAtomicI32::new(-1),
         getegid: interface::RustAtomicI32::new(-1),
         geteuid: interface::RustAtomicI32::new(-1),
+        cred: interface::RustLock::new(Credentials::new(DEFAULT_UID, DEFAULT_GID)),
         rev_shm: interface::Mutex::new(vec![]),
         mutex_table: interface::RustLock::new(vec![]),
         cv_table: interface::RustLock::new(vec![]),
         sem_table: interface::RustHashMap::new(),
         thread_table: interface::RustHashMap::new(),
-        signalhandler: interface::RustHashMap::new(),
+        signalhandler: interface::RustRfc::new(interface::RustHashMap::new()),
         sigset: interface::RustHashMap::new(),
         pendingsigset: interface::RustHashMap::new(),
+        sharedpendingsigset: interface::RustAtomicU64::new(0),
+        sigqueue: interface::Mutex::new(interface::RustBTreeMap::new()),
         main_threadid: interface::RustAtomicU64::new(0),
         interval_timer: interface::IntervalTimer::new(1),
+        cpu_usage: interface::CpuUsage::new(),
+        memory_region: interface::RustLock::new(None),
+        posix_timers: interface::RustLock::new(vec![]),
+        file_mappings: interface::Mutex::new(vec![]),
+        rootdir: interface::RustRfc::new(interface::RustLock::new(interface::RustRfc::new(
+            interface::RustPathBuf::from("/"),
+        ))),
+        fs_users: interface::RustRfc::new(interface::RustAtomicUsize::new(1)),
+        environ: interface::RustLock::new(vec![]),
+        syscall_filter: interface::RustLock::new(SyscallFilter::new()),
+        quota: interface::RustLock::new(None),
+        zombies: interface::Mutex::new(vec![]),
+        zombie_cv: interface::Condvar::new(),
+        select_epoll: interface::Mutex::new(interface::KernelEpollCache::new()),
+        max_watched_fds: interface::RustAtomicI32::new(MAX_WATCHED_FDS),
+        context: context::current_context(),
     };
     interface::cagetable_insert(1, initcage);
+    fire_lifecycle_hooks(LIFECYCLE_CREATE, 1, 1, 0);
     // make sure /tmp is clean
     cleartmp(true);
+    // /tmp is scratch space, so keep it in memory rather than in the metadata log
+    let cage = interface::cagetable_getref(0);
+    cage.mount_syscall("tmpfs", "/tmp", "tmpfs", 0, None);
+    // so is /dev/shm, which backs posix shared memory
+    cage.mkdir_syscall(SHM_OPEN_DIR, S_IRWXA);
+    cage.mount_syscall("tmpfs", SHM_OPEN_DIR, "tmpfs", 0, None);
+    // procfs is generated on demand, and only needs a directory to cover
+    cage.mkdir_syscall(PROCMOUNTPOINT, S_IRWXA);
+    cage.mount_syscall(
+        "proc",
+        PROCMOUNTPOINT,
+        "proc",
+        MS_NOSUID | MS_NODEV | MS_NOEXEC,
+        None,
+    );
 }
 
 #[no_mangle]
 pub extern "C" fn lindrustfinalize() {
-    // remove any open domain socket inodes
+    // remove the domain socket paths bound during this run, closed or not
     for truepath in NET_METADATA.get_domainsock_paths() {
         remove_domain_sock(truepath);
     }
+    // the connections of this run are gone along with it, so no port waits on them
+    NET_METADATA.time_wait_ports.clear();
 
     // clear /tmp folder
     cleartmp(false);
     interface::cagetable_clear();
-    // if we get here, persist and delete log
+    // nothing under a non-persistent mount should outlive the process
+    umount_all();
+    stop_metadata_flusher();
+    // if we get here, persist and delete log, once any compaction in flight has finished with it
+    let _compacting = LOG_COMPACT_LOCK.lock();
     persist_metadata(&FS_METADATA);
     if interface::pathexists(LOGFILENAME.to_string()) {
         // remove file if it exists, assigning it to nothing to avoid the compiler yelling about unused result
@@ -0,0 +1,217 @@
+// Snapshots of cage state for debugging, such as working out where a hung workload is stuck
+#![allow(dead_code)]
+
+use super::cage::*;
+use crate::interface;
+
+//Everything is read with try_lock and friends, so that a dump can be taken while syscalls are
+//blocked holding locks, which is exactly when one is wanted. Whatever is locked shows up as None.
+#[derive(Debug, Clone)]
+pub struct CageDump {
+    pub cageid: u64,
+    pub parent: u64,
+    pub pgid: u64,
+    pub sid: u64,
+    pub cwd: Option<interface::RustPathBuf>,
+    pub threads: Vec<ThreadDump>,
+    pub sharedpending: u64,        // signals pending on the cage as a whole
+    pub children: Vec<u64>,        // live cages whose parent this is
+    pub zombies: Option<Vec<u64>>, // exited children not yet waited for
+    pub fds: Vec<FdDump>,          // open descriptors, lowest first
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadDump {
+    pub threadid: u64,
+    pub blocked: u64, // signal masks, a bit for each signal
+    pub pending: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct FdDump {
+    pub fd: i32,
+    pub desc: Option<FdDescDump>, // None if a syscall has the descriptor locked
+}
+
+#[derive(Debug, Clone)]
+pub struct FdDescDump {
+    pub flags: i32,
+    pub statusflags: i32,
+    pub kind: FdKindDump,
+}
+
+#[derive(Debug, Clone)]
+pub enum FdKindDump {
+    File {
+        inode: usize,
+        position: usize,
+    },
+    Stream {
+        stream: i32,
+        position: usize,
+    },
+    Socket {
+        id: usize, // the same for every descriptor of the socket
+        domain: i32,
+        rawfd: i32,
+        handle: Option<SocketDump>, // None if a syscall has the socket locked
+    },
+    Pipe {
+        id: usize, // the same for both ends of the pipe
+    },
+    Epoll {
+        registered: Vec<i32>,
+    },
+    Inotify,
+    Mqueue,
+}
+
+#[derive(Debug, Clone)]
+pub struct SocketDump {
+    pub socktype: i32,
+    pub protocol: i32,
+    pub state: ConnState,
+    pub localaddr: Option<interface::GenSockaddr>,
+    pub remoteaddr: Option<interface::GenSockaddr>,
+    pub errno: i32,
+}
+
+fn dump_fd(filedesc_enum: &FileDescriptor) -> FdDescDump {
+    let (flags, statusflags, kind) = match filedesc_enum {
+        FileDescriptor::File(f) => (
+            f.flags,
+            f.statusflags.get(),
+            FdKindDump::File {
+                inode: f.inode,
+                position: f.position,
+            },
+        ),
+        FileDescriptor::Stream(f) => (
+            f.flags,
+            f.statusflags.get(),
+            FdKindDump::Stream {
+                stream: f.stream,
+                position: f.position,
+            },
+        ),
+        FileDescriptor::Socket(f) => {
+            let handle = f.handle.try_read().map(|sockhandle| SocketDump {
+                socktype: sockhandle.socktype,
+                protocol: sockhandle.protocol,
+                state: sockhandle.state.clone(),
+                localaddr: sockhandle.localaddr,
+                remoteaddr: sockhandle.remoteaddr,
+                errno: sockhandle.errno,
+            });
+            (
+                f.flags,
+                f.statusflags.get(),
+                FdKindDump::Socket {
+                    id: interface::RustRfc::as_ptr(&f.handle) as usize,
+                    domain: f.domain,
+                    rawfd: f.rawfd,
+                    handle: handle,
+                },
+            )
+        }
+        FileDescriptor::Pipe(f) => (
+            f.flags,
+            f.statusflags.get(),
+            FdKindDump::Pipe {
+                id: interface::RustRfc::as_ptr(&f.pipe) as usize,
+            },
+        ),
+        FileDescriptor::Epoll(f) => {
+            let mut registered: Vec<i32> = f.registered_fds.iter().map(|e| *e.key()).collect();
+            registered.sort_unstable();
+            (
+                f.flags,
+                f.statusflags.get(),
+                FdKindDump::Epoll {
+                    registered: registered,
+                },
+            )
+        }
+        FileDescriptor::Inotify(f) => (f.flags, f.statusflags.get(), FdKindDump::Inotify),
+        FileDescriptor::Mqueue(f) => (f.flags, f.statusflags.get(), FdKindDump::Mqueue),
+    };
+    FdDescDump {
+        flags: flags,
+        statusflags: statusflags,
+        kind: kind,
+    }
+}
+
+impl Cage {
+    //the open descriptors alone, lowest first
+    pub fn fd_dumps(&self) -> Vec<FdDump> {
+        let mut fds = vec![];
+        for (fd, entry) in self.filedescriptortable.iter().enumerate() {
+            match entry.try_read() {
+                Some(guard) => {
+                    if let Some(filedesc_enum) = &*guard {
+                        fds.push(FdDump {
+                            fd: fd as i32,
+                            desc: Some(dump_fd(filedesc_enum)),
+                        });
+                    }
+                }
+                None => fds.push(FdDump {
+                    fd: fd as i32,
+                    desc: None,
+                }),
+            }
+        }
+        fds
+    }
+
+    pub fn debug_dump(&self) -> CageDump {
+        let mut threads: Vec<ThreadDump> = self
+            .sigset
+            .iter()
+            .map(|entry| ThreadDump {
+                threadid: *entry.key(),
+                blocked: entry.value().load(interface::RustAtomicOrdering::Relaxed),
+                pending: self
+                    .pendingsigset
+                    .get(entry.key())
+                    .map(|pending| pending.load(interface::RustAtomicOrdering::Relaxed))
+                    .unwrap_or(0),
+            })
+            .collect();
+        threads.sort_by_key(|thread| thread.threadid);
+
+        let children = (0..interface::MAXCAGEID as u64)
+            .filter(|&cageid| cageid != self.cageid)
+            .filter_map(interface::cagetable_getref_opt)
+            .filter(|cage| cage.parent == self.cageid)
+            .map(|cage| cage.cageid)
+            .collect();
+
+        CageDump {
+            cageid: self.cageid,
+            parent: self.parent,
+            pgid: self.pgid.load(interface::RustAtomicOrdering::Relaxed),
+            sid: self.sid.load(interface::RustAtomicOrdering::Relaxed),
+            cwd: self.cwd.try_read().map(|cwd| (**cwd).clone()),
+            threads: threads,
+            sharedpending: self
+                .sharedpendingsigset
+                .load(interface::RustAtomicOrdering::Relaxed),
+            children: children,
+            zombies: self
+                .zombies
+                .try_lock()
+                .map(|zombies| zombies.iter().map(|zombie| zombie.cageid).collect()),
+            fds: self.fd_dumps(),
+        }
+    }
+}
+
+//a dump of every live cage, by cage id
+pub fn dump_all_cages() -> Vec<CageDump> {
+    (0..interface::MAXCAGEID as u64)
+        .filter_map(interface::cagetable_getref_opt)
+        .map(|cage| cage.debug_dump())
+        .collect()
+}
@@ -0,0 +1,167 @@
+// Signal-driven I/O, for sockets and pipes with O_ASYNC set
+//
+// Setting O_ASYNC hands the open file description to a helper thread, which looks at it again
+// whenever something may have made a descriptor ready. When it becomes readable, or writable
+// after it was full, the owner set by F_SETOWN is sent the signal set by F_SETSIG, or SIGIO if
+// none was. A description is dropped once O_ASYNC is cleared or the descriptor it was set
+// through is closed.
+
+use super::cage::*;
+use super::syscalls::net_constants::*;
+use super::syscalls::sys_constants::*;
+use crate::interface;
+
+use std::collections::HashMap;
+
+//kernel sockets only wake the helper when they become readable, so it also looks this often
+const FASYNC_RECHECK_INTERVAL: interface::RustDuration = interface::RustDuration::from_millis(100);
+
+#[derive(Clone)]
+struct AsyncWatch {
+    cageid: u64,
+    fd: i32,
+    statusflags: interface::RustRfc<StatusFlags>,
+    lastmask: i16, // POLLIN and POLLOUT, as they were when last looked at
+}
+
+//watched open file descriptions, by the address of their status flags
+static ASYNC_WATCHES: interface::RustLazyGlobal<interface::Mutex<HashMap<usize, AsyncWatch>>> =
+    interface::RustLazyGlobal::new(|| {
+        interface::helper_thread(fasync_loop);
+        interface::Mutex::new(HashMap::new())
+    });
+
+//start watching the open file description of fd. It is taken to be writable already, so that
+//only space freeing up after it was full raises a signal, but data already queued raises one
+pub fn fasync_register(cageid: u64, fd: i32, statusflags: &interface::RustRfc<StatusFlags>) {
+    let key = interface::RustRfc::as_ptr(statusflags) as usize;
+    ASYNC_WATCHES
+        .lock()
+        .entry(key)
+        .or_insert_with(|| AsyncWatch {
+            cageid: cageid,
+            fd: fd,
+            statusflags: statusflags.clone(),
+            lastmask: POLLOUT,
+        });
+    interface::notify_readiness();
+}
+
+fn fasync_loop() {
+    loop {
+        let generation = interface::readiness_generation();
+        //the watches are looked at without holding the table, since fcntl registers with the
+        //descriptor locked and looking at one takes its lock
+        let watches: Vec<(usize, AsyncWatch)> = ASYNC_WATCHES
+            .lock()
+            .iter()
+            .map(|(key, watch)| (*key, watch.clone()))
+            .collect();
+        let mut rawfds = vec![];
+        let mut updates = vec![];
+        for (key, watch) in watches {
+            let newmask = fasync_check(&watch, &mut rawfds);
+            if let Some(mask) = newmask {
+                let raised = mask & !watch.lastmask;
+                if raised != 0 {
+                    fasync_signal(&watch.statusflags, raised);
+                }
+            }
+            updates.push((key, newmask));
+        }
+
+        let mut table = ASYNC_WATCHES.lock();
+        for (key, newmask) in updates {
+            match newmask {
+                Some(mask) => {
+                    if let Some(watch) = table.get_mut(&key) {
+                        watch.lastmask = mask;
+                    }
+                }
+                None => {
+                    table.remove(&key);
+                }
+            }
+        }
+        drop(table);
+
+        interface::wait_for_readiness(generation, &rawfds, FASYNC_RECHECK_INTERVAL);
+    }
+}
+
+//whether the description is readable and writable now, or None once it is no longer watched
+fn fasync_check(watch: &AsyncWatch, rawfds: &mut Vec<i32>) -> Option<i16> {
+    if watch.statusflags.get() & O_ASYNC == 0 {
+        return None;
+    }
+    let cage = interface::cagetable_getref_opt(watch.cageid)?;
+    //the helper serves every machine, so it looks at each description from within its cage's
+    let _context = super::context::enter_context(cage.context);
+    let checkedfd = cage.get_filedescriptor(watch.fd).ok()?;
+    //a syscall has the descriptor, so leave it be until next time
+    let guard = match checkedfd.try_read() {
+        Some(guard) => guard,
+        None => return Some(watch.lastmask),
+    };
+    let statusflags = match &*guard {
+        Some(FileDescriptor::Socket(sockfdobj)) => {
+            if sockfdobj.rawfd >= 0 {
+                rawfds.push(sockfdobj.rawfd);
+            }
+            &sockfdobj.statusflags
+        }
+        Some(FileDescriptor::Pipe(pipefdobj)) => &pipefdobj.statusflags,
+        _ => return None,
+    };
+    //the descriptor was closed, and its number may have gone to another open since
+    if !interface::RustRfc::ptr_eq(statusflags, &watch.statusflags) {
+        return None;
+    }
+    drop(guard);
+
+    let mut pollfd = [interface::PollStruct {
+        fd: watch.fd,
+        events: POLLIN | POLLOUT,
+        revents: 0,
+    }];
+    if cage.poll_syscall(&mut pollfd, Some(interface::RustDuration::ZERO)) < 0 {
+        return Some(watch.lastmask);
+    }
+    Some(pollfd[0].revents & (POLLIN | POLLOUT))
+}
+
+//send the owner the signal for the description, if it has an owner. A signal set by F_SETSIG
+//says whether input or output became possible
+fn fasync_signal(statusflags: &StatusFlags, band: i16) {
+    let owner = statusflags.owner();
+    if owner == 0 {
+        return;
+    }
+    let info = match statusflags.sig() {
+        0 => interface::SiginfoStruct {
+            si_signo: SIGIO,
+            si_code: SI_KERNEL,
+            ..Default::default()
+        },
+        sig => interface::SiginfoStruct {
+            si_signo: sig,
+            si_code: if band & POLLIN != 0 {
+                POLL_IN
+            } else {
+                POLL_OUT
+            },
+            ..Default::default()
+        },
+    };
+    if owner > 0 {
+        if let Some(cage) = interface::cagetable_getref_opt(owner as u64) {
+            cage.queue_signal(info);
+        }
+    } else {
+        for cage in interface::cagetable_getrefs() {
+            if cage.pgid.load(interface::RustAtomicOrdering::Relaxed) == -owner as u64 {
+                cage.queue_signal(info);
+            }
+        }
+    }
+}
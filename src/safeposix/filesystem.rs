@@ -7,23 +7,128 @@ use super::syscalls::sys_constants::*;
 use crate::interface;
 
 use super::cage::Cage;
+use super::context::ContextGlobal;
+use super::inotify::InotifyWatch;
+use super::procfs::{procfs_refresh, procfs_unmount, ProcNode, PROCMOUNTPOINT};
+use super::quota::{quota_release_inode, DiskQuota};
 
 pub const METADATAFILENAME: &str = "lind.metadata";
 
 pub const LOGFILENAME: &str = "lind.md.log";
 
-pub static LOGMAP: interface::RustLazyGlobal<
+//while the log is being compacted, entries from before the new snapshot live here
+pub const OLDLOGFILENAME: &str = "lind.md.log.old";
+
+//metadata that couldn't be read back is moved here, so it can be looked at after we start over
+pub const CORRUPTMETADATAFILENAME: &str = "lind.metadata.corrupt";
+
+//The filesystem of one lind machine, see context.rs. Everything here is keyed by inode number or
+//tied to the log, so each machine has its own
+pub struct FsContext {
+    metadata: interface::RustLazyGlobal<interface::RustRfc<FilesystemMetadata>>,
+    logmap: interface::RustRfc<interface::RustLock<Option<interface::EmulatedFileMap>>>,
+    fileobjects: FileObjectTable,
+    seals: interface::RustHashMap<usize, i32>,
+    leases: interface::RustHashMap<usize, Vec<FileLease>>,
+    mounts: interface::RustLazyGlobal<MountTable>,
+    dentries: interface::RustHashMap<interface::RustPathBuf, DentryCacheEntry>,
+    logbatcher: LogBatcher,
+    compacting: interface::RustAtomicBool,
+    compactlock: interface::Mutex<()>,
+    persistlock: interface::Mutex<()>,
+    flusher: MetadataFlusher,
+    readonly: interface::RustAtomicBool,
+    dentrygeneration: interface::RustAtomicU64,
+    pub(super) inotifywatches: interface::RustHashMap<usize, Vec<InotifyWatch>>,
+    pub(super) quotaowners: interface::RustHashMap<usize, interface::RustRfc<DiskQuota>>,
+    pub(super) procnodes: interface::RustHashMap<usize, ProcNode>,
+    pub(super) procrefreshlock: interface::Mutex<()>,
+}
+
+impl FsContext {
+    pub fn new() -> FsContext {
+        FsContext {
+            //we want to check if fs exists before doing a blank init, but not for now
+            metadata: interface::RustLazyGlobal::new(|| {
+                interface::RustRfc::new(FilesystemMetadata::init_fs_metadata())
+            }),
+            logmap: interface::RustRfc::new(interface::RustLock::new(None)),
+            fileobjects: interface::RustHashMap::new(),
+            seals: interface::RustHashMap::new(),
+            leases: interface::RustHashMap::new(),
+            mounts: interface::RustLazyGlobal::new(MountTable::new),
+            dentries: interface::RustHashMap::new(),
+            logbatcher: LogBatcher {
+                pending: interface::Mutex::new(PendingLog {
+                    entries: Vec::new(),
+                    queuedgroups: 0,
+                    committedgroups: 0,
+                    committing: false,
+                }),
+                committed: interface::Condvar::new(),
+            },
+            compacting: interface::RustAtomicBool::new(false),
+            compactlock: interface::Mutex::new(()),
+            persistlock: interface::Mutex::new(()),
+            flusher: MetadataFlusher {
+                stop: interface::Mutex::new(false),
+                wakeup: interface::Condvar::new(),
+                handle: interface::Mutex::new(None),
+            },
+            readonly: interface::RustAtomicBool::new(false),
+            dentrygeneration: interface::RustAtomicU64::new(0),
+            inotifywatches: interface::RustHashMap::new(),
+            quotaowners: interface::RustHashMap::new(),
+            procnodes: interface::RustHashMap::new(),
+            procrefreshlock: interface::Mutex::new(()),
+        }
+    }
+}
+
+pub static LOGMAP: ContextGlobal<
     interface::RustRfc<interface::RustLock<Option<interface::EmulatedFileMap>>>,
-> = interface::RustLazyGlobal::new(|| interface::RustRfc::new(interface::RustLock::new(None)));
+> = ContextGlobal::new(|context| &context.fs.logmap);
 
-pub static FS_METADATA: interface::RustLazyGlobal<interface::RustRfc<FilesystemMetadata>> =
+//Once the log holds more than this many bytes of entries it is folded into a fresh metadata
+//snapshot in the background. The log only grows between compactions, so this bounds its size.
+pub const DEFAULT_LOG_COMPACT_THRESHOLD: usize = 8 * 1024 * 1024;
+pub static LOG_COMPACT_THRESHOLD: interface::RustLazyGlobal<interface::RustAtomicUsize> =
     interface::RustLazyGlobal::new(|| {
-        interface::RustRfc::new(FilesystemMetadata::init_fs_metadata())
-    }); //we want to check if fs exists before doing a blank init, but not for now
+        interface::RustAtomicUsize::new(DEFAULT_LOG_COMPACT_THRESHOLD)
+    });
+static LOG_COMPACTING: ContextGlobal<interface::RustAtomicBool> =
+    ContextGlobal::new(|context| &context.fs.compacting);
+//held for the whole of a compaction, and by anyone who needs the log and snapshot to stay put
+pub static LOG_COMPACT_LOCK: ContextGlobal<interface::Mutex<()>> =
+    ContextGlobal::new(|context| &context.fs.compactlock);
+static PERSIST_LOCK: ContextGlobal<interface::Mutex<()>> =
+    ContextGlobal::new(|context| &context.fs.persistlock);
+
+pub static FS_METADATA: ContextGlobal<interface::RustRfc<FilesystemMetadata>> =
+    ContextGlobal::new(|context| &*context.fs.metadata);
+
+pub type FileObjectTable = interface::RustHashMap<usize, interface::EmulatedFile>;
+pub static FILEOBJECTTABLE: ContextGlobal<FileObjectTable> =
+    ContextGlobal::new(|context| &context.fs.fileobjects);
+
+//the seals of each memfd by inode number; files that aren't memfds can't be sealed
+pub static FILE_SEALS: ContextGlobal<interface::RustHashMap<usize, i32>> =
+    ContextGlobal::new(|context| &context.fs.seals);
+
+//a lease on a file, held by one open file description of a cage
+#[derive(Debug, Clone)]
+pub struct FileLease {
+    pub holder: usize, // the open file description, by the address of its status flags
+    pub cageid: u64,
+    pub leasetype: i32, // F_RDLCK or F_WRLCK
+    //once an open conflicts with the lease, what it is being broken down to and when it is
+    //broken by force if the holder hasn't given it up by then
+    pub breaking: Option<(i32, interface::RustInstant)>,
+}
 
-type FileObjectTable = interface::RustHashMap<usize, interface::EmulatedFile>;
-pub static FILEOBJECTTABLE: interface::RustLazyGlobal<FileObjectTable> =
-    interface::RustLazyGlobal::new(|| interface::RustHashMap::new());
+//the leases on each file by inode number; only regular files can be leased
+pub static FILE_LEASES: ContextGlobal<interface::RustHashMap<usize, Vec<FileLease>>> =
+    ContextGlobal::new(|context| &context.fs.leases);
 
 #[derive(interface::SerdeSerialize, interface::SerdeDeserialize, Debug)]
 pub enum Inode {
@@ -46,6 +151,9 @@ pub struct GenericInode {
     pub atime: u64,
     pub ctime: u64,
     pub mtime: u64,
+    #[serde(default)]
+    //kept last, so inodes persisted before birth times were kept read back with 0, meaning unknown
+    pub btime: u64,
 }
 
 #[derive(interface::SerdeSerialize, interface::SerdeDeserialize, Debug)]
@@ -62,6 +170,9 @@ pub struct DeviceInode {
     pub ctime: u64,
     pub mtime: u64,
     pub dev: DevNo,
+    #[serde(default)]
+    //kept last, so inodes persisted before birth times were kept read back with 0, meaning unknown
+    pub btime: u64,
 }
 
 #[derive(interface::SerdeSerialize, interface::SerdeDeserialize, Debug)]
@@ -76,6 +187,9 @@ pub struct SocketInode {
     pub atime: u64,
     pub ctime: u64,
     pub mtime: u64,
+    #[serde(default)]
+    //kept last, so inodes persisted before birth times were kept read back with 0, meaning unknown
+    pub btime: u64,
 }
 
 #[derive(interface::SerdeSerialize, interface::SerdeDeserialize, Debug)]
@@ -91,7 +205,118 @@ pub struct DirectoryInode {
     pub atime: u64,
     pub ctime: u64,
     pub mtime: u64,
-    pub filename_to_inode_dict: interface::RustHashMap<String, usize>,
+    pub filename_to_inode_dict: DirectoryIndex,
+    #[serde(default)]
+    //kept last, so inodes persisted before birth times were kept read back with 0, meaning unknown
+    pub btime: u64,
+}
+
+//A directory's entries, looked up by name and listed in the order they were created. Each entry
+//is given a cookie that stays the same for as long as it exists, and directory offsets refer to
+//cookies, so reading a directory that changes meanwhile neither skips nor repeats the entries
+//that stay put.
+#[derive(Debug, Default)]
+pub struct DirectoryIndex {
+    byname: interface::RustBTreeMap<String, (usize, u64)>,
+    bycookie: interface::RustBTreeMap<u64, (String, usize)>,
+    nextcookie: u64,
+}
+
+impl DirectoryIndex {
+    pub fn new() -> DirectoryIndex {
+        DirectoryIndex::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.byname.get(name).map(|(inodenum, _)| *inodenum)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.byname.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.byname.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.byname.is_empty()
+    }
+
+    //pointing an existing name at a different inode keeps its place in the listing
+    pub fn insert(&mut self, name: String, inodenum: usize) -> Option<usize> {
+        if let Some((oldinodenum, cookie)) = self.byname.get_mut(&name) {
+            let old = std::mem::replace(oldinodenum, inodenum);
+            self.bycookie.get_mut(cookie).unwrap().1 = inodenum;
+            return Some(old);
+        }
+        let cookie = self.nextcookie;
+        self.nextcookie += 1;
+        self.bycookie.insert(cookie, (name.clone(), inodenum));
+        self.byname.insert(name, (inodenum, cookie));
+        None
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<usize> {
+        let (inodenum, cookie) = self.byname.remove(name)?;
+        self.bycookie.remove(&cookie);
+        Some(inodenum)
+    }
+
+    //entries in listing order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.bycookie
+            .values()
+            .map(|(name, inodenum)| (name.as_str(), *inodenum))
+    }
+
+    //entries in listing order with their cookies, starting from the first with a cookie of at
+    //least cookie
+    pub fn iter_from(&self, cookie: u64) -> impl Iterator<Item = (u64, &str, usize)> {
+        self.bycookie
+            .range(cookie..)
+            .map(|(cookie, (name, inodenum))| (*cookie, name.as_str(), *inodenum))
+    }
+
+    //the offset just past the last entry
+    pub fn end_cookie(&self) -> u64 {
+        self.nextcookie
+    }
+}
+
+//stored as a plain map from name to inode number, in listing order so a reload lists entries the
+//same way
+impl interface::SerdeSerialize for DirectoryIndex {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de> interface::SerdeDeserialize<'de> for DirectoryIndex {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IndexVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for IndexVisitor {
+            type Value = DirectoryIndex;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a map from file names to inode numbers")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<DirectoryIndex, A::Error> {
+                let mut index = DirectoryIndex::new();
+                while let Some((name, inodenum)) = map.next_entry::<String, usize>()? {
+                    index.insert(name, inodenum);
+                }
+                Ok(index)
+            }
+        }
+
+        deserializer.deserialize_map(IndexVisitor)
+    }
 }
 
 #[derive(interface::SerdeSerialize, interface::SerdeDeserialize, Debug)]
@@ -101,11 +326,8 @@ pub struct FilesystemMetadata {
     pub inodetable: interface::RustHashMap<usize, Inode>,
 }
 
-pub fn init_filename_to_inode_dict(
-    curinode: usize,
-    parentinode: usize,
-) -> interface::RustHashMap<String, usize> {
-    let retval = interface::RustHashMap::new();
+pub fn init_filename_to_inode_dict(curinode: usize, parentinode: usize) -> DirectoryIndex {
+    let mut retval = DirectoryIndex::new();
     retval.insert(".".to_string(), curinode);
     retval.insert("..".to_string(), parentinode);
     retval
@@ -132,6 +354,7 @@ impl FilesystemMetadata {
             refcount: 0,
             atime: time,
             ctime: time,
+            btime: time,
             mtime: time,
             filename_to_inode_dict: init_filename_to_inode_dict(
                 ROOTDIRECTORYINODE,
@@ -145,16 +368,34 @@ impl FilesystemMetadata {
         retval
     }
 
-    // Read file, and deserialize CBOR to FS METADATA
+    // Read file, and deserialize it to FS METADATA in whichever format it was written
     pub fn init_fs_metadata() -> FilesystemMetadata {
         // Read CBOR from file
         if interface::pathexists(METADATAFILENAME.to_string()) {
-            let metadata_fileobj = interface::openmetadata(METADATAFILENAME.to_string()).unwrap();
-            let metadatabytes = metadata_fileobj.readfile_to_new_bytes().unwrap();
-            metadata_fileobj.close().unwrap();
+            let metadatabytes = interface::openmetadata(METADATAFILENAME.to_string()).and_then(
+                |metadata_fileobj| {
+                    let metadatabytes = metadata_fileobj.readfile_to_new_bytes();
+                    let _ = metadata_fileobj.close();
+                    metadatabytes
+                },
+            );
 
             // Restore metadata
-            interface::serde_deserialize_from_bytes(&metadatabytes).unwrap()
+            match metadatabytes.map(|bytes| interface::deserialize_metadata(&bytes)) {
+                Ok(Ok(metadata)) => metadata,
+                _ => {
+                    // load_fs will find no metadata and format a new filesystem just like this one
+                    interface::report_corruption(&format!(
+                        "metadata could not be read back, moved it to {}",
+                        CORRUPTMETADATAFILENAME
+                    ));
+                    let _ = interface::renamefile(
+                        METADATAFILENAME.to_string(),
+                        CORRUPTMETADATAFILENAME.to_string(),
+                    );
+                    formatted_fs_metadata()
+                }
+            }
         } else {
             FilesystemMetadata::blank_fs_init()
         }
@@ -162,6 +403,16 @@ impl FilesystemMetadata {
 }
 
 pub fn format_fs() {
+    let newmetadata = formatted_fs_metadata();
+
+    let _logremove = interface::removefile(LOGFILENAME.to_string());
+    let _logremove = interface::removefile(OLDLOGFILENAME.to_string());
+
+    persist_metadata(&newmetadata);
+}
+
+//the metadata of a newly formatted filesystem, with the root, /dev and /tmp
+fn formatted_fs_metadata() -> FilesystemMetadata {
     let newmetadata = FilesystemMetadata::blank_fs_init();
     //Because we keep the metadata as a synclazy, it is not possible to completely wipe it and
     //reinstate something over it in-place. Thus we create a new file system, wipe the old one, and
@@ -178,17 +429,19 @@ pub fn format_fs() {
     }
     drop(rootinode);
 
-    let devchildren = interface::RustHashMap::new();
-    devchildren.insert("..".to_string(), 1);
+    let mut devchildren = DirectoryIndex::new();
     devchildren.insert(".".to_string(), 2);
+    devchildren.insert("..".to_string(), 1);
     devchildren.insert("null".to_string(), 3);
     devchildren.insert("zero".to_string(), 4);
     devchildren.insert("urandom".to_string(), 5);
     devchildren.insert("random".to_string(), 6);
+    devchildren.insert("full".to_string(), 8);
+    devchildren.insert("tty".to_string(), 9);
 
-    let tmpchildren = interface::RustHashMap::new();
-    tmpchildren.insert("..".to_string(), 1);
+    let mut tmpchildren = DirectoryIndex::new();
     tmpchildren.insert(".".to_string(), 2);
+    tmpchildren.insert("..".to_string(), 1);
 
     let time = interface::timestamp(); //We do a real timestamp now
     let devdirinode = Inode::Dir(DirectoryInode {
@@ -196,10 +449,11 @@ pub fn format_fs() {
         uid: DEFAULT_UID,
         gid: DEFAULT_GID,
         mode: (S_IFDIR | 0o755) as u32,
-        linkcount: 3 + 4, //3 for ., .., and the parent dir, 4 is one for each child we will create
+        linkcount: 3 + 6, //3 for ., .., and the parent dir, 6 is one for each child we will create
         refcount: 0,
         atime: time,
         ctime: time,
+        btime: time,
         mtime: time,
         filename_to_inode_dict: devchildren,
     }); //inode 2
@@ -212,6 +466,7 @@ pub fn format_fs() {
         refcount: 0,
         atime: time,
         ctime: time,
+        btime: time,
         mtime: time,
         dev: DevNo { major: 1, minor: 3 },
     }); //inode 3
@@ -224,6 +479,7 @@ pub fn format_fs() {
         refcount: 0,
         atime: time,
         ctime: time,
+        btime: time,
         mtime: time,
         dev: DevNo { major: 1, minor: 5 },
     }); //inode 4
@@ -236,6 +492,7 @@ pub fn format_fs() {
         refcount: 0,
         atime: time,
         ctime: time,
+        btime: time,
         mtime: time,
         dev: DevNo { major: 1, minor: 9 },
     }); //inode 5
@@ -248,6 +505,7 @@ pub fn format_fs() {
         refcount: 0,
         atime: time,
         ctime: time,
+        btime: time,
         mtime: time,
         dev: DevNo { major: 1, minor: 8 },
     }); //inode 6
@@ -260,81 +518,90 @@ pub fn format_fs() {
         refcount: 0,
         atime: time,
         ctime: time,
+        btime: time,
         mtime: time,
         filename_to_inode_dict: tmpchildren,
     }); //inode 7
+    let fullinode = Inode::CharDev(DeviceInode {
+        size: 0,
+        uid: DEFAULT_UID,
+        gid: DEFAULT_UID,
+        mode: (S_IFCHR | 0o666) as u32,
+        linkcount: 1,
+        refcount: 0,
+        atime: time,
+        ctime: time,
+        btime: time,
+        mtime: time,
+        dev: DevNo { major: 1, minor: 7 },
+    }); //inode 8
+    let ttyinode = Inode::CharDev(DeviceInode {
+        size: 0,
+        uid: DEFAULT_UID,
+        gid: DEFAULT_UID,
+        mode: (S_IFCHR | 0o666) as u32,
+        linkcount: 1,
+        refcount: 0,
+        atime: time,
+        ctime: time,
+        btime: time,
+        mtime: time,
+        dev: DevNo { major: 5, minor: 0 },
+    }); //inode 9
     newmetadata
         .nextinode
-        .store(8, interface::RustAtomicOrdering::Relaxed);
+        .store(10, interface::RustAtomicOrdering::Relaxed);
     newmetadata.inodetable.insert(2, devdirinode);
     newmetadata.inodetable.insert(3, nullinode);
     newmetadata.inodetable.insert(4, zeroinode);
     newmetadata.inodetable.insert(5, urandominode);
     newmetadata.inodetable.insert(6, randominode);
     newmetadata.inodetable.insert(7, tmpdirinode);
+    newmetadata.inodetable.insert(8, fullinode);
+    newmetadata.inodetable.insert(9, ttyinode);
 
-    let _logremove = interface::removefile(LOGFILENAME.to_string());
-
-    persist_metadata(&newmetadata);
+    newmetadata
 }
 
 pub fn load_fs() {
-    // If the metadata file exists, just close the file for later restore
-    // If it doesn't, lets create a new one, load special files, and persist it.
-    if interface::pathexists(METADATAFILENAME.to_string()) {
-        let metadata_fileobj = interface::openmetadata(METADATAFILENAME.to_string()).unwrap();
-        metadata_fileobj.close().unwrap();
+    //inode numbers aren't stable across a reformat, so nothing cached can be kept
+    DENTRY_CACHE.clear();
 
-        // if we have a log file at this point, we need to sync it with the existing metadata
-        if interface::pathexists(LOGFILENAME.to_string()) {
-            let log_fileobj = interface::openmetadata(LOGFILENAME.to_string()).unwrap();
-            // read log file and parse count
-            let mut logread = log_fileobj.readfile_to_new_bytes().unwrap();
-            let logsize = interface::convert_bytes_to_size(&logread[0..interface::COUNTMAPSIZE]);
-
-            // create vec of log file bounded by indefinite encoding bytes (0x9F, 0xFF)
-            let mut logbytes: Vec<u8> = Vec::new();
-            logbytes.push(0x9F);
-            logbytes.extend_from_slice(
-                &mut logread[interface::COUNTMAPSIZE..(interface::COUNTMAPSIZE + logsize)],
-            );
-            logbytes.push(0xFF);
-            let mut logvec: Vec<(usize, Option<Inode>)> =
-                interface::serde_deserialize_from_bytes(&logbytes).unwrap();
-
-            // drain the vector and deserialize into pairs of inodenum + inodes,
-            // if the inode exists, add it, if not, remove it
-            // keep track of the largest inodenum we see so we can update the nextinode counter
-            let mut max_inodenum = FS_METADATA
-                .nextinode
-                .load(interface::RustAtomicOrdering::Relaxed);
-            for serialpair in logvec.drain(..) {
-                let (inodenum, inode) = serialpair;
-                match inode {
-                    Some(inode) => {
-                        max_inodenum = interface::rust_max(max_inodenum, inodenum);
-                        FS_METADATA.inodetable.insert(inodenum, inode);
-                    }
-                    None => {
-                        FS_METADATA.inodetable.remove(&inodenum);
-                    }
-                }
+    // If the metadata file exists, restore it now, which moves it aside if it is unreadable.
+    // If it still exists, sync it with the logs. If it doesn't, lets create a new one, load
+    // special files, and persist it.
+    if interface::pathexists(METADATAFILENAME.to_string()) {
+        interface::RustLazyGlobal::force(&super::context::current_context().fs.metadata);
+    }
+    if interface::pathexists(METADATAFILENAME.to_string()) {
+        // if we have log files at this point, we need to sync them with the existing metadata,
+        // a log left over from an interrupted compaction holds the older entries
+        let mut replayed = false;
+        for logfilename in [OLDLOGFILENAME, LOGFILENAME] {
+            if interface::pathexists(logfilename.to_string()) {
+                replay_log(logfilename);
+                replayed = true;
             }
+        }
 
-            // update the nextinode counter to avoid collisions
-            FS_METADATA
-                .nextinode
-                .store(max_inodenum + 1, interface::RustAtomicOrdering::Relaxed);
+        // clean up broken links and the data of files that are gone
+        let report = fsck();
+        if report.inodes > 0 || report.datafiles > 0 {
+            interface::report_corruption(&format!(
+                "fsck dropped {} unlinked inodes and {} orphaned data files, reclaiming {} bytes",
+                report.inodes, report.datafiles, report.bytes
+            ));
+        }
 
-            let _logclose = log_fileobj.close();
+        if replayed || report.inodes > 0 {
+            // persist what we replayed so the logs can go
+            persist_metadata(&FS_METADATA);
+            let _logremove = interface::removefile(OLDLOGFILENAME.to_string());
             let _logremove = interface::removefile(LOGFILENAME.to_string());
-
-            // clean up broken links
-            fsck();
         }
     } else {
         if interface::pathexists(LOGFILENAME.to_string()) {
-            println!("Filesystem in very corrupted state: log existed but metadata did not!");
+            interface::report_corruption("metadata log existed but the metadata did not");
         }
         format_fs();
     }
@@ -343,7 +610,81 @@ pub fn load_fs() {
     create_log();
 }
 
-pub fn fsck() {
+//Apply every entry of a log file to FS_METADATA. Each entry holds the full state of an inode, so
+//replaying a log over a snapshot that already contains some of its entries is harmless.
+fn replay_log(logfilename: &str) {
+    let logread = match interface::openmetadata(logfilename.to_string()).and_then(|log_fileobj| {
+        let logread = log_fileobj.readfile_to_new_bytes();
+        let _logclose = log_fileobj.close();
+        logread
+    }) {
+        Ok(logread) => logread,
+        Err(_) => {
+            interface::report_corruption(&format!(
+                "metadata log {} could not be read, skipping it",
+                logfilename
+            ));
+            return;
+        }
+    };
+
+    // a crash can tear the last entries written, everything before them is still good
+    let (entries, mut intact) = interface::read_log_entries(&logread);
+    let mut logvec: Vec<(usize, Option<Inode>)> = Vec::new();
+    for entry in entries {
+        match interface::deserialize_metadata(entry) {
+            Ok(serialpair) => logvec.push(serialpair),
+            Err(_) => {
+                intact = false;
+                break;
+            }
+        }
+    }
+    if !intact {
+        interface::report_corruption(&format!(
+            "metadata log {} was torn by a crash, replaying its {} intact entries",
+            logfilename,
+            logvec.len()
+        ));
+    }
+
+    // drain the vector and deserialize into pairs of inodenum + inodes,
+    // if the inode exists, add it, if not, remove it
+    // keep track of the largest inodenum we see so we can update the nextinode counter
+    let mut max_inodenum = FS_METADATA
+        .nextinode
+        .load(interface::RustAtomicOrdering::Relaxed);
+    for serialpair in logvec.drain(..) {
+        let (inodenum, inode) = serialpair;
+        match inode {
+            Some(inode) => {
+                max_inodenum = interface::rust_max(max_inodenum, inodenum);
+                FS_METADATA.inodetable.insert(inodenum, inode);
+            }
+            None => {
+                FS_METADATA.inodetable.remove(&inodenum);
+            }
+        }
+    }
+
+    // update the nextinode counter to avoid collisions
+    FS_METADATA
+        .nextinode
+        .store(max_inodenum + 1, interface::RustAtomicOrdering::Relaxed);
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FsckReport {
+    pub inodes: usize,    // inodes dropped because nothing links to them anymore
+    pub datafiles: usize, // orphaned data files deleted from the host
+    pub bytes: u64,       // bytes those data files took up
+}
+
+//Drop inodes nothing links to anymore and delete data files no inode owns. This only runs while
+//the filesystem is loaded, before any cage can hold one of those inodes open or be creating the
+//data file of a new one, so it must not be called on a filesystem that is in use.
+pub(crate) fn fsck() -> FsckReport {
+    let inodecount = FS_METADATA.inodetable.len();
     FS_METADATA.inodetable.retain(|_inodenum, inode_obj| {
         match inode_obj {
             Inode::File(ref mut normalfile_inode) => normalfile_inode.linkcount != 0,
@@ -352,9 +693,39 @@ pub fn fsck() {
                 dir_inode.linkcount > 2
             }
             Inode::CharDev(ref mut char_inodej) => char_inodej.linkcount != 0,
-            Inode::Socket(_) => false,
+            //a socket path stays until it is unlinked, even once nothing is bound to it, and
+            //unnamed sockets like those from socketpair only have open descriptors keeping them
+            Inode::Socket(ref mut socket_inode) => {
+                socket_inode.linkcount != 0 || socket_inode.refcount != 0
+            }
         }
     });
+    let mut report = FsckReport {
+        inodes: inodecount - FS_METADATA.inodetable.len(),
+        ..Default::default()
+    };
+
+    //a data file is orphaned once no regular file inode owns it, which happens when we stop
+    //between dropping an inode and removing its data, or when the inode is dropped above
+    for (filename, size) in interface::listfiles(FILEDATAPREFIX).unwrap_or_default() {
+        let inodenum = match filename[FILEDATAPREFIX.len()..].parse::<usize>() {
+            Ok(inodenum) => inodenum,
+            Err(_) => continue,
+        };
+        let owned = matches!(
+            FS_METADATA.inodetable.get(&inodenum).as_deref(),
+            Some(Inode::File(_))
+        );
+        if owned || FILEOBJECTTABLE.contains_key(&inodenum) {
+            continue;
+        }
+        if interface::removefile(filename).is_ok() {
+            report.datafiles += 1;
+            report.bytes += size;
+        }
+    }
+
+    report
 }
 
 pub fn create_log() {
@@ -364,40 +735,630 @@ pub fn create_log() {
     logobj.replace(log_mapobj);
 }
 
-// Serialize New Metadata to CBOR, write to logfile
+// Serialize New Metadata in the configured format, write to logfile
 pub fn log_metadata(metadata: &FilesystemMetadata, inodenum: usize) {
-    let serialpair: (usize, Option<&Inode>);
-    let entrybytes;
+    log_metadata_batch(metadata, &[inodenum]);
+}
 
-    // pack and serialize log entry
-    if let Some(inode) = metadata.inodetable.get(&inodenum) {
-        serialpair = (inodenum, Some(&*inode));
-        entrybytes = interface::serde_serialize_to_bytes(&serialpair).unwrap();
-    } else {
-        serialpair = (inodenum, None);
-        entrybytes = interface::serde_serialize_to_bytes(&serialpair).unwrap();
+// Log several inodes changed by one operation as a single record, so replay never sees some of
+// them changed and not the others
+pub fn log_metadata_batch(metadata: &FilesystemMetadata, inodenums: &[usize]) {
+    log_entries(inodenums.iter().map(|&inodenum| {
+        // inodes belonging to a non-persistent mount never reach the log
+        if !inode_is_persistent(inodenum) {
+            return None;
+        }
+        match metadata.inodetable.get(&inodenum) {
+            Some(inode) => Some(LogEntry::serialize(inodenum, Some(&*inode))),
+            None => Some(LogEntry::serialize(inodenum, None)),
+        }
+    }));
+}
+
+//An inode's change serialized in the configured format for the log. A caller still holding the
+//inode it just changed builds the entry from it, rather than logging by number and having the
+//inode looked up and serialized all over again.
+pub struct LogEntry(Vec<u8>);
+
+impl LogEntry {
+    //the inode as it is now, or None if it is on a non-persistent mount
+    pub fn inode(inodenum: usize, inode: &Inode) -> Option<LogEntry> {
+        if !inode_is_persistent(inodenum) {
+            return None;
+        }
+        Some(Self::serialize(inodenum, Some(inode)))
     }
 
-    // write to file
-    let mut mapopt = LOGMAP.write();
-    let map = mapopt.as_mut().unwrap();
-    map.write_to_map(&entrybytes).unwrap();
+    //the inode having been dropped from the inode table
+    pub fn removed(inodenum: usize) -> Option<LogEntry> {
+        if !inode_is_persistent(inodenum) {
+            return None;
+        }
+        Some(Self::serialize(inodenum, None))
+    }
+
+    fn serialize(inodenum: usize, inode: Option<&Inode>) -> LogEntry {
+        let serialpair: (usize, Option<&Inode>) = (inodenum, inode);
+        LogEntry(interface::serialize_metadata(&serialpair).unwrap())
+    }
+}
+
+// Log entries from one operation as a single record, skipping the Nones of inodes that don't persist
+pub fn log_entries<I: IntoIterator<Item = Option<LogEntry>>>(entries: I) {
+    let entries: Vec<Vec<u8>> = entries.into_iter().flatten().map(|entry| entry.0).collect();
+    if entries.is_empty() {
+        return;
+    }
+
+    commit_log_entries(entries);
+
+    // only one compaction runs at a time, whoever crosses the threshold first starts it
+    if log_over_threshold() && !LOG_COMPACTING.swap(true, interface::RustAtomicOrdering::AcqRel) {
+        // the compaction is of the log of this thread's machine, which the helper works in
+        interface::helper_thread(move || {
+            loop {
+                compact_log();
+                LOG_COMPACTING.store(false, interface::RustAtomicOrdering::Release);
+                // entries logged during the compaction may have crossed the threshold again
+                // without anyone being able to start another one
+                if !log_over_threshold()
+                    || LOG_COMPACTING.swap(true, interface::RustAtomicOrdering::AcqRel)
+                {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+//How long whoever commits a group of log entries waits for more to join it before writing them.
+//Zero writes whatever gathered while the previous group was being written, without waiting.
+pub const DEFAULT_LOG_BATCH_WINDOW_US: u64 = 0;
+pub static LOG_BATCH_WINDOW_US: interface::RustLazyGlobal<interface::RustAtomicU64> =
+    interface::RustLazyGlobal::new(|| interface::RustAtomicU64::new(DEFAULT_LOG_BATCH_WINDOW_US));
+
+pub fn set_log_batch_window(window_us: u64) {
+    LOG_BATCH_WINDOW_US.store(window_us, interface::RustAtomicOrdering::Relaxed);
+}
+
+//Entries waiting for the log. Each caller queues its entries and the first to find no commit
+//under way becomes the committer, writing everything queued as one record; the rest wait until a
+//commit has covered their entries, so nobody returns before what they logged is in the map.
+struct LogBatcher {
+    pending: interface::Mutex<PendingLog>,
+    committed: interface::Condvar,
 }
 
-// Serialize Metadata Struct to CBOR, write to file
+struct PendingLog {
+    entries: Vec<Vec<u8>>,
+    queuedgroups: u64,    // groups of entries queued so far
+    committedgroups: u64, // of those, how many are in the log
+    committing: bool,
+}
+
+static LOG_BATCHER: ContextGlobal<LogBatcher> =
+    ContextGlobal::new(|context| &context.fs.logbatcher);
+
+fn commit_log_entries(mut entries: Vec<Vec<u8>>) {
+    let mut pending = LOG_BATCHER.pending.lock();
+    pending.entries.append(&mut entries);
+    pending.queuedgroups += 1;
+    let group = pending.queuedgroups;
+
+    while pending.committedgroups < group {
+        if pending.committing {
+            LOG_BATCHER.committed.wait(&mut pending);
+            continue;
+        }
+        pending.committing = true;
+
+        let window = LOG_BATCH_WINDOW_US.load(interface::RustAtomicOrdering::Relaxed);
+        if window > 0 {
+            interface::MutexGuard::unlocked(&mut pending, || {
+                interface::sleep(interface::RustDuration::from_micros(window))
+            });
+        }
+        let batch = std::mem::take(&mut pending.entries);
+        let upto = pending.queuedgroups;
+
+        // the batch lock is let go of while writing, so the next group gathers in the meantime
+        interface::MutexGuard::unlocked(&mut pending, || {
+            let mut mapopt = LOGMAP.write();
+            let map = mapopt.as_mut().unwrap();
+            map.write_batch_to_map(&batch).unwrap();
+        });
+
+        pending.committedgroups = upto;
+        pending.committing = false;
+        LOG_BATCHER.committed.notify_all();
+    }
+}
+
+fn log_over_threshold() -> bool {
+    log_size().unwrap_or(0) > LOG_COMPACT_THRESHOLD.load(interface::RustAtomicOrdering::Relaxed)
+}
+
+pub fn set_log_compact_threshold(threshold: usize) {
+    LOG_COMPACT_THRESHOLD.store(threshold, interface::RustAtomicOrdering::Relaxed);
+}
+
+//bytes of entries in the current log, or None if there is no log open
+pub fn log_size() -> Option<usize> {
+    LOGMAP.read().as_ref().map(|map| map.bytes_logged())
+}
+
+//Fold the log into a fresh metadata snapshot. New entries go to a fresh log from the moment it is
+//swapped in, so the old log is only needed until the snapshot covering it has been written; a
+//crash before then leaves both logs behind and load_fs replays them in order.
+pub fn compact_log() {
+    let _compacting = LOG_COMPACT_LOCK.lock();
+
+    {
+        let mut logobj = LOGMAP.write();
+        let log = match logobj.take() {
+            Some(log) => log,
+            None => return, // the filesystem has been finalized
+        };
+        log.close().unwrap();
+        interface::renamefile(LOGFILENAME.to_string(), OLDLOGFILENAME.to_string()).unwrap();
+        logobj.replace(interface::mapfilenew(LOGFILENAME.to_string()).unwrap());
+    }
+
+    persist_metadata(&FS_METADATA);
+    let _logremove = interface::removefile(OLDLOGFILENAME.to_string());
+}
+
+//How often the background flusher snapshots the metadata, so a crash loses at most this much of
+//whatever the log failed to capture. Zero turns periodic snapshots off.
+pub const DEFAULT_PERSIST_INTERVAL_MS: u64 = 5000;
+pub static PERSIST_INTERVAL_MS: interface::RustLazyGlobal<interface::RustAtomicU64> =
+    interface::RustLazyGlobal::new(|| interface::RustAtomicU64::new(DEFAULT_PERSIST_INTERVAL_MS));
+
+pub struct MetadataFlusher {
+    stop: interface::Mutex<bool>,
+    wakeup: interface::Condvar,
+    handle: interface::Mutex<Option<interface::RustJoinHandle<()>>>,
+}
+
+pub static METADATA_FLUSHER: ContextGlobal<MetadataFlusher> =
+    ContextGlobal::new(|context| &context.fs.flusher);
+
+pub fn set_persist_interval(interval_ms: u64) {
+    PERSIST_INTERVAL_MS.store(interval_ms, interface::RustAtomicOrdering::Relaxed);
+    // restart the flusher's wait so the new interval takes effect right away
+    let _stop = METADATA_FLUSHER.stop.lock();
+    METADATA_FLUSHER.wakeup.notify_all();
+}
+
+//Snapshot the metadata if anything has been logged since the last snapshot. Taking the snapshot
+//goes through log compaction, so writers logging concurrently land either in the log that is
+//being retired, and thus in the snapshot, or in the fresh log that follows it, and the snapshot
+//leaves out non-persistent inodes just as a compaction's does.
+pub fn flush_metadata() {
+    if log_size().unwrap_or(0) > 0 {
+        compact_log();
+    }
+}
+
+//Flush the data of every open file on one mount, or on all of them, and snapshot the metadata if
+//the persistent filesystem is involved. Files of non-persistent mounts only live in memory, so
+//there is nothing to flush for them. Returns false if any file failed to reach the disk.
+pub fn sync_filesystem(mountid: Option<usize>) -> bool {
+    //collect the inodes first, as looking up a file's mount while iterating could deadlock with a
+    //writer holding its inode
+    let inodes: Vec<usize> = FILEOBJECTTABLE.iter().map(|entry| *entry.key()).collect();
+    let mut synced = true;
+    for inodenum in inodes {
+        if !inode_is_persistent(inodenum)
+            || mountid.map_or(false, |id| id != mount_id_of_inode(inodenum))
+        {
+            continue;
+        }
+        if let Some(fileobject) = FILEOBJECTTABLE.get(&inodenum) {
+            synced &= fileobject.fsync().is_ok();
+        }
+    }
+    if mountid.map_or(true, |id| id == ROOTMOUNTID) {
+        flush_metadata();
+    }
+    synced
+}
+
+//each machine has a flusher of its own, started from within it
+pub fn start_metadata_flusher() {
+    let mut handle = METADATA_FLUSHER.handle.lock();
+    if handle.is_some() {
+        return;
+    }
+    *METADATA_FLUSHER.stop.lock() = false;
+
+    handle.replace(interface::helper_thread(move || {
+        let mut stop = METADATA_FLUSHER.stop.lock();
+        while !*stop {
+            let interval = PERSIST_INTERVAL_MS.load(interface::RustAtomicOrdering::Relaxed);
+            if interval == 0 {
+                METADATA_FLUSHER.wakeup.wait(&mut stop);
+                continue;
+            }
+
+            let waited = METADATA_FLUSHER
+                .wakeup
+                .wait_for(&mut stop, interface::RustDuration::from_millis(interval));
+            if waited.timed_out() && !*stop {
+                interface::MutexGuard::unlocked(&mut stop, flush_metadata);
+            }
+        }
+    }));
+}
+
+//stop the flusher and wait for any snapshot it is in the middle of
+pub fn stop_metadata_flusher() {
+    let mut handle = METADATA_FLUSHER.handle.lock();
+    if let Some(flusher) = handle.take() {
+        *METADATA_FLUSHER.stop.lock() = true;
+        METADATA_FLUSHER.wakeup.notify_all();
+        flusher.join().unwrap();
+    }
+}
+
+//The metadata as it is persisted, which is FilesystemMetadata without the inodes of tmpfs, procfs
+//and devpts mounts and of memfds. Those are gone after a restart, so a snapshot leaves them out
+//just as the log does, and it reads back as an ordinary FilesystemMetadata.
+#[derive(interface::SerdeSerialize)]
+struct PersistentMetadata<'a> {
+    nextinode: &'a interface::RustAtomicUsize,
+    dev_id: u64,
+    inodetable: PersistentInodes<'a>,
+}
+
+struct PersistentInodes<'a>(&'a interface::RustHashMap<usize, Inode>);
+
+impl interface::SerdeSerialize for PersistentInodes<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        //counted first like the inode table itself does, as holding every inode while writing
+        //them out could deadlock with a syscall locking two of them
+        let count = self
+            .0
+            .iter()
+            .filter(|entry| inode_is_persistent(*entry.key()))
+            .count();
+        let mut map = serializer.serialize_map(Some(count))?;
+        for entry in self.0.iter() {
+            if inode_is_persistent(*entry.key()) {
+                map.serialize_entry(entry.key(), entry.value())?;
+            }
+        }
+        map.end()
+    }
+}
+
+// Serialize Metadata Struct in the configured format, write to file
 pub fn persist_metadata(metadata: &FilesystemMetadata) {
-    // Serialize metadata to string
-    let metadatabytes = interface::serde_serialize_to_bytes(&metadata).unwrap();
+    let _persisting = PERSIST_LOCK.lock();
+
+    // Serialize the persistent part of the metadata
+    let persistent = PersistentMetadata {
+        nextinode: &metadata.nextinode,
+        dev_id: metadata.dev_id,
+        inodetable: PersistentInodes(&metadata.inodetable),
+    };
+    let metadatabytes = interface::serialize_metadata(&persistent).unwrap();
 
+    // write to a scratch file first and rename it into place, so a crash midway never leaves us
+    // without a complete snapshot
+    let tmpfilename = format!("{}.tmp", METADATAFILENAME);
     // remove file if it exists, assigning it to nothing to avoid the compiler yelling about unused result
-    let _ = interface::removefile(METADATAFILENAME.to_string());
+    let _ = interface::removefile(tmpfilename.clone());
 
     // write to file
-    let mut metadata_fileobj = interface::openmetadata(METADATAFILENAME.to_string()).unwrap();
+    let mut metadata_fileobj = interface::openmetadata(tmpfilename.clone()).unwrap();
     metadata_fileobj
         .writefile_from_bytes(&metadatabytes)
         .unwrap();
+    metadata_fileobj.fsync().unwrap();
     metadata_fileobj.close().unwrap();
+    interface::renamefile(tmpfilename, METADATAFILENAME.to_string()).unwrap();
+}
+
+//Every mounted filesystem shares the inode number space of FS_METADATA. A mount supplies its own
+//root inode, which path walks substitute for the directory it covers, and inodes created beneath
+//a non-persistent mount are tracked so they never reach the metadata log.
+pub static MOUNT_TABLE: ContextGlobal<MountTable> =
+    ContextGlobal::new(|context| &*context.fs.mounts);
+
+pub const ROOTMOUNTID: usize = 0;
+//memfds are reachable from no directory and belong to no real mount; owning them by this id
+//keeps them in memory and out of the metadata log like the files of a tmpfs
+pub const ANONMOUNTID: usize = usize::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MountFsType {
+    LindFs, // the persisted filesystem mounted at /
+    Tmpfs,  // inodes only live as long as the mount does
+    Bind,   // passthrough to an existing directory tree
+    Procfs, // generated from live cage state
+    Devpts, // holds the slave ends of pseudo-terminals
+}
+
+impl MountFsType {
+    pub fn from_name(name: &str) -> Option<MountFsType> {
+        match name {
+            "lindfs" => Some(MountFsType::LindFs),
+            "tmpfs" => Some(MountFsType::Tmpfs),
+            "bind" | "passthrough" => Some(MountFsType::Bind),
+            "proc" => Some(MountFsType::Procfs),
+            "devpts" => Some(MountFsType::Devpts),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MountFsType::LindFs => "lindfs",
+            MountFsType::Tmpfs => "tmpfs",
+            MountFsType::Bind => "bind",
+            MountFsType::Procfs => "proc",
+            MountFsType::Devpts => "devpts",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MountEntry {
+    pub id: usize,
+    pub source: String,
+    pub mountpoint: interface::RustPathBuf,
+    pub fstype: MountFsType,
+    pub flags: u64,
+    pub rootinode: usize,
+    pub coveredinode: usize,
+}
+
+#[derive(Debug)]
+pub struct MountTable {
+    pub mounts: interface::RustLock<Vec<MountEntry>>, // in the order they were mounted
+    pub covered: interface::RustHashMap<usize, usize>, // covered directory inode -> mount root inode
+    pub roots: interface::RustHashMap<usize, usize>, // mount root inode -> covered directory inode
+    pub inodemounts: interface::RustHashMap<usize, usize>, // inode -> id of the non-persistent mount owning it
+    pub usage: interface::RustHashMap<usize, MountUsage>, // mount id -> space accounting for size limited mounts
+    pub nextid: interface::RustAtomicUsize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MountUsage {
+    pub limit: usize, // in bytes
+    pub used: usize,
+}
+
+impl MountTable {
+    pub fn new() -> MountTable {
+        let rootmount = MountEntry {
+            id: ROOTMOUNTID,
+            source: "lind".to_string(),
+            mountpoint: interface::RustPathBuf::from("/"),
+            fstype: MountFsType::LindFs,
+            flags: 0,
+            rootinode: ROOTDIRECTORYINODE,
+            coveredinode: ROOTDIRECTORYINODE,
+        };
+        MountTable {
+            mounts: interface::RustLock::new(vec![rootmount]),
+            covered: interface::RustHashMap::new(),
+            roots: interface::RustHashMap::new(),
+            inodemounts: interface::RustHashMap::new(),
+            usage: interface::RustHashMap::new(),
+            nextid: interface::RustAtomicUsize::new(ROOTMOUNTID + 1),
+        }
+    }
+}
+
+//returns the root inode of the mount stacked on this directory, or the inode itself
+pub fn mount_cover(inodenum: usize) -> usize {
+    match MOUNT_TABLE.covered.get(&inodenum) {
+        Some(rootinode) => *rootinode,
+        None => inodenum,
+    }
+}
+
+pub fn is_mount_root(inodenum: usize) -> bool {
+    MOUNT_TABLE.roots.contains_key(&inodenum)
+}
+
+//find the mount a normalized path lives on, which is the mount with the longest matching prefix
+pub fn mount_lookup(path: &interface::RustPath) -> MountEntry {
+    let mounts = MOUNT_TABLE.mounts.read();
+    let mut best = &mounts[0];
+    for entry in mounts.iter() {
+        //later mounts win ties as they are stacked on top of earlier ones
+        if path.starts_with(&entry.mountpoint)
+            && entry.mountpoint.components().count() >= best.mountpoint.components().count()
+        {
+            best = entry;
+        }
+    }
+    best.clone()
+}
+
+pub fn mount_flags(path: &interface::RustPath) -> u64 {
+    mount_lookup(path).flags
+}
+
+//When set, the persistent filesystem refuses every change with EROFS while still serving reads, so
+//a pristine base image can be shared by many cages. Non-persistent mounts such as /tmp stay
+//writable unless they were mounted read-only themselves.
+static FS_READONLY: ContextGlobal<interface::RustAtomicBool> =
+    ContextGlobal::new(|context| &context.fs.readonly);
+
+pub fn set_fs_readonly(readonly: bool) {
+    FS_READONLY.store(readonly, interface::RustAtomicOrdering::Relaxed);
+}
+
+pub fn fs_readonly() -> bool {
+    FS_READONLY.load(interface::RustAtomicOrdering::Relaxed)
+}
+
+//whether changes to a normalized path have to be refused because of where it lives
+pub fn path_is_readonly(path: &interface::RustPath) -> bool {
+    let entry = mount_lookup(path);
+    entry.flags & MS_RDONLY != 0
+        || (fs_readonly() && matches!(entry.fstype, MountFsType::LindFs | MountFsType::Bind))
+}
+
+//the same for an inode reached through a file descriptor rather than a path
+pub fn inode_is_readonly(inodenum: usize) -> bool {
+    let mountid = mount_id_of_inode(inodenum);
+    if mountid == ROOTMOUNTID && fs_readonly() {
+        return true;
+    }
+    MOUNT_TABLE
+        .mounts
+        .read()
+        .iter()
+        .any(|entry| entry.id == mountid && entry.flags & MS_RDONLY != 0)
+}
+
+pub fn mount_id_of_inode(inodenum: usize) -> usize {
+    match MOUNT_TABLE.inodemounts.get(&inodenum) {
+        Some(id) => *id,
+        None => ROOTMOUNTID,
+    }
+}
+
+pub fn inode_is_persistent(inodenum: usize) -> bool {
+    !MOUNT_TABLE.inodemounts.contains_key(&inodenum)
+}
+
+//a newly created inode belongs to the same filesystem as the directory it was created in
+pub fn mount_claim_inode(parentinodenum: usize, newinodenum: usize) {
+    let mountid = mount_id_of_inode(parentinodenum);
+    if mountid != ROOTMOUNTID {
+        MOUNT_TABLE.inodemounts.insert(newinodenum, mountid);
+    }
+}
+
+//account for a regular file under a size limited mount changing size, returning false without
+//charging anything if growing it would exceed the mount's limit
+pub fn mount_resize_inode(inodenum: usize, oldsize: usize, newsize: usize) -> bool {
+    let mountid = mount_id_of_inode(inodenum);
+    if let Some(mut usage) = MOUNT_TABLE.usage.get_mut(&mountid) {
+        if newsize > oldsize {
+            if usage.used + (newsize - oldsize) > usage.limit {
+                return false;
+            }
+            usage.used += newsize - oldsize;
+        } else {
+            usage.used -= interface::rust_min(oldsize - newsize, usage.used);
+        }
+    }
+    true
+}
+
+//the inode has been removed from the filesystem, so release its space and its data if it was
+//held in memory
+pub fn mount_release_inode(inodenum: usize, size: usize) {
+    if inode_is_persistent(inodenum) {
+        return;
+    }
+    mount_resize_inode(inodenum, size, 0);
+    if let Some((_, fobj)) = FILEOBJECTTABLE.remove(&inodenum) {
+        fobj.close().unwrap();
+    }
+    FILE_SEALS.remove(&inodenum);
+    MOUNT_TABLE.inodemounts.remove(&inodenum);
+}
+
+//give up the lease an open file description holds on the file, if it has one
+pub fn release_lease(inodenum: usize, holder: usize) {
+    if let Some(mut leases) = FILE_LEASES.get_mut(&inodenum) {
+        leases.retain(|lease| lease.holder != holder);
+    }
+    FILE_LEASES.remove_if(&inodenum, |_, leases| leases.is_empty());
+}
+
+pub fn seals_of_inode(inodenum: usize) -> i32 {
+    match FILE_SEALS.get(&inodenum) {
+        Some(seals) => *seals,
+        None => 0,
+    }
+}
+
+//parse the "size=" option of a tmpfs mount, which may carry a k, m or g suffix
+pub fn parse_tmpfs_size(data: &str) -> Option<usize> {
+    let mut size = TMPFS_DEFAULT_SIZE;
+    for option in data.split(',').filter(|option| !option.is_empty()) {
+        let value = option.strip_prefix("size=")?;
+        let (digits, multiplier) = match value.chars().last()? {
+            'k' | 'K' => (&value[..value.len() - 1], 1 << 10),
+            'm' | 'M' => (&value[..value.len() - 1], 1 << 20),
+            'g' | 'G' => (&value[..value.len() - 1], 1 << 30),
+            _ => (value, 1),
+        };
+        size = digits.parse::<usize>().ok()?.checked_mul(multiplier)?;
+    }
+    Some(size)
+}
+
+pub fn mount_insert(entry: MountEntry) {
+    let mut mounts = MOUNT_TABLE.mounts.write();
+    MOUNT_TABLE
+        .covered
+        .insert(entry.coveredinode, entry.rootinode);
+    MOUNT_TABLE
+        .roots
+        .insert(entry.rootinode, entry.coveredinode);
+    mounts.push(entry);
+    dentry_invalidate_all();
+}
+
+//detach a mount from the tree, dropping every inode it owned
+pub fn mount_remove(mountid: usize) -> Option<MountEntry> {
+    let mut mounts = MOUNT_TABLE.mounts.write();
+    let index = mounts.iter().position(|entry| entry.id == mountid)?;
+    let entry = mounts.remove(index);
+    drop(mounts);
+
+    MOUNT_TABLE.covered.remove(&entry.coveredinode);
+    MOUNT_TABLE.roots.remove(&entry.rootinode);
+    dentry_invalidate_all();
+    MOUNT_TABLE.usage.remove(&mountid);
+    procfs_unmount(mountid);
+
+    let mut owned = vec![];
+    MOUNT_TABLE.inodemounts.retain(|inodenum, id| {
+        if *id == mountid {
+            owned.push(*inodenum);
+            false
+        } else {
+            true
+        }
+    });
+    for inodenum in owned {
+        let size = match FS_METADATA.inodetable.remove(&inodenum) {
+            Some((_, Inode::File(file))) => file.size,
+            _ => 0,
+        };
+        quota_release_inode(inodenum, size);
+        if let Some((_, fobj)) = FILEOBJECTTABLE.remove(&inodenum) {
+            fobj.close().unwrap();
+        }
+    }
+    Some(entry)
+}
+
+//tear down every mount but the root, most recent first, so nothing volatile is persisted
+pub fn umount_all() {
+    let ids: Vec<usize> = MOUNT_TABLE
+        .mounts
+        .read()
+        .iter()
+        .rev()
+        .map(|entry| entry.id)
+        .filter(|id| *id != ROOTMOUNTID)
+        .collect();
+    for id in ids {
+        mount_remove(id);
+    }
 }
 
 pub fn convpath(cpath: &str) -> interface::RustPathBuf {
@@ -426,19 +1387,21 @@ pub fn pathnamefrominodenum(inodenum: usize) -> Option<String> {
     let mut current_inodenum = inodenum;
 
     loop {
-        let mut thisinode = match FS_METADATA.inodetable.get_mut(&current_inodenum) {
+        let thisinode = match FS_METADATA.inodetable.get(&current_inodenum) {
             Some(inode) => inode,
             None => {
                 return None;
             }
         };
 
-        match *thisinode {
-            Inode::Dir(ref mut dir_inode) => {
+        match &*thisinode {
+            Inode::Dir(dir_inode) => {
                 // We try to get the parent directory inode.
                 if let Some(parent_dir_inode) = dir_inode.filename_to_inode_dict.get("..") {
+                    // Let go of this inode before looking at its parent, which may be in the same shard of the table
+                    drop(thisinode);
                     // If the parent node is 1 (indicating the root directory) and this is not the first iteration, this indicates that we have arrived at the root directory. Here we add a '/' to the beginning of the path string and return it.
-                    if *parent_dir_inode == (1 as usize) {
+                    if parent_dir_inode == (1 as usize) {
                         if !first_iteration {
                             path_string.insert(0, '/');
                             return Some(path_string);
@@ -446,10 +1409,17 @@ pub fn pathnamefrominodenum(inodenum: usize) -> Option<String> {
                         first_iteration = false;
                     }
 
-                    match filenamefrominode(*parent_dir_inode, current_inodenum) {
+                    //the root of a mount is named after the directory it covers
+                    let lookup_inodenum = match MOUNT_TABLE.roots.get(&current_inodenum) {
+                        Some(covered) => *covered,
+                        None => current_inodenum,
+                    };
+                    match filenamefrominode(parent_dir_inode, lookup_inodenum)
+                        .or_else(|| filenamefrominode(parent_dir_inode, current_inodenum))
+                    {
                         Some(filename) => {
                             path_string = filename + "/" + &path_string;
-                            current_inodenum = *parent_dir_inode;
+                            current_inodenum = parent_dir_inode;
                         }
                         None => return None,
                     };
@@ -472,9 +1442,9 @@ pub fn filenamefrominode(dir_inode_no: usize, target_inode: usize) -> Option<Str
         Inode::Dir(d) => {
             let mut target_variable_name: Option<String> = None;
 
-            for entry in d.filename_to_inode_dict.iter() {
-                if entry.value() == &target_inode {
-                    target_variable_name = Some(entry.key().to_owned());
+            for (name, inodenum) in d.filename_to_inode_dict.iter() {
+                if inodenum == target_inode {
+                    target_variable_name = Some(name.to_owned());
                     break;
                 }
             }
@@ -485,7 +1455,82 @@ pub fn filenamefrominode(dir_inode_no: usize, target_inode: usize) -> Option<Str
 }
 
 //returns tuple consisting of inode number of file (if it exists), and inode number of parent (if it exists)
+//Caches the outcome of walking a path, keyed by the path. A hit is only trusted if the cached
+//parent still lists the cached inode under the path's last component, which also catches entries
+//unlinked through another path such as a bind mount. Changes that move or cover whole subtrees
+//bump the generation instead, invalidating every entry at once.
+pub static DENTRY_CACHE: ContextGlobal<
+    interface::RustHashMap<interface::RustPathBuf, DentryCacheEntry>,
+> = ContextGlobal::new(|context| &context.fs.dentries);
+static DENTRY_GENERATION: ContextGlobal<interface::RustAtomicU64> =
+    ContextGlobal::new(|context| &context.fs.dentrygeneration);
+static DENTRY_CACHE_ENABLED: interface::RustAtomicBool = interface::RustAtomicBool::new(true);
+pub const DENTRY_CACHE_MAX: usize = 4096; // entries, the cache is emptied once it grows past this
+
+#[derive(Debug, Clone, Copy)]
+pub struct DentryCacheEntry {
+    pub inodenum: usize,
+    pub parentinodenum: usize,
+    pub generation: u64,
+}
+
+//the path no longer names what it did
+pub fn dentry_invalidate(path: &interface::RustPath) {
+    DENTRY_CACHE.remove(path);
+}
+
+//some directory was moved or covered, so no cached path can be trusted anymore
+pub fn dentry_invalidate_all() {
+    DENTRY_GENERATION.fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+}
+
+//the cache is on by default, turning it off is meant for measuring what it saves
+pub fn dentry_cache_set_enabled(enabled: bool) {
+    DENTRY_CACHE_ENABLED.store(enabled, interface::RustAtomicOrdering::Relaxed);
+    DENTRY_CACHE.clear();
+}
+
+fn _dentry_lookup(path: &interface::RustPath) -> Option<(usize, usize)> {
+    let entry = *DENTRY_CACHE.get(path)?;
+    if entry.generation != DENTRY_GENERATION.load(interface::RustAtomicOrdering::Relaxed) {
+        return None;
+    }
+    let name = path.file_name()?.to_str()?;
+    match &*FS_METADATA.inodetable.get(&entry.parentinodenum)? {
+        Inode::Dir(dir) => match dir.filename_to_inode_dict.get(name) {
+            Some(num) if mount_cover(num) == entry.inodenum => {
+                Some((entry.inodenum, entry.parentinodenum))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn _dentry_insert(path: &interface::RustPath, inodenum: usize, parentinodenum: usize) {
+    if DENTRY_CACHE.len() >= DENTRY_CACHE_MAX {
+        DENTRY_CACHE.clear();
+    }
+    DENTRY_CACHE.insert(
+        path.to_path_buf(),
+        DentryCacheEntry {
+            inodenum: inodenum,
+            parentinodenum: parentinodenum,
+            generation: DENTRY_GENERATION.load(interface::RustAtomicOrdering::Relaxed),
+        },
+    );
+}
+
 pub fn metawalkandparent(path: &interface::RustPath) -> (Option<usize>, Option<usize>) {
+    let cacheenabled = DENTRY_CACHE_ENABLED.load(interface::RustAtomicOrdering::Relaxed);
+    if cacheenabled {
+        if let Some((inodenum, parentinodenum)) = _dentry_lookup(path) {
+            return (Some(inodenum), Some(parentinodenum));
+        }
+    }
+    //procfs contents are regenerated on every walk, so they are never cached
+    let mut cacheable = cacheenabled;
+
     let mut curnode = Some(FS_METADATA.inodetable.get(&ROOTDIRECTORYINODE).unwrap());
     let mut inodeno = Some(ROOTDIRECTORYINODE);
     let mut previnodeno = None;
@@ -501,33 +1546,34 @@ pub fn metawalkandparent(path: &interface::RustPath) -> (Option<usize>, Option<u
                 if inodeno.is_none() {
                     return (None, None);
                 }
-                match &*curnode.unwrap() {
-                    Inode::Dir(d) => {
-                        previnodeno = inodeno;
-
-                        //populate child inode number from parent directory's inode dict
-                        inodeno = match d
-                            .filename_to_inode_dict
-                            .get(&f.to_str().unwrap().to_string())
-                        {
-                            Some(num) => {
-                                curnode = FS_METADATA.inodetable.get(&num);
-                                Some(*num)
-                            }
-
-                            //if no such child exists, update curnode, inodeno accordingly so that
-                            //we can check against none as we do at the beginning of the Normal match arm
-                            None => {
-                                curnode = None;
-                                None
-                            }
-                        }
-                    }
+                //populate child inode number from parent directory's inode dict
+                let childinodeno = match &*curnode.unwrap() {
+                    Inode::Dir(d) => d
+                        .filename_to_inode_dict
+                        .get(f.to_str().unwrap())
+                        //if a filesystem is mounted over this directory, continue from its root
+                        .map(mount_cover),
                     //if we're trying to get a child of a non-directory inode, exit out
                     _ => {
                         return (None, None);
                     }
-                }
+                };
+                previnodeno = inodeno;
+                inodeno = childinodeno;
+
+                //if no such child exists, update curnode, inodeno accordingly so that
+                //we can check against none as we do at the beginning of the Normal match arm
+                curnode = match childinodeno {
+                    Some(num) => {
+                        //synthetic directories are regenerated as they are walked through, which
+                        //must happen while no inode is held
+                        if procfs_refresh(num) {
+                            cacheable = false;
+                        }
+                        FS_METADATA.inodetable.get(&num)
+                    }
+                    None => None,
+                };
             }
 
             //If it's a component of the pathbuf that we don't expect given a normed path, exit out
@@ -536,6 +1582,10 @@ pub fn metawalkandparent(path: &interface::RustPath) -> (Option<usize>, Option<u
             }
         }
     }
+    drop(curnode);
+    if let (true, Some(inodenum), Some(parentinodenum)) = (cacheable, inodeno, previnodeno) {
+        _dentry_insert(path, inodenum, parentinodenum);
+    }
     //return inode number and it's parent's number
     (inodeno, previnodeno)
 }
@@ -543,11 +1593,14 @@ pub fn metawalk(path: &interface::RustPath) -> Option<usize> {
     metawalkandparent(path).0
 }
 pub fn normpath(origp: interface::RustPathBuf, cage: &Cage) -> interface::RustPathBuf {
+    //paths are resolved inside the cage's root directory, which is "/" unless it has been chrooted
+    let rootdir = cage.rootdir.read().clone();
+
     //If path is relative, prefix it with the current working directory, otherwise populate it with rootdir
     let mut newp = if origp.is_relative() {
         (**cage.cwd.read()).clone()
     } else {
-        interface::RustPathBuf::from("/")
+        (*rootdir).clone()
     };
 
     for comp in origp.components() {
@@ -557,15 +1610,42 @@ pub fn normpath(origp: interface::RustPathBuf, cage: &Cage) -> interface::RustPa
                 newp.push(comp);
             }
 
-            //if we have a .. path component, pop the last component off our normed path
+            //if we have a .. path component, pop the last component off our normed path,
+            //but never climb out of the cage's root directory
             interface::RustPathComponent::ParentDir => {
-                newp.pop();
+                if newp != *rootdir {
+                    newp.pop();
+                }
             }
 
             //if we have a . path component (Or a root dir or a prefix(?)) do nothing
             _ => {}
         };
     }
+
+    let procdir = rootdir.join(PROCMOUNTPOINT.trim_start_matches('/'));
+
+    //like the symlink it is on linux, /dev/fd leads to the cage's own procfs fd directory
+    if let Ok(rest) = newp.strip_prefix(rootdir.join("dev/fd")) {
+        let mut fdp: interface::RustPathBuf = procdir.join("self/fd");
+        if !rest.as_os_str().is_empty() {
+            fdp.push(rest);
+        }
+        newp = fdp;
+    }
+
+    //procfs names the calling cage's own directory "self"
+    if let Ok(rest) = newp
+        .strip_prefix(&procdir)
+        .and_then(|p| p.strip_prefix("self"))
+    {
+        let mut selfp = procdir.clone();
+        selfp.push(cage.cageid.to_string());
+        if !rest.as_os_str().is_empty() {
+            selfp.push(rest);
+        }
+        return selfp;
+    }
     newp
 }
 
@@ -573,12 +1653,10 @@ pub fn remove_domain_sock(truepath: interface::RustPathBuf) {
     match metawalkandparent(truepath.as_path()) {
         //If the file does not exist
         (None, ..) => {
-            panic!("path does not exist")
+            interface::report_corruption(&format!("domain socket {:?} does not exist", truepath))
         }
         //If the file exists but has no parent, it's the root directory
-        (Some(_), None) => {
-            panic!("cannot unlink root directory")
-        }
+        (Some(_), None) => interface::report_corruption("root directory is a domain socket"),
 
         //If both the file and the parent directory exists
         (Some(inodenum), Some(parentinodenum)) => {
@@ -586,34 +1664,65 @@ pub fn remove_domain_sock(truepath: interface::RustPathBuf) {
 
             FS_METADATA.inodetable.remove(&inodenum);
             NET_METADATA.domsock_paths.remove(&truepath);
+            NET_METADATA.domsock_accept_waits.remove(&truepath);
         }
     }
 }
 
 pub fn incref_root() {
-    if let Inode::Dir(ref mut rootdir_dirinode_obj) =
-        *(FS_METADATA.inodetable.get_mut(&ROOTDIRECTORYINODE).unwrap())
+    match FS_METADATA
+        .inodetable
+        .get_mut(&ROOTDIRECTORYINODE)
+        .as_deref_mut()
     {
-        rootdir_dirinode_obj.refcount += 1;
+        Some(Inode::Dir(rootdir_dirinode_obj)) => rootdir_dirinode_obj.refcount += 1,
+        _ => interface::report_corruption("root directory inode is not a directory"),
+    }
+}
+
+pub fn incref_dir(dir_container: &interface::RustPathBuf) {
+    if let Some(dirinodenum) = metawalk(&dir_container) {
+        match FS_METADATA.inodetable.get_mut(&dirinodenum).as_deref_mut() {
+            Some(Inode::Dir(dir)) => dir.refcount += 1,
+            _ => interface::report_corruption(&format!(
+                "cage directory {:?} is not a directory",
+                dir_container
+            )),
+        }
     } else {
-        panic!("Root directory inode was not a directory");
+        interface::report_corruption(&format!(
+            "cage directory {:?} does not exist",
+            dir_container
+        ));
     }
 }
 
+//a cage chrooted somewhere other than "/" holds a reference on its root directory like its cwd
+pub fn is_jailed(rootdir: &interface::RustPathBuf) -> bool {
+    rootdir.as_path() != interface::RustPath::new("/")
+}
+
 pub fn decref_dir(cwd_container: &interface::RustPathBuf) {
     if let Some(cwdinodenum) = metawalk(&cwd_container) {
-        if let Inode::Dir(ref mut cwddir) = *(FS_METADATA.inodetable.get_mut(&cwdinodenum).unwrap())
-        {
+        let mut inodeobj = FS_METADATA.inodetable.get_mut(&cwdinodenum);
+        if let Some(Inode::Dir(cwddir)) = inodeobj.as_deref_mut() {
             cwddir.refcount -= 1;
 
             //if the directory has been removed but this cwd was the last open handle to it
             if cwddir.refcount == 0 && cwddir.linkcount == 0 {
+                drop(inodeobj);
                 FS_METADATA.inodetable.remove(&cwdinodenum);
             }
         } else {
-            panic!("Cage had a cwd that was not a directory!");
+            interface::report_corruption(&format!(
+                "cage directory {:?} is not a directory",
+                cwd_container
+            ));
         }
     } else {
-        panic!("Cage had a cwd which did not exist!");
+        interface::report_corruption(&format!(
+            "cage directory {:?} does not exist",
+            cwd_container
+        ));
     } //we probably want to handle this case, maybe cwd should be an inode number?? Not urgent
 }
@@ -0,0 +1,236 @@
+// inotify watcher registry and event queues
+#![allow(dead_code)]
+
+use super::context::ContextGlobal;
+use super::syscalls::fs_constants::*;
+use crate::interface;
+
+//Maps an inode number to every watch placed on it, across the cages of a machine. Filesystem syscalls publish
+//events by looking up the affected inode here, so the common case of an unwatched inode is a
+//single failed hashmap lookup.
+pub static INOTIFY_WATCHES: ContextGlobal<interface::RustHashMap<usize, Vec<InotifyWatch>>> =
+    ContextGlobal::new(|context| &context.fs.inotifywatches);
+
+//rename pairs IN_MOVED_FROM and IN_MOVED_TO events through a shared cookie
+static NEXT_COOKIE: interface::RustAtomicU32 = interface::RustAtomicU32::new(1);
+
+#[derive(Debug, Clone)]
+pub struct InotifyEvent {
+    pub wd: i32,
+    pub mask: u32,
+    pub cookie: u32,
+    pub name: Option<String>,
+}
+
+impl InotifyEvent {
+    //length of the name field as reported to the user: null terminated and padded to 4 bytes
+    pub fn name_len(&self) -> usize {
+        match &self.name {
+            Some(name) => (name.len() + 1 + 3) & !3,
+            None => 0,
+        }
+    }
+
+    pub fn packed_len(&self) -> usize {
+        INOTIFY_EVENT_HEADER_SIZE + self.name_len()
+    }
+
+    //serialize the event as a struct inotify_event
+    pub fn pack(&self) -> Vec<u8> {
+        let namelen = self.name_len();
+        let mut bytes = Vec::with_capacity(INOTIFY_EVENT_HEADER_SIZE + namelen);
+        bytes.extend_from_slice(&self.wd.to_ne_bytes());
+        bytes.extend_from_slice(&self.mask.to_ne_bytes());
+        bytes.extend_from_slice(&self.cookie.to_ne_bytes());
+        bytes.extend_from_slice(&(namelen as u32).to_ne_bytes());
+        if let Some(name) = &self.name {
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.resize(INOTIFY_EVENT_HEADER_SIZE + namelen, 0);
+        }
+        bytes
+    }
+}
+
+//The queue is shared between the inotify instance and every watch it has registered, so that
+//publishers never have to touch the file descriptor table
+#[derive(Debug)]
+pub struct InotifyQueue {
+    pub events: interface::Mutex<interface::RustDeque<InotifyEvent>>,
+}
+
+impl InotifyQueue {
+    pub fn push(&self, event: InotifyEvent) {
+        let mut events = self.events.lock();
+        if events.len() >= INOTIFY_MAX_QUEUED_EVENTS {
+            return;
+        }
+        //coalesce identical back to back events as linux does
+        if let Some(last) = events.back() {
+            if last.wd == event.wd
+                && last.mask == event.mask
+                && last.cookie == event.cookie
+                && last.name == event.name
+            {
+                return;
+            }
+        }
+        events.push_back(event);
+        interface::notify_readiness();
+    }
+
+    pub fn has_events(&self) -> bool {
+        !self.events.lock().is_empty()
+    }
+}
+
+//maps watch descriptor to inode number
+pub type InotifyWds = interface::RustHashMap<i32, usize>;
+
+#[derive(Debug, Clone)]
+pub struct InotifyWatch {
+    pub wd: i32,
+    pub mask: u32,
+    pub queue: interface::RustRfc<InotifyQueue>,
+    pub wds: interface::RustRfc<InotifyWds>, // the owning instance's, so a watch can drop itself
+}
+
+impl InotifyWatch {
+    //the watch is gone without inotify_rm_watch being called, so its wd is released here
+    fn ignored(&self) {
+        self.wds.remove(&self.wd);
+        self.queue.push(InotifyEvent {
+            wd: self.wd,
+            mask: IN_IGNORED,
+            cookie: 0,
+            name: None,
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct InotifyInstance {
+    pub queue: interface::RustRfc<InotifyQueue>,
+    pub watches: interface::RustRfc<InotifyWds>,
+    pub nextwd: interface::RustAtomicI32,
+}
+
+impl InotifyInstance {
+    pub fn new() -> InotifyInstance {
+        InotifyInstance {
+            queue: interface::RustRfc::new(InotifyQueue {
+                events: interface::Mutex::new(interface::RustDeque::new()),
+            }),
+            watches: interface::RustRfc::new(interface::RustHashMap::new()),
+            nextwd: interface::RustAtomicI32::new(1),
+        }
+    }
+
+    //add or update a watch on inodenum, returning its watch descriptor
+    pub fn add_watch(&self, inodenum: usize, mask: u32) -> i32 {
+        let mut watchlist = INOTIFY_WATCHES.entry(inodenum).or_insert(vec![]);
+        for watch in watchlist.iter_mut() {
+            if interface::RustRfc::ptr_eq(&watch.queue, &self.queue) {
+                watch.mask = if mask & IN_MASK_ADD != 0 {
+                    watch.mask | mask
+                } else {
+                    mask
+                };
+                return watch.wd;
+            }
+        }
+        let wd = self
+            .nextwd
+            .fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+        watchlist.push(InotifyWatch {
+            wd: wd,
+            mask: mask,
+            queue: self.queue.clone(),
+            wds: self.watches.clone(),
+        });
+        self.watches.insert(wd, inodenum);
+        wd
+    }
+
+    pub fn rm_watch(&self, wd: i32) -> bool {
+        if let Some((_, inodenum)) = self.watches.remove(&wd) {
+            _remove_watch_entry(inodenum, wd, &self.queue);
+            self.queue.push(InotifyEvent {
+                wd: wd,
+                mask: IN_IGNORED,
+                cookie: 0,
+                name: None,
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    //drop every watch this instance holds, called when the last fd referring to it is closed
+    pub fn release(&self) {
+        //collected first, as publishers lock the registry before our wds
+        let watches: Vec<(i32, usize)> = self
+            .watches
+            .iter()
+            .map(|entry| (*entry.key(), *entry.value()))
+            .collect();
+        self.watches.clear();
+        for (wd, inodenum) in watches {
+            _remove_watch_entry(inodenum, wd, &self.queue);
+        }
+    }
+}
+
+fn _remove_watch_entry(inodenum: usize, wd: i32, queue: &interface::RustRfc<InotifyQueue>) {
+    if let interface::RustHashEntry::Occupied(mut occ) = INOTIFY_WATCHES.entry(inodenum) {
+        occ.get_mut()
+            .retain(|watch| !(watch.wd == wd && interface::RustRfc::ptr_eq(&watch.queue, queue)));
+        if occ.get().is_empty() {
+            occ.remove();
+        }
+    }
+}
+
+pub fn inotify_new_cookie() -> u32 {
+    NEXT_COOKIE.fetch_add(1, interface::RustAtomicOrdering::Relaxed)
+}
+
+//queue an event for every watch on inodenum whose mask includes it
+pub fn inotify_publish(inodenum: usize, mask: u32, cookie: u32, name: Option<&str>) {
+    if let Some(mut watchlist) = INOTIFY_WATCHES.get_mut(&inodenum) {
+        //a oneshot watch is removed as soon as it fires, just as if inotify_rm_watch were called
+        watchlist.retain(|watch| {
+            if watch.mask & mask & IN_ALL_EVENTS == 0 {
+                return true;
+            }
+            watch.queue.push(InotifyEvent {
+                wd: watch.wd,
+                mask: mask,
+                cookie: cookie,
+                name: name.map(|n| n.to_string()),
+            });
+            if watch.mask & IN_ONESHOT != 0 {
+                watch.ignored();
+                return false;
+            }
+            true
+        });
+    }
+}
+
+//the inode itself went away, so all watches on it are implicitly removed
+pub fn inotify_inode_removed(inodenum: usize) {
+    if let Some((_, watchlist)) = INOTIFY_WATCHES.remove(&inodenum) {
+        for watch in watchlist {
+            if watch.mask & IN_DELETE_SELF != 0 {
+                watch.queue.push(InotifyEvent {
+                    wd: watch.wd,
+                    mask: IN_DELETE_SELF,
+                    cookie: 0,
+                    name: None,
+                });
+            }
+            watch.ignored();
+        }
+    }
+}
@@ -0,0 +1,44 @@
+// Lifecycle hooks, so an embedder can follow cages being created, forked, exec'd and exiting
+// without polling the cage table
+#![allow(dead_code)]
+
+use crate::interface;
+
+pub const LIFECYCLE_CREATE: i32 = 0; // a cage made from nothing, like init
+pub const LIFECYCLE_FORK: i32 = 1; // relatedid is the parent
+pub const LIFECYCLE_EXEC: i32 = 2; // relatedid is the cage id the old image had
+pub const LIFECYCLE_EXIT: i32 = 3; // status is the wait status the parent will see
+
+//called with the event, the cage it happened to, the cage it relates to, and the exit status
+pub type LifecycleHook = extern "C" fn(event: i32, cageid: u64, relatedid: u64, status: i32);
+
+//hooks are kept with the handle they were registered under, in the order they were registered
+static LIFECYCLE_HOOKS: interface::RustLazyGlobal<interface::RustLock<Vec<(u64, LifecycleHook)>>> =
+    interface::RustLazyGlobal::new(|| interface::RustLock::new(vec![]));
+static NEXT_HOOK_HANDLE: interface::RustAtomicU64 = interface::RustAtomicU64::new(1);
+
+pub fn register_lifecycle_hook(hook: LifecycleHook) -> u64 {
+    let handle = NEXT_HOOK_HANDLE.fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+    LIFECYCLE_HOOKS.write().push((handle, hook));
+    handle
+}
+
+pub fn unregister_lifecycle_hook(handle: u64) -> bool {
+    let mut hooks = LIFECYCLE_HOOKS.write();
+    let before = hooks.len();
+    hooks.retain(|(registered, _)| *registered != handle);
+    hooks.len() != before
+}
+
+//The hooks run on the thread that caused the event, after the cage table reflects it, and with
+//no cage locks held, so a hook may look the cage up or make syscalls of its own.
+pub fn fire_lifecycle_hooks(event: i32, cageid: u64, relatedid: u64, status: i32) {
+    let hooks: Vec<LifecycleHook> = LIFECYCLE_HOOKS
+        .read()
+        .iter()
+        .map(|(_, hook)| *hook)
+        .collect();
+    for hook in hooks {
+        hook(event, cageid, relatedid, status);
+    }
+}
@@ -0,0 +1,182 @@
+// Per-syscall call counts, errors and latencies, kept for each cage for embedders to monitor
+#![allow(dead_code)]
+
+use crate::interface;
+
+//latencies are counted in buckets of powers of two nanoseconds, the last one taking everything
+//from about a second up
+const LATENCY_BUCKETS: usize = 32;
+
+static METRICS_ENABLED: interface::RustLazyGlobal<interface::RustAtomicBool> =
+    interface::RustLazyGlobal::new(|| interface::RustAtomicBool::new(false));
+
+//keyed by cage id and syscall number
+static SYSCALL_STATS: interface::RustLazyGlobal<
+    interface::RustHashMap<(u64, i32), interface::RustRfc<SyscallStats>>,
+> = interface::RustLazyGlobal::new(|| interface::RustHashMap::new());
+
+struct SyscallStats {
+    calls: interface::RustAtomicU64,
+    latency: [interface::RustAtomicU64; LATENCY_BUCKETS],
+    errors: interface::Mutex<interface::RustBTreeMap<i32, u64>>, // by errno, only touched on failure
+}
+
+impl SyscallStats {
+    fn new() -> SyscallStats {
+        SyscallStats {
+            calls: interface::RustAtomicU64::new(0),
+            latency: Default::default(),
+            errors: interface::Mutex::new(interface::RustBTreeMap::new()),
+        }
+    }
+}
+
+//A snapshot of the metrics of one syscall made by one cage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyscallMetrics {
+    pub cageid: u64,
+    pub callnum: i32,
+    pub calls: u64,
+    pub errors: interface::RustBTreeMap<i32, u64>, // failed calls by errno
+    pub p50: interface::RustDuration, // latency percentiles, each the upper end of its bucket
+    pub p90: interface::RustDuration,
+    pub p99: interface::RustDuration,
+}
+
+impl SyscallMetrics {
+    pub fn error_count(&self) -> u64 {
+        self.errors.values().sum()
+    }
+}
+
+//Metrics are off until an embedder turns them on, so that a syscall doesn't pay for reading the
+//clock unless somebody is looking
+pub fn set_syscall_metrics_enabled(enabled: bool) {
+    METRICS_ENABLED.store(enabled, interface::RustAtomicOrdering::Relaxed);
+}
+
+pub fn syscall_metrics_enabled() -> bool {
+    METRICS_ENABLED.load(interface::RustAtomicOrdering::Relaxed)
+}
+
+//when a syscall being counted started, or None if metrics are off
+pub fn syscall_started() -> Option<interface::RustInstant> {
+    if syscall_metrics_enabled() {
+        Some(interface::starttimer())
+    } else {
+        None
+    }
+}
+
+//count a syscall that started at start and returned retval, negative retvals being errnos
+pub fn syscall_finished(
+    cageid: u64,
+    callnum: i32,
+    start: Option<interface::RustInstant>,
+    retval: i32,
+) {
+    let start = match start {
+        Some(start) => start,
+        None => return,
+    };
+    let nanos = interface::readtimer(start).as_nanos() as u64;
+
+    let stats = match SYSCALL_STATS.get(&(cageid, callnum)) {
+        Some(stats) => stats.clone(),
+        None => SYSCALL_STATS
+            .entry((cageid, callnum))
+            .or_insert_with(|| interface::RustRfc::new(SyscallStats::new()))
+            .clone(),
+    };
+    stats
+        .calls
+        .fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+    let bucket = interface::rust_min((64 - nanos.leading_zeros()) as usize, LATENCY_BUCKETS - 1);
+    stats.latency[bucket].fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+    if retval < 0 {
+        *stats.errors.lock().entry(-retval).or_insert(0) += 1;
+    }
+}
+
+fn percentile(
+    counts: &[u64; LATENCY_BUCKETS],
+    total: u64,
+    percent: u64,
+) -> interface::RustDuration {
+    if total == 0 {
+        return interface::RustDuration::ZERO;
+    }
+    let rank = (total * percent + 99) / 100;
+    let mut seen = 0;
+    for (bucket, count) in counts.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return interface::RustDuration::from_nanos((1u64 << bucket) - 1);
+        }
+    }
+    interface::RustDuration::from_nanos((1u64 << (LATENCY_BUCKETS - 1)) - 1)
+}
+
+fn snapshot(cageid: u64, callnum: i32, stats: &SyscallStats) -> SyscallMetrics {
+    let mut counts = [0u64; LATENCY_BUCKETS];
+    for (bucket, count) in stats.latency.iter().enumerate() {
+        counts[bucket] = count.load(interface::RustAtomicOrdering::Relaxed);
+    }
+    //the buckets are read one by one while calls go on, so the percentiles go by what they add up to
+    let total = counts.iter().sum();
+    SyscallMetrics {
+        cageid: cageid,
+        callnum: callnum,
+        calls: stats.calls.load(interface::RustAtomicOrdering::Relaxed),
+        errors: stats.errors.lock().clone(),
+        p50: percentile(&counts, total, 50),
+        p90: percentile(&counts, total, 90),
+        p99: percentile(&counts, total, 99),
+    }
+}
+
+//the metrics of every syscall any cage has made, ordered by cage and then syscall
+pub fn syscall_metrics() -> Vec<SyscallMetrics> {
+    let mut metrics: Vec<SyscallMetrics> = SYSCALL_STATS
+        .iter()
+        .map(|entry| snapshot(entry.key().0, entry.key().1, entry.value()))
+        .collect();
+    metrics.sort_by_key(|m| (m.cageid, m.callnum));
+    metrics
+}
+
+pub fn cage_syscall_metrics(cageid: u64) -> Vec<SyscallMetrics> {
+    syscall_metrics()
+        .into_iter()
+        .filter(|m| m.cageid == cageid)
+        .collect()
+}
+
+pub fn reset_syscall_metrics() {
+    SYSCALL_STATS.clear();
+}
+
+//the metrics as /proc/lind/stats shows them, a line for each cage and syscall
+pub fn syscall_metrics_text() -> String {
+    let mut text = String::from("cage\tsyscall\tcalls\terrors\tp50_ns\tp90_ns\tp99_ns\terrnos\n");
+    for m in syscall_metrics() {
+        let errnos = m
+            .errors
+            .iter()
+            .map(|(errno, count)| format!("{}:{}", errno, count))
+            .collect::<Vec<String>>()
+            .join(",");
+        text.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            m.cageid,
+            m.callnum,
+            m.calls,
+            m.error_count(),
+            m.p50.as_nanos(),
+            m.p90.as_nanos(),
+            m.p99.as_nanos(),
+            if errnos.is_empty() { "-" } else { &errnos }
+        ));
+    }
+    text
+}
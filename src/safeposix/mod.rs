@@ -1,6 +1,21 @@
 pub mod cage;
+pub mod cancel;
+pub mod context;
+pub mod devices;
 pub mod dispatcher;
+pub mod dump;
+pub mod fasync;
 pub mod filesystem;
+pub mod inotify;
+pub mod lifecycle;
+pub mod metrics;
+pub mod mqueue;
+pub mod msg;
 pub mod net;
+pub mod procfs;
+pub mod quota;
+pub mod sem;
 pub mod shm;
 pub mod syscalls;
+pub mod tar;
+pub mod tty;
@@ -0,0 +1,190 @@
+// POSIX message queues
+#![allow(dead_code)]
+
+use super::syscalls::sys_constants::*;
+use crate::interface;
+use crate::interface::errnos::{syscall_error, Errno};
+
+//Queues live in their own namespace rather than the filesystem, as they do under /dev/mqueue.
+//Unlinking a name only removes it from here; descriptors that are still open keep the queue.
+pub static MQUEUE_TABLE: interface::RustLazyGlobal<
+    interface::RustHashMap<String, interface::RustRfc<MqueueInstance>>,
+> = interface::RustLazyGlobal::new(|| interface::RustHashMap::new());
+
+#[derive(Debug)]
+pub struct MqMessage {
+    pub prio: u32,
+    pub data: Vec<u8>,
+}
+
+//the cage to notify the next time a message arrives on an empty queue, and how
+#[derive(Debug, Clone, Copy)]
+pub struct MqNotification {
+    pub cageid: u64,
+    pub notify: i32,
+    pub signo: i32,
+    pub value: u64, // the sigev_value the signal carries
+}
+
+#[derive(Debug)]
+pub struct MqueueState {
+    pub messages: interface::RustDeque<MqMessage>, // highest priority first, oldest first within a priority
+    pub receivers: usize,                          // receivers blocked waiting for a message
+    pub notification: Option<MqNotification>,
+}
+
+#[derive(Debug)]
+pub struct MqueueInstance {
+    pub maxmsg: usize,
+    pub msgsize: usize,
+    pub mode: u32,
+    pub state: interface::Mutex<MqueueState>,
+    pub cv: interface::Condvar, // signalled whenever a message is sent or received
+}
+
+impl MqueueInstance {
+    pub fn new(maxmsg: usize, msgsize: usize, mode: u32) -> MqueueInstance {
+        MqueueInstance {
+            maxmsg: maxmsg,
+            msgsize: msgsize,
+            mode: mode,
+            state: interface::Mutex::new(MqueueState {
+                messages: interface::RustDeque::new(),
+                receivers: 0,
+                notification: None,
+            }),
+            cv: interface::Condvar::new(),
+        }
+    }
+
+    pub fn send(
+        &self,
+        data: Vec<u8>,
+        prio: u32,
+        nonblocking: bool,
+        senderid: u64,
+        interrupted: &dyn Fn() -> Option<i32>,
+    ) -> i32 {
+        let mut state = self.state.lock();
+        while state.messages.len() >= self.maxmsg {
+            if nonblocking {
+                return syscall_error(
+                    Errno::EAGAIN,
+                    "mq_send",
+                    "the queue is full and O_NONBLOCK is set",
+                );
+            }
+            if let Some(interrupted) = interrupted() {
+                return interrupted;
+            }
+            self.cv
+                .wait_for(&mut state, interface::SIGNAL_CHECK_INTERVAL);
+        }
+
+        // a message arriving on an empty queue notifies the registered cage, but only if nobody
+        // is already waiting to receive it; the registration is used up either way
+        if state.messages.is_empty() && state.receivers == 0 {
+            if let Some(notification) = state.notification.take() {
+                if notification.notify == SIGEV_SIGNAL {
+                    let sender = interface::cagetable_getref_opt(senderid);
+                    interface::lind_sigqueue_from_id(
+                        notification.cageid,
+                        interface::SiginfoStruct {
+                            si_signo: notification.signo,
+                            si_errno: 0,
+                            si_code: SI_MESGQ,
+                            si_pid: senderid as i32,
+                            si_uid: sender.map_or(0, |cage| cage.cred.read().ruid),
+                            si_status: 0,
+                            si_value: notification.value,
+                            si_timerid: 0,
+                            si_overrun: 0,
+                        },
+                    );
+                }
+            }
+        }
+
+        let position = state
+            .messages
+            .iter()
+            .position(|message| message.prio < prio)
+            .unwrap_or(state.messages.len());
+        state.messages.insert(
+            position,
+            MqMessage {
+                prio: prio,
+                data: data,
+            },
+        );
+        self.cv.notify_all();
+        interface::notify_readiness();
+        0
+    }
+
+    pub fn receive(
+        &self,
+        nonblocking: bool,
+        interrupted: &dyn Fn() -> Option<i32>,
+    ) -> Result<MqMessage, i32> {
+        let mut state = self.state.lock();
+        while state.messages.is_empty() {
+            if nonblocking {
+                return Err(syscall_error(
+                    Errno::EAGAIN,
+                    "mq_receive",
+                    "the queue is empty and O_NONBLOCK is set",
+                ));
+            }
+            if let Some(interrupted) = interrupted() {
+                return Err(interrupted);
+            }
+            state.receivers += 1;
+            self.cv
+                .wait_for(&mut state, interface::SIGNAL_CHECK_INTERVAL);
+            state.receivers -= 1;
+        }
+
+        let message = state.messages.pop_front().unwrap();
+        self.cv.notify_all();
+        interface::notify_readiness();
+        Ok(message)
+    }
+
+    pub fn curmsgs(&self) -> usize {
+        self.state.lock().messages.len()
+    }
+
+    // only one cage at a time may be registered for notification
+    pub fn set_notification(&self, notification: Option<MqNotification>, cageid: u64) -> i32 {
+        let mut state = self.state.lock();
+        match notification {
+            Some(notification) => {
+                if state.notification.is_some() {
+                    return syscall_error(
+                        Errno::EBUSY,
+                        "mq_notify",
+                        "another cage is already registered for notification",
+                    );
+                }
+                state.notification = Some(notification);
+            }
+            None => {
+                if let Some(current) = state.notification {
+                    if current.cageid == cageid {
+                        state.notification = None;
+                    }
+                }
+            }
+        }
+        0
+    }
+
+    pub fn check_select_read(&self) -> bool {
+        !self.state.lock().messages.is_empty()
+    }
+
+    pub fn check_select_write(&self) -> bool {
+        self.state.lock().messages.len() < self.maxmsg
+    }
+}
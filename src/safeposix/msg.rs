@@ -0,0 +1,240 @@
+// System V message queues
+#![allow(dead_code)]
+
+use super::syscalls::fs_constants::*;
+use crate::interface;
+use crate::interface::errnos::{syscall_error, Errno};
+
+pub static MSG_METADATA: interface::RustLazyGlobal<interface::RustRfc<MsgMetadata>> =
+    interface::RustLazyGlobal::new(|| interface::RustRfc::new(MsgMetadata::init_msg_metadata()));
+
+pub struct Message {
+    pub mtype: i64,
+    pub mtext: Vec<u8>,
+}
+
+pub struct MessageQueue {
+    pub key: i32,
+    pub state: interface::Mutex<MsgQueueState>,
+    pub cv: interface::Condvar, // signalled whenever a message comes or goes, the limit changes, or the queue is removed
+}
+
+pub struct MsgQueueState {
+    pub msqinfo: interface::MsqidsStruct,
+    pub messages: interface::RustDeque<Message>,
+    pub removed: bool,
+}
+
+pub fn new_message_queue(key: i32, uid: u32, gid: u32, mode: u16) -> MessageQueue {
+    MessageQueue::new(key, uid, gid, mode)
+}
+
+impl MessageQueue {
+    pub fn new(key: i32, uid: u32, gid: u32, mode: u16) -> MessageQueue {
+        let time = interface::timestamp() as isize;
+        let permstruct = interface::IpcPermStruct {
+            __key: key,
+            uid: uid,
+            gid: gid,
+            cuid: uid,
+            cgid: gid,
+            mode: mode,
+            __pad1: 0,
+            __seq: 0,
+            __pad2: 0,
+            __unused1: 0,
+            __unused2: 0,
+        };
+        let msqinfo = interface::MsqidsStruct {
+            msg_perm: permstruct,
+            msg_stime: 0,
+            msg_rtime: 0,
+            msg_ctime: time,
+            msg_cbytes: 0,
+            msg_qnum: 0,
+            msg_qbytes: MSGMNB as u64,
+            msg_lspid: 0,
+            msg_lrpid: 0,
+        };
+
+        MessageQueue {
+            key: key,
+            state: interface::Mutex::new(MsgQueueState {
+                msqinfo: msqinfo,
+                messages: interface::RustDeque::new(),
+                removed: false,
+            }),
+            cv: interface::Condvar::new(),
+        }
+    }
+
+    // queue a message once it fits under the byte limit, waiting for room unless IPC_NOWAIT is set
+    pub fn send(
+        &self,
+        mtype: i64,
+        mtext: Vec<u8>,
+        msgflg: i32,
+        cageid: u64,
+        interrupted: &dyn Fn() -> Option<i32>,
+    ) -> i32 {
+        let mut state = self.state.lock();
+        loop {
+            if state.removed {
+                return syscall_error(Errno::EIDRM, "msgsnd", "the message queue was removed");
+            }
+            if state.msqinfo.msg_cbytes + mtext.len() as u64 <= state.msqinfo.msg_qbytes {
+                break;
+            }
+            if msgflg & IPC_NOWAIT != 0 {
+                return syscall_error(
+                    Errno::EAGAIN,
+                    "msgsnd",
+                    "the queue is full and IPC_NOWAIT was specified",
+                );
+            }
+            if let Some(interrupted) = interrupted() {
+                return interrupted;
+            }
+            self.cv
+                .wait_for(&mut state, interface::SIGNAL_CHECK_INTERVAL);
+        }
+
+        state.msqinfo.msg_cbytes += mtext.len() as u64;
+        state.msqinfo.msg_qnum += 1;
+        state.msqinfo.msg_lspid = cageid as u32;
+        state.msqinfo.msg_stime = interface::timestamp() as isize;
+        state.messages.push_back(Message {
+            mtype: mtype,
+            mtext: mtext,
+        });
+        self.cv.notify_all();
+        0
+    }
+
+    // take the first message msgtyp selects, waiting for one to arrive unless IPC_NOWAIT is set
+    pub fn receive(
+        &self,
+        msgtyp: i64,
+        maxsize: usize,
+        msgflg: i32,
+        cageid: u64,
+        interrupted: &dyn Fn() -> Option<i32>,
+    ) -> Result<Message, i32> {
+        let mut state = self.state.lock();
+        let index = loop {
+            if state.removed {
+                return Err(syscall_error(
+                    Errno::EIDRM,
+                    "msgrcv",
+                    "the message queue was removed",
+                ));
+            }
+            if let Some(index) = Self::_select_message(&state.messages, msgtyp, msgflg) {
+                break index;
+            }
+            if msgflg & IPC_NOWAIT != 0 {
+                return Err(syscall_error(
+                    Errno::ENOMSG,
+                    "msgrcv",
+                    "no message of the requested type and IPC_NOWAIT was specified",
+                ));
+            }
+            if let Some(interrupted) = interrupted() {
+                return Err(interrupted);
+            }
+            self.cv
+                .wait_for(&mut state, interface::SIGNAL_CHECK_INTERVAL);
+        };
+
+        // a message that doesn't fit stays queued unless the caller accepts truncation
+        if state.messages[index].mtext.len() > maxsize && msgflg & MSG_NOERROR == 0 {
+            return Err(syscall_error(
+                Errno::EBIG,
+                "msgrcv",
+                "the message is longer than msgsz and MSG_NOERROR was not specified",
+            ));
+        }
+
+        let mut message = state.messages.remove(index).unwrap();
+        state.msqinfo.msg_cbytes -= message.mtext.len() as u64;
+        state.msqinfo.msg_qnum -= 1;
+        state.msqinfo.msg_lrpid = cageid as u32;
+        state.msqinfo.msg_rtime = interface::timestamp() as isize;
+        self.cv.notify_all();
+
+        message.mtext.truncate(maxsize);
+        Ok(message)
+    }
+
+    // 0 takes the oldest message, a positive type the oldest of that type (or of any other type
+    // with MSG_EXCEPT), and a negative type the oldest of the lowest type not above its magnitude
+    fn _select_message(
+        messages: &interface::RustDeque<Message>,
+        msgtyp: i64,
+        msgflg: i32,
+    ) -> Option<usize> {
+        if msgtyp == 0 {
+            return if messages.is_empty() { None } else { Some(0) };
+        }
+        if msgtyp > 0 {
+            let except = msgflg & MSG_EXCEPT != 0;
+            return messages
+                .iter()
+                .position(|message| (message.mtype == msgtyp) != except);
+        }
+
+        let mut selected: Option<usize> = None;
+        for (index, message) in messages.iter().enumerate() {
+            if message.mtype <= -msgtyp
+                && selected.map_or(true, |current| message.mtype < messages[current].mtype)
+            {
+                selected = Some(index);
+            }
+        }
+        selected
+    }
+
+    pub fn stat(&self) -> interface::MsqidsStruct {
+        self.state.lock().msqinfo
+    }
+
+    // update the owner, mode and byte limit, letting blocked senders retry against the new limit
+    pub fn set(&self, buf: &interface::MsqidsStruct) {
+        let mut state = self.state.lock();
+        state.msqinfo.msg_perm.uid = buf.msg_perm.uid;
+        state.msqinfo.msg_perm.gid = buf.msg_perm.gid;
+        state.msqinfo.msg_perm.mode = buf.msg_perm.mode & 0x1FF;
+        state.msqinfo.msg_qbytes = buf.msg_qbytes;
+        state.msqinfo.msg_ctime = interface::timestamp() as isize;
+        self.cv.notify_all();
+    }
+
+    // wake everyone waiting on the queue so they see it is gone
+    pub fn remove(&self) {
+        let mut state = self.state.lock();
+        state.removed = true;
+        state.messages.clear();
+        self.cv.notify_all();
+    }
+}
+
+pub struct MsgMetadata {
+    pub nextid: interface::RustAtomicI32,
+    pub msgkeyidtable: interface::RustHashMap<i32, i32>,
+    pub msgtable: interface::RustHashMap<i32, interface::RustRfc<MessageQueue>>,
+}
+
+impl MsgMetadata {
+    pub fn init_msg_metadata() -> MsgMetadata {
+        MsgMetadata {
+            nextid: interface::RustAtomicI32::new(1),
+            msgkeyidtable: interface::RustHashMap::new(),
+            msgtable: interface::RustHashMap::new(),
+        }
+    }
+
+    pub fn new_keyid(&self) -> i32 {
+        self.nextid
+            .fetch_add(1, interface::RustAtomicOrdering::Relaxed)
+    }
+}
@@ -1,4 +1,5 @@
 use super::cage::{Cage, FileDescriptor};
+use super::context::ContextGlobal;
 use super::syscalls::net_constants::*;
 use crate::interface;
 use crate::interface::errnos::{syscall_error, Errno};
@@ -18,28 +19,37 @@ const EPHEMERAL_PORT_RANGE_END: u16 = 60999;
 pub const TCPPORT: bool = true;
 pub const UDPPORT: bool = false;
 
-pub static NET_METADATA: interface::RustLazyGlobal<interface::RustRfc<NetMetadata>> =
-    interface::RustLazyGlobal::new(|| {
-        interface::RustRfc::new(NetMetadata {
-            used_port_set: interface::RustHashMap::new(),
-            next_ephemeral_port_tcpv4: interface::RustRfc::new(interface::RustLock::new(
-                EPHEMERAL_PORT_RANGE_END,
-            )),
-            next_ephemeral_port_udpv4: interface::RustRfc::new(interface::RustLock::new(
-                EPHEMERAL_PORT_RANGE_END,
-            )),
-            next_ephemeral_port_tcpv6: interface::RustRfc::new(interface::RustLock::new(
-                EPHEMERAL_PORT_RANGE_END,
-            )),
-            next_ephemeral_port_udpv6: interface::RustRfc::new(interface::RustLock::new(
-                EPHEMERAL_PORT_RANGE_END,
-            )),
-            listening_port_set: interface::RustHashSet::new(),
-            pending_conn_table: interface::RustHashMap::new(),
-            domsock_accept_table: interface::RustHashMap::new(), // manages domain socket connection process
-            domsock_paths: interface::RustHashSet::new(), // set of all currently bound domain sockets
-        })
-    }); //we want to check if fs exists before doing a blank init, but not for now
+//how long the local port of a closed tcp connection stays reserved, as in TIME_WAIT on linux, so
+//that what the peer still has in flight doesn't reach a new socket. SO_REUSEADDR binds it anyway
+pub const TIME_WAIT_INTERVAL: interface::RustDuration = interface::RustDuration::from_secs(60);
+
+pub static NET_METADATA: ContextGlobal<interface::RustRfc<NetMetadata>> =
+    ContextGlobal::new(|context| &*context.net);
+
+//the network tables of a new lind machine, see context.rs
+pub fn new_net_metadata() -> interface::RustRfc<NetMetadata> {
+    interface::RustRfc::new(NetMetadata {
+        used_port_set: interface::RustHashMap::new(),
+        next_ephemeral_port_tcpv4: interface::RustRfc::new(interface::RustLock::new(
+            EPHEMERAL_PORT_RANGE_END,
+        )),
+        next_ephemeral_port_udpv4: interface::RustRfc::new(interface::RustLock::new(
+            EPHEMERAL_PORT_RANGE_END,
+        )),
+        next_ephemeral_port_tcpv6: interface::RustRfc::new(interface::RustLock::new(
+            EPHEMERAL_PORT_RANGE_END,
+        )),
+        next_ephemeral_port_udpv6: interface::RustRfc::new(interface::RustLock::new(
+            EPHEMERAL_PORT_RANGE_END,
+        )),
+        listening_port_set: interface::RustHashSet::new(),
+        pending_conn_table: interface::RustHashMap::new(),
+        domsock_accept_table: interface::RustHashMap::new(), // manages domain socket connection process
+        domsock_paths: interface::RustHashMap::new(), // bound domain sockets, with the credentials of their binders
+        domsock_accept_waits: interface::RustHashMap::new(), // what accepts on a domain socket path sleep on
+        time_wait_ports: interface::RustHashMap::new(), // until when closed connections keep their ports
+    })
+}
 
 //A list of all network devices present on the machine
 //It is populated from a file that should be present prior to running rustposix, see
@@ -66,6 +76,69 @@ fn ips_from_ifaddrs() -> Vec<interface::GenIpaddr> {
     return ips;
 }
 
+//An interface as listed in the net_devices file, which gives each one's name, flags, address,
+//netmask and broadcast address. Interfaces are indexed from 1 in the order they are listed.
+#[derive(Debug, Clone)]
+pub struct NetDevice {
+    pub name: String,
+    pub flags: i32,
+    pub addr: interface::V4Addr,
+    pub netmask: interface::V4Addr,
+    pub broadaddr: interface::V4Addr,
+}
+
+impl NetDevice {
+    //the file has no MTUs, so each interface reports the usual one for its kind
+    pub fn mtu(&self) -> i32 {
+        if self.flags & IFF_LOOPBACK != 0 {
+            LOOPBACK_MTU
+        } else {
+            ETHERNET_MTU
+        }
+    }
+}
+
+pub static NET_DEVICES: interface::RustLazyGlobal<Vec<NetDevice>> =
+    interface::RustLazyGlobal::new(|| net_devices_from_ifaddrs());
+
+fn net_devices_from_ifaddrs() -> Vec<NetDevice> {
+    //a missing or unparsable address is reported as 0.0.0.0
+    let v4addr = |field: Option<&&str>| match field.map(|s| interface::GenIpaddr::from_string(s)) {
+        Some(Some(interface::GenIpaddr::V4(addr))) => addr,
+        _ => interface::V4Addr::default(),
+    };
+    let mut devices = vec![];
+    for net_device in NET_IFADDRS_STR.as_str().split('\n') {
+        if net_device == "" {
+            continue;
+        }
+        let fields: Vec<&str> = net_device.split(' ').collect();
+        devices.push(NetDevice {
+            name: fields[0].to_string(),
+            flags: fields.get(1).and_then(|s| s.parse().ok()).unwrap_or(0),
+            addr: v4addr(fields.get(2)),
+            netmask: v4addr(fields.get(3)),
+            broadaddr: v4addr(fields.get(4)),
+        });
+    }
+    devices
+}
+
+//the index and listing of the interface called name
+pub fn net_device_by_name(name: &str) -> Option<(usize, NetDevice)> {
+    NET_DEVICES
+        .iter()
+        .position(|device| device.name == name)
+        .map(|position| (position + 1, NET_DEVICES[position].clone()))
+}
+
+pub fn net_device_by_index(index: i32) -> Option<NetDevice> {
+    if index < 1 {
+        return None;
+    }
+    NET_DEVICES.get(index as usize - 1).cloned()
+}
+
 #[derive(Debug, Hash, Eq, PartialEq, Clone)]
 pub enum PortType {
     IPv4UDP,
@@ -110,6 +183,8 @@ pub struct UnixSocketInfo {
     pub sendpipe: Option<interface::RustRfc<interface::EmulatedPipe>>,
     pub receivepipe: Option<interface::RustRfc<interface::EmulatedPipe>>,
     pub inode: usize,
+    pub peercred: Option<interface::UCred>, // the peer's credentials once connected
+    pub boundpath: Option<interface::RustPathBuf>, // the path the socket bound, not one it shares
 }
 
 //This structure contains all socket-associated data that is not held in the fd
@@ -119,14 +194,21 @@ pub struct SocketHandle {
     pub socket_options: i32,
     pub tcp_options: i32,
     pub state: ConnState,
+    pub shut_rd: bool, // shut down for reading, the state still says whether it is connected
+    pub shut_wr: bool, // shut down for writing
     pub protocol: i32,
     pub domain: i32,
-    pub last_peek: interface::RustDeque<u8>,
+    //the recv side keeps its own lock, so a recv need only read-lock the handle and a send on the
+    //same socket can proceed alongside it
+    pub last_peek: interface::RustRfc<interface::Mutex<interface::RustDeque<u8>>>,
     pub localaddr: Option<interface::GenSockaddr>,
+    pub shares_listener_port: bool, // accepted, so the port in localaddr is the listener's to release
     pub remoteaddr: Option<interface::GenSockaddr>,
+    pub remote_hostaddr: Option<interface::HostSockaddr>, // remoteaddr ready for a udp send
     pub unix_info: Option<UnixSocketInfo>,
     pub socktype: i32,
     pub sndbuf: i32,
+    pub sndtimeo: Option<interface::RustDuration>, // SO_SNDTIMEO, which bounds a blocking connect
     pub rcvbuf: i32,
     pub errno: i32,
 }
@@ -158,15 +240,40 @@ impl ConnCondVar {
         }
     }
 
-    pub fn wait(&self) {
+    //wait until the connection is accepted or refused or timeout passes, saying whether it was
+    //either. Once the connecting side has waited, it counts as waiting even if it gave up since
+    pub fn wait_for(&self, timeout: interface::RustDuration) -> bool {
         let mut guard = self.lock.lock();
-        *guard += 1;
-        self.cv.wait(&mut guard);
+        if *guard == 0 {
+            *guard = 1;
+        }
+        if *guard == 1 {
+            self.cv.wait_for(&mut guard, timeout);
+        }
+        *guard >= 2
+    }
+
+    //for a listener closed before it accepted the connection
+    pub fn refuse(&self) {
+        let mut guard = self.lock.lock();
+        *guard = 3;
+        self.cv.notify_all();
+    }
+
+    pub fn refused(&self) -> bool {
+        *self.lock.lock() == 3
+    }
+
+    //wake the connecting side without accepting, so it looks at why it is waiting again
+    pub fn wake(&self) {
+        let _guard = self.lock.lock();
+        self.cv.notify_all();
     }
 
     pub fn broadcast(&self) -> bool {
-        let guard = self.lock.lock();
+        let mut guard = self.lock.lock();
         if *guard == 1 {
+            *guard = 2;
             self.cv.notify_all();
             return true;
         } else {
@@ -175,11 +282,47 @@ impl ConnCondVar {
     }
 }
 
+//Woken whenever a connection is queued in the accept table for a domain socket listener, so that
+//a blocking accept sleeps rather than polling the table
+#[derive(Debug)]
+pub struct AcceptCondVar {
+    lock: interface::Mutex<u64>, // how many times it has been woken
+    cv: interface::Condvar,
+}
+
+impl AcceptCondVar {
+    pub fn new() -> Self {
+        Self {
+            lock: interface::Mutex::new(0),
+            cv: interface::Condvar::new(),
+        }
+    }
+
+    //taken before looking at the accept table; a wait given it returns once woken after that
+    pub fn generation(&self) -> u64 {
+        *self.lock.lock()
+    }
+
+    pub fn wait_for(&self, generation: u64, timeout: interface::RustDuration) {
+        let mut guard = self.lock.lock();
+        if *guard == generation {
+            self.cv.wait_for(&mut guard, timeout);
+        }
+    }
+
+    pub fn notify(&self) {
+        let mut guard = self.lock.lock();
+        *guard += 1;
+        self.cv.notify_all();
+    }
+}
+
 pub struct DomsockTableEntry {
     pub sockaddr: interface::GenSockaddr,
     pub receive_pipe: interface::RustRfc<interface::EmulatedPipe>,
     pub send_pipe: interface::RustRfc<interface::EmulatedPipe>,
     pub cond_var: Option<interface::RustRfc<ConnCondVar>>,
+    pub peercred: interface::UCred, // the connecting cage's credentials
 }
 
 impl DomsockTableEntry {
@@ -209,15 +352,49 @@ pub struct NetMetadata {
         Vec<(Result<interface::Socket, i32>, interface::GenSockaddr)>,
     >,
     pub domsock_accept_table: interface::RustHashMap<interface::RustPathBuf, DomsockTableEntry>,
-    pub domsock_paths: interface::RustHashSet<interface::RustPathBuf>,
+    pub domsock_paths: interface::RustHashMap<interface::RustPathBuf, interface::UCred>,
+    pub domsock_accept_waits:
+        interface::RustHashMap<interface::RustPathBuf, interface::RustRfc<AcceptCondVar>>,
+    pub time_wait_ports:
+        interface::RustHashMap<(interface::GenIpaddr, u16, PortType), interface::RustInstant>,
 }
 
 impl NetMetadata {
+    //keep the local port of a tcp connection that has just closed for TIME_WAIT_INTERVAL
+    pub fn enter_time_wait(&self, addr: interface::GenIpaddr, port: u16, domain: i32) {
+        self.time_wait_ports.insert(
+            mux_port(addr, port, domain, TCPPORT),
+            interface::starttimer() + TIME_WAIT_INTERVAL,
+        );
+    }
+
+    //whether a closed connection still holds the port at the address, or at any address when
+    //either side is unspecified. Reservations that have run out are dropped along the way
+    fn in_time_wait(&self, tup: &(interface::GenIpaddr, u16, PortType)) -> bool {
+        if self.time_wait_ports.is_empty() {
+            return false;
+        }
+        let now = interface::starttimer();
+        self.time_wait_ports.retain(|_, expiry| *expiry > now);
+        self.time_wait_ports.iter().any(|entry| {
+            let (addr, port, porttype) = entry.key();
+            *port == tup.1
+                && *porttype == tup.2
+                && (*addr == tup.0 || addr.is_unspecified() || tup.0.is_unspecified())
+        })
+    }
+
     fn initialize_port(
         &self,
         tup: &(interface::GenIpaddr, u16, PortType),
         rebindability: u32,
     ) -> bool {
+        //an ephemeral port is never handed out while a closed connection holds it
+        if tup.2 == PortType::IPv4TCP || tup.2 == PortType::IPv6TCP {
+            if self.in_time_wait(tup) {
+                return false;
+            }
+        }
         let used_port_tup = (tup.1, tup.2.clone());
         if tup.0.is_unspecified() {
             let tupclone = used_port_tup.clone();
@@ -359,6 +536,7 @@ impl NetMetadata {
         protocol: i32,
         domain: i32,
         rebindability: bool,
+        reuseaddr: bool,
     ) -> Result<u16, i32> {
         if !NET_DEVICE_IPLIST.contains(&addr) {
             return Err(syscall_error(
@@ -384,7 +562,18 @@ impl NetMetadata {
                 muxed = mux_port(addr, port, domain, TCPPORT);
             }
         } else {
-            panic!("Unknown protocol was set on socket somehow");
+            return Err(interface::corruption_error(
+                "bind",
+                "unknown protocol set on socket",
+            ));
+        }
+
+        if protocol == IPPROTO_TCP && !reuseaddr && self.in_time_wait(&muxed) {
+            return Err(syscall_error(
+                Errno::EADDRINUSE,
+                "reserve port",
+                "a recently closed connection still holds the port",
+            ));
         }
 
         let usedport_muxed = (muxed.1, muxed.2);
@@ -511,10 +700,21 @@ impl NetMetadata {
         }
     }
 
+    //what accepts on the domain socket bound to path wait on for a connection to be queued
+    pub fn domsock_accept_cv(
+        &self,
+        path: &interface::RustPathBuf,
+    ) -> interface::RustRfc<AcceptCondVar> {
+        self.domsock_accept_waits
+            .entry(path.clone())
+            .or_insert_with(|| interface::RustRfc::new(AcceptCondVar::new()))
+            .clone()
+    }
+
     pub fn get_domainsock_paths(&self) -> Vec<interface::RustPathBuf> {
         let mut domainsock_paths: Vec<interface::RustPathBuf> = vec![];
         for ds_path in self.domsock_paths.iter() {
-            domainsock_paths.push(ds_path.clone());
+            domainsock_paths.push(ds_path.key().clone());
         } // get vector of domain sock table keys
         domainsock_paths
     }
@@ -562,3 +762,38 @@ pub fn update_readfds_from_kernel_select(
     }
     return kernel_ret;
 }
+
+// Same as update_readfds_from_kernel_select, but asks the cage's cached kernel epoll instance,
+// which only needs its registrations touched when the set of inet sockets changes
+pub fn update_readfds_from_kernel_epoll(
+    readfds: &mut interface::FdSet,
+    inet_info: &mut SelectInetInfo,
+    epoll_cache: &mut interface::KernelEpollCache,
+    retval: &mut i32,
+) -> i32 {
+    let mut rawfds: Vec<i32> = inet_info
+        .rawfd_lindfd_tuples
+        .iter()
+        .map(|(rawfd, _)| *rawfd)
+        .collect();
+    rawfds.sort_unstable();
+    rawfds.dedup();
+
+    let mut ready = vec![];
+    let kernel_ret = epoll_cache.ready_to_read(&rawfds, &mut ready);
+    if kernel_ret < 0 {
+        // leave it to select to report what is wrong with the set
+        return update_readfds_from_kernel_select(readfds, inet_info, retval);
+    }
+    *retval += kernel_ret;
+    for rawfd in ready {
+        if let Some((_, lindfd)) = inet_info
+            .rawfd_lindfd_tuples
+            .iter()
+            .find(|(tuplefd, _)| *tuplefd == rawfd)
+        {
+            readfds.set(*lindfd);
+        }
+    }
+    return kernel_ret;
+}
@@ -0,0 +1,411 @@
+// Synthetic procfs, regenerated from live cage state as its directories are walked
+#![allow(dead_code)]
+
+use super::context::ContextGlobal;
+use super::dump::{FdDump, FdKindDump};
+use super::filesystem::*;
+use super::metrics;
+use super::syscalls::fs_constants::*;
+use super::syscalls::sys_constants::*;
+use crate::interface;
+
+pub const PROCMOUNTPOINT: &str = "/proc";
+
+//virtualized memory figures reported in /proc/meminfo, in kB
+pub const PROC_MEMTOTAL_KB: usize = 4 * 1024 * 1024;
+pub const PROC_MEMFREE_KB: usize = 2 * 1024 * 1024;
+
+//Maps the inode of every generated procfs directory to what it shows. Path walks look up each
+//directory they pass through here, so anything outside of procfs costs one failed lookup.
+pub static PROC_NODES: ContextGlobal<interface::RustHashMap<usize, ProcNode>> =
+    ContextGlobal::new(|context| &context.fs.procnodes);
+
+//serializes regeneration so that concurrent walks can't create the same entry twice
+static PROC_REFRESH_LOCK: ContextGlobal<interface::Mutex<()>> =
+    ContextGlobal::new(|context| &context.fs.procrefreshlock);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcNode {
+    Root(usize),        // mount id
+    Cage(usize, u64),   // mount id, cage id
+    Fds(usize, u64),    // mount id, cage id
+    FdInfo(usize, u64), // mount id, cage id
+    Lind(usize),        // mount id
+}
+
+impl ProcNode {
+    fn mountid(&self) -> usize {
+        match self {
+            ProcNode::Root(mountid)
+            | ProcNode::Cage(mountid, _)
+            | ProcNode::Fds(mountid, _)
+            | ProcNode::FdInfo(mountid, _)
+            | ProcNode::Lind(mountid) => *mountid,
+        }
+    }
+}
+
+pub fn procfs_mount(mountid: usize, rootinode: usize) {
+    PROC_NODES.insert(rootinode, ProcNode::Root(mountid));
+}
+
+pub fn procfs_unmount(mountid: usize) {
+    PROC_NODES.retain(|_, node| node.mountid() != mountid);
+}
+
+//regenerate the contents of a procfs directory, returning whether inodenum was one
+pub fn procfs_refresh(inodenum: usize) -> bool {
+    let node = match PROC_NODES.get(&inodenum) {
+        Some(node) => *node,
+        None => return false,
+    };
+    let _guard = PROC_REFRESH_LOCK.lock();
+
+    match node {
+        ProcNode::Root(mountid) => {
+            let mut keep = vec![
+                "meminfo".to_string(),
+                "cpuinfo".to_string(),
+                "lind".to_string(),
+            ];
+            let meminfo = _proc_file(inodenum, "meminfo", mountid);
+            _proc_set_contents(meminfo, &_proc_meminfo());
+            let cpuinfo = _proc_file(inodenum, "cpuinfo", mountid);
+            _proc_set_contents(cpuinfo, &_proc_cpuinfo());
+            let linddir = _proc_dir(inodenum, "lind", mountid);
+            PROC_NODES.insert(linddir, ProcNode::Lind(mountid));
+
+            for cageid in 0..interface::MAXCAGEID as u64 {
+                if interface::cagetable_getref_opt(cageid).is_some() {
+                    let cagedir = _proc_dir(inodenum, &cageid.to_string(), mountid);
+                    PROC_NODES.insert(cagedir, ProcNode::Cage(mountid, cageid));
+                    keep.push(cageid.to_string());
+                }
+            }
+            _proc_prune(inodenum, &keep);
+        }
+        ProcNode::Cage(mountid, cageid) => {
+            let cage = match interface::cagetable_getref_opt(cageid) {
+                Some(cage) => cage,
+                None => return true,
+            };
+            let status = _proc_file(inodenum, "status", mountid);
+            let cred = cage.cred.read().clone();
+            let statustext = format!(
+                "Name:\tlind\nState:\tR (running)\nTgid:\t{}\nPid:\t{}\nPPid:\t{}\nUid:\t{4}\t{5}\t{6}\t{5}\nGid:\t{7}\t{8}\t{9}\t{8}\nFDSize:\t{3}\nGroups:\t{11}\nThreads:\t{10}\nCapInh:\t{12:016x}\nCapPrm:\t{13:016x}\nCapEff:\t{14:016x}\nCapBnd:\t{15:016x}\n",
+                cageid,
+                cageid,
+                cage.parent,
+                cage.filedescriptortable.allocated(),
+                cred.ruid,
+                cred.euid,
+                cred.suid,
+                cred.rgid,
+                cred.egid,
+                cred.sgid,
+                interface::rust_max(cage.thread_table.len(), 1),
+                cred.groups
+                    .iter()
+                    .map(|gid| gid.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                cred.cap_inheritable,
+                cred.cap_permitted,
+                cred.cap_effective,
+                cred.cap_bounding,
+            );
+            _proc_set_contents(status, &statustext);
+            let fddir = _proc_dir(inodenum, "fd", mountid);
+            PROC_NODES.insert(fddir, ProcNode::Fds(mountid, cageid));
+            let fdinfodir = _proc_dir(inodenum, "fdinfo", mountid);
+            PROC_NODES.insert(fdinfodir, ProcNode::FdInfo(mountid, cageid));
+        }
+        ProcNode::Fds(mountid, cageid) => {
+            let cage = match interface::cagetable_getref_opt(cageid) {
+                Some(cage) => cage,
+                None => return true,
+            };
+            //a descriptor locked for writing is being used, possibly by the walk itself, and its
+            //entry keeps whatever target it was last given
+            let fds = cage.fd_dumps();
+            let paths = _proc_fd_paths(&fds);
+            let mut keep = vec![];
+            for fddump in fds.iter() {
+                let fdname = fddump.fd.to_string();
+                let fdinode = _proc_file(inodenum, &fdname, mountid);
+                _proc_set_mode(fdinode, S_IFLNK as u32 | S_IRWXU);
+                if let Some(desc) = &fddump.desc {
+                    _proc_set_contents(fdinode, &_proc_fd_target(&desc.kind, &paths));
+                }
+                keep.push(fdname);
+            }
+            _proc_prune(inodenum, &keep);
+        }
+        ProcNode::FdInfo(mountid, cageid) => {
+            let cage = match interface::cagetable_getref_opt(cageid) {
+                Some(cage) => cage,
+                None => return true,
+            };
+            let mut keep = vec![];
+            for fddump in cage.fd_dumps() {
+                let fdname = fddump.fd.to_string();
+                let fdinode = _proc_file(inodenum, &fdname, mountid);
+                if let Some(desc) = &fddump.desc {
+                    let (pos, mntid, ino) = match desc.kind {
+                        FdKindDump::File { inode, position } => {
+                            (position, mount_id_of_inode(inode), inode)
+                        }
+                        FdKindDump::Stream { position, .. } => (position, 0, STREAMINODE),
+                        FdKindDump::Socket { id, .. } | FdKindDump::Pipe { id } => (0, 0, id),
+                        _ => (0, 0, 0),
+                    };
+                    let fdinfotext = format!(
+                        "pos:\t{}\nflags:\t0{:o}\nmnt_id:\t{}\nino:\t{}\n",
+                        pos,
+                        desc.flags | desc.statusflags,
+                        mntid,
+                        ino
+                    );
+                    _proc_set_contents(fdinode, &fdinfotext);
+                }
+                keep.push(fdname);
+            }
+            _proc_prune(inodenum, &keep);
+        }
+        ProcNode::Lind(mountid) => {
+            let stats = _proc_file(inodenum, "stats", mountid);
+            _proc_set_contents(stats, &metrics::syscall_metrics_text());
+        }
+    }
+    true
+}
+
+//if path names one of cageid's entries in procfs' fd directory, returns that descriptor
+pub fn procfs_own_fd(path: &interface::RustPath, cageid: u64) -> Option<i32> {
+    let fddir: interface::RustPathBuf =
+        [PROCMOUNTPOINT, &cageid.to_string(), "fd"].iter().collect();
+    let rest = path.strip_prefix(&fddir).ok()?;
+    let mut components = rest.components();
+    let fd = components
+        .next()?
+        .as_os_str()
+        .to_str()?
+        .parse::<i32>()
+        .ok()?;
+    match components.next() {
+        Some(_) => None,
+        None => Some(fd),
+    }
+}
+
+//Where the files and directories open in fds are linked, by inode. Files don't know which
+//directory they are in, so this takes one pass over every directory.
+fn _proc_fd_paths(fds: &[FdDump]) -> interface::RustBTreeMap<usize, String> {
+    let mut wanted: Vec<usize> = fds
+        .iter()
+        .filter_map(|fddump| match fddump.desc.as_ref()?.kind {
+            FdKindDump::File { inode, .. } => Some(inode),
+            _ => None,
+        })
+        .collect();
+    wanted.sort_unstable();
+    wanted.dedup();
+
+    let mut links = vec![];
+    for entry in FS_METADATA.inodetable.iter() {
+        if let Inode::Dir(dir) = entry.value() {
+            for (name, child) in dir.filename_to_inode_dict.iter() {
+                //the root of a mount is linked where the directory it covers is
+                let child = mount_cover(child);
+                if name != "." && name != ".." && wanted.binary_search(&child).is_ok() {
+                    links.push((child, *entry.key(), name.to_string()));
+                }
+            }
+        }
+    }
+
+    let mut paths = interface::RustBTreeMap::new();
+    paths.insert(ROOTDIRECTORYINODE, "/".to_string());
+    for (inodenum, parent, name) in links {
+        let parentpath = if parent == ROOTDIRECTORYINODE {
+            String::new()
+        } else {
+            match pathnamefrominodenum(parent) {
+                Some(parentpath) => parentpath.trim_end_matches('/').to_string(),
+                None => continue,
+            }
+        };
+        paths
+            .entry(inodenum)
+            .or_insert(format!("{}/{}", parentpath, name));
+    }
+    paths
+}
+
+//what an entry of /proc/<cageid>/fd leads to, in the form linux gives its links
+fn _proc_fd_target(kind: &FdKindDump, paths: &interface::RustBTreeMap<usize, String>) -> String {
+    match kind {
+        FdKindDump::File { inode, .. } => match paths.get(inode) {
+            Some(path) => path.clone(),
+            None => format!("inode:[{}] (deleted)", inode),
+        },
+        FdKindDump::Stream { .. } => "/dev/tty".to_string(),
+        FdKindDump::Socket { id, .. } => format!("socket:[{}]", id),
+        FdKindDump::Pipe { id } => format!("pipe:[{}]", id),
+        FdKindDump::Epoll { .. } => "anon_inode:[eventpoll]".to_string(),
+        FdKindDump::Inotify => "anon_inode:inotify".to_string(),
+        FdKindDump::Mqueue => "anon_inode:[mqueue]".to_string(),
+    }
+}
+
+fn _proc_meminfo() -> String {
+    format!(
+        "MemTotal:       {:8} kB\nMemFree:        {:8} kB\nMemAvailable:   {:8} kB\nBuffers:        {:8} kB\nCached:         {:8} kB\nSwapTotal:      {:8} kB\nSwapFree:       {:8} kB\n",
+        PROC_MEMTOTAL_KB, PROC_MEMFREE_KB, PROC_MEMFREE_KB, 0, 0, 0, 0
+    )
+}
+
+fn _proc_cpuinfo() -> String {
+    let cpus = match std::thread::available_parallelism() {
+        Ok(count) => count.get(),
+        Err(_) => 1,
+    };
+    let mut text = String::new();
+    for cpu in 0..cpus {
+        text.push_str(&format!(
+            "processor\t: {}\nvendor_id\t: Lind\nmodel name\t: Lind virtual CPU\ncpu cores\t: {}\n\n",
+            cpu, cpus
+        ));
+    }
+    text
+}
+
+fn _proc_lookup(parent: usize, name: &str) -> Option<usize> {
+    match &*FS_METADATA.inodetable.get(&parent)? {
+        Inode::Dir(dir) => dir.filename_to_inode_dict.get(name),
+        _ => None,
+    }
+}
+
+fn _proc_link(parent: usize, name: &str, newinodenum: usize, newinode: Inode, mountid: usize) {
+    FS_METADATA.inodetable.insert(newinodenum, newinode);
+    MOUNT_TABLE.inodemounts.insert(newinodenum, mountid);
+    if let Inode::Dir(ref mut dir) = *FS_METADATA.inodetable.get_mut(&parent).unwrap() {
+        dir.filename_to_inode_dict
+            .insert(name.to_string(), newinodenum);
+        dir.linkcount += 1;
+    }
+}
+
+//find or create the generated file called name in parent
+fn _proc_file(parent: usize, name: &str, mountid: usize) -> usize {
+    if let Some(inodenum) = _proc_lookup(parent, name) {
+        return inodenum;
+    }
+    let newinodenum = FS_METADATA
+        .nextinode
+        .fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+    let time = interface::timestamp();
+    let newinode = Inode::File(GenericInode {
+        size: 0,
+        uid: DEFAULT_UID,
+        gid: DEFAULT_GID,
+        mode: S_IFREG as u32 | S_IRUSR | S_IRGRP | S_IROTH,
+        linkcount: 1,
+        refcount: 0,
+        atime: time,
+        ctime: time,
+        btime: time,
+        mtime: time,
+    });
+    let sysfilename = format!("{}{}", FILEDATAPREFIX, newinodenum);
+    FILEOBJECTTABLE.insert(newinodenum, interface::openmemfile(sysfilename).unwrap());
+    _proc_link(parent, name, newinodenum, newinode, mountid);
+    newinodenum
+}
+
+//find or create the generated directory called name in parent
+fn _proc_dir(parent: usize, name: &str, mountid: usize) -> usize {
+    if let Some(inodenum) = _proc_lookup(parent, name) {
+        return inodenum;
+    }
+    let newinodenum = FS_METADATA
+        .nextinode
+        .fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+    let time = interface::timestamp();
+    let newinode = Inode::Dir(DirectoryInode {
+        size: 0,
+        uid: DEFAULT_UID,
+        gid: DEFAULT_GID,
+        mode: S_IFDIR as u32 | S_IRUSR | S_IXUSR | S_IRGRP | S_IXGRP | S_IROTH | S_IXOTH,
+        linkcount: 3,
+        refcount: 0,
+        atime: time,
+        ctime: time,
+        btime: time,
+        mtime: time,
+        filename_to_inode_dict: init_filename_to_inode_dict(newinodenum, parent),
+    });
+    _proc_link(parent, name, newinodenum, newinode, mountid);
+    newinodenum
+}
+
+fn _proc_set_contents(inodenum: usize, contents: &str) {
+    let mut fobj = FILEOBJECTTABLE.get_mut(&inodenum).unwrap();
+    fobj.shrink(0).unwrap();
+    fobj.writeat(contents.as_ptr(), contents.len(), 0).unwrap();
+    drop(fobj);
+    if let Inode::File(ref mut file) = *FS_METADATA.inodetable.get_mut(&inodenum).unwrap() {
+        file.size = contents.len();
+        file.mtime = interface::timestamp();
+    }
+}
+
+fn _proc_set_mode(inodenum: usize, mode: u32) {
+    if let Inode::File(ref mut file) = *FS_METADATA.inodetable.get_mut(&inodenum).unwrap() {
+        file.mode = mode;
+    }
+}
+
+//drop every entry of parent other than the ones named in keep
+fn _proc_prune(parent: usize, keep: &Vec<String>) {
+    let stale: Vec<(String, usize)> = match &*FS_METADATA.inodetable.get(&parent).unwrap() {
+        Inode::Dir(dir) => dir
+            .filename_to_inode_dict
+            .iter()
+            .filter(|(name, _)| *name != "." && *name != "..")
+            .filter(|(name, _)| !keep.iter().any(|kept| kept == name))
+            .map(|(name, inodenum)| (name.to_string(), inodenum))
+            .collect(),
+        _ => return,
+    };
+    for (name, inodenum) in stale {
+        if let Inode::Dir(ref mut dir) = *FS_METADATA.inodetable.get_mut(&parent).unwrap() {
+            dir.filename_to_inode_dict.remove(&name);
+            dir.linkcount -= 1;
+        }
+        _proc_remove(inodenum);
+    }
+}
+
+//remove a generated inode, leaving it to close() if something still has it open
+fn _proc_remove(inodenum: usize) {
+    if PROC_NODES.remove(&inodenum).is_some() {
+        _proc_prune(inodenum, &vec![]);
+    }
+    let inuse = match &mut *FS_METADATA.inodetable.get_mut(&inodenum).unwrap() {
+        Inode::File(file) => {
+            file.linkcount = 0;
+            file.refcount > 0
+        }
+        Inode::Dir(dir) => {
+            dir.linkcount = 2;
+            dir.refcount > 0
+        }
+        _ => false,
+    };
+    if !inuse {
+        FS_METADATA.inodetable.remove(&inodenum);
+        mount_release_inode(inodenum, 0);
+    }
+}
@@ -0,0 +1,107 @@
+// Per-cage disk quotas on the bytes and inodes taken up by the files a cage creates
+#![allow(dead_code)]
+
+use super::cage::Cage;
+use super::context::ContextGlobal;
+use crate::interface;
+
+//Maps every inode created under a quota to the quota it was charged to, so that whichever cage
+//truncates or unlinks the file refunds its owner, even after the owner has exited
+pub static QUOTA_OWNERS: ContextGlobal<
+    interface::RustHashMap<usize, interface::RustRfc<DiskQuota>>,
+> = ContextGlobal::new(|context| &context.fs.quotaowners);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub bytelimit: Option<usize>, // None for no limit
+    pub inodelimit: Option<usize>,
+    pub bytes: usize,
+    pub inodes: usize,
+}
+
+//A quota is shared by a cage and every cage forked from it after it was set
+#[derive(Debug)]
+pub struct DiskQuota {
+    pub usage: interface::Mutex<QuotaUsage>,
+}
+
+impl DiskQuota {
+    pub fn new(bytelimit: Option<usize>, inodelimit: Option<usize>) -> DiskQuota {
+        DiskQuota {
+            usage: interface::Mutex::new(QuotaUsage {
+                bytelimit: bytelimit,
+                inodelimit: inodelimit,
+                bytes: 0,
+                inodes: 0,
+            }),
+        }
+    }
+}
+
+impl Cage {
+    //limit the bytes and inodes this cage and its future children may use, files created before
+    //the quota was set are not charged to it
+    pub fn set_disk_quota(&self, bytelimit: Option<usize>, inodelimit: Option<usize>) {
+        let mut quota = self.quota.write();
+        match &*quota {
+            Some(existing) => {
+                let mut usage = existing.usage.lock();
+                usage.bytelimit = bytelimit;
+                usage.inodelimit = inodelimit;
+            }
+            None => {
+                *quota = Some(interface::RustRfc::new(DiskQuota::new(
+                    bytelimit, inodelimit,
+                )))
+            }
+        }
+    }
+
+    pub fn disk_quota_usage(&self) -> Option<QuotaUsage> {
+        self.quota.read().as_ref().map(|quota| *quota.usage.lock())
+    }
+}
+
+//charge a newly created inode to the creating cage's quota, returning false without charging
+//anything if the quota has no inodes left
+pub fn quota_claim_inode(cage: &Cage, inodenum: usize) -> bool {
+    if let Some(quota) = &*cage.quota.read() {
+        let mut usage = quota.usage.lock();
+        if let Some(limit) = usage.inodelimit {
+            if usage.inodes + 1 > limit {
+                return false;
+            }
+        }
+        usage.inodes += 1;
+        QUOTA_OWNERS.insert(inodenum, quota.clone());
+    }
+    true
+}
+
+//account for a file charged to a quota changing size, returning false without charging anything
+//if growing it would exceed the quota
+pub fn quota_resize_inode(inodenum: usize, oldsize: usize, newsize: usize) -> bool {
+    if let Some(quota) = QUOTA_OWNERS.get(&inodenum) {
+        let mut usage = quota.usage.lock();
+        if newsize > oldsize {
+            if let Some(limit) = usage.bytelimit {
+                if usage.bytes + (newsize - oldsize) > limit {
+                    return false;
+                }
+            }
+            usage.bytes += newsize - oldsize;
+        } else {
+            usage.bytes -= interface::rust_min(oldsize - newsize, usage.bytes);
+        }
+    }
+    true
+}
+
+//the inode has been removed from the filesystem, so refund its space and the inode itself
+pub fn quota_release_inode(inodenum: usize, size: usize) {
+    if let Some((_, quota)) = QUOTA_OWNERS.remove(&inodenum) {
+        let mut usage = quota.usage.lock();
+        usage.bytes -= interface::rust_min(size, usage.bytes);
+        usage.inodes -= interface::rust_min(1, usage.inodes);
+    }
+}
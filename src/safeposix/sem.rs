@@ -0,0 +1,198 @@
+// System V semaphore sets
+#![allow(dead_code)]
+
+use super::syscalls::fs_constants::*;
+use crate::interface;
+use crate::interface::errnos::{syscall_error, Errno};
+use crate::interface::SembufStruct;
+
+pub static SEM_METADATA: interface::RustLazyGlobal<interface::RustRfc<SemMetadata>> =
+    interface::RustLazyGlobal::new(|| interface::RustRfc::new(SemMetadata::init_sem_metadata()));
+
+pub struct SemaphoreSet {
+    pub key: i32,
+    pub perm: interface::IpcPermStruct,
+    pub nsems: usize,
+    pub state: interface::Mutex<SemSetState>,
+    pub cv: interface::Condvar, // signalled whenever a value changes or the set is removed
+}
+
+pub struct SemSetState {
+    pub semvals: Vec<i32>,
+    pub sempids: Vec<u64>, // cage that last operated on each semaphore
+    // per cage, the adjustments that reverse its SEM_UNDO operations once it exits
+    pub undo: interface::RustBTreeMap<u64, Vec<i32>>,
+    pub removed: bool,
+}
+
+pub fn new_semaphore_set(key: i32, nsems: usize, uid: u32, gid: u32, mode: u16) -> SemaphoreSet {
+    SemaphoreSet::new(key, nsems, uid, gid, mode)
+}
+
+impl SemaphoreSet {
+    pub fn new(key: i32, nsems: usize, uid: u32, gid: u32, mode: u16) -> SemaphoreSet {
+        let permstruct = interface::IpcPermStruct {
+            __key: key,
+            uid: uid,
+            gid: gid,
+            cuid: uid,
+            cgid: gid,
+            mode: mode,
+            __pad1: 0,
+            __seq: 0,
+            __pad2: 0,
+            __unused1: 0,
+            __unused2: 0,
+        };
+
+        SemaphoreSet {
+            key: key,
+            perm: permstruct,
+            nsems: nsems,
+            state: interface::Mutex::new(SemSetState {
+                semvals: vec![0; nsems],
+                sempids: vec![0; nsems],
+                undo: interface::RustBTreeMap::new(),
+                removed: false,
+            }),
+            cv: interface::Condvar::new(),
+        }
+    }
+
+    // Perform every operation or none of them. Unless the operation that can't go ahead yet has
+    // IPC_NOWAIT set, wait until the whole array can be applied at once, or until interrupted
+    // reports a signal for the caller.
+    pub fn semop(
+        &self,
+        sops: &[SembufStruct],
+        cageid: u64,
+        interrupted: &dyn Fn() -> Option<i32>,
+    ) -> i32 {
+        let mut state = self.state.lock();
+        let semvals = loop {
+            if state.removed {
+                return syscall_error(Errno::EIDRM, "semop", "the semaphore set was removed");
+            }
+
+            let mut semvals = state.semvals.clone();
+            let mut blockedop = None;
+            for sop in sops {
+                let semval = &mut semvals[sop.sem_num as usize];
+                let semop = sop.sem_op as i32;
+                if (semop == 0 && *semval != 0) || (semop < 0 && *semval < -semop) {
+                    blockedop = Some(sop);
+                    break;
+                }
+                if *semval + semop > SEMVMX {
+                    return syscall_error(
+                        Errno::ERANGE,
+                        "semop",
+                        "the operation would exceed the maximum semaphore value",
+                    );
+                }
+                *semval += semop;
+            }
+
+            match blockedop {
+                None => break semvals,
+                Some(sop) if sop.sem_flg as i32 & IPC_NOWAIT != 0 => {
+                    return syscall_error(
+                        Errno::EAGAIN,
+                        "semop",
+                        "the operation would block and IPC_NOWAIT was specified",
+                    );
+                }
+                Some(_) => {
+                    if let Some(interrupted) = interrupted() {
+                        return interrupted;
+                    }
+                    self.cv
+                        .wait_for(&mut state, interface::SIGNAL_CHECK_INTERVAL);
+                }
+            }
+        };
+
+        state.semvals = semvals;
+        let nsems = self.nsems;
+        for sop in sops {
+            state.sempids[sop.sem_num as usize] = cageid;
+            if sop.sem_flg as i32 & SEM_UNDO != 0 {
+                let adjustments = state.undo.entry(cageid).or_insert_with(|| vec![0; nsems]);
+                adjustments[sop.sem_num as usize] -= sop.sem_op as i32;
+            }
+        }
+        self.cv.notify_all();
+        0
+    }
+
+    pub fn getval(&self, semnum: usize) -> i32 {
+        self.state.lock().semvals[semnum]
+    }
+
+    // setting a value directly discards what every cage would have undone on it
+    pub fn setval(&self, semnum: usize, value: i32, cageid: u64) {
+        let mut state = self.state.lock();
+        state.semvals[semnum] = value;
+        state.sempids[semnum] = cageid;
+        for adjustments in state.undo.values_mut() {
+            adjustments[semnum] = 0;
+        }
+        self.cv.notify_all();
+    }
+
+    // wake everyone waiting on the set so they see it is gone
+    pub fn remove(&self) {
+        self.state.lock().removed = true;
+        self.cv.notify_all();
+    }
+}
+
+pub struct SemMetadata {
+    pub nextid: interface::RustAtomicI32,
+    pub semkeyidtable: interface::RustHashMap<i32, i32>,
+    pub semtable: interface::RustHashMap<i32, interface::RustRfc<SemaphoreSet>>,
+}
+
+impl SemMetadata {
+    pub fn init_sem_metadata() -> SemMetadata {
+        SemMetadata {
+            nextid: interface::RustAtomicI32::new(1),
+            semkeyidtable: interface::RustHashMap::new(),
+            semtable: interface::RustHashMap::new(),
+        }
+    }
+
+    pub fn new_keyid(&self) -> i32 {
+        self.nextid
+            .fetch_add(1, interface::RustAtomicOrdering::Relaxed)
+    }
+}
+
+// reverse the SEM_UNDO operations of an exiting cage, clamping values into the valid range
+pub fn sem_undo_exit(cageid: u64) {
+    let sets: Vec<interface::RustRfc<SemaphoreSet>> = SEM_METADATA
+        .semtable
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect();
+    for set in sets {
+        let mut state = set.state.lock();
+        if let Some(adjustments) = state.undo.remove(&cageid) {
+            for (semnum, adjustment) in adjustments.into_iter().enumerate() {
+                let semval = state.semvals[semnum] + adjustment;
+                state.semvals[semnum] = interface::rust_min(interface::rust_max(semval, 0), SEMVMX);
+            }
+            set.cv.notify_all();
+        }
+    }
+}
+
+// a cage keeps its pending adjustments across exec, which gives it a new cage id
+pub fn sem_undo_exec(oldcageid: u64, newcageid: u64) {
+    for entry in SEM_METADATA.semtable.iter() {
+        let mut state = entry.value().state.lock();
+        if let Some(adjustments) = state.undo.remove(&oldcageid) {
+            state.undo.insert(newcageid, adjustments);
+        }
+    }
+}
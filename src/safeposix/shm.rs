@@ -76,6 +76,7 @@ impl ShmSegment {
         let fobjfdno = self.filebacking.as_fd_handle_raw_int();
         self.shminfo.shm_nattch += 1;
         self.shminfo.shm_atime = interface::timestamp() as isize;
+        self.shminfo.shm_lpid = cageid as u32;
 
         match self.attached_cages.entry(cageid) {
             interface::RustHashEntry::Occupied(mut occupied) => {
@@ -85,20 +86,25 @@ impl ShmSegment {
                 vacant.insert(1);
             }
         };
-        interface::libc_mmap(
+        match interface::libc_mmap(
             shmaddr,
             self.size as usize,
             prot,
             MAP_SHARED | MAP_FIXED,
             fobjfdno,
             0,
-        )
+        ) {
+            Ok(mapaddr) => mapaddr as i32,
+            Err(errno) => {
+                interface::host_syscall_error(errno, "shmat", "The libc call to mmap failed!")
+            }
+        }
     }
 
     // unmap shared segment, decrease attachments
     // decrease references within attached cages map
     pub fn unmap_shm(&mut self, shmaddr: *mut u8, cageid: u64) {
-        interface::libc_mmap(
+        let _ = interface::libc_mmap(
             shmaddr,
             self.size as usize,
             PROT_NONE,
@@ -108,6 +114,7 @@ impl ShmSegment {
         );
         self.shminfo.shm_nattch -= 1;
         self.shminfo.shm_dtime = interface::timestamp() as isize;
+        self.shminfo.shm_lpid = cageid as u32;
         match self.attached_cages.entry(cageid) {
             interface::RustHashEntry::Occupied(mut occupied) => {
                 *occupied.get_mut() -= 1;
@@ -120,6 +127,23 @@ impl ShmSegment {
             }
         };
     }
+
+    // only the owner, permission bits and change time can be set, the rest is live state
+    pub fn set(&mut self, buf: &interface::ShmidsStruct) {
+        self.shminfo.shm_perm.uid = buf.shm_perm.uid;
+        self.shminfo.shm_perm.gid = buf.shm_perm.gid;
+        self.shminfo.shm_perm.mode =
+            (self.shminfo.shm_perm.mode & !0x1FF) | (buf.shm_perm.mode & 0x1FF);
+        self.shminfo.shm_ctime = interface::timestamp() as isize;
+    }
+
+    // Mark the segment to be destroyed once nothing has it attached. Its key is released right
+    // away, so shmget can no longer find it and may create a new segment under the same key.
+    pub fn mark_removed(&mut self) {
+        self.rmid = true;
+        self.shminfo.shm_perm.mode |= SHM_DEST as u16;
+        self.shminfo.shm_perm.__key = IPC_PRIVATE;
+    }
 }
 
 pub struct ShmMetadata {
@@ -2,13 +2,23 @@
 
 // File system related system calls
 use super::fs_constants::*;
+use super::net_constants::is_ifreq_request;
 use super::sys_constants::*;
 use crate::interface;
 use crate::safeposix::cage::Errno::EINVAL;
 use crate::safeposix::cage::{FileDescriptor::*, *};
+use crate::safeposix::devices::*;
+use crate::safeposix::fasync::fasync_register;
 use crate::safeposix::filesystem::*;
+use crate::safeposix::inotify::*;
+use crate::safeposix::mqueue::*;
+use crate::safeposix::msg::*;
 use crate::safeposix::net::NET_METADATA;
+use crate::safeposix::procfs::{procfs_mount, procfs_own_fd};
+use crate::safeposix::quota::*;
+use crate::safeposix::sem::*;
 use crate::safeposix::shm::*;
+use crate::safeposix::tty::*;
 
 impl Cage {
     //------------------------------------OPEN SYSCALL------------------------------------
@@ -16,11 +26,12 @@ impl Cage {
     fn _file_initializer(&self, inodenum: usize, flags: i32, size: usize) -> FileDesc {
         //insert file descriptor into self.filedescriptortableable of the cage
         let position = if 0 != flags & O_APPEND { size } else { 0 };
-        let allowmask = O_RDWRFLAGS | O_CLOEXEC;
+        let allowmask = O_RDWRFLAGS | O_CLOEXEC | O_PATH | O_SYNC | O_DSYNC;
         FileDesc {
             position: position,
             inode: inodenum,
             flags: flags & allowmask,
+            statusflags: StatusFlags::new(flags),
             advlock: interface::RustRfc::new(interface::AdvisoryLock::new()),
         }
     }
@@ -30,8 +41,45 @@ impl Cage {
         if path.len() == 0 {
             return syscall_error(Errno::ENOENT, "open", "given path was null");
         }
+        //O_PATH only locates the file, so flags that would access, create or change it are ignored
+        let flags = if is_pathonly(flags) {
+            flags & (O_PATH | O_CLOEXEC | O_DIRECTORY | O_NOFOLLOW)
+        } else {
+            flags
+        };
+        //O_DIRECTORY can't be satisfied by creating a regular file
+        if flags & (O_CREAT | O_DIRECTORY) == O_CREAT | O_DIRECTORY {
+            return syscall_error(
+                Errno::EINVAL,
+                "open",
+                "O_CREAT and O_DIRECTORY were both specified",
+            );
+        }
+        //there are no symbolic links in the filesystem, so O_NOFOLLOW never has one to refuse
         let truepath = normpath(convpath(path), self);
 
+        //opening one of the cage's own descriptors through /dev/fd or procfs duplicates it
+        if let Some(targetfd) = procfs_own_fd(&truepath, self.cageid) {
+            //a descriptor that isn't open has no entry to open
+            return match self.dup_syscall(targetfd, None) {
+                ret if ret == -(Errno::EBADF as i32) => syscall_error(
+                    Errno::ENOENT,
+                    "open",
+                    "tried to open a file that did not exist, and O_CREAT was not specified",
+                ),
+                ret => ret,
+            };
+        }
+
+        let readonlymount = path_is_readonly(&truepath);
+        if readonlymount && (!is_rdonly(flags) || flags & O_TRUNC != 0) {
+            return syscall_error(
+                Errno::EROFS,
+                "open",
+                "write access was requested on a read-only mount",
+            );
+        }
+
         let (fd, guardopt) = self.get_next_fd(None);
         if fd < 0 {
             return fd;
@@ -60,12 +108,27 @@ impl Cage {
                         "tried to open a file that did not exist, and O_CREAT was not specified",
                     );
                 }
+                if readonlymount {
+                    return syscall_error(
+                        Errno::EROFS,
+                        "open",
+                        "cannot create a file on a read-only mount",
+                    );
+                }
 
                 let filename = truepath.file_name().unwrap().to_str().unwrap().to_string(); //for now we assume this is sane, but maybe this should be checked later
 
                 if S_IFCHR == (S_IFCHR & flags) {
                     return syscall_error(Errno::EINVAL, "open", "Invalid value in flags");
                 }
+                let cred = self.cred.read().clone();
+                if !Self::_inode_permits(&cred, pardirinode, W_OK | X_OK) {
+                    return syscall_error(
+                        Errno::EACCES,
+                        "open",
+                        "write access to the parent directory was denied",
+                    );
+                }
 
                 let effective_mode = S_IFREG as u32 | mode;
 
@@ -76,19 +139,27 @@ impl Cage {
                 let time = interface::timestamp(); //We do a real timestamp now
                 let newinode = Inode::File(GenericInode {
                     size: 0,
-                    uid: DEFAULT_UID,
-                    gid: DEFAULT_GID,
+                    uid: cred.euid,
+                    gid: cred.egid,
                     mode: effective_mode,
                     linkcount: 1,
                     refcount: 1,
                     atime: time,
                     ctime: time,
+                    btime: time,
                     mtime: time,
                 });
 
                 let newinodenum = FS_METADATA
                     .nextinode
                     .fetch_add(1, interface::RustAtomicOrdering::Relaxed); //fetch_add returns the previous value, which is the inode number we want
+                if !quota_claim_inode(self, newinodenum) {
+                    return syscall_error(
+                        Errno::EDQUOT,
+                        "open",
+                        "the inode quota of the cage has been exhausted",
+                    );
+                }
                 if let Inode::Dir(ref mut ind) =
                     *(FS_METADATA.inodetable.get_mut(&pardirinode).unwrap())
                 {
@@ -103,12 +174,24 @@ impl Cage {
                     );
                 }
                 FS_METADATA.inodetable.insert(newinodenum, newinode);
-                log_metadata(&FS_METADATA, pardirinode);
-                log_metadata(&FS_METADATA, newinodenum);
+                mount_claim_inode(pardirinode, newinodenum);
+                log_metadata_batch(&FS_METADATA, &[pardirinode, newinodenum]);
+                inotify_publish(
+                    pardirinode,
+                    IN_CREATE,
+                    0,
+                    truepath.file_name().unwrap().to_str(),
+                );
 
                 if let interface::RustHashEntry::Vacant(vac) = FILEOBJECTTABLE.entry(newinodenum) {
                     let sysfilename = format!("{}{}", FILEDATAPREFIX, newinodenum);
-                    vac.insert(interface::openfile(sysfilename, 0).unwrap()); // new file of size 0
+                    if inode_is_persistent(newinodenum) {
+                        vac.insert(interface::openfile(sysfilename, 0).unwrap());
+                    // new file of size 0
+                    } else {
+                        //files on a non-persistent mount keep their data in memory
+                        vac.insert(interface::openmemfile(sysfilename).unwrap());
+                    }
                 }
 
                 let _insertval =
@@ -126,26 +209,71 @@ impl Cage {
                 }
                 let size;
 
-                let mut inodeobj = FS_METADATA.inodetable.get_mut(&inodenum).unwrap();
+                //O_PATH only locates the file, so it asks for no access to it
+                let mut want = if is_pathonly(flags) {
+                    0
+                } else if is_rdonly(flags) {
+                    R_OK
+                } else if is_wronly(flags) {
+                    W_OK
+                } else {
+                    R_OK | W_OK
+                };
+                if flags & O_TRUNC != 0 {
+                    want |= W_OK;
+                }
+                if !Self::_inode_permits(&self.cred.read(), inodenum, want) {
+                    return syscall_error(
+                        Errno::EACCES,
+                        "open",
+                        "the requested access to the file is not allowed",
+                    );
+                }
+                if !is_pathonly(flags) {
+                    let breakret = self._break_leases(inodenum, flags);
+                    if breakret < 0 {
+                        return breakret;
+                    }
+                }
+
+                let mut inodeobj = match FS_METADATA.inodetable.get_mut(&inodenum) {
+                    Some(inodeobj) => inodeobj,
+                    None => {
+                        return corruption_error(
+                            "open",
+                            "path leads to an inode that does not exist",
+                        )
+                    }
+                };
+                if flags & O_DIRECTORY != 0 && !matches!(*inodeobj, Inode::Dir(_)) {
+                    return syscall_error(
+                        Errno::ENOTDIR,
+                        "open",
+                        "O_DIRECTORY was specified and the file is not a directory",
+                    );
+                }
                 match *inodeobj {
                     Inode::File(ref mut f) => {
                         if O_TRUNC == (flags & O_TRUNC) {
                             // We only do this to regular files, otherwise O_TRUNC is undefined
-                            //close the file object if another cage has it open
-                            let entry = FILEOBJECTTABLE.entry(inodenum);
-                            if let interface::RustHashEntry::Occupied(occ) = &entry {
-                                occ.get().close().unwrap();
+                            // The backing file is truncated in place rather than replaced, so that
+                            // any shared mappings of it keep referring to the file's data
+                            match FILEOBJECTTABLE.entry(inodenum) {
+                                interface::RustHashEntry::Occupied(mut occ) => {
+                                    occ.get_mut().shrink(0).unwrap();
+                                }
+                                interface::RustHashEntry::Vacant(vac) => {
+                                    let sysfilename = format!("{}{}", FILEDATAPREFIX, inodenum);
+                                    let mut fobj =
+                                        interface::openfile(sysfilename, f.size).unwrap();
+                                    fobj.shrink(0).unwrap();
+                                    vac.insert(fobj);
+                                }
                             }
                             // resize it to 0
+                            quota_resize_inode(inodenum, f.size, 0);
+                            mount_resize_inode(inodenum, f.size, 0);
                             f.size = 0;
-
-                            //remove the previous file and add a new one of 0 length
-                            if let interface::RustHashEntry::Occupied(occ) = entry {
-                                occ.remove_entry();
-                            }
-
-                            let sysfilename = format!("{}{}", FILEDATAPREFIX, inodenum);
-                            interface::removefile(sysfilename.clone()).unwrap();
                         }
 
                         if let interface::RustHashEntry::Vacant(vac) =
@@ -164,6 +292,10 @@ impl Cage {
                         f.refcount += 1;
                     }
                     Inode::CharDev(ref mut f) => {
+                        let openret = tty_open(&f.dev);
+                        if openret < 0 {
+                            return openret;
+                        }
                         size = f.size;
                         f.refcount += 1;
                     }
@@ -188,6 +320,9 @@ impl Cage {
             return syscall_error(Errno::ENOENT, "mkdir", "given path was null");
         }
         let truepath = normpath(convpath(path), self);
+        if path_is_readonly(&truepath) {
+            return syscall_error(Errno::EROFS, "mkdir", "path is on a read-only filesystem");
+        }
 
         //pass the metadata to this helper. If passed table is none, then create new instance
         let metadata = &FS_METADATA;
@@ -210,21 +345,37 @@ impl Cage {
                 if mode & (S_IRWXA | S_FILETYPEFLAGS as u32) != mode {
                     return syscall_error(Errno::EPERM, "mkdir", "Mode bits were not sane");
                 }
+                let cred = self.cred.read().clone();
+                if !Self::_inode_permits(&cred, pardirinode, W_OK | X_OK) {
+                    return syscall_error(
+                        Errno::EACCES,
+                        "mkdir",
+                        "write access to the parent directory was denied",
+                    );
+                }
 
                 let newinodenum = FS_METADATA
                     .nextinode
                     .fetch_add(1, interface::RustAtomicOrdering::Relaxed); //fetch_add returns the previous value, which is the inode number we want
+                if !quota_claim_inode(self, newinodenum) {
+                    return syscall_error(
+                        Errno::EDQUOT,
+                        "mkdir",
+                        "the inode quota of the cage has been exhausted",
+                    );
+                }
                 let time = interface::timestamp(); //We do a real timestamp now
 
                 let newinode = Inode::Dir(DirectoryInode {
                     size: 0,
-                    uid: DEFAULT_UID,
-                    gid: DEFAULT_GID,
+                    uid: cred.euid,
+                    gid: cred.egid,
                     mode: effective_mode,
                     linkcount: 3,
                     refcount: 0, //2 because ., and .., as well as reference in parent directory
                     atime: time,
                     ctime: time,
+                    btime: time,
                     mtime: time,
                     filename_to_inode_dict: init_filename_to_inode_dict(newinodenum, pardirinode),
                 });
@@ -242,8 +393,14 @@ impl Cage {
                     unreachable!();
                 }
                 metadata.inodetable.insert(newinodenum, newinode);
-                log_metadata(&metadata, pardirinode);
-                log_metadata(&metadata, newinodenum);
+                mount_claim_inode(pardirinode, newinodenum);
+                log_metadata_batch(&metadata, &[pardirinode, newinodenum]);
+                inotify_publish(
+                    pardirinode,
+                    IN_CREATE | IN_ISDIR,
+                    0,
+                    truepath.file_name().unwrap().to_str(),
+                );
                 0 //mkdir has succeeded
             }
 
@@ -263,6 +420,9 @@ impl Cage {
             return syscall_error(Errno::ENOENT, "mknod", "given path was null");
         }
         let truepath = normpath(convpath(path), self);
+        if path_is_readonly(&truepath) {
+            return syscall_error(Errno::EROFS, "mknod", "path is on a read-only filesystem");
+        }
 
         //pass the metadata to this helper. If passed table is none, then create new instance
         let metadata = &FS_METADATA;
@@ -290,16 +450,25 @@ impl Cage {
                         "only character files are supported",
                     );
                 }
+                let cred = self.cred.read().clone();
+                if !Self::_inode_permits(&cred, pardirinode, W_OK | X_OK) {
+                    return syscall_error(
+                        Errno::EACCES,
+                        "mknod",
+                        "write access to the parent directory was denied",
+                    );
+                }
                 let time = interface::timestamp(); //We do a real timestamp now
                 let newinode = Inode::CharDev(DeviceInode {
                     size: 0,
-                    uid: DEFAULT_UID,
-                    gid: DEFAULT_GID,
+                    uid: cred.euid,
+                    gid: cred.egid,
                     mode: mode,
                     linkcount: 1,
                     refcount: 0,
                     atime: time,
                     ctime: time,
+                    btime: time,
                     mtime: time,
                     dev: devtuple(dev),
                 });
@@ -307,6 +476,13 @@ impl Cage {
                 let newinodenum = FS_METADATA
                     .nextinode
                     .fetch_add(1, interface::RustAtomicOrdering::Relaxed); //fetch_add returns the previous value, which is the inode number we want
+                if !quota_claim_inode(self, newinodenum) {
+                    return syscall_error(
+                        Errno::EDQUOT,
+                        "mknod",
+                        "the inode quota of the cage has been exhausted",
+                    );
+                }
                 if let Inode::Dir(ref mut parentdir) =
                     *(FS_METADATA.inodetable.get_mut(&pardirinode).unwrap())
                 {
@@ -316,8 +492,8 @@ impl Cage {
                     parentdir.linkcount += 1;
                 } //insert a reference to the file in the parent directory
                 metadata.inodetable.insert(newinodenum, newinode);
-                log_metadata(metadata, pardirinode);
-                log_metadata(metadata, newinodenum);
+                mount_claim_inode(pardirinode, newinodenum);
+                log_metadata_batch(metadata, &[pardirinode, newinodenum]);
                 0 //mknod has succeeded
             }
 
@@ -340,6 +516,9 @@ impl Cage {
         }
         let trueoldpath = normpath(convpath(oldpath), self);
         let truenewpath = normpath(convpath(newpath), self);
+        if path_is_readonly(&truenewpath) {
+            return syscall_error(Errno::EROFS, "link", "path is on a read-only filesystem");
+        }
         let filename = truenewpath
             .file_name()
             .unwrap()
@@ -355,7 +534,15 @@ impl Cage {
                 "a directory component in pathname does not exist or is a dangling symbolic link",
             ),
             Some(inodenum) => {
-                let mut inodeobj = FS_METADATA.inodetable.get_mut(&inodenum).unwrap();
+                let mut inodeobj = match FS_METADATA.inodetable.get_mut(&inodenum) {
+                    Some(inodeobj) => inodeobj,
+                    None => {
+                        return corruption_error(
+                            "link",
+                            "path leads to an inode that does not exist",
+                        )
+                    }
+                };
 
                 match *inodeobj {
                     Inode::File(ref mut normalfile_inode_obj) => {
@@ -382,6 +569,16 @@ impl Cage {
                         syscall_error(Errno::ENOENT, "link", "newpath cannot be created")
                     }
 
+                    (None, Some(pardirinode))
+                        if mount_id_of_inode(pardirinode) != mount_id_of_inode(inodenum) =>
+                    {
+                        syscall_error(
+                            Errno::EXDEV,
+                            "link",
+                            "oldpath and newpath are not on the same mounted filesystem",
+                        )
+                    }
+
                     (None, Some(pardirinode)) => {
                         let mut parentinodeobj =
                             FS_METADATA.inodetable.get_mut(&pardirinode).unwrap();
@@ -392,12 +589,11 @@ impl Cage {
                                 .insert(filename, inodenum);
                             parentdirinodeobj.linkcount += 1;
                             drop(parentinodeobj);
-                            log_metadata(&FS_METADATA, pardirinode);
-                            log_metadata(&FS_METADATA, inodenum);
+                            log_metadata_batch(&FS_METADATA, &[pardirinode, inodenum]);
+                            0 //link has succeeded
                         } else {
-                            panic!("Parent directory was not a directory!");
+                            corruption_error("link", "parent directory is not a directory")
                         }
-                        0 //link has succeeded
                     }
 
                     (Some(_), ..) => syscall_error(Errno::EEXIST, "link", "newpath already exists"),
@@ -405,7 +601,15 @@ impl Cage {
 
                 if retval != 0 {
                     //reduce the linkcount to its previous value if linking failed
-                    let mut inodeobj = FS_METADATA.inodetable.get_mut(&inodenum).unwrap();
+                    let mut inodeobj = match FS_METADATA.inodetable.get_mut(&inodenum) {
+                        Some(inodeobj) => inodeobj,
+                        None => {
+                            return corruption_error(
+                                "link",
+                                "path leads to an inode that does not exist",
+                            )
+                        }
+                    };
 
                     match *inodeobj {
                         Inode::File(ref mut normalfile_inode_obj) => {
@@ -421,7 +625,9 @@ impl Cage {
                         }
 
                         Inode::Dir(_) => {
-                            panic!("Known non-directory file has been replaced with a directory!");
+                            interface::report_corruption(
+                                "link: a file being linked became a directory",
+                            );
                         }
                     }
                 }
@@ -438,6 +644,9 @@ impl Cage {
             return syscall_error(Errno::ENOENT, "unmknod", "given oldpath was null");
         }
         let truepath = normpath(convpath(path), self);
+        if path_is_readonly(&truepath) {
+            return syscall_error(Errno::EROFS, "unlink", "path is on a read-only filesystem");
+        }
 
         match metawalkandparent(truepath.as_path()) {
             //If the file does not exist
@@ -450,20 +659,28 @@ impl Cage {
 
             //If both the file and the parent directory exists
             (Some(inodenum), Some(parentinodenum)) => {
-                let mut inodeobj = FS_METADATA.inodetable.get_mut(&inodenum).unwrap();
+                let mut inodeobj = match FS_METADATA.inodetable.get_mut(&inodenum) {
+                    Some(inodeobj) => inodeobj,
+                    None => {
+                        return corruption_error(
+                            "unlink",
+                            "path leads to an inode that does not exist",
+                        )
+                    }
+                };
 
-                let (currefcount, curlinkcount, has_fobj, log) = match *inodeobj {
+                let (currefcount, curlinkcount, cursize, has_fobj, log) = match *inodeobj {
                     Inode::File(ref mut f) => {
                         f.linkcount -= 1;
-                        (f.refcount, f.linkcount, true, true)
+                        (f.refcount, f.linkcount, f.size, true, true)
                     }
                     Inode::CharDev(ref mut f) => {
                         f.linkcount -= 1;
-                        (f.refcount, f.linkcount, false, true)
+                        (f.refcount, f.linkcount, f.size, false, true)
                     }
                     Inode::Socket(ref mut f) => {
                         f.linkcount -= 1;
-                        (f.refcount, f.linkcount, false, false)
+                        (f.refcount, f.linkcount, f.size, false, false)
                     }
                     Inode::Dir(_) => {
                         return syscall_error(Errno::EISDIR, "unlink", "cannot unlink directory");
@@ -477,22 +694,32 @@ impl Cage {
                     return removal_result;
                 }
 
+                inotify_publish(
+                    parentinodenum,
+                    IN_DELETE,
+                    0,
+                    truepath.file_name().unwrap().to_str(),
+                );
                 if curlinkcount == 0 {
+                    inotify_inode_removed(inodenum);
                     if currefcount == 0 {
                         //actually remove file and the handle to it
                         FS_METADATA.inodetable.remove(&inodenum);
-                        if has_fobj {
+                        quota_release_inode(inodenum, cursize);
+                        if !inode_is_persistent(inodenum) {
+                            mount_release_inode(inodenum, cursize);
+                        } else if has_fobj {
                             let sysfilename = format!("{}{}", FILEDATAPREFIX, inodenum);
                             interface::removefile(sysfilename).unwrap();
                         }
                     } //we don't need a separate unlinked flag, we can just check that refcount is 0
                 }
                 NET_METADATA.domsock_paths.remove(&truepath);
+                NET_METADATA.domsock_accept_waits.remove(&truepath);
 
                 // the log boolean will be false if we are workign on a domain socket
                 if log {
-                    log_metadata(&FS_METADATA, parentinodenum);
-                    log_metadata(&FS_METADATA, inodenum);
+                    log_metadata_batch(&FS_METADATA, &[parentinodenum, inodenum]);
                 }
                 0 //unlink has succeeded
             }
@@ -512,7 +739,12 @@ impl Cage {
 
         //Walk the file tree to get inode from path
         if let Some(inodenum) = metawalk(truepath.as_path()) {
-            let inodeobj = FS_METADATA.inodetable.get(&inodenum).unwrap();
+            let inodeobj = match FS_METADATA.inodetable.get(&inodenum) {
+                Some(inodeobj) => inodeobj,
+                None => {
+                    return corruption_error("stat", "path leads to an inode that does not exist")
+                }
+            };
 
             //populate those fields in statbuf which depend on things other than the inode object
             statbuf.st_dev = FS_METADATA.dev_id;
@@ -589,8 +821,9 @@ impl Cage {
         statbuf.st_ino = inodenum;
         statbuf.st_mode = 49590; //r and w priveliged
         statbuf.st_nlink = 1;
-        statbuf.st_uid = DEFAULT_UID;
-        statbuf.st_gid = DEFAULT_GID;
+        let cred = self.cred.read().clone();
+        statbuf.st_uid = cred.euid;
+        statbuf.st_gid = cred.egid;
         statbuf.st_rdev = 0;
         statbuf.st_size = 0;
         statbuf.st_blksize = 0;
@@ -609,10 +842,10 @@ impl Cage {
             //files based on the information in the inode.
             match filedesc_enum {
                 File(normalfile_filedesc_obj) => {
-                    let inode = FS_METADATA
-                        .inodetable
-                        .get(&normalfile_filedesc_obj.inode)
-                        .unwrap();
+                    let inode = match FS_METADATA.inodetable.get(&normalfile_filedesc_obj.inode) {
+                        Some(inodeobj) => inodeobj,
+                        None => return corruption_error("fstat", "open file has no inode"),
+                    };
 
                     //populate those fields in statbuf which depend on things other than the inode object
                     statbuf.st_ino = normalfile_filedesc_obj.inode;
@@ -646,7 +879,7 @@ impl Cage {
                 Pipe(_) => {
                     self._stat_alt_helper(statbuf, 0xfeef0000);
                 }
-                Epoll(_) => {
+                Epoll(_) | Inotify(_) | Mqueue(_) => {
                     self._stat_alt_helper(statbuf, 0xfeef0000);
                 }
             }
@@ -656,6 +889,42 @@ impl Cage {
         }
     }
 
+    //------------------------------------READLINK SYSCALL------------------------------------
+
+    //The only links are the entries of procfs' fd directories, which hold their targets. Like
+    //linux, the target is not null terminated and is cut short if buf is too small for it.
+    pub fn readlink_syscall(&self, path: &str, buf: *mut u8, bufsize: usize) -> i32 {
+        if path.len() == 0 {
+            return syscall_error(Errno::ENOENT, "readlink", "given path was null");
+        }
+        let truepath = normpath(convpath(path), self);
+
+        let inodenum = match metawalk(truepath.as_path()) {
+            Some(inodenum) => inodenum,
+            None => return syscall_error(Errno::ENOENT, "readlink", "path does not exist"),
+        };
+        let inodeobj = match FS_METADATA.inodetable.get(&inodenum) {
+            Some(inodeobj) => inodeobj,
+            None => {
+                return corruption_error("readlink", "path leads to an inode that does not exist")
+            }
+        };
+        let size = match &*inodeobj {
+            Inode::File(f) if f.mode as i32 & S_FILETYPEFLAGS == S_IFLNK => f.size,
+            _ => return syscall_error(Errno::EINVAL, "readlink", "path is not a symbolic link"),
+        };
+        drop(inodeobj);
+
+        let fileobject = match FILEOBJECTTABLE.get(&inodenum) {
+            Some(fileobject) => fileobject,
+            None => return corruption_error("readlink", "link has no file object"),
+        };
+        match fileobject.readat(buf, interface::rust_min(size, bufsize), 0) {
+            Ok(bytesread) => bytesread as i32,
+            Err(_) => syscall_error(Errno::EIO, "readlink", "the link could not be read"),
+        }
+    }
+
     //------------------------------------STATFS SYSCALL------------------------------------
 
     pub fn statfs_syscall(&self, path: &str, databuf: &mut FSData) -> i32 {
@@ -663,13 +932,18 @@ impl Cage {
 
         //Walk the file tree to get inode from path
         if let Some(inodenum) = metawalk(truepath.as_path()) {
-            let _inodeobj = FS_METADATA.inodetable.get(&inodenum).unwrap();
+            let _inodeobj = match FS_METADATA.inodetable.get(&inodenum) {
+                Some(inodeobj) => inodeobj,
+                None => {
+                    return corruption_error("statfs", "path leads to an inode that does not exist")
+                }
+            };
 
             //populate the dev id field -- can be done outside of the helper
             databuf.f_fsid = FS_METADATA.dev_id;
 
             //delegate the rest of populating statbuf to the relevant helper
-            return Self::_istatfs_helper(self, databuf);
+            return Self::_istatfs_helper(self, databuf, inodenum);
         } else {
             syscall_error(Errno::ENOENT, "stat", "path refers to an invalid file")
         }
@@ -686,18 +960,19 @@ impl Cage {
 
             match filedesc_enum {
                 File(normalfile_filedesc_obj) => {
-                    let _inodeobj = FS_METADATA
-                        .inodetable
-                        .get(&normalfile_filedesc_obj.inode)
-                        .unwrap();
+                    let _inodeobj = match FS_METADATA.inodetable.get(&normalfile_filedesc_obj.inode)
+                    {
+                        Some(inodeobj) => inodeobj,
+                        None => return corruption_error("fstatfs", "open file has no inode"),
+                    };
 
-                    return Self::_istatfs_helper(self, databuf);
+                    return Self::_istatfs_helper(self, databuf, normalfile_filedesc_obj.inode);
                 }
-                Socket(_) | Pipe(_) | Stream(_) | Epoll(_) => {
+                Socket(_) | Pipe(_) | Stream(_) | Epoll(_) | Inotify(_) | Mqueue(_) => {
                     return syscall_error(
                         Errno::EBADF,
                         "fstatfs",
-                        "can't fstatfs on socket, stream, pipe, or epollfd",
+                        "can't fstatfs on socket, stream, pipe, epollfd, inotifyfd, or mqueuefd",
                     );
                 }
             }
@@ -705,7 +980,7 @@ impl Cage {
         return syscall_error(Errno::EBADF, "statfs", "invalid file descriptor");
     }
 
-    pub fn _istatfs_helper(&self, databuf: &mut FSData) -> i32 {
+    pub fn _istatfs_helper(&self, databuf: &mut FSData, inodenum: usize) -> i32 {
         databuf.f_type = 0xBEEFC0DE; //unassigned
         databuf.f_bsize = 4096;
         databuf.f_blocks = 0; //int(limits['diskused']) / 4096
@@ -717,6 +992,21 @@ impl Cage {
         databuf.f_frsize = 4096;
         databuf.f_spare = [0; 32];
 
+        //size limited mounts report their actual capacity
+        let mountid = mount_id_of_inode(inodenum);
+        if let Some(usage) = MOUNT_TABLE.usage.get(&mountid) {
+            let inodecount = MOUNT_TABLE
+                .inodemounts
+                .iter()
+                .filter(|owner| *owner.value() == mountid)
+                .count() as u64;
+            databuf.f_type = TMPFS_MAGIC;
+            databuf.f_blocks = (usage.limit / 4096) as u64;
+            databuf.f_bfree = ((usage.limit - usage.used) / 4096) as u64;
+            databuf.f_bavail = databuf.f_bfree;
+            databuf.f_ffiles = databuf.f_files - inodecount;
+        }
+
         0 //success!
     }
 
@@ -730,6 +1020,13 @@ impl Cage {
             match filedesc_enum {
                 //we must borrow the filedesc object as a mutable reference to update the position
                 File(ref mut normalfile_filedesc_obj) => {
+                    if is_pathonly(normalfile_filedesc_obj.flags) {
+                        return syscall_error(
+                            Errno::EBADF,
+                            "read",
+                            "file descriptor was opened with O_PATH",
+                        );
+                    }
                     if is_wronly(normalfile_filedesc_obj.flags) {
                         return syscall_error(
                             Errno::EBADF,
@@ -738,10 +1035,21 @@ impl Cage {
                         );
                     }
 
-                    let inodeobj = FS_METADATA
-                        .inodetable
-                        .get(&normalfile_filedesc_obj.inode)
-                        .unwrap();
+                    //terminals may have to wait for input, so they are read without the fd locked
+                    if let Some((tty, end)) = tty_of_inode(normalfile_filedesc_obj.inode) {
+                        let nonblocking =
+                            normalfile_filedesc_obj.statusflags.get() & O_NONBLOCK != 0;
+                        drop(unlocked_fd);
+                        return tty.read(end, buf, count, nonblocking, &|| {
+                            self.signal_interrupt("read", true)
+                        });
+                    }
+
+                    let inodeobj = match FS_METADATA.inodetable.get(&normalfile_filedesc_obj.inode)
+                    {
+                        Some(inodeobj) => inodeobj,
+                        None => return corruption_error("read", "open file has no inode"),
+                    };
 
                     //delegate to character if it's a character file, checking based on the type of the inode object
                     match &*inodeobj {
@@ -765,7 +1073,7 @@ impl Cage {
                         }
 
                         Inode::Socket(_) => {
-                            panic!("read(): Socket inode found on a filedesc fd.")
+                            corruption_error("read", "socket inode found on a file fd")
                         }
 
                         Inode::Dir(_) => syscall_error(
@@ -779,11 +1087,20 @@ impl Cage {
                     drop(unlocked_fd);
                     self.recv_common(fd, buf, count, 0, &mut None)
                 }
-                Stream(_) => syscall_error(
-                    Errno::EOPNOTSUPP,
-                    "read",
-                    "reading from stdin not implemented yet",
-                ),
+                Stream(stream_filedesc_obj) => {
+                    if stream_filedesc_obj.stream != 0 {
+                        return syscall_error(
+                            Errno::EBADF,
+                            "read",
+                            "specified stream not open for reading",
+                        );
+                    }
+                    let nonblocking = stream_filedesc_obj.statusflags.get() & O_NONBLOCK != 0;
+                    drop(unlocked_fd);
+                    CONSOLE.read(TtyEnd::Console, buf, count, nonblocking, &|| {
+                        self.signal_interrupt("read", true)
+                    })
+                }
                 Pipe(pipe_filedesc_obj) => {
                     if is_wronly(pipe_filedesc_obj.flags) {
                         return syscall_error(
@@ -793,34 +1110,41 @@ impl Cage {
                         );
                     }
                     let mut nonblocking = false;
-                    if pipe_filedesc_obj.flags & O_NONBLOCK != 0 {
+                    if pipe_filedesc_obj.statusflags.get() & O_NONBLOCK != 0 {
                         nonblocking = true;
                     }
+                    let pipe = pipe_filedesc_obj.pipe.clone();
+                    let mut cancel = self
+                        .cancel_registry
+                        .point_waking(move || pipe.wake_parked());
                     loop {
                         // loop over pipe reads so we can periodically check for cancellation
                         let ret = pipe_filedesc_obj
                             .pipe
                             .read_from_pipe(buf, count, nonblocking)
                             as i32;
-                        if pipe_filedesc_obj.flags & O_NONBLOCK == 0
+                        if pipe_filedesc_obj.statusflags.get() & O_NONBLOCK == 0
                             && ret == -(Errno::EAGAIN as i32)
                         {
-                            if self
-                                .cancelstatus
-                                .load(interface::RustAtomicOrdering::Relaxed)
-                            {
-                                // if the cancel status is set in the cage, we trap around a cancel point
-                                // until the individual thread is signaled to cancel itself
-                                loop {
-                                    interface::cancelpoint(self.cageid);
-                                }
+                            if let Some(cancelled) = cancel.check("read") {
+                                return cancelled;
+                            }
+                            if let Some(interrupted) = self.signal_interrupt("read", true) {
+                                return interrupted;
                             }
                             continue; //received EAGAIN on blocking pipe, try again
                         }
                         return ret; // if we get here we can return
                     }
                 }
-                Epoll(_) => syscall_error(
+                Inotify(inotify_filedesc_obj) => {
+                    let instance = inotify_filedesc_obj.instance.clone();
+                    let nonblocking = inotify_filedesc_obj.statusflags.get() & O_NONBLOCK != 0;
+                    // don't hold the fd lock while we wait for events to arrive
+                    drop(unlocked_fd);
+                    self._read_inotify(&instance, buf, count, nonblocking)
+                }
+                Epoll(_) | Mqueue(_) => syscall_error(
                     Errno::EINVAL,
                     "read",
                     "fd is attached to an object which is unsuitable for reading",
@@ -839,6 +1163,13 @@ impl Cage {
             match filedesc_enum {
                 //we must borrow the filedesc object as a mutable reference to update the position
                 File(ref mut normalfile_filedesc_obj) => {
+                    if is_pathonly(normalfile_filedesc_obj.flags) {
+                        return syscall_error(
+                            Errno::EBADF,
+                            "pread",
+                            "file descriptor was opened with O_PATH",
+                        );
+                    }
                     if is_wronly(normalfile_filedesc_obj.flags) {
                         return syscall_error(
                             Errno::EBADF,
@@ -847,10 +1178,11 @@ impl Cage {
                         );
                     }
 
-                    let inodeobj = FS_METADATA
-                        .inodetable
-                        .get(&normalfile_filedesc_obj.inode)
-                        .unwrap();
+                    let inodeobj = match FS_METADATA.inodetable.get(&normalfile_filedesc_obj.inode)
+                    {
+                        Some(inodeobj) => inodeobj,
+                        None => return corruption_error("pread", "open file has no inode"),
+                    };
 
                     //delegate to character if it's a character file, checking based on the type of the inode object
                     match &*inodeobj {
@@ -870,7 +1202,7 @@ impl Cage {
                         }
 
                         Inode::Socket(_) => {
-                            panic!("pread(): Socket inode found on a filedesc fd")
+                            corruption_error("pread", "socket inode found on a file fd")
                         }
 
                         Inode::Dir(_) => syscall_error(
@@ -900,6 +1232,16 @@ impl Cage {
                     "pread",
                     "file descriptor is associated with an epollfd, cannot seek",
                 ),
+                Inotify(_) => syscall_error(
+                    Errno::ESPIPE,
+                    "pread",
+                    "file descriptor is associated with an inotifyfd, cannot seek",
+                ),
+                Mqueue(_) => syscall_error(
+                    Errno::ESPIPE,
+                    "pread",
+                    "file descriptor is associated with an mqueuefd, cannot seek",
+                ),
             }
         } else {
             syscall_error(Errno::EBADF, "pread", "invalid file descriptor")
@@ -907,12 +1249,9 @@ impl Cage {
     }
 
     fn _read_chr_file(&self, inodeobj: &DeviceInode, buf: *mut u8, count: usize) -> i32 {
-        match inodeobj.dev {
-            NULLDEVNO => 0, //reading from /dev/null always reads 0 bytes
-            ZERODEVNO => interface::fillzero(buf, count),
-            RANDOMDEVNO => interface::fillrandom(buf, count),
-            URANDOMDEVNO => interface::fillrandom(buf, count),
-            _ => syscall_error(
+        match char_device(&inodeobj.dev) {
+            Some(device) => device.read(buf, count),
+            None => syscall_error(
                 Errno::EOPNOTSUPP,
                 "read or pread",
                 "read from specified device not implemented",
@@ -920,6 +1259,50 @@ impl Cage {
         }
     }
 
+    fn _read_inotify(
+        &self,
+        instance: &interface::RustRfc<InotifyInstance>,
+        buf: *mut u8,
+        count: usize,
+        nonblocking: bool,
+    ) -> i32 {
+        let mut cancel = self.cancel_registry.point();
+        loop {
+            let mut events = instance.queue.events.lock();
+            if let Some(first) = events.front() {
+                if first.packed_len() > count {
+                    return syscall_error(
+                        Errno::EINVAL,
+                        "read",
+                        "buffer is too small to hold the next inotify event",
+                    );
+                }
+                //copy out as many whole events as fit into the user buffer
+                let mut packed: Vec<u8> = vec![];
+                while let Some(event) = events.front() {
+                    if packed.len() + event.packed_len() > count {
+                        break;
+                    }
+                    packed.extend_from_slice(&event.pack());
+                    events.pop_front();
+                }
+                return interface::fill(buf, packed.len(), &packed);
+            }
+            drop(events);
+
+            if nonblocking {
+                return syscall_error(Errno::EAGAIN, "read", "there are no inotify events queued");
+            }
+            if let Some(cancelled) = cancel.check("read") {
+                return cancelled;
+            }
+            if let Some(interrupted) = self.signal_interrupt("read", true) {
+                return interrupted;
+            }
+            interface::lind_yield();
+        }
+    }
+
     //------------------------------------WRITE SYSCALL------------------------------------
 
     pub fn write_syscall(&self, fd: i32, buf: *const u8, count: usize) -> i32 {
@@ -938,49 +1321,153 @@ impl Cage {
                         );
                     }
 
-                    let mut inodeobj = FS_METADATA
+                    if let Some((tty, end)) = tty_of_inode(normalfile_filedesc_obj.inode) {
+                        let nonblocking =
+                            normalfile_filedesc_obj.statusflags.get() & O_NONBLOCK != 0;
+                        drop(unlocked_fd);
+                        let buf = match interface::UserBuffer::new(
+                            self.memory_region(),
+                            buf as *mut u8,
+                            count,
+                        ) {
+                            Ok(buf) => buf,
+                            Err(e) => return e,
+                        };
+                        return tty.write(end, buf, nonblocking, &|| {
+                            self.signal_interrupt("write", true)
+                        });
+                    }
+
+                    let mut inodeobj = match FS_METADATA
                         .inodetable
                         .get_mut(&normalfile_filedesc_obj.inode)
-                        .unwrap();
+                    {
+                        Some(inodeobj) => inodeobj,
+                        None => return corruption_error("write", "open file has no inode"),
+                    };
 
                     //delegate to character helper or print out if it's a character file or stream,
                     //checking based on the type of the inode object
                     match *inodeobj {
                         Inode::File(ref mut normalfile_inode_obj) => {
-                            let position = normalfile_filedesc_obj.position;
+                            //with O_APPEND every write goes to the end of the file
+                            let position =
+                                if normalfile_filedesc_obj.statusflags.get() & O_APPEND != 0 {
+                                    normalfile_inode_obj.size
+                                } else {
+                                    normalfile_filedesc_obj.position
+                                };
 
                             let filesize = normalfile_inode_obj.size;
                             let blankbytecount = position as isize - filesize as isize;
 
-                            let mut fileobject = FILEOBJECTTABLE
-                                .get_mut(&normalfile_filedesc_obj.inode)
-                                .unwrap();
+                            let newsize = interface::rust_max(filesize, position + count);
+                            let sealcheck = Self::_check_seals(
+                                normalfile_filedesc_obj.inode,
+                                filesize,
+                                newsize,
+                                true,
+                                "write",
+                            );
+                            if sealcheck < 0 {
+                                return sealcheck;
+                            }
+                            if !quota_resize_inode(normalfile_filedesc_obj.inode, filesize, newsize)
+                            {
+                                return syscall_error(
+                                    Errno::EDQUOT,
+                                    "write",
+                                    "the byte quota of the file's owner has been exhausted",
+                                );
+                            }
+                            if !mount_resize_inode(normalfile_filedesc_obj.inode, filesize, newsize)
+                            {
+                                quota_resize_inode(
+                                    normalfile_filedesc_obj.inode,
+                                    newsize,
+                                    filesize,
+                                );
+                                return syscall_error(
+                                    Errno::ENOSPC,
+                                    "write",
+                                    "the mounted filesystem has no space left",
+                                );
+                            }
 
-                            //we need to pad the file with blank bytes if we are at a position past the end of the file!
-                            if blankbytecount > 0 {
-                                if let Ok(byteswritten) =
-                                    fileobject.zerofill_at(filesize, blankbytecount as usize)
-                                {
-                                    if byteswritten != blankbytecount as usize {
-                                        panic!("Write of blank bytes for write failed!");
+                            let mut fileobject =
+                                match FILEOBJECTTABLE.get_mut(&normalfile_filedesc_obj.inode) {
+                                    Some(fileobject) => fileobject,
+                                    None => {
+                                        quota_resize_inode(
+                                            normalfile_filedesc_obj.inode,
+                                            newsize,
+                                            filesize,
+                                        );
+                                        mount_resize_inode(
+                                            normalfile_filedesc_obj.inode,
+                                            newsize,
+                                            filesize,
+                                        );
+                                        return corruption_error(
+                                            "write",
+                                            "open file has no file object",
+                                        );
                                     }
-                                } else {
-                                    panic!("Write of blank bytes for write failed!");
-                                }
+                                };
+
+                            //we need to pad the file with blank bytes if we are at a position past the end of the file!
+                            if blankbytecount > 0
+                                && fileobject
+                                    .zerofill_at(filesize, blankbytecount as usize)
+                                    .ok()
+                                    != Some(blankbytecount as usize)
+                            {
+                                drop(fileobject);
+                                quota_resize_inode(
+                                    normalfile_filedesc_obj.inode,
+                                    newsize,
+                                    filesize,
+                                );
+                                mount_resize_inode(
+                                    normalfile_filedesc_obj.inode,
+                                    newsize,
+                                    filesize,
+                                );
+                                return syscall_error(
+                                    Errno::EIO,
+                                    "write",
+                                    "write of blank bytes failed",
+                                );
                             }
 
                             let newposition;
                             if let Ok(byteswritten) = fileobject.writeat(buf, count, position) {
+                                //the size still has to be updated if syncing fails
+                                let synced = Self::_sync_written_file(
+                                    &fileobject,
+                                    normalfile_filedesc_obj.flags,
+                                )
+                                .is_ok();
                                 //move position forward by the number of bytes we've written
                                 normalfile_filedesc_obj.position = position + byteswritten;
                                 newposition = normalfile_filedesc_obj.position;
                                 if newposition > normalfile_inode_obj.size {
                                     normalfile_inode_obj.size = newposition;
+                                    let entry =
+                                        LogEntry::inode(normalfile_filedesc_obj.inode, &inodeobj);
                                     drop(inodeobj);
                                     drop(fileobject);
-                                    log_metadata(&FS_METADATA, normalfile_filedesc_obj.inode);
+                                    log_entries([entry]);
                                 } //update file size if necessary
+                                inotify_publish(normalfile_filedesc_obj.inode, IN_MODIFY, 0, None);
 
+                                if !synced {
+                                    return syscall_error(
+                                        Errno::EIO,
+                                        "write",
+                                        "the write could not be synchronized to disk",
+                                    );
+                                }
                                 byteswritten as i32
                             } else {
                                 0 //0 bytes written, but not an error value that can/should be passed to the user
@@ -992,7 +1479,7 @@ impl Cage {
                         }
 
                         Inode::Socket(_) => {
-                            panic!("write(): Socket inode found on a filedesc fd")
+                            corruption_error("write", "socket inode found on a file fd")
                         }
 
                         Inode::Dir(_) => syscall_error(
@@ -1029,20 +1516,21 @@ impl Cage {
                     }
 
                     let mut nonblocking = false;
-                    if pipe_filedesc_obj.flags & O_NONBLOCK != 0 {
+                    if pipe_filedesc_obj.statusflags.get() & O_NONBLOCK != 0 {
                         nonblocking = true;
                     }
 
-                    let retval = pipe_filedesc_obj
-                        .pipe
-                        .write_to_pipe(buf, count, nonblocking)
-                        as i32;
+                    //don't hold the descriptor while blocked on a full pipe, so it can still be
+                    //resized or have its flags changed through the same descriptor
+                    let pipe = pipe_filedesc_obj.pipe.clone();
+                    drop(unlocked_fd);
+                    let retval = pipe.write_to_pipe(buf, count, nonblocking) as i32;
                     if retval == -(Errno::EPIPE as i32) {
                         interface::lind_kill_from_id(self.cageid, SIGPIPE);
                     } // Trigger SIGPIPE
                     retval
                 }
-                Epoll(_) => syscall_error(
+                Epoll(_) | Inotify(_) | Mqueue(_) => syscall_error(
                     Errno::EINVAL,
                     "write",
                     "fd is attached to an object which is unsuitable for writing",
@@ -1070,10 +1558,13 @@ impl Cage {
                         );
                     }
 
-                    let mut inodeobj = FS_METADATA
+                    let mut inodeobj = match FS_METADATA
                         .inodetable
                         .get_mut(&normalfile_filedesc_obj.inode)
-                        .unwrap();
+                    {
+                        Some(inodeobj) => inodeobj,
+                        None => return corruption_error("pwrite", "open file has no inode"),
+                    };
 
                     //delegate to character helper or print out if it's a character file or stream,
                     //checking based on the type of the inode object
@@ -1083,31 +1574,107 @@ impl Cage {
                             let filesize = normalfile_inode_obj.size;
                             let blankbytecount = offset - filesize as isize;
 
-                            let mut fileobject = FILEOBJECTTABLE
-                                .get_mut(&normalfile_filedesc_obj.inode)
-                                .unwrap();
+                            let newsize = interface::rust_max(filesize, position + count);
+                            let sealcheck = Self::_check_seals(
+                                normalfile_filedesc_obj.inode,
+                                filesize,
+                                newsize,
+                                true,
+                                "pwrite",
+                            );
+                            if sealcheck < 0 {
+                                return sealcheck;
+                            }
+                            if !quota_resize_inode(normalfile_filedesc_obj.inode, filesize, newsize)
+                            {
+                                return syscall_error(
+                                    Errno::EDQUOT,
+                                    "pwrite",
+                                    "the byte quota of the file's owner has been exhausted",
+                                );
+                            }
+                            if !mount_resize_inode(normalfile_filedesc_obj.inode, filesize, newsize)
+                            {
+                                quota_resize_inode(
+                                    normalfile_filedesc_obj.inode,
+                                    newsize,
+                                    filesize,
+                                );
+                                return syscall_error(
+                                    Errno::ENOSPC,
+                                    "pwrite",
+                                    "the mounted filesystem has no space left",
+                                );
+                            }
 
-                            //we need to pad the file with blank bytes if we are seeking past the end of the file!
-                            if blankbytecount > 0 {
-                                if let Ok(byteswritten) =
-                                    fileobject.zerofill_at(filesize, blankbytecount as usize)
-                                {
-                                    if byteswritten != blankbytecount as usize {
-                                        panic!("Write of blank bytes for pwrite failed!");
+                            let mut fileobject =
+                                match FILEOBJECTTABLE.get_mut(&normalfile_filedesc_obj.inode) {
+                                    Some(fileobject) => fileobject,
+                                    None => {
+                                        quota_resize_inode(
+                                            normalfile_filedesc_obj.inode,
+                                            newsize,
+                                            filesize,
+                                        );
+                                        mount_resize_inode(
+                                            normalfile_filedesc_obj.inode,
+                                            newsize,
+                                            filesize,
+                                        );
+                                        return corruption_error(
+                                            "pwrite",
+                                            "open file has no file object",
+                                        );
                                     }
-                                } else {
-                                    panic!("Write of blank bytes for pwrite failed!");
-                                }
-                            }
+                                };
 
-                            let newposition;
-                            let retval = if let Ok(byteswritten) =
-                                fileobject.writeat(buf, count, position)
+                            //we need to pad the file with blank bytes if we are seeking past the end of the file!
+                            if blankbytecount > 0
+                                && fileobject
+                                    .zerofill_at(filesize, blankbytecount as usize)
+                                    .ok()
+                                    != Some(blankbytecount as usize)
                             {
-                                //move position forward by the number of bytes we've written
-                                newposition = position + byteswritten;
+                                drop(fileobject);
+                                quota_resize_inode(
+                                    normalfile_filedesc_obj.inode,
+                                    newsize,
+                                    filesize,
+                                );
+                                mount_resize_inode(
+                                    normalfile_filedesc_obj.inode,
+                                    newsize,
+                                    filesize,
+                                );
+                                return syscall_error(
+                                    Errno::EIO,
+                                    "pwrite",
+                                    "write of blank bytes failed",
+                                );
+                            }
 
-                                byteswritten as i32
+                            let newposition;
+                            let retval = if let Ok(byteswritten) =
+                                fileobject.writeat(buf, count, position)
+                            {
+                                //the size still has to be updated if syncing fails
+                                let synced = Self::_sync_written_file(
+                                    &fileobject,
+                                    normalfile_filedesc_obj.flags,
+                                )
+                                .is_ok();
+                                //move position forward by the number of bytes we've written
+                                newposition = position + byteswritten;
+
+                                if synced {
+                                    byteswritten as i32
+                                } else {
+                                    syscall_error(
+                                        Errno::EIO,
+                                        "pwrite",
+                                        "the write could not be synchronized to disk",
+                                    )
+                                }
                             } else {
                                 newposition = position;
                                 0 //0 bytes written, but not an error value that can/should be passed to the user
@@ -1116,10 +1683,13 @@ impl Cage {
 
                             if newposition > filesize {
                                 normalfile_inode_obj.size = newposition;
+                                let entry =
+                                    LogEntry::inode(normalfile_filedesc_obj.inode, &inodeobj);
                                 drop(fileobject);
                                 drop(inodeobj);
-                                log_metadata(&FS_METADATA, normalfile_filedesc_obj.inode);
+                                log_entries([entry]);
                             } //update file size if necessary
+                            inotify_publish(normalfile_filedesc_obj.inode, IN_MODIFY, 0, None);
 
                             retval
                         }
@@ -1129,7 +1699,7 @@ impl Cage {
                         }
 
                         Inode::Socket(_) => {
-                            panic!("pwrite: socket fd and inode don't match types")
+                            corruption_error("pwrite", "socket inode found on a file fd")
                         }
 
                         Inode::Dir(_) => syscall_error(
@@ -1159,20 +1729,70 @@ impl Cage {
                     "pwrite",
                     "file descriptor is associated with an epollfd, cannot seek",
                 ),
+                Inotify(_) => syscall_error(
+                    Errno::ESPIPE,
+                    "pwrite",
+                    "file descriptor is associated with an inotifyfd, cannot seek",
+                ),
+                Mqueue(_) => syscall_error(
+                    Errno::ESPIPE,
+                    "pwrite",
+                    "file descriptor is associated with an mqueuefd, cannot seek",
+                ),
             }
         } else {
             syscall_error(Errno::EBADF, "pwrite", "invalid file descriptor")
         }
     }
 
-    fn _write_chr_file(&self, inodeobj: &DeviceInode, _buf: *const u8, count: usize) -> i32 {
-        //writes to any of these device files transparently succeed while doing nothing
-        match inodeobj.dev {
-            NULLDEVNO => count as i32,
-            ZERODEVNO => count as i32,
-            RANDOMDEVNO => count as i32,
-            URANDOMDEVNO => count as i32,
-            _ => syscall_error(
+    //a write on an O_SYNC or O_DSYNC descriptor has to reach the disk before it returns
+    fn _sync_written_file(fileobject: &interface::EmulatedFile, flags: i32) -> std::io::Result<()> {
+        if is_sync(flags) {
+            fileobject.fsync()
+        } else if is_dsync(flags) {
+            fileobject.fdatasync()
+        } else {
+            Ok(())
+        }
+    }
+
+    //a memfd refuses the writes and size changes that its seals rule out
+    fn _check_seals(
+        inodenum: usize,
+        oldsize: usize,
+        newsize: usize,
+        writesdata: bool,
+        syscallname: &str,
+    ) -> i32 {
+        let seals = seals_of_inode(inodenum);
+        if writesdata && seals & (F_SEAL_WRITE | F_SEAL_FUTURE_WRITE) != 0 {
+            return syscall_error(
+                Errno::EPERM,
+                syscallname,
+                "the file is sealed against writes",
+            );
+        }
+        if newsize > oldsize && seals & F_SEAL_GROW != 0 {
+            return syscall_error(
+                Errno::EPERM,
+                syscallname,
+                "the file is sealed against growing",
+            );
+        }
+        if newsize < oldsize && seals & F_SEAL_SHRINK != 0 {
+            return syscall_error(
+                Errno::EPERM,
+                syscallname,
+                "the file is sealed against shrinking",
+            );
+        }
+        0
+    }
+
+    fn _write_chr_file(&self, inodeobj: &DeviceInode, buf: *const u8, count: usize) -> i32 {
+        match char_device(&inodeobj.dev) {
+            Some(device) => device.write(buf, count),
+            None => syscall_error(
                 Errno::EOPNOTSUPP,
                 "write or pwrite",
                 "write to specified device not implemented",
@@ -1200,10 +1820,10 @@ impl Cage {
                     match sockhandle.domain {
                         AF_INET | AF_INET6 => match sockhandle.protocol {
                             IPPROTO_TCP => {
-                                // to be able to send here we either need to be fully connected, or connected for write only
-                                if (sockhandle.state != ConnState::CONNECTED)
-                                    && (sockhandle.state != ConnState::CONNWRONLY)
-                                {
+                                if sockhandle.shut_wr {
+                                    return self._send_after_shutdown(0);
+                                }
+                                if sockhandle.state != ConnState::CONNECTED {
                                     return syscall_error(
                                         Errno::ENOTCONN,
                                         "send",
@@ -1213,27 +1833,19 @@ impl Cage {
 
                                 //because socket must be connected it must have an inner raw socket
                                 // lets call the kernel writev on that socket
-                                let retval = sockhandle
+                                return match sockhandle
                                     .innersocket
                                     .as_ref()
                                     .unwrap()
-                                    .writev(iovec, iovcnt);
-                                if retval < 0 {
-                                    match Errno::from_discriminant(interface::get_errno()) {
-                                        Ok(i) => {
-                                            return syscall_error(
-                                                i,
-                                                "writev",
-                                                "The libc call to writev failed!",
-                                            );
-                                        }
-                                        Err(()) => panic!(
-                                            "Unknown errno value from socket writev returned!"
-                                        ),
-                                    };
-                                } else {
-                                    return retval;
-                                }
+                                    .writev(iovec, iovcnt)
+                                {
+                                    Ok(retval) => retval,
+                                    Err(errno) => interface::host_syscall_error(
+                                        errno,
+                                        "writev",
+                                        "The libc call to writev failed!",
+                                    ),
+                                };
                             }
                             _ => {
                                 return syscall_error(
@@ -1273,10 +1885,18 @@ impl Cage {
             //confirm fd type is seekable
             match filedesc_enum {
                 File(ref mut normalfile_filedesc_obj) => {
-                    let inodeobj = FS_METADATA
-                        .inodetable
-                        .get(&normalfile_filedesc_obj.inode)
-                        .unwrap();
+                    if is_pathonly(normalfile_filedesc_obj.flags) {
+                        return syscall_error(
+                            Errno::EBADF,
+                            "lseek",
+                            "file descriptor was opened with O_PATH",
+                        );
+                    }
+                    let inodeobj = match FS_METADATA.inodetable.get(&normalfile_filedesc_obj.inode)
+                    {
+                        Some(inodeobj) => inodeobj,
+                        None => return corruption_error("lseek", "open file has no inode"),
+                    };
 
                     //handle files/directories differently
                     match &*inodeobj {
@@ -1310,17 +1930,16 @@ impl Cage {
                         }
 
                         Inode::Socket(_) => {
-                            panic!("lseek: socket fd and inode don't match types")
+                            corruption_error("lseek", "socket inode found on a file fd")
                         }
 
                         Inode::Dir(dir_inode_obj) => {
-                            //for directories we seek between entries, and thus our end position is the total number of entries
+                            //for directories we seek between entry cookies, and thus our end position is just past the newest entry
+                            let endpos = dir_inode_obj.filename_to_inode_dict.end_cookie() as isize;
                             let eventualpos = match whence {
                                 SEEK_SET => offset,
                                 SEEK_CUR => normalfile_filedesc_obj.position as isize + offset,
-                                SEEK_END => {
-                                    dir_inode_obj.filename_to_inode_dict.len() as isize + offset
-                                }
+                                SEEK_END => endpos + offset,
                                 _ => {
                                     return syscall_error(Errno::EINVAL, "lseek", "unknown whence");
                                 }
@@ -1334,7 +1953,7 @@ impl Cage {
                                     "seek to before position 0 in directory",
                                 );
                             }
-                            if eventualpos > dir_inode_obj.filename_to_inode_dict.len() as isize {
+                            if eventualpos > endpos {
                                 return syscall_error(
                                     Errno::EINVAL,
                                     "lseek",
@@ -1368,6 +1987,16 @@ impl Cage {
                     "lseek",
                     "file descriptor is associated with an epollfd, cannot seek",
                 ),
+                Inotify(_) => syscall_error(
+                    Errno::ESPIPE,
+                    "lseek",
+                    "file descriptor is associated with an inotifyfd, cannot seek",
+                ),
+                Mqueue(_) => syscall_error(
+                    Errno::ESPIPE,
+                    "lseek",
+                    "file descriptor is associated with an mqueuefd, cannot seek",
+                ),
             }
         } else {
             syscall_error(Errno::EBADF, "lseek", "invalid file descriptor")
@@ -1376,38 +2005,60 @@ impl Cage {
 
     //------------------------------------ACCESS SYSCALL------------------------------------
 
+    //the mode and owner of an inode, which are what its permissions are judged by
+    fn _inode_perms(inodeobj: &Inode) -> (u32, u32, u32) {
+        match inodeobj {
+            Inode::File(f) => (f.mode, f.uid, f.gid),
+            Inode::CharDev(f) => (f.mode, f.uid, f.gid),
+            Inode::Socket(f) => (f.mode, f.uid, f.gid),
+            Inode::Dir(f) => (f.mode, f.uid, f.gid),
+        }
+    }
+
+    fn _inode_permits(cred: &Credentials, inodenum: usize, want: u32) -> bool {
+        let (mode, uid, gid) = Self::_inode_perms(&FS_METADATA.inodetable.get(&inodenum).unwrap());
+        cred.permits(mode, uid, gid, want)
+    }
+
+    //only the owner of a file, or a cage with CAP_FOWNER, may change its mode
+    fn _inode_owned(&self, inodenum: usize) -> bool {
+        let (_, uid, _) = Self::_inode_perms(&FS_METADATA.inodetable.get(&inodenum).unwrap());
+        let cred = self.cred.read();
+        cred.capable(CAP_FOWNER) || cred.euid == uid
+    }
+
     pub fn access_syscall(&self, path: &str, amode: u32) -> i32 {
         let truepath = normpath(convpath(path), self);
 
         //Walk the file tree to get inode from path
         if let Some(inodenum) = metawalk(truepath.as_path()) {
-            let inodeobj = FS_METADATA.inodetable.get(&inodenum).unwrap();
-
-            //Get the mode bits if the type of the inode is sane
-            let mode = match &*inodeobj {
-                Inode::File(f) => f.mode,
-                Inode::CharDev(f) => f.mode,
-                Inode::Socket(f) => f.mode,
-                Inode::Dir(f) => f.mode,
-            };
-
-            //We assume that the current user owns the file
-
-            //Construct desired access bits (i.e. 0777) based on the amode parameter
-            let mut newmode: u32 = 0;
-            if amode & X_OK == X_OK {
-                newmode |= S_IXUSR;
-            }
-            if amode & W_OK == W_OK {
-                newmode |= S_IWUSR;
+            let mountflags = mount_flags(&truepath);
+            if amode & W_OK == W_OK && path_is_readonly(&truepath) {
+                return syscall_error(
+                    Errno::EROFS,
+                    "access",
+                    "write access was requested on a read-only mount",
+                );
             }
-            if amode & R_OK == R_OK {
-                newmode |= S_IRUSR;
+            if amode & X_OK == X_OK && mountflags & MS_NOEXEC != 0 {
+                return syscall_error(
+                    Errno::EACCES,
+                    "access",
+                    "execute access was requested on a noexec mount",
+                );
             }
 
-            //if the desired access bits are compatible with the actual access bits
-            //of the file, return a success result, else return a failure result
-            if mode & newmode == newmode {
+            //access is judged by the real ids rather than the effective ones, and only a real
+            //root keeps its capabilities for it
+            let mut cred = self.cred.read().clone();
+            cred.euid = cred.ruid;
+            cred.egid = cred.rgid;
+            cred.cap_effective = if cred.ruid == 0 {
+                cred.cap_permitted
+            } else {
+                0
+            };
+            if Self::_inode_permits(&cred, inodenum, amode & (R_OK | W_OK | X_OK)) {
                 0
             } else {
                 syscall_error(
@@ -1431,33 +2082,57 @@ impl Cage {
         let checkedfd = self.get_filedescriptor(fd).unwrap();
         let unlocked_fd = checkedfd.read();
 
-        let path_string = match &*unlocked_fd {
-            Some(File(normalfile_filedesc_obj)) => {
-                let inodenum = normalfile_filedesc_obj.inode;
-                match pathnamefrominodenum(inodenum) {
-                    Some(name) => name,
-                    None => {
-                        return syscall_error(
-                            Errno::ENOTDIR,
-                            "fchdir",
-                            "the file descriptor does not refer to a directory",
-                        )
-                    }
-                }
-            }
+        let inodenum = match &*unlocked_fd {
+            Some(File(normalfile_filedesc_obj)) => normalfile_filedesc_obj.inode,
             Some(_) => {
                 return syscall_error(
-                    Errno::EACCES,
+                    Errno::ENOTDIR,
                     "fchdir",
-                    "cannot change working directory on this file descriptor",
+                    "the file descriptor does not refer to a directory",
                 )
             }
             None => return syscall_error(Errno::EBADF, "fchdir", "invalid file descriptor"),
         };
+        drop(unlocked_fd);
+
+        if let Inode::Dir(ref mut dir) = *(FS_METADATA.inodetable.get_mut(&inodenum).unwrap()) {
+            //increment refcount of new cwd inode to ensure that you can't remove a directory while it is the cwd of a cage
+            dir.refcount += 1;
+        } else {
+            return syscall_error(
+                Errno::ENOTDIR,
+                "fchdir",
+                "the file descriptor does not refer to a directory",
+            );
+        }
+        //a directory that has been removed while open has no path to become the cwd
+        let path_string = match pathnamefrominodenum(inodenum) {
+            Some(name) => name,
+            None => {
+                if let Inode::Dir(ref mut dir) =
+                    *(FS_METADATA.inodetable.get_mut(&inodenum).unwrap())
+                {
+                    dir.refcount -= 1;
+                }
+                return syscall_error(
+                    Errno::ENOENT,
+                    "fchdir",
+                    "the directory referred to by the file descriptor has been removed",
+                );
+            }
+        };
 
         let mut cwd_container = self.cwd.write();
 
-        *cwd_container = interface::RustRfc::new(convpath(path_string.as_str()));
+        //decrement refcount of previous cwd's inode, to allow it to be removed if no cage has it as cwd
+        decref_dir(&*cwd_container);
+
+        //the path is already a true path, collecting its components just tidies it up
+        *cwd_container = interface::RustRfc::new(
+            convpath(path_string.as_str())
+                .components()
+                .collect::<interface::RustPathBuf>(),
+        );
 
         0 // fchdir success
     }
@@ -1495,6 +2170,48 @@ impl Cage {
         0 //chdir has succeeded!;
     }
 
+    //------------------------------------CHROOT SYSCALL------------------------------------
+
+    pub fn chroot_syscall(&self, path: &str) -> i32 {
+        let truepath = normpath(convpath(path), self);
+        if let Some(inodenum) = metawalk(&truepath) {
+            if let Inode::Dir(ref mut dir) = *(FS_METADATA.inodetable.get_mut(&inodenum).unwrap()) {
+                //like the cwd, the new root can't be removed while a cage is jailed in it
+                if is_jailed(&truepath) {
+                    dir.refcount += 1;
+                }
+            } else {
+                return syscall_error(
+                    Errno::ENOTDIR,
+                    "chroot",
+                    "the last component in path is not a directory",
+                );
+            }
+        } else {
+            return syscall_error(
+                Errno::ENOENT,
+                "chroot",
+                "the directory referred to in path does not exist",
+            );
+        }
+
+        let mut rootdir_container = self.rootdir.write();
+        if is_jailed(&rootdir_container) {
+            decref_dir(&*rootdir_container);
+        }
+
+        //a cwd left outside of the new root would let relative paths escape it, so move it in
+        let mut cwd_container = self.cwd.write();
+        if !cwd_container.starts_with(&truepath) {
+            incref_dir(&truepath);
+            decref_dir(&*cwd_container);
+            *cwd_container = interface::RustRfc::new(truepath.clone());
+        }
+
+        *rootdir_container = interface::RustRfc::new(truepath);
+        0 //chroot has succeeded!;
+    }
+
     //------------------------------------DUP & DUP2 SYSCALLS------------------------------------
 
     pub fn dup_syscall(&self, fd: i32, start_desc: Option<i32>) -> i32 {
@@ -1518,12 +2235,12 @@ impl Cage {
         };
 
         //checking whether the fd exists in the file table
-        return Self::_dup2_helper(&self, filedesc_enum, start_fd, false);
+        return Self::_dup2_helper(&self, filedesc_enum, start_fd, false, false);
     }
 
     pub fn dup2_syscall(&self, oldfd: i32, newfd: i32) -> i32 {
         //checking if the new fd is out of range
-        if newfd >= MAXFD || newfd < 0 {
+        if newfd >= self.filedescriptortable.limit() || newfd < 0 {
             return syscall_error(
                 Errno::EBADF,
                 "dup2",
@@ -1537,7 +2254,7 @@ impl Cage {
 
         // get the filedesc_enum
         let checkedfd = self.get_filedescriptor(oldfd).unwrap();
-        let filedesc_enum = checkedfd.write();
+        let filedesc_enum = checkedfd.read();
         let filedesc_enum = if let Some(f) = &*filedesc_enum {
             f
         } else {
@@ -1545,10 +2262,57 @@ impl Cage {
         };
 
         //if the old fd exists, execute the helper, else return error
-        return Self::_dup2_helper(&self, filedesc_enum, newfd, true);
+        return Self::_dup2_helper(&self, filedesc_enum, newfd, true, false);
+    }
+
+    pub fn dup3_syscall(&self, oldfd: i32, newfd: i32, flags: i32) -> i32 {
+        //O_CLOEXEC is the only flag dup3 accepts
+        if flags & !O_CLOEXEC != 0 {
+            return syscall_error(Errno::EINVAL, "dup3", "invalid flags were provided");
+        }
+
+        //checking if the new fd is out of range
+        if newfd >= self.filedescriptortable.limit() || newfd < 0 {
+            return syscall_error(
+                Errno::EBADF,
+                "dup3",
+                "provided file descriptor is out of range",
+            );
+        }
+
+        //unlike dup2, duplicating a descriptor onto itself is an error
+        if newfd == oldfd {
+            return syscall_error(
+                Errno::EINVAL,
+                "dup3",
+                "old and new file descriptors are the same",
+            );
+        }
+
+        // get the filedesc_enum
+        let checkedfd = match self.get_filedescriptor(oldfd) {
+            Ok(checkedfd) => checkedfd,
+            Err(()) => return syscall_error(Errno::EBADF, "dup3", "Invalid old file descriptor."),
+        };
+        let filedesc_enum = checkedfd.read();
+        let filedesc_enum = if let Some(f) = &*filedesc_enum {
+            f
+        } else {
+            return syscall_error(Errno::EBADF, "dup3", "Invalid old file descriptor.");
+        };
+
+        return Self::_dup2_helper(&self, filedesc_enum, newfd, true, flags & O_CLOEXEC != 0);
     }
 
-    pub fn _dup2_helper(&self, filedesc_enum: &FileDescriptor, newfd: i32, fromdup2: bool) -> i32 {
+    //the duplicate never inherits the close-on-exec flag of the original, it's set on the new
+    //descriptor only when cloexec is requested
+    pub fn _dup2_helper(
+        &self,
+        filedesc_enum: &FileDescriptor,
+        newfd: i32,
+        fromdup2: bool,
+        cloexec: bool,
+    ) -> i32 {
         let (dupfd, mut dupfdguard) = if fromdup2 {
             let mut fdguard = self.filedescriptortable[newfd as usize].write();
             let closebool = fdguard.is_some();
@@ -1556,6 +2320,7 @@ impl Cage {
             // close the fd in the way of the new fd. mirror the implementation of linux, ignore the potential error of the close here
             if closebool {
                 let _close_result = Self::_close_helper_inner(&self, newfd);
+                self._epoll_forget_fd(newfd);
             }
 
             // re-grab clean fd
@@ -1565,7 +2330,7 @@ impl Cage {
             let (newdupfd, guardopt) = self.get_next_fd(Some(newfd));
             if newdupfd < 0 {
                 return syscall_error(
-                    Errno::ENFILE,
+                    Errno::EMFILE,
                     "dup2_helper",
                     "no available file descriptor number could be found",
                 );
@@ -1578,7 +2343,10 @@ impl Cage {
         match filedesc_enum {
             File(normalfile_filedesc_obj) => {
                 let inodenum = normalfile_filedesc_obj.inode;
-                let mut inodeobj = FS_METADATA.inodetable.get_mut(&inodenum).unwrap();
+                let mut inodeobj = match FS_METADATA.inodetable.get_mut(&inodenum) {
+                    Some(inodeobj) => inodeobj,
+                    None => return corruption_error("dup2", "open file has no inode"),
+                };
                 //incrementing the ref count so that when close is executed on the dup'd file
                 //the original file does not get a negative ref count
                 match *inodeobj {
@@ -1591,7 +2359,9 @@ impl Cage {
                     Inode::CharDev(ref mut chardev_inode_obj) => {
                         chardev_inode_obj.refcount += 1;
                     }
-                    Inode::Socket(_) => panic!("dup: fd and inode do not match."),
+                    Inode::Socket(_) => {
+                        return corruption_error("dup", "socket inode found on a file fd")
+                    }
                 }
             }
             Pipe(pipe_filedesc_obj) => {
@@ -1612,12 +2382,26 @@ impl Cage {
                         if let Some(receivepipe) = sockinfo.receivepipe.as_ref() {
                             receivepipe.incr_ref(O_RDONLY);
                         }
+                        //close gives up a reference to the inode for every descriptor, as with fork
+                        if let Some(Inode::Socket(ref mut sock)) = FS_METADATA
+                            .inodetable
+                            .get_mut(&sockinfo.inode)
+                            .as_deref_mut()
+                        {
+                            sock.refcount += 1;
+                        }
                     }
                 }
             }
             Stream(_normalfile_filedesc_obj) => {
                 // no stream refs
             }
+            Inotify(_) => {
+                // the inotify instance is refcounted through its Rfc
+            }
+            Mqueue(_) => {
+                // the message queue is refcounted through its Rfc
+            }
             _ => {
                 return syscall_error(Errno::EACCES, "dup or dup2", "can't dup the provided file");
             }
@@ -1641,11 +2425,29 @@ impl Cage {
             Stream(ref mut stream_filedesc_obj) => {
                 stream_filedesc_obj.flags = stream_filedesc_obj.flags & !O_CLOEXEC;
             }
+            Inotify(ref mut inotify_filedesc_obj) => {
+                inotify_filedesc_obj.flags = inotify_filedesc_obj.flags & !O_CLOEXEC;
+            }
+            Mqueue(ref mut mqueue_filedesc_obj) => {
+                mqueue_filedesc_obj.flags = mqueue_filedesc_obj.flags & !O_CLOEXEC;
+            }
             _ => {
                 return syscall_error(Errno::EACCES, "dup or dup2", "can't dup the provided file");
             }
         }
 
+        if cloexec {
+            match dupd_fd_enum {
+                File(ref mut obj) => obj.flags |= O_CLOEXEC,
+                Pipe(ref mut obj) => obj.flags |= O_CLOEXEC,
+                Socket(ref mut obj) => obj.flags |= O_CLOEXEC,
+                Stream(ref mut obj) => obj.flags |= O_CLOEXEC,
+                Inotify(ref mut obj) => obj.flags |= O_CLOEXEC,
+                Epoll(ref mut obj) => obj.flags |= O_CLOEXEC,
+                Mqueue(ref mut obj) => obj.flags |= O_CLOEXEC,
+            }
+        }
+
         let _insertval = dupfdoption.insert(dupd_fd_enum);
 
         return dupfd;
@@ -1669,7 +2471,24 @@ impl Cage {
             match filedesc_enum {
                 //if we are a socket, we dont change disk metadata
                 Stream(_) => {}
-                Epoll(_) => {} //Epoll closing not implemented yet
+                Epoll(ref epoll_filedesc_obj) => {
+                    //the fds it watched are no longer registered with it
+                    for registered in epoll_filedesc_obj.registered_fds.iter() {
+                        self._epoll_unregister(fd, *registered.key());
+                    }
+                }
+                Inotify(ref inotify_filedesc_obj) => {
+                    //the watches are only torn down once no other fd refers to the instance
+                    if interface::RustRfc::strong_count(&inotify_filedesc_obj.instance) == 1 {
+                        inotify_filedesc_obj.instance.release();
+                    }
+                }
+                Mqueue(ref mqueue_filedesc_obj) => {
+                    //any close by the registered cage gives up its notification
+                    mqueue_filedesc_obj
+                        .mqueue
+                        .set_notification(None, self.cageid);
+                }
                 Socket(ref mut socket_filedesc_obj) => {
                     let sock_tmp = socket_filedesc_obj.handle.clone();
                     let mut sockhandle = sock_tmp.write();
@@ -1700,19 +2519,18 @@ impl Cage {
                                 ui.receivepipe = None;
                             }
                         }
-                        let mut inodeobj = FS_METADATA.inodetable.get_mut(&ui.inode).unwrap();
+                        let mut inodeobj = match FS_METADATA.inodetable.get_mut(&ui.inode) {
+                            Some(inodeobj) => inodeobj,
+                            None => return corruption_error("close", "open file has no inode"),
+                        };
                         if let Inode::Socket(ref mut sock) = *inodeobj {
                             sock.refcount -= 1;
-                            if sock.refcount == 0 {
-                                if sock.linkcount == 0 {
-                                    drop(inodeobj);
-                                    let path = normpath(
-                                        convpath(sockhandle.localaddr.unwrap().path()),
-                                        self,
-                                    );
-                                    FS_METADATA.inodetable.remove(&inodenum);
-                                    NET_METADATA.domsock_paths.remove(&path);
-                                }
+                            //a bound path outlives the socket until it is unlinked, and connecting
+                            //to it is refused from here on. unlink has already forgotten the path
+                            //of an inode with no links, so a new socket bound there is untouched
+                            if sock.refcount == 0 && sock.linkcount == 0 {
+                                drop(inodeobj);
+                                FS_METADATA.inodetable.remove(&inodenum);
                             }
                         }
                     }
@@ -1730,7 +2548,17 @@ impl Cage {
                 }
                 File(ref normalfile_filedesc_obj) => {
                     let inodenum = normalfile_filedesc_obj.inode;
-                    let mut inodeobj = FS_METADATA.inodetable.get_mut(&inodenum).unwrap();
+                    //the open file description goes with its last descriptor, and its lease too
+                    if interface::RustRfc::strong_count(&normalfile_filedesc_obj.statusflags) == 1 {
+                        let holder =
+                            interface::RustRfc::as_ptr(&normalfile_filedesc_obj.statusflags)
+                                as usize;
+                        release_lease(inodenum, holder);
+                    }
+                    let mut inodeobj = match FS_METADATA.inodetable.get_mut(&inodenum) {
+                        Some(inodeobj) => inodeobj,
+                        None => return corruption_error("close", "open file has no inode"),
+                    };
 
                     match *inodeobj {
                         Inode::File(ref mut normalfile_inode_obj) => {
@@ -1738,7 +2566,18 @@ impl Cage {
 
                             //if it's not a reg file, then we have nothing to close
                             //Inode::File is a regular file by default
-                            if normalfile_inode_obj.refcount == 0 {
+                            if normalfile_inode_obj.refcount == 0 && !inode_is_persistent(inodenum)
+                            {
+                                //the file object of an in-memory file holds its data, so it is
+                                //only dropped along with the inode
+                                if normalfile_inode_obj.linkcount == 0 {
+                                    let size = normalfile_inode_obj.size;
+                                    drop(inodeobj);
+                                    FS_METADATA.inodetable.remove(&inodenum);
+                                    quota_release_inode(inodenum, size);
+                                    mount_release_inode(inodenum, size);
+                                }
+                            } else if normalfile_inode_obj.refcount == 0 {
                                 FILEOBJECTTABLE
                                     .remove(&inodenum)
                                     .unwrap()
@@ -1746,12 +2585,14 @@ impl Cage {
                                     .close()
                                     .unwrap();
                                 if normalfile_inode_obj.linkcount == 0 {
+                                    let size = normalfile_inode_obj.size;
                                     drop(inodeobj);
                                     //removing the file from the entire filesystem (interface, metadata, and object table)
                                     FS_METADATA.inodetable.remove(&inodenum);
+                                    quota_release_inode(inodenum, size);
                                     let sysfilename = format!("{}{}", FILEDATAPREFIX, inodenum);
                                     interface::removefile(sysfilename).unwrap();
-                                    log_metadata(&FS_METADATA, inodenum);
+                                    log_entries([LogEntry::removed(inodenum)]);
                                 } else {
                                     drop(inodeobj);
                                 }
@@ -1763,10 +2604,9 @@ impl Cage {
                             //if it's not a reg file, then we have nothing to close
                             match FILEOBJECTTABLE.get(&inodenum) {
                                 Some(_) => {
-                                    return syscall_error(
-                                        Errno::ENOEXEC,
-                                        "close or dup",
-                                        "Non-regular file in file object table",
+                                    return corruption_error(
+                                        "close",
+                                        "non-regular file in file object table",
                                     );
                                 }
                                 None => {}
@@ -1775,7 +2615,7 @@ impl Cage {
                                 //removing the file from the metadata
                                 FS_METADATA.inodetable.remove(&inodenum);
                                 drop(inodeobj);
-                                log_metadata(&FS_METADATA, inodenum);
+                                log_entries([LogEntry::removed(inodenum)]);
                             }
                         }
                         Inode::CharDev(ref mut char_inode_obj) => {
@@ -1784,25 +2624,31 @@ impl Cage {
                             //if it's not a reg file, then we have nothing to close
                             match FILEOBJECTTABLE.get(&inodenum) {
                                 Some(_) => {
-                                    return syscall_error(
-                                        Errno::ENOEXEC,
-                                        "close or dup",
-                                        "Non-regular file in file object table",
+                                    return corruption_error(
+                                        "close",
+                                        "non-regular file in file object table",
                                     );
                                 }
                                 None => {}
                             }
+                            let released = char_inode_obj.refcount == 0;
+                            let dev = char_inode_obj.dev;
                             if char_inode_obj.linkcount == 0 && char_inode_obj.refcount == 0 {
                                 //removing the file from the metadata
                                 drop(inodeobj);
                                 FS_METADATA.inodetable.remove(&inodenum);
+                                quota_release_inode(inodenum, 0);
+                                mount_release_inode(inodenum, 0);
                             } else {
                                 drop(inodeobj);
                             }
                             log_metadata(&FS_METADATA, inodenum);
+                            if released {
+                                tty_release(&dev);
+                            }
                         }
                         Inode::Socket(_) => {
-                            panic!("close(): Socket inode found on a filedesc fd.")
+                            return corruption_error("close", "socket inode found on a file fd");
                         }
                     }
                 }
@@ -1815,7 +2661,8 @@ impl Cage {
 
     pub fn _close_helper(&self, fd: i32) -> i32 {
         let inner_result = self._close_helper_inner(fd);
-        if inner_result < 0 {
+        //a descriptor found to be corrupted is still dropped, since nothing could close it otherwise
+        if inner_result < 0 && inner_result != -(Errno::EIO as i32) {
             return inner_result;
         }
 
@@ -1825,7 +2672,9 @@ impl Cage {
         if unlocked_fd.is_some() {
             let _discarded_fd = unlocked_fd.take();
         }
-        0 //_close_helper has succeeded!
+        drop(unlocked_fd);
+        self._epoll_forget_fd(fd);
+        inner_result //0 unless what the descriptor referred to was corrupted
     }
 
     //------------------------------------FCNTL SYSCALL------------------------------------
@@ -1834,42 +2683,56 @@ impl Cage {
         let checkedfd = self.get_filedescriptor(fd).unwrap();
         let mut unlocked_fd = checkedfd.write();
         if let Some(filedesc_enum) = &mut *unlocked_fd {
-            let flags = match filedesc_enum {
-                Epoll(obj) => &mut obj.flags,
-                Pipe(obj) => &mut obj.flags,
-                Stream(obj) => &mut obj.flags,
-                File(obj) => &mut obj.flags,
-                Socket(ref mut sockfdobj) => {
-                    if cmd == F_SETFL && arg >= 0 {
-                        let sock_tmp = sockfdobj.handle.clone();
-                        let mut sockhandle = sock_tmp.write();
-
-                        if let Some(ins) = &mut sockhandle.innersocket {
-                            let fcntlret;
-                            if arg & O_NONBLOCK == O_NONBLOCK {
-                                //set for non-blocking I/O
-                                fcntlret = ins.set_nonblocking();
-                            } else {
-                                //clear non-blocking I/O
-                                fcntlret = ins.set_blocking();
-                            }
-                            if fcntlret < 0 {
-                                match Errno::from_discriminant(interface::get_errno()) {
-                                    Ok(i) => {
-                                        return syscall_error(
-                                            i,
-                                            "fcntl",
-                                            "The libc call to fcntl failed!",
-                                        );
-                                    }
-                                    Err(()) => panic!("Unknown errno value from fcntl returned!"),
-                                };
-                            }
+            if cmd == F_GETPIPE_SZ || cmd == F_SETPIPE_SZ {
+                return match filedesc_enum {
+                    Pipe(pipe_filedesc_obj) if cmd == F_GETPIPE_SZ => {
+                        pipe_filedesc_obj.pipe.capacity() as i32
+                    }
+                    Pipe(pipe_filedesc_obj) => Self::_set_pipe_size(&pipe_filedesc_obj.pipe, arg),
+                    _ => syscall_error(Errno::EBADF, "fcntl", "fd is not a pipe"),
+                };
+            }
+            if cmd == F_GET_SEALS || cmd == F_ADD_SEALS {
+                return match filedesc_enum {
+                    File(normalfile_filedesc_obj) if cmd == F_GET_SEALS => {
+                        match FILE_SEALS.get(&normalfile_filedesc_obj.inode) {
+                            Some(seals) => *seals,
+                            None => syscall_error(Errno::EINVAL, "fcntl", "fd is not a memfd"),
                         }
                     }
+                    File(normalfile_filedesc_obj) => Self::_add_seals(normalfile_filedesc_obj, arg),
+                    _ => syscall_error(Errno::EINVAL, "fcntl", "fd is not a memfd"),
+                };
+            }
 
-                    &mut sockfdobj.flags
-                }
+            if cmd == F_SETLEASE || cmd == F_GETLEASE {
+                return match filedesc_enum {
+                    File(normalfile_filedesc_obj) if cmd == F_GETLEASE => {
+                        Self::_get_lease(normalfile_filedesc_obj)
+                    }
+                    File(normalfile_filedesc_obj) => self._set_lease(normalfile_filedesc_obj, arg),
+                    _ => syscall_error(Errno::EINVAL, "fcntl", "fd is not a regular file"),
+                };
+            }
+
+            //sockets are always open for reading and writing, but keep no access mode in their flags
+            let accmode = match filedesc_enum {
+                Socket(_) => O_RDWR,
+                _ => 0,
+            };
+
+            //only sockets and pipes raise signals when O_ASYNC is set
+            let asyncable = matches!(filedesc_enum, Socket(_) | Pipe(_));
+
+            //close-on-exec lives in the descriptor, the rest in its shared open file description
+            let (flags, statusflags) = match filedesc_enum {
+                Epoll(obj) => (&mut obj.flags, obj.statusflags.clone()),
+                Inotify(obj) => (&mut obj.flags, obj.statusflags.clone()),
+                Mqueue(obj) => (&mut obj.flags, obj.statusflags.clone()),
+                Pipe(obj) => (&mut obj.flags, obj.statusflags.clone()),
+                Stream(obj) => (&mut obj.flags, obj.statusflags.clone()),
+                File(obj) => (&mut obj.flags, obj.statusflags.clone()),
+                Socket(obj) => (&mut obj.flags, obj.statusflags.clone()),
             };
 
             //matching the tuple
@@ -1877,8 +2740,8 @@ impl Cage {
                 //because the arg parameter is not used in certain commands, it can be anything (..)
                 (F_GETFD, ..) => *flags & O_CLOEXEC,
                 // set the flags but make sure that the flags are valid
-                (F_SETFD, arg) if arg >= 0 => {
-                    if arg & O_CLOEXEC != 0 {
+                (F_SETFD, arg) => {
+                    if arg & (O_CLOEXEC | FD_CLOEXEC) != 0 {
                         *flags |= O_CLOEXEC;
                     } else {
                         *flags &= !O_CLOEXEC;
@@ -1887,20 +2750,35 @@ impl Cage {
                 }
                 (F_GETFL, ..) => {
                     //for get, we just need to return the flags
-                    *flags & !O_CLOEXEC
+                    (*flags & !O_CLOEXEC) | accmode | statusflags.get()
                 }
-                (F_SETFL, arg) if arg >= 0 => {
-                    *flags |= arg;
+                //the access mode and creation flags can't be changed, only the status flags;
+                //blocking sockets consult these on every call, so O_NONBLOCK takes effect at once
+                (F_SETFL, arg) => {
+                    statusflags.set(arg);
+                    if arg & O_ASYNC != 0 && asyncable {
+                        fasync_register(self.cageid, fd, &statusflags);
+                    }
                     0
                 }
-                (F_DUPFD, arg) if arg >= 0 => self._dup2_helper(&filedesc_enum, arg, false),
-                //TO DO: implement. this one is saying get the signals
-                (F_GETOWN, ..) => {
-                    0 //TO DO: traditional SIGIO behavior
+                (F_DUPFD, arg) if arg >= 0 && arg < self.filedescriptortable.limit() => {
+                    self._dup2_helper(&filedesc_enum, arg, false, false)
+                }
+                (F_DUPFD_CLOEXEC, arg) if arg >= 0 && arg < self.filedescriptortable.limit() => {
+                    self._dup2_helper(&filedesc_enum, arg, false, true)
                 }
-                (F_SETOWN, arg) if arg >= 0 => {
-                    0 //this would return the PID if positive and the process group if negative,
-                      //either way do nothing and return success
+                //who O_ASYNC signals go to: a cage if positive, a process group if negative, or
+                //no one if 0
+                (F_GETOWN, ..) => statusflags.owner(),
+                (F_SETOWN, arg) if arg > -interface::MAXCAGEID && arg < interface::MAXCAGEID => {
+                    statusflags.set_owner(arg);
+                    0
+                }
+                //and the signal they are sent, where 0 is SIGIO
+                (F_GETSIG, ..) => statusflags.sig(),
+                (F_SETSIG, arg) if arg >= 0 && arg <= SIGNAL_MAX => {
+                    statusflags.set_sig(arg);
+                    0
                 }
                 _ => syscall_error(
                     Errno::EINVAL,
@@ -1913,49 +2791,304 @@ impl Cage {
         }
     }
 
-    //------------------------------------IOCTL SYSCALL------------------------------------
+    //like Linux, the capacity is rounded up to a power of two number of pages
+    fn _set_pipe_size(pipe: &interface::EmulatedPipe, arg: i32) -> i32 {
+        if arg < 0 {
+            return syscall_error(Errno::EINVAL, "fcntl", "the pipe size is too large");
+        }
+        let capacity = interface::rust_max(arg as usize, PIPE_MIN_CAPACITY).next_power_of_two();
+        if capacity > PIPE_MAX_CAPACITY {
+            return syscall_error(
+                Errno::EPERM,
+                "fcntl",
+                "the pipe size exceeds the maximum pipe capacity",
+            );
+        }
+        pipe.set_capacity(capacity)
+    }
 
-    pub fn ioctl_syscall(&self, fd: i32, request: u32, ptrunion: IoctlPtrUnion) -> i32 {
-        let checkedfd = self.get_filedescriptor(fd).unwrap();
-        let mut unlocked_fd = checkedfd.write();
-        if let Some(filedesc_enum) = &mut *unlocked_fd {
-            match request {
-                FIONBIO => {
-                    let arg_result = interface::get_ioctl_int(ptrunion);
-                    //matching the tuple and passing in filedesc_enum
-                    match (arg_result, filedesc_enum) {
-                        (Err(arg_result), ..)=> {
-                            return arg_result; //syscall_error
-                        }
-                        (Ok(arg_result), Socket(ref mut sockfdobj)) => {
-                            let sock_tmp = sockfdobj.handle.clone();
-                            let mut sockhandle = sock_tmp.write();
+    fn _add_seals(filedesc: &FileDesc, seals: i32) -> i32 {
+        let validseals =
+            F_SEAL_SEAL | F_SEAL_SHRINK | F_SEAL_GROW | F_SEAL_WRITE | F_SEAL_FUTURE_WRITE;
+        if seals & !validseals != 0 {
+            return syscall_error(Errno::EINVAL, "fcntl", "unknown seals were specified");
+        }
+        if is_rdonly(filedesc.flags) {
+            return syscall_error(Errno::EPERM, "fcntl", "fd is not open for writing");
+        }
 
-                            let flags = &mut sockfdobj.flags;
-                            let arg: i32 = arg_result;
-                            let mut ioctlret = 0;
+        let mut currentseals = match FILE_SEALS.get_mut(&filedesc.inode) {
+            Some(currentseals) => currentseals,
+            None => return syscall_error(Errno::EINVAL, "fcntl", "fd is not a memfd"),
+        };
+        if *currentseals & F_SEAL_SEAL != 0 {
+            return syscall_error(Errno::EPERM, "fcntl", "the file's seals have been sealed");
+        }
+        //stores through a writable shared mapping would get around F_SEAL_WRITE
+        if seals & F_SEAL_WRITE != 0 && Self::_inode_writably_mapped(filedesc.inode) {
+            return syscall_error(
+                Errno::EBUSY,
+                "fcntl",
+                "the file has a writable shared mapping",
+            );
+        }
+        *currentseals |= seals;
+        0
+    }
 
-                            if arg == 0 { //clear non-blocking I/O
-                                *flags &= !O_NONBLOCK;
-                                if let Some(ins) = &mut sockhandle.innersocket {
-                                    ioctlret = ins.set_blocking();
-                                }
-                            } else { //set for non-blocking I/O
-                                *flags |= O_NONBLOCK;
-                                if let Some(ins) = &mut sockhandle.innersocket {
-                                    ioctlret = ins.set_nonblocking();
-                                }
-                            }
-                            if ioctlret < 0 {
-                                match Errno::from_discriminant(interface::get_errno()) {
-                                    Ok(i) => {return syscall_error(i, "ioctl", "The libc call to ioctl failed!");},
-                                    Err(()) => panic!("Unknown errno value from ioctl returned!"),
-                                };
-                            }
+    //the lease the open file description holds, or what it is being broken down to if an open
+    //is waiting on it
+    fn _get_lease(filedesc: &FileDesc) -> i32 {
+        let holder = interface::RustRfc::as_ptr(&filedesc.statusflags) as usize;
+        FILE_LEASES
+            .get(&filedesc.inode)
+            .and_then(|leases| {
+                leases
+                    .iter()
+                    .find(|lease| lease.holder == holder)
+                    .map(|lease| match lease.breaking {
+                        Some((target, _)) => target,
+                        None => lease.leasetype,
+                    })
+            })
+            .unwrap_or(F_UNLCK)
+    }
 
-                            0
-                        }
-                        _ => {syscall_error(Errno::ENOTTY, "ioctl", "The specified request does not apply to the kind of object that the file descriptor fd references.")}
+    fn _set_lease(&self, filedesc: &FileDesc, leasetype: i32) -> i32 {
+        if leasetype != F_RDLCK && leasetype != F_WRLCK && leasetype != F_UNLCK {
+            return syscall_error(Errno::EINVAL, "fcntl", "invalid lease type");
+        }
+        let inodenum = filedesc.inode;
+        let holder = interface::RustRfc::as_ptr(&filedesc.statusflags) as usize;
+        let owner = match FS_METADATA.inodetable.get(&inodenum).as_deref() {
+            Some(Inode::File(f)) => f.uid,
+            Some(_) => {
+                return syscall_error(Errno::EINVAL, "fcntl", "fd is not a regular file");
+            }
+            None => return corruption_error("fcntl", "open file has no inode"),
+        };
+        if leasetype == F_UNLCK {
+            release_lease(inodenum, holder);
+            return 0;
+        }
+        let cred = self.cred.read();
+        if cred.euid != owner && !cred.capable(CAP_LEASE) {
+            return syscall_error(
+                Errno::EACCES,
+                "fcntl",
+                "only the owner of the file can lease it",
+            );
+        }
+        drop(cred);
+
+        let mut leases = FILE_LEASES.entry(inodenum).or_insert_with(Vec::new);
+        if let Some(lease) = leases.iter_mut().find(|lease| lease.holder == holder) {
+            //nothing else can have opened the file under a write lease, so it can always be
+            //downgraded, which is also how the holder answers a break down to a read lease
+            let downgrade = leasetype == F_RDLCK
+                && match lease.breaking {
+                    Some((target, _)) => target == F_RDLCK,
+                    None => true,
+                };
+            if downgrade || (leasetype == lease.leasetype && lease.breaking.is_none()) {
+                lease.leasetype = leasetype;
+                lease.breaking = None;
+                return 0;
+            }
+            if lease.breaking.is_some() {
+                return syscall_error(
+                    Errno::EAGAIN,
+                    "fcntl",
+                    "the lease is being broken, so it can only be given up",
+                );
+            }
+        }
+        //a read lease keeps others from writing the file, and a write lease from opening it at all
+        let writersonly = leasetype == F_RDLCK;
+        if leases
+            .iter()
+            .any(|lease| lease.holder != holder && (!writersonly || lease.leasetype == F_WRLCK))
+            || Self::_inode_opened_elsewhere(inodenum, holder, writersonly)
+        {
+            return syscall_error(
+                Errno::EAGAIN,
+                "fcntl",
+                "the file is open in a way that conflicts with the lease",
+            );
+        }
+        match leases.iter_mut().find(|lease| lease.holder == holder) {
+            Some(lease) => lease.leasetype = leasetype,
+            None => leases.push(FileLease {
+                holder: holder,
+                cageid: self.cageid,
+                leasetype: leasetype,
+                breaking: None,
+            }),
+        }
+        0
+    }
+
+    //whether some other open file description has the file open, for writing if only writers
+    //count. Descriptors that a syscall has locked, the caller's own among them, are passed over
+    fn _inode_opened_elsewhere(inodenum: usize, holder: usize, writersonly: bool) -> bool {
+        (0..interface::MAXCAGEID as u64).any(|cageid| {
+            match interface::cagetable_getref_opt(cageid) {
+                Some(cage) => {
+                    cage.filedescriptortable
+                        .iter()
+                        .any(|entry| match entry.try_read().as_deref() {
+                            Some(Some(File(f))) => {
+                                f.inode == inodenum
+                                    && interface::RustRfc::as_ptr(&f.statusflags) as usize != holder
+                                    && (!writersonly || !is_rdonly(f.flags))
+                            }
+                            _ => false,
+                        })
+                }
+                None => false,
+            }
+        })
+    }
+
+    //An open by another cage that conflicts with a lease on the file, by writing it or by opening
+    //it at all under a write lease, breaks the lease. The holder is sent SIGIO and the open waits
+    //for it to give the lease up or downgrade it, until LEASE_BREAK_TIME passes and the lease is
+    //broken for it. A nonblocking open doesn't wait, but fails with EAGAIN.
+    fn _break_leases(&self, inodenum: usize, flags: i32) -> i32 {
+        let mut cancel = self.cancel_registry.point();
+        let writing = !is_rdonly(flags) || flags & O_TRUNC != 0;
+        let target = if writing { F_UNLCK } else { F_RDLCK };
+        loop {
+            let mut conflicting = false;
+            if let Some(mut leases) = FILE_LEASES.get_mut(&inodenum) {
+                let now = interface::RustInstant::now();
+                for lease in leases.iter_mut().filter(|lease| {
+                    lease.cageid != self.cageid && (writing || lease.leasetype == F_WRLCK)
+                }) {
+                    let (newtarget, deadline) = match lease.breaking {
+                        //F_UNLCK is above F_RDLCK, so a break only ever goes further
+                        Some((oldtarget, deadline)) => {
+                            (interface::rust_max(oldtarget, target), deadline)
+                        }
+                        None => {
+                            interface::lind_kill_from_id(lease.cageid, SIGIO);
+                            (
+                                target,
+                                now + interface::RustDuration::from_secs(LEASE_BREAK_TIME),
+                            )
+                        }
+                    };
+                    if now >= deadline {
+                        lease.leasetype = newtarget;
+                        lease.breaking = None;
+                    } else {
+                        lease.breaking = Some((newtarget, deadline));
+                        conflicting = true;
+                    }
+                }
+                leases.retain(|lease| lease.leasetype != F_UNLCK);
+            }
+            FILE_LEASES.remove_if(&inodenum, |_, leases| leases.is_empty());
+            if !conflicting {
+                return 0;
+            }
+
+            if flags & O_NONBLOCK != 0 {
+                return syscall_error(
+                    Errno::EAGAIN,
+                    "open",
+                    "the file is leased, and the lease is being broken",
+                );
+            }
+            if let Some(cancelled) = cancel.check("open") {
+                return cancelled;
+            }
+            if let Some(interrupted) = self.signal_interrupt("open", true) {
+                return interrupted;
+            }
+            cancel.sleep(interface::SIGNAL_CHECK_INTERVAL);
+        }
+    }
+
+    fn _inode_writably_mapped(inodenum: usize) -> bool {
+        (0..interface::MAXCAGEID as u64).any(|cageid| {
+            match interface::cagetable_getref_opt(cageid) {
+                Some(cage) => cage
+                    .file_mappings
+                    .lock()
+                    .iter()
+                    .any(|mapping| mapping.inode == inodenum && mapping.prot & PROT_WRITE != 0),
+                None => false,
+            }
+        })
+    }
+
+    //------------------------------------IOCTL SYSCALL------------------------------------
+
+    pub fn ioctl_syscall(&self, fd: i32, request: u32, ptrunion: IoctlPtrUnion) -> i32 {
+        let checkedfd = self.get_filedescriptor(fd).unwrap();
+        let mut unlocked_fd = checkedfd.write();
+        if let Some(filedesc_enum) = &mut *unlocked_fd {
+            match request {
+                FIONBIO => {
+                    let arg_result = interface::get_ioctl_int(ptrunion);
+                    //matching the tuple and passing in filedesc_enum
+                    match (arg_result, filedesc_enum) {
+                        (Err(arg_result), ..)=> {
+                            return arg_result; //syscall_error
+                        }
+                        (Ok(arg_result), Socket(ref sockfdobj)) => {
+                            //like fcntl F_SETFL, this only changes the status flags every socket call consults
+                            let statusflags = &sockfdobj.statusflags;
+                            if arg_result == 0 { //clear non-blocking I/O
+                                statusflags.set(statusflags.get() & !O_NONBLOCK);
+                            } else { //set for non-blocking I/O
+                                statusflags.set(statusflags.get() | O_NONBLOCK);
+                            }
+
+                            0
+                        }
+                        _ => {syscall_error(Errno::ENOTTY, "ioctl", "The specified request does not apply to the kind of object that the file descriptor fd references.")}
+                    }
+                }
+                FIONREAD if matches!(filedesc_enum, Socket(_) | Pipe(_)) => {
+                    let readable = match filedesc_enum {
+                        Socket(ref sockfdobj) => {
+                            let sockhandle = sockfdobj.handle.read();
+                            //bytes an earlier MSG_PEEK pulled out are read before anything else
+                            let mut readable = sockhandle.last_peek.lock().len();
+                            if let Some(ins) = &sockhandle.innersocket {
+                                match ins.readable_bytes() {
+                                    Ok(ret) => readable += ret as usize,
+                                    Err(errno) => {
+                                        return interface::host_syscall_error(
+                                            errno,
+                                            "ioctl",
+                                            "The libc call to ioctl failed!",
+                                        );
+                                    }
+                                }
+                            } else if let Some(receivepipe) = sockhandle
+                                .unix_info
+                                .as_ref()
+                                .and_then(|unixinfo| unixinfo.receivepipe.as_ref())
+                            {
+                                readable += receivepipe.readable_bytes();
+                            }
+                            readable
+                        }
+                        Pipe(ref pipe_filedesc_obj) => pipe_filedesc_obj.pipe.readable_bytes(),
+                        _ => unreachable!(),
+                    };
+                    match interface::set_ioctl_int(ptrunion, readable as i32) {
+                        Ok(()) => 0,
+                        Err(e) => e,
+                    }
+                }
+                request if is_ifreq_request(request) && matches!(filedesc_enum, Socket(_)) => {
+                    match self._ioctl_ifreq(request, ptrunion) {
+                        Ok(()) => 0,
+                        Err(e) => e,
                     }
                 }
                 FIOASYNC => {
@@ -1965,11 +3098,35 @@ impl Cage {
                     );
                     0
                 }
-                _ => syscall_error(
-                    Errno::EINVAL,
-                    "ioctl",
-                    "Arguments provided do not match implemented parameters",
-                ),
+                _ => {
+                    //any other request is up to the terminal or the driver of a device file
+                    if let Stream(_) = filedesc_enum {
+                        return CONSOLE.ioctl(TtyEnd::Console, request, ptrunion);
+                    }
+                    if let File(ref normalfile_filedesc_obj) = filedesc_enum {
+                        if is_pathonly(normalfile_filedesc_obj.flags) {
+                            return syscall_error(
+                                Errno::EBADF,
+                                "ioctl",
+                                "file descriptor was opened with O_PATH",
+                            );
+                        }
+                        if let Some((tty, end)) = tty_of_inode(normalfile_filedesc_obj.inode) {
+                            return tty.ioctl(end, request, ptrunion);
+                        }
+                        if let Some(device) = char_device_of_inode(normalfile_filedesc_obj.inode) {
+                            return device.ioctl(request, ptrunion);
+                        }
+                    }
+                    if is_tty_request(request) {
+                        return syscall_error(Errno::ENOTTY, "ioctl", "fd is not a terminal");
+                    }
+                    syscall_error(
+                        Errno::EINVAL,
+                        "ioctl",
+                        "Arguments provided do not match implemented parameters",
+                    )
+                }
             }
         } else {
             syscall_error(Errno::EBADF, "ioctl", "Invalid file descriptor")
@@ -1978,29 +3135,39 @@ impl Cage {
 
     //------------------------------------CHMOD HELPER FUNCTION------------------------------------
 
+    //only the permission bits change, and the change time along with them
     pub fn _chmod_helper(inodenum: usize, mode: u32) {
         let mut thisinode = FS_METADATA.inodetable.get_mut(&inodenum).unwrap();
         let mut log = true;
         if mode & (S_IRWXA | (S_FILETYPEFLAGS as u32)) == mode {
+            let time = interface::timestamp();
             match *thisinode {
                 Inode::File(ref mut general_inode) => {
-                    general_inode.mode = (general_inode.mode & !S_IRWXA) | mode
+                    general_inode.mode = (general_inode.mode & !S_IRWXA) | mode;
+                    general_inode.ctime = time;
                 }
                 Inode::CharDev(ref mut dev_inode) => {
                     dev_inode.mode = (dev_inode.mode & !S_IRWXA) | mode;
+                    dev_inode.ctime = time;
                 }
                 Inode::Socket(ref mut sock_inode) => {
                     sock_inode.mode = (sock_inode.mode & !S_IRWXA) | mode;
+                    sock_inode.ctime = time;
                     log = false;
                 }
                 Inode::Dir(ref mut dir_inode) => {
                     dir_inode.mode = (dir_inode.mode & !S_IRWXA) | mode;
+                    dir_inode.ctime = time;
                 }
             }
-            drop(thisinode);
             if log {
-                log_metadata(&FS_METADATA, inodenum)
-            };
+                let entry = LogEntry::inode(inodenum, &thisinode);
+                drop(thisinode);
+                log_entries([entry]);
+            } else {
+                drop(thisinode);
+            }
+            inotify_publish(inodenum, IN_ATTRIB, 0, None);
         }
     }
 
@@ -2008,9 +3175,15 @@ impl Cage {
 
     pub fn chmod_syscall(&self, path: &str, mode: u32) -> i32 {
         let truepath = normpath(convpath(path), self);
+        if path_is_readonly(&truepath) {
+            return syscall_error(Errno::EROFS, "chmod", "path is on a read-only filesystem");
+        }
 
         //check if there is a valid path or not there to an inode
         if let Some(inodenum) = metawalk(truepath.as_path()) {
+            if !self._inode_owned(inodenum) {
+                return syscall_error(Errno::EPERM, "chmod", "the caller does not own the file");
+            }
             if mode & (S_IRWXA | (S_FILETYPEFLAGS as u32)) == mode {
                 Self::_chmod_helper(inodenum, mode);
             } else {
@@ -2026,12 +3199,38 @@ impl Cage {
     //------------------------------------FCHMOD SYSCALL------------------------------------
 
     pub fn fchmod_syscall(&self, fd: i32, mode: u32) -> i32 {
-        let checkedfd = self.get_filedescriptor(fd).unwrap();
+        let checkedfd = match self.get_filedescriptor(fd) {
+            Ok(checkedfd) => checkedfd,
+            Err(()) => {
+                return syscall_error(Errno::EBADF, "fchmod", "invalid file descriptor");
+            }
+        };
         let unlocked_fd = checkedfd.read();
         if let Some(filedesc_enum) = &*unlocked_fd {
             match filedesc_enum {
                 File(normalfile_filedesc_obj) => {
+                    if is_pathonly(normalfile_filedesc_obj.flags) {
+                        return syscall_error(
+                            Errno::EBADF,
+                            "fchmod",
+                            "file descriptor was opened with O_PATH",
+                        );
+                    }
                     let inodenum = normalfile_filedesc_obj.inode;
+                    if inode_is_readonly(inodenum) {
+                        return syscall_error(
+                            Errno::EROFS,
+                            "fchmod",
+                            "file is on a read-only filesystem",
+                        );
+                    }
+                    if !self._inode_owned(inodenum) {
+                        return syscall_error(
+                            Errno::EPERM,
+                            "fchmod",
+                            "the caller does not own the file",
+                        );
+                    }
                     if mode & (S_IRWXA | (S_FILETYPEFLAGS as u32)) == mode {
                         Self::_chmod_helper(inodenum, mode);
                     } else {
@@ -2063,7 +3262,7 @@ impl Cage {
                         "cannot change mode on this file descriptor",
                     );
                 }
-                Epoll(_) => {
+                Epoll(_) | Inotify(_) | Mqueue(_) => {
                     return syscall_error(
                         Errno::EACCES,
                         "fchmod",
@@ -2073,14 +3272,131 @@ impl Cage {
             }
         } else {
             return syscall_error(
-                Errno::ENOENT,
+                Errno::EBADF,
                 "fchmod",
-                "the provided file descriptor  does not exist",
+                "the provided file descriptor does not exist",
             );
         }
         0 //success!
     }
 
+    //------------------------------------CHOWN SYSCALL------------------------------------
+
+    //Giving a file away takes CAP_CHOWN. Without it the owner may still hand the file to one of
+    //its own groups. An id of -1 leaves that id as it is.
+    fn _chown_helper(&self, inodenum: usize, uid: u32, gid: u32, callname: &str) -> i32 {
+        let (_, olduid, oldgid) =
+            Self::_inode_perms(&FS_METADATA.inodetable.get(&inodenum).unwrap());
+        let newuid = if uid == u32::MAX { olduid } else { uid };
+        let newgid = if gid == u32::MAX { oldgid } else { gid };
+
+        let cred = self.cred.read().clone();
+        if !cred.capable(CAP_CHOWN) {
+            let owngroup = newgid == cred.egid || cred.groups.contains(&newgid);
+            if newuid != olduid || (newgid != oldgid && (cred.euid != olduid || !owngroup)) {
+                return syscall_error(
+                    Errno::EPERM,
+                    callname,
+                    "changing the owner or to a group the caller is not in takes CAP_CHOWN",
+                );
+            }
+        }
+
+        let mut thisinode = match FS_METADATA.inodetable.get_mut(&inodenum) {
+            Some(inodeobj) => inodeobj,
+            None => return corruption_error(callname, "inode does not exist"),
+        };
+        let mut log = true;
+        let time = interface::timestamp();
+        match *thisinode {
+            Inode::File(ref mut general_inode) => {
+                general_inode.uid = newuid;
+                general_inode.gid = newgid;
+                general_inode.ctime = time;
+            }
+            Inode::CharDev(ref mut dev_inode) => {
+                dev_inode.uid = newuid;
+                dev_inode.gid = newgid;
+                dev_inode.ctime = time;
+            }
+            Inode::Socket(ref mut sock_inode) => {
+                sock_inode.uid = newuid;
+                sock_inode.gid = newgid;
+                sock_inode.ctime = time;
+                log = false;
+            }
+            Inode::Dir(ref mut dir_inode) => {
+                dir_inode.uid = newuid;
+                dir_inode.gid = newgid;
+                dir_inode.ctime = time;
+            }
+        }
+        if log {
+            let entry = LogEntry::inode(inodenum, &thisinode);
+            drop(thisinode);
+            log_entries([entry]);
+        } else {
+            drop(thisinode);
+        }
+        inotify_publish(inodenum, IN_ATTRIB, 0, None);
+        0
+    }
+
+    pub fn chown_syscall(&self, path: &str, uid: u32, gid: u32) -> i32 {
+        let truepath = normpath(convpath(path), self);
+        if path_is_readonly(&truepath) {
+            return syscall_error(Errno::EROFS, "chown", "path is on a read-only filesystem");
+        }
+
+        if let Some(inodenum) = metawalk(truepath.as_path()) {
+            self._chown_helper(inodenum, uid, gid, "chown")
+        } else {
+            syscall_error(Errno::ENOENT, "chown", "the provided path does not exist")
+        }
+    }
+
+    //------------------------------------FCHOWN SYSCALL------------------------------------
+
+    pub fn fchown_syscall(&self, fd: i32, uid: u32, gid: u32) -> i32 {
+        let checkedfd = match self.get_filedescriptor(fd) {
+            Ok(checkedfd) => checkedfd,
+            Err(()) => {
+                return syscall_error(Errno::EBADF, "fchown", "invalid file descriptor");
+            }
+        };
+        let unlocked_fd = checkedfd.read();
+        match &*unlocked_fd {
+            Some(File(normalfile_filedesc_obj)) => {
+                if is_pathonly(normalfile_filedesc_obj.flags) {
+                    return syscall_error(
+                        Errno::EBADF,
+                        "fchown",
+                        "file descriptor was opened with O_PATH",
+                    );
+                }
+                let inodenum = normalfile_filedesc_obj.inode;
+                if inode_is_readonly(inodenum) {
+                    return syscall_error(
+                        Errno::EROFS,
+                        "fchown",
+                        "file is on a read-only filesystem",
+                    );
+                }
+                self._chown_helper(inodenum, uid, gid, "fchown")
+            }
+            Some(_) => syscall_error(
+                Errno::EACCES,
+                "fchown",
+                "cannot change ownership on this file descriptor",
+            ),
+            None => syscall_error(
+                Errno::EBADF,
+                "fchown",
+                "the provided file descriptor does not exist",
+            ),
+        }
+    }
+
     //------------------------------------MMAP SYSCALL------------------------------------
 
     pub fn mmap_syscall(
@@ -2093,11 +3409,11 @@ impl Cage {
         off: i64,
     ) -> i32 {
         if len == 0 {
-            syscall_error(Errno::EINVAL, "mmap", "the value of len is 0");
+            return syscall_error(Errno::EINVAL, "mmap", "the value of len is 0");
         }
 
         if 0 == flags & (MAP_PRIVATE | MAP_SHARED) {
-            syscall_error(
+            return syscall_error(
                 Errno::EINVAL,
                 "mmap",
                 "The value of flags is invalid (neither MAP_PRIVATE nor MAP_SHARED is set)",
@@ -2105,7 +3421,12 @@ impl Cage {
         }
 
         if 0 != flags & MAP_ANONYMOUS {
-            return interface::libc_mmap(addr, len, prot, flags, -1, 0);
+            return match interface::libc_mmap(addr, len, prot, flags, -1, 0) {
+                Ok(mapret) => mapret as i32,
+                Err(errno) => {
+                    interface::host_syscall_error(errno, "mmap", "The libc call to mmap failed!")
+                }
+            };
         }
 
         let checkedfd = self.get_filedescriptor(fildes).unwrap();
@@ -2114,18 +3435,33 @@ impl Cage {
             //confirm fd type is mappable
             match filedesc_enum {
                 File(ref mut normalfile_filedesc_obj) => {
-                    let inodeobj = FS_METADATA
-                        .inodetable
-                        .get(&normalfile_filedesc_obj.inode)
-                        .unwrap();
+                    if is_pathonly(normalfile_filedesc_obj.flags) {
+                        return syscall_error(
+                            Errno::EBADF,
+                            "mmap",
+                            "file descriptor was opened with O_PATH",
+                        );
+                    }
+                    let inodeobj = match FS_METADATA.inodetable.get(&normalfile_filedesc_obj.inode)
+                    {
+                        Some(inodeobj) => inodeobj,
+                        None => return corruption_error("mmap", "open file has no inode"),
+                    };
 
                     //confirm inode type is mappable
                     match &*inodeobj {
                         Inode::File(normalfile_inode_obj) => {
+                            //every mapping reads the file, so it must be open for reading
+                            if is_wronly(normalfile_filedesc_obj.flags) {
+                                return syscall_error(Errno::EACCES, "mmap", "file descriptor is not open for reading");
+                            }
                             //if we want to write our changes back to the file the file needs to be open for reading and writing
-                            if (flags & MAP_SHARED != 0) && (flags & PROT_WRITE != 0) && (normalfile_filedesc_obj.flags & O_RDWR != 0) {
+                            if (flags & MAP_SHARED != 0) && (prot & PROT_WRITE != 0) && (normalfile_filedesc_obj.flags & O_RDWRFLAGS != O_RDWR) {
                                 return syscall_error(Errno::EACCES, "mmap", "file descriptor is not open RDWR, but MAP_SHARED and PROT_WRITE are set");
                             }
+                            if (flags & MAP_SHARED != 0) && (prot & PROT_WRITE != 0) && (seals_of_inode(normalfile_filedesc_obj.inode) & (F_SEAL_WRITE | F_SEAL_FUTURE_WRITE) != 0) {
+                                return syscall_error(Errno::EPERM, "mmap", "the file is sealed against writes, but MAP_SHARED and PROT_WRITE are set");
+                            }
                             let filesize = normalfile_inode_obj.size;
                             if off < 0 || off > filesize as i64 {
                                 return syscall_error(Errno::ENXIO, "mmap", "Addresses in the range [off,off+len) are invalid for the object specified by fildes.");
@@ -2134,10 +3470,24 @@ impl Cage {
                             let fobj = FILEOBJECTTABLE.get(&normalfile_filedesc_obj.inode).unwrap();
                             //we cannot mmap a rust file in quite the right way so we retrieve the fd number from it
                             //this is the system fd number--the number of the lind.<inodenum> file in our host system
+                            //every cage maps the same host file, so MAP_SHARED stores are seen by read() and by other cages
                             let fobjfdno = fobj.as_fd_handle_raw_int();
 
-
-                            interface::libc_mmap(addr, len, prot, flags, fobjfdno, off)
+                            let mapret = match interface::libc_mmap(addr, len, prot, flags, fobjfdno, off) {
+                                Ok(mapret) => mapret,
+                                Err(errno) => return interface::host_syscall_error(errno, "mmap", "The libc call to mmap failed!"),
+                            };
+                            if flags & MAP_SHARED != 0 {
+                                let mut mappings = self.file_mappings.lock();
+                                Self::_remove_file_mappings(&mut mappings, mapret, len);
+                                mappings.push(FileMapping {
+                                    addr: mapret,
+                                    len: len,
+                                    inode: normalfile_filedesc_obj.inode,
+                                    prot: prot,
+                                });
+                            }
+                            mapret as i32
                         }
 
                         Inode::CharDev(_chardev_inode_obj) => {
@@ -2158,22 +3508,87 @@ impl Cage {
         }
     }
 
+    //drop our record of any shared file mapping that overlaps [addr, addr+len)
+    fn _remove_file_mappings(mappings: &mut Vec<FileMapping>, start: usize, len: usize) {
+        let end = start + len;
+        mappings.retain(|mapping| mapping.addr + mapping.len <= start || mapping.addr >= end);
+    }
+
     //------------------------------------MUNMAP SYSCALL------------------------------------
 
     pub fn munmap_syscall(&self, addr: *mut u8, len: usize) -> i32 {
         if len == 0 {
-            syscall_error(Errno::EINVAL, "mmap", "the value of len is 0");
+            return syscall_error(Errno::EINVAL, "munmap", "the value of len is 0");
         }
+        //any shared mapping in the range has to be flushed before its pages are replaced
+        let mut mappings = self.file_mappings.lock();
+        let start = addr as usize;
+        if mappings
+            .iter()
+            .any(|m| m.addr < start + len && start < m.addr + m.len)
+        {
+            let _ = interface::libc_msync(addr, len, MS_SYNC);
+        }
+        Self::_remove_file_mappings(&mut mappings, start, len);
+        drop(mappings);
+
         //NaCl's munmap implementation actually just writes over the previously mapped data with PROT_NONE
         //This frees all of the resources except page table space, and is put inside safeposix for consistency
-        interface::libc_mmap(
+        match interface::libc_mmap(
             addr,
             len,
             PROT_NONE,
             MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED,
             -1,
             0,
-        )
+        ) {
+            Ok(mapret) => mapret as i32,
+            Err(errno) => {
+                interface::host_syscall_error(errno, "munmap", "The libc call to mmap failed!")
+            }
+        }
+    }
+
+    //------------------------------------MSYNC SYSCALL------------------------------------
+
+    pub fn msync_syscall(&self, addr: *mut u8, len: usize, flags: i32) -> i32 {
+        if flags & !(MS_ASYNC | MS_INVALIDATE | MS_SYNC) != 0 {
+            return syscall_error(Errno::EINVAL, "msync", "flags specifies an invalid bit");
+        }
+        if (flags & MS_ASYNC != 0) && (flags & MS_SYNC != 0) {
+            return syscall_error(Errno::EINVAL, "msync", "both MS_SYNC and MS_ASYNC are set");
+        }
+
+        if let Err(errno) = interface::libc_msync(addr, len, flags) {
+            return interface::host_syscall_error(errno, "msync", "The libc call to msync failed!");
+        }
+
+        //stores through a shared mapping modify the file, so we record that the same way write does
+        let start = addr as usize;
+        let mut touched = vec![];
+        for mapping in self.file_mappings.lock().iter() {
+            if mapping.addr < start + len && start < mapping.addr + mapping.len {
+                if !touched.contains(&mapping.inode) {
+                    touched.push(mapping.inode);
+                }
+            }
+        }
+        for inodenum in touched {
+            if let Some(mut inodeobj) = FS_METADATA.inodetable.get_mut(&inodenum) {
+                if let Inode::File(ref mut f) = *inodeobj {
+                    let time = interface::timestamp();
+                    f.mtime = time;
+                    f.ctime = time;
+                } else {
+                    continue;
+                }
+                let entry = LogEntry::inode(inodenum, &inodeobj);
+                drop(inodeobj);
+                log_entries([entry]);
+                inotify_publish(inodenum, IN_MODIFY, 0, None);
+            }
+        }
+        0
     }
 
     //------------------------------------FLOCK SYSCALL------------------------------------
@@ -2183,11 +3598,20 @@ impl Cage {
         let unlocked_fd = checkedfd.read();
         if let Some(filedesc_enum) = &*unlocked_fd {
             let lock = match filedesc_enum {
+                File(normalfile_filedesc_obj) if is_pathonly(normalfile_filedesc_obj.flags) => {
+                    return syscall_error(
+                        Errno::EBADF,
+                        "flock",
+                        "file descriptor was opened with O_PATH",
+                    );
+                }
                 File(normalfile_filedesc_obj) => &normalfile_filedesc_obj.advlock,
                 Socket(socket_filedesc_obj) => &socket_filedesc_obj.advlock,
                 Stream(stream_filedesc_obj) => &stream_filedesc_obj.advlock,
                 Pipe(pipe_filedesc_obj) => &pipe_filedesc_obj.advlock,
                 Epoll(epoll_filedesc_obj) => &epoll_filedesc_obj.advlock,
+                Inotify(inotify_filedesc_obj) => &inotify_filedesc_obj.advlock,
+                Mqueue(mqueue_filedesc_obj) => &mqueue_filedesc_obj.advlock,
             };
             match operation & (LOCK_SH | LOCK_EX | LOCK_UN) {
                 LOCK_SH => {
@@ -2238,9 +3662,11 @@ impl Cage {
         parent_inodenum: usize,
         truepath: &interface::RustPathBuf,
     ) -> i32 {
-        if let Inode::Dir(ref mut parent_dir) =
-            *(FS_METADATA.inodetable.get_mut(&parent_inodenum).unwrap())
-        {
+        let mut parentinodeobj = match FS_METADATA.inodetable.get_mut(&parent_inodenum) {
+            Some(inodeobj) => inodeobj,
+            None => return corruption_error("rmdir", "parent directory does not exist"),
+        };
+        if let Inode::Dir(ref mut parent_dir) = *parentinodeobj {
             // check if parent dir has write permission
             if parent_dir.mode as u32 & (S_IWOTH | S_IWGRP | S_IWUSR) == 0 {
                 return syscall_error(
@@ -2251,14 +3677,19 @@ impl Cage {
             }
 
             // remove entry of corresponding filename from filename-inode dict
-            parent_dir
+            if parent_dir
                 .filename_to_inode_dict
                 .remove(&truepath.file_name().unwrap().to_str().unwrap().to_string())
-                .unwrap();
+                .is_none()
+            {
+                return corruption_error("rmdir", "directory is missing from its parent");
+            }
             parent_dir.linkcount -= 1; // decrement linkcount of parent dir
         } else {
-            panic!("Non directory file was parent!");
+            return corruption_error("rmdir", "parent is not a directory");
         }
+        drop(parentinodeobj);
+        dentry_invalidate(truepath);
         0
     }
 
@@ -2269,6 +3700,9 @@ impl Cage {
             return syscall_error(Errno::ENOENT, "rmdir", "Given path is null");
         }
         let truepath = normpath(convpath(path), self);
+        if path_is_readonly(&truepath) {
+            return syscall_error(Errno::EROFS, "rmdir", "path is on a read-only filesystem");
+        }
 
         // try to get inodenum of input path and its parent
         match metawalkandparent(truepath.as_path()) {
@@ -2277,8 +3711,19 @@ impl Cage {
                 // path exists but parent does not => path is root dir
                 syscall_error(Errno::EBUSY, "rmdir", "Cannot remove root directory")
             }
+            (Some(inodenum), Some(_)) if is_mount_root(inodenum) => {
+                syscall_error(Errno::EBUSY, "rmdir", "Path is a mount point")
+            }
             (Some(inodenum), Some(parent_inodenum)) => {
-                let mut inodeobj = FS_METADATA.inodetable.get_mut(&inodenum).unwrap();
+                let mut inodeobj = match FS_METADATA.inodetable.get_mut(&inodenum) {
+                    Some(inodeobj) => inodeobj,
+                    None => {
+                        return corruption_error(
+                            "rmdir",
+                            "path leads to an inode that does not exist",
+                        )
+                    }
+                };
 
                 match &mut *inodeobj {
                     // make sure inode matches a directory
@@ -2291,7 +3736,10 @@ impl Cage {
                             );
                         }
                         if !is_dir(dir_obj.mode) {
-                            panic!("This directory does not have its mode set to S_IFDIR");
+                            return corruption_error(
+                                "rmdir",
+                                "directory does not have its mode set to S_IFDIR",
+                            );
                         }
 
                         // check if dir has write permission
@@ -2315,13 +3763,23 @@ impl Cage {
                             return removal_result;
                         }
 
+                        //an empty directory holds nothing but its inode, so refund that right away
+                        quota_release_inode(inodenum, 0);
+
                         // remove entry of corresponding inodenum from inodetable
                         if remove_inode {
                             FS_METADATA.inodetable.remove(&inodenum).unwrap();
+                            mount_release_inode(inodenum, 0);
                         }
+                        inotify_publish(
+                            parent_inodenum,
+                            IN_DELETE | IN_ISDIR,
+                            0,
+                            truepath.file_name().unwrap().to_str(),
+                        );
+                        inotify_inode_removed(inodenum);
 
-                        log_metadata(&FS_METADATA, parent_inodenum);
-                        log_metadata(&FS_METADATA, inodenum);
+                        log_metadata_batch(&FS_METADATA, &[parent_inodenum, inodenum]);
                         0 // success
                     }
                     _ => syscall_error(Errno::ENOTDIR, "rmdir", "Path is not a directory"),
@@ -2342,6 +3800,9 @@ impl Cage {
 
         let true_oldpath = normpath(convpath(oldpath), self);
         let true_newpath = normpath(convpath(newpath), self);
+        if path_is_readonly(&true_oldpath) || path_is_readonly(&true_newpath) {
+            return syscall_error(Errno::EROFS, "rename", "path is on a read-only filesystem");
+        }
 
         // try to get inodenum of old path and its parent
         match metawalkandparent(true_oldpath.as_path()) {
@@ -2349,6 +3810,9 @@ impl Cage {
             (Some(_), None) => {
                 syscall_error(Errno::EBUSY, "rename", "Cannot rename root directory")
             }
+            (Some(inodenum), Some(_)) if is_mount_root(inodenum) => {
+                syscall_error(Errno::EBUSY, "rename", "Old path is a mount point")
+            }
             (Some(inodenum), Some(parent_inodenum)) => {
                 // make sure file is not moved to another dir
                 // get inodenum for parent of new path
@@ -2362,8 +3826,16 @@ impl Cage {
                     );
                 }
 
-                let pardir_inodeobj = FS_METADATA.inodetable.get_mut(&parent_inodenum).unwrap();
-                if let Inode::Dir(parent_dir) = &*pardir_inodeobj {
+                let mut pardir_inodeobj = match FS_METADATA.inodetable.get_mut(&parent_inodenum) {
+                    Some(inodeobj) => inodeobj,
+                    None => {
+                        return corruption_error(
+                            "rename",
+                            "path leads to an inode that does not exist",
+                        )
+                    }
+                };
+                if let Inode::Dir(parent_dir) = &mut *pardir_inodeobj {
                     // add pair of new path and its inodenum to filename-inode dict
                     parent_dir.filename_to_inode_dict.insert(
                         true_newpath
@@ -2384,11 +3856,31 @@ impl Cage {
                             .unwrap()
                             .to_string(),
                     );
+                    let entry = LogEntry::inode(parent_inodenum, &pardir_inodeobj);
                     drop(pardir_inodeobj);
-                    log_metadata(&FS_METADATA, parent_inodenum);
+                    log_entries([entry]);
+                    //everything beneath a renamed directory moves with it
+                    dentry_invalidate_all();
+
+                    let cookie = inotify_new_cookie();
+                    inotify_publish(
+                        parent_inodenum,
+                        IN_MOVED_FROM,
+                        cookie,
+                        true_oldpath.file_name().unwrap().to_str(),
+                    );
+                    inotify_publish(
+                        parent_inodenum,
+                        IN_MOVED_TO,
+                        cookie,
+                        true_newpath.file_name().unwrap().to_str(),
+                    );
+                    inotify_publish(inodenum, IN_MOVE_SELF, 0, None);
+                }
+                //only a renamed unix socket carries its domain socket entry along
+                if let Some((_, bindcred)) = NET_METADATA.domsock_paths.remove(&true_oldpath) {
+                    NET_METADATA.domsock_paths.insert(true_newpath, bindcred);
                 }
-                NET_METADATA.domsock_paths.insert(true_newpath);
-                NET_METADATA.domsock_paths.remove(&true_oldpath);
                 0 // success
             }
         }
@@ -2398,7 +3890,10 @@ impl Cage {
         if length < 0 {
             return syscall_error(Errno::EINVAL, "truncate", "length specified as less than 0");
         }
-        let mut inodeobj = FS_METADATA.inodetable.get_mut(&inodenum).unwrap();
+        let mut inodeobj = match FS_METADATA.inodetable.get_mut(&inodenum) {
+            Some(inodeobj) => inodeobj,
+            None => return corruption_error("truncate", "inode does not exist"),
+        };
 
         match *inodeobj {
             // only proceed when inode matches with a file
@@ -2406,56 +3901,94 @@ impl Cage {
                 let ulength = length as usize;
                 let filesize = normalfile_inode_obj.size as usize;
 
+                let sealcheck = Self::_check_seals(inodenum, filesize, ulength, false, "truncate");
+                if sealcheck < 0 {
+                    return sealcheck;
+                }
+                if !quota_resize_inode(inodenum, filesize, ulength) {
+                    return syscall_error(
+                        Errno::EDQUOT,
+                        "truncate",
+                        "the byte quota of the file's owner has been exhausted",
+                    );
+                }
+                if !mount_resize_inode(inodenum, filesize, ulength) {
+                    quota_resize_inode(inodenum, ulength, filesize);
+                    return syscall_error(
+                        Errno::ENOSPC,
+                        "truncate",
+                        "the mounted filesystem has no space left",
+                    );
+                }
+
                 // get file object table with write lock
                 let mut maybe_fileobject = FILEOBJECTTABLE.entry(inodenum);
                 let mut tempbind;
                 let close_on_exit;
 
                 //We check if the fileobject exists. If file_must_exist is true (i.e. we called the helper from
-                //ftruncate) then we know that an fd must exist and thus we panic if the fileobject does not
-                //exist. If file_must_exist is false (i.e. we called the helper from truncate), if the file does
-                //not exist,  we create a new fileobject to use which we remove once we are done with it
-                let fileobject = if let interface::RustHashEntry::Occupied(ref mut occ) =
-                    maybe_fileobject
-                {
-                    close_on_exit = false;
-                    occ.get_mut()
-                } else if file_must_exist {
-                    panic!("Somehow a normal file with an fd was truncated but there was no file object in rustposix?");
-                } else {
-                    let sysfilename = format!("{}{}", FILEDATAPREFIX, inodenum);
-                    tempbind = interface::openfile(sysfilename, filesize).unwrap(); // open file with size given from inode
-                    close_on_exit = true;
-                    &mut tempbind
-                };
-
-                // if length is greater than original filesize,
+                //ftruncate) then we know that an fd must exist and thus the state is corrupted if the fileobject
+                //does not exist. If file_must_exist is false (i.e. we called the helper from truncate), if the file
+                //does not exist,  we create a new fileobject to use which we remove once we are done with it
+                let fileobject =
+                    if let interface::RustHashEntry::Occupied(ref mut occ) = maybe_fileobject {
+                        close_on_exit = false;
+                        occ.get_mut()
+                    } else if file_must_exist {
+                        quota_resize_inode(inodenum, ulength, filesize);
+                        mount_resize_inode(inodenum, ulength, filesize);
+                        return corruption_error("ftruncate", "open file has no file object");
+                    } else {
+                        let sysfilename = format!("{}{}", FILEDATAPREFIX, inodenum);
+                        // open file with size given from inode
+                        tempbind = match interface::openfile(sysfilename, filesize) {
+                            Ok(fileobject) => fileobject,
+                            Err(_) => {
+                                quota_resize_inode(inodenum, ulength, filesize);
+                                mount_resize_inode(inodenum, ulength, filesize);
+                                return syscall_error(
+                                    Errno::EIO,
+                                    "truncate",
+                                    "the file's data could not be opened",
+                                );
+                            }
+                        };
+                        close_on_exit = true;
+                        &mut tempbind
+                    };
+
+                // if length is greater than original filesize,
                 // file is extented with null bytes
-                if filesize < ulength {
+                let resized = if filesize < ulength {
                     let blankbytecount = ulength - filesize;
-                    if let Ok(byteswritten) = fileobject.zerofill_at(filesize, blankbytecount) {
-                        if byteswritten != blankbytecount {
-                            panic!("zerofill_at() has failed");
-                        }
-                    } else {
-                        panic!("zerofill_at() has failed");
-                    }
+                    fileobject.zerofill_at(filesize, blankbytecount).ok() == Some(blankbytecount)
                 } else {
                     // if length is smaller than original filesize,
                     // extra data are cut off
-                    fileobject.shrink(ulength).unwrap();
-                }
+                    fileobject.shrink(ulength).is_ok()
+                };
 
                 if close_on_exit {
                     fileobject.close().unwrap();
                 }
+                if !resized {
+                    quota_resize_inode(inodenum, ulength, filesize);
+                    mount_resize_inode(inodenum, ulength, filesize);
+                    return syscall_error(
+                        Errno::EIO,
+                        "truncate",
+                        "resizing the file's data failed",
+                    );
+                }
 
                 drop(maybe_fileobject);
 
                 normalfile_inode_obj.size = ulength;
 
+                let entry = LogEntry::inode(inodenum, &inodeobj);
                 drop(inodeobj);
-                log_metadata(&FS_METADATA, inodenum);
+                log_entries([entry]);
+                inotify_publish(inodenum, IN_MODIFY, 0, None);
                 0 // truncating has succeeded!
             }
             Inode::CharDev(_) => syscall_error(
@@ -2474,6 +4007,337 @@ impl Cage {
         }
     }
 
+    //------------------------------------*AT SYSCALLS------------------------------------
+
+    //resolve path the way the *at calls do: relative paths start from the directory dirfd refers
+    //to, or from the working directory for AT_FDCWD. The result is an absolute path within the
+    //cage's root directory, ready to hand to the path based syscall.
+    fn _resolve_at_path(&self, dirfd: i32, path: &str, syscallname: &str) -> Result<String, i32> {
+        if path.len() == 0 {
+            return Err(syscall_error(
+                Errno::ENOENT,
+                syscallname,
+                "given path was null",
+            ));
+        }
+        if path.starts_with('/') || dirfd == AT_FDCWD {
+            return Ok(path.to_string());
+        }
+
+        let checkedfd = match self.get_filedescriptor(dirfd) {
+            Ok(checkedfd) => checkedfd,
+            Err(_) => {
+                return Err(syscall_error(
+                    Errno::EBADF,
+                    syscallname,
+                    "invalid file descriptor",
+                ))
+            }
+        };
+        let unlocked_fd = checkedfd.read();
+        let dirinodenum = match &*unlocked_fd {
+            Some(File(normalfile_filedesc_obj)) => normalfile_filedesc_obj.inode,
+            Some(_) => {
+                return Err(syscall_error(
+                    Errno::ENOTDIR,
+                    syscallname,
+                    "dirfd does not refer to a directory",
+                ))
+            }
+            None => {
+                return Err(syscall_error(
+                    Errno::EBADF,
+                    syscallname,
+                    "invalid file descriptor",
+                ))
+            }
+        };
+        drop(unlocked_fd);
+
+        match FS_METADATA.inodetable.get(&dirinodenum).as_deref() {
+            Some(Inode::Dir(_)) => {}
+            _ => {
+                return Err(syscall_error(
+                    Errno::ENOTDIR,
+                    syscallname,
+                    "dirfd does not refer to a directory",
+                ))
+            }
+        }
+        //an open directory that has since been removed has no path left to resolve from
+        let dirpath = match pathnamefrominodenum(dirinodenum) {
+            Some(dirpath) => interface::RustPathBuf::from(dirpath),
+            None => {
+                return Err(syscall_error(
+                    Errno::ENOENT,
+                    syscallname,
+                    "the directory dirfd refers to has been removed",
+                ))
+            }
+        };
+
+        //the directory's path is a true path, so strip the cage's root directory from it
+        let rootdir = self.rootdir.read().clone();
+        let cagedirpath = match dirpath.strip_prefix(&*rootdir) {
+            Ok(rest) => interface::RustPath::new("/").join(rest),
+            Err(_) => dirpath,
+        };
+        Ok(cagedirpath.join(path).to_str().unwrap().to_string())
+    }
+
+    pub fn openat_syscall(&self, dirfd: i32, path: &str, flags: i32, mode: u32) -> i32 {
+        match self._resolve_at_path(dirfd, path, "openat") {
+            Ok(path) => self.open_syscall(&path, flags, mode),
+            Err(e) => e,
+        }
+    }
+
+    pub fn mkdirat_syscall(&self, dirfd: i32, path: &str, mode: u32) -> i32 {
+        match self._resolve_at_path(dirfd, path, "mkdirat") {
+            Ok(path) => self.mkdir_syscall(&path, mode),
+            Err(e) => e,
+        }
+    }
+
+    //AT_REMOVEDIR makes unlinkat act as rmdir
+    pub fn unlinkat_syscall(&self, dirfd: i32, path: &str, flags: i32) -> i32 {
+        if flags & !AT_REMOVEDIR != 0 {
+            return syscall_error(Errno::EINVAL, "unlinkat", "invalid flags");
+        }
+        match self._resolve_at_path(dirfd, path, "unlinkat") {
+            Ok(path) if flags & AT_REMOVEDIR != 0 => self.rmdir_syscall(&path),
+            Ok(path) => self.unlink_syscall(&path),
+            Err(e) => e,
+        }
+    }
+
+    //there are no symbolic links in the filesystem, so AT_SYMLINK_NOFOLLOW changes nothing
+    pub fn fstatat_syscall(
+        &self,
+        dirfd: i32,
+        path: &str,
+        statbuf: &mut StatData,
+        flags: i32,
+    ) -> i32 {
+        if flags & !(AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH) != 0 {
+            return syscall_error(Errno::EINVAL, "fstatat", "invalid flags");
+        }
+        //with AT_EMPTY_PATH an empty path stats dirfd itself, which needn't be a directory
+        if path.len() == 0 && flags & AT_EMPTY_PATH != 0 {
+            return if dirfd == AT_FDCWD {
+                self.stat_syscall(".", statbuf)
+            } else {
+                self.fstat_syscall(dirfd, statbuf)
+            };
+        }
+        match self._resolve_at_path(dirfd, path, "fstatat") {
+            Ok(path) => self.stat_syscall(&path, statbuf),
+            Err(e) => e,
+        }
+    }
+
+    pub fn renameat_syscall(
+        &self,
+        olddirfd: i32,
+        oldpath: &str,
+        newdirfd: i32,
+        newpath: &str,
+    ) -> i32 {
+        let oldpath = match self._resolve_at_path(olddirfd, oldpath, "renameat") {
+            Ok(path) => path,
+            Err(e) => return e,
+        };
+        match self._resolve_at_path(newdirfd, newpath, "renameat") {
+            Ok(newpath) => self.rename_syscall(&oldpath, &newpath),
+            Err(e) => e,
+        }
+    }
+
+    //there are no symbolic links in the filesystem, so AT_SYMLINK_FOLLOW changes nothing
+    pub fn linkat_syscall(
+        &self,
+        olddirfd: i32,
+        oldpath: &str,
+        newdirfd: i32,
+        newpath: &str,
+        flags: i32,
+    ) -> i32 {
+        if flags & !AT_SYMLINK_FOLLOW != 0 {
+            return syscall_error(Errno::EINVAL, "linkat", "invalid flags");
+        }
+        let oldpath = match self._resolve_at_path(olddirfd, oldpath, "linkat") {
+            Ok(path) => path,
+            Err(e) => return e,
+        };
+        match self._resolve_at_path(newdirfd, newpath, "linkat") {
+            Ok(newpath) => self.link_syscall(&oldpath, &newpath),
+            Err(e) => e,
+        }
+    }
+
+    //------------------------------------STATX SYSCALL------------------------------------
+
+    //statx fills in every field it can whatever mask asks for, and stx_mask tells the caller
+    //which those were. Birth times are only known for inodes created since they started being
+    //kept, so STATX_BTIME is left out of stx_mask for older ones.
+    pub fn statx_syscall(
+        &self,
+        dirfd: i32,
+        path: &str,
+        flags: i32,
+        mask: u32,
+        statxbuf: &mut StatxStruct,
+    ) -> i32 {
+        if flags & !(AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH | AT_STATX_SYNC_TYPE) != 0
+            || flags & AT_STATX_SYNC_TYPE == AT_STATX_SYNC_TYPE
+        {
+            return syscall_error(Errno::EINVAL, "statx", "invalid flags");
+        }
+        if mask & STATX__RESERVED != 0 {
+            return syscall_error(Errno::EINVAL, "statx", "reserved mask bit was set");
+        }
+
+        let inodenum = if path.len() == 0 && flags & AT_EMPTY_PATH != 0 && dirfd != AT_FDCWD {
+            let checkedfd = match self.get_filedescriptor(dirfd) {
+                Ok(checkedfd) => checkedfd,
+                Err(_) => return syscall_error(Errno::EBADF, "statx", "invalid file descriptor"),
+            };
+            let unlocked_fd = checkedfd.read();
+            match &*unlocked_fd {
+                Some(File(normalfile_filedesc_obj)) => normalfile_filedesc_obj.inode,
+                Some(_) => {
+                    //pipes, streams and the like have no inode, so report what fstat makes up
+                    drop(unlocked_fd);
+                    let mut statdata = StatData::default();
+                    let ret = self.fstat_syscall(dirfd, &mut statdata);
+                    if ret == 0 {
+                        Self::_statx_from_statdata(&statdata, statxbuf);
+                    }
+                    return ret;
+                }
+                None => return syscall_error(Errno::EBADF, "statx", "invalid file descriptor"),
+            }
+        } else {
+            let path = if path.len() == 0 && flags & AT_EMPTY_PATH != 0 {
+                ".".to_string()
+            } else {
+                match self._resolve_at_path(dirfd, path, "statx") {
+                    Ok(path) => path,
+                    Err(e) => return e,
+                }
+            };
+            let truepath = normpath(convpath(&path), self);
+            match metawalk(truepath.as_path()) {
+                Some(inodenum) => inodenum,
+                None => {
+                    return syscall_error(Errno::ENOENT, "statx", "path refers to an invalid file")
+                }
+            }
+        };
+
+        let inodeobj = match FS_METADATA.inodetable.get(&inodenum) {
+            Some(inodeobj) => inodeobj,
+            None => return syscall_error(Errno::ENOENT, "statx", "the file has been removed"),
+        };
+        //every kind of inode keeps these fields under the same names
+        macro_rules! inode_fields {
+            ($f:expr) => {
+                (
+                    $f.mode,
+                    $f.linkcount,
+                    $f.uid,
+                    $f.gid,
+                    $f.size,
+                    $f.atime,
+                    $f.mtime,
+                    $f.ctime,
+                    $f.btime,
+                )
+            };
+        }
+        let (mode, nlink, uid, gid, size, atime, mtime, ctime, btime) = match &*inodeobj {
+            Inode::File(f) => inode_fields!(f),
+            Inode::CharDev(f) => inode_fields!(f),
+            Inode::Socket(f) => inode_fields!(f),
+            Inode::Dir(f) => inode_fields!(f),
+        };
+        //character devices report the same device number stat gives them
+        let (dev, rdev) = match &*inodeobj {
+            Inode::CharDev(f) => (5, f.dev),
+            _ => (FS_METADATA.dev_id, DevNo { major: 0, minor: 0 }),
+        };
+        drop(inodeobj);
+
+        let timestamp = |secs: u64| StatxTimestamp {
+            tv_sec: secs as i64,
+            ..Default::default()
+        };
+        *statxbuf = StatxStruct {
+            stx_mask: STATX_BASIC_STATS | STATX_MNT_ID | if btime != 0 { STATX_BTIME } else { 0 },
+            stx_blksize: STATX_BLOCK_SIZE,
+            stx_nlink: nlink,
+            stx_uid: uid,
+            stx_gid: gid,
+            stx_mode: mode as u16,
+            stx_ino: inodenum as u64,
+            stx_size: size as u64,
+            stx_blocks: ((size + 511) / 512) as u64, // in 512 byte units, whatever stx_blksize is
+            stx_attributes_mask: STATX_ATTR_MOUNT_ROOT,
+            stx_atime: timestamp(atime),
+            stx_btime: timestamp(btime),
+            stx_ctime: timestamp(ctime),
+            stx_mtime: timestamp(mtime),
+            stx_rdev_major: rdev.major,
+            stx_rdev_minor: rdev.minor,
+            stx_dev_major: major(dev),
+            stx_dev_minor: minor(dev),
+            stx_mnt_id: mount_id_of_inode(inodenum) as u64,
+            ..Default::default()
+        };
+        if inodenum == ROOTDIRECTORYINODE || is_mount_root(inodenum) {
+            statxbuf.stx_attributes |= STATX_ATTR_MOUNT_ROOT;
+        }
+        0 //statx has succeeded!
+    }
+
+    fn _statx_from_statdata(statdata: &StatData, statxbuf: &mut StatxStruct) {
+        *statxbuf = StatxStruct {
+            //no times are kept for these
+            stx_mask: STATX_BASIC_STATS & !(STATX_ATIME | STATX_MTIME | STATX_CTIME),
+            stx_blksize: STATX_BLOCK_SIZE,
+            stx_nlink: statdata.st_nlink,
+            stx_uid: statdata.st_uid,
+            stx_gid: statdata.st_gid,
+            stx_mode: statdata.st_mode as u16,
+            stx_ino: statdata.st_ino as u64,
+            stx_size: statdata.st_size as u64,
+            stx_dev_major: major(statdata.st_dev),
+            stx_dev_minor: minor(statdata.st_dev),
+            ..Default::default()
+        };
+    }
+
+    //------------------------------------STAT64 SYSCALLS------------------------------------
+
+    //stat and fstat for 32-bit guests, whose struct stat64 is laid out differently
+    pub fn stat64_syscall(&self, path: &str, statbuf: &mut Stat64Struct) -> i32 {
+        let mut statxbuf = StatxStruct::default();
+        let ret = self.statx_syscall(AT_FDCWD, path, 0, STATX_BASIC_STATS, &mut statxbuf);
+        if ret == 0 {
+            *statbuf = Stat64Struct::from_statx(&statxbuf);
+        }
+        ret
+    }
+
+    pub fn fstat64_syscall(&self, fd: i32, statbuf: &mut Stat64Struct) -> i32 {
+        let mut statxbuf = StatxStruct::default();
+        let ret = self.statx_syscall(fd, "", AT_EMPTY_PATH, STATX_BASIC_STATS, &mut statxbuf);
+        if ret == 0 {
+            *statbuf = Stat64Struct::from_statx(&statxbuf);
+        }
+        ret
+    }
+
     //------------------------------------FSYNC SYSCALL------------------------------------
 
     pub fn fsync_syscall(&self, fd: i32) -> i32 {
@@ -2489,10 +4353,11 @@ impl Cage {
                             "specified file not open for sync",
                         );
                     }
-                    let inodeobj = FS_METADATA
-                        .inodetable
-                        .get(&normalfile_filedesc_obj.inode)
-                        .unwrap();
+                    let inodeobj = match FS_METADATA.inodetable.get(&normalfile_filedesc_obj.inode)
+                    {
+                        Some(inodeobj) => inodeobj,
+                        None => return corruption_error("fsync", "open file has no inode"),
+                    };
                     match &*inodeobj {
                         Inode::File(_) => {
                             let fileobject =
@@ -2540,10 +4405,11 @@ impl Cage {
                             "specified file not open for sync",
                         );
                     }
-                    let inodeobj = FS_METADATA
-                        .inodetable
-                        .get(&normalfile_filedesc_obj.inode)
-                        .unwrap();
+                    let inodeobj = match FS_METADATA.inodetable.get(&normalfile_filedesc_obj.inode)
+                    {
+                        Some(inodeobj) => inodeobj,
+                        None => return corruption_error("fdatasync", "open file has no inode"),
+                    };
                     match &*inodeobj {
                         Inode::File(_) => {
                             let fileobject =
@@ -2590,31 +4456,21 @@ impl Cage {
         if let Some(filedesc_enum) = &mut *unlocked_fd {
             match filedesc_enum {
                 File(ref mut normalfile_filedesc_obj) => {
-                    let inodeobj = FS_METADATA
-                        .inodetable
-                        .get(&normalfile_filedesc_obj.inode)
-                        .unwrap();
+                    let inodeobj = match FS_METADATA.inodetable.get(&normalfile_filedesc_obj.inode)
+                    {
+                        Some(inodeobj) => inodeobj,
+                        None => {
+                            return corruption_error("sync_file_range", "open file has no inode")
+                        }
+                    };
                     match &*inodeobj {
                         Inode::File(_) => {
                             // This code segment obtains the file object associated with the specified inode from FILEOBJECTTABLE.
                             // It calls 'sync_file_range' on this file object, where initially the flags are validated, returning -EINVAL for incorrect flags.
-                            // If the flags are correct, libc::sync_file_range is invoked; if it fails (returns -1), 'from_discriminant' function handles the error code.
+                            // If the flags are correct, libc::sync_file_range is invoked, and if it fails the errno it set is returned.
 
                             let fobj = FILEOBJECTTABLE.get(&normalfile_filedesc_obj.inode).unwrap();
-                            let result = fobj.sync_file_range(offset, nbytes, flags);
-                            if result == 0 || result == -(EINVAL as i32) {
-                                return result;
-                            }
-                            match Errno::from_discriminant(interface::get_errno()) {
-                                Ok(i) => {
-                                    return syscall_error(
-                                        i,
-                                        "sync_file_range",
-                                        "The libc call to sync_file_range failed!",
-                                    );
-                                }
-                                Err(()) => panic!("Unknown errno value from setsockopt returned!"),
-                            };
+                            return fobj.sync_file_range(offset, nbytes, flags);
                         }
                         _ => syscall_error(
                             Errno::ESPIPE,
@@ -2634,6 +4490,42 @@ impl Cage {
         }
     }
 
+    //------------------------------------SYNC SYSCALL------------------------------------
+
+    pub fn sync_syscall(&self) -> i32 {
+        if !sync_filesystem(None) {
+            return syscall_error(
+                Errno::EIO,
+                "sync",
+                "an error occurred during synchronization",
+            );
+        }
+        0
+    }
+
+    //------------------------------------SYNCFS SYSCALL------------------------------------
+
+    pub fn syncfs_syscall(&self, fd: i32) -> i32 {
+        let checkedfd = self.get_filedescriptor(fd).unwrap();
+        let unlocked_fd = checkedfd.read();
+        let inodenum = match &*unlocked_fd {
+            Some(File(normalfile_filedesc_obj)) => normalfile_filedesc_obj.inode,
+            //sockets, pipes and the like live on no filesystem with anything to flush
+            Some(_) => return 0,
+            None => return syscall_error(Errno::EBADF, "syncfs", "invalid file descriptor"),
+        };
+        drop(unlocked_fd);
+
+        if !sync_filesystem(Some(mount_id_of_inode(inodenum))) {
+            return syscall_error(
+                Errno::EIO,
+                "syncfs",
+                "an error occurred during synchronization",
+            );
+        }
+        0
+    }
+
     //------------------FTRUNCATE SYSCALL------------------
 
     pub fn ftruncate_syscall(&self, fd: i32, length: isize) -> i32 {
@@ -2651,6 +4543,13 @@ impl Cage {
                         );
                     }
                     let inodenum = normalfile_filedesc_obj.inode;
+                    if inode_is_readonly(inodenum) {
+                        return syscall_error(
+                            Errno::EROFS,
+                            "ftruncate",
+                            "file is on a read-only filesystem",
+                        );
+                    }
                     self._truncate_helper(inodenum, length, true)
                 }
                 _ => syscall_error(
@@ -2671,6 +4570,13 @@ impl Cage {
     //------------------TRUNCATE SYSCALL------------------
     pub fn truncate_syscall(&self, path: &str, length: isize) -> i32 {
         let truepath = normpath(convpath(path), self);
+        if path_is_readonly(&truepath) {
+            return syscall_error(
+                Errno::EROFS,
+                "truncate",
+                "path is on a read-only filesystem",
+            );
+        }
 
         //Walk the file tree to get inode from path
         if let Some(inodenum) = metawalk(truepath.as_path()) {
@@ -2698,32 +4604,970 @@ impl Cage {
         // get an fd for each end of the pipe and set flags to RD_ONLY and WR_ONLY
         // append each to pipefds list
 
-        let accflags = [O_RDONLY, O_WRONLY];
-        for accflag in accflags {
-            let (fd, guardopt) = self.get_next_fd(None);
-            if fd < 0 {
-                return fd;
+        let accflags = [O_RDONLY, O_WRONLY];
+        for accflag in accflags {
+            let (fd, guardopt) = self.get_next_fd(None);
+            if fd < 0 {
+                return fd;
+            }
+            let fdoption = &mut *guardopt.unwrap();
+
+            let _insertval = fdoption.insert(Pipe(PipeDesc {
+                pipe: pipe.clone(),
+                flags: accflag | (actualflags & O_CLOEXEC),
+                statusflags: StatusFlags::new(actualflags),
+                advlock: interface::RustRfc::new(interface::AdvisoryLock::new()),
+            }));
+
+            match accflag {
+                O_RDONLY => {
+                    pipefd.readfd = fd;
+                }
+                O_WRONLY => {
+                    pipefd.writefd = fd;
+                }
+                _ => panic!("How did you get here."),
+            }
+        }
+
+        0 // success
+    }
+
+    //------------------MOUNT & UMOUNT SYSCALLS------------------
+
+    pub fn mount_syscall(
+        &self,
+        source: &str,
+        target: &str,
+        fstype: &str,
+        flags: u64,
+        data: Option<&str>,
+    ) -> i32 {
+        if target.len() == 0 {
+            return syscall_error(Errno::ENOENT, "mount", "given target was null");
+        }
+        let truetarget = normpath(convpath(target), self);
+
+        //remounting only changes the flags of an existing mount
+        if flags & MS_REMOUNT != 0 {
+            let mut mounts = MOUNT_TABLE.mounts.write();
+            return match mounts
+                .iter_mut()
+                .rev()
+                .find(|entry| entry.mountpoint == truetarget)
+            {
+                Some(entry) => {
+                    //a tmpfs can also be resized, but not below what it already holds
+                    if let (Some(data), Some(mut usage)) =
+                        (data, MOUNT_TABLE.usage.get_mut(&entry.id))
+                    {
+                        match parse_tmpfs_size(data) {
+                            Some(limit) if limit >= usage.used => usage.limit = limit,
+                            _ => {
+                                return syscall_error(
+                                    Errno::EINVAL,
+                                    "mount",
+                                    "invalid size for the mounted filesystem",
+                                )
+                            }
+                        }
+                    }
+                    entry.flags = flags & MS_PERMFLAGS;
+                    0
+                }
+                None => syscall_error(Errno::EINVAL, "mount", "target is not a mount point"),
+            };
+        }
+
+        let fstype = if flags & MS_BIND != 0 {
+            MountFsType::Bind
+        } else {
+            match MountFsType::from_name(fstype) {
+                Some(MountFsType::LindFs) | None => {
+                    return syscall_error(
+                        Errno::ENODEV,
+                        "mount",
+                        "filesystem type is not supported",
+                    );
+                }
+                Some(fstype) => fstype,
+            }
+        };
+
+        let coveredinode = match metawalk(&truetarget) {
+            Some(inodenum) => inodenum,
+            None => return syscall_error(Errno::ENOENT, "mount", "target does not exist"),
+        };
+        let parentinode = match &*FS_METADATA.inodetable.get(&coveredinode).unwrap() {
+            Inode::Dir(dir) => dir.filename_to_inode_dict.get("..").unwrap(),
+            _ => return syscall_error(Errno::ENOTDIR, "mount", "target is not a directory"),
+        };
+        if coveredinode == ROOTDIRECTORYINODE || is_mount_root(coveredinode) {
+            return syscall_error(Errno::EBUSY, "mount", "target is already a mount point");
+        }
+
+        let mountid = MOUNT_TABLE
+            .nextid
+            .fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+        let rootinode = match fstype {
+            MountFsType::Tmpfs | MountFsType::Procfs | MountFsType::Devpts => {
+                if fstype == MountFsType::Tmpfs {
+                    let limit = match parse_tmpfs_size(data.unwrap_or("")) {
+                        Some(limit) => limit,
+                        None => {
+                            return syscall_error(Errno::EINVAL, "mount", "invalid tmpfs options")
+                        }
+                    };
+                    MOUNT_TABLE.usage.insert(
+                        mountid,
+                        MountUsage {
+                            limit: limit,
+                            used: 0,
+                        },
+                    );
+                }
+
+                let newinodenum = FS_METADATA
+                    .nextinode
+                    .fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+                let time = interface::timestamp(); //We do a real timestamp now
+                let newinode = Inode::Dir(DirectoryInode {
+                    size: 0,
+                    uid: DEFAULT_UID,
+                    gid: DEFAULT_GID,
+                    mode: S_IFDIR as u32 | S_IRWXA,
+                    linkcount: 3, //like the root directory, a mount root can never be removed
+                    refcount: 0,
+                    atime: time,
+                    ctime: time,
+                    btime: time,
+                    mtime: time,
+                    filename_to_inode_dict: init_filename_to_inode_dict(newinodenum, parentinode),
+                });
+                FS_METADATA.inodetable.insert(newinodenum, newinode);
+                MOUNT_TABLE.inodemounts.insert(newinodenum, mountid);
+                if fstype == MountFsType::Procfs {
+                    procfs_mount(mountid, newinodenum);
+                }
+                newinodenum
+            }
+            MountFsType::Bind => {
+                let truesource = normpath(convpath(source), self);
+                let sourceinode = match metawalk(&truesource) {
+                    Some(inodenum) => inodenum,
+                    None => return syscall_error(Errno::ENOENT, "mount", "source does not exist"),
+                };
+                if !matches!(
+                    &*FS_METADATA.inodetable.get(&sourceinode).unwrap(),
+                    Inode::Dir(_)
+                ) {
+                    return syscall_error(Errno::ENOTDIR, "mount", "source is not a directory");
+                }
+                if sourceinode == ROOTDIRECTORYINODE || sourceinode == coveredinode {
+                    return syscall_error(Errno::EINVAL, "mount", "source cannot be bound here");
+                }
+                if is_mount_root(sourceinode) {
+                    return syscall_error(Errno::EBUSY, "mount", "source is already mounted");
+                }
+                sourceinode
+            }
+            MountFsType::LindFs => unreachable!(),
+        };
+
+        mount_insert(MountEntry {
+            id: mountid,
+            source: source.to_string(),
+            mountpoint: truetarget,
+            fstype: fstype,
+            flags: flags & MS_PERMFLAGS,
+            rootinode: rootinode,
+            coveredinode: coveredinode,
+        });
+        0 // success
+    }
+
+    pub fn umount_syscall(&self, target: &str) -> i32 {
+        if target.len() == 0 {
+            return syscall_error(Errno::ENOENT, "umount", "given target was null");
+        }
+        let truetarget = normpath(convpath(target), self);
+
+        let entry = match MOUNT_TABLE
+            .mounts
+            .read()
+            .iter()
+            .rev()
+            .find(|entry| entry.mountpoint == truetarget)
+        {
+            Some(entry) => entry.clone(),
+            None => return syscall_error(Errno::EINVAL, "umount", "target is not a mount point"),
+        };
+        if entry.id == ROOTMOUNTID {
+            return syscall_error(Errno::EBUSY, "umount", "cannot unmount the root filesystem");
+        }
+
+        //a mount is busy while anything is mounted beneath it or any of its inodes are in use
+        let nested =
+            MOUNT_TABLE.mounts.read().iter().any(|other| {
+                other.id != entry.id && other.mountpoint.starts_with(&entry.mountpoint)
+            });
+        if nested {
+            return syscall_error(
+                Errno::EBUSY,
+                "umount",
+                "target has filesystems mounted beneath it",
+            );
+        }
+        let inuse = |inodenum: &usize| match FS_METADATA.inodetable.get(inodenum) {
+            Some(inode) => match &*inode {
+                Inode::File(f) => f.refcount > 0,
+                Inode::CharDev(f) => f.refcount > 0,
+                Inode::Socket(f) => f.refcount > 0,
+                Inode::Dir(f) => f.refcount > 0,
+            },
+            None => false,
+        };
+        //a devpts is also in use as long as any of its ptys' masters are open
+        if inuse(&entry.rootinode)
+            || MOUNT_TABLE
+                .inodemounts
+                .iter()
+                .any(|owner| *owner.value() == entry.id && inuse(owner.key()))
+            || PTY_TABLE.iter().any(|tty| tty.ptsmount == entry.id)
+        {
+            return syscall_error(Errno::EBUSY, "umount", "target is in use");
+        }
+
+        mount_remove(entry.id);
+        0 // success
+    }
+
+    //------------------INOTIFY SYSCALLS------------------
+
+    pub fn inotify_init1_syscall(&self, flags: i32) -> i32 {
+        if flags & !(IN_NONBLOCK | IN_CLOEXEC) != 0 {
+            return syscall_error(Errno::EINVAL, "inotify_init1", "invalid flags specified");
+        }
+
+        let (fd, guardopt) = self.get_next_fd(None);
+        if fd < 0 {
+            return fd;
+        }
+        let fdoption = &mut *guardopt.unwrap();
+
+        let _insertval = fdoption.insert(Inotify(InotifyDesc {
+            instance: interface::RustRfc::new(InotifyInstance::new()),
+            flags: O_RDONLY | (flags & O_CLOEXEC),
+            statusflags: StatusFlags::new(flags),
+            advlock: interface::RustRfc::new(interface::AdvisoryLock::new()),
+        }));
+
+        fd
+    }
+
+    pub fn inotify_init_syscall(&self) -> i32 {
+        self.inotify_init1_syscall(0)
+    }
+
+    pub fn inotify_add_watch_syscall(&self, fd: i32, path: &str, mask: u32) -> i32 {
+        if mask & IN_ALL_EVENTS == 0 {
+            return syscall_error(
+                Errno::EINVAL,
+                "inotify_add_watch",
+                "the event mask contains no valid events",
+            );
+        }
+
+        let checkedfd = self.get_filedescriptor(fd).unwrap();
+        let unlocked_fd = checkedfd.read();
+        if let Some(Inotify(inotify_filedesc_obj)) = &*unlocked_fd {
+            let truepath = normpath(convpath(path), self);
+            let inodenum = match metawalk(truepath.as_path()) {
+                Some(inodenum) => inodenum,
+                None => {
+                    return syscall_error(
+                        Errno::ENOENT,
+                        "inotify_add_watch",
+                        "a component of the path does not exist",
+                    );
+                }
+            };
+            if mask & IN_ONLYDIR != 0 {
+                if let Inode::Dir(_) = *FS_METADATA.inodetable.get(&inodenum).unwrap() {
+                } else {
+                    return syscall_error(
+                        Errno::ENOTDIR,
+                        "inotify_add_watch",
+                        "IN_ONLYDIR was specified and path is not a directory",
+                    );
+                }
+            }
+            inotify_filedesc_obj.instance.add_watch(inodenum, mask)
+        } else if unlocked_fd.is_none() {
+            syscall_error(Errno::EBADF, "inotify_add_watch", "invalid file descriptor")
+        } else {
+            syscall_error(
+                Errno::EINVAL,
+                "inotify_add_watch",
+                "fd is not an inotify file descriptor",
+            )
+        }
+    }
+
+    pub fn inotify_rm_watch_syscall(&self, fd: i32, wd: i32) -> i32 {
+        let checkedfd = self.get_filedescriptor(fd).unwrap();
+        let unlocked_fd = checkedfd.read();
+        if let Some(Inotify(inotify_filedesc_obj)) = &*unlocked_fd {
+            if inotify_filedesc_obj.instance.rm_watch(wd) {
+                0
+            } else {
+                syscall_error(
+                    Errno::EINVAL,
+                    "inotify_rm_watch",
+                    "wd is not a valid watch descriptor for this instance",
+                )
+            }
+        } else if unlocked_fd.is_none() {
+            syscall_error(Errno::EBADF, "inotify_rm_watch", "invalid file descriptor")
+        } else {
+            syscall_error(
+                Errno::EINVAL,
+                "inotify_rm_watch",
+                "fd is not an inotify file descriptor",
+            )
+        }
+    }
+
+    //------------------MQUEUE SYSCALLS------------------
+
+    //a message queue or shared memory object name is a slash followed by one or more
+    //characters, none of them slashes
+    fn _check_ipc_name(name: &str, syscallname: &str) -> i32 {
+        if name.len() < 2 || !name.starts_with('/') || name[1..].contains('/') {
+            return syscall_error(
+                Errno::EINVAL,
+                syscallname,
+                "name must be a slash followed by characters other than slash",
+            );
+        }
+        if name.len() - 1 > MQ_NAME_MAX {
+            return syscall_error(Errno::ENAMETOOLONG, syscallname, "name is too long");
+        }
+        0
+    }
+
+    pub fn mq_open_syscall(
+        &self,
+        name: &str,
+        oflag: i32,
+        mode: u32,
+        attr: Option<&MqAttrStruct>,
+    ) -> i32 {
+        let checkname = Self::_check_ipc_name(name, "mq_open");
+        if checkname < 0 {
+            return checkname;
+        }
+
+        let (fd, guardopt) = self.get_next_fd(None);
+        if fd < 0 {
+            return fd;
+        }
+        let fdoption = &mut *guardopt.unwrap();
+
+        let mqueue = match MQUEUE_TABLE.entry(name.to_string()) {
+            interface::RustHashEntry::Occupied(occupied) => {
+                if (O_CREAT | O_EXCL) == (oflag & (O_CREAT | O_EXCL)) {
+                    return syscall_error(
+                        Errno::EEXIST,
+                        "mq_open",
+                        "queue already exists and O_CREAT and O_EXCL were used",
+                    );
+                }
+                occupied.get().clone()
+            }
+            interface::RustHashEntry::Vacant(vacant) => {
+                if 0 == (oflag & O_CREAT) {
+                    return syscall_error(
+                        Errno::ENOENT,
+                        "mq_open",
+                        "tried to open a queue that did not exist, and O_CREAT was not specified",
+                    );
+                }
+
+                let (maxmsg, msgsize) = match attr {
+                    Some(attr) => {
+                        if attr.mq_maxmsg <= 0
+                            || attr.mq_msgsize <= 0
+                            || attr.mq_maxmsg as usize > MQ_MAXMSG_MAX
+                            || attr.mq_msgsize as usize > MQ_MSGSIZE_MAX
+                        {
+                            return syscall_error(
+                                Errno::EINVAL,
+                                "mq_open",
+                                "mq_maxmsg or mq_msgsize is out of range",
+                            );
+                        }
+                        (attr.mq_maxmsg as usize, attr.mq_msgsize as usize)
+                    }
+                    None => (MQ_DEFAULT_MAXMSG, MQ_DEFAULT_MSGSIZE),
+                };
+
+                let mqueue =
+                    interface::RustRfc::new(MqueueInstance::new(maxmsg, msgsize, mode & S_IRWXA));
+                vacant.insert(mqueue.clone());
+                mqueue
+            }
+        };
+
+        let _insertval = fdoption.insert(Mqueue(MqueueDesc {
+            mqueue: mqueue,
+            flags: oflag & (O_RDWRFLAGS | O_CLOEXEC),
+            statusflags: StatusFlags::new(oflag),
+            advlock: interface::RustRfc::new(interface::AdvisoryLock::new()),
+        }));
+
+        fd
+    }
+
+    pub fn mq_unlink_syscall(&self, name: &str) -> i32 {
+        let checkname = Self::_check_ipc_name(name, "mq_unlink");
+        if checkname < 0 {
+            return checkname;
+        }
+
+        //descriptors that still have the queue open keep using it
+        match MQUEUE_TABLE.remove(name) {
+            Some(_) => 0,
+            None => syscall_error(
+                Errno::ENOENT,
+                "mq_unlink",
+                "there is no queue with this name",
+            ),
+        }
+    }
+
+    pub fn mq_send_syscall(&self, fd: i32, msg: &[u8], msg_prio: u32) -> i32 {
+        let checkedfd = self.get_filedescriptor(fd).unwrap();
+        let unlocked_fd = checkedfd.read();
+        if let Some(Mqueue(mqueue_filedesc_obj)) = &*unlocked_fd {
+            if is_rdonly(mqueue_filedesc_obj.flags) {
+                return syscall_error(
+                    Errno::EBADF,
+                    "mq_send",
+                    "specified queue not open for writing",
+                );
+            }
+            let mqueue = mqueue_filedesc_obj.mqueue.clone();
+            let nonblocking = mqueue_filedesc_obj.statusflags.get() & O_NONBLOCK != 0;
+            // don't hold the fd lock while we wait for room in the queue
+            drop(unlocked_fd);
+
+            if msg.len() > mqueue.msgsize {
+                return syscall_error(
+                    Errno::EMSGSIZE,
+                    "mq_send",
+                    "msg_len is greater than the queue's mq_msgsize",
+                );
+            }
+            if msg_prio >= MQ_PRIO_MAX {
+                return syscall_error(
+                    Errno::EINVAL,
+                    "mq_send",
+                    "msg_prio is not below MQ_PRIO_MAX",
+                );
+            }
+
+            mqueue.send(msg.to_vec(), msg_prio, nonblocking, self.cageid, &|| {
+                self.signal_interrupt("mq_send", true)
+            })
+        } else if unlocked_fd.is_none() {
+            syscall_error(Errno::EBADF, "mq_send", "invalid file descriptor")
+        } else {
+            syscall_error(
+                Errno::EBADF,
+                "mq_send",
+                "fd is not a message queue descriptor",
+            )
+        }
+    }
+
+    pub fn mq_receive_syscall(&self, fd: i32, msg: &mut [u8], msg_prio: Option<&mut u32>) -> i32 {
+        let checkedfd = self.get_filedescriptor(fd).unwrap();
+        let unlocked_fd = checkedfd.read();
+        if let Some(Mqueue(mqueue_filedesc_obj)) = &*unlocked_fd {
+            if is_wronly(mqueue_filedesc_obj.flags) {
+                return syscall_error(
+                    Errno::EBADF,
+                    "mq_receive",
+                    "specified queue not open for reading",
+                );
+            }
+            let mqueue = mqueue_filedesc_obj.mqueue.clone();
+            let nonblocking = mqueue_filedesc_obj.statusflags.get() & O_NONBLOCK != 0;
+            // don't hold the fd lock while we wait for a message to arrive
+            drop(unlocked_fd);
+
+            if msg.len() < mqueue.msgsize {
+                return syscall_error(
+                    Errno::EMSGSIZE,
+                    "mq_receive",
+                    "msg_len is less than the queue's mq_msgsize",
+                );
+            }
+
+            match mqueue.receive(nonblocking, &|| self.signal_interrupt("mq_receive", true)) {
+                Ok(message) => {
+                    msg[..message.data.len()].copy_from_slice(&message.data);
+                    if let Some(prio) = msg_prio {
+                        *prio = message.prio;
+                    }
+                    message.data.len() as i32
+                }
+                Err(e) => e,
+            }
+        } else if unlocked_fd.is_none() {
+            syscall_error(Errno::EBADF, "mq_receive", "invalid file descriptor")
+        } else {
+            syscall_error(
+                Errno::EBADF,
+                "mq_receive",
+                "fd is not a message queue descriptor",
+            )
+        }
+    }
+
+    //backs both mq_getattr and mq_setattr; only O_NONBLOCK can be changed after mq_open
+    pub fn mq_getsetattr_syscall(
+        &self,
+        fd: i32,
+        newattr: Option<&MqAttrStruct>,
+        oldattr: Option<&mut MqAttrStruct>,
+    ) -> i32 {
+        let checkedfd = self.get_filedescriptor(fd).unwrap();
+        let unlocked_fd = checkedfd.read();
+        if let Some(Mqueue(mqueue_filedesc_obj)) = &*unlocked_fd {
+            if let Some(newattr) = newattr {
+                if newattr.mq_flags & !(O_NONBLOCK as i64) != 0 {
+                    return syscall_error(
+                        Errno::EINVAL,
+                        "mq_setattr",
+                        "mq_flags contains flags other than O_NONBLOCK",
+                    );
+                }
+            }
+
+            let statusflags = &mqueue_filedesc_obj.statusflags;
+            if let Some(oldattr) = oldattr {
+                let mqueue = &mqueue_filedesc_obj.mqueue;
+                *oldattr = MqAttrStruct {
+                    mq_flags: (statusflags.get() & O_NONBLOCK) as i64,
+                    mq_maxmsg: mqueue.maxmsg as i64,
+                    mq_msgsize: mqueue.msgsize as i64,
+                    mq_curmsgs: mqueue.curmsgs() as i64,
+                    ..Default::default()
+                };
+            }
+            if let Some(newattr) = newattr {
+                statusflags.set((statusflags.get() & !O_NONBLOCK) | newattr.mq_flags as i32);
+            }
+            0
+        } else if unlocked_fd.is_none() {
+            syscall_error(Errno::EBADF, "mq_getsetattr", "invalid file descriptor")
+        } else {
+            syscall_error(
+                Errno::EBADF,
+                "mq_getsetattr",
+                "fd is not a message queue descriptor",
+            )
+        }
+    }
+
+    pub fn mq_notify_syscall(&self, fd: i32, sevp: Option<&interface::SigeventStruct>) -> i32 {
+        let checkedfd = self.get_filedescriptor(fd).unwrap();
+        let unlocked_fd = checkedfd.read();
+        if let Some(Mqueue(mqueue_filedesc_obj)) = &*unlocked_fd {
+            let notification = match sevp {
+                //a null sevp removes this cage's registration
+                None => None,
+                Some(sev) => {
+                    if sev.sigev_notify != SIGEV_NONE && sev.sigev_notify != SIGEV_SIGNAL {
+                        return syscall_error(
+                            Errno::EINVAL,
+                            "mq_notify",
+                            "only SIGEV_NONE and SIGEV_SIGNAL notifications are supported",
+                        );
+                    }
+                    if sev.sigev_notify == SIGEV_SIGNAL
+                        && (sev.sigev_signo < 1 || sev.sigev_signo > SIGNAL_MAX)
+                    {
+                        return syscall_error(
+                            Errno::EINVAL,
+                            "mq_notify",
+                            "sigev_signo is not a valid signal number",
+                        );
+                    }
+                    Some(MqNotification {
+                        cageid: self.cageid,
+                        notify: sev.sigev_notify,
+                        signo: sev.sigev_signo,
+                        value: sev.sigev_value,
+                    })
+                }
+            };
+            mqueue_filedesc_obj
+                .mqueue
+                .set_notification(notification, self.cageid)
+        } else if unlocked_fd.is_none() {
+            syscall_error(Errno::EBADF, "mq_notify", "invalid file descriptor")
+        } else {
+            syscall_error(
+                Errno::EBADF,
+                "mq_notify",
+                "fd is not a message queue descriptor",
+            )
+        }
+    }
+
+    //------------------SHM_OPEN SYSCALL------------------
+
+    //POSIX shared memory objects are files in the tmpfs on /dev/shm, so they are sized with
+    //ftruncate and mapped with MAP_SHARED across cages just like any other file
+    pub fn shm_open_syscall(&self, name: &str, oflag: i32, mode: u32) -> i32 {
+        let checkname = Self::_check_ipc_name(name, "shm_open");
+        if checkname < 0 {
+            return checkname;
+        }
+        if is_wronly(oflag) {
+            return syscall_error(
+                Errno::EINVAL,
+                "shm_open",
+                "shared memory objects must be opened O_RDONLY or O_RDWR",
+            );
+        }
+
+        self.open_syscall(
+            &format!("{}{}", SHM_OPEN_DIR, name),
+            oflag | O_NOFOLLOW | O_CLOEXEC,
+            mode,
+        )
+    }
+
+    //------------------SHM_UNLINK SYSCALL------------------
+
+    pub fn shm_unlink_syscall(&self, name: &str) -> i32 {
+        let checkname = Self::_check_ipc_name(name, "shm_unlink");
+        if checkname < 0 {
+            return checkname;
+        }
+
+        //existing mappings and descriptors keep the object alive until they are gone
+        self.unlink_syscall(&format!("{}{}", SHM_OPEN_DIR, name))
+    }
+
+    //------------------MEMFD_CREATE SYSCALL------------------
+
+    pub fn memfd_create_syscall(&self, name: &str, flags: u32) -> i32 {
+        if flags & !(MFD_CLOEXEC | MFD_ALLOW_SEALING) != 0 {
+            return syscall_error(
+                Errno::EINVAL,
+                "memfd_create",
+                "unknown flags were specified",
+            );
+        }
+        if name.len() > MFD_NAME_MAX {
+            return syscall_error(Errno::EINVAL, "memfd_create", "name is too long");
+        }
+
+        let (fd, guardopt) = self.get_next_fd(None);
+        if fd < 0 {
+            return fd;
+        }
+        let fdoption = &mut *guardopt.unwrap();
+
+        //the file is linked nowhere, so it goes away along with its last descriptor
+        let time = interface::timestamp(); //We do a real timestamp now
+        let cred = self.cred.read().clone();
+        let newinode = Inode::File(GenericInode {
+            size: 0,
+            uid: cred.euid,
+            gid: cred.egid,
+            mode: S_IFREG as u32 | S_IRWXA,
+            linkcount: 0,
+            refcount: 1,
+            atime: time,
+            ctime: time,
+            btime: time,
+            mtime: time,
+        });
+
+        let newinodenum = FS_METADATA
+            .nextinode
+            .fetch_add(1, interface::RustAtomicOrdering::Relaxed); //fetch_add returns the previous value, which is the inode number we want
+        if !quota_claim_inode(self, newinodenum) {
+            return syscall_error(
+                Errno::EDQUOT,
+                "memfd_create",
+                "the inode quota of the cage has been exhausted",
+            );
+        }
+        FS_METADATA.inodetable.insert(newinodenum, newinode);
+        MOUNT_TABLE.inodemounts.insert(newinodenum, ANONMOUNTID);
+        let sysfilename = format!("{}{}", FILEDATAPREFIX, newinodenum);
+        FILEOBJECTTABLE.insert(newinodenum, interface::openmemfile(sysfilename).unwrap());
+
+        //without MFD_ALLOW_SEALING the file starts out sealed against any seals
+        let seals = if flags & MFD_ALLOW_SEALING != 0 {
+            0
+        } else {
+            F_SEAL_SEAL
+        };
+        FILE_SEALS.insert(newinodenum, seals);
+
+        let cloexec = if flags & MFD_CLOEXEC != 0 {
+            O_CLOEXEC
+        } else {
+            0
+        };
+        let _insertval = fdoption.insert(File(self._file_initializer(
+            newinodenum,
+            O_RDWR | cloexec,
+            0,
+        )));
+
+        fd
+    }
+
+    //------------------PSEUDO-TERMINAL SYSCALLS------------------
+
+    pub fn posix_openpt_syscall(&self, flags: i32) -> i32 {
+        if flags & !(O_RDWR | O_NOCTTY | O_CLOEXEC) != 0 {
+            return syscall_error(
+                Errno::EINVAL,
+                "posix_openpt",
+                "unknown flags were specified",
+            );
+        }
+
+        //the slave is created in the most recently mounted devpts
+        let ptsmount = match MOUNT_TABLE
+            .mounts
+            .read()
+            .iter()
+            .rev()
+            .find(|entry| entry.fstype == MountFsType::Devpts)
+        {
+            Some(entry) => entry.clone(),
+            None => return syscall_error(Errno::ENOENT, "posix_openpt", "no devpts is mounted"),
+        };
+
+        let (fd, guardopt) = self.get_next_fd(None);
+        if fd < 0 {
+            return fd;
+        }
+        let fdoption = &mut *guardopt.unwrap();
+
+        let masterinodenum = FS_METADATA
+            .nextinode
+            .fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+        if !quota_claim_inode(self, masterinodenum) {
+            return syscall_error(
+                Errno::EDQUOT,
+                "posix_openpt",
+                "the inode quota of the cage has been exhausted",
+            );
+        }
+        let slaveinodenum = FS_METADATA
+            .nextinode
+            .fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+
+        //ptys take the lowest free index
+        let mut index = None;
+        for candidate in 0..PTY_MAX {
+            if let interface::RustHashEntry::Vacant(vacant) = PTY_TABLE.entry(candidate) {
+                vacant.insert(interface::RustRfc::new(Tty::new(
+                    candidate,
+                    ptsmount.id,
+                    ptsmount.rootinode,
+                    slaveinodenum,
+                    default_termios(),
+                    DEFAULT_WINSIZE,
+                )));
+                index = Some(candidate);
+                break;
+            }
+        }
+        let index = match index {
+            Some(index) => index,
+            None => {
+                quota_release_inode(masterinodenum, 0);
+                return syscall_error(Errno::ENOSPC, "posix_openpt", "every pty is in use");
+            }
+        };
+
+        let time = interface::timestamp(); //We do a real timestamp now
+        let cred = self.cred.read().clone();
+        let slaveinode = Inode::CharDev(DeviceInode {
+            size: 0,
+            uid: cred.euid,
+            gid: cred.egid,
+            mode: S_IFCHR as u32 | S_IRUSR | S_IWUSR | S_IWGRP,
+            linkcount: 1,
+            refcount: 0,
+            atime: time,
+            ctime: time,
+            btime: time,
+            mtime: time,
+            dev: DevNo {
+                major: PTY_SLAVE_MAJOR,
+                minor: index,
+            },
+        });
+        FS_METADATA.inodetable.insert(slaveinodenum, slaveinode);
+        MOUNT_TABLE.inodemounts.insert(slaveinodenum, ptsmount.id);
+        if let Inode::Dir(ref mut dir) =
+            *FS_METADATA.inodetable.get_mut(&ptsmount.rootinode).unwrap()
+        {
+            dir.filename_to_inode_dict
+                .insert(index.to_string(), slaveinodenum);
+            dir.linkcount += 1;
+        }
+
+        //like a memfd, the master is linked nowhere and goes away along with its last descriptor
+        let masterinode = Inode::CharDev(DeviceInode {
+            size: 0,
+            uid: cred.euid,
+            gid: cred.egid,
+            mode: S_IFCHR as u32 | S_IRUSR | S_IWUSR,
+            linkcount: 0,
+            refcount: 1,
+            atime: time,
+            ctime: time,
+            btime: time,
+            mtime: time,
+            dev: DevNo {
+                major: PTY_MASTER_MAJOR,
+                minor: index,
+            },
+        });
+        FS_METADATA.inodetable.insert(masterinodenum, masterinode);
+        MOUNT_TABLE.inodemounts.insert(masterinodenum, ANONMOUNTID);
+
+        let _insertval = fdoption.insert(File(self._file_initializer(
+            masterinodenum,
+            O_RDWR | (flags & O_CLOEXEC),
+            0,
+        )));
+
+        fd
+    }
+
+    fn _pty_master_of_fd(
+        &self,
+        fd: i32,
+        callname: &str,
+        errno: Errno,
+    ) -> Result<interface::RustRfc<Tty>, i32> {
+        let checkedfd = match self.get_filedescriptor(fd) {
+            Ok(checkedfd) => checkedfd,
+            Err(()) => {
+                return Err(syscall_error(
+                    Errno::EBADF,
+                    callname,
+                    "invalid file descriptor",
+                ))
+            }
+        };
+        let unlocked_fd = checkedfd.read();
+        match &*unlocked_fd {
+            Some(File(normalfile_filedesc_obj)) => {
+                match tty_of_inode(normalfile_filedesc_obj.inode) {
+                    Some((tty, TtyEnd::Master)) => Ok(tty),
+                    _ => Err(syscall_error(errno, callname, "fd is not a pty master")),
+                }
+            }
+            Some(_) => Err(syscall_error(errno, callname, "fd is not a pty master")),
+            None => Err(syscall_error(
+                Errno::EBADF,
+                callname,
+                "invalid file descriptor",
+            )),
+        }
+    }
+
+    //hand the slave of the pty master fd to the caller's real user
+    pub fn grantpt_syscall(&self, fd: i32) -> i32 {
+        let tty = match self._pty_master_of_fd(fd, "grantpt", Errno::EINVAL) {
+            Ok(tty) => tty,
+            Err(e) => return e,
+        };
+        if let Some(mut inode) = FS_METADATA.inodetable.get_mut(&tty.slaveinode) {
+            if let Inode::CharDev(ref mut dev_inode) = *inode {
+                dev_inode.uid = self.cred.read().ruid;
+                dev_inode.mode = S_IFCHR as u32 | S_IRUSR | S_IWUSR | S_IWGRP;
+                dev_inode.ctime = interface::timestamp();
+            }
+        }
+        0
+    }
+
+    pub fn unlockpt_syscall(&self, fd: i32) -> i32 {
+        match self._pty_master_of_fd(fd, "unlockpt", Errno::EINVAL) {
+            Ok(tty) => {
+                tty.set_locked(false);
+                0
             }
-            let fdoption = &mut *guardopt.unwrap();
+            Err(e) => e,
+        }
+    }
 
-            let _insertval = fdoption.insert(Pipe(PipeDesc {
-                pipe: pipe.clone(),
-                flags: accflag | actualflags,
-                advlock: interface::RustRfc::new(interface::AdvisoryLock::new()),
-            }));
+    //the path of the slave of the pty master fd, as seen from the cage's root directory
+    pub fn ptsname_syscall(&self, fd: i32, buf: *mut u8, buflen: usize) -> i32 {
+        let tty = match self._pty_master_of_fd(fd, "ptsname", Errno::ENOTTY) {
+            Ok(tty) => tty,
+            Err(e) => return e,
+        };
+        let mountpoint = match MOUNT_TABLE
+            .mounts
+            .read()
+            .iter()
+            .find(|entry| entry.id == tty.ptsmount)
+        {
+            Some(entry) => entry.mountpoint.clone(),
+            None => return syscall_error(Errno::ENOTTY, "ptsname", "the pty has no slave"),
+        };
+        let rootdir = self.rootdir.read().clone();
+        let slavepath = match mountpoint.strip_prefix(&*rootdir) {
+            Ok(rest) => interface::RustPath::new("/").join(rest),
+            Err(_) => mountpoint,
+        }
+        .join(tty.index.to_string());
 
-            match accflag {
-                O_RDONLY => {
-                    pipefd.readfd = fd;
-                }
-                O_WRONLY => {
-                    pipefd.writefd = fd;
-                }
-                _ => panic!("How did you get here."),
-            }
+        let mut bytes: Vec<u8> = slavepath.to_str().unwrap().as_bytes().to_vec();
+        bytes.push(0u8); //Adding a null terminator to the end of the string
+        if buflen < bytes.len() {
+            return syscall_error(Errno::ERANGE, "ptsname", "buf is too small for the name");
         }
+        interface::fill(buf, bytes.len(), &bytes);
+        0
+    }
 
-        0 // success
+    pub fn isatty_syscall(&self, fd: i32) -> i32 {
+        let checkedfd = match self.get_filedescriptor(fd) {
+            Ok(checkedfd) => checkedfd,
+            Err(()) => return syscall_error(Errno::EBADF, "isatty", "invalid file descriptor"),
+        };
+        let unlocked_fd = checkedfd.read();
+        match &*unlocked_fd {
+            //the stream descriptors are the console
+            Some(Stream(_)) => 1,
+            Some(File(normalfile_filedesc_obj))
+                if tty_of_inode(normalfile_filedesc_obj.inode).is_some() =>
+            {
+                1
+            }
+            Some(_) => syscall_error(Errno::ENOTTY, "isatty", "fd does not refer to a terminal"),
+            None => syscall_error(Errno::EBADF, "isatty", "invalid file descriptor"),
+        }
     }
 
     //------------------GETDENTS SYSCALL------------------
@@ -2742,26 +5586,32 @@ impl Cage {
             match filedesc_enum {
                 // only proceed when fd represents a file
                 File(ref mut normalfile_filedesc_obj) => {
-                    let inodeobj = FS_METADATA
-                        .inodetable
-                        .get(&normalfile_filedesc_obj.inode)
-                        .unwrap();
+                    if is_pathonly(normalfile_filedesc_obj.flags) {
+                        return syscall_error(
+                            Errno::EBADF,
+                            "getdents",
+                            "file descriptor was opened with O_PATH",
+                        );
+                    }
+                    let inodeobj = match FS_METADATA.inodetable.get(&normalfile_filedesc_obj.inode)
+                    {
+                        Some(inodeobj) => inodeobj,
+                        None => return corruption_error("getdents", "open file has no inode"),
+                    };
 
                     match &*inodeobj {
                         // only proceed when inode is a dir
                         Inode::Dir(dir_inode_obj) => {
-                            let position = normalfile_filedesc_obj.position;
+                            // the position is the cookie of the next entry to return
+                            let position = normalfile_filedesc_obj.position as u64;
                             let mut bufcount = 0;
                             let mut curr_size;
-                            let mut count = 0;
+                            let mut nextposition = None;
                             let mut temp_len;
 
-                            // iterate over filename-inode pairs in dict
-                            for (filename, inode) in dir_inode_obj
-                                .filename_to_inode_dict
-                                .clone()
-                                .into_iter()
-                                .skip(position)
+                            // iterate over filename-inode pairs in the directory, in order, starting at the position
+                            for (cookie, filename, inode) in
+                                dir_inode_obj.filename_to_inode_dict.iter_from(position)
                             {
                                 // convert filename to a filename vector of u8
                                 let mut vec_filename: Vec<u8> = filename.as_bytes().to_vec();
@@ -2797,13 +5647,12 @@ impl Cage {
                                     },
                                     vec_filename,
                                 ));
-                                count += 1;
+                                nextposition = Some(cookie + 1);
+                            }
+                            // update file position to just past the last entry returned
+                            if let Some(nextposition) = nextposition {
+                                normalfile_filedesc_obj.position = nextposition as usize;
                             }
-                            // update file position
-                            normalfile_filedesc_obj.position = interface::rust_min(
-                                position + count,
-                                dir_inode_obj.filename_to_inode_dict.len(),
-                            );
 
                             interface::pack_dirents(vec, dirp);
                             bufcount as i32 // return the number of bytes written
@@ -2830,7 +5679,14 @@ impl Cage {
     //------------------------------------GETCWD SYSCALL------------------------------------
 
     pub fn getcwd_syscall(&self, buf: *mut u8, bufsize: u32) -> i32 {
-        let mut bytes: Vec<u8> = self.cwd.read().to_str().unwrap().as_bytes().to_vec();
+        //the cwd is reported relative to the cage's root directory
+        let cwd = self.cwd.read().clone();
+        let rootdir = self.rootdir.read().clone();
+        let cwd = match cwd.strip_prefix(&*rootdir) {
+            Ok(rest) => interface::RustPath::new("/").join(rest),
+            Err(_) => (*cwd).clone(),
+        };
+        let mut bytes: Vec<u8> = cwd.to_str().unwrap().as_bytes().to_vec();
         bytes.push(0u8); //Adding a null terminator to the end of the string
         let length = bytes.len();
 
@@ -2924,14 +5780,9 @@ impl Cage {
                 vacant.insert(shmid);
                 let mode = (shmflg & 0x1FF) as u16; // mode is 9 least signficant bits of shmflag, even if we dont really do anything with them
 
-                let segment = new_shm_segment(
-                    key,
-                    size,
-                    self.cageid as u32,
-                    DEFAULT_UID,
-                    DEFAULT_GID,
-                    mode,
-                );
+                let cred = self.cred.read().clone();
+                let segment =
+                    new_shm_segment(key, size, self.cageid as u32, cred.euid, cred.egid, mode);
                 metadata.shmtable.insert(shmid, segment);
             }
         };
@@ -3005,16 +5856,15 @@ impl Cage {
                     }
                     rev_shm.swap_remove(index);
 
+                    // the key was already released when the segment was marked for removal
                     if rm {
-                        let key = segment.key;
                         occupied.remove_entry();
-                        metadata.shmkeyidtable.remove(&key);
                     }
 
                     return shmid; //NaCl relies on this non-posix behavior of returning the shmid on success
                 }
                 interface::RustHashEntry::Vacant(_) => {
-                    panic!("Inode not created for some reason");
+                    return corruption_error("shmdt", "attached segment does not exist");
                 }
             };
         } else {
@@ -3033,17 +5883,28 @@ impl Cage {
 
         if let Some(mut segment) = metadata.shmtable.get_mut(&shmid) {
             match cmd {
-                IPC_STAT => {
-                    *buf.unwrap() = segment.shminfo;
+                IPC_STAT | IPC_SET => {
+                    let buf = match buf {
+                        Some(buf) => buf,
+                        None => {
+                            return syscall_error(Errno::EFAULT, "shmctl", "buf is null");
+                        }
+                    };
+                    if cmd == IPC_STAT {
+                        *buf = segment.shminfo;
+                    } else {
+                        segment.set(buf);
+                    }
                 }
                 IPC_RMID => {
-                    segment.rmid = true;
-                    segment.shminfo.shm_perm.mode |= SHM_DEST as u16;
+                    // a segment can only be marked once, after that its key may belong to another
+                    if !segment.rmid {
+                        metadata.shmkeyidtable.remove(&segment.key);
+                        segment.mark_removed();
+                    }
                     if segment.shminfo.shm_nattch == 0 {
-                        let key = segment.key;
                         drop(segment);
                         metadata.shmtable.remove(&shmid);
-                        metadata.shmkeyidtable.remove(&key);
                     }
                 }
                 _ => {
@@ -3061,6 +5922,284 @@ impl Cage {
         0 //shmctl has succeeded!
     }
 
+    //------------------SEMGET SYSCALL------------------
+
+    pub fn semget_syscall(&self, key: i32, nsems: i32, semflg: i32) -> i32 {
+        let metadata = &SEM_METADATA;
+        let mode = (semflg & 0x1FF) as u16; // mode is 9 least signficant bits of semflg
+
+        let semid = match metadata.semkeyidtable.entry(key) {
+            interface::RustHashEntry::Occupied(occupied) if key != IPC_PRIVATE => {
+                if (IPC_CREAT | IPC_EXCL) == (semflg & (IPC_CREAT | IPC_EXCL)) {
+                    return syscall_error(
+                        Errno::EEXIST,
+                        "semget",
+                        "key already exists and IPC_CREAT and IPC_EXCL were used",
+                    );
+                }
+                let semid = *occupied.get();
+                if let Some(set) = metadata.semtable.get(&semid) {
+                    if nsems as usize > set.nsems {
+                        return syscall_error(
+                            Errno::EINVAL,
+                            "semget",
+                            "nsems is larger than the number of semaphores in the existing set",
+                        );
+                    }
+                }
+                semid
+            }
+            entry => {
+                // a private key always creates a new set, which is only reachable by its id
+                if key != IPC_PRIVATE && 0 == (semflg & IPC_CREAT) {
+                    return syscall_error(
+                        Errno::ENOENT,
+                        "semget",
+                        "tried to use a key that did not exist, and IPC_CREAT was not specified",
+                    );
+                }
+
+                if nsems <= 0 || nsems > SEMMSL {
+                    return syscall_error(
+                        Errno::EINVAL,
+                        "semget",
+                        "nsems is less than 1 or more than SEMMSL",
+                    );
+                }
+
+                let semid = metadata.new_keyid();
+                if key != IPC_PRIVATE {
+                    entry.or_insert(semid);
+                }
+                let cred = self.cred.read().clone();
+                let set = new_semaphore_set(key, nsems as usize, cred.euid, cred.egid, mode);
+                metadata
+                    .semtable
+                    .insert(semid, interface::RustRfc::new(set));
+                semid
+            }
+        };
+        semid // return the semid
+    }
+
+    //------------------SEMOP SYSCALL------------------
+
+    pub fn semop_syscall(&self, semid: i32, sops: &[SembufStruct]) -> i32 {
+        if sops.is_empty() {
+            return syscall_error(Errno::EINVAL, "semop", "nsops was 0");
+        }
+        if sops.len() > SEMOPM {
+            return syscall_error(Errno::EBIG, "semop", "nsops is more than SEMOPM");
+        }
+
+        // clone the set out of the table so no table lock is held while we block
+        let set = match SEM_METADATA.semtable.get(&semid) {
+            Some(set) => set.clone(),
+            None => return syscall_error(Errno::EINVAL, "semop", "Invalid identifier"),
+        };
+
+        if sops.iter().any(|sop| sop.sem_num as usize >= set.nsems) {
+            return syscall_error(
+                Errno::EFBIG,
+                "semop",
+                "sem_num is not less than the number of semaphores in the set",
+            );
+        }
+
+        set.semop(sops, self.cageid, &|| self.signal_interrupt("semop", false))
+    }
+
+    //------------------SEMCTL SYSCALL------------------
+
+    pub fn semctl_syscall(&self, semid: i32, semnum: i32, cmd: i32, arg: i32) -> i32 {
+        let metadata = &SEM_METADATA;
+
+        let set = match metadata.semtable.get(&semid) {
+            Some(set) => set.clone(),
+            None => return syscall_error(Errno::EINVAL, "semctl", "Invalid identifier"),
+        };
+
+        match cmd {
+            IPC_RMID => {
+                metadata.semtable.remove(&semid);
+                if set.key != IPC_PRIVATE {
+                    metadata.semkeyidtable.remove(&set.key);
+                }
+                set.remove();
+                0
+            }
+            GETVAL | SETVAL => {
+                if semnum < 0 || semnum as usize >= set.nsems {
+                    return syscall_error(
+                        Errno::EINVAL,
+                        "semctl",
+                        "semnum is not less than the number of semaphores in the set",
+                    );
+                }
+                if cmd == GETVAL {
+                    return set.getval(semnum as usize);
+                }
+                if arg < 0 || arg > SEMVMX {
+                    return syscall_error(
+                        Errno::ERANGE,
+                        "semctl",
+                        "value is less than 0 or more than SEMVMX",
+                    );
+                }
+                set.setval(semnum as usize, arg, self.cageid);
+                0
+            }
+            _ => syscall_error(
+                Errno::EINVAL,
+                "semctl",
+                "Arguments provided do not match implemented parameters",
+            ),
+        }
+    }
+
+    //------------------MSGGET SYSCALL------------------
+
+    pub fn msgget_syscall(&self, key: i32, msgflg: i32) -> i32 {
+        let metadata = &MSG_METADATA;
+        let mode = (msgflg & 0x1FF) as u16; // mode is 9 least signficant bits of msgflg
+
+        let msqid = match metadata.msgkeyidtable.entry(key) {
+            interface::RustHashEntry::Occupied(occupied) if key != IPC_PRIVATE => {
+                if (IPC_CREAT | IPC_EXCL) == (msgflg & (IPC_CREAT | IPC_EXCL)) {
+                    return syscall_error(
+                        Errno::EEXIST,
+                        "msgget",
+                        "key already exists and IPC_CREAT and IPC_EXCL were used",
+                    );
+                }
+                *occupied.get()
+            }
+            entry => {
+                // a private key always creates a new queue, which is only reachable by its id
+                if key != IPC_PRIVATE && 0 == (msgflg & IPC_CREAT) {
+                    return syscall_error(
+                        Errno::ENOENT,
+                        "msgget",
+                        "tried to use a key that did not exist, and IPC_CREAT was not specified",
+                    );
+                }
+
+                let msqid = metadata.new_keyid();
+                if key != IPC_PRIVATE {
+                    entry.or_insert(msqid);
+                }
+                let cred = self.cred.read().clone();
+                let queue = new_message_queue(key, cred.euid, cred.egid, mode);
+                metadata
+                    .msgtable
+                    .insert(msqid, interface::RustRfc::new(queue));
+                msqid
+            }
+        };
+        msqid // return the msqid
+    }
+
+    //------------------MSGSND SYSCALL------------------
+
+    // msgp holds a long message type followed by the message text
+    pub fn msgsnd_syscall(&self, msqid: i32, msgp: &[u8], msgflg: i32) -> i32 {
+        if msgp.len() < 8 {
+            return syscall_error(Errno::EFAULT, "msgsnd", "msgp can't hold a message type");
+        }
+        let (mtype, mtext) = msgp.split_at(8);
+        if mtext.len() > MSGMAX {
+            return syscall_error(Errno::EINVAL, "msgsnd", "msgsz is more than MSGMAX");
+        }
+
+        let mut typebytes = [0u8; 8];
+        typebytes.copy_from_slice(mtype);
+        let mtype = i64::from_ne_bytes(typebytes);
+        if mtype < 1 {
+            return syscall_error(Errno::EINVAL, "msgsnd", "mtype is less than 1");
+        }
+        let mtext = mtext.to_vec();
+
+        // clone the queue out of the table so no table lock is held while we block
+        let queue = match MSG_METADATA.msgtable.get(&msqid) {
+            Some(queue) => queue.clone(),
+            None => return syscall_error(Errno::EINVAL, "msgsnd", "Invalid identifier"),
+        };
+
+        queue.send(mtype, mtext, msgflg, self.cageid, &|| {
+            self.signal_interrupt("msgsnd", false)
+        })
+    }
+
+    //------------------MSGRCV SYSCALL------------------
+
+    // msgp has room for a long message type followed by the longest message text we may receive
+    pub fn msgrcv_syscall(&self, msqid: i32, msgp: &mut [u8], msgtyp: i64, msgflg: i32) -> i32 {
+        if msgp.len() < 8 {
+            return syscall_error(Errno::EFAULT, "msgrcv", "msgp can't hold a message type");
+        }
+        let msgsz = msgp.len() - 8;
+        let queue = match MSG_METADATA.msgtable.get(&msqid) {
+            Some(queue) => queue.clone(),
+            None => return syscall_error(Errno::EINVAL, "msgrcv", "Invalid identifier"),
+        };
+
+        match queue.receive(msgtyp, msgsz, msgflg, self.cageid, &|| {
+            self.signal_interrupt("msgrcv", false)
+        }) {
+            Ok(message) => {
+                let (mtype, mtext) = msgp.split_at_mut(8);
+                mtype.copy_from_slice(&message.mtype.to_ne_bytes());
+                mtext[..message.mtext.len()].copy_from_slice(&message.mtext);
+                message.mtext.len() as i32
+            }
+            Err(e) => e,
+        }
+    }
+
+    //------------------MSGCTL SYSCALL------------------
+
+    pub fn msgctl_syscall(&self, msqid: i32, cmd: i32, buf: Option<&mut MsqidsStruct>) -> i32 {
+        let metadata = &MSG_METADATA;
+
+        let queue = match metadata.msgtable.get(&msqid) {
+            Some(queue) => queue.clone(),
+            None => return syscall_error(Errno::EINVAL, "msgctl", "Invalid identifier"),
+        };
+
+        match cmd {
+            IPC_STAT => {
+                *buf.unwrap() = queue.stat();
+            }
+            IPC_SET => {
+                let newinfo = buf.unwrap();
+                if newinfo.msg_qbytes > MSGMNB as u64 {
+                    return syscall_error(
+                        Errno::EPERM,
+                        "msgctl",
+                        "msg_qbytes can not be raised above MSGMNB",
+                    );
+                }
+                queue.set(newinfo);
+            }
+            IPC_RMID => {
+                metadata.msgtable.remove(&msqid);
+                if queue.key != IPC_PRIVATE {
+                    metadata.msgkeyidtable.remove(&queue.key);
+                }
+                queue.remove();
+            }
+            _ => {
+                return syscall_error(
+                    Errno::EINVAL,
+                    "msgctl",
+                    "Arguments provided do not match implemented parameters",
+                );
+            }
+        }
+
+        0 //msgctl has succeeded!
+    }
+
     //------------------MUTEX SYSCALLS------------------
 
     pub fn mutex_create_syscall(&self) -> i32 {
@@ -3086,14 +6225,11 @@ impl Cage {
                 mutextable[index] = Some(interface::RustRfc::new(mutex));
                 index as i32
             }
-            Err(_) => match Errno::from_discriminant(interface::get_errno()) {
-                Ok(i) => syscall_error(
-                    i,
-                    "mutex_create",
-                    "The libc call to pthread_mutex_init failed!",
-                ),
-                Err(()) => panic!("Unknown errno value from pthread_mutex_init returned!"),
-            },
+            Err(errno) => interface::host_syscall_error(
+                errno,
+                "mutex_create",
+                "The libc call to pthread_mutex_init failed!",
+            ),
         }
     }
 
@@ -3127,22 +6263,14 @@ impl Cage {
         {
             let clonedmutex = mutextable[mutex_handle as usize].as_ref().unwrap().clone();
             drop(mutextable);
-            let retval = clonedmutex.lock();
-
-            if retval < 0 {
-                match Errno::from_discriminant(interface::get_errno()) {
-                    Ok(i) => {
-                        return syscall_error(
-                            i,
-                            "mutex_lock",
-                            "The libc call to pthread_mutex_lock failed!",
-                        );
-                    }
-                    Err(()) => panic!("Unknown errno value from pthread_mutex_lock returned!"),
-                };
+            match clonedmutex.lock() {
+                Ok(retval) => retval,
+                Err(errno) => interface::host_syscall_error(
+                    errno,
+                    "mutex_lock",
+                    "The libc call to pthread_mutex_lock failed!",
+                ),
             }
-
-            retval
         } else {
             //undefined behavior
             syscall_error(
@@ -3161,22 +6289,14 @@ impl Cage {
         {
             let clonedmutex = mutextable[mutex_handle as usize].as_ref().unwrap().clone();
             drop(mutextable);
-            let retval = clonedmutex.trylock();
-
-            if retval < 0 {
-                match Errno::from_discriminant(interface::get_errno()) {
-                    Ok(i) => {
-                        return syscall_error(
-                            i,
-                            "mutex_trylock",
-                            "The libc call to pthread_mutex_trylock failed!",
-                        );
-                    }
-                    Err(()) => panic!("Unknown errno value from pthread_mutex_trylock returned!"),
-                };
+            match clonedmutex.trylock() {
+                Ok(retval) => retval,
+                Err(errno) => interface::host_syscall_error(
+                    errno,
+                    "mutex_trylock",
+                    "The libc call to pthread_mutex_trylock failed!",
+                ),
             }
-
-            retval
         } else {
             //undefined behavior
             syscall_error(
@@ -3195,22 +6315,14 @@ impl Cage {
         {
             let clonedmutex = mutextable[mutex_handle as usize].as_ref().unwrap().clone();
             drop(mutextable);
-            let retval = clonedmutex.unlock();
-
-            if retval < 0 {
-                match Errno::from_discriminant(interface::get_errno()) {
-                    Ok(i) => {
-                        return syscall_error(
-                            i,
-                            "mutex_unlock",
-                            "The libc call to pthread_mutex_unlock failed!",
-                        );
-                    }
-                    Err(()) => panic!("Unknown errno value from pthread_mutex_unlock returned!"),
-                };
+            match clonedmutex.unlock() {
+                Ok(retval) => retval,
+                Err(errno) => interface::host_syscall_error(
+                    errno,
+                    "mutex_unlock",
+                    "The libc call to pthread_mutex_unlock failed!",
+                ),
             }
-
-            retval
         } else {
             //undefined behavior
             syscall_error(
@@ -3246,14 +6358,11 @@ impl Cage {
                 cvtable[index] = Some(interface::RustRfc::new(cv));
                 index as i32
             }
-            Err(_) => match Errno::from_discriminant(interface::get_errno()) {
-                Ok(i) => syscall_error(
-                    i,
-                    "cond_create",
-                    "The libc call to pthread_cond_init failed!",
-                ),
-                Err(()) => panic!("Unknown errno value from pthread_cond_init returned!"),
-            },
+            Err(errno) => interface::host_syscall_error(
+                errno,
+                "cond_create",
+                "The libc call to pthread_cond_init failed!",
+            ),
         }
     }
 
@@ -3287,22 +6396,14 @@ impl Cage {
         {
             let clonedcv = cvtable[cv_handle as usize].as_ref().unwrap().clone();
             drop(cvtable);
-            let retval = clonedcv.signal();
-
-            if retval < 0 {
-                match Errno::from_discriminant(interface::get_errno()) {
-                    Ok(i) => {
-                        return syscall_error(
-                            i,
-                            "cond_signal",
-                            "The libc call to pthread_cond_signal failed!",
-                        );
-                    }
-                    Err(()) => panic!("Unknown errno value from pthread_cond_signal returned!"),
-                };
+            match clonedcv.signal() {
+                Ok(retval) => retval,
+                Err(errno) => interface::host_syscall_error(
+                    errno,
+                    "cond_signal",
+                    "The libc call to pthread_cond_signal failed!",
+                ),
             }
-
-            retval
         } else {
             //undefined behavior
             syscall_error(
@@ -3321,22 +6422,14 @@ impl Cage {
         {
             let clonedcv = cvtable[cv_handle as usize].as_ref().unwrap().clone();
             drop(cvtable);
-            let retval = clonedcv.broadcast();
-
-            if retval < 0 {
-                match Errno::from_discriminant(interface::get_errno()) {
-                    Ok(i) => {
-                        return syscall_error(
-                            i,
-                            "cond_broadcast",
-                            "The libc call to pthread_cond_broadcast failed!",
-                        );
-                    }
-                    Err(()) => panic!("Unknown errno value from pthread_cond_broadcast returned!"),
-                };
+            match clonedcv.broadcast() {
+                Ok(retval) => retval,
+                Err(errno) => interface::host_syscall_error(
+                    errno,
+                    "cond_broadcast",
+                    "The libc call to pthread_cond_broadcast failed!",
+                ),
             }
-
-            retval
         } else {
             //undefined behavior
             syscall_error(
@@ -3363,33 +6456,27 @@ impl Cage {
             {
                 let clonedmutex = mutextable[mutex_handle as usize].as_ref().unwrap().clone();
                 drop(mutextable);
-                let retval = clonedcv.wait(&*clonedmutex);
-
-                // if the cancel status is set in the cage, we trap around a cancel point
-                // until the individual thread is signaled to cancel itself
-                if self
-                    .cancelstatus
-                    .load(interface::RustAtomicOrdering::Relaxed)
-                {
-                    loop {
-                        interface::cancelpoint(self.cageid);
-                    } // we check cancellation status here without letting the function return
+                //cancelling the cage wakes the wait by broadcasting on the condvar
+                let wakecv = clonedcv.clone();
+                let mut cancel = self.cancel_registry.point_waking(move || {
+                    let _ = wakecv.broadcast();
+                });
+                if let Some(cancelled) = cancel.check("cond_wait") {
+                    return cancelled;
                 }
-
-                if retval < 0 {
-                    match Errno::from_discriminant(interface::get_errno()) {
-                        Ok(i) => {
-                            return syscall_error(
-                                i,
-                                "cond_wait",
-                                "The libc call to pthread_cond_wait failed!",
-                            );
-                        }
-                        Err(()) => panic!("Unknown errno value from pthread_cond_wait returned!"),
-                    };
+                let result = clonedcv.wait(&*clonedmutex);
+                if let Some(cancelled) = cancel.check("cond_wait") {
+                    return cancelled;
                 }
 
-                retval
+                match result {
+                    Ok(retval) => retval,
+                    Err(errno) => interface::host_syscall_error(
+                        errno,
+                        "cond_wait",
+                        "The libc call to pthread_cond_wait failed!",
+                    ),
+                }
             } else {
                 //undefined behavior
                 syscall_error(
@@ -3429,21 +6516,14 @@ impl Cage {
             {
                 let clonedmutex = mutextable[mutex_handle as usize].as_ref().unwrap().clone();
                 drop(mutextable);
-                let retval = clonedcv.timedwait(&*clonedmutex, time);
-                if retval < 0 {
-                    match Errno::from_discriminant(interface::get_errno()) {
-                        Ok(i) => {
-                            return syscall_error(
-                                i,
-                                "cond_wait",
-                                "The libc call to pthread_cond_wait failed!",
-                            );
-                        }
-                        Err(()) => panic!("Unknown errno value from pthread_cond_wait returned!"),
-                    };
+                match clonedcv.timedwait(&*clonedmutex, time) {
+                    Ok(retval) => retval,
+                    Err(errno) => interface::host_syscall_error(
+                        errno,
+                        "cond_wait",
+                        "The libc call to pthread_cond_wait failed!",
+                    ),
                 }
-
-                retval
             } else {
                 //undefined behavior
                 syscall_error(
@@ -3644,4 +6724,75 @@ impl Cage {
         }
         return 0;
     }
+
+    //------------------FUTEX SYSCALL------------------
+
+    // Words in an attached shared memory segment are keyed by the segment so waiters and wakers
+    // in different cages meet on one queue. FUTEX_PRIVATE_FLAG promises the word is never shared,
+    // which skips the lookup.
+    fn futex_key(&self, uaddr: *mut u32, private: bool) -> interface::FutexKey {
+        if !private {
+            let rev_shm = self.rev_shm.lock();
+            if let Some((mapaddr, shmid)) = Self::search_for_addr_in_region(&rev_shm, uaddr as u32)
+            {
+                return interface::FutexKey::Shared(shmid, (uaddr as u32 - mapaddr) as usize);
+            }
+        }
+        interface::FutexKey::Private(self.cageid, uaddr as usize)
+    }
+
+    pub fn futex_syscall(
+        &self,
+        uaddr: *mut u32,
+        futex_op: i32,
+        val: u32,
+        val2: usize,
+        timeout: Option<interface::RustDuration>,
+        uaddr2: Option<*mut u32>,
+    ) -> i32 {
+        if uaddr.is_null() {
+            return syscall_error(Errno::EFAULT, "futex", "uaddr is null");
+        }
+        if uaddr as usize % 4 != 0 {
+            return syscall_error(Errno::EINVAL, "futex", "uaddr is not 4-byte aligned");
+        }
+        let private = futex_op & FUTEX_PRIVATE_FLAG != 0;
+        let key = self.futex_key(uaddr, private);
+
+        match futex_op & FUTEX_CMD_MASK {
+            FUTEX_WAIT => match interface::futex_wait(key, uaddr, val, timeout, &|| {
+                self.signal_interrupt("futex", false).is_some()
+            }) {
+                Ok(()) => 0,
+                Err(Errno::EAGAIN) => syscall_error(
+                    Errno::EAGAIN,
+                    "futex",
+                    "the futex word did not hold the expected value",
+                ),
+                Err(Errno::EINTR) => {
+                    syscall_error(Errno::EINTR, "futex", "interrupted function call")
+                }
+                Err(_) => syscall_error(
+                    Errno::ETIMEDOUT,
+                    "futex",
+                    "the timeout expired before the futex was woken",
+                ),
+            },
+            FUTEX_WAKE => interface::futex_wake(key, val as usize) as i32,
+            FUTEX_REQUEUE => {
+                let uaddr2 = match uaddr2 {
+                    Some(uaddr2) => uaddr2,
+                    None => {
+                        return syscall_error(Errno::EFAULT, "futex", "uaddr2 is null");
+                    }
+                };
+                if uaddr2 as usize % 4 != 0 {
+                    return syscall_error(Errno::EINVAL, "futex", "uaddr2 is not 4-byte aligned");
+                }
+                let key2 = self.futex_key(uaddr2, private);
+                interface::futex_requeue(key, val as usize, key2, val2) as i32
+            }
+            _ => syscall_error(Errno::ENOSYS, "futex", "unsupported futex operation"),
+        }
+    }
 }
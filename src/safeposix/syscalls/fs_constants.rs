@@ -9,7 +9,7 @@ use crate::interface;
 pub const DT_UNKNOWN: u8 = 0;
 
 pub const STARTINGFD: i32 = 0;
-pub const MAXFD: i32 = 1024;
+pub const MAXFD: i32 = 4 * 1024; // the hard RLIMIT_NOFILE, NOFILE_MAX
 pub const STARTINGPIPE: i32 = 0;
 pub const MAXPIPE: i32 = 1024;
 
@@ -17,6 +17,8 @@ pub const ROOTDIRECTORYINODE: usize = 1;
 pub const STREAMINODE: usize = 2;
 
 pub const PIPE_CAPACITY: usize = 65536;
+pub const PIPE_MIN_CAPACITY: usize = 4096; // one page
+pub const PIPE_MAX_CAPACITY: usize = 1048576; // the default of /proc/sys/fs/pipe-max-size
 
 pub const F_OK: u32 = 0;
 pub const X_OK: u32 = 1;
@@ -35,10 +37,51 @@ pub const O_TRUNC: i32 = 0o1000;
 pub const O_APPEND: i32 = 0o2000;
 pub const O_NONBLOCK: i32 = 0o4000;
 // O_NDELAY=O_NONBLOCK
-pub const O_SYNC: i32 = 0o10000;
+pub const O_DSYNC: i32 = 0o10000;
+pub const O_SYNC: i32 = 0o4010000;
 // O_FSYNC=O_SYNC
 pub const O_ASYNC: i32 = 0o20000;
+pub const O_DIRECTORY: i32 = 0o200000;
+pub const O_NOFOLLOW: i32 = 0o400000;
 pub const O_CLOEXEC: i32 = 0o2000000;
+//the status flags fcntl F_SETFL can change
+pub const O_STATUSFLAGS: i32 = O_APPEND | O_ASYNC | O_NONBLOCK;
+pub const O_PATH: i32 = 0o10000000;
+
+//Flags for the *at family of calls
+pub const AT_FDCWD: i32 = -100;
+pub const AT_SYMLINK_NOFOLLOW: i32 = 0x100;
+pub const AT_REMOVEDIR: i32 = 0x200;
+pub const AT_SYMLINK_FOLLOW: i32 = 0x400;
+pub const AT_EMPTY_PATH: i32 = 0x1000;
+//statx sync flags; every file is local, so the call behaves the same for all three
+pub const AT_STATX_SYNC_AS_STAT: i32 = 0x0000;
+pub const AT_STATX_FORCE_SYNC: i32 = 0x2000;
+pub const AT_STATX_DONT_SYNC: i32 = 0x4000;
+pub const AT_STATX_SYNC_TYPE: i32 = 0x6000;
+
+//statx mask bits, naming the fields asked for and those filled in
+pub const STATX_TYPE: u32 = 0x0001;
+pub const STATX_MODE: u32 = 0x0002;
+pub const STATX_NLINK: u32 = 0x0004;
+pub const STATX_UID: u32 = 0x0008;
+pub const STATX_GID: u32 = 0x0010;
+pub const STATX_ATIME: u32 = 0x0020;
+pub const STATX_MTIME: u32 = 0x0040;
+pub const STATX_CTIME: u32 = 0x0080;
+pub const STATX_INO: u32 = 0x0100;
+pub const STATX_SIZE: u32 = 0x0200;
+pub const STATX_BLOCKS: u32 = 0x0400;
+pub const STATX_BASIC_STATS: u32 = 0x07ff;
+pub const STATX_BTIME: u32 = 0x0800;
+pub const STATX_MNT_ID: u32 = 0x1000;
+pub const STATX_ALL: u32 = 0x0fff;
+pub const STATX__RESERVED: u32 = 0x80000000; // asking for this fails with EINVAL
+
+//the only stx_attributes bit the filesystem can say anything about
+pub const STATX_ATTR_MOUNT_ROOT: u64 = 0x2000;
+
+pub const STATX_BLOCK_SIZE: u32 = 4096; // the stx_blksize reported for every file
 
 pub const DEFAULTTIME: u64 = 1323630836;
 
@@ -76,10 +119,79 @@ pub const F_GETSIG: i32 = 11;
 pub const F_SETLEASE: i32 = 1024;
 pub const F_GETLEASE: i32 = 1025;
 pub const F_NOTIFY: i32 = 1026;
+pub const F_DUPFD_CLOEXEC: i32 = 1030;
+pub const F_SETPIPE_SZ: i32 = 1031;
+pub const F_GETPIPE_SZ: i32 = 1032;
+pub const F_ADD_SEALS: i32 = 1033;
+pub const F_GET_SEALS: i32 = 1034;
+
+pub const FD_CLOEXEC: i32 = 1; //descriptor flag for F_GETFD/F_SETFD
+
+//lease types for F_SETLEASE/F_GETLEASE
+pub const F_RDLCK: i32 = 0;
+pub const F_WRLCK: i32 = 1;
+pub const F_UNLCK: i32 = 2;
+pub const LEASE_BREAK_TIME: u64 = 45; // seconds, the default of /proc/sys/fs/lease-break-time
+
+//for memfd_create and file sealing
+pub const MFD_CLOEXEC: u32 = 0x0001;
+pub const MFD_ALLOW_SEALING: u32 = 0x0002;
+pub const MFD_NAME_MAX: usize = 249;
+pub const F_SEAL_SEAL: i32 = 0x0001; // no more seals can be added
+pub const F_SEAL_SHRINK: i32 = 0x0002; // the file can't shrink
+pub const F_SEAL_GROW: i32 = 0x0004; // the file can't grow
+pub const F_SEAL_WRITE: i32 = 0x0008; // the contents can't be written
+pub const F_SEAL_FUTURE_WRITE: i32 = 0x0010; // like F_SEAL_WRITE, but existing writable mappings stay
 
 //Commands for IOCTL
+pub const FIONREAD: u32 = 21531; // bytes that can be read right away
 pub const FIONBIO: u32 = 21537;
 pub const FIOASYNC: u32 = 21586;
+pub const TCGETS: u32 = 0x5401;
+pub const TCSETS: u32 = 0x5402;
+pub const TCSETSW: u32 = 0x5403; // set once output has drained
+pub const TCSETSF: u32 = 0x5404; // set once output has drained, discarding pending input
+pub const TIOCGPGRP: u32 = 0x540F;
+pub const TIOCSPGRP: u32 = 0x5410;
+pub const TIOCGWINSZ: u32 = 0x5413;
+pub const TIOCSWINSZ: u32 = 0x5414;
+pub const TIOCGPTN: u32 = 0x80045430; // index of a pty master's slave
+pub const TIOCSPTLCK: u32 = 0x40045431; // lock or unlock a pty master's slave
+
+//termios c_iflag bits
+pub const INLCR: u32 = 0o100;
+pub const IGNCR: u32 = 0o200;
+pub const ICRNL: u32 = 0o400;
+//termios c_oflag bits
+pub const OPOST: u32 = 0o1;
+pub const ONLCR: u32 = 0o4;
+//termios c_cflag bits
+pub const B38400: u32 = 0o17;
+pub const CS8: u32 = 0o60;
+pub const CREAD: u32 = 0o200;
+//termios c_lflag bits
+pub const ISIG: u32 = 0o1;
+pub const ICANON: u32 = 0o2;
+pub const ECHO: u32 = 0o10;
+pub const ECHOE: u32 = 0o20;
+pub const ECHOK: u32 = 0o40;
+pub const ECHONL: u32 = 0o100;
+pub const IEXTEN: u32 = 0o100000;
+//indices into termios c_cc
+pub const VINTR: usize = 0;
+pub const VQUIT: usize = 1;
+pub const VERASE: usize = 2;
+pub const VKILL: usize = 3;
+pub const VEOF: usize = 4;
+pub const VTIME: usize = 5;
+pub const VMIN: usize = 6;
+pub const VSTART: usize = 8;
+pub const VSTOP: usize = 9;
+pub const VSUSP: usize = 10;
+pub const VEOL: usize = 11;
+pub const NCCS: usize = 19;
+
+pub const TTY_BUFFER_MAX: usize = 4096; // bytes a terminal holds in either direction before writers wait
 
 //File types for open/stat etc.
 pub const S_IFBLK: i32 = 0o60000;
@@ -104,6 +216,22 @@ pub const MAP_ANONYMOUS: i32 = 32;
 pub const MAP_HUGE_SHIFT: i32 = 26;
 pub const MAP_HUGETLB: i32 = 262144;
 
+pub const MS_ASYNC: i32 = 1;
+pub const MS_INVALIDATE: i32 = 2;
+pub const MS_SYNC: i32 = 4;
+
+//for mount/umount syscalls
+pub const MS_RDONLY: u64 = 1;
+pub const MS_NOSUID: u64 = 2;
+pub const MS_NODEV: u64 = 4;
+pub const MS_NOEXEC: u64 = 8;
+pub const MS_SYNCHRONOUS: u64 = 16;
+pub const MS_REMOUNT: u64 = 32;
+pub const MS_BIND: u64 = 4096;
+pub const MS_PERMFLAGS: u64 = MS_RDONLY | MS_NOSUID | MS_NODEV | MS_NOEXEC | MS_SYNCHRONOUS;
+pub const TMPFS_MAGIC: u64 = 0x01021994;
+pub const TMPFS_DEFAULT_SIZE: usize = 1 << 30; // 1 GiB unless a size= option is given
+
 pub const PROT_NONE: i32 = 0;
 pub const PROT_READ: i32 = 1;
 pub const PROT_WRITE: i32 = 2;
@@ -116,6 +244,7 @@ pub const SEEK_END: i32 = 2;
 pub const IPC_PRIVATE: i32 = 0o0;
 pub const IPC_CREAT: i32 = 0o1000;
 pub const IPC_EXCL: i32 = 0o2000;
+pub const IPC_NOWAIT: i32 = 0o4000;
 
 pub const IPC_RMID: i32 = 0;
 pub const IPC_SET: i32 = 1;
@@ -140,8 +269,65 @@ pub const SHMSEG: u32 = SHMMNI;
 
 pub const SEM_VALUE_MAX: u32 = 2147483647;
 
+//for System V semaphore sets
+pub const SEM_UNDO: i32 = 0x1000;
+pub const GETPID: i32 = 11;
+pub const GETVAL: i32 = 12;
+pub const GETALL: i32 = 13;
+pub const GETNCNT: i32 = 14;
+pub const GETZCNT: i32 = 15;
+pub const SETVAL: i32 = 16;
+pub const SETALL: i32 = 17;
+pub const SEMMSL: i32 = 32000; // most semaphores in a set
+pub const SEMOPM: usize = 500; // most operations in a single semop
+pub const SEMVMX: i32 = 32767; // largest semaphore value
+
+//for System V message queues
+pub const MSG_NOERROR: i32 = 0o10000;
+pub const MSG_EXCEPT: i32 = 0o20000;
+
+pub const MSGMAX: usize = 8192; // largest single message
+pub const MSGMNB: usize = 16384; // default byte limit of a queue
+
+//for POSIX message queues
+pub const MQ_PRIO_MAX: u32 = 32768;
+pub const MQ_NAME_MAX: usize = 255;
+pub const MQ_DEFAULT_MAXMSG: usize = 10;
+pub const MQ_DEFAULT_MSGSIZE: usize = 8192;
+pub const MQ_MAXMSG_MAX: usize = 65536;
+pub const MQ_MSGSIZE_MAX: usize = 16777216;
+
+//POSIX shared memory objects live in the tmpfs mounted here
+pub const SHM_OPEN_DIR: &str = "/dev/shm";
+
+//for inotify syscalls
+pub const IN_ACCESS: u32 = 0x00000001;
+pub const IN_MODIFY: u32 = 0x00000002;
+pub const IN_ATTRIB: u32 = 0x00000004;
+pub const IN_CLOSE_WRITE: u32 = 0x00000008;
+pub const IN_CLOSE_NOWRITE: u32 = 0x00000010;
+pub const IN_OPEN: u32 = 0x00000020;
+pub const IN_MOVED_FROM: u32 = 0x00000040;
+pub const IN_MOVED_TO: u32 = 0x00000080;
+pub const IN_CREATE: u32 = 0x00000100;
+pub const IN_DELETE: u32 = 0x00000200;
+pub const IN_DELETE_SELF: u32 = 0x00000400;
+pub const IN_MOVE_SELF: u32 = 0x00000800;
+pub const IN_ALL_EVENTS: u32 = 0x00000fff;
+pub const IN_IGNORED: u32 = 0x00008000;
+pub const IN_ONLYDIR: u32 = 0x01000000;
+pub const IN_MASK_ADD: u32 = 0x20000000;
+pub const IN_ISDIR: u32 = 0x40000000;
+pub const IN_ONESHOT: u32 = 0x80000000;
+pub const IN_NONBLOCK: i32 = O_NONBLOCK;
+pub const IN_CLOEXEC: i32 = O_CLOEXEC;
+pub const INOTIFY_EVENT_HEADER_SIZE: usize = 16; // wd, mask, cookie and len
+pub const INOTIFY_MAX_QUEUED_EVENTS: usize = 16384;
+
 //device info for char files
-#[derive(interface::SerdeSerialize, interface::SerdeDeserialize, PartialEq, Eq, Debug)]
+#[derive(
+    interface::SerdeSerialize, interface::SerdeDeserialize, PartialEq, Eq, Hash, Clone, Copy, Debug,
+)]
 pub struct DevNo {
     pub major: u32,
     pub minor: u32,
@@ -150,6 +336,12 @@ pub const NULLDEVNO: DevNo = DevNo { major: 1, minor: 3 };
 pub const ZERODEVNO: DevNo = DevNo { major: 1, minor: 5 };
 pub const RANDOMDEVNO: DevNo = DevNo { major: 1, minor: 8 };
 pub const URANDOMDEVNO: DevNo = DevNo { major: 1, minor: 9 };
+pub const FULLDEVNO: DevNo = DevNo { major: 1, minor: 7 };
+pub const TTYDEVNO: DevNo = DevNo { major: 5, minor: 0 };
+//ptys are numbered by minor, slaves appear as /dev/pts/<minor> while masters never have a name
+pub const PTY_MASTER_MAJOR: u32 = 128;
+pub const PTY_SLAVE_MAJOR: u32 = 136;
+pub const PTY_MAX: u32 = 4096;
 
 pub const FILEDATAPREFIX: &str = "linddata.";
 
@@ -171,6 +363,17 @@ pub fn is_wronly(flags: i32) -> bool {
 pub fn is_rdonly(flags: i32) -> bool {
     (flags & O_RDWRFLAGS) == O_RDONLY
 }
+//a descriptor opened with O_PATH only locates a file, it can't be used to access it
+pub fn is_pathonly(flags: i32) -> bool {
+    (flags & O_PATH) == O_PATH
+}
+//O_SYNC includes the O_DSYNC bit, so check it first
+pub fn is_sync(flags: i32) -> bool {
+    (flags & O_SYNC) == O_SYNC
+}
+pub fn is_dsync(flags: i32) -> bool {
+    (flags & O_DSYNC) == O_DSYNC
+}
 
 //the same as the glibc makedev
 pub fn makedev(dev: &DevNo) -> u64 {
@@ -8,6 +8,7 @@ use super::sys_constants::*;
 use crate::interface;
 use crate::interface::errnos::{syscall_error, Errno};
 use crate::safeposix::cage::{FileDescriptor::*, *};
+use crate::safeposix::devices::file_poll;
 use crate::safeposix::filesystem::*;
 use crate::safeposix::net::*;
 
@@ -24,11 +25,12 @@ impl Cage {
         let flags = if nonblocking { O_NONBLOCK } else { 0 } | if cloexec { O_CLOEXEC } else { 0 };
 
         let sockfd = SocketDesc {
-            flags: flags,
+            flags: flags & O_CLOEXEC,
+            statusflags: StatusFlags::new(flags),
             domain: domain,
             rawfd: -1, // RawFD set in bind for inet, or stays at -1 for others
             handle: interface::RustRfc::new(interface::RustLock::new(Self::mksockhandle(
-                domain, socktype, protocol, conn, flags,
+                domain, socktype, protocol, conn, 0,
             ))),
             advlock: interface::RustRfc::new(interface::AdvisoryLock::new()),
         }; //currently on failure to create handle we create successfully but it's corrupted, change?
@@ -57,19 +59,9 @@ impl Cage {
                 Err(e) => return e,
             };
 
-            let bindret = self.bind_inner_socket(sockhandle, &localaddr, true);
-
-            if bindret < 0 {
-                match Errno::from_discriminant(interface::get_errno()) {
-                    Ok(i) => {
-                        return syscall_error(
-                            i,
-                            "recvfrom",
-                            "syscall error from attempting to bind within recvfrom",
-                        );
-                    }
-                    Err(()) => panic!("Unknown errno value from socket recvfrom returned!"),
-                };
+            //the error from the bind itself, with the errno the bind captured
+            if let Err(bindret) = self.bind_inner_socket(sockhandle, &localaddr, true) {
+                return bindret;
             }
         }
         0
@@ -147,6 +139,21 @@ impl Cage {
                 }
             }
 
+            SOCK_RAW => {
+                if !self.cred.read().capable(CAP_NET_RAW) {
+                    return syscall_error(
+                        Errno::EPERM,
+                        "socket",
+                        "creating a raw socket takes CAP_NET_RAW",
+                    );
+                }
+                return syscall_error(
+                    Errno::EOPNOTSUPP,
+                    "socket",
+                    "raw sockets are not implemented",
+                );
+            }
+
             _ => {
                 return syscall_error(
                     Errno::EOPNOTSUPP,
@@ -167,8 +174,7 @@ impl Cage {
                 if sockhandle.socket_options & (1 << reuse) == 0 {
                     continue;
                 }
-                let sockret = thissock.setsockopt(SOL_SOCKET, reuse, 1);
-                if sockret < 0 {
+                if thissock.setsockopt(SOL_SOCKET, reuse, 1).is_err() {
                     panic!("Cannot handle failure in setsockopt on socket creation");
                 }
             }
@@ -187,21 +193,35 @@ impl Cage {
         sockhandle: &mut SocketHandle,
         localaddr: &interface::GenSockaddr,
         prereserved: bool,
-    ) -> i32 {
+    ) -> Result<i32, i32> {
         if localaddr.get_family() != sockhandle.domain as u16 {
-            return syscall_error(
+            return Err(syscall_error(
                 Errno::EINVAL,
                 "bind",
                 "An address with an invalid family for the given domain was specified",
-            );
+            ));
         }
 
         if sockhandle.localaddr.is_some() {
-            return syscall_error(
+            return Err(syscall_error(
                 Errno::EINVAL,
                 "bind",
                 "The socket is already bound to an address",
-            );
+            ));
+        }
+
+        //ports are stored in network endian order
+        if sockhandle.domain != AF_UNIX
+            && !prereserved
+            && localaddr.port() != 0
+            && u16::from_be(localaddr.port()) < PROT_SOCK
+            && !self.cred.read().capable(CAP_NET_BIND_SERVICE)
+        {
+            return Err(syscall_error(
+                Errno::EACCES,
+                "bind",
+                "binding a port below 1024 takes CAP_NET_BIND_SERVICE",
+            ));
         }
 
         let mut newsockaddr = localaddr.clone();
@@ -212,11 +232,18 @@ impl Cage {
                 self.bind_inner_socket_inet(sockhandle, &mut newsockaddr, prereserved)
             }
             _ => {
-                return syscall_error(Errno::EINVAL, "bind", "Unsupported domain provided");
+                return Err(syscall_error(
+                    Errno::EINVAL,
+                    "bind",
+                    "Unsupported domain provided",
+                ));
             }
         };
 
-        sockhandle.localaddr = Some(newsockaddr);
+        //a unix bind that failed claimed no path, so the socket can still be bound later
+        if res.is_ok() || sockhandle.domain != AF_UNIX {
+            sockhandle.localaddr = Some(newsockaddr);
+        }
 
         res
     }
@@ -225,19 +252,26 @@ impl Cage {
         &self,
         sockhandle: &mut SocketHandle,
         newsockaddr: &mut interface::GenSockaddr,
-    ) -> i32 {
+    ) -> Result<i32, i32> {
         // Unix Sockets
         let path = newsockaddr.path();
         //Check that path is not empty
         if path.len() == 0 {
-            return syscall_error(Errno::ENOENT, "bind", "given path was null");
+            return Err(syscall_error(Errno::ENOENT, "bind", "given path was null"));
         }
         let truepath = normpath(convpath(path), self);
+        if path_is_readonly(&truepath) {
+            return Err(syscall_error(
+                Errno::EROFS,
+                "bind",
+                "path is on a read-only filesystem",
+            ));
+        }
 
         match metawalkandparent(truepath.as_path()) {
             //If neither the file nor parent exists
             (None, None) => {
-                return syscall_error(Errno::ENOENT, "bind", "a directory component in pathname does not exist or is a dangling symbolic link");
+                return Err(syscall_error(Errno::ENOENT, "bind", "a directory component in pathname does not exist or is a dangling symbolic link"));
             }
             //If the file doesn't exist but the parent does
             (None, Some(pardirinode)) => {
@@ -256,15 +290,17 @@ impl Cage {
                     let effective_mode = S_IFSOCK as u32 | mode;
 
                     let time = interface::timestamp(); //We do a real timestamp now
+                    let cred = self.cred.read().clone();
                     newinode = Inode::Socket(SocketInode {
                         size: 0,
-                        uid: DEFAULT_UID,
-                        gid: DEFAULT_GID,
+                        uid: cred.euid,
+                        gid: cred.egid,
                         mode: effective_mode,
                         linkcount: 1,
                         refcount: 1,
                         atime: time,
                         ctime: time,
+                        btime: time,
                         mtime: time,
                     });
 
@@ -272,28 +308,35 @@ impl Cage {
                         .insert(filename.clone(), newinodenum);
                     dir.linkcount += 1;
                 } else {
-                    return syscall_error(
+                    return Err(syscall_error(
                         Errno::ENOTDIR,
                         "bind",
                         "unix domain socket path made socket address child of non-directory file",
-                    );
+                    ));
                 }
                 sockhandle.unix_info = Some(UnixSocketInfo {
                     mode: S_IFSOCK | 0o666,
                     sendpipe: None,
                     receivepipe: None,
                     inode: newinodenum,
+                    peercred: None,
+                    boundpath: Some(truepath.clone()),
                 });
 
-                NET_METADATA.domsock_paths.insert(truepath);
+                NET_METADATA.domsock_paths.insert(truepath, self.ucred());
                 FS_METADATA.inodetable.insert(newinodenum, newinode);
+                mount_claim_inode(pardirinode, newinodenum);
             }
             (Some(_inodenum), ..) => {
-                return syscall_error(Errno::EADDRINUSE, "bind", "Address already in use");
+                return Err(syscall_error(
+                    Errno::EADDRINUSE,
+                    "bind",
+                    "Address already in use",
+                ));
             }
         }
 
-        0
+        Ok(0)
     }
 
     fn bind_inner_socket_inet(
@@ -301,40 +344,34 @@ impl Cage {
         sockhandle: &mut SocketHandle,
         newsockaddr: &mut interface::GenSockaddr,
         prereserved: bool,
-    ) -> i32 {
+    ) -> Result<i32, i32> {
         // INET Sockets
         let intent_to_rebind = sockhandle.socket_options & (1 << SO_REUSEPORT) != 0;
+        let reuseaddr = sockhandle.socket_options & (1 << SO_REUSEADDR) != 0;
         Self::force_innersocket(sockhandle);
 
         let newlocalport = if prereserved {
             newsockaddr.port()
         } else {
-            let localout = NET_METADATA._reserve_localport(
+            NET_METADATA._reserve_localport(
                 newsockaddr.addr(),
                 newsockaddr.port(),
                 sockhandle.protocol,
                 sockhandle.domain,
                 intent_to_rebind,
-            );
-            if let Err(errnum) = localout {
-                return errnum;
-            }
-            localout.unwrap()
+                reuseaddr,
+            )?
         };
 
         newsockaddr.set_port(newlocalport);
-        let bindret = sockhandle.innersocket.as_ref().unwrap().bind(&newsockaddr);
-
-        if bindret < 0 {
-            match Errno::from_discriminant(interface::get_errno()) {
-                Ok(i) => {
-                    return syscall_error(i, "bind", "The libc call to bind failed!");
-                }
-                Err(()) => panic!("Unknown errno value from socket bind returned!"),
-            };
-        }
-
-        0
+        sockhandle
+            .innersocket
+            .as_ref()
+            .unwrap()
+            .bind(&newsockaddr)
+            .map_err(|errno| {
+                interface::host_syscall_error(errno, "bind", "The libc call to bind failed!")
+            })
     }
 
     pub fn bind_inner(
@@ -350,7 +387,15 @@ impl Cage {
                 Socket(ref mut sockfdobj) => {
                     let sock_tmp = sockfdobj.handle.clone();
                     let mut sockhandle = sock_tmp.write();
-                    self.bind_inner_socket(&mut *sockhandle, localaddr, prereserved)
+                    let bindret = self.bind_inner_socket(&mut *sockhandle, localaddr, prereserved);
+                    // a bound inet socket can receive, so set the rawfd for select and poll
+                    if bindret.is_ok() && sockhandle.domain != AF_UNIX {
+                        sockfdobj.rawfd = sockhandle.innersocket.as_ref().unwrap().raw_sys_fd;
+                    }
+                    match bindret {
+                        Ok(_) => 0,
+                        Err(e) => e,
+                    }
                 }
                 _ => syscall_error(
                     Errno::ENOTSOCK,
@@ -406,6 +451,7 @@ impl Cage {
                             sockhandle.protocol,
                             sockhandle.domain,
                             rebindability,
+                            false,
                         ) {
                             Ok(portnum) => portnum,
                             Err(errnum) => return Err(errnum),
@@ -424,6 +470,7 @@ impl Cage {
                             sockhandle.protocol,
                             sockhandle.domain,
                             rebindability,
+                            false,
                         ) {
                             Ok(portnum) => portnum,
                             Err(errnum) => return Err(errnum),
@@ -496,6 +543,7 @@ impl Cage {
         //for UDP, just set the addresses and return
         //we don't need to check connection state for UDP, it's connectionless!
         sockhandle.remoteaddr = Some(remoteaddr.clone());
+        sockhandle.remote_hostaddr = Some(interface::HostSockaddr::new(remoteaddr));
         match sockhandle.localaddr {
             Some(_) => return 0,
             None => {
@@ -511,7 +559,10 @@ impl Cage {
                 let bindret = self.bind_inner_socket(&mut *sockhandle, &localaddr, true);
                 // udp now connected so lets set rawfd for select
                 sockfdobj.rawfd = sockhandle.innersocket.as_ref().unwrap().raw_sys_fd;
-                return bindret;
+                return match bindret {
+                    Ok(_) => 0,
+                    Err(e) => e,
+                };
             }
         };
     }
@@ -531,9 +582,13 @@ impl Cage {
             );
         }
 
+        //a blocking connect waits no longer than SO_SNDTIMEO, if set
+        let timeout = sockhandle.sndtimeo;
         match sockhandle.domain {
-            AF_UNIX => self.connect_tcp_unix(&mut *sockhandle, sockfdobj, remoteaddr),
-            AF_INET | AF_INET6 => self.connect_tcp_inet(&mut *sockhandle, sockfdobj, remoteaddr),
+            AF_UNIX => self.connect_tcp_unix(&mut *sockhandle, sockfdobj, remoteaddr, timeout),
+            AF_INET | AF_INET6 => {
+                self.connect_tcp_inet(&mut *sockhandle, sockfdobj, remoteaddr, timeout)
+            }
             _ => return syscall_error(Errno::EINVAL, "connect", "Unsupported domain provided"),
         }
     }
@@ -543,28 +598,42 @@ impl Cage {
         sockhandle: &mut SocketHandle,
         sockfdobj: &mut SocketDesc,
         remoteaddr: &interface::GenSockaddr,
+        timeout: Option<interface::RustDuration>,
     ) -> i32 {
         // TCP domain socket logic
         if let None = sockhandle.localaddr {
             let localaddr = Self::assign_new_addr_unix(&sockhandle);
-            self.bind_inner_socket(&mut *sockhandle, &localaddr, false);
+            let _bindret = self.bind_inner_socket(&mut *sockhandle, &localaddr, false);
         }
         let remotepathbuf = normpath(convpath(remoteaddr.path()), self);
 
         // try to get and hold reference to the key-value pair, so other process can't alter it
         let path_ref = NET_METADATA.domsock_paths.get(&remotepathbuf);
-        // if the entry doesn't exist, return an error.
-        if path_ref.is_none() {
-            return syscall_error(Errno::ENOENT, "connect", "not valid unix domain path");
+        // a path that is there but that no open socket is bound to refuses the connection
+        let inodenum = match metawalk(remotepathbuf.as_path()) {
+            Some(inodenum) => inodenum,
+            None => return syscall_error(Errno::ENOENT, "connect", "not valid unix domain path"),
+        };
+        let bound = match FS_METADATA.inodetable.get(&inodenum).as_deref() {
+            Some(Inode::Socket(sock)) => sock.refcount > 0,
+            _ => false,
+        };
+        if path_ref.is_none() || !bound {
+            return syscall_error(
+                Errno::ECONNREFUSED,
+                "connect",
+                "no socket is bound to the unix domain path",
+            );
         }
 
-        let (pipe1, pipe2) = create_unix_sockpipes();
+        let (pipe1, pipe2) = create_unix_sockpipes(&sockhandle);
 
         sockhandle.remoteaddr = Some(remoteaddr.clone());
         sockhandle.unix_info.as_mut().unwrap().sendpipe = Some(pipe1.clone());
         sockhandle.unix_info.as_mut().unwrap().receivepipe = Some(pipe2.clone());
+        sockhandle.unix_info.as_mut().unwrap().peercred = Some(*path_ref.as_ref().unwrap().value());
 
-        let connvar = if sockfdobj.flags & O_NONBLOCK == 0 {
+        let connvar = if sockfdobj.statusflags.get() & O_NONBLOCK == 0 {
             Some(interface::RustRfc::new(ConnCondVar::new()))
         } else {
             None
@@ -579,13 +648,45 @@ impl Cage {
             receive_pipe: Some(pipe1.clone()).unwrap(),
             send_pipe: Some(pipe2.clone()).unwrap(),
             cond_var: connvar.clone(),
+            peercred: self.ucred(),
         };
         NET_METADATA
             .domsock_accept_table
-            .insert(remotepathbuf, entry);
+            .insert(remotepathbuf.clone(), entry);
+        if let Some(acceptcv) = NET_METADATA.domsock_accept_waits.get(&remotepathbuf) {
+            acceptcv.notify();
+        }
+        interface::notify_readiness();
         sockhandle.state = ConnState::CONNECTED;
-        if sockfdobj.flags & O_NONBLOCK == 0 {
-            connvar.unwrap().wait();
+        if let Some(connvar) = connvar {
+            let wakevar = connvar.clone();
+            let mut cancel = self.cancel_registry.point_waking(move || wakevar.wake());
+            let start_time = interface::starttimer();
+            //a signal or the timeout running out leaves the connection to be accepted later, as
+            //POSIX has it
+            while !connvar.wait_for(interface::SIGNAL_CHECK_INTERVAL) {
+                if let Some(cancelled) = cancel.check("connect") {
+                    return cancelled;
+                }
+                if let Some(interrupted) = self.signal_interrupt("connect", false) {
+                    return interrupted;
+                }
+                if timeout.map_or(false, |timeout| interface::readtimer(start_time) >= timeout) {
+                    return syscall_error(
+                        Errno::EINPROGRESS,
+                        "connect",
+                        "the connection was not accepted before the send timeout",
+                    );
+                }
+            }
+            if connvar.refused() {
+                sockhandle.state = ConnState::NOTCONNECTED;
+                return syscall_error(
+                    Errno::ECONNREFUSED,
+                    "connect",
+                    "the listener was closed before accepting the connection",
+                );
+            }
         }
         return 0;
     }
@@ -595,6 +696,7 @@ impl Cage {
         sockhandle: &mut SocketHandle,
         sockfdobj: &mut SocketDesc,
         remoteaddr: &interface::GenSockaddr,
+        timeout: Option<interface::RustDuration>,
     ) -> i32 {
         // TCP inet domain logic
         //for TCP, actually create the internal socket object and connect it
@@ -620,40 +722,51 @@ impl Cage {
                 Err(e) => return e,
             };
             let bindret = sockhandle.innersocket.as_ref().unwrap().bind(&localaddr);
-            if bindret < 0 {
-                sockhandle.localaddr = Some(localaddr);
-                match Errno::from_discriminant(interface::get_errno()) {
-                    Ok(i) => {
-                        return syscall_error(
-                            i,
-                            "connect",
-                            "The libc call to bind within connect failed",
-                        );
-                    }
-                    Err(()) => {
-                        panic!("Unknown errno value from socket bind within connect returned!")
-                    }
-                };
+            //the port is reserved either way, and the peer will see this address once we connect
+            sockhandle.localaddr = Some(localaddr);
+            if let Err(errno) = bindret {
+                return interface::host_syscall_error(
+                    errno,
+                    "connect",
+                    "The libc call to bind within connect failed",
+                );
             }
         }
 
         let mut inprogress = false;
-        let connectret = sockhandle
-            .innersocket
-            .as_ref()
-            .unwrap()
-            .connect(&remoteclone);
-        if connectret < 0 {
-            match Errno::from_discriminant(interface::get_errno()) {
-                Ok(i) => {
-                    if i == Errno::EINPROGRESS {
-                        inprogress = true;
-                    } else {
-                        return syscall_error(i, "connect", "The libc call to connect failed!");
-                    };
+        let innersocket = sockhandle.innersocket.as_ref().unwrap();
+        //a blocking connect is started nonblocking too and waited for here, so that a signal
+        //can cut the wait short. The connection then carries on as a nonblocking one would
+        let mut connectret = innersocket.connect_nonblocking(&remoteclone);
+        let mut interrupted = None;
+        if sockfdobj.statusflags.get() & O_NONBLOCK == 0 {
+            let mut cancel = self.cancel_registry.point();
+            let start_time = interface::starttimer();
+            while connectret == Err(libc::EINPROGRESS) {
+                if innersocket.wait_writable(interface::SIGNAL_CHECK_INTERVAL) {
+                    connectret = innersocket.connect_result();
+                    break;
                 }
-                Err(()) => panic!("Unknown errno value from socket connect returned!"),
-            };
+                //once the timeout runs out, the connection carries on as a nonblocking one would
+                if timeout.map_or(false, |timeout| interface::readtimer(start_time) >= timeout) {
+                    break;
+                }
+                if let Some(cancelled) = cancel.check("connect") {
+                    return cancelled;
+                }
+                interrupted = self.signal_interrupt("connect", false);
+                if interrupted.is_some() {
+                    break;
+                }
+            }
+        }
+        if let Err(errno) = connectret {
+            let i = Errno::from_host(errno);
+            if i == Errno::EINPROGRESS {
+                inprogress = true;
+            } else {
+                return syscall_error(i, "connect", "The libc call to connect failed!");
+            }
         }
 
         sockhandle.state = ConnState::CONNECTED;
@@ -663,6 +776,9 @@ impl Cage {
         sockfdobj.rawfd = sockhandle.innersocket.as_ref().unwrap().raw_sys_fd;
         if inprogress {
             sockhandle.state = ConnState::INPROGRESS;
+            if let Some(interrupted) = interrupted {
+                return interrupted;
+            }
             return syscall_error(
                 Errno::EINPROGRESS,
                 "connect",
@@ -685,14 +801,19 @@ impl Cage {
             socket_options: socket_options,
             tcp_options: 0,
             state: conn,
+            shut_rd: false,
+            shut_wr: false,
             protocol: protocol,
             domain: domain,
-            last_peek: interface::RustDeque::new(),
+            last_peek: interface::RustRfc::new(interface::Mutex::new(interface::RustDeque::new())),
             localaddr: None,
+            shares_listener_port: false,
             remoteaddr: None,
+            remote_hostaddr: None,
             unix_info: None,
             socktype: socktype,
             sndbuf: 131070, //buffersize, which is only used by getsockopt
+            sndtimeo: None,
             rcvbuf: 262140, //buffersize, which is only used by getsockopt
             errno: 0,
         }
@@ -712,12 +833,12 @@ impl Cage {
         }
 
         let checkedfd = self.get_filedescriptor(fd).unwrap();
-        let mut unlocked_fd = checkedfd.write();
-        if let Some(filedesc_enum) = &mut *unlocked_fd {
+        let unlocked_fd = checkedfd.read();
+        if let Some(filedesc_enum) = &*unlocked_fd {
             match filedesc_enum {
-                Socket(ref mut sockfdobj) => {
-                    let sock_tmp = sockfdobj.handle.clone();
-                    let mut sockhandle = sock_tmp.write();
+                Socket(sockfdobj) => {
+                    //the handle is only locked for writing if a send has to bind the socket first
+                    let mut sockhandle = sockfdobj.handle.read();
 
                     // check if this is a domain socket
                     if sockhandle.domain == AF_UNIX {
@@ -762,11 +883,17 @@ impl Cage {
                         }
 
                         IPPROTO_UDP => {
-                            let tmpdest = *dest_addr;
-                            let ibindret =
-                                self._implicit_bind(&mut *sockhandle, tmpdest.get_family() as i32);
-                            if ibindret < 0 {
-                                return ibindret;
+                            if sockhandle.localaddr.is_none() {
+                                drop(sockhandle);
+                                let mut writehandle = sockfdobj.handle.write();
+                                let ibindret = self._implicit_bind(
+                                    &mut *writehandle,
+                                    dest_addr.get_family() as i32,
+                                );
+                                if ibindret < 0 {
+                                    return ibindret;
+                                }
+                                sockhandle = interface::RustLockWriteGuard::downgrade(writehandle);
                             }
 
                             //unwrap ok because we implicit_bind_right before
@@ -774,25 +901,20 @@ impl Cage {
                                 buf,
                                 buflen,
                                 Some(dest_addr),
+                                0,
                             );
 
                             //we don't mind if this fails for now and we will just get the error
                             //from calling sendto
-                            if sockret < 0 {
-                                match Errno::from_discriminant(interface::get_errno()) {
-                                    Ok(i) => {
-                                        return syscall_error(
-                                            i,
-                                            "sendto",
-                                            "The libc call to sendto failed!",
-                                        );
-                                    }
-                                    Err(()) => {
-                                        panic!("Unknown errno value from socket sendto returned!")
-                                    }
-                                };
-                            } else {
-                                return sockret;
+                            match sockret {
+                                Ok(sockret) => return sockret,
+                                Err(errno) => {
+                                    return interface::host_syscall_error(
+                                        errno,
+                                        "sendto",
+                                        "The libc call to sendto failed!",
+                                    );
+                                }
                             }
                         }
 
@@ -819,232 +941,278 @@ impl Cage {
         }
     }
 
-    pub fn send_syscall(&self, fd: i32, buf: *const u8, buflen: usize, flags: i32) -> i32 {
-        let checkedfd = self.get_filedescriptor(fd).unwrap();
-        let mut unlocked_fd = checkedfd.write();
-        if let Some(filedesc_enum) = &mut *unlocked_fd {
-            match filedesc_enum {
-                Socket(ref mut sockfdobj) => {
-                    let sock_tmp = sockfdobj.handle.clone();
-                    let sockhandle = sock_tmp.write();
+    //The socket fd refers to and the status flags of its open file description. The descriptor's
+    //slot is only locked while these are looked up, so that a send or recv that blocks holds
+    //nothing but the SocketHandle lock, and dup'd descriptors or a poll of fd can still get at it.
+    fn _socket_of_fd(
+        &self,
+        fd: i32,
+        syscallname: &str,
+    ) -> Result<
+        (
+            interface::RustRfc<interface::RustLock<SocketHandle>>,
+            interface::RustRfc<StatusFlags>,
+        ),
+        i32,
+    > {
+        let checkedfd = match self.get_filedescriptor(fd) {
+            Ok(checkedfd) => checkedfd,
+            Err(()) => {
+                return Err(syscall_error(
+                    Errno::EBADF,
+                    syscallname,
+                    "invalid file descriptor",
+                ))
+            }
+        };
+        let unlocked_fd = checkedfd.read();
+        match &*unlocked_fd {
+            Some(Socket(sockfdobj)) => {
+                Ok((sockfdobj.handle.clone(), sockfdobj.statusflags.clone()))
+            }
+            Some(_) => Err(syscall_error(
+                Errno::ENOTSOCK,
+                syscallname,
+                "file descriptor refers to something other than a socket",
+            )),
+            None => Err(syscall_error(
+                Errno::EBADF,
+                syscallname,
+                "invalid file descriptor",
+            )),
+        }
+    }
 
-                    if (flags & !MSG_NOSIGNAL) != 0 {
-                        return syscall_error(
-                            Errno::EOPNOTSUPP,
-                            "send",
-                            "The flags are not understood!",
-                        );
-                    }
+    //Send buflen bytes on a stream socket a chunk at a time through send_some, which is given how
+    //much has gone so far and returns how much more went or why none did. A nonblocking socket
+    //gives back a short count as soon as there's no room for the rest, and a blocking one keeps on
+    //until everything is sent, or until the peer goes away or a signal arrives, in which case what
+    //was sent so far is returned if there was any. A broken connection raises SIGPIPE unless the
+    //send asked for MSG_NOSIGNAL.
+    fn _send_chunks<F: FnMut(usize) -> Result<usize, Errno>>(
+        &self,
+        statusflags: &StatusFlags,
+        buflen: usize,
+        flags: i32,
+        mut send_some: F,
+    ) -> i32 {
+        let mut sent = 0;
+        let mut cancel = self.cancel_registry.point();
+        while sent < buflen {
+            let errno = match send_some(sent) {
+                Ok(count) => {
+                    sent += count;
+                    continue;
+                }
+                Err(errno) => errno,
+            };
+            if errno == Errno::EAGAIN && statusflags.get() & O_NONBLOCK == 0 {
+                // a blocking send goes back around, once it has checked for cancellation and signals
+                if let Some(cancelled) = cancel.check("send") {
+                    return cancelled;
+                }
+                match self.signal_interrupt("send", true) {
+                    Some(_) if sent > 0 => break,
+                    Some(interrupted) => return interrupted,
+                    None => continue,
+                }
+            }
+            if sent > 0 {
+                break;
+            }
+            if errno == Errno::EPIPE && flags & MSG_NOSIGNAL == 0 {
+                interface::lind_kill_from_id(self.cageid, SIGPIPE);
+            }
+            return syscall_error(errno, "send", "the send could not be completed");
+        }
+        sent as i32
+    }
 
-                    // check if this is a domain socket
-                    let socket_type = sockhandle.domain;
-                    match socket_type {
-                        AF_UNIX => {
-                            match sockhandle.protocol {
-                                IPPROTO_TCP => {
-                                    if sockhandle.state != ConnState::CONNECTED {
-                                        return syscall_error(
-                                            Errno::ENOTCONN,
-                                            "send",
-                                            "The descriptor is not connected",
-                                        );
-                                    }
+    //a send on a socket shut down for writing fails with EPIPE, raising SIGPIPE unless flags has
+    //MSG_NOSIGNAL
+    pub fn _send_after_shutdown(&self, flags: i32) -> i32 {
+        if flags & MSG_NOSIGNAL == 0 {
+            interface::lind_kill_from_id(self.cageid, SIGPIPE);
+        }
+        syscall_error(
+            Errno::EPIPE,
+            "send",
+            "the socket has been shut down for writing",
+        )
+    }
 
-                                    // get the socket pipe, write to it, and return bytes written
-                                    if let Some(sockinfo) = &sockhandle.unix_info {
-                                        let mut nonblocking = false;
-                                        if sockfdobj.flags & O_NONBLOCK != 0 {
-                                            nonblocking = true;
-                                        }
-                                        let retval = match sockinfo.sendpipe.as_ref() {
-                                            Some(sendpipe) => {
-                                                sendpipe.write_to_pipe(buf, buflen, nonblocking)
-                                                    as i32
-                                            }
-                                            None => {
-                                                return syscall_error(Errno::EAGAIN, "write", "there is no data available right now, try again later");
-                                            }
-                                        };
-                                        if retval < 0 {
-                                            return syscall_error(Errno::EAGAIN, "write", "there is no data available right now, try again later");
-                                        } else {
-                                            return retval;
-                                        }
-                                    }
+    pub fn send_syscall(&self, fd: i32, buf: *const u8, buflen: usize, flags: i32) -> i32 {
+        let (sock_tmp, statusflags) = match self._socket_of_fd(fd, "send") {
+            Ok(socket) => socket,
+            Err(e) => return e,
+        };
+        //send only reads the handle, so it doesn't wait on a recv blocked on the same socket
+        let sockhandle = sock_tmp.read();
 
-                                    return syscall_error(
-                                        Errno::EINPROGRESS,
-                                        "connect",
-                                        "The libc call to connect failed!",
-                                    );
-                                }
-                                _ => {
-                                    return syscall_error(
-                                        Errno::EOPNOTSUPP,
-                                        "send",
-                                        "Unkown protocol in send",
-                                    );
-                                }
-                            }
+        if (flags & !MSG_NOSIGNAL) != 0 {
+            return syscall_error(Errno::EOPNOTSUPP, "send", "The flags are not understood!");
+        }
+
+        // check if this is a domain socket
+        let socket_type = sockhandle.domain;
+        match socket_type {
+            AF_UNIX => {
+                match sockhandle.protocol {
+                    IPPROTO_TCP => {
+                        if sockhandle.shut_wr {
+                            return self._send_after_shutdown(flags);
                         }
-                        // for inet
-                        AF_INET | AF_INET6 => match sockhandle.protocol {
-                            IPPROTO_TCP => {
-                                if (sockhandle.state != ConnState::CONNECTED)
-                                    && (sockhandle.state != ConnState::CONNWRONLY)
-                                {
+                        if sockhandle.state != ConnState::CONNECTED {
+                            return syscall_error(
+                                Errno::ENOTCONN,
+                                "send",
+                                "The descriptor is not connected",
+                            );
+                        }
+
+                        // get the socket pipe, write to it, and return bytes written
+                        let sendpipe = match &sockhandle.unix_info {
+                            Some(sockinfo) => match &sockinfo.sendpipe {
+                                Some(sendpipe) => sendpipe.clone(),
+                                None => {
                                     return syscall_error(
-                                        Errno::ENOTCONN,
-                                        "send",
-                                        "The descriptor is not connected",
+                                        Errno::EAGAIN,
+                                        "write",
+                                        "there is no data available right now, try again later",
                                     );
                                 }
-
-                                //because socket must be connected it must have an inner socket
-                                let retval = sockhandle
-                                    .innersocket
-                                    .as_ref()
-                                    .unwrap()
-                                    .sendto(buf, buflen, None);
-                                if retval < 0 {
-                                    match Errno::from_discriminant(interface::get_errno()) {
-                                        Ok(i) => {
-                                            return syscall_error(
-                                                i,
-                                                "send",
-                                                "The libc call to sendto failed!",
-                                            );
-                                        }
-                                        Err(()) => panic!(
-                                            "Unknown errno value from socket sendto returned!"
-                                        ),
-                                    };
-                                } else {
-                                    return retval;
-                                }
-                            }
-
-                            IPPROTO_UDP => {
-                                let remoteaddr = match &sockhandle.remoteaddr {
-                                    Some(x) => x.clone(),
-                                    None => {
-                                        return syscall_error(
-                                            Errno::ENOTCONN,
-                                            "send",
-                                            "The descriptor is not connected",
-                                        );
-                                    }
-                                };
-                                drop(unlocked_fd);
-                                drop(sockhandle);
-                                //send from a udp socket is just shunted off to sendto with the remote address set
-                                return self.sendto_syscall(fd, buf, buflen, flags, &remoteaddr);
-                            }
-
-                            _ => {
+                            },
+                            None => {
                                 return syscall_error(
-                                    Errno::EOPNOTSUPP,
-                                    "send",
-                                    "Unkown protocol in send",
+                                    Errno::EINPROGRESS,
+                                    "connect",
+                                    "The libc call to connect failed!",
                                 );
                             }
-                        },
-                        _ => {
-                            return syscall_error(
-                                Errno::EINVAL,
-                                "connect",
-                                "Unsupported domain provided",
-                            )
-                        }
+                        };
+                        //the pipe parks us while it's full, so the handle doesn't need to stay locked
+                        drop(sockhandle);
+                        let nonblocking = statusflags.get() & O_NONBLOCK != 0;
+                        return self._send_chunks(&statusflags, buflen, flags, |sent| {
+                            let chunk = unsafe {
+                                std::slice::from_raw_parts(buf.wrapping_add(sent), buflen - sent)
+                            };
+                            let retval = sendpipe.write_some_to_pipe(chunk, nonblocking);
+                            if retval < 0 {
+                                Err(Errno::from_host(-retval))
+                            } else {
+                                Ok(retval as usize)
+                            }
+                        });
+                    }
+                    _ => {
+                        return syscall_error(Errno::EOPNOTSUPP, "send", "Unkown protocol in send");
                     }
-                }
-                _ => {
-                    return syscall_error(
-                        Errno::ENOTSOCK,
-                        "send",
-                        "file descriptor refers to something other than a socket",
-                    );
                 }
             }
-        } else {
-            return syscall_error(Errno::EBADF, "send", "invalid file descriptor");
-        }
-    }
-
-    fn recv_common_inner(
-        &self,
-        filedesc_enum: &mut FileDescriptor,
-        buf: *mut u8,
-        buflen: usize,
-        flags: i32,
-        addr: &mut Option<&mut interface::GenSockaddr>,
-    ) -> i32 {
-        match &mut *filedesc_enum {
-            Socket(ref mut sockfdobj) => {
-                let sock_tmp = sockfdobj.handle.clone();
-                let mut sockhandle = sock_tmp.write();
-                match sockhandle.protocol {
-                    IPPROTO_TCP => {
-                        return self.recv_common_inner_tcp(
-                            &mut sockhandle,
-                            sockfdobj,
-                            buf,
-                            buflen,
-                            flags,
-                            addr,
-                        )
-                    }
-                    IPPROTO_UDP => {
-                        return self.recv_common_inner_udp(
-                            &mut sockhandle,
-                            sockfdobj,
-                            buf,
-                            buflen,
-                            addr,
-                        )
+            // for inet
+            AF_INET | AF_INET6 => match sockhandle.protocol {
+                IPPROTO_TCP => {
+                    if sockhandle.shut_wr {
+                        return self._send_after_shutdown(flags);
                     }
-
-                    _ => {
+                    if sockhandle.state != ConnState::CONNECTED {
                         return syscall_error(
-                            Errno::EOPNOTSUPP,
-                            "recvfrom",
-                            "Unkown protocol in recvfrom",
+                            Errno::ENOTCONN,
+                            "send",
+                            "The descriptor is not connected",
                         );
                     }
+
+                    //because socket must be connected it must have an inner socket
+                    let innersocket = sockhandle.innersocket.as_ref().unwrap();
+                    //the host send never blocks or raises SIGPIPE itself; waiting for room and the
+                    //signal are both left to _send_chunks
+                    return self._send_chunks(&statusflags, buflen, flags, |sent| {
+                        let retval = innersocket.sendto(
+                            buf.wrapping_add(sent),
+                            buflen - sent,
+                            None,
+                            libc::MSG_DONTWAIT | libc::MSG_NOSIGNAL,
+                        );
+                        let errno = match retval {
+                            Ok(retval) => return Ok(retval as usize),
+                            Err(errno) => Errno::from_host(errno),
+                        };
+                        if errno == Errno::EAGAIN && statusflags.get() & O_NONBLOCK == 0 {
+                            innersocket.wait_writable(interface::SIGNAL_CHECK_INTERVAL);
+                        }
+                        Err(errno)
+                    });
                 }
-            }
-            _ => {
-                return syscall_error(
-                    Errno::ENOTSOCK,
-                    "recvfrom",
-                    "file descriptor refers to something other than a socket",
-                );
-            }
+
+                IPPROTO_UDP => {
+                    let remoteaddr = match &sockhandle.remoteaddr {
+                        Some(x) => *x,
+                        None => {
+                            return syscall_error(
+                                Errno::ENOTCONN,
+                                "send",
+                                "The descriptor is not connected",
+                            );
+                        }
+                    };
+                    //connect bound the socket, so it can send straight to the address it laid out
+                    if let (Some(hostaddr), Some(innersocket)) =
+                        (&sockhandle.remote_hostaddr, &sockhandle.innersocket)
+                    {
+                        return match innersocket.sendto_host(buf, buflen, hostaddr) {
+                            Ok(retval) => retval,
+                            Err(errno) => interface::host_syscall_error(
+                                errno,
+                                "send",
+                                "The libc call to sendto failed!",
+                            ),
+                        };
+                    }
+                    drop(sockhandle);
+                    //send from a udp socket is just shunted off to sendto with the remote address set
+                    return self.sendto_syscall(fd, buf, buflen, flags, &remoteaddr);
+                }
+
+                _ => {
+                    return syscall_error(Errno::EOPNOTSUPP, "send", "Unkown protocol in send");
+                }
+            },
+            _ => return syscall_error(Errno::EINVAL, "connect", "Unsupported domain provided"),
         }
     }
 
     fn recv_common_inner_tcp(
         &self,
-        sockhandle: &mut interface::RustLockWriteGuard<SocketHandle>,
-        sockfdobj: &mut SocketDesc,
+        sock_tmp: &interface::RustRfc<interface::RustLock<SocketHandle>>,
+        statusflags: &StatusFlags,
         buf: *mut u8,
         buflen: usize,
         flags: i32,
         addr: &mut Option<&mut interface::GenSockaddr>,
     ) -> i32 {
         // maybe select reported a INPROGRESS tcp socket as readable, so re-check the state here
-        if sockhandle.state == ConnState::INPROGRESS
-            && sockhandle
-                .innersocket
-                .as_ref()
-                .unwrap()
-                .check_rawconnection()
-        {
-            sockhandle.state = ConnState::CONNECTED;
+        if sock_tmp.read().state == ConnState::INPROGRESS {
+            let mut sockhandle = sock_tmp.write();
+            if sockhandle.state == ConnState::INPROGRESS
+                && sockhandle
+                    .innersocket
+                    .as_ref()
+                    .unwrap()
+                    .check_rawconnection()
+            {
+                sockhandle.state = ConnState::CONNECTED;
+            }
         }
 
-        if (sockhandle.state != ConnState::CONNECTED) && (sockhandle.state != ConnState::CONNRDONLY)
-        {
+        let mut sockhandle = sock_tmp.read();
+
+        //once shut down for reading there is nothing more to read, and what was peeked is gone
+        if sockhandle.shut_rd {
+            return 0;
+        }
+        if sockhandle.state != ConnState::CONNECTED {
             return syscall_error(
                 Errno::ENOTCONN,
                 "recvfrom",
@@ -1052,13 +1220,18 @@ impl Cage {
             );
         }
 
+        //the peek buffer's lock is held for the whole recv so that concurrent recvs on this socket
+        //still see the stream in order
+        let last_peek_tmp = sockhandle.last_peek.clone();
+        let mut last_peek = last_peek_tmp.lock();
+
         let mut newbuflen = buflen;
         let mut newbufptr = buf;
 
         //if we have peeked some data before, fill our buffer with that data before moving on
-        if !sockhandle.last_peek.is_empty() {
-            let bytecount = interface::rust_min(sockhandle.last_peek.len(), newbuflen);
-            interface::copy_fromrustdeque_sized(buf, bytecount, &sockhandle.last_peek);
+        if !last_peek.is_empty() {
+            let bytecount = interface::rust_min(last_peek.len(), newbuflen);
+            interface::copy_fromrustdeque_sized(buf, bytecount, &last_peek);
             newbuflen -= bytecount;
             newbufptr = newbufptr.wrapping_add(bytecount);
 
@@ -1066,10 +1239,8 @@ impl Cage {
             //and if the bytecount is more than the length of the peeked data, then we remove the entire
             //buffer
             if flags & MSG_PEEK == 0 {
-                let len = sockhandle.last_peek.len();
-                sockhandle
-                    .last_peek
-                    .drain(..(if bytecount > len { len } else { bytecount }));
+                let len = last_peek.len();
+                last_peek.drain(..(if bytecount > len { len } else { bytecount }));
             }
 
             if newbuflen == 0 {
@@ -1084,33 +1255,35 @@ impl Cage {
 
         if sockhandle.domain == AF_UNIX {
             // get the remote socket pipe, read from it, and return bytes read
-            let mut nonblocking = false;
-            if sockfdobj.flags & O_NONBLOCK != 0 {
-                nonblocking = true;
-            }
+            let nonblocking = statusflags.get() & O_NONBLOCK != 0;
+            let receivepipe = sockhandle
+                .unix_info
+                .as_ref()
+                .unwrap()
+                .receivepipe
+                .clone()
+                .unwrap();
+            //the pipe parks us while it's empty, so the handle doesn't need to stay locked for it
+            drop(sockhandle);
+            let wakepipe = receivepipe.clone();
+            let mut cancel = self
+                .cancel_registry
+                .point_waking(move || wakepipe.wake_parked());
             loop {
-                let sockinfo = &sockhandle.unix_info.as_ref().unwrap();
-                let receivepipe = sockinfo.receivepipe.as_ref().unwrap();
                 retval = receivepipe.read_from_pipe(bufleft, buflenleft, nonblocking) as i32;
                 if retval < 0 {
                     //If we have already read from a peek but have failed to read more, exit!
                     if buflen != buflenleft {
                         return (buflen - buflenleft) as i32;
                     }
-                    if sockfdobj.flags & O_NONBLOCK == 0 && retval == -(Errno::EAGAIN as i32) {
+                    if statusflags.get() & O_NONBLOCK == 0 && retval == -(Errno::EAGAIN as i32) {
                         // with blocking sockets, we return EAGAIN here to check for cancellation, then return to reading
-                        if self
-                            .cancelstatus
-                            .load(interface::RustAtomicOrdering::Relaxed)
-                        {
-                            // if the cancel status is set in the cage, we trap around a cancel point
-                            // until the individual thread is signaled to cancel itself
-                            loop {
-                                interface::cancelpoint(self.cageid)
-                            }
+                        if let Some(cancelled) = cancel.check("recvfrom") {
+                            return cancelled;
+                        }
+                        if let Some(interrupted) = self.signal_interrupt("recvfrom", true) {
+                            return interrupted;
                         }
-                        // in order to prevent deadlock
-                        interface::RustLockWriteGuard::<SocketHandle>::bump(sockhandle);
                         continue;
                     } else {
                         //if not EAGAIN, return the error
@@ -1120,60 +1293,51 @@ impl Cage {
                 break;
             }
         } else {
+            let mut cancel = self.cancel_registry.point();
             loop {
                 // we loop here so we can cancel blocking recvs
                 //socket must be connected so unwrap ok
-                if sockfdobj.flags & O_NONBLOCK != 0 {
-                    retval = sockhandle
+                let result = if statusflags.get() & O_NONBLOCK != 0 {
+                    sockhandle
                         .innersocket
                         .as_ref()
                         .unwrap()
-                        .recvfrom_nonblocking(bufleft, buflenleft, addr);
+                        .recvfrom_nonblocking(bufleft, buflenleft, addr)
                 } else {
-                    retval = sockhandle
+                    sockhandle
                         .innersocket
                         .as_ref()
                         .unwrap()
-                        .recvfrom(bufleft, buflenleft, addr);
-                }
+                        .recvfrom(bufleft, buflenleft, addr)
+                };
 
-                if retval < 0 {
+                if let Err(errno) = result {
                     //If we have already read from a peek but have failed to read more, exit!
                     if buflen != buflenleft {
                         return (buflen - buflenleft) as i32;
                     }
 
-                    match Errno::from_discriminant(interface::get_errno()) {
-                        Ok(i) => {
-                            //We have the recieve timeout set to every one second, so
-                            //if our blocking socket ever returns EAGAIN, it must be
-                            //the case that this recv timeout was exceeded, and we
-                            //should thus not treat this as a failure in our emulated
-                            //socket; see comment in Socket::new in interface/comm.rs
-                            if sockfdobj.flags & O_NONBLOCK == 0 && i == Errno::EAGAIN {
-                                if self
-                                    .cancelstatus
-                                    .load(interface::RustAtomicOrdering::Relaxed)
-                                {
-                                    // if the cancel status is set in the cage, we trap around a cancel point
-                                    // until the individual thread is signaled to cancel itself
-                                    loop {
-                                        interface::cancelpoint(self.cageid);
-                                    }
-                                }
-                                interface::RustLockWriteGuard::<SocketHandle>::bump(sockhandle);
-                                continue; // EAGAIN, try again
-                            }
-
-                            return syscall_error(
-                                i,
-                                "recvfrom",
-                                "Internal call to recvfrom failed",
-                            );
+                    let i = Errno::from_host(errno);
+                    //We have the recieve timeout set to every one second, so
+                    //if our blocking socket ever returns EAGAIN, it must be
+                    //the case that this recv timeout was exceeded, and we
+                    //should thus not treat this as a failure in our emulated
+                    //socket; see comment in Socket::new in interface/comm.rs
+                    if statusflags.get() & O_NONBLOCK == 0 && i == Errno::EAGAIN {
+                        if let Some(cancelled) = cancel.check("recvfrom") {
+                            return cancelled;
                         }
-                        Err(()) => panic!("Unknown errno value from socket recvfrom returned!"),
-                    };
+                        if let Some(interrupted) = self.signal_interrupt("recvfrom", true) {
+                            return interrupted;
+                        }
+                        interface::RustLockReadGuard::<SocketHandle>::bump(&mut sockhandle);
+                        interface::MutexGuard::bump(&mut last_peek);
+                        continue; // EAGAIN, try again
+                    }
+
+                    return syscall_error(i, "recvfrom", "Internal call to recvfrom failed");
                 }
+                retval = result.unwrap();
                 break; // we're okay to move on
             }
         }
@@ -1181,7 +1345,7 @@ impl Cage {
 
         if flags & MSG_PEEK != 0 {
             //extend from the point after we read our previously peeked bytes
-            interface::extend_fromptr_sized(newbufptr, retval as usize, &mut sockhandle.last_peek);
+            interface::extend_fromptr_sized(newbufptr, retval as usize, &mut last_peek);
         }
 
         return totalbyteswritten;
@@ -1189,8 +1353,8 @@ impl Cage {
 
     fn recv_common_inner_udp(
         &self,
-        sockhandle: &mut interface::RustLockWriteGuard<SocketHandle>,
-        sockfdobj: &mut SocketDesc,
+        sock_tmp: &interface::RustRfc<interface::RustLock<SocketHandle>>,
+        statusflags: &StatusFlags,
         buf: *mut u8,
         buflen: usize,
         addr: &mut Option<&mut interface::GenSockaddr>,
@@ -1201,52 +1365,42 @@ impl Cage {
             AF_INET
         };
 
-        let ibindret = self._implicit_bind(&mut *sockhandle, binddomain);
-        if ibindret < 0 {
-            return ibindret;
+        let mut sockhandle = sock_tmp.read();
+        if sockhandle.localaddr.is_none() {
+            drop(sockhandle);
+            let mut writehandle = sock_tmp.write();
+            let ibindret = self._implicit_bind(&mut *writehandle, binddomain);
+            if ibindret < 0 {
+                return ibindret;
+            }
+            sockhandle = interface::RustLockWriteGuard::downgrade(writehandle);
         }
 
+        let mut cancel = self.cancel_registry.point();
         loop {
             // loop for blocking sockets
-            //if the remoteaddr is set and addr is not, use remoteaddr
             //unwrap is ok because of implicit bind
-            let retval = if let (None, Some(ref mut remoteaddr)) = (&addr, sockhandle.remoteaddr) {
-                sockhandle.innersocket.as_ref().unwrap().recvfrom(
-                    buf,
-                    buflen,
-                    &mut Some(remoteaddr),
-                )
-            } else {
-                sockhandle
-                    .innersocket
-                    .as_ref()
-                    .unwrap()
-                    .recvfrom(buf, buflen, addr)
-            };
-
-            if retval < 0 {
-                match Errno::from_discriminant(interface::get_errno()) {
-                    Ok(i) => {
-                        if sockfdobj.flags & O_NONBLOCK == 0 && i == Errno::EAGAIN {
-                            if self
-                                .cancelstatus
-                                .load(interface::RustAtomicOrdering::Relaxed)
-                            {
-                                // if the cancel status is set in the cage, we trap around a cancel point
-                                // until the individual thread is signaled to cancel itself
-                                loop {
-                                    interface::cancelpoint(self.cageid);
-                                }
-                            }
-                            interface::RustLockWriteGuard::<SocketHandle>::bump(sockhandle);
-                            continue; //received EAGAIN on blocking socket, try again
-                        }
-                        return syscall_error(i, "recvfrom", "Internal call to recvfrom failed");
+            let retval = sockhandle
+                .innersocket
+                .as_ref()
+                .unwrap()
+                .recvfrom(buf, buflen, addr);
+
+            if let Err(errno) = retval {
+                let i = Errno::from_host(errno);
+                if statusflags.get() & O_NONBLOCK == 0 && i == Errno::EAGAIN {
+                    if let Some(cancelled) = cancel.check("recvfrom") {
+                        return cancelled;
                     }
-                    Err(()) => panic!("Unknown errno value from socket recvfrom returned!"),
-                };
+                    if let Some(interrupted) = self.signal_interrupt("recvfrom", true) {
+                        return interrupted;
+                    }
+                    interface::RustLockReadGuard::<SocketHandle>::bump(&mut sockhandle);
+                    continue; //received EAGAIN on blocking socket, try again
+                }
+                return syscall_error(i, "recvfrom", "Internal call to recvfrom failed");
             } else {
-                return retval; // we can proceed
+                return retval.unwrap(); // we can proceed
             }
         }
     }
@@ -1259,12 +1413,17 @@ impl Cage {
         flags: i32,
         addr: &mut Option<&mut interface::GenSockaddr>,
     ) -> i32 {
-        let checkedfd = self.get_filedescriptor(fd).unwrap();
-        let mut unlocked_fd = checkedfd.write();
-        if let Some(ref mut filedesc_enum) = &mut *unlocked_fd {
-            return self.recv_common_inner(filedesc_enum, buf, buflen, flags, addr);
-        } else {
-            return syscall_error(Errno::EBADF, "recvfrom", "invalid file descriptor");
+        let (sock_tmp, statusflags) = match self._socket_of_fd(fd, "recvfrom") {
+            Ok(socket) => socket,
+            Err(e) => return e,
+        };
+        let protocol = sock_tmp.read().protocol;
+        match protocol {
+            IPPROTO_TCP => {
+                self.recv_common_inner_tcp(&sock_tmp, &statusflags, buf, buflen, flags, addr)
+            }
+            IPPROTO_UDP => self.recv_common_inner_udp(&sock_tmp, &statusflags, buf, buflen, addr),
+            _ => syscall_error(Errno::EOPNOTSUPP, "recvfrom", "Unkown protocol in recvfrom"),
         }
     }
 
@@ -1329,10 +1488,7 @@ impl Cage {
                                 let shd = sockhandle.domain as i32;
                                 let ibindret = self._implicit_bind(&mut *sockhandle, shd);
                                 if ibindret < 0 {
-                                    match Errno::from_discriminant(interface::get_errno()) {
-                                        Ok(i) => {return syscall_error(i, "listen", "The libc call to bind within listen failed");},
-                                        Err(()) => panic!("Unknown errno value from socket bind within listen returned!"),
-                                    };
+                                    return ibindret;
                                 }
                             }
 
@@ -1348,17 +1504,12 @@ impl Cage {
                             sockhandle.state = ConnState::LISTEN;
 
                             let listenret = sockhandle.innersocket.as_ref().unwrap().listen(5); //default backlog in repy for whatever reason, we replicate it
-                            if listenret < 0 {
-                                let lr = match Errno::from_discriminant(interface::get_errno()) {
-                                    Ok(i) => syscall_error(
-                                        i,
-                                        "listen",
-                                        "The libc call to listen failed!",
-                                    ),
-                                    Err(()) => {
-                                        panic!("Unknown errno value from socket listen returned!")
-                                    }
-                                };
+                            if let Err(errno) = listenret {
+                                let lr = interface::host_syscall_error(
+                                    errno,
+                                    "listen",
+                                    "The libc call to listen failed!",
+                                );
                                 NET_METADATA.listening_port_set.remove(&mux_port(
                                     ladr.addr().clone(),
                                     ladr.port(),
@@ -1417,34 +1568,66 @@ impl Cage {
         how: i32,
         shutdown: bool,
     ) -> i32 {
+        if shutdown && sockhandle.shut_rd && sockhandle.shut_wr {
+            return syscall_error(
+                Errno::ENOTCONN,
+                "netshutdown",
+                "the socket has already been shut down",
+            );
+        }
+
+        //a closed unix socket gives up the path it bound, and a listener the connections queued
+        //on it
+        if sockhandle.domain == AF_UNIX && !shutdown {
+            Self::_release_unix_path(sockhandle);
+        }
+
+        //what is waiting to be read is discarded on shutting down reads, and the peer of a unix
+        //socket finds the end of the stream on shutting down writes
+        if shutdown {
+            if how == SHUT_RD || how == SHUT_RDWR {
+                sockhandle.last_peek.lock().clear();
+            }
+            if let Some(unixinfo) = sockhandle.unix_info.as_ref() {
+                if how == SHUT_RD || how == SHUT_RDWR {
+                    if let Some(receivepipe) = unixinfo.receivepipe.as_ref() {
+                        receivepipe.shutdown_read();
+                    }
+                }
+                if how == SHUT_WR || how == SHUT_RDWR {
+                    if let Some(sendpipe) = unixinfo.sendpipe.as_ref() {
+                        sendpipe.set_eof();
+                    }
+                }
+            }
+        }
+
         // we need to do a bunch of actual socket cleanup for INET sockets
         if sockhandle.domain != AF_UNIX {
+            let connected =
+                sockhandle.protocol == IPPROTO_TCP && sockhandle.state == ConnState::CONNECTED;
             let mut releaseflag = false;
+            let mut closed_first = false;
             if let Some(ref sobj) = sockhandle.innersocket {
                 if shutdown {
                     let shutresult = sobj.shutdown(how);
 
-                    if shutresult < 0 {
-                        match Errno::from_discriminant(interface::get_errno()) {
-                            Ok(i) => {
-                                return syscall_error(
-                                    i,
-                                    "shutdown",
-                                    "The libc call to setsockopt failed!",
-                                );
-                            }
-                            Err(()) => panic!("Unknown errno value from setsockopt returned!"),
-                        };
+                    if let Err(errno) = shutresult {
+                        return interface::host_syscall_error(
+                            errno,
+                            "shutdown",
+                            "The libc call to setsockopt failed!",
+                        );
                     }
 
                     match how {
                         SHUT_RD => {
-                            if sockhandle.state == ConnState::CONNRDONLY {
+                            if sockhandle.shut_wr {
                                 releaseflag = true;
                             }
                         }
                         SHUT_WR => {
-                            if sockhandle.state == ConnState::CONNWRONLY {
+                            if sockhandle.shut_rd {
                                 releaseflag = true;
                             }
                         }
@@ -1460,15 +1643,34 @@ impl Cage {
                             );
                         }
                     }
+                    closed_first = !sobj.peer_closed_first();
                 } else {
                     //Reaching this means that the socket is closed. Removing the sockobj
                     //indicates that the sockobj will drop, and therefore close
                     releaseflag = true;
+                    closed_first = !sobj.peer_closed_first();
                     sockhandle.innersocket = None;
                 }
             }
 
-            if releaseflag {
+            if releaseflag && sockhandle.state == ConnState::LISTEN {
+                Self::_release_listener_port(sockhandle);
+                sockhandle.state = ConnState::NOTCONNECTED;
+            }
+
+            //the port of a connection outlives it for a while on the side that closed it first, even
+            //when it is the listener's
+            if releaseflag && connected && closed_first {
+                if let Some(localaddr) = sockhandle.localaddr.as_ref() {
+                    NET_METADATA.enter_time_wait(
+                        localaddr.addr(),
+                        localaddr.port(),
+                        sockhandle.domain,
+                    );
+                }
+            }
+
+            if releaseflag && !sockhandle.shares_listener_port {
                 if let Some(localaddr) = sockhandle.localaddr.as_ref().clone() {
                     //move to end
                     let release_ret_val = NET_METADATA._release_localport(
@@ -1485,24 +1687,18 @@ impl Cage {
             }
         }
 
-        // now change the connections for all socket types
+        // now record which halves are shut down for all socket types; a connection stays connected,
+        // so a recv still finds the end of the stream and a send still fails with EPIPE
         match how {
             SHUT_RD => {
-                if sockhandle.state == ConnState::CONNWRONLY {
-                    sockhandle.state = ConnState::NOTCONNECTED;
-                } else {
-                    sockhandle.state = ConnState::CONNWRONLY;
-                }
+                sockhandle.shut_rd = true;
             }
             SHUT_WR => {
-                if sockhandle.state == ConnState::CONNRDONLY {
-                    sockhandle.state = ConnState::NOTCONNECTED;
-                } else {
-                    sockhandle.state = ConnState::CONNRDONLY;
-                }
+                sockhandle.shut_wr = true;
             }
             SHUT_RDWR => {
-                sockhandle.state = ConnState::NOTCONNECTED;
+                sockhandle.shut_rd = true;
+                sockhandle.shut_wr = true;
             }
             _ => {
                 //See http://linux.die.net/man/2/shutdown for nuance to this error
@@ -1517,6 +1713,57 @@ impl Cage {
         return 0;
     }
 
+    //stop listening on the port, resetting the connections that were taken off the host socket
+    //but never accepted
+    fn _release_listener_port(sockhandle: &SocketHandle) {
+        let localaddr = match sockhandle.localaddr.as_ref() {
+            Some(localaddr) => localaddr,
+            None => return,
+        };
+        let porttuple = mux_port(
+            localaddr.addr().clone(),
+            localaddr.port(),
+            sockhandle.domain,
+            TCPPORT,
+        );
+        NET_METADATA.listening_port_set.remove(&porttuple);
+        if let Some((_, pending)) = NET_METADATA.pending_conn_table.remove(&porttuple) {
+            for (connection, _) in pending {
+                if let Ok(connection) = connection {
+                    connection.reset();
+                }
+            }
+        }
+    }
+
+    fn _release_unix_path(sockhandle: &SocketHandle) {
+        let (boundpath, inodenum) = match sockhandle.unix_info.as_ref() {
+            Some(UnixSocketInfo {
+                boundpath: Some(boundpath),
+                inode,
+                ..
+            }) => (boundpath, *inode),
+            _ => return,
+        };
+        //the path may have been unlinked and bound by another socket since
+        if metawalk(boundpath.as_path()) != Some(inodenum) {
+            return;
+        }
+
+        if sockhandle.state == ConnState::LISTEN {
+            //a connection not yet accepted finds its peer gone
+            if let Some((_, pending)) = NET_METADATA.domsock_accept_table.remove(boundpath) {
+                pending.send_pipe.set_eof();
+                if let Some(connvar) = pending.get_cond_var() {
+                    connvar.refuse();
+                }
+            }
+            NET_METADATA.domsock_accept_waits.remove(boundpath);
+        }
+        NET_METADATA.domsock_paths.remove(boundpath);
+        interface::notify_readiness();
+    }
+
     pub fn _cleanup_socket_inner(
         &self,
         filedesc: &mut FileDescriptor,
@@ -1545,10 +1792,6 @@ impl Cage {
             if inner_result < 0 {
                 return inner_result;
             }
-
-            if how == SHUT_RDWR {
-                let _discarded_fd = unlocked_fd.take();
-            }
         } else {
             return syscall_error(Errno::EBADF, "cleanup socket", "invalid file descriptor");
         }
@@ -1641,18 +1884,23 @@ impl Cage {
                     sockhandle.domain,
                     sockhandle.socktype,
                     sockhandle.protocol,
-                    sockfdobj.flags & O_NONBLOCK != 0,
-                    sockfdobj.flags & O_CLOEXEC != 0,
+                    false, //like Linux, the new socket does not inherit O_NONBLOCK or O_CLOEXEC
+                    false,
                     ConnState::CONNECTED,
                 );
 
                 let remote_addr: interface::GenSockaddr;
                 let sendpipenumber;
                 let receivepipenumber;
+                let peercred;
 
+                let localpathbuf = normpath(convpath(sockhandle.localaddr.unwrap().path()), self);
+                //a blocking accept sleeps until connect queues a connection for it
+                let acceptcv = NET_METADATA.domsock_accept_cv(&localpathbuf);
+                let wakecv = acceptcv.clone();
+                let mut cancel = self.cancel_registry.point_waking(move || wakecv.notify());
                 loop {
-                    let localpathbuf =
-                        normpath(convpath(sockhandle.localaddr.unwrap().path()), self);
+                    let generation = acceptcv.generation();
                     let dsconnobj = NET_METADATA.domsock_accept_table.get(&localpathbuf);
 
                     if let Some(ds) = dsconnobj {
@@ -1669,11 +1917,13 @@ impl Cage {
                         remote_addr = addr.clone();
                         receivepipenumber = ds.get_receive_pipe().clone();
                         sendpipenumber = ds.get_send_pipe().clone();
+                        peercred = ds.peercred;
                         drop(ds);
                         NET_METADATA.domsock_accept_table.remove(&localpathbuf);
+                        interface::notify_readiness();
                         break;
                     } else {
-                        if 0 != (sockfdobj.flags & O_NONBLOCK) {
+                        if 0 != (sockfdobj.statusflags.get() & O_NONBLOCK) {
                             // if non block return EAGAIN
                             return syscall_error(
                                 Errno::EAGAIN,
@@ -1681,6 +1931,13 @@ impl Cage {
                                 "host system accept call failed",
                             );
                         }
+                        if let Some(cancelled) = cancel.check("accept") {
+                            return cancelled;
+                        }
+                        if let Some(interrupted) = self.signal_interrupt("accept", true) {
+                            return interrupted;
+                        }
+                        acceptcv.wait_for(generation, interface::SIGNAL_CHECK_INTERVAL);
                     }
                 }
 
@@ -1694,6 +1951,8 @@ impl Cage {
                         mode: sockhandle.unix_info.as_ref().unwrap().mode,
                         sendpipe: Some(sendpipenumber.clone()),
                         receivepipe: Some(receivepipenumber.clone()),
+                        peercred: Some(peercred),
+                        boundpath: None,
                     });
                     if let Inode::Socket(ref mut sock) =
                         *(FS_METADATA.inodetable.get_mut(&inodenum).unwrap())
@@ -1745,11 +2004,12 @@ impl Cage {
                     sockhandle.domain,
                     sockhandle.socktype,
                     sockhandle.protocol,
-                    sockfdobj.flags & O_NONBLOCK != 0,
-                    sockfdobj.flags & O_CLOEXEC != 0,
+                    false, //like Linux, the new socket does not inherit O_NONBLOCK or O_CLOEXEC
+                    false,
                     ConnState::CONNECTED,
                 );
 
+                let mut cancel = self.cancel_registry.point();
                 loop {
                     // we loop here so we can cancel blocking accept, see comments below and in Socket::new in interface/comm.rs
 
@@ -1765,7 +2025,7 @@ impl Cage {
                         Some(pendingtup) => pendingtup,
                         None => {
                             //unwrap ok because listening
-                            if 0 == (sockfdobj.flags & O_NONBLOCK) {
+                            if 0 == (sockfdobj.statusflags.get() & O_NONBLOCK) {
                                 match sockhandle.domain {
                                     PF_INET => {
                                         sockhandle.innersocket.as_ref().unwrap().accept(true)
@@ -1773,7 +2033,12 @@ impl Cage {
                                     PF_INET6 => {
                                         sockhandle.innersocket.as_ref().unwrap().accept(false)
                                     }
-                                    _ => panic!("Unknown domain in accepting socket"),
+                                    _ => {
+                                        return corruption_error(
+                                            "accept",
+                                            "unknown domain on listening socket",
+                                        )
+                                    }
                                 }
                             } else {
                                 match sockhandle.domain {
@@ -1787,66 +2052,52 @@ impl Cage {
                                         .as_ref()
                                         .unwrap()
                                         .nonblock_accept(false),
-                                    _ => panic!("Unknown domain in accepting socket"),
+                                    _ => {
+                                        return corruption_error(
+                                            "accept",
+                                            "unknown domain on listening socket",
+                                        )
+                                    }
                                 }
                             }
                         }
                     };
 
-                    if let Err(_) = acceptedresult {
-                        match Errno::from_discriminant(interface::get_errno()) {
-                            Ok(i) => {
-                                //We have the socket timeout set to every one second, so
-                                //if our blocking socket ever returns EAGAIN, it must be
-                                //the case that this recv timeout was exceeded, and we
-                                //should thus not treat this as a failure in our emulated
-                                //socket; see comment in Socket::new in interface/comm.rs
-                                if sockfdobj.flags & O_NONBLOCK == 0 && i == Errno::EAGAIN {
-                                    if self
-                                        .cancelstatus
-                                        .load(interface::RustAtomicOrdering::Relaxed)
-                                    {
-                                        // if the cancel status is set in the cage, we trap around a cancel point
-                                        // until the individual thread is signaled to cancel itself
-                                        loop {
-                                            interface::cancelpoint(self.cageid);
-                                        }
-                                    }
-                                    continue; // EAGAIN, try again
-                                }
-
-                                return syscall_error(
-                                    i,
-                                    "accept",
-                                    "Internal call to accept failed",
-                                );
+                    if let Err(errno) = acceptedresult {
+                        let i = Errno::from_host(errno);
+                        //We have the socket timeout set to every one second, so
+                        //if our blocking socket ever returns EAGAIN, it must be
+                        //the case that this recv timeout was exceeded, and we
+                        //should thus not treat this as a failure in our emulated
+                        //socket; see comment in Socket::new in interface/comm.rs
+                        if sockfdobj.statusflags.get() & O_NONBLOCK == 0 && i == Errno::EAGAIN {
+                            if let Some(cancelled) = cancel.check("accept") {
+                                return cancelled;
                             }
-                            Err(()) => panic!("Unknown errno value from socket accept returned!"),
-                        };
+                            if let Some(interrupted) = self.signal_interrupt("accept", true) {
+                                return interrupted;
+                            }
+                            continue; // EAGAIN, try again
+                        }
+
+                        return syscall_error(i, "accept", "Internal call to accept failed");
                     }
 
                     // if we get here we have an accepted socket
                     let acceptedsock = acceptedresult.unwrap();
 
-                    let mut newaddr = sockhandle.localaddr.unwrap().clone();
-                    let newport = match NET_METADATA._reserve_localport(
-                        newaddr.addr(),
-                        0,
-                        sockhandle.protocol,
-                        sockhandle.domain,
-                        false,
-                    ) {
-                        Ok(portnum) => portnum,
-                        Err(errnum) => {
-                            return errnum;
-                        }
+                    //like linux, the accepted socket has the listener's port rather than one of its
+                    //own, at whichever of the listener's addresses the peer connected to
+                    let newaddr = match acceptedsock.getsockname(sockhandle.domain == PF_INET) {
+                        Ok(hostaddr) => hostaddr,
+                        Err(_) => sockhandle.localaddr.unwrap().clone(),
                     };
-                    newaddr.set_port(newport);
 
                     let newsock_tmp = newsockfd.handle.clone();
                     let mut newsockhandle = newsock_tmp.write();
 
                     newsockhandle.localaddr = Some(newaddr);
+                    newsockhandle.shares_listener_port = true;
                     newsockhandle.remoteaddr = Some(remote_addr.clone());
 
                     //create socket object for new connected socket
@@ -1874,10 +2125,29 @@ impl Cage {
         exceptfds: Option<&mut interface::FdSet>,
         timeout: Option<interface::RustDuration>,
     ) -> i32 {
-        if nfds < STARTINGFD || nfds >= FD_SET_MAX_FD {
+        //nfds is a count of descriptors, one past the highest to look at, so any count up to the
+        //cage's limit will do, down to 0 for a select that only sleeps
+        if nfds < 0 || nfds > self.max_watched_fds() {
             return syscall_error(Errno::EINVAL, "select", "Number of FDs is wrong");
         }
+        self._select_fds(nfds, readfds, writefds, exceptfds, timeout)
+    }
+
+    //how many fds a select, poll or epoll_wait of the cage may be asked about
+    pub fn max_watched_fds(&self) -> i32 {
+        self.max_watched_fds
+            .load(interface::RustAtomicOrdering::Relaxed)
+    }
 
+    //select without the limit on nfds, which poll limits by how many fds it is given instead
+    fn _select_fds(
+        &self,
+        nfds: i32,
+        readfds: Option<&mut interface::FdSet>,
+        writefds: Option<&mut interface::FdSet>,
+        exceptfds: Option<&mut interface::FdSet>,
+        timeout: Option<interface::RustDuration>,
+    ) -> i32 {
         let start_time = interface::starttimer();
 
         let end_time = match timeout {
@@ -1887,12 +2157,14 @@ impl Cage {
 
         let mut retval = 0;
         // in the loop below, we always read from original fd_sets, but make updates to the new copies
-        let new_readfds = &mut interface::FdSet::new();
-        let new_writefds = &mut interface::FdSet::new();
+        let new_readfds = &mut interface::FdSet::with_capacity(nfds);
+        let new_writefds = &mut interface::FdSet::with_capacity(nfds);
         loop {
-            //we must block manually
+            //we must block manually, sleeping until something changes if nothing is ready yet
+            let generation = interface::readiness_generation();
+
             // 1. iterate thru readfds
-            if let Some(readfds_ref) = readfds.as_ref() {
+            if let Some(readfds_ref) = readfds.as_deref() {
                 let res = self.select_readfds(nfds, readfds_ref, new_readfds, &mut retval);
                 if res != 0 {
                     return res;
@@ -1900,7 +2172,7 @@ impl Cage {
             }
 
             // 2. iterate thru writefds
-            if let Some(writefds_ref) = writefds.as_ref() {
+            if let Some(writefds_ref) = writefds.as_deref() {
                 let res = self.select_writefds(nfds, writefds_ref, new_writefds, &mut retval);
                 if res != 0 {
                     return res;
@@ -1909,7 +2181,7 @@ impl Cage {
 
             // 3. iterate thru exceptfds
             // currently we don't really do select on execptfds, we just check if those fds are valid
-            if let Some(exceptfds_ref) = exceptfds.as_ref() {
+            if let Some(exceptfds_ref) = exceptfds.as_deref() {
                 for fd in 0..nfds {
                     // find the bit and see if it's on
                     if !exceptfds_ref.is_set(fd) {
@@ -1927,10 +2199,16 @@ impl Cage {
                 break;
             } else {
                 // at this point lets check if we got a signal before sleeping
-                if interface::sigcheck() {
-                    return syscall_error(Errno::EINTR, "select", "interrupted function call");
+                if let Some(interrupted) = self.signal_interrupt("select", false) {
+                    return interrupted;
                 }
-                interface::lind_yield();
+                let rawfds = match readfds.as_deref() {
+                    Some(readfds_ref) => {
+                        self._kernel_read_fds((0..nfds).filter(|fd| readfds_ref.is_set(*fd)))
+                    }
+                    None => vec![],
+                };
+                self._wait_for_readiness(generation, &rawfds, start_time, end_time);
             }
         }
 
@@ -1996,6 +2274,10 @@ impl Cage {
                                         new_readfds.set(fd);
                                         *retval += 1;
                                     }
+                                } else if sockhandle.shut_rd {
+                                    // shut down for reading, so a recv returns at once
+                                    new_readfds.set(fd);
+                                    *retval += 1;
                                 } else if sockhandle.state == ConnState::CONNECTED || newconnection
                                 {
                                     let sockinfo = &sockhandle.unix_info.as_ref().unwrap();
@@ -2046,6 +2328,28 @@ impl Cage {
                         }
                     }
 
+                    Inotify(inotifyfdobj) => {
+                        if inotifyfdobj.instance.queue.has_events() {
+                            new_readfds.set(fd);
+                            *retval += 1;
+                        }
+                    }
+
+                    Mqueue(mqueuefdobj) => {
+                        if mqueuefdobj.mqueue.check_select_read() {
+                            new_readfds.set(fd);
+                            *retval += 1;
+                        }
+                    }
+
+                    //device files are as ready as their driver says
+                    File(normalfile_filedesc_obj) => {
+                        if file_poll(normalfile_filedesc_obj.inode, POLLIN) != 0 {
+                            new_readfds.set(fd);
+                            *retval += 1;
+                        }
+                    }
+
                     //these file reads never block
                     _ => {
                         new_readfds.set(fd);
@@ -2059,7 +2363,16 @@ impl Cage {
 
         // do the kernel_select for inet sockets
         if !inet_info.kernel_fds.is_empty() {
-            let kernel_ret = update_readfds_from_kernel_select(new_readfds, &mut inet_info, retval);
+            let kernel_ret = match self.select_epoll.try_lock() {
+                Some(mut epoll_cache) => update_readfds_from_kernel_epoll(
+                    new_readfds,
+                    &mut inet_info,
+                    &mut epoll_cache,
+                    retval,
+                ),
+                // another thread of the cage is selecting, likely on other sockets
+                None => update_readfds_from_kernel_select(new_readfds, &mut inet_info, retval),
+            };
             // NOTE: we ignore the kernel_select error if some domsocks are ready
             if kernel_ret < 0 && *retval <= 0 {
                 return kernel_ret;
@@ -2142,6 +2455,20 @@ impl Cage {
                         }
                     }
 
+                    Mqueue(mqueuefdobj) => {
+                        if mqueuefdobj.mqueue.check_select_write() {
+                            new_writefds.set(fd);
+                            *retval += 1;
+                        }
+                    }
+
+                    File(normalfile_filedesc_obj) => {
+                        if file_poll(normalfile_filedesc_obj.inode, POLLOUT) != 0 {
+                            new_writefds.set(fd);
+                            *retval += 1;
+                        }
+                    }
+
                     //these file writes never block
                     _ => {
                         new_writefds.set(fd);
@@ -2155,6 +2482,116 @@ impl Cage {
         return 0;
     }
 
+    //SO_PEERCRED, which reports a struct ucred rather than an int: the credentials the peer of a
+    //connected unix domain socket had when the connection was made
+    pub fn getpeercred_syscall(&self, fd: i32, cred: &mut interface::UCred) -> i32 {
+        let checkedfd = match self.get_filedescriptor(fd) {
+            Ok(checkedfd) => checkedfd,
+            Err(()) => {
+                return syscall_error(
+                    Errno::EBADF,
+                    "getsockopt",
+                    "the provided file descriptor is not valid",
+                );
+            }
+        };
+        let unlocked_fd = checkedfd.read();
+        match &*unlocked_fd {
+            Some(Socket(sockfdobj)) => {
+                let sockhandle = sockfdobj.handle.read();
+                *cred = match sockhandle.unix_info.as_ref().and_then(|info| info.peercred) {
+                    Some(peercred) => peercred,
+                    //like Linux, a socket without a peer reports no pid and the overflow ids
+                    None => interface::UCred {
+                        pid: 0,
+                        uid: u32::MAX,
+                        gid: u32::MAX,
+                    },
+                };
+                0
+            }
+            Some(_) => syscall_error(
+                Errno::ENOTSOCK,
+                "getsockopt",
+                "the provided file descriptor is not a socket",
+            ),
+            None => syscall_error(
+                Errno::EBADF,
+                "getsockopt",
+                "the provided file descriptor is not valid",
+            ),
+        }
+    }
+
+    //SO_SNDTIMEO, which getsockopt and setsockopt pass as a struct timeval. A zero timeout means
+    //waiting for as long as it takes
+    pub fn getsndtimeo_syscall(&self, fd: i32, timeout: &mut interface::TimeVal) -> i32 {
+        self._with_sockhandle(fd, "getsockopt", |sockhandle| {
+            let duration = sockhandle.sndtimeo.unwrap_or(interface::RustDuration::ZERO);
+            *timeout = interface::TimeVal {
+                tv_sec: duration.as_secs() as i64,
+                tv_usec: duration.subsec_micros() as i64,
+            };
+            0
+        })
+    }
+
+    pub fn setsndtimeo_syscall(&self, fd: i32, timeout: &interface::TimeVal) -> i32 {
+        if timeout.tv_usec < 0 || timeout.tv_usec >= 1000000 {
+            return syscall_error(
+                Errno::EDOM,
+                "setsockopt",
+                "the timeout's microseconds are out of range",
+            );
+        }
+        self._with_sockhandle(fd, "setsockopt", |sockhandle| {
+            //like linux, a negative timeout is taken as no wait at all
+            sockhandle.sndtimeo = if timeout.tv_sec < 0 {
+                Some(interface::RustDuration::ZERO)
+            } else if timeout.tv_sec == 0 && timeout.tv_usec == 0 {
+                None
+            } else {
+                Some(interface::RustDuration::new(
+                    timeout.tv_sec as u64,
+                    timeout.tv_usec as u32 * 1000,
+                ))
+            };
+            0
+        })
+    }
+
+    fn _with_sockhandle<F: FnOnce(&mut SocketHandle) -> i32>(
+        &self,
+        fd: i32,
+        callname: &str,
+        f: F,
+    ) -> i32 {
+        let checkedfd = match self.get_filedescriptor(fd) {
+            Ok(checkedfd) => checkedfd,
+            Err(()) => {
+                return syscall_error(
+                    Errno::EBADF,
+                    callname,
+                    "the provided file descriptor is not valid",
+                );
+            }
+        };
+        let unlocked_fd = checkedfd.read();
+        match &*unlocked_fd {
+            Some(Socket(sockfdobj)) => f(&mut *sockfdobj.handle.write()),
+            Some(_) => syscall_error(
+                Errno::ENOTSOCK,
+                callname,
+                "the provided file descriptor is not a socket",
+            ),
+            None => syscall_error(
+                Errno::EBADF,
+                callname,
+                "the provided file descriptor is not valid",
+            ),
+        }
+    }
+
     pub fn getsockopt_syscall(&self, fd: i32, level: i32, optname: i32, optval: &mut i32) -> i32 {
         let checkedfd = self.get_filedescriptor(fd).unwrap();
         let mut unlocked_fd = checkedfd.write();
@@ -2295,20 +2732,12 @@ impl Cage {
 
                             if newoptions != sockhandle.tcp_options {
                                 if let Some(sock) = sockhandle.innersocket.as_ref() {
-                                    let sockret = sock.setsockopt(SOL_TCP, optname, optval);
-                                    if sockret < 0 {
-                                        match Errno::from_discriminant(interface::get_errno()) {
-                                            Ok(i) => {
-                                                return syscall_error(
-                                                    i,
-                                                    "setsockopt",
-                                                    "The libc call to setsockopt failed!",
-                                                );
-                                            }
-                                            Err(()) => panic!(
-                                                "Unknown errno value from setsockopt returned!"
-                                            ),
-                                        };
+                                    if let Err(errno) = sock.setsockopt(SOL_TCP, optname, optval) {
+                                        return interface::host_syscall_error(
+                                            errno,
+                                            "setsockopt",
+                                            "The libc call to setsockopt failed!",
+                                        );
                                     }
                                 }
                             }
@@ -2360,20 +2789,14 @@ impl Cage {
 
                                 if newoptions != sockhandle.socket_options {
                                     if let Some(sock) = sockhandle.innersocket.as_ref() {
-                                        let sockret = sock.setsockopt(SOL_SOCKET, optname, optval);
-                                        if sockret < 0 {
-                                            match Errno::from_discriminant(interface::get_errno()) {
-                                                Ok(i) => {
-                                                    return syscall_error(
-                                                        i,
-                                                        "setsockopt",
-                                                        "The libc call to setsockopt failed!",
-                                                    );
-                                                }
-                                                Err(()) => panic!(
-                                                    "Unknown errno value from setsockopt returned!"
-                                                ),
-                                            };
+                                        if let Err(errno) =
+                                            sock.setsockopt(SOL_SOCKET, optname, optval)
+                                        {
+                                            return interface::host_syscall_error(
+                                                errno,
+                                                "setsockopt",
+                                                "The libc call to setsockopt failed!",
+                                            );
                                         }
                                     }
                                 }
@@ -2502,7 +2925,19 @@ impl Cage {
                         ret_addr.set_family(sockhandle.domain as u16);
                         return 0;
                     }
-                    *ret_addr = sockhandle.localaddr.unwrap();
+                    let mut localaddr = sockhandle.localaddr.unwrap();
+                    //a socket bound to the wildcard address is named, once connected, by the
+                    //address the peer sees it at, which only the host knows
+                    if localaddr.addr().is_unspecified() && sockhandle.remoteaddr.is_some() {
+                        if let Some(Ok(hostaddr)) = sockhandle
+                            .innersocket
+                            .as_ref()
+                            .map(|sock| sock.getsockname(sockhandle.domain == AF_INET))
+                        {
+                            localaddr.set_addr(hostaddr.addr());
+                        }
+                    }
+                    *ret_addr = localaddr;
                     return 0;
                 }
             } else {
@@ -2552,7 +2987,10 @@ impl Cage {
     ) -> i32 {
         //timeout is supposed to be in milliseconds
 
-        let mut return_code: i32 = 0;
+        if fds.len() > self.max_watched_fds() as usize {
+            return syscall_error(Errno::EINVAL, "poll", "Number of FDs is wrong");
+        }
+
         let start_time = interface::starttimer();
 
         let end_time = match timeout {
@@ -2560,15 +2998,33 @@ impl Cage {
             None => interface::RustDuration::MAX,
         };
 
+        let isopen = |fd: i32| match self.get_filedescriptor(fd) {
+            Ok(checkedfd) => checkedfd.read().is_some(),
+            Err(_) => false,
+        };
+
         loop {
-            for structpoll in &mut *fds {
+            let generation = interface::readiness_generation();
+
+            // gather every fd into one set of each kind, so that a single pass of select looks at
+            // all of them and the inet ones go to the kernel together
+            let reads = &mut interface::FdSet::new();
+            let writes = &mut interface::FdSet::new();
+            let errors = &mut interface::FdSet::new();
+            let mut nfds = 0;
+            //fds that aren't open, which are reported with POLLNVAL rather than failing the poll
+            let mut invalid = vec![false; fds.len()];
+            for (index, structpoll) in fds.iter().enumerate() {
                 let fd = structpoll.fd;
                 let events = structpoll.events;
-
-                // init FdSet structures
-                let reads = &mut interface::FdSet::new();
-                let writes = &mut interface::FdSet::new();
-                let errors = &mut interface::FdSet::new();
+                //negative fds are ignored, as in linux
+                if fd < 0 {
+                    continue;
+                }
+                if !isopen(fd) {
+                    invalid[index] = true;
+                    continue;
+                }
 
                 //read
                 if events & POLLIN > 0 {
@@ -2582,43 +3038,105 @@ impl Cage {
                 if events & POLLERR > 0 {
                     errors.set(fd)
                 }
+                // NOTE that the nfds argument is highest fd + 1
+                nfds = interface::rust_max(nfds, fd + 1);
+            }
+
+            let selectret = self._select_fds(
+                nfds,
+                Some(reads),
+                Some(writes),
+                Some(errors),
+                Some(interface::RustDuration::ZERO),
+            );
+            //an fd closed since we looked is found not to be open on the next pass
+            if selectret == -(Errno::EBADF as i32)
+                && fds.iter().enumerate().any(|(index, structpoll)| {
+                    structpoll.fd >= 0 && !invalid[index] && !isopen(structpoll.fd)
+                })
+            {
+                continue;
+            }
+            if selectret < 0 {
+                return selectret;
+            }
 
+            let mut return_code: i32 = 0;
+            for (index, structpoll) in fds.iter_mut().enumerate() {
+                let fd = structpoll.fd;
+                let events = structpoll.events;
                 let mut mask: i16 = 0;
 
-                //0 essentially sets the timeout to the max value allowed (which is almost always more than enough time)
-                // NOTE that the nfds argument is highest fd + 1
-                let selectret = Self::select_syscall(
-                    &self,
-                    fd + 1,
-                    Some(reads),
-                    Some(writes),
-                    Some(errors),
-                    Some(interface::RustDuration::ZERO),
-                );
-                if selectret > 0 {
-                    mask += if !reads.is_empty() { POLLIN } else { 0 };
-                    mask += if !writes.is_empty() { POLLOUT } else { 0 };
-                    mask += if !errors.is_empty() { POLLERR } else { 0 };
+                if invalid[index] {
+                    mask = POLLNVAL;
+                } else if fd >= 0 {
+                    if events & POLLIN > 0 && reads.is_set(fd) {
+                        mask |= POLLIN;
+                    }
+                    if events & POLLOUT > 0 && writes.is_set(fd) {
+                        mask |= POLLOUT;
+                    }
+                    //select doesn't look at exceptional conditions, so an fd that is otherwise
+                    //ready reports POLLERR if it was asked for
+                    if mask != 0 && events & POLLERR > 0 {
+                        mask |= POLLERR;
+                    }
+                }
+                if mask != 0 {
                     return_code += 1;
-                } else if selectret < 0 {
-                    return selectret;
                 }
                 structpoll.revents = mask;
             }
 
             if return_code != 0 || interface::readtimer(start_time) > end_time {
-                break;
+                return return_code;
             } else {
-                if interface::sigcheck() {
-                    return syscall_error(Errno::EINTR, "poll", "interrupted function call");
+                if let Some(interrupted) = self.signal_interrupt("poll", false) {
+                    return interrupted;
+                }
+                let rawfds = self._kernel_read_fds(
+                    fds.iter()
+                        .filter(|structpoll| structpoll.events & POLLIN > 0)
+                        .map(|structpoll| structpoll.fd),
+                );
+                self._wait_for_readiness(generation, &rawfds, start_time, end_time);
+            }
+        }
+    }
+
+    //the host fds of the inet sockets among fds, which the kernel has to tell us the readiness of
+    fn _kernel_read_fds(&self, fds: impl Iterator<Item = i32>) -> Vec<i32> {
+        let mut rawfds = vec![];
+        for fd in fds {
+            if let Ok(checkedfd) = self.get_filedescriptor(fd) {
+                if let Some(Socket(sockfdobj)) = &*checkedfd.read() {
+                    if sockfdobj.rawfd >= 0 {
+                        rawfds.push(sockfdobj.rawfd);
+                    }
                 }
-                interface::lind_yield();
             }
         }
-        return return_code;
+        rawfds
+    }
+
+    //sleep until an fd may have become ready, going back to check for signals at least every
+    //SIGNAL_CHECK_INTERVAL, and for no longer than what is left of the timeout
+    fn _wait_for_readiness(
+        &self,
+        generation: u64,
+        rawfds: &[i32],
+        start_time: interface::RustInstant,
+        end_time: interface::RustDuration,
+    ) {
+        let remaining = end_time.saturating_sub(interface::readtimer(start_time));
+        interface::wait_for_readiness(
+            generation,
+            rawfds,
+            remaining.min(interface::SIGNAL_CHECK_INTERVAL),
+        );
     }
 
-    pub fn _epoll_object_allocator(&self) -> i32 {
+    pub fn _epoll_object_allocator(&self, cloexec: bool) -> i32 {
         //seems to only be called in functions that don't have a filedesctable lock, so not passing the lock.
 
         let epollobjfd = Epoll(EpollDesc {
@@ -2626,7 +3144,8 @@ impl Cage {
             registered_fds: interface::RustHashMap::<i32, EpollEvent>::new(),
             advlock: interface::RustRfc::new(interface::AdvisoryLock::new()),
             errno: 0,
-            flags: 0,
+            flags: if cloexec { O_CLOEXEC } else { 0 },
+            statusflags: StatusFlags::new(0),
         });
         //get a file descriptor
         let (fd, guardopt) = self.get_next_fd(None);
@@ -2639,6 +3158,38 @@ impl Cage {
         return fd;
     }
 
+    pub fn _epoll_register(&self, epfd: i32, fd: i32) {
+        let mut epfds = self.epoll_registrations.entry(fd).or_insert_with(Vec::new);
+        if !epfds.contains(&epfd) {
+            epfds.push(epfd);
+        }
+    }
+
+    pub fn _epoll_unregister(&self, epfd: i32, fd: i32) {
+        if let interface::RustHashEntry::Occupied(mut epfds) = self.epoll_registrations.entry(fd) {
+            epfds.get_mut().retain(|registered| *registered != epfd);
+            if epfds.get().is_empty() {
+                epfds.remove();
+            }
+        }
+    }
+
+    //Take a closed fd out of every epoll it was registered with, so that none of them reports it
+    //or mistakes a later fd of the same number for it. The fd's own lock mustn't be held, since
+    //epoll_ctl and epoll_wait lock their epoll fd before the fds registered with it.
+    pub fn _epoll_forget_fd(&self, fd: i32) {
+        if let Some((_, epfds)) = self.epoll_registrations.remove(&fd) {
+            for epfd in epfds {
+                //recursive, since dup2 holds a read lock on the fd it duplicates, which may be one
+                //of the epolls
+                let checkedfd = self.get_filedescriptor(epfd).unwrap();
+                if let Some(Epoll(epollfdobj)) = &*checkedfd.read_recursive() {
+                    epollfdobj.registered_fds.remove(&fd);
+                }
+            }
+        }
+    }
+
     pub fn epoll_create_syscall(&self, size: i32) -> i32 {
         if size <= 0 {
             return syscall_error(
@@ -2647,7 +3198,14 @@ impl Cage {
                 "provided size argument is invalid",
             );
         }
-        return Self::_epoll_object_allocator(self);
+        return Self::_epoll_object_allocator(self, false);
+    }
+
+    pub fn epoll_create1_syscall(&self, flags: i32) -> i32 {
+        if flags & !EPOLL_CLOEXEC != 0 {
+            return syscall_error(Errno::EINVAL, "epoll create", "invalid flags specified");
+        }
+        return Self::_epoll_object_allocator(self, flags & EPOLL_CLOEXEC != 0);
     }
 
     //this one can still be optimized
@@ -2679,9 +3237,14 @@ impl Cage {
                 //now that we know that the types are all good...
                 match op {
                     EPOLL_CTL_DEL => {
-                        //since remove returns the value at the key and the values will always be EpollEvents,
-                        //I am using this to optimize the code
-                        epollfdobj.registered_fds.remove(&fd).unwrap().1;
+                        if epollfdobj.registered_fds.remove(&fd).is_none() {
+                            return syscall_error(
+                                Errno::ENOENT,
+                                "epoll ctl",
+                                "fd is not registered with this epfd",
+                            );
+                        }
+                        self._epoll_unregister(epfd, fd);
                     }
                     EPOLL_CTL_MOD => {
                         //check if the fd that we are modifying exists or not
@@ -2716,6 +3279,7 @@ impl Cage {
                                 fd: event.fd,
                             },
                         );
+                        self._epoll_register(epfd, fd);
                     }
                     _ => {
                         return syscall_error(Errno::EINVAL, "epoll ctl", "provided op is invalid");
@@ -2745,9 +3309,10 @@ impl Cage {
         maxevents: i32,
         timeout: Option<interface::RustDuration>,
     ) -> i32 {
+        //only read locked, so that closing one of the fds doesn't wait for the wait to end
         let checkedfd = self.get_filedescriptor(epfd).unwrap();
-        let mut unlocked_fd = checkedfd.write();
-        if let Some(filedesc_enum) = &mut *unlocked_fd {
+        let unlocked_fd = checkedfd.read();
+        if let Some(filedesc_enum) = &*unlocked_fd {
             if let Epoll(epollfdobj) = filedesc_enum {
                 if maxevents < 0 {
                     return syscall_error(
@@ -2756,6 +3321,13 @@ impl Cage {
                         "max events argument is not a positive number",
                     );
                 }
+                if maxevents > self.max_watched_fds() {
+                    return syscall_error(
+                        Errno::EINVAL,
+                        "epoll wait",
+                        "max events argument is more than the cage may watch",
+                    );
+                }
                 let mut poll_fds_vec: Vec<PollStruct> = vec![];
                 let mut rm_fds_vec: Vec<i32> = vec![];
                 let mut num_events: usize = 0;
@@ -2791,6 +3363,7 @@ impl Cage {
 
                 for fd in rm_fds_vec.iter() {
                     epollfdobj.registered_fds.remove(fd);
+                    self._epoll_unregister(epfd, *fd);
                 } // remove closed fds
 
                 let poll_fds_slice = &mut poll_fds_vec[..];
@@ -2802,9 +3375,14 @@ impl Cage {
                 let end_idx: usize = interface::rust_min(num_events, maxevents as usize);
                 for result in poll_fds_slice[..end_idx].iter() {
                     let mut poll_event = false;
+                    //an fd closed during the wait has been taken out already, and isn't reported
+                    let registered = match epollfdobj.registered_fds.get(&result.fd) {
+                        Some(registered) => registered.fd,
+                        None => continue,
+                    };
                     let mut event = EpollEvent {
                         events: 0,
-                        fd: epollfdobj.registered_fds.get(&result.fd).unwrap().fd,
+                        fd: registered,
                     };
                     if result.revents & POLLIN > 0 {
                         event.events |= EPOLLIN as u32;
@@ -2891,26 +3469,36 @@ impl Cage {
         );
         let sock2fd = this._socket_inserter(Socket(sock2fdobj.clone()));
 
-        // assign local addresses and connect
+        // the ends are unnamed, like linux's, so neither has a path in the filesystem
         let sock1tmp = sock1fdobj.handle.clone();
         let sock2tmp = sock2fdobj.handle.clone();
         let mut sock1handle = sock1tmp.write();
         let mut sock2handle = sock2tmp.write();
-        let localaddr1 = Self::assign_new_addr_unix(&sock1handle);
-        let localaddr2 = Self::assign_new_addr_unix(&sock2handle);
-        this.bind_inner_socket(&mut *sock1handle, &localaddr1, false);
-        this.bind_inner_socket(&mut *sock2handle, &localaddr2, false);
+        let unnamed =
+            interface::GenSockaddr::Unix(interface::new_sockaddr_unix(AF_UNIX as u16, &[]));
 
         // setup the pipes
-        let (pipe1, pipe2) = create_unix_sockpipes();
-        // one handle's remote address is the other's local address
-        sock1handle.remoteaddr = Some(localaddr2.clone());
-        sock2handle.remoteaddr = Some(localaddr1.clone());
-        // one handle's sendpipe is the other's receivepipe
-        sock1handle.unix_info.as_mut().unwrap().sendpipe = Some(pipe1.clone());
-        sock1handle.unix_info.as_mut().unwrap().receivepipe = Some(pipe2.clone());
-        sock2handle.unix_info.as_mut().unwrap().sendpipe = Some(pipe2.clone());
-        sock2handle.unix_info.as_mut().unwrap().receivepipe = Some(pipe1.clone());
+        let (pipe1, pipe2) = create_unix_sockpipes(&sock1handle);
+        // each handle's remote address is the other's, which is unnamed
+        sock1handle.remoteaddr = Some(unnamed.clone());
+        sock2handle.remoteaddr = Some(unnamed.clone());
+        // one handle's sendpipe is the other's receivepipe, and both ends belong to the caller
+        sock1handle.unix_info = Some(UnixSocketInfo {
+            mode: S_IFSOCK | 0o666,
+            sendpipe: Some(pipe1.clone()),
+            receivepipe: Some(pipe2.clone()),
+            inode: this.anonymous_socket_inode(),
+            peercred: Some(this.ucred()),
+            boundpath: None,
+        });
+        sock2handle.unix_info = Some(UnixSocketInfo {
+            mode: S_IFSOCK | 0o666,
+            sendpipe: Some(pipe2.clone()),
+            receivepipe: Some(pipe1.clone()),
+            inode: this.anonymous_socket_inode(),
+            peercred: Some(this.ucred()),
+            boundpath: None,
+        });
 
         // now they are connected
         sock1handle.state = ConnState::CONNECTED;
@@ -2919,22 +3507,76 @@ impl Cage {
         sv.sock1 = sock1fd;
         sv.sock2 = sock2fd;
 
-        // we need to increment the refcount of the sockets we created
-        // reason: in bind_inner_socket, we added entries to the inode table
-        let inode1num = sock1handle.unix_info.as_mut().unwrap().inode;
-        if let Inode::Socket(ref mut sock) = *(FS_METADATA.inodetable.get_mut(&inode1num).unwrap())
-        {
-            sock.refcount += 1;
-        }
-        let inode2num = sock2handle.unix_info.as_mut().unwrap().inode;
-        if let Inode::Socket(ref mut sock) = *(FS_METADATA.inodetable.get_mut(&inode2num).unwrap())
-        {
-            sock.refcount += 1;
-        }
-
         return 0;
     }
 
+    //A socket inode linked nowhere, for an unnamed unix socket. Its one reference is the socket's
+    //own, so it goes away when the last descriptor of the socket is closed.
+    fn anonymous_socket_inode(&self) -> usize {
+        let newinodenum = FS_METADATA
+            .nextinode
+            .fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+        let time = interface::timestamp();
+        let cred = self.cred.read().clone();
+        FS_METADATA.inodetable.insert(
+            newinodenum,
+            Inode::Socket(SocketInode {
+                size: 0,
+                uid: cred.euid,
+                gid: cred.egid,
+                mode: S_IFSOCK as u32 | 0o777,
+                linkcount: 0,
+                refcount: 1,
+                atime: time,
+                ctime: time,
+                btime: time,
+                mtime: time,
+            }),
+        );
+        newinodenum
+    }
+
+    //Answer one of the SIOCGIF ioctls from the interfaces in net_devices, the same ones
+    //getifaddrs lists, rather than from the host's
+    pub fn _ioctl_ifreq(&self, request: u32, ptrunion: IoctlPtrUnion) -> Result<(), i32> {
+        let ifreq = interface::get_ioctl_ifreq(ptrunion)?;
+        if request == SIOCGIFNAME {
+            let device =
+                net_device_by_index(unsafe { ifreq.ifr_ifru.ifru_ivalue }).ok_or_else(|| {
+                    syscall_error(Errno::ENODEV, "ioctl", "no interface has that index")
+                })?;
+            ifreq.set_name(&device.name);
+            return Ok(());
+        }
+
+        let (index, device) = ifreq
+            .name()
+            .and_then(net_device_by_name)
+            .ok_or_else(|| syscall_error(Errno::ENODEV, "ioctl", "no interface has that name"))?;
+        let sockaddr = |addr: interface::V4Addr| interface::SockaddrV4 {
+            sin_family: interface::family_to_abi(AF_INET as u16),
+            sin_port: 0,
+            sin_addr: addr,
+            padding: 0,
+        };
+        match request {
+            SIOCGIFFLAGS => ifreq.ifr_ifru.ifru_flags = device.flags as i16,
+            SIOCGIFADDR => ifreq.ifr_ifru.ifru_addr = sockaddr(device.addr),
+            SIOCGIFNETMASK => ifreq.ifr_ifru.ifru_addr = sockaddr(device.netmask),
+            SIOCGIFBRDADDR => ifreq.ifr_ifru.ifru_addr = sockaddr(device.broadaddr),
+            SIOCGIFMTU => ifreq.ifr_ifru.ifru_mtu = device.mtu(),
+            SIOCGIFINDEX => ifreq.ifr_ifru.ifru_ivalue = index as i32,
+            _ => {
+                return Err(syscall_error(
+                    Errno::EINVAL,
+                    "ioctl",
+                    "Arguments provided do not match implemented parameters",
+                ))
+            }
+        }
+        Ok(())
+    }
+
     // all this does is send the net_devs data in a string to libc, where we will later parse and
     // alloc into getifaddrs structs
     pub fn getifaddrs_syscall(&self, buf: *mut u8, count: usize) -> i32 {
@@ -21,6 +21,8 @@ pub const SOCK_SEQPACKET: i32 = 5; //sequenced packet stream
 pub const SOCK_CLOEXEC: i32 = 0o02000000; // Atomically set close-on-exec
 pub const SOCK_NONBLOCK: i32 = 0o00004000; // Mark as non-blocking
 
+pub const PROT_SOCK: u16 = 1024; // binding a port below this takes CAP_NET_BIND_SERVICE
+
 /* Supported address families. */
 pub const AF_UNSPEC: i32 = 0;
 pub const AF_UNIX: i32 = 1; /* Unix domain sockets   */
@@ -298,6 +300,7 @@ pub const SO_RCVLOWAT: i32 = 18;
 pub const SO_SNDLOWAT: i32 = 19;
 pub const SO_RCVTIMEO_OLD: i32 = 20;
 pub const SO_SNDTIMEO_OLD: i32 = 21;
+pub const SO_SNDTIMEO: i32 = SO_SNDTIMEO_OLD; // the timeval of a 64 bit host
 pub const SO_PEERNAME: i32 = 28;
 pub const SO_ACCEPTCONN: i32 = 30;
 
@@ -378,7 +381,41 @@ pub const EPOLL_CTL_ADD: i32 = 1;
 pub const EPOLL_CTL_DEL: i32 = 2;
 pub const EPOLL_CTL_MOD: i32 = 3;
 
+pub const EPOLL_CLOEXEC: i32 = 0o2000000;
+
 pub const FD_SET_MAX_FD: i32 = 1024;
+//how many fds select, poll and epoll_wait watch at most, unless the embedder sets another limit
+pub const MAX_WATCHED_FDS: i32 = 4 * 1024;
+
+//INTERFACE IOCTLS, each passed a struct ifreq naming the interface
+pub const SIOCGIFNAME: u32 = 0x8910; // by index
+pub const SIOCGIFFLAGS: u32 = 0x8913;
+pub const SIOCGIFADDR: u32 = 0x8915;
+pub const SIOCGIFBRDADDR: u32 = 0x8919;
+pub const SIOCGIFNETMASK: u32 = 0x891b;
+pub const SIOCGIFMTU: u32 = 0x8921;
+pub const SIOCGIFINDEX: u32 = 0x8933;
+
+pub fn is_ifreq_request(request: u32) -> bool {
+    matches!(
+        request,
+        SIOCGIFNAME
+            | SIOCGIFFLAGS
+            | SIOCGIFADDR
+            | SIOCGIFBRDADDR
+            | SIOCGIFNETMASK
+            | SIOCGIFMTU
+            | SIOCGIFINDEX
+    )
+}
+
+pub const IFNAMSIZ: usize = 16;
+pub const IFF_UP: i32 = 0x1;
+pub const IFF_BROADCAST: i32 = 0x2;
+pub const IFF_LOOPBACK: i32 = 0x8;
+pub const IFF_RUNNING: i32 = 0x40;
+pub const LOOPBACK_MTU: i32 = 65536;
+pub const ETHERNET_MTU: i32 = 1500;
 
 //for internal use
 #[derive(Debug, PartialEq, Eq, Clone)]
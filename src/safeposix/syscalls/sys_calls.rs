@@ -6,12 +6,80 @@ use super::net_constants::*;
 use super::sys_constants::*;
 use crate::interface;
 use crate::safeposix::cage::{FileDescriptor::*, *};
-use crate::safeposix::filesystem::{decref_dir, metawalk, Inode, FS_METADATA};
+use crate::safeposix::filesystem::{
+    decref_dir, incref_dir, is_jailed, metawalk, Inode, FS_METADATA,
+};
+use crate::safeposix::lifecycle::*;
 use crate::safeposix::net::NET_METADATA;
+use crate::safeposix::procfs::{PROC_MEMFREE_KB, PROC_MEMTOTAL_KB};
+use crate::safeposix::sem::{sem_undo_exec, sem_undo_exit};
 use crate::safeposix::shm::SHM_METADATA;
 
 use std::sync::Arc as RustRfc;
 
+//None for a timeval whose microseconds are out of range or that is negative
+fn timeval_to_duration(tv: &interface::TimeVal) -> Option<interface::RustDuration> {
+    if tv.tv_sec < 0 || tv.tv_usec < 0 || tv.tv_usec >= 1000000 {
+        return None;
+    }
+    Some(interface::RustDuration::new(
+        tv.tv_sec as u64,
+        tv.tv_usec as u32 * 1000,
+    ))
+}
+
+fn duration_to_timeval(duration: interface::RustDuration) -> interface::TimeVal {
+    interface::TimeVal {
+        tv_sec: duration.as_secs() as i64,
+        tv_usec: duration.subsec_micros() as i64,
+    }
+}
+
+//None for a timespec whose nanoseconds are out of range or that is negative
+fn timespec_to_duration(ts: &interface::TimeSpec) -> Option<interface::RustDuration> {
+    if ts.tv_sec < 0 || ts.tv_nsec < 0 || ts.tv_nsec >= 1000000000 {
+        return None;
+    }
+    Some(interface::RustDuration::new(
+        ts.tv_sec as u64,
+        ts.tv_nsec as u32,
+    ))
+}
+
+fn duration_to_timespec(duration: interface::RustDuration) -> interface::TimeSpec {
+    interface::TimeSpec {
+        tv_sec: duration.as_secs() as i64,
+        tv_nsec: duration.subsec_nanos() as i64,
+    }
+}
+
+//what uname reports; the embedder can change the fields it lists with lindsetutsname
+pub static UTSNAME: interface::RustLazyGlobal<interface::RustLock<interface::UtsName>> =
+    interface::RustLazyGlobal::new(|| {
+        interface::RustLock::new(interface::UtsName {
+            sysname: interface::uts_field(DEFAULT_SYSNAME),
+            nodename: interface::uts_field(DEFAULT_HOSTNAME),
+            release: interface::uts_field(DEFAULT_RELEASE),
+            version: interface::uts_field(DEFAULT_VERSION),
+            machine: interface::uts_field(DEFAULT_MACHINE),
+            domainname: interface::uts_field(DEFAULT_DOMAINNAME),
+        })
+    });
+
+//sets one of the UTS_* fields, truncating values too long to fit
+pub fn set_uts_field(field: i32, value: &str) -> i32 {
+    let mut utsname = UTSNAME.write();
+    let slot = match field {
+        UTS_SYSNAME => &mut utsname.sysname,
+        UTS_RELEASE => &mut utsname.release,
+        UTS_VERSION => &mut utsname.version,
+        UTS_MACHINE => &mut utsname.machine,
+        _ => return syscall_error(Errno::EINVAL, "lindsetutsname", "no such utsname field"),
+    };
+    *slot = interface::uts_field(value);
+    0
+}
+
 impl Cage {
     fn unmap_shm_mappings(&self) {
         //unmap shm mappings on exit or exec
@@ -23,12 +91,11 @@ impl Cage {
                     let segment = occupied.get_mut();
                     segment.shminfo.shm_nattch -= 1;
                     segment.shminfo.shm_dtime = interface::timestamp() as isize;
+                    segment.shminfo.shm_lpid = self.cageid as u32;
                     segment.attached_cages.remove(&self.cageid);
 
                     if segment.rmid && segment.shminfo.shm_nattch == 0 {
-                        let key = segment.key;
                         occupied.remove_entry();
-                        metadata.shmkeyidtable.remove(&key);
                     }
                 }
                 interface::RustHashEntry::Vacant(_) => {
@@ -38,68 +105,11 @@ impl Cage {
         }
     }
 
-    pub fn fork_syscall(&self, child_cageid: u64) -> i32 {
-        //construct a new mutex in the child cage where each initialized mutex is in the parent cage
-        let mutextable = self.mutex_table.read();
-        let mut new_mutex_table = vec![];
-        for elem in mutextable.iter() {
-            if elem.is_some() {
-                let new_mutex_result = interface::RawMutex::create();
-                match new_mutex_result {
-                    Ok(new_mutex) => new_mutex_table.push(Some(interface::RustRfc::new(new_mutex))),
-                    Err(_) => {
-                        match Errno::from_discriminant(interface::get_errno()) {
-                            Ok(i) => {
-                                return syscall_error(
-                                    i,
-                                    "fork",
-                                    "The libc call to pthread_mutex_init failed!",
-                                );
-                            }
-                            Err(()) => {
-                                panic!("Unknown errno value from pthread_mutex_init returned!")
-                            }
-                        };
-                    }
-                }
-            } else {
-                new_mutex_table.push(None);
-            }
-        }
-        drop(mutextable);
-
-        //construct a new condvar in the child cage where each initialized condvar is in the parent cage
-        let cvtable = self.cv_table.read();
-        let mut new_cv_table = vec![];
-        for elem in cvtable.iter() {
-            if elem.is_some() {
-                let new_cv_result = interface::RawCondvar::create();
-                match new_cv_result {
-                    Ok(new_cv) => new_cv_table.push(Some(interface::RustRfc::new(new_cv))),
-                    Err(_) => {
-                        match Errno::from_discriminant(interface::get_errno()) {
-                            Ok(i) => {
-                                return syscall_error(
-                                    i,
-                                    "fork",
-                                    "The libc call to pthread_cond_init failed!",
-                                );
-                            }
-                            Err(()) => {
-                                panic!("Unknown errno value from pthread_cond_init returned!")
-                            }
-                        };
-                    }
-                }
-            } else {
-                new_cv_table.push(None);
-            }
-        }
-        drop(cvtable);
-
-        //construct new cage struct with a cloned fdtable
+    //a copy of the fd table whose descriptors each hold their own reference to what they refer to
+    fn copy_fdtable(&self) -> FdTable {
         let newfdtable = init_fdtable();
-        for fd in 0..MAXFD {
+        newfdtable.set_limit(self.filedescriptortable.limit());
+        for fd in 0..self.filedescriptortable.allocated() {
             let checkedfd = self.get_filedescriptor(fd).unwrap();
             let unlocked_fd = checkedfd.read();
             if let Some(filedesc_enum) = &*unlocked_fd {
@@ -156,15 +166,6 @@ impl Cage {
                             }
                         }
                         drop(sockhandle);
-                        let sock_tmp = socket_filedesc_obj.handle.clone();
-                        let mut sockhandle = sock_tmp.write();
-                        if let Some(uinfo) = &mut sockhandle.unix_info {
-                            if let Inode::Socket(ref mut sock) =
-                                *(FS_METADATA.inodetable.get_mut(&uinfo.inode).unwrap())
-                            {
-                                sock.refcount += 1;
-                            }
-                        }
                     }
                     _ => {}
                 }
@@ -175,18 +176,109 @@ impl Cage {
                 //add deep copied fd to fd table
             }
         }
-        let cwd_container = self.cwd.read();
-        if let Some(cwdinodenum) = metawalk(&cwd_container) {
-            if let Inode::Dir(ref mut cwddir) =
-                *(FS_METADATA.inodetable.get_mut(&cwdinodenum).unwrap())
-            {
-                cwddir.refcount += 1;
+        newfdtable
+    }
+
+    pub fn fork_syscall(&self, child_cageid: u64) -> i32 {
+        self.clone_syscall(0, child_cageid)
+    }
+
+    //like fork, but the flags choose whether the child shares the fd table, the cwd and root
+    //directory, and the signal handlers with the parent instead of getting its own copies
+    pub fn clone_syscall(&self, flags: i32, child_cageid: u64) -> i32 {
+        //the exit signal in the low byte is accepted, but an exiting child always sends SIGCHLD
+        if flags & !(CSIGNAL | CLONE_FS | CLONE_FILES | CLONE_SIGHAND) != 0 {
+            return syscall_error(Errno::EINVAL, "clone", "Unsupported clone flags");
+        }
+
+        //construct a new mutex in the child cage where each initialized mutex is in the parent cage
+        let mutextable = self.mutex_table.read();
+        let mut new_mutex_table = vec![];
+        for elem in mutextable.iter() {
+            if elem.is_some() {
+                let new_mutex_result = interface::RawMutex::create();
+                match new_mutex_result {
+                    Ok(new_mutex) => new_mutex_table.push(Some(interface::RustRfc::new(new_mutex))),
+                    Err(errno) => {
+                        return interface::host_syscall_error(
+                            errno,
+                            "fork",
+                            "The libc call to pthread_mutex_init failed!",
+                        );
+                    }
+                }
             } else {
-                panic!("We changed from a directory that was not a directory in chdir!");
+                new_mutex_table.push(None);
+            }
+        }
+        drop(mutextable);
+
+        //construct a new condvar in the child cage where each initialized condvar is in the parent cage
+        let cvtable = self.cv_table.read();
+        let mut new_cv_table = vec![];
+        for elem in cvtable.iter() {
+            if elem.is_some() {
+                let new_cv_result = interface::RawCondvar::create();
+                match new_cv_result {
+                    Ok(new_cv) => new_cv_table.push(Some(interface::RustRfc::new(new_cv))),
+                    Err(errno) => {
+                        return interface::host_syscall_error(
+                            errno,
+                            "fork",
+                            "The libc call to pthread_cond_init failed!",
+                        );
+                    }
+                }
+            } else {
+                new_cv_table.push(None);
             }
-        } else {
-            panic!("We changed from a directory that was not a directory in chdir!");
         }
+        drop(cvtable);
+
+        //construct new cage struct with either the parent's fdtable or a copy of it
+        let (newfdtable, fdtable_users, epoll_registrations) = if flags & CLONE_FILES != 0 {
+            self.fdtable_users
+                .fetch_add(1, interface::RustAtomicOrdering::SeqCst);
+            (
+                self.filedescriptortable.clone(),
+                self.fdtable_users.clone(),
+                self.epoll_registrations.clone(),
+            )
+        } else {
+            (
+                self.copy_fdtable(),
+                interface::RustRfc::new(interface::RustAtomicUsize::new(1)),
+                interface::RustRfc::new((*self.epoll_registrations).clone()),
+            )
+        };
+
+        let (cwd, rootdir, fs_users) = if flags & CLONE_FS != 0 {
+            self.fs_users
+                .fetch_add(1, interface::RustAtomicOrdering::SeqCst);
+            (
+                self.cwd.clone(),
+                self.rootdir.clone(),
+                self.fs_users.clone(),
+            )
+        } else {
+            let cwd_container = self.cwd.read();
+            incref_dir(&cwd_container);
+            let rootdir_container = self.rootdir.read();
+            if is_jailed(&rootdir_container) {
+                incref_dir(&rootdir_container);
+            }
+            (
+                interface::RustRfc::new(interface::RustLock::new(cwd_container.clone())),
+                interface::RustRfc::new(interface::RustLock::new(rootdir_container.clone())),
+                interface::RustRfc::new(interface::RustAtomicUsize::new(1)),
+            )
+        };
+
+        let signalhandler = if flags & CLONE_SIGHAND != 0 {
+            self.signalhandler.clone()
+        } else {
+            interface::RustRfc::new((*self.signalhandler).clone())
+        };
 
         // we grab the parent cages main threads sigset and store it at 0
         // we do this because we haven't established a thread for the cage yet, and dont have a threadid to store it at
@@ -208,6 +300,12 @@ impl Cage {
             newsigset.insert(0, mainsigset);
         }
 
+        // the child's main thread starts out on the cpus of the thread that made it
+        let newaffinity = interface::RustHashMap::new();
+        if let Some(mask) = self.recorded_cpumask(interface::get_pthreadid()) {
+            newaffinity.insert(0, mask);
+        }
+
         /*
          *  Construct a new semaphore table in child cage which equals to the one in the parent cage
          */
@@ -223,10 +321,22 @@ impl Cage {
 
         let cageobj = Cage {
             cageid: child_cageid,
-            cwd: interface::RustLock::new(self.cwd.read().clone()),
+            cwd: cwd,
             parent: self.cageid,
+            pgid: interface::RustAtomicU64::new(
+                self.pgid.load(interface::RustAtomicOrdering::Relaxed),
+            ),
+            sid: interface::RustAtomicU64::new(
+                self.sid.load(interface::RustAtomicOrdering::Relaxed),
+            ),
+            nice: interface::RustAtomicI32::new(
+                self.nice.load(interface::RustAtomicOrdering::Relaxed),
+            ),
+            cpu_affinity: newaffinity,
             filedescriptortable: newfdtable,
-            cancelstatus: interface::RustAtomicBool::new(false),
+            fdtable_users: fdtable_users,
+            epoll_registrations: epoll_registrations,
+            cancel_registry: CancelRegistry::new(),
             // This happens because self.getgid tries to copy atomic value which does not implement "Copy" trait; self.getgid.load returns i32.
             getgid: interface::RustAtomicI32::new(
                 self.getgid.load(interface::RustAtomicOrdering::Relaxed),
@@ -240,16 +350,36 @@ impl Cage {
             geteuid: interface::RustAtomicI32::new(
                 self.geteuid.load(interface::RustAtomicOrdering::Relaxed),
             ),
+            cred: interface::RustLock::new(self.cred.read().clone()),
             rev_shm: interface::Mutex::new((*self.rev_shm.lock()).clone()),
             mutex_table: interface::RustLock::new(new_mutex_table),
             cv_table: interface::RustLock::new(new_cv_table),
             sem_table: new_semtable,
             thread_table: interface::RustHashMap::new(),
-            signalhandler: self.signalhandler.clone(),
+            signalhandler: signalhandler,
             sigset: newsigset,
             pendingsigset: interface::RustHashMap::new(),
+            sharedpendingsigset: interface::RustAtomicU64::new(0),
+            sigqueue: interface::Mutex::new(interface::RustBTreeMap::new()),
             main_threadid: interface::RustAtomicU64::new(0),
             interval_timer: interface::IntervalTimer::new(child_cageid),
+            cpu_usage: interface::CpuUsage::new(),
+            memory_region: interface::RustLock::new(None),
+            posix_timers: interface::RustLock::new(vec![]),
+            file_mappings: interface::Mutex::new((*self.file_mappings.lock()).clone()),
+            rootdir: rootdir,
+            fs_users: fs_users,
+            environ: interface::RustLock::new(self.environ.read().clone()),
+            syscall_filter: interface::RustLock::new(self.syscall_filter.read().clone()),
+            quota: interface::RustLock::new(self.quota.read().clone()),
+            zombies: interface::Mutex::new(vec![]),
+            zombie_cv: interface::Condvar::new(),
+            select_epoll: interface::Mutex::new(interface::KernelEpollCache::new()),
+            max_watched_fds: interface::RustAtomicI32::new(
+                self.max_watched_fds
+                    .load(interface::RustAtomicOrdering::Relaxed),
+            ),
+            context: self.context,
         };
 
         let shmtable = &SHM_METADATA.shmtable;
@@ -263,17 +393,27 @@ impl Cage {
             shment.attached_cages.insert(child_cageid, childrefs);
         }
         interface::cagetable_insert(child_cageid, cageobj);
+        fire_lifecycle_hooks(LIFECYCLE_FORK, child_cageid, self.cageid, 0);
 
         0
     }
 
-    pub fn exec_syscall(&self, child_cageid: u64) -> i32 {
-        interface::cagetable_remove(self.cageid);
-
-        self.unmap_shm_mappings();
+    //the last cage using an fd table closes what is left in it
+    fn release_fdtable(&self) {
+        if self
+            .fdtable_users
+            .fetch_sub(1, interface::RustAtomicOrdering::SeqCst)
+            == 1
+        {
+            for fd in 0..self.filedescriptortable.allocated() {
+                self._close_helper(fd);
+            }
+        }
+    }
 
+    fn close_on_exec(&self) {
         let mut cloexecvec = vec![];
-        for fd in 0..MAXFD {
+        for fd in 0..self.filedescriptortable.allocated() {
             let checkedfd = self.get_filedescriptor(fd).unwrap();
             let unlocked_fd = checkedfd.read();
             if let Some(filedesc_enum) = &*unlocked_fd {
@@ -283,6 +423,8 @@ impl Cage {
                     Socket(s) => s.flags & O_CLOEXEC,
                     Pipe(p) => p.flags & O_CLOEXEC,
                     Epoll(p) => p.flags & O_CLOEXEC,
+                    Inotify(p) => p.flags & O_CLOEXEC,
+                    Mqueue(p) => p.flags & O_CLOEXEC,
                 } != 0
                 {
                     cloexecvec.push(fd);
@@ -290,9 +432,75 @@ impl Cage {
             }
         }
 
-        for fdnum in cloexecvec {
-            self.close_syscall(fdnum);
+        for fdnum in &cloexecvec {
+            //a descriptor whose close fails still mustn't survive the exec
+            if self.close_syscall(*fdnum) < 0 {
+                let _discarded_fd = self.get_filedescriptor(*fdnum).unwrap().write().take();
+                self._epoll_forget_fd(*fdnum);
+            }
+        }
+    }
+
+    //envp replaces the environment of the new image, which otherwise keeps the caller's
+    pub fn exec_syscall(&self, child_cageid: u64, envp: Option<Vec<&str>>) -> i32 {
+        interface::cagetable_remove(self.cageid);
+
+        // the new image takes a new cage id, so a group or session it leads follows it there
+        for cage in interface::cagetable_getrefs() {
+            let _ = cage.pgid.compare_exchange(
+                self.cageid,
+                child_cageid,
+                interface::RustAtomicOrdering::Relaxed,
+                interface::RustAtomicOrdering::Relaxed,
+            );
+            let _ = cage.sid.compare_exchange(
+                self.cageid,
+                child_cageid,
+                interface::RustAtomicOrdering::Relaxed,
+                interface::RustAtomicOrdering::Relaxed,
+            );
         }
+        let newpgid = match self.pgid.load(interface::RustAtomicOrdering::Relaxed) {
+            pgid if pgid == self.cageid => child_cageid,
+            pgid => pgid,
+        };
+        let newsid = match self.sid.load(interface::RustAtomicOrdering::Relaxed) {
+            sid if sid == self.cageid => child_cageid,
+            sid => sid,
+        };
+        //the new image keeps its ids, and saves the effective ones it starts with
+        let mut newcred = self.cred.read().clone();
+        newcred.suid = newcred.euid;
+        newcred.sgid = newcred.egid;
+        newcred.exec_caps();
+
+        self.unmap_shm_mappings();
+        sem_undo_exec(self.cageid, child_cageid);
+        self.delete_posix_timers();
+
+        //a table shared through clone stays with the cages sharing it, and the new image gets its
+        //own copy instead
+        let unshare_fdtable = self
+            .fdtable_users
+            .load(interface::RustAtomicOrdering::SeqCst)
+            > 1;
+        let (newfdtable, fdtable_users, epoll_registrations) = if unshare_fdtable {
+            let newfdtable = self.copy_fdtable();
+            let epoll_registrations = interface::RustRfc::new((*self.epoll_registrations).clone());
+            self.release_fdtable();
+            (
+                newfdtable,
+                interface::RustRfc::new(interface::RustAtomicUsize::new(1)),
+                epoll_registrations,
+            )
+        } else {
+            self.close_on_exec();
+            (
+                self.filedescriptortable.clone(),
+                self.fdtable_users.clone(),
+                self.epoll_registrations.clone(),
+            )
+        };
 
         // we grab the parent cages main threads sigset and store it at 0
         // this way the child can initialize the sigset properly when it establishes its own mainthreadid
@@ -313,61 +521,237 @@ impl Cage {
             newsigset.insert(0, mainsigset);
         }
 
+        // the child's main thread starts out on the cpus of the thread that made it
+        let newaffinity = interface::RustHashMap::new();
+        if let Some(mask) = self.recorded_cpumask(interface::get_pthreadid()) {
+            newaffinity.insert(0, mask);
+        }
+
         let newcage = Cage {
             cageid: child_cageid,
-            cwd: interface::RustLock::new(self.cwd.read().clone()),
+            cwd: self.cwd.clone(),
             parent: self.parent,
-            filedescriptortable: self.filedescriptortable.clone(),
-            cancelstatus: interface::RustAtomicBool::new(false),
+            pgid: interface::RustAtomicU64::new(newpgid),
+            sid: interface::RustAtomicU64::new(newsid),
+            nice: interface::RustAtomicI32::new(
+                self.nice.load(interface::RustAtomicOrdering::Relaxed),
+            ),
+            cpu_affinity: newaffinity,
+            filedescriptortable: newfdtable,
+            fdtable_users: fdtable_users,
+            epoll_registrations: epoll_registrations,
+            cancel_registry: CancelRegistry::new(),
             getgid: interface::RustAtomicI32::new(-1),
             getuid: interface::RustAtomicI32::new(-1),
             getegid: interface::RustAtomicI32::new(-1),
             geteuid: interface::RustAtomicI32::new(-1),
+            cred: interface::RustLock::new(newcred),
             rev_shm: interface::Mutex::new(vec![]),
             mutex_table: interface::RustLock::new(vec![]),
             cv_table: interface::RustLock::new(vec![]),
             sem_table: interface::RustHashMap::new(),
             thread_table: interface::RustHashMap::new(),
-            signalhandler: interface::RustHashMap::new(),
+            signalhandler: interface::RustRfc::new(interface::RustHashMap::new()),
             sigset: newsigset,
             pendingsigset: interface::RustHashMap::new(),
+            sharedpendingsigset: interface::RustAtomicU64::new(
+                self.sharedpendingsigset
+                    .load(interface::RustAtomicOrdering::Relaxed),
+            ),
+            sigqueue: interface::Mutex::new(self.sigqueue.lock().clone()),
             main_threadid: interface::RustAtomicU64::new(0),
             interval_timer: self.interval_timer.clone_with_new_cageid(child_cageid),
+            cpu_usage: self.cpu_usage.carry_over(),
+            memory_region: interface::RustLock::new(None),
+            posix_timers: interface::RustLock::new(vec![]),
+            file_mappings: interface::Mutex::new(vec![]),
+            rootdir: self.rootdir.clone(),
+            fs_users: self.fs_users.clone(),
+            environ: interface::RustLock::new(match envp {
+                Some(envp) => envp.iter().map(|entry| entry.to_string()).collect(),
+                None => self.environ.read().clone(),
+            }),
+            syscall_filter: interface::RustLock::new(self.syscall_filter.read().clone()),
+            quota: interface::RustLock::new(self.quota.read().clone()),
+            // children that already exited can still be waited for by the new image
+            zombies: interface::Mutex::new(std::mem::take(&mut *self.zombies.lock())),
+            zombie_cv: interface::Condvar::new(),
+            select_epoll: interface::Mutex::new(interface::KernelEpollCache::new()),
+            max_watched_fds: interface::RustAtomicI32::new(
+                self.max_watched_fds
+                    .load(interface::RustAtomicOrdering::Relaxed),
+            ),
+            context: self.context,
         };
-        //wasteful clone of fdtable, but mutability constraints exist
+        if unshare_fdtable {
+            newcage.close_on_exec();
+        }
 
         interface::cagetable_insert(child_cageid, newcage);
+        fire_lifecycle_hooks(LIFECYCLE_EXEC, child_cageid, self.cageid, 0);
         0
     }
 
     pub fn exit_syscall(&self, status: i32) -> i32 {
+        // the low byte of the exit status goes in the second byte of the wait status
+        self.exit_with_wstatus((status & 0xff) << 8);
+        status
+    }
+
+    // for when the default action of a signal, rather than a call to exit, ends the cage
+    pub fn exit_by_signal(&self, sig: i32) {
+        self.exit_with_wstatus(sig & 0x7f);
+    }
+
+    fn exit_with_wstatus(&self, wstatus: i32) {
         //flush anything left in stdout
         interface::flush_stdout();
 
         self.unmap_shm_mappings();
+        sem_undo_exit(self.cageid);
+        self.delete_posix_timers();
 
-        // close fds
-        for fd in 0..MAXFD {
-            self._close_helper(fd);
-        }
+        self.release_fdtable();
 
-        //get file descriptor table into a vector
-        let cwd_container = self.cwd.read();
-        decref_dir(&*cwd_container);
+        //cages sharing the cwd and root directory through clone leave their references alone
+        if self
+            .fs_users
+            .fetch_sub(1, interface::RustAtomicOrdering::SeqCst)
+            == 1
+        {
+            let cwd_container = self.cwd.read();
+            decref_dir(&*cwd_container);
+            let rootdir_container = self.rootdir.read();
+            if is_jailed(&rootdir_container) {
+                decref_dir(&*rootdir_container);
+            }
+        }
 
         //may not be removable in case of lindrustfinalize, we don't unwrap the remove result
-        interface::cagetable_remove(self.cageid);
+        //leaving the cage table and becoming a zombie happen under the parent's lock, so a
+        //parent looking for children never sees this cage as neither running nor exited
+        let parent = if self.parent != self.cageid {
+            interface::cagetable_getref_opt(self.parent)
+        } else {
+            None
+        };
+        match parent {
+            Some(parentcage) => {
+                // a parent that ignores SIGCHLD or set SA_NOCLDWAIT on it has its children reaped
+                // for it, and its waits fail with ECHILD once none are left running
+                let autoreap = parentcage
+                    .signalhandler
+                    .get(&SIGCHLD)
+                    .map_or(false, |action| {
+                        action.sa_handler == SIG_IGN || action.sa_flags & SA_NOCLDWAIT != 0
+                    });
+                let mut zombies = parentcage.zombies.lock();
+                interface::cagetable_remove(self.cageid);
+                if !autoreap {
+                    zombies.push(Zombie {
+                        cageid: self.cageid,
+                        pgid: self.pgid.load(interface::RustAtomicOrdering::Relaxed),
+                        wstatus: wstatus,
+                    });
+                }
+                parentcage.zombie_cv.notify_all();
+                drop(zombies);
 
-        // Trigger SIGCHLD
-        if !interface::RUSTPOSIX_TESTSUITE.load(interface::RustAtomicOrdering::Relaxed) {
-            // dont trigger SIGCHLD for test suite
-            if self.cageid != self.parent {
-                interface::lind_kill_from_id(self.parent, SIGCHLD);
+                // SIGCHLD goes out once the parent can wait for the child, and is dropped if the
+                // parent ignores it
+                let mut info = self.siginfo(SIGCHLD, CLD_EXITED, 0);
+                if wstatus & 0x7f == 0 {
+                    info.si_status = (wstatus >> 8) & 0xff;
+                } else {
+                    info.si_code = CLD_KILLED;
+                    info.si_status = wstatus & 0x7f;
+                }
+                parentcage.queue_signal(info);
             }
+            // nobody is left to wait for an orphan
+            None => interface::cagetable_remove(self.cageid),
         }
 
+        fire_lifecycle_hooks(LIFECYCLE_EXIT, self.cageid, self.parent, wstatus);
+
         //fdtable will be dropped at end of dispatcher scope because of Arc
-        status
+    }
+
+    // -1 waits for any child, 0 for one in the caller's process group, and any other negative
+    // pid for one in the group -pid
+    fn wait_matches(&self, pid: i32, childid: u64, childpgid: u64) -> bool {
+        match pid {
+            -1 => true,
+            0 => childpgid == self.pgid.load(interface::RustAtomicOrdering::Relaxed),
+            pid if pid < -1 => childpgid == -pid as u64,
+            pid => pid as u64 == childid,
+        }
+    }
+
+    pub fn waitpid_syscall(&self, pid: i32, wstatus: Option<&mut i32>, options: i32) -> i32 {
+        if options & !(WNOHANG | WUNTRACED | WCONTINUED) != 0 {
+            return syscall_error(Errno::EINVAL, "waitpid", "Invalid options");
+        }
+
+        let mut zombies = self.zombies.lock();
+        loop {
+            if let Some(index) = zombies
+                .iter()
+                .position(|zombie| self.wait_matches(pid, zombie.cageid, zombie.pgid))
+            {
+                let zombie = zombies.remove(index);
+                if let Some(status) = wstatus {
+                    *status = zombie.wstatus;
+                }
+                return zombie.cageid as i32;
+            }
+
+            // cages never stop or continue, so only exits are ever reported
+            let haschild = interface::cagetable_getrefs().iter().any(|cage| {
+                cage.parent == self.cageid
+                    && cage.cageid != self.cageid
+                    && self.wait_matches(
+                        pid,
+                        cage.cageid,
+                        cage.pgid.load(interface::RustAtomicOrdering::Relaxed),
+                    )
+            });
+            if !haschild {
+                return syscall_error(
+                    Errno::ECHILD,
+                    "waitpid",
+                    "The cage has no children it can wait for",
+                );
+            }
+            if options & WNOHANG != 0 {
+                return 0;
+            }
+            if let Some(interrupted) = self.signal_interrupt("waitpid", true) {
+                return interrupted;
+            }
+            self.zombie_cv
+                .wait_for(&mut zombies, interface::SIGNAL_CHECK_INTERVAL);
+        }
+    }
+
+    pub fn wait_syscall(&self, wstatus: Option<&mut i32>) -> i32 {
+        self.waitpid_syscall(-1, wstatus, 0)
+    }
+
+    pub fn wait4_syscall(
+        &self,
+        pid: i32,
+        wstatus: Option<&mut i32>,
+        options: i32,
+        rusage: Option<&mut Rusage>,
+    ) -> i32 {
+        let ret = self.waitpid_syscall(pid, wstatus, options);
+        if ret > 0 {
+            if let Some(usage) = rusage {
+                *usage = Rusage::default();
+            }
+        }
+        ret
     }
 
     pub fn getpid_syscall(&self) -> i32 {
@@ -377,93 +761,497 @@ impl Cage {
         self.parent as i32 // mimicing the call above -- easy to change later if necessary
     }
 
+    // pid 0 names the calling cage
+    fn cage_from_pid(&self, pid: i32) -> Option<interface::RustRfc<Cage>> {
+        if pid == 0 {
+            return interface::cagetable_getref_opt(self.cageid);
+        }
+        if pid < 0 || pid >= interface::MAXCAGEID {
+            return None;
+        }
+        interface::cagetable_getref_opt(pid as u64)
+    }
+
+    pub fn getpgid_syscall(&self, pid: i32) -> i32 {
+        match self.cage_from_pid(pid) {
+            Some(cage) => cage.pgid.load(interface::RustAtomicOrdering::Relaxed) as i32,
+            None => syscall_error(Errno::ESRCH, "getpgid", "Target cage does not exist"),
+        }
+    }
+
+    pub fn getsid_syscall(&self, pid: i32) -> i32 {
+        match self.cage_from_pid(pid) {
+            Some(cage) => cage.sid.load(interface::RustAtomicOrdering::Relaxed) as i32,
+            None => syscall_error(Errno::ESRCH, "getsid", "Target cage does not exist"),
+        }
+    }
+
+    pub fn setpgid_syscall(&self, pid: i32, pgid: i32) -> i32 {
+        if pgid < 0 {
+            return syscall_error(Errno::EINVAL, "setpgid", "pgid is negative");
+        }
+        // only the caller itself or one of its children can be moved
+        let target = match self.cage_from_pid(pid) {
+            Some(cage) if cage.cageid == self.cageid || cage.parent == self.cageid => cage,
+            _ => {
+                return syscall_error(
+                    Errno::ESRCH,
+                    "setpgid",
+                    "pid is neither the calling cage nor one of its children",
+                );
+            }
+        };
+        let pgid = if pgid == 0 {
+            target.cageid
+        } else {
+            pgid as u64
+        };
+
+        let sid = self.sid.load(interface::RustAtomicOrdering::Relaxed);
+        let targetsid = target.sid.load(interface::RustAtomicOrdering::Relaxed);
+        if targetsid != sid {
+            return syscall_error(
+                Errno::EPERM,
+                "setpgid",
+                "the child is in a different session",
+            );
+        }
+        if targetsid == target.cageid {
+            return syscall_error(Errno::EPERM, "setpgid", "pid is a session leader");
+        }
+        // joining a group other than its own needs that group to exist in the same session
+        if pgid != target.cageid
+            && !interface::cagetable_getrefs().iter().any(|cage| {
+                cage.pgid.load(interface::RustAtomicOrdering::Relaxed) == pgid
+                    && cage.sid.load(interface::RustAtomicOrdering::Relaxed) == sid
+            })
+        {
+            return syscall_error(
+                Errno::EPERM,
+                "setpgid",
+                "no process group with that id exists in the session",
+            );
+        }
+
+        target
+            .pgid
+            .store(pgid, interface::RustAtomicOrdering::Relaxed);
+        0
+    }
+
+    pub fn setsid_syscall(&self) -> i32 {
+        // a group leader can't leave its group behind, and neither can anyone whose id names one
+        if interface::cagetable_getrefs()
+            .iter()
+            .any(|cage| cage.pgid.load(interface::RustAtomicOrdering::Relaxed) == self.cageid)
+        {
+            return syscall_error(
+                Errno::EPERM,
+                "setsid",
+                "the calling cage is already a process group leader",
+            );
+        }
+        self.pgid
+            .store(self.cageid, interface::RustAtomicOrdering::Relaxed);
+        self.sid
+            .store(self.cageid, interface::RustAtomicOrdering::Relaxed);
+        self.cageid as i32
+    }
+
     /*if its negative 1
-    return -1, but also set the values in the cage struct to the DEFAULTs for future calls*/
+    return -1, but also set the values in the cage struct to the ids for future calls*/
     pub fn getgid_syscall(&self) -> i32 {
+        let id = self.cred.read().rgid as i32;
         if self.getgid.load(interface::RustAtomicOrdering::Relaxed) == -1 {
             self.getgid
-                .store(DEFAULT_GID as i32, interface::RustAtomicOrdering::Relaxed);
+                .store(id, interface::RustAtomicOrdering::Relaxed);
             return -1;
         }
-        DEFAULT_GID as i32 //Lind is only run in one group so a default value is returned
+        id
     }
     pub fn getegid_syscall(&self) -> i32 {
+        let id = self.cred.read().egid as i32;
         if self.getegid.load(interface::RustAtomicOrdering::Relaxed) == -1 {
             self.getegid
-                .store(DEFAULT_GID as i32, interface::RustAtomicOrdering::Relaxed);
+                .store(id, interface::RustAtomicOrdering::Relaxed);
             return -1;
         }
-        DEFAULT_GID as i32 //Lind is only run in one group so a default value is returned
+        id
     }
 
     pub fn getuid_syscall(&self) -> i32 {
+        let id = self.cred.read().ruid as i32;
         if self.getuid.load(interface::RustAtomicOrdering::Relaxed) == -1 {
             self.getuid
-                .store(DEFAULT_UID as i32, interface::RustAtomicOrdering::Relaxed);
+                .store(id, interface::RustAtomicOrdering::Relaxed);
             return -1;
         }
-        DEFAULT_UID as i32 //Lind is only run as one user so a default value is returned
+        id
     }
     pub fn geteuid_syscall(&self) -> i32 {
+        let id = self.cred.read().euid as i32;
         if self.geteuid.load(interface::RustAtomicOrdering::Relaxed) == -1 {
             self.geteuid
-                .store(DEFAULT_UID as i32, interface::RustAtomicOrdering::Relaxed);
+                .store(id, interface::RustAtomicOrdering::Relaxed);
             return -1;
         }
-        DEFAULT_UID as i32 //Lind is only run as one user so a default value is returned
+        id
     }
 
-    pub fn sigaction_syscall(
-        &self,
-        sig: i32,
-        act: Option<&interface::SigactionStruct>,
-        oact: Option<&mut interface::SigactionStruct>,
-    ) -> i32 {
-        if let Some(some_oact) = oact {
-            let old_sigactionstruct = self.signalhandler.get(&sig);
-
-            if let Some(entry) = old_sigactionstruct {
-                some_oact.clone_from(entry.value());
-            } else {
-                some_oact.clone_from(&interface::SigactionStruct::default()); // leave handler field as NULL
-            }
+    //with an empty list only the number of supplementary groups is reported
+    pub fn getgroups_syscall(&self, list: &mut [u32]) -> i32 {
+        let cred = self.cred.read();
+        if list.is_empty() {
+            return cred.groups.len() as i32;
         }
-
-        if let Some(some_act) = act {
-            if sig == 9 || sig == 19 {
-                // Disallow changing the action for SIGKILL and SIGSTOP
-                return syscall_error(
-                    Errno::EINVAL,
-                    "sigaction",
-                    "Cannot modify the action of SIGKILL or SIGSTOP",
-                );
-            }
-
-            self.signalhandler.insert(sig, some_act.clone());
+        if list.len() < cred.groups.len() {
+            return syscall_error(
+                Errno::EINVAL,
+                "getgroups",
+                "the list is too small to hold every supplementary group",
+            );
         }
-
-        0
+        list[..cred.groups.len()].copy_from_slice(&cred.groups);
+        cred.groups.len() as i32
     }
 
-    pub fn kill_syscall(&self, cage_id: i32, sig: i32) -> i32 {
-        if (cage_id < 0) || (cage_id >= interface::MAXCAGEID) {
-            return syscall_error(Errno::EINVAL, "sigkill", "Invalid cage id.");
+    pub fn setgroups_syscall(&self, list: &[u32]) -> i32 {
+        if list.len() > NGROUPS_MAX {
+            return syscall_error(
+                Errno::EINVAL,
+                "setgroups",
+                "more supplementary groups than NGROUPS_MAX were given",
+            );
         }
-
-        if let Some(cage) = interface::cagetable_getref_opt(cage_id as u64) {
-            interface::lind_threadkill(
-                cage.main_threadid
-                    .load(interface::RustAtomicOrdering::Relaxed),
-                sig,
+        let mut cred = self.cred.write();
+        if !cred.capable(CAP_SETGID) {
+            return syscall_error(
+                Errno::EPERM,
+                "setgroups",
+                "setting the supplementary groups takes CAP_SETGID",
             );
-            return 0;
-        } else {
-            return syscall_error(Errno::ESRCH, "kill", "Target cage does not exist");
         }
+        cred.groups = list.to_vec();
+        0
     }
 
-    pub fn sigprocmask_syscall(
-        &self,
+    //a cage with CAP_SETUID sets all three uids, any other may only switch its effective uid to
+    //its real or saved one
+    pub fn setuid_syscall(&self, uid: u32) -> i32 {
+        if uid == u32::MAX {
+            return syscall_error(Errno::EINVAL, "setuid", "Invalid uid");
+        }
+        let mut cred = self.cred.write();
+        let (oldruid, oldeuid, oldsuid) = (cred.ruid, cred.euid, cred.suid);
+        if cred.capable(CAP_SETUID) {
+            cred.ruid = uid;
+            cred.euid = uid;
+            cred.suid = uid;
+        } else if uid == cred.ruid || uid == cred.suid {
+            cred.euid = uid;
+        } else {
+            return syscall_error(
+                Errno::EPERM,
+                "setuid",
+                "the caller lacks CAP_SETUID and uid is neither its real nor its saved uid",
+            );
+        }
+        cred.fix_caps_after_setuid(oldruid, oldeuid, oldsuid);
+        0
+    }
+
+    pub fn seteuid_syscall(&self, euid: u32) -> i32 {
+        if euid == u32::MAX {
+            return syscall_error(Errno::EINVAL, "seteuid", "Invalid uid");
+        }
+        let mut cred = self.cred.write();
+        if !cred.capable(CAP_SETUID) && euid != cred.ruid && euid != cred.suid {
+            return syscall_error(
+                Errno::EPERM,
+                "seteuid",
+                "the caller lacks CAP_SETUID and euid is neither its real nor its saved uid",
+            );
+        }
+        let (oldruid, oldeuid, oldsuid) = (cred.ruid, cred.euid, cred.suid);
+        cred.euid = euid;
+        cred.fix_caps_after_setuid(oldruid, oldeuid, oldsuid);
+        0
+    }
+
+    //like setuid, with CAP_SETGID as the privilege
+    pub fn setgid_syscall(&self, gid: u32) -> i32 {
+        if gid == u32::MAX {
+            return syscall_error(Errno::EINVAL, "setgid", "Invalid gid");
+        }
+        let mut cred = self.cred.write();
+        if cred.capable(CAP_SETGID) {
+            cred.rgid = gid;
+            cred.egid = gid;
+            cred.sgid = gid;
+        } else if gid == cred.rgid || gid == cred.sgid {
+            cred.egid = gid;
+        } else {
+            return syscall_error(
+                Errno::EPERM,
+                "setgid",
+                "the caller lacks CAP_SETGID and gid is neither its real nor its saved gid",
+            );
+        }
+        0
+    }
+
+    pub fn setegid_syscall(&self, egid: u32) -> i32 {
+        if egid == u32::MAX {
+            return syscall_error(Errno::EINVAL, "setegid", "Invalid gid");
+        }
+        let mut cred = self.cred.write();
+        if !cred.capable(CAP_SETGID) && egid != cred.rgid && egid != cred.sgid {
+            return syscall_error(
+                Errno::EPERM,
+                "setegid",
+                "the caller lacks CAP_SETGID and egid is neither its real nor its saved gid",
+            );
+        }
+        cred.egid = egid;
+        0
+    }
+
+    //only the capability parts of prctl are supported
+    pub fn prctl_syscall(&self, option: i32, arg2: u64) -> i32 {
+        let mut cred = self.cred.write();
+        match option {
+            PR_GET_KEEPCAPS => cred.keepcaps as i32,
+            PR_SET_KEEPCAPS => match arg2 {
+                0 | 1 => {
+                    cred.keepcaps = arg2 == 1;
+                    0
+                }
+                _ => syscall_error(Errno::EINVAL, "prctl", "keepcaps must be 0 or 1"),
+            },
+            PR_CAPBSET_READ | PR_CAPBSET_DROP => {
+                if arg2 > CAP_LAST_CAP as u64 {
+                    return syscall_error(Errno::EINVAL, "prctl", "not a valid capability");
+                }
+                let mask = cap_to_mask(arg2 as u32);
+                if option == PR_CAPBSET_READ {
+                    return (cred.cap_bounding & mask != 0) as i32;
+                }
+                if !cred.capable(CAP_SETPCAP) {
+                    return syscall_error(
+                        Errno::EPERM,
+                        "prctl",
+                        "dropping from the bounding set takes CAP_SETPCAP",
+                    );
+                }
+                cred.cap_bounding &= !mask;
+                0
+            }
+            _ => syscall_error(Errno::EINVAL, "prctl", "unsupported option"),
+        }
+    }
+
+    //an unsupported version is answered with the one we use, which is all a null data asks for
+    fn cap_version_ok(header: &mut interface::CapUserHeader) -> bool {
+        if header.version == _LINUX_CAPABILITY_VERSION_3 {
+            return true;
+        }
+        header.version = _LINUX_CAPABILITY_VERSION_3;
+        false
+    }
+
+    pub fn capget_syscall(
+        &self,
+        header: &mut interface::CapUserHeader,
+        data: Option<&mut [interface::CapUserData]>,
+    ) -> i32 {
+        let versionok = Self::cap_version_ok(header);
+        let data = match data {
+            Some(data) if versionok => data,
+            Some(_) => {
+                return syscall_error(Errno::EINVAL, "capget", "unsupported capability version")
+            }
+            None => return 0,
+        };
+        if header.pid < 0 {
+            return syscall_error(Errno::EINVAL, "capget", "invalid pid");
+        }
+        let target = if header.pid == 0 {
+            None
+        } else {
+            match interface::cagetable_getref_opt(header.pid as u64) {
+                Some(cage) => Some(cage),
+                None => return syscall_error(Errno::ESRCH, "capget", "no such cage"),
+            }
+        };
+        let cred = match &target {
+            Some(cage) => cage.cred.read().clone(),
+            None => self.cred.read().clone(),
+        };
+        for (i, word) in data.iter_mut().enumerate() {
+            let shift = 32 * i;
+            word.effective = (cred.cap_effective >> shift) as u32;
+            word.permitted = (cred.cap_permitted >> shift) as u32;
+            word.inheritable = (cred.cap_inheritable >> shift) as u32;
+        }
+        0
+    }
+
+    //A cage may only change its own sets. Permitted can only shrink, effective has to stay
+    //within it, and inheritable may only gain what is permitted unless the cage has CAP_SETPCAP,
+    //which lets it add anything left in the bounding set.
+    pub fn capset_syscall(
+        &self,
+        header: &mut interface::CapUserHeader,
+        data: &[interface::CapUserData],
+    ) -> i32 {
+        if !Self::cap_version_ok(header) {
+            return syscall_error(Errno::EINVAL, "capset", "unsupported capability version");
+        }
+        if header.pid != 0 && header.pid as u64 != self.cageid {
+            return syscall_error(
+                Errno::EPERM,
+                "capset",
+                "a cage may only set its own capabilities",
+            );
+        }
+        let (mut effective, mut permitted, mut inheritable) = (0u64, 0u64, 0u64);
+        for (i, word) in data.iter().enumerate() {
+            let shift = 32 * i;
+            effective |= (word.effective as u64) << shift;
+            permitted |= (word.permitted as u64) << shift;
+            inheritable |= (word.inheritable as u64) << shift;
+        }
+        effective &= CAP_FULL_SET;
+        permitted &= CAP_FULL_SET;
+        inheritable &= CAP_FULL_SET;
+
+        let mut cred = self.cred.write();
+        let inheritable_limit = if cred.capable(CAP_SETPCAP) {
+            cred.cap_inheritable | cred.cap_bounding
+        } else {
+            cred.cap_inheritable | cred.cap_permitted
+        };
+        if inheritable & !inheritable_limit != 0
+            || permitted & !cred.cap_permitted != 0
+            || effective & !permitted != 0
+        {
+            return syscall_error(
+                Errno::EPERM,
+                "capset",
+                "the requested capabilities exceed what the cage may hold",
+            );
+        }
+        cred.cap_effective = effective;
+        cred.cap_permitted = permitted;
+        cred.cap_inheritable = inheritable;
+        0
+    }
+
+    pub fn sigaction_syscall(
+        &self,
+        sig: i32,
+        act: Option<&interface::SigactionStruct>,
+        oact: Option<&mut interface::SigactionStruct>,
+    ) -> i32 {
+        if let Some(some_oact) = oact {
+            let old_sigactionstruct = self.signalhandler.get(&sig);
+
+            if let Some(entry) = old_sigactionstruct {
+                some_oact.clone_from(entry.value());
+            } else {
+                some_oact.clone_from(&interface::SigactionStruct::default()); // leave handler field as NULL
+            }
+        }
+
+        if let Some(some_act) = act {
+            if sig == 9 || sig == 19 {
+                // Disallow changing the action for SIGKILL and SIGSTOP
+                return syscall_error(
+                    Errno::EINVAL,
+                    "sigaction",
+                    "Cannot modify the action of SIGKILL or SIGSTOP",
+                );
+            }
+
+            self.signalhandler.insert(sig, some_act.clone());
+            if self.signal_ignored(sig) {
+                self.discard_pending_signal(sig);
+            }
+        }
+
+        0
+    }
+
+    pub fn kill_syscall(&self, cage_id: i32, sig: i32) -> i32 {
+        if (cage_id == -1)
+            || (cage_id <= -interface::MAXCAGEID)
+            || (cage_id >= interface::MAXCAGEID)
+        {
+            return syscall_error(Errno::EINVAL, "sigkill", "Invalid cage id.");
+        }
+        if sig < 0 || sig > SIGNAL_MAX {
+            return syscall_error(Errno::EINVAL, "kill", "Invalid signal number.");
+        }
+
+        // 0 signals every cage in the caller's process group, and -pgid every cage in group pgid
+        if cage_id <= 0 {
+            let pgid = if cage_id == 0 {
+                self.pgid.load(interface::RustAtomicOrdering::Relaxed)
+            } else {
+                -cage_id as u64
+            };
+            let members: Vec<interface::RustRfc<Cage>> = interface::cagetable_getrefs()
+                .into_iter()
+                .filter(|cage| cage.pgid.load(interface::RustAtomicOrdering::Relaxed) == pgid)
+                .collect();
+            if members.is_empty() {
+                return syscall_error(Errno::ESRCH, "kill", "Target process group is empty");
+            }
+            for cage in members {
+                cage.queue_signal(self.siginfo(sig, SI_USER, 0));
+            }
+            return 0;
+        }
+
+        if let Some(cage) = interface::cagetable_getref_opt(cage_id as u64) {
+            return cage.queue_signal(self.siginfo(sig, SI_USER, 0));
+        } else {
+            return syscall_error(Errno::ESRCH, "kill", "Target cage does not exist");
+        }
+    }
+
+    //the siginfo a signal this cage sends carries
+    fn siginfo(&self, sig: i32, code: i32, value: u64) -> interface::SiginfoStruct {
+        interface::SiginfoStruct {
+            si_signo: sig,
+            si_errno: 0,
+            si_code: code,
+            si_pid: self.cageid as i32,
+            si_uid: self.cred.read().ruid,
+            si_status: 0,
+            si_value: value,
+            si_timerid: 0,
+            si_overrun: 0,
+        }
+    }
+
+    // Send sig to a single cage along with value, which its SA_SIGINFO handler finds in si_value.
+    // A real-time signal is queued once per call, so none of the values are lost.
+    pub fn sigqueue_syscall(&self, cage_id: i32, sig: i32, value: u64) -> i32 {
+        if sig < 0 || sig > SIGNAL_MAX {
+            return syscall_error(Errno::EINVAL, "sigqueue", "Invalid signal number.");
+        }
+        if cage_id <= 0 || cage_id >= interface::MAXCAGEID {
+            return syscall_error(Errno::ESRCH, "sigqueue", "Target cage does not exist");
+        }
+        match interface::cagetable_getref_opt(cage_id as u64) {
+            Some(cage) => cage.queue_signal(self.siginfo(sig, SI_QUEUE, value)),
+            None => syscall_error(Errno::ESRCH, "sigqueue", "Target cage does not exist"),
+        }
+    }
+
+    pub fn sigprocmask_syscall(
+        &self,
         how: i32,
         set: Option<&interface::SigsetType>,
         oldset: Option<&mut interface::SigsetType>,
@@ -491,7 +1279,7 @@ impl Cage {
                 SIG_UNBLOCK => {
                     // Unblock signals in set
                     let newset = curr_sigset & !*some_set;
-                    let pendingsignals = curr_sigset & some_set;
+                    let pendingsignals = self.pending_signals(pthreadid) & curr_sigset & some_set;
                     sigset.store(newset, interface::RustAtomicOrdering::Relaxed);
                     self.send_pending_signals(pendingsignals, pthreadid);
                     0
@@ -513,39 +1301,586 @@ impl Cage {
         new_value: Option<&interface::ITimerVal>,
         old_value: Option<&mut interface::ITimerVal>,
     ) -> i32 {
-        match which {
-            ITIMER_REAL => {
-                if let Some(some_old_value) = old_value {
-                    let (curr_duration, next_duration) = self.interval_timer.get_itimer();
-                    some_old_value.it_value.tv_sec = curr_duration.as_secs() as i64;
-                    some_old_value.it_value.tv_usec = curr_duration.subsec_millis() as i64;
-                    some_old_value.it_interval.tv_sec = next_duration.as_secs() as i64;
-                    some_old_value.it_interval.tv_usec = next_duration.subsec_millis() as i64;
+        if which != ITIMER_REAL && which != ITIMER_VIRTUAL && which != ITIMER_PROF {
+            return syscall_error(Errno::EINVAL, "setitimer", "which is not a valid timer");
+        }
+        let newdurations = match new_value {
+            Some(some_new_value) => {
+                match (
+                    timeval_to_duration(&some_new_value.it_value),
+                    timeval_to_duration(&some_new_value.it_interval),
+                ) {
+                    (Some(curr_duration), Some(next_duration)) => {
+                        Some((curr_duration, next_duration))
+                    }
+                    _ => {
+                        return syscall_error(
+                            Errno::EINVAL,
+                            "setitimer",
+                            "the timer value holds an invalid timeval",
+                        )
+                    }
                 }
+            }
+            None => None,
+        };
 
-                if let Some(some_new_value) = new_value {
-                    let curr_duration = interface::RustDuration::new(
-                        some_new_value.it_value.tv_sec as u64,
-                        some_new_value.it_value.tv_usec as u32,
-                    );
-                    let next_duration = interface::RustDuration::new(
-                        some_new_value.it_interval.tv_sec as u64,
-                        some_new_value.it_interval.tv_usec as u32,
-                    );
+        if let Some(some_old_value) = old_value {
+            let (curr_duration, next_duration) = match which {
+                ITIMER_REAL => self.interval_timer.get_itimer(),
+                _ => self.cpu_usage.get_cputimer(which == ITIMER_VIRTUAL),
+            };
+            some_old_value.it_value = duration_to_timeval(curr_duration);
+            some_old_value.it_interval = duration_to_timeval(next_duration);
+        }
+
+        if let Some((curr_duration, next_duration)) = newdurations {
+            match which {
+                ITIMER_REAL => self.interval_timer.set_itimer(curr_duration, next_duration),
+                // the virtual timer counts down the time the cage spends running its own code
+                // and sends SIGVTALRM, the profiling timer that and the time spent in syscalls
+                // too and sends SIGPROF
+                _ => self.cpu_usage.set_cputimer(
+                    which == ITIMER_VIRTUAL,
+                    curr_duration,
+                    next_duration,
+                ),
+            }
+        }
+        0
+    }
+
+    // The time on one of the clocks, or None for an unknown clock id. The process cpu clock counts
+    // the time the cage's threads have been charged so far, which is up to their last syscall.
+    pub fn clock_now(&self, clockid: i32) -> Option<interface::RustDuration> {
+        match clockid {
+            CLOCK_REALTIME => Some(interface::clock_time(libc::CLOCK_REALTIME)),
+            CLOCK_MONOTONIC => Some(interface::clock_time(libc::CLOCK_MONOTONIC)),
+            CLOCK_MONOTONIC_RAW => Some(interface::clock_time(libc::CLOCK_MONOTONIC_RAW)),
+            CLOCK_PROCESS_CPUTIME_ID => {
+                Some(self.cpu_usage.user_time() + self.cpu_usage.system_time())
+            }
+            CLOCK_THREAD_CPUTIME_ID => Some(interface::thread_cputime()),
+            _ => None,
+        }
+    }
+
+    pub fn clock_gettime_syscall(&self, clockid: i32, tp: Option<&mut interface::TimeSpec>) -> i32 {
+        let now = match self.clock_now(clockid) {
+            Some(now) => now,
+            None => {
+                return syscall_error(Errno::EINVAL, "clock_gettime", "clockid is not a clock");
+            }
+        };
+        match tp {
+            Some(tp) => {
+                *tp = duration_to_timespec(now);
+                0
+            }
+            None => syscall_error(Errno::EFAULT, "clock_gettime", "tp is null"),
+        }
+    }
 
-                    self.interval_timer.set_itimer(curr_duration, next_duration);
+    //every clock reads to the nanosecond
+    pub fn clock_getres_syscall(&self, clockid: i32, res: Option<&mut interface::TimeSpec>) -> i32 {
+        if self.clock_now(clockid).is_none() {
+            return syscall_error(Errno::EINVAL, "clock_getres", "clockid is not a clock");
+        }
+        if let Some(res) = res {
+            *res = duration_to_timespec(interface::RustDuration::from_nanos(1));
+        }
+        0
+    }
+
+    // Sleep for request, or with TIMER_ABSTIME until clockid reads request. A signal the caller
+    // doesn't block cuts the sleep short with EINTR, and a relative sleep then puts the time it
+    // had left in remain. The cpu time clocks can't be slept on.
+    pub fn clock_nanosleep_syscall(
+        &self,
+        clockid: i32,
+        flags: i32,
+        request: Option<&interface::TimeSpec>,
+        remain: Option<&mut interface::TimeSpec>,
+    ) -> i32 {
+        match clockid {
+            CLOCK_REALTIME | CLOCK_MONOTONIC | CLOCK_MONOTONIC_RAW => {}
+            CLOCK_PROCESS_CPUTIME_ID => {
+                return syscall_error(
+                    Errno::EOPNOTSUPP,
+                    "clock_nanosleep",
+                    "sleeping on the process cpu time clock is not supported",
+                );
+            }
+            _ => {
+                return syscall_error(
+                    Errno::EINVAL,
+                    "clock_nanosleep",
+                    "clockid is not a clock that can be slept on",
+                );
+            }
+        }
+        let request = match request {
+            Some(request) => request,
+            None => {
+                return syscall_error(Errno::EFAULT, "clock_nanosleep", "request is null");
+            }
+        };
+        let requested = match timespec_to_duration(request) {
+            Some(requested) => requested,
+            None => {
+                return syscall_error(
+                    Errno::EINVAL,
+                    "clock_nanosleep",
+                    "request is negative or its nanosecond count is more than 1 billion",
+                );
+            }
+        };
+
+        // an absolute sleep reads the clock afresh each time, so it follows the clock being set
+        let absolute = flags & TIMER_ABSTIME != 0;
+        let start = interface::RustInstant::now();
+        let remaining = || {
+            if absolute {
+                requested.saturating_sub(self.clock_now(clockid).unwrap())
+            } else {
+                requested.saturating_sub(start.elapsed())
+            }
+        };
+        loop {
+            let left = remaining();
+            if left.is_zero() {
+                return 0;
+            }
+            if let Some(e) = self.signal_interrupt("clock_nanosleep", false) {
+                if !absolute {
+                    if let Some(remain) = remain {
+                        *remain = duration_to_timespec(left);
+                    }
+                }
+                return e;
+            }
+            interface::sleep(left.min(interface::SIGNAL_CHECK_INTERVAL));
+        }
+    }
+
+    pub fn nanosleep_syscall(
+        &self,
+        request: Option<&interface::TimeSpec>,
+        remain: Option<&mut interface::TimeSpec>,
+    ) -> i32 {
+        self.clock_nanosleep_syscall(CLOCK_MONOTONIC, 0, request, remain)
+    }
+
+    // Make a timer measuring clockid and put its id in timerid. When it expires it notifies the
+    // cage as sevp says, which for SIGEV_SIGNAL means queueing sigev_signo with si_code SI_TIMER
+    // and sigev_value; with no sevp that is SIGALRM carrying the timer id. Only one signal is
+    // queued for a timer at a time, and expiries while it waits are counted as overruns.
+    pub fn timer_create_syscall(
+        &self,
+        clockid: i32,
+        sevp: Option<&interface::SigeventStruct>,
+        timerid: Option<&mut i32>,
+    ) -> i32 {
+        if clockid != CLOCK_REALTIME && clockid != CLOCK_MONOTONIC {
+            return syscall_error(
+                Errno::EINVAL,
+                "timer_create",
+                "clockid is not a supported clock",
+            );
+        }
+        let timerid = match timerid {
+            Some(timerid) => timerid,
+            None => {
+                return syscall_error(Errno::EFAULT, "timer_create", "timerid is null");
+            }
+        };
+        let mut timers = self.posix_timers.write();
+        let id = timers
+            .iter()
+            .position(|timer| timer.is_none())
+            .unwrap_or(timers.len());
+        if id >= TIMER_MAX {
+            return syscall_error(
+                Errno::EAGAIN,
+                "timer_create",
+                "the cage has as many timers as it may",
+            );
+        }
+        let (notify, signo, value) = match sevp {
+            Some(sev) => (sev.sigev_notify, sev.sigev_signo, sev.sigev_value),
+            None => (SIGEV_SIGNAL, SIGALRM, id as u64),
+        };
+        let info = match notify {
+            SIGEV_NONE => None,
+            SIGEV_SIGNAL => {
+                if signo < 1 || signo > SIGNAL_MAX {
+                    return syscall_error(Errno::EINVAL, "timer_create", "Invalid signal number.");
                 }
+                let mut info = self.siginfo(signo, SI_TIMER, value);
+                info.si_timerid = id as i32;
+                Some(info)
+            }
+            _ => {
+                return syscall_error(
+                    Errno::EINVAL,
+                    "timer_create",
+                    "only SIGEV_NONE and SIGEV_SIGNAL notifications are supported",
+                );
+            }
+        };
+
+        let timer = interface::PosixTimer::new(self.cageid, clockid, info);
+        if id == timers.len() {
+            timers.push(Some(timer));
+        } else {
+            timers[id] = Some(timer);
+        }
+        *timerid = id as i32;
+        0
+    }
+
+    fn get_posix_timer(&self, timerid: i32, callname: &str) -> Result<interface::PosixTimer, i32> {
+        match self.posix_timers.read().get(timerid as usize) {
+            Some(Some(timer)) if timerid >= 0 => Ok(timer.clone()),
+            _ => Err(syscall_error(
+                Errno::EINVAL,
+                callname,
+                "timerid is not a valid timer",
+            )),
+        }
+    }
+
+    // Arm the timer to first expire after it_value, or at it_value on the timer's clock with
+    // TIMER_ABSTIME, and then every it_interval; a zero it_value disarms it.
+    pub fn timer_settime_syscall(
+        &self,
+        timerid: i32,
+        flags: i32,
+        new_value: Option<&interface::ITimerSpec>,
+        old_value: Option<&mut interface::ITimerSpec>,
+    ) -> i32 {
+        let timer = match self.get_posix_timer(timerid, "timer_settime") {
+            Ok(timer) => timer,
+            Err(e) => return e,
+        };
+        let new_value = match new_value {
+            Some(new_value) => new_value,
+            None => {
+                return syscall_error(Errno::EFAULT, "timer_settime", "new_value is null");
+            }
+        };
+        let (mut value, interval) = match (
+            timespec_to_duration(&new_value.it_value),
+            timespec_to_duration(&new_value.it_interval),
+        ) {
+            (Some(value), Some(interval)) => (value, interval),
+            _ => {
+                return syscall_error(
+                    Errno::EINVAL,
+                    "timer_settime",
+                    "the timer value holds an invalid timespec",
+                )
+            }
+        };
+        if flags & TIMER_ABSTIME != 0 && !value.is_zero() {
+            // a time already passed expires the timer straight away
+            let now = self.clock_now(timer.clockid()).unwrap();
+            value = value
+                .saturating_sub(now)
+                .max(interface::RustDuration::from_nanos(1));
+        }
+
+        let (oldvalue, oldinterval) = timer.set_time(value, interval);
+        if let Some(old_value) = old_value {
+            old_value.it_value = duration_to_timespec(oldvalue);
+            old_value.it_interval = duration_to_timespec(oldinterval);
+        }
+        0
+    }
+
+    pub fn timer_gettime_syscall(
+        &self,
+        timerid: i32,
+        curr_value: Option<&mut interface::ITimerSpec>,
+    ) -> i32 {
+        let timer = match self.get_posix_timer(timerid, "timer_gettime") {
+            Ok(timer) => timer,
+            Err(e) => return e,
+        };
+        match curr_value {
+            Some(curr_value) => {
+                let (value, interval) = timer.get_time();
+                curr_value.it_value = duration_to_timespec(value);
+                curr_value.it_interval = duration_to_timespec(interval);
+                0
+            }
+            None => syscall_error(Errno::EFAULT, "timer_gettime", "curr_value is null"),
+        }
+    }
+
+    //how many more times the timer expired while the signal delivered for it last was waiting
+    pub fn timer_getoverrun_syscall(&self, timerid: i32) -> i32 {
+        match self.get_posix_timer(timerid, "timer_getoverrun") {
+            Ok(timer) => timer.overrun(),
+            Err(e) => e,
+        }
+    }
+
+    //the timer's id may be handed out again, and a signal of its still waiting is dropped
+    pub fn timer_delete_syscall(&self, timerid: i32) -> i32 {
+        let timer = {
+            let mut timers = self.posix_timers.write();
+            match timers.get_mut(timerid as usize) {
+                Some(slot) if timerid >= 0 => slot.take(),
+                _ => None,
+            }
+        };
+        match timer {
+            Some(timer) => {
+                timer.delete();
+                self.discard_timer_signal(timerid);
+                0
+            }
+            None => syscall_error(
+                Errno::EINVAL,
+                "timer_delete",
+                "timerid is not a valid timer",
+            ),
+        }
+    }
+
+    // which == PRIO_USER names every cage whose real uid is who, or the caller's own for 0
+    fn priority_targets(&self, which: i32, who: i32) -> Result<Vec<interface::RustRfc<Cage>>, i32> {
+        let targets: Vec<interface::RustRfc<Cage>> = match which {
+            PRIO_PROCESS => self.cage_from_pid(who).into_iter().collect(),
+            PRIO_PGRP => {
+                let pgid = if who == 0 {
+                    self.pgid.load(interface::RustAtomicOrdering::Relaxed)
+                } else {
+                    who as u64
+                };
+                interface::cagetable_getrefs()
+                    .into_iter()
+                    .filter(|cage| cage.pgid.load(interface::RustAtomicOrdering::Relaxed) == pgid)
+                    .collect()
+            }
+            PRIO_USER => {
+                let uid = if who == 0 {
+                    self.cred.read().ruid
+                } else {
+                    who as u32
+                };
+                interface::cagetable_getrefs()
+                    .into_iter()
+                    .filter(|cage| cage.cageid != 0 && cage.cred.read().ruid == uid)
+                    .collect()
+            }
+            _ => {
+                return Err(syscall_error(
+                    Errno::EINVAL,
+                    "getpriority",
+                    "which is not PRIO_PROCESS, PRIO_PGRP or PRIO_USER",
+                ));
+            }
+        };
+        if targets.is_empty() {
+            return Err(syscall_error(
+                Errno::ESRCH,
+                "getpriority",
+                "no cage matched which and who",
+            ));
+        }
+        Ok(targets)
+    }
+
+    // Like the kernel, this hands back 20 - nice so a successful result is never negative.
+    // Across several cages it reports the highest priority among them.
+    pub fn getpriority_syscall(&self, which: i32, who: i32) -> i32 {
+        match self.priority_targets(which, who) {
+            Ok(targets) => {
+                let nice = targets
+                    .iter()
+                    .map(|cage| cage.nice.load(interface::RustAtomicOrdering::Relaxed))
+                    .min()
+                    .unwrap();
+                20 - nice
+            }
+            Err(e) => e,
+        }
+    }
+
+    pub fn setpriority_syscall(&self, which: i32, who: i32, prio: i32) -> i32 {
+        let targets = match self.priority_targets(which, who) {
+            Ok(targets) => targets,
+            Err(e) => return e,
+        };
+        let nice = interface::rust_min(interface::rust_max(prio, PRIO_MIN), PRIO_MAX - 1);
+        // raising a priority takes CAP_SYS_NICE
+        let privileged = self.cred.read().capable(CAP_SYS_NICE);
+        for cage in targets.iter() {
+            if !privileged && nice < cage.nice.load(interface::RustAtomicOrdering::Relaxed) {
+                return syscall_error(
+                    Errno::EACCES,
+                    "setpriority",
+                    "the caller may not raise a cage's priority",
+                );
+            }
+        }
+        for cage in targets {
+            cage.nice
+                .store(nice, interface::RustAtomicOrdering::Relaxed);
+        }
+        0
+    }
+
+    pub fn nice_syscall(&self, inc: i32) -> i32 {
+        let oldnice = self.nice.load(interface::RustAtomicOrdering::Relaxed);
+        let nice = interface::rust_min(
+            interface::rust_max(oldnice.saturating_add(inc), PRIO_MIN),
+            PRIO_MAX - 1,
+        );
+        if nice < oldnice && !self.cred.read().capable(CAP_SYS_NICE) {
+            return syscall_error(
+                Errno::EPERM,
+                "nice",
+                "the caller may not raise its own priority",
+            );
+        }
+        self.nice
+            .store(nice, interface::RustAtomicOrdering::Relaxed);
+        nice
+    }
+
+    //the mask recorded for a thread, or failing that for the cage's main thread
+    fn recorded_cpumask(&self, threadid: u64) -> Option<Vec<u8>> {
+        if let Some(mask) = self.cpu_affinity.get(&threadid) {
+            return Some(mask.clone());
+        }
+        let mainthreadid = self
+            .main_threadid
+            .load(interface::RustAtomicOrdering::Relaxed);
+        self.cpu_affinity
+            .get(&mainthreadid)
+            .map(|mask| mask.clone())
+    }
+
+    //pid 0 is the calling thread, any other pid the main thread of that cage
+    fn affinity_target(
+        &self,
+        pid: i32,
+        callname: &str,
+    ) -> Result<(interface::RustRfc<Cage>, u64), i32> {
+        if pid < 0 {
+            return Err(syscall_error(Errno::EINVAL, callname, "invalid pid"));
+        }
+        let cageid = if pid == 0 { self.cageid } else { pid as u64 };
+        let cage = match interface::cagetable_getref_opt(cageid) {
+            Some(cage) => cage,
+            None => return Err(syscall_error(Errno::ESRCH, callname, "no such cage")),
+        };
+        let threadid = if pid == 0 {
+            interface::get_pthreadid()
+        } else {
+            cage.main_threadid
+                .load(interface::RustAtomicOrdering::Relaxed)
+        };
+        Ok((cage, threadid))
+    }
+
+    pub fn sched_setaffinity_syscall(&self, pid: i32, mask: &[u8]) -> i32 {
+        let (cage, threadid) = match self.affinity_target(pid, "sched_setaffinity") {
+            Ok(target) => target,
+            Err(e) => return e,
+        };
+        if cage.cageid != self.cageid {
+            let mycred = self.cred.read().clone();
+            let theircred = cage.cred.read().clone();
+            if !mycred.capable(CAP_SYS_NICE)
+                && mycred.euid != theircred.euid
+                && mycred.euid != theircred.ruid
+            {
+                return syscall_error(
+                    Errno::EPERM,
+                    "sched_setaffinity",
+                    "the caller may not change another user's cage",
+                );
+            }
+        }
+
+        //bits past the cpus the host has are ignored, and only the cpus this process may use count
+        let mut newmask = interface::lind_process_cpumask();
+        for (i, byte) in newmask.iter_mut().enumerate() {
+            *byte &= mask.get(i).copied().unwrap_or(0);
+        }
+        if newmask.iter().all(|byte| *byte == 0) {
+            return syscall_error(
+                Errno::EINVAL,
+                "sched_setaffinity",
+                "the mask holds none of the cpus the cage may run on",
+            );
+        }
+
+        if threadid != 0 {
+            let ret = interface::lind_setaffinity(threadid, &newmask);
+            if ret != 0 {
+                return syscall_error(
+                    Errno::from_host(ret),
+                    "sched_setaffinity",
+                    "pinning the host thread failed",
+                );
             }
+        }
+        cage.cpu_affinity.insert(threadid, newmask);
+        0
+    }
 
-            _ => { /* ITIMER_VIRTUAL and ITIMER_PROF is not implemented*/ }
+    //like the raw syscall, returns how many bytes of mask it filled in
+    pub fn sched_getaffinity_syscall(&self, pid: i32, mask: &mut [u8]) -> i32 {
+        let (cage, threadid) = match self.affinity_target(pid, "sched_getaffinity") {
+            Ok(target) => target,
+            Err(e) => return e,
+        };
+        let current = match cage.recorded_cpumask(threadid) {
+            Some(current) => current,
+            None => interface::lind_process_cpumask(),
+        };
+        //the buffer has to be whole words, and hold every cpu the host has
+        let hostbytes = interface::lind_process_cpumask()
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |i| i + 1);
+        if mask.len() % 8 != 0 || mask.len() < hostbytes {
+            return syscall_error(
+                Errno::EINVAL,
+                "sched_getaffinity",
+                "the mask is too small or not a whole number of words",
+            );
         }
+        let len = interface::rust_min(mask.len(), current.len());
+        mask[..len].copy_from_slice(&current[..len]);
+        len as i32
+    }
+
+    pub fn uname_syscall(&self, buf: &mut interface::UtsName) -> i32 {
+        *buf = *UTSNAME.read();
+        0
+    }
+
+    //memory is given as the same virtualized figures /proc/meminfo shows, and procs counts cages
+    pub fn sysinfo_syscall(&self, info: &mut interface::SysInfo) -> i32 {
+        *info = interface::SysInfo::default();
+        info.uptime = interface::clock_time(libc::CLOCK_MONOTONIC).as_secs() as i64;
+        info.totalram = (PROC_MEMTOTAL_KB * 1024) as u64;
+        info.freeram = (PROC_MEMFREE_KB * 1024) as u64;
+        info.procs = interface::cagetable_getrefs().len() as u16;
+        info.mem_unit = 1;
         0
     }
 
     pub fn getrlimit(&self, res_type: u64, rlimit: &mut Rlimit) -> i32 {
         match res_type {
             RLIMIT_NOFILE => {
-                rlimit.rlim_cur = NOFILE_CUR;
+                rlimit.rlim_cur = self.filedescriptortable.limit() as u64;
                 rlimit.rlim_max = NOFILE_MAX;
             }
             RLIMIT_STACK => {
@@ -557,17 +1892,107 @@ impl Cage {
         0
     }
 
-    pub fn setrlimit(&self, res_type: u64, _limit_value: u64) -> i32 {
+    //sets the soft limit, which may be raised as far as the hard limit. Cages sharing the fd table
+    //share its limit too
+    pub fn setrlimit(&self, res_type: u64, limit_value: u64) -> i32 {
         match res_type {
             RLIMIT_NOFILE => {
-                if NOFILE_CUR > NOFILE_MAX {
-                    -1
-                } else {
-                    0
+                if limit_value > NOFILE_MAX {
+                    return syscall_error(
+                        Errno::EINVAL,
+                        "setrlimit",
+                        "the soft limit can't be above the hard limit",
+                    );
                 }
-                //FIXME: not implemented yet to update value in program
+                self.filedescriptortable.set_limit(limit_value as i32);
+                0
             }
             _ => -1,
         }
     }
+
+    //the environment is kept as NAME=value entries, in the order the names were first set
+    fn env_position(environ: &Vec<String>, name: &str) -> Option<usize> {
+        environ.iter().position(|entry| {
+            entry.starts_with(name) && entry.as_bytes().get(name.len()) == Some(&b'=')
+        })
+    }
+
+    fn env_name_ok(name: &str) -> bool {
+        !name.is_empty() && !name.contains('=')
+    }
+
+    //copies the value of name into buf and returns its length, not counting the null terminator
+    pub fn getenv_syscall(&self, name: &str, buf: *mut u8, bufsize: u32) -> i32 {
+        if !Self::env_name_ok(name) {
+            return syscall_error(Errno::EINVAL, "getenv", "Invalid variable name");
+        }
+        let environ = self.environ.read();
+        let index = match Self::env_position(&environ, name) {
+            Some(index) => index,
+            None => return syscall_error(Errno::ENOENT, "getenv", "The variable is not set"),
+        };
+        let mut bytes: Vec<u8> = environ[index][name.len() + 1..].as_bytes().to_vec();
+        bytes.push(0u8);
+        if (bufsize as usize) < bytes.len() {
+            return syscall_error(
+                Errno::ERANGE,
+                "getenv",
+                "the value of the variable exceeds the given size",
+            );
+        }
+        interface::fill(buf, bytes.len(), &bytes);
+        (bytes.len() - 1) as i32
+    }
+
+    pub fn setenv_syscall(&self, name: &str, value: &str, overwrite: i32) -> i32 {
+        if !Self::env_name_ok(name) {
+            return syscall_error(Errno::EINVAL, "setenv", "Invalid variable name");
+        }
+        let mut environ = self.environ.write();
+        let entry = format!("{}={}", name, value);
+        match Self::env_position(&environ, name) {
+            Some(index) => {
+                if overwrite != 0 {
+                    environ[index] = entry;
+                }
+            }
+            None => environ.push(entry),
+        }
+        0
+    }
+
+    pub fn unsetenv_syscall(&self, name: &str) -> i32 {
+        if !Self::env_name_ok(name) {
+            return syscall_error(Errno::EINVAL, "unsetenv", "Invalid variable name");
+        }
+        let mut environ = self.environ.write();
+        if let Some(index) = Self::env_position(&environ, name) {
+            environ.remove(index);
+        }
+        0
+    }
+
+    //copies every entry of the environment into buf, each null terminated, so a cage can build
+    //its envp from them. Returns the number of bytes the entries take, which is all that is
+    //reported when buf is null.
+    pub fn getenviron_syscall(&self, buf: Option<*mut u8>, bufsize: u32) -> i32 {
+        let mut bytes: Vec<u8> = vec![];
+        for entry in self.environ.read().iter() {
+            bytes.extend_from_slice(entry.as_bytes());
+            bytes.push(0u8);
+        }
+        let buf = match buf {
+            Some(buf) => buf,
+            None => return bytes.len() as i32,
+        };
+        if (bufsize as usize) < bytes.len() {
+            return syscall_error(
+                Errno::ERANGE,
+                "getenviron",
+                "the environment exceeds the given size",
+            );
+        }
+        interface::fill(buf, bytes.len(), &bytes)
+    }
 }
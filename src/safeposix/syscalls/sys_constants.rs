@@ -11,6 +11,7 @@ use crate::interface;
 
 pub const DEFAULT_UID: u32 = 1000;
 pub const DEFAULT_GID: u32 = 1000;
+pub const NGROUPS_MAX: usize = 65536;
 
 // RESOURCE LIMITS
 
@@ -25,11 +26,82 @@ pub const STACK_MAX: u64 = 1 << 32;
 pub const RLIMIT_STACK: u64 = 0;
 pub const RLIMIT_NOFILE: u64 = 1;
 
+// CAPABILITIES
+
+pub const CAP_CHOWN: u32 = 0;
+pub const CAP_DAC_OVERRIDE: u32 = 1;
+pub const CAP_DAC_READ_SEARCH: u32 = 2;
+pub const CAP_FOWNER: u32 = 3;
+pub const CAP_FSETID: u32 = 4;
+pub const CAP_KILL: u32 = 5;
+pub const CAP_SETGID: u32 = 6;
+pub const CAP_SETUID: u32 = 7;
+pub const CAP_SETPCAP: u32 = 8;
+pub const CAP_NET_BIND_SERVICE: u32 = 10;
+pub const CAP_NET_BROADCAST: u32 = 11;
+pub const CAP_NET_ADMIN: u32 = 12;
+pub const CAP_NET_RAW: u32 = 13;
+pub const CAP_IPC_LOCK: u32 = 14;
+pub const CAP_IPC_OWNER: u32 = 15;
+pub const CAP_SYS_CHROOT: u32 = 18;
+pub const CAP_SYS_ADMIN: u32 = 21;
+pub const CAP_SYS_NICE: u32 = 23;
+pub const CAP_SYS_RESOURCE: u32 = 24;
+pub const CAP_LEASE: u32 = 28;
+pub const CAP_LAST_CAP: u32 = 40;
+pub const CAP_FULL_SET: u64 = (1 << (CAP_LAST_CAP + 1)) - 1;
+
+pub fn cap_valid(cap: u32) -> bool {
+    cap <= CAP_LAST_CAP
+}
+
+pub fn cap_to_mask(cap: u32) -> u64 {
+    1 << cap
+}
+
+pub const _LINUX_CAPABILITY_VERSION_3: u32 = 0x20080522;
+pub const _LINUX_CAPABILITY_U32S_3: usize = 2;
+
+pub const PR_GET_KEEPCAPS: i32 = 7;
+pub const PR_SET_KEEPCAPS: i32 = 8;
+pub const PR_CAPBSET_READ: i32 = 23;
+pub const PR_CAPBSET_DROP: i32 = 24;
+
+// SYSCALL FILTER ACTIONS, as the embedder names them
+
+pub const FILTER_ALLOW: i32 = 0;
+pub const FILTER_LOG: i32 = 1;
+pub const FILTER_DENY: i32 = 2; // fail with EPERM
+pub const FILTER_ERRNO: i32 = 3; // fail with an errno of the embedder's choosing
+pub const FILTER_KILL: i32 = 4;
+
+// PRIORITIES
+
+pub const PRIO_PROCESS: i32 = 0;
+pub const PRIO_PGRP: i32 = 1;
+pub const PRIO_USER: i32 = 2;
+
+pub const PRIO_MIN: i32 = -20;
+pub const PRIO_MAX: i32 = 20; // nice values run from PRIO_MIN up to PRIO_MAX - 1
+
 // Constants for exit_syscall status
 
 pub const EXIT_SUCCESS: i32 = 0;
 pub const EXIT_FAILURE: i32 = 1;
 
+// Options for waitpid_syscall
+
+pub const WNOHANG: i32 = 1;
+pub const WUNTRACED: i32 = 2;
+pub const WCONTINUED: i32 = 8;
+
+// Flags for clone_syscall
+
+pub const CSIGNAL: i32 = 0xff; // signal sent to the parent when the child exits
+pub const CLONE_FS: i32 = 0x200;
+pub const CLONE_FILES: i32 = 0x400;
+pub const CLONE_SIGHAND: i32 = 0x800;
+
 // Signal Table (x86/ARM)
 // Based on https://man7.org/linux/man-pages/man7/signal.7.html
 pub const SIGHUP: i32 = 1;
@@ -70,8 +142,74 @@ pub const SIGPWR: i32 = 30;
 // pub const SIGLOST: i32
 pub const SIGSYS: i32 = 31;
 pub const SIGUNUSED: i32 = 31;
+//real-time signals, which queue rather than collapse, run from SIGRTMIN to SIGRTMAX
+pub const SIGRTMIN: i32 = 32;
+pub const SIGRTMAX: i32 = SIGNAL_MAX;
+pub const SIGQUEUE_MAX: usize = 1024; // real-time signals that may be queued for a cage at once
 
 pub const SIG_BLOCK: i32 = 0;
 pub const SIG_UNBLOCK: i32 = 1;
 pub const SIG_SETMASK: i32 = 2;
+
+//special sa_handler values
+pub const SIG_DFL: u32 = 0;
+pub const SIG_IGN: u32 = 1;
+
+//sa_flags
+pub const SA_NOCLDSTOP: i32 = 0x1;
+pub const SA_NOCLDWAIT: i32 = 0x2;
+pub const SA_SIGINFO: i32 = 0x4;
+pub const SA_ONSTACK: i32 = 0x08000000;
+pub const SA_RESTART: i32 = 0x10000000;
+pub const SA_NODEFER: i32 = 0x40000000;
+pub const SA_RESETHAND: i32 = 0x80000000u32 as i32;
+
+//si_code values, saying where a signal came from
+pub const SI_USER: i32 = 0; // kill
+pub const SI_KERNEL: i32 = 0x80;
+pub const SI_QUEUE: i32 = -1; // sigqueue
+pub const SI_TIMER: i32 = -2;
+pub const SI_MESGQ: i32 = -3; // a message arriving on an empty message queue
+pub const SI_ASYNCIO: i32 = -4;
+//si_code values of a signal set by F_SETSIG
+pub const POLL_IN: i32 = 1; // input became possible
+pub const POLL_OUT: i32 = 2; // output became possible
+//si_code values of SIGCHLD
+pub const CLD_EXITED: i32 = 1;
+pub const CLD_KILLED: i32 = 2;
+
+//sigev_notify values for mq_notify
+pub const SIGEV_SIGNAL: i32 = 0;
+pub const SIGEV_NONE: i32 = 1;
+pub const SIGEV_THREAD: i32 = 2;
+
 pub const ITIMER_REAL: i32 = 0;
+pub const ITIMER_VIRTUAL: i32 = 1;
+pub const ITIMER_PROF: i32 = 2;
+
+//clock ids; a timer made by timer_create can measure the first two
+pub const CLOCK_REALTIME: i32 = 0;
+pub const CLOCK_MONOTONIC: i32 = 1;
+pub const CLOCK_PROCESS_CPUTIME_ID: i32 = 2;
+pub const CLOCK_THREAD_CPUTIME_ID: i32 = 3;
+pub const CLOCK_MONOTONIC_RAW: i32 = 4;
+pub const TIMER_ABSTIME: i32 = 1; // timer_settime and clock_nanosleep flag for a time given on the clock
+pub const TIMER_MAX: usize = 256; // timers a cage may have at once
+
+//futex operations, optionally or'd with FUTEX_PRIVATE_FLAG
+pub const FUTEX_WAIT: i32 = 0;
+pub const FUTEX_WAKE: i32 = 1;
+pub const FUTEX_REQUEUE: i32 = 3;
+pub const FUTEX_PRIVATE_FLAG: i32 = 128;
+pub const FUTEX_CMD_MASK: i32 = !FUTEX_PRIVATE_FLAG;
+
+//the utsname fields an embedder may set with lindsetutsname, and what they start as
+pub const UTS_SYSNAME: i32 = 0;
+pub const UTS_RELEASE: i32 = 1;
+pub const UTS_VERSION: i32 = 2;
+pub const UTS_MACHINE: i32 = 3;
+pub const DEFAULT_SYSNAME: &str = "Linux"; // glibc and most runtimes only know what to do with Linux
+pub const DEFAULT_RELEASE: &str = "6.1.0-lind";
+pub const DEFAULT_VERSION: &str = "#1 SMP Lind";
+pub const DEFAULT_MACHINE: &str = std::env::consts::ARCH;
+pub const DEFAULT_DOMAINNAME: &str = "(none)";
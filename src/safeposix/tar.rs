@@ -0,0 +1,641 @@
+// Export a directory tree of the filesystem to a tar archive and populate one from an archive, so
+// cage images can be provisioned without copying files in from the host one at a time
+#![allow(dead_code)]
+
+use std::io::{Read, Write};
+
+use super::cage::Cage;
+use super::filesystem::*;
+use super::syscalls::fs_constants::*;
+use crate::interface;
+use crate::interface::errnos::{syscall_error, Errno};
+
+const BLOCKSIZE: usize = 512;
+const NAMELEN: usize = 100;
+const PREFIXLEN: usize = 155;
+const COPYCHUNK: usize = 65536;
+
+const REGTYPE: u8 = b'0';
+const AREGTYPE: u8 = 0; // pre-POSIX archives mark regular files with a NUL
+const LNKTYPE: u8 = b'1';
+const CHRTYPE: u8 = b'3';
+const DIRTYPE: u8 = b'5';
+const CONTTYPE: u8 = b'7';
+// GNU extensions, the data of the entry is the full name or link target of the entry after it
+const GNULONGNAME: u8 = b'L';
+const GNULONGLINK: u8 = b'K';
+const GNULONGENTRY: &str = "././@LongLink";
+
+struct TarHeader {
+    name: String,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    size: usize,
+    mtime: u64,
+    typeflag: u8,
+    linkname: String,
+    dev: DevNo,
+}
+
+impl TarHeader {
+    fn new(name: String, typeflag: u8) -> TarHeader {
+        TarHeader {
+            name: name,
+            mode: 0,
+            uid: 0,
+            gid: 0,
+            size: 0,
+            mtime: 0,
+            typeflag: typeflag,
+            linkname: String::new(),
+            dev: DevNo { major: 0, minor: 0 },
+        }
+    }
+}
+
+fn put_str(field: &mut [u8], value: &str) {
+    field[..value.len()].copy_from_slice(value.as_bytes());
+}
+
+//values with more octal digits than the field holds, like sizes of 8GiB and up or large uids,
+//are stored in the GNU base-256 form: the high bit of the first byte set, then big endian binary
+fn put_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    if digits.len() < field.len() {
+        put_str(field, &digits);
+        return;
+    }
+    let bytes = value.to_be_bytes();
+    let len = field.len();
+    for b in field.iter_mut() {
+        *b = 0;
+    }
+    let copied = interface::rust_min(len - 1, bytes.len());
+    field[len - copied..].copy_from_slice(&bytes[bytes.len() - copied..]);
+    field[0] |= 0x80;
+}
+
+fn get_str(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn get_octal(field: &[u8]) -> Option<u64> {
+    if field.first().map_or(false, |b| b & 0x80 != 0) {
+        //negative base-256 values also have 0x40 set in the first byte, and no header field is negative
+        if field[0] & 0x40 != 0 {
+            return None;
+        }
+        let mut value: u64 = (field[0] & 0x3f) as u64;
+        for b in &field[1..] {
+            if value >> 56 != 0 {
+                return None;
+            }
+            value = (value << 8) | *b as u64;
+        }
+        return Some(value);
+    }
+    let digits = get_str(field);
+    let digits = digits.trim_matches(|c| c == ' ' || c == '\0');
+    if digits.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(digits, 8).ok()
+}
+
+//checksum of a header, with the checksum field itself counted as spaces
+fn header_checksum(block: &[u8; BLOCKSIZE]) -> u64 {
+    block
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            if (148..156).contains(&i) {
+                b' ' as u64
+            } else {
+                *b as u64
+            }
+        })
+        .sum()
+}
+
+//ustar names of up to 255 bytes are split between the name and prefix fields at a slash
+fn split_ustar_name(name: &str) -> Option<(&str, &str)> {
+    if name.len() <= NAMELEN {
+        return Some(("", name));
+    }
+    let bytes = name.as_bytes();
+    for split in (0..interface::rust_min(name.len(), PREFIXLEN + 1)).rev() {
+        if bytes[split] == b'/' && name.len() - split - 1 <= NAMELEN && split > 0 {
+            return Some((&name[..split], &name[split + 1..]));
+        }
+    }
+    None
+}
+
+//the longest prefix of value that fits a fixed size field, cut on a character boundary
+fn truncate_to(value: &str, len: usize) -> &str {
+    let mut end = interface::rust_min(value.len(), len);
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
+
+fn pad_to_block(out: &mut dyn Write, len: usize) -> std::io::Result<()> {
+    let padding = (BLOCKSIZE - len % BLOCKSIZE) % BLOCKSIZE;
+    out.write_all(&[0u8; BLOCKSIZE][..padding])
+}
+
+fn write_long_entry(out: &mut dyn Write, typeflag: u8, value: &str) -> std::io::Result<()> {
+    let mut longheader = TarHeader::new(GNULONGENTRY.to_string(), typeflag);
+    longheader.size = value.len() + 1;
+    write_header(out, &longheader)?;
+    out.write_all(value.as_bytes())?;
+    out.write_all(&[0])?;
+    pad_to_block(out, value.len() + 1)
+}
+
+fn write_header(out: &mut dyn Write, header: &TarHeader) -> std::io::Result<()> {
+    //names that do not fit the ustar fields go out in a GNU long name entry first
+    let (prefix, name) = match split_ustar_name(&header.name) {
+        Some(split) => split,
+        None => {
+            write_long_entry(out, GNULONGNAME, &header.name)?;
+            ("", truncate_to(&header.name, NAMELEN))
+        }
+    };
+    let linkname = if header.linkname.len() > NAMELEN {
+        write_long_entry(out, GNULONGLINK, &header.linkname)?;
+        truncate_to(&header.linkname, NAMELEN)
+    } else {
+        &header.linkname[..]
+    };
+
+    let mut block = [0u8; BLOCKSIZE];
+    put_str(&mut block[0..100], name);
+    put_octal(&mut block[100..108], header.mode as u64 & 0o7777);
+    put_octal(&mut block[108..116], header.uid as u64);
+    put_octal(&mut block[116..124], header.gid as u64);
+    put_octal(&mut block[124..136], header.size as u64);
+    put_octal(&mut block[136..148], header.mtime);
+    block[156] = header.typeflag;
+    put_str(&mut block[157..257], linkname);
+    put_str(&mut block[257..263], "ustar\0");
+    put_str(&mut block[263..265], "00");
+    put_octal(&mut block[329..337], header.dev.major as u64);
+    put_octal(&mut block[337..345], header.dev.minor as u64);
+    put_str(&mut block[345..500], prefix);
+
+    let checksum = format!("{:06o}\0 ", header_checksum(&block));
+    put_str(&mut block[148..156], &checksum);
+    out.write_all(&block)
+}
+
+//reads the next header, returning None at the end of the archive
+fn read_header(input: &mut dyn Read) -> Result<Option<TarHeader>, i32> {
+    let mut block = [0u8; BLOCKSIZE];
+    match input.read_exact(&mut block) {
+        Ok(()) => {}
+        //archives cut short of their end marker are accepted like tar itself does
+        Err(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(_) => {
+            return Err(syscall_error(
+                Errno::EIO,
+                "import_tar",
+                "could not read the archive",
+            ))
+        }
+    }
+    if block.iter().all(|b| *b == 0) {
+        return Ok(None);
+    }
+
+    let invalid = || syscall_error(Errno::EINVAL, "import_tar", "not a valid tar archive");
+    if get_octal(&block[148..156]) != Some(header_checksum(&block)) {
+        return Err(invalid());
+    }
+
+    let mut name = get_str(&block[0..100]);
+    if &block[257..262] == b"ustar" {
+        let prefix = get_str(&block[345..500]);
+        if !prefix.is_empty() {
+            name = format!("{}/{}", prefix, name);
+        }
+    }
+
+    Ok(Some(TarHeader {
+        name: name,
+        mode: get_octal(&block[100..108]).ok_or_else(invalid)? as u32,
+        uid: get_octal(&block[108..116]).ok_or_else(invalid)? as u32,
+        gid: get_octal(&block[116..124]).ok_or_else(invalid)? as u32,
+        size: get_octal(&block[124..136]).ok_or_else(invalid)? as usize,
+        mtime: get_octal(&block[136..148]).ok_or_else(invalid)?,
+        typeflag: block[156],
+        linkname: get_str(&block[157..257]),
+        dev: DevNo {
+            major: get_octal(&block[329..337]).ok_or_else(invalid)? as u32,
+            minor: get_octal(&block[337..345]).ok_or_else(invalid)? as u32,
+        },
+    }))
+}
+
+//reads an entry's data along with the padding after it, handing each chunk to sink
+fn read_data(
+    input: &mut dyn Read,
+    size: usize,
+    sink: &mut dyn FnMut(&[u8]) -> i32,
+) -> Result<(), i32> {
+    let mut buf = vec![0u8; COPYCHUNK];
+    let mut remaining = size + (BLOCKSIZE - size % BLOCKSIZE) % BLOCKSIZE;
+    let mut datauntil = size;
+    while remaining > 0 {
+        let chunk = interface::rust_min(remaining, COPYCHUNK);
+        if input.read_exact(&mut buf[..chunk]).is_err() {
+            return Err(syscall_error(
+                Errno::EIO,
+                "import_tar",
+                "archive ended in the middle of an entry",
+            ));
+        }
+        let datalen = interface::rust_min(chunk, datauntil);
+        if datalen > 0 {
+            let res = sink(&buf[..datalen]);
+            if res < 0 {
+                return Err(res);
+            }
+        }
+        datauntil -= datalen;
+        remaining -= chunk;
+    }
+    Ok(())
+}
+
+//strips leading slashes and ./ from an archive path, refusing anything that climbs out with ..
+fn clean_archive_path(name: &str) -> Option<String> {
+    let mut components = vec![];
+    for component in name.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => return None,
+            _ => components.push(component),
+        }
+    }
+    if components.is_empty() {
+        None
+    } else {
+        Some(components.join("/"))
+    }
+}
+
+fn join_lind_path(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+impl Cage {
+    //Write the tree under path to out as a tar archive. Mounted filesystems are not descended
+    //into, and sockets are left out; hard links are kept, as each inode is archived only once.
+    pub fn export_tar(&self, path: &str, out: &mut dyn Write) -> i32 {
+        let truepath = normpath(convpath(path), self);
+        let dirinodenum = match metawalk(truepath.as_path()) {
+            Some(inodenum) => inodenum,
+            None => {
+                return syscall_error(Errno::ENOENT, "export_tar", "path does not exist");
+            }
+        };
+        if !matches!(
+            FS_METADATA.inodetable.get(&dirinodenum).as_deref(),
+            Some(Inode::Dir(_))
+        ) {
+            return syscall_error(Errno::ENOTDIR, "export_tar", "path is not a directory");
+        }
+
+        let archived = interface::RustHashMap::new();
+        let res = self._export_tar_dir(path, "", dirinodenum, out, &archived);
+        if res < 0 {
+            return res;
+        }
+
+        //the archive ends with two zero blocks
+        match out
+            .write_all(&[0u8; 2 * BLOCKSIZE])
+            .and_then(|_| out.flush())
+        {
+            Ok(()) => 0,
+            Err(_) => syscall_error(Errno::EIO, "export_tar", "could not write the archive"),
+        }
+    }
+
+    fn _export_tar_dir(
+        &self,
+        lindpath: &str,
+        archivepath: &str,
+        dirinodenum: usize,
+        out: &mut dyn Write,
+        archived: &interface::RustHashMap<usize, String>,
+    ) -> i32 {
+        let mut children: Vec<(String, usize)> =
+            match &*FS_METADATA.inodetable.get(&dirinodenum).unwrap() {
+                Inode::Dir(dir) => dir
+                    .filename_to_inode_dict
+                    .iter()
+                    .filter(|(name, _)| *name != "." && *name != "..")
+                    .map(|(name, inodenum)| (name.to_string(), inodenum))
+                    .collect(),
+                _ => return 0,
+            };
+        children.sort();
+
+        for (name, childinodenum) in children {
+            //nothing under a non-persistent mount belongs in an image
+            if !inode_is_persistent(childinodenum) {
+                continue;
+            }
+            let covered = mount_cover(childinodenum) != childinodenum;
+            let childlindpath = join_lind_path(lindpath, &name);
+            let childarchivepath = if archivepath.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", archivepath, name)
+            };
+
+            let mut header;
+            match &*FS_METADATA.inodetable.get(&childinodenum).unwrap() {
+                Inode::Dir(dir) => {
+                    header = TarHeader::new(format!("{}/", childarchivepath), DIRTYPE);
+                    header.mode = dir.mode;
+                    header.uid = dir.uid;
+                    header.gid = dir.gid;
+                    header.mtime = dir.mtime;
+                }
+                Inode::File(file) => {
+                    header = TarHeader::new(childarchivepath.clone(), REGTYPE);
+                    header.mode = file.mode;
+                    header.uid = file.uid;
+                    header.gid = file.gid;
+                    header.mtime = file.mtime;
+                    header.size = file.size;
+                    if let Some(firstpath) = archived.get(&childinodenum) {
+                        header.typeflag = LNKTYPE;
+                        header.linkname = firstpath.clone();
+                        header.size = 0;
+                    }
+                }
+                Inode::CharDev(dev) => {
+                    header = TarHeader::new(childarchivepath.clone(), CHRTYPE);
+                    header.mode = dev.mode;
+                    header.uid = dev.uid;
+                    header.gid = dev.gid;
+                    header.mtime = dev.mtime;
+                    header.dev = dev.dev;
+                }
+                Inode::Socket(_) => continue,
+            }
+
+            if write_header(out, &header).is_err() {
+                return syscall_error(Errno::EIO, "export_tar", "could not write the archive");
+            }
+            match header.typeflag {
+                DIRTYPE if !covered => {
+                    let res = self._export_tar_dir(
+                        &childlindpath,
+                        &childarchivepath,
+                        childinodenum,
+                        out,
+                        archived,
+                    );
+                    if res < 0 {
+                        return res;
+                    }
+                }
+                REGTYPE => {
+                    archived.insert(childinodenum, childarchivepath);
+                    let res = self._export_tar_file(&childlindpath, header.size, out);
+                    if res < 0 {
+                        return res;
+                    }
+                }
+                _ => {}
+            }
+        }
+        0
+    }
+
+    fn _export_tar_file(&self, lindpath: &str, size: usize, out: &mut dyn Write) -> i32 {
+        let fd = self.open_syscall(lindpath, O_RDONLY, 0);
+        if fd < 0 {
+            return fd;
+        }
+
+        let mut buf = vec![0u8; COPYCHUNK];
+        let mut copied = 0;
+        let mut res = 0;
+        while copied < size {
+            let chunk = interface::rust_min(size - copied, COPYCHUNK);
+            let readlen = self.read_syscall(fd, buf.as_mut_ptr(), chunk);
+            if readlen <= 0 {
+                res = if readlen < 0 {
+                    readlen
+                } else {
+                    syscall_error(Errno::EIO, "export_tar", "file shrank while being archived")
+                };
+                break;
+            }
+            if out.write_all(&buf[..readlen as usize]).is_err() {
+                res = syscall_error(Errno::EIO, "export_tar", "could not write the archive");
+                break;
+            }
+            copied += readlen as usize;
+        }
+        self.close_syscall(fd);
+
+        if res == 0 && pad_to_block(out, size).is_err() {
+            res = syscall_error(Errno::EIO, "export_tar", "could not write the archive");
+        }
+        res
+    }
+
+    //Populate the directory at path from the tar archive read from input, keeping the modes,
+    //owners and modification times it records. Parent directories missing from the archive are
+    //created. The filesystem has no symbolic links, so symlink entries are skipped, as are
+    //entries that would land outside of path.
+    pub fn import_tar(&self, path: &str, input: &mut dyn Read) -> i32 {
+        let mut statdata = interface::StatData::default();
+        let res = self.stat_syscall(path, &mut statdata);
+        if res < 0 {
+            return res;
+        }
+        if !is_dir(statdata.st_mode) {
+            return syscall_error(Errno::ENOTDIR, "import_tar", "path is not a directory");
+        }
+
+        //attributes are applied once everything is in place, deepest first, so creating an
+        //entry does not bump the times of a directory that has already been restored
+        let mut restored: Vec<(String, TarHeader)> = vec![];
+        let mut longname: Option<String> = None;
+        let mut longlink: Option<String> = None;
+
+        loop {
+            let mut header = match read_header(input) {
+                Ok(Some(header)) => header,
+                Ok(None) => break,
+                Err(e) => return e,
+            };
+
+            if header.typeflag == GNULONGNAME || header.typeflag == GNULONGLINK {
+                let mut value = vec![];
+                let res = read_data(input, header.size, &mut |data| {
+                    value.extend_from_slice(data);
+                    0
+                });
+                if let Err(e) = res {
+                    return e;
+                }
+                let value = get_str(&value);
+                if header.typeflag == GNULONGNAME {
+                    longname = Some(value);
+                } else {
+                    longlink = Some(value);
+                }
+                continue;
+            }
+            if let Some(name) = longname.take() {
+                header.name = name;
+            }
+            if let Some(linkname) = longlink.take() {
+                header.linkname = linkname;
+            }
+
+            let dest = match clean_archive_path(&header.name) {
+                Some(cleanname) => join_lind_path(path, &cleanname),
+                None => {
+                    if let Err(e) = read_data(input, header.size, &mut |_| 0) {
+                        return e;
+                    }
+                    continue;
+                }
+            };
+            let res = self._import_tar_parents(path, &dest);
+            if res < 0 {
+                return res;
+            }
+
+            let res = match header.typeflag {
+                REGTYPE | AREGTYPE | CONTTYPE => {
+                    let fd = self.open_syscall(&dest, O_CREAT | O_TRUNC | O_WRONLY, S_IRWXU);
+                    if fd < 0 {
+                        return fd;
+                    }
+                    let res = read_data(input, header.size, &mut |data| {
+                        let written = self.write_syscall(fd, data.as_ptr(), data.len());
+                        if written >= 0 && written as usize != data.len() {
+                            syscall_error(Errno::ENOSPC, "import_tar", "short write")
+                        } else {
+                            written
+                        }
+                    });
+                    self.close_syscall(fd);
+                    match res {
+                        Ok(()) => 0,
+                        Err(e) => e,
+                    }
+                }
+                DIRTYPE => {
+                    let res = self.mkdir_syscall(&dest, S_IRWXU);
+                    if res == -(Errno::EEXIST as i32) {
+                        0
+                    } else {
+                        res
+                    }
+                }
+                CHRTYPE => self.mknod_syscall(
+                    &dest,
+                    S_IFCHR as u32 | (header.mode & 0o7777),
+                    makedev(&header.dev),
+                ),
+                LNKTYPE => match clean_archive_path(&header.linkname) {
+                    Some(target) => self.link_syscall(&join_lind_path(path, &target), &dest),
+                    None => 0,
+                },
+                _ => {
+                    if let Err(e) = read_data(input, header.size, &mut |_| 0) {
+                        return e;
+                    }
+                    continue;
+                }
+            };
+            if res < 0 {
+                return res;
+            }
+
+            if header.typeflag != LNKTYPE {
+                restored.push((dest, header));
+            }
+        }
+
+        for (dest, header) in restored.iter().rev() {
+            self._import_tar_attrs(dest, header);
+        }
+        0
+    }
+
+    fn _import_tar_parents(&self, path: &str, dest: &str) -> i32 {
+        let relative = &dest[path.trim_end_matches('/').len() + 1..];
+        let mut parent = path.trim_end_matches('/').to_string();
+        let components: Vec<&str> = relative.split('/').collect();
+        for component in &components[..components.len() - 1] {
+            parent = join_lind_path(&parent, component);
+            let res = self.mkdir_syscall(&parent, S_IRWXA);
+            if res < 0 && res != -(Errno::EEXIST as i32) {
+                return res;
+            }
+        }
+        0
+    }
+
+    fn _import_tar_attrs(&self, dest: &str, header: &TarHeader) {
+        let truepath = normpath(convpath(dest), self);
+        let inodenum = match metawalk(truepath.as_path()) {
+            Some(inodenum) => inodenum,
+            None => return,
+        };
+
+        {
+            let mut inodeobj = FS_METADATA.inodetable.get_mut(&inodenum).unwrap();
+            let (mode, uid, gid, atime, ctime, mtime) = match *inodeobj {
+                Inode::File(ref mut f) => (
+                    &mut f.mode,
+                    &mut f.uid,
+                    &mut f.gid,
+                    &mut f.atime,
+                    &mut f.ctime,
+                    &mut f.mtime,
+                ),
+                Inode::CharDev(ref mut f) => (
+                    &mut f.mode,
+                    &mut f.uid,
+                    &mut f.gid,
+                    &mut f.atime,
+                    &mut f.ctime,
+                    &mut f.mtime,
+                ),
+                Inode::Dir(ref mut f) => (
+                    &mut f.mode,
+                    &mut f.uid,
+                    &mut f.gid,
+                    &mut f.atime,
+                    &mut f.ctime,
+                    &mut f.mtime,
+                ),
+                Inode::Socket(_) => return,
+            };
+            *mode = (*mode & S_FILETYPEFLAGS as u32) | (header.mode & 0o7777);
+            *uid = header.uid;
+            *gid = header.gid;
+            *atime = header.mtime;
+            *ctime = header.mtime;
+            *mtime = header.mtime;
+        }
+        log_metadata(&FS_METADATA, inodenum);
+    }
+}
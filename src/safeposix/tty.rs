@@ -0,0 +1,724 @@
+// Terminals: the console behind the stream descriptors and /dev/tty, and pseudo-terminal pairs
+#![allow(dead_code)]
+
+use super::filesystem::*;
+use super::syscalls::fs_constants::*;
+use super::syscalls::net_constants::{POLLIN, POLLOUT};
+use super::syscalls::sys_constants::*;
+use crate::interface;
+use crate::interface::errnos::{syscall_error, Errno};
+
+//Which side of a terminal a descriptor is on. Reads and writes of the console go to the host,
+//those of a pty slave go through the line discipline to and from its master.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TtyEnd {
+    Console,
+    Master,
+    Slave,
+}
+
+pub struct TtyState {
+    pub termios: interface::Termios,
+    pub winsize: interface::WinSize,
+    pub fgpgrp: u64, // foreground process group, signalled by ISIG characters, 0 for none
+    console: bool,
+    line: Vec<u8>, // canonical mode input not yet committed by a newline
+    input: interface::RustDeque<Vec<u8>>, // input ready to be read, an empty chunk is an end of file
+    output: interface::RustDeque<u8>,     // slave output waiting for the master to read it
+    locked: bool,
+    masterclosed: bool,
+    slaveclosed: bool,
+    slaveopen: bool, // whether the slave has a descriptor open now
+}
+
+pub struct Tty {
+    pub index: u32,      // the n in /dev/pts/n
+    pub ptsmount: usize, // id of the devpts mount holding the slave
+    pub ptsdir: usize,   // root directory of that mount
+    pub slaveinode: usize,
+    state: interface::Mutex<TtyState>,
+    cv: interface::Condvar,
+}
+
+//the settings a terminal starts with, matching what Linux gives a new pty
+pub fn default_termios() -> interface::Termios {
+    let mut c_cc = [0u8; NCCS];
+    c_cc[VINTR] = 0x03; // ^C
+    c_cc[VQUIT] = 0x1c; // ^\
+    c_cc[VERASE] = 0x7f; // DEL
+    c_cc[VKILL] = 0x15; // ^U
+    c_cc[VEOF] = 0x04; // ^D
+    c_cc[VMIN] = 1;
+    c_cc[VSTART] = 0x11; // ^Q
+    c_cc[VSTOP] = 0x13; // ^S
+    c_cc[VSUSP] = 0x1a; // ^Z
+    interface::Termios {
+        c_iflag: ICRNL,
+        c_oflag: OPOST | ONLCR,
+        c_cflag: B38400 | CS8 | CREAD,
+        c_lflag: ISIG | ICANON | ECHO | ECHOE | ECHOK | IEXTEN,
+        c_line: 0,
+        c_cc: c_cc,
+    }
+}
+
+pub const DEFAULT_WINSIZE: interface::WinSize = interface::WinSize {
+    ws_row: 24,
+    ws_col: 80,
+    ws_xpixel: 0,
+    ws_ypixel: 0,
+};
+
+//The console is shared by every cage. Its input comes from the host's standard input, which the
+//host terminal has already echoed, so unlike a pty it starts out with ECHO off.
+pub static CONSOLE: interface::RustLazyGlobal<interface::RustRfc<Tty>> =
+    interface::RustLazyGlobal::new(|| {
+        let mut termios = default_termios();
+        termios.c_lflag &= !ECHO;
+        let winsize = interface::host_winsize().unwrap_or(DEFAULT_WINSIZE);
+        let console = Tty::new(0, ANONMOUNTID, 0, 0, termios, winsize);
+        console.state.lock().console = true;
+        interface::RustRfc::new(console)
+    });
+
+//live ptys by index, an entry lasts as long as either side is open, so a slave outliving its
+//master stays hung up rather than losing its driver
+pub static PTY_TABLE: interface::RustLazyGlobal<
+    interface::RustHashMap<u32, interface::RustRfc<Tty>>,
+> = interface::RustLazyGlobal::new(|| interface::RustHashMap::new());
+
+//the terminal behind a character device number, if it is one
+pub fn tty_of_dev(dev: &DevNo) -> Option<(interface::RustRfc<Tty>, TtyEnd)> {
+    if *dev == TTYDEVNO {
+        return Some((CONSOLE.clone(), TtyEnd::Console));
+    }
+    let end = match dev.major {
+        PTY_MASTER_MAJOR => TtyEnd::Master,
+        PTY_SLAVE_MAJOR => TtyEnd::Slave,
+        _ => return None,
+    };
+    PTY_TABLE.get(&dev.minor).map(|tty| (tty.clone(), end))
+}
+
+pub fn tty_of_inode(inodenum: usize) -> Option<(interface::RustRfc<Tty>, TtyEnd)> {
+    let dev = match &*FS_METADATA.inodetable.get(&inodenum)? {
+        Inode::CharDev(devinode) => devinode.dev,
+        _ => return None,
+    };
+    tty_of_dev(&dev)
+}
+
+//called when an existing device file is opened, a pty slave can only be opened once unlocked and
+//for as long as its master is open
+pub fn tty_open(dev: &DevNo) -> i32 {
+    if dev.major != PTY_SLAVE_MAJOR && dev.major != PTY_MASTER_MAJOR {
+        return 0;
+    }
+    let tty = match PTY_TABLE.get(&dev.minor) {
+        Some(tty) => tty.clone(),
+        None => return syscall_error(Errno::EIO, "open", "the pty has been hung up"),
+    };
+    if dev.major == PTY_SLAVE_MAJOR {
+        let mut state = tty.state.lock();
+        if state.locked {
+            return syscall_error(Errno::EIO, "open", "the pty slave is still locked");
+        }
+        if state.masterclosed {
+            return syscall_error(Errno::EIO, "open", "the pty has been hung up");
+        }
+        state.slaveclosed = false;
+        state.slaveopen = true;
+    }
+    0
+}
+
+//called once the last descriptor of a terminal device file is closed
+pub fn tty_release(dev: &DevNo) {
+    match dev.major {
+        PTY_MASTER_MAJOR => {
+            let tty = match PTY_TABLE.get(&dev.minor) {
+                Some(tty) => tty.clone(),
+                None => return,
+            };
+            if !tty.master_hangup() {
+                PTY_TABLE.remove(&dev.minor);
+            }
+        }
+        PTY_SLAVE_MAJOR => {
+            let tty = match PTY_TABLE.get(&dev.minor) {
+                Some(tty) => tty.clone(),
+                None => return,
+            };
+            let mut state = tty.state.lock();
+            state.slaveclosed = true;
+            state.slaveopen = false;
+            let hungup = state.masterclosed;
+            drop(state);
+            tty.wake();
+            if hungup {
+                PTY_TABLE.remove(&dev.minor);
+            }
+        }
+        _ => {}
+    }
+}
+
+//send sig to every cage in the process group pgid
+fn signal_pgrp(pgid: u64, sig: i32) {
+    if pgid == 0 {
+        return;
+    }
+    for cage in interface::cagetable_getrefs() {
+        if cage.pgid.load(interface::RustAtomicOrdering::Relaxed) == pgid {
+            cage.post_signal(sig);
+        }
+    }
+}
+
+//whether request is one of the ioctls only terminals answer
+pub fn is_tty_request(request: u32) -> bool {
+    matches!(
+        request,
+        TCGETS
+            | TCSETS
+            | TCSETSW
+            | TCSETSF
+            | TIOCGPGRP
+            | TIOCSPGRP
+            | TIOCGWINSZ
+            | TIOCSWINSZ
+            | TIOCGPTN
+            | TIOCSPTLCK
+    )
+}
+
+fn is_cc(termios: &interface::Termios, index: usize, c: u8) -> bool {
+    termios.c_cc[index] != 0 && termios.c_cc[index] == c // a 0 disables the character
+}
+
+impl TtyState {
+    fn canonical(&self) -> bool {
+        self.termios.c_lflag & ICANON != 0
+    }
+
+    fn pending(&self) -> usize {
+        self.line.len() + self.input.iter().map(|chunk| chunk.len()).sum::<usize>()
+    }
+
+    fn readable(&self) -> bool {
+        if self.canonical() {
+            !self.input.is_empty()
+        } else {
+            self.input.iter().any(|chunk| !chunk.is_empty())
+        }
+    }
+
+    //move the line being edited to the input that can be read, returns false if it was empty
+    fn commit_line(&mut self) -> bool {
+        if self.line.is_empty() {
+            return false;
+        }
+        let line = std::mem::take(&mut self.line);
+        self.input.push_back(line);
+        true
+    }
+
+    //Canonical reads return at most one line, raw reads gather whatever has arrived. VMIN above
+    //1 and VTIME are not honoured, a raw read returns as soon as anything can be read.
+    fn take_input(&mut self, buf: *mut u8, count: usize) -> Option<i32> {
+        if self.canonical() {
+            let chunk = self.input.front_mut()?;
+            if chunk.is_empty() {
+                self.input.pop_front();
+                return Some(0);
+            }
+            let n = interface::rust_min(count, chunk.len());
+            unsafe { std::ptr::copy(chunk.as_ptr(), buf, n) };
+            chunk.drain(..n);
+            if chunk.is_empty() {
+                self.input.pop_front();
+            }
+            return Some(n as i32);
+        }
+        let mut n = 0;
+        while n < count {
+            let chunk = match self.input.front_mut() {
+                Some(chunk) => chunk,
+                None => break,
+            };
+            let take = interface::rust_min(count - n, chunk.len());
+            unsafe { std::ptr::copy(chunk.as_ptr(), buf.wrapping_add(n), take) };
+            chunk.drain(..take);
+            n += take;
+            if chunk.is_empty() {
+                self.input.pop_front();
+            }
+        }
+        if n == 0 {
+            None
+        } else {
+            Some(n as i32)
+        }
+    }
+
+    //write bytes out the way the terminal shows them, on a pty that is to the master
+    fn emit(&mut self, bytes: &[u8]) {
+        if self.console {
+            interface::log_from_ptr(bytes.as_ptr(), bytes.len());
+            return;
+        }
+        let onlcr = self.termios.c_oflag & (OPOST | ONLCR) == OPOST | ONLCR;
+        for &byte in bytes {
+            if byte == b'\n' && onlcr {
+                self.output.push_back(b'\r');
+            }
+            self.output.push_back(byte);
+        }
+    }
+}
+
+impl Tty {
+    pub fn new(
+        index: u32,
+        ptsmount: usize,
+        ptsdir: usize,
+        slaveinode: usize,
+        termios: interface::Termios,
+        winsize: interface::WinSize,
+    ) -> Tty {
+        Tty {
+            index: index,
+            ptsmount: ptsmount,
+            ptsdir: ptsdir,
+            slaveinode: slaveinode,
+            state: interface::Mutex::new(TtyState {
+                termios: termios,
+                winsize: winsize,
+                fgpgrp: 0,
+                console: false,
+                line: vec![],
+                input: interface::RustDeque::new(),
+                output: interface::RustDeque::new(),
+                locked: true, // a new pty stays locked until unlockpt
+                masterclosed: false,
+                slaveclosed: false,
+                slaveopen: false,
+            }),
+            cv: interface::Condvar::new(),
+        }
+    }
+
+    //wake readers and writers blocked on this tty, along with any select or poll watching it
+    fn wake(&self) {
+        self.cv.notify_all();
+        interface::notify_readiness();
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.state.lock().locked
+    }
+
+    pub fn set_locked(&self, locked: bool) {
+        self.state.lock().locked = locked;
+    }
+
+    //Run typed bytes through the line discipline: input mapping, signal characters, and in
+    //canonical mode line editing, with whatever is echoed written back out.
+    pub fn input(&self, bytes: &[u8]) {
+        let mut signals = vec![];
+        let mut state = self.state.lock();
+        let termios = state.termios;
+        let echo = termios.c_lflag & ECHO != 0;
+        for &byte in bytes {
+            let mut c = byte;
+            if c == b'\r' {
+                if termios.c_iflag & IGNCR != 0 {
+                    continue;
+                }
+                if termios.c_iflag & ICRNL != 0 {
+                    c = b'\n';
+                }
+            } else if c == b'\n' && termios.c_iflag & INLCR != 0 {
+                c = b'\r';
+            }
+
+            if termios.c_lflag & ISIG != 0 {
+                let sig = if is_cc(&termios, VINTR, c) {
+                    SIGINT
+                } else if is_cc(&termios, VQUIT, c) {
+                    SIGQUIT
+                } else if is_cc(&termios, VSUSP, c) {
+                    SIGTSTP
+                } else {
+                    0
+                };
+                if sig != 0 {
+                    state.line.clear();
+                    signals.push(sig);
+                    continue;
+                }
+            }
+
+            if !state.canonical() {
+                //input past what the terminal can hold is dropped, as Linux does
+                if state.pending() >= TTY_BUFFER_MAX {
+                    continue;
+                }
+                match state.input.back_mut() {
+                    Some(chunk) if !chunk.is_empty() => chunk.push(c),
+                    _ => state.input.push_back(vec![c]),
+                }
+                if echo {
+                    state.emit(&[c]);
+                }
+                continue;
+            }
+
+            if is_cc(&termios, VERASE, c) {
+                if state.line.pop().is_some() && echo && termios.c_lflag & ECHOE != 0 {
+                    state.emit(b"\x08 \x08");
+                }
+            } else if is_cc(&termios, VKILL, c) {
+                state.line.clear();
+                if echo && termios.c_lflag & ECHOK != 0 {
+                    state.emit(b"\n");
+                }
+            } else if is_cc(&termios, VEOF, c) {
+                //commits the line without a newline, or on an empty line makes a read return 0
+                let line = std::mem::take(&mut state.line);
+                state.input.push_back(line);
+            } else if state.pending() < TTY_BUFFER_MAX {
+                state.line.push(c);
+                if echo || (c == b'\n' && termios.c_lflag & ECHONL != 0) {
+                    state.emit(&[c]);
+                }
+                if c == b'\n' || is_cc(&termios, VEOL, c) {
+                    state.commit_line();
+                }
+            }
+        }
+        let fgpgrp = state.fgpgrp;
+        drop(state);
+        self.wake();
+        for sig in signals {
+            signal_pgrp(fgpgrp, sig);
+        }
+    }
+
+    //returns whether the slave is still open, and so still needs the pty
+    fn master_hangup(&self) -> bool {
+        let mut state = self.state.lock();
+        state.masterclosed = true;
+        let slaveopen = state.slaveopen;
+        let fgpgrp = state.fgpgrp;
+        drop(state);
+        self.wake();
+        signal_pgrp(fgpgrp, SIGHUP);
+
+        //the slave's name goes away with its master, though descriptors for it stay usable
+        let slavename = self.index.to_string();
+        let slaveinuse = match FS_METADATA.inodetable.get_mut(&self.slaveinode) {
+            Some(mut inode) => match &mut *inode {
+                Inode::CharDev(devinode) => {
+                    devinode.linkcount = 0;
+                    Some(devinode.refcount > 0)
+                }
+                _ => None,
+            },
+            None => None,
+        };
+        let slaveinuse = match slaveinuse {
+            Some(inuse) => inuse,
+            None => return slaveopen,
+        };
+        if let Some(mut dirinode) = FS_METADATA.inodetable.get_mut(&self.ptsdir) {
+            if let Inode::Dir(ref mut dir) = *dirinode {
+                if dir.filename_to_inode_dict.remove(&slavename).is_some() {
+                    dir.linkcount -= 1;
+                }
+            }
+        }
+        if !slaveinuse {
+            FS_METADATA.inodetable.remove(&self.slaveinode);
+            mount_release_inode(self.slaveinode, 0);
+        }
+        slaveopen
+    }
+
+    //returns an errno if the read can't wait for input, or None once it is ready to
+    fn _read_would_block(
+        vminzero: bool,
+        nonblocking: bool,
+        interrupted: &dyn Fn() -> Option<i32>,
+    ) -> Option<i32> {
+        if vminzero {
+            return Some(0);
+        }
+        if nonblocking {
+            return Some(syscall_error(
+                Errno::EAGAIN,
+                "read",
+                "there is no data available right now, try again later",
+            ));
+        }
+        interrupted()
+    }
+
+    pub fn read(
+        &self,
+        end: TtyEnd,
+        buf: *mut u8,
+        count: usize,
+        nonblocking: bool,
+        interrupted: &dyn Fn() -> Option<i32>,
+    ) -> i32 {
+        if count == 0 {
+            return 0;
+        }
+        if end == TtyEnd::Master {
+            return self.master_read(buf, count, nonblocking, interrupted);
+        }
+        loop {
+            let mut state = self.state.lock();
+            if let Some(ret) = state.take_input(buf, count) {
+                self.wake();
+                return ret;
+            }
+            if end == TtyEnd::Slave && state.masterclosed {
+                return 0;
+            }
+            let vminzero = !state.canonical() && state.termios.c_cc[VMIN] == 0;
+
+            if end != TtyEnd::Console {
+                if let Some(ret) = Tty::_read_would_block(vminzero, nonblocking, interrupted) {
+                    return ret;
+                }
+                self.cv
+                    .wait_for(&mut state, interface::SIGNAL_CHECK_INTERVAL);
+                continue;
+            }
+
+            //the console has nothing buffered, so see what the host has been sent
+            drop(state);
+            let mut hostbuf = [0u8; TTY_BUFFER_MAX];
+            let timeout = if vminzero || nonblocking {
+                interface::RustDuration::from_millis(0)
+            } else {
+                interface::SIGNAL_CHECK_INTERVAL
+            };
+            let ret = interface::read_host_stdin(&mut hostbuf, timeout);
+            if ret > 0 {
+                self.input(&hostbuf[..ret as usize]);
+                continue;
+            }
+            if ret != -(Errno::EAGAIN as i32) {
+                //the host's input has ended, which ends whatever line was being typed
+                if self.state.lock().commit_line() {
+                    continue;
+                }
+                return 0;
+            }
+            if let Some(ret) = Tty::_read_would_block(vminzero, nonblocking, interrupted) {
+                return ret;
+            }
+        }
+    }
+
+    fn master_read(
+        &self,
+        buf: *mut u8,
+        count: usize,
+        nonblocking: bool,
+        interrupted: &dyn Fn() -> Option<i32>,
+    ) -> i32 {
+        loop {
+            let mut state = self.state.lock();
+            if !state.output.is_empty() {
+                let n = interface::rust_min(count, state.output.len());
+                interface::copy_fromrustdeque_sized(buf, n, &state.output);
+                state.output.drain(..n);
+                drop(state);
+                self.wake();
+                return n as i32;
+            }
+            if state.slaveclosed {
+                return syscall_error(Errno::EIO, "read", "the pty slave has been closed");
+            }
+            if let Some(ret) = Tty::_read_would_block(false, nonblocking, interrupted) {
+                return ret;
+            }
+            self.cv
+                .wait_for(&mut state, interface::SIGNAL_CHECK_INTERVAL);
+        }
+    }
+
+    pub fn write(
+        &self,
+        end: TtyEnd,
+        buf: interface::UserBuffer,
+        nonblocking: bool,
+        interrupted: &dyn Fn() -> Option<i32>,
+    ) -> i32 {
+        let bytes = unsafe { buf.as_slice() };
+        let count = bytes.len();
+        match end {
+            //the host terminal does its own output processing
+            TtyEnd::Console => {
+                if let Ok(s) = std::str::from_utf8(bytes) {
+                    interface::log_to_stdout(s);
+                }
+                count as i32
+            }
+            TtyEnd::Master => {
+                self.input(bytes);
+                count as i32
+            }
+            TtyEnd::Slave => {
+                let mut written = 0;
+                loop {
+                    let mut state = self.state.lock();
+                    if state.masterclosed {
+                        return syscall_error(
+                            Errno::EIO,
+                            "write",
+                            "the pty master has been closed",
+                        );
+                    }
+                    let room = TTY_BUFFER_MAX.saturating_sub(state.output.len());
+                    if room > 0 {
+                        let n = interface::rust_min(room, count - written);
+                        state.emit(&bytes[written..written + n]);
+                        written += n;
+                        if written == count {
+                            drop(state);
+                            self.wake();
+                            return count as i32;
+                        }
+                    }
+                    self.wake();
+                    if nonblocking {
+                        if written > 0 {
+                            return written as i32;
+                        }
+                        return syscall_error(
+                            Errno::EAGAIN,
+                            "write",
+                            "the pty is full, try again later",
+                        );
+                    }
+                    if let Some(ret) = interrupted() {
+                        return if written > 0 { written as i32 } else { ret };
+                    }
+                    self.cv
+                        .wait_for(&mut state, interface::SIGNAL_CHECK_INTERVAL);
+                }
+            }
+        }
+    }
+
+    //returns the subset of POLLIN and POLLOUT in events that would not block right now
+    pub fn poll(&self, end: TtyEnd, events: i16) -> i16 {
+        let state = self.state.lock();
+        let (readable, writable) = match end {
+            TtyEnd::Console => (state.readable(), true),
+            TtyEnd::Master => (!state.output.is_empty() || state.slaveclosed, true),
+            TtyEnd::Slave => (
+                state.readable() || state.masterclosed,
+                state.output.len() < TTY_BUFFER_MAX || state.masterclosed,
+            ),
+        };
+        let mut revents = 0;
+        if readable {
+            revents |= POLLIN;
+        }
+        if writable {
+            revents |= POLLOUT;
+        }
+        events & revents
+    }
+
+    pub fn ioctl(&self, end: TtyEnd, request: u32, ptrunion: interface::IoctlPtrUnion) -> i32 {
+        let mut state = self.state.lock();
+        match request {
+            TCGETS => match interface::get_ioctl_termios(ptrunion) {
+                Ok(termios) => {
+                    *termios = state.termios;
+                    0
+                }
+                Err(e) => e,
+            },
+            //there is never output waiting on the terminal itself, so TCSETSW is TCSETS
+            TCSETS | TCSETSW | TCSETSF => {
+                let termios = match interface::get_ioctl_termios(ptrunion) {
+                    Ok(termios) => *termios,
+                    Err(e) => return e,
+                };
+                let wascanonical = state.canonical();
+                state.termios = termios;
+                if request == TCSETSF {
+                    state.line.clear();
+                    state.input.clear();
+                }
+                if wascanonical && !state.canonical() {
+                    state.commit_line();
+                }
+                drop(state);
+                self.wake();
+                0
+            }
+            TIOCGWINSZ => match interface::get_ioctl_winsize(ptrunion) {
+                Ok(winsize) => {
+                    *winsize = state.winsize;
+                    0
+                }
+                Err(e) => e,
+            },
+            TIOCSWINSZ => {
+                let winsize = match interface::get_ioctl_winsize(ptrunion) {
+                    Ok(winsize) => *winsize,
+                    Err(e) => return e,
+                };
+                let changed = state.winsize != winsize;
+                state.winsize = winsize;
+                let fgpgrp = state.fgpgrp;
+                drop(state);
+                if changed {
+                    signal_pgrp(fgpgrp, SIGWINCH);
+                }
+                0
+            }
+            TIOCGPGRP => match interface::set_ioctl_int(ptrunion, state.fgpgrp as i32) {
+                Ok(()) => 0,
+                Err(e) => e,
+            },
+            TIOCSPGRP => match interface::get_ioctl_int(ptrunion) {
+                Ok(pgid) if pgid < 0 => {
+                    syscall_error(Errno::EINVAL, "ioctl", "process group is negative")
+                }
+                Ok(pgid) => {
+                    state.fgpgrp = pgid as u64;
+                    0
+                }
+                Err(e) => e,
+            },
+            TIOCGPTN if end == TtyEnd::Master => {
+                match interface::set_ioctl_int(ptrunion, self.index as i32) {
+                    Ok(()) => 0,
+                    Err(e) => e,
+                }
+            }
+            TIOCSPTLCK if end == TtyEnd::Master => match interface::get_ioctl_int(ptrunion) {
+                Ok(lock) => {
+                    state.locked = lock != 0;
+                    0
+                }
+                Err(e) => e,
+            },
+            _ => syscall_error(
+                Errno::ENOTTY,
+                "ioctl",
+                "The specified request does not apply to the kind of object that the file descriptor fd references.",
+            ),
+        }
+    }
+}
@@ -4,7 +4,10 @@ pub mod fs_tests {
     use super::super::*;
     use crate::interface;
     use crate::safeposix::syscalls::fs_calls::*;
-    use crate::safeposix::{cage::*, dispatcher::*, filesystem};
+    use crate::safeposix::{
+        cage::*, context, dispatcher::*, dump, filesystem, inotify, lifecycle::*, metrics, procfs,
+        quota, tty,
+    };
     use std::fs::OpenOptions;
     use std::os::unix::fs::PermissionsExt;
 
@@ -45,10 +48,69 @@ pub mod fs_tests {
         ut_lind_fs_exec_cloexec();
         ut_lind_fs_shm();
         ut_lind_fs_getpid_getppid();
+        ut_lind_fs_waitpid();
+        ut_lind_fs_pgid_sid();
+        ut_lind_fs_priority();
+        ut_lind_fs_clone();
+        ut_lind_fs_environ();
+        ut_lind_fs_setuid();
+        ut_lind_fs_groups();
+        ut_lind_fs_capabilities();
+        ut_lind_fs_syscall_filter();
+        ut_lind_fs_syscall_metrics();
+        ut_lind_fs_debug_dump();
+        ut_lind_fs_corrupted_inode();
+        ut_lind_fs_affinity();
+        ut_lind_fs_lifecycle_hooks();
+        ut_lind_fs_signal_delivery();
+        ut_lind_fs_rt_signals();
+        ut_lind_fs_sigchld();
+        ut_lind_fs_cpu_timers();
+        ut_lind_fs_posix_timers();
+        ut_lind_fs_clocks();
+        ut_lind_fs_user_buffers();
+        ut_lind_fs_statx();
+        ut_lind_fs_pty();
+        ut_lind_fs_uname();
+        ut_lind_fs_sysinfo();
         ut_lind_fs_sem_fork();
         ut_lind_fs_sem_trytimed();
         ut_lind_fs_sem_test();
         ut_lind_fs_tmp_file_test();
+        ut_lind_fs_inotify();
+        ut_lind_fs_mmap_shared();
+        ut_lind_fs_mount();
+        ut_lind_fs_tmpfs();
+        ut_lind_fs_procfs();
+        ut_lind_fs_procfs_fds();
+        ut_lind_fs_devices();
+        ut_lind_fs_chardev_registry();
+        ut_lind_fs_chroot();
+        ut_lind_fs_quota();
+        ut_lind_fs_dentry_cache();
+        ut_lind_fs_log_compaction();
+        ut_lind_fs_log_batching();
+        ut_lind_fs_log_entries();
+        ut_lind_fs_periodic_persist();
+        ut_lind_fs_metadata_format();
+        ut_lind_fs_fsck_gc();
+        ut_lind_fs_tar();
+        ut_lind_fs_dir_index();
+        ut_lind_fs_at_syscalls();
+        ut_lind_fs_open_dir_path_flags();
+        ut_lind_fs_fchdir();
+        ut_lind_fs_readonly();
+        ut_lind_fs_sync_flags();
+        ut_lind_fs_leases();
+        ut_lind_fs_attr_changes();
+        ut_lind_fs_contexts();
+        ut_lind_fs_sync_syncfs();
+        ut_lind_fs_dup3();
+        ut_lind_fs_fd_status_flags();
+        ut_lind_fs_exec_cloexec_fd_kinds();
+        ut_lind_fs_memfd_create();
+        ut_lind_fs_fdtable_growth();
+        ut_lind_fs_cancel_blocked();
     }
 
     pub fn ut_lind_fs_simple() {
@@ -63,7 +125,7 @@ pub mod fs_tests {
         assert_eq!(cage.stat_syscall("/", &mut statdata2), 0);
         //ensure that there are two hard links
 
-        assert_eq!(statdata2.st_nlink, 5); //2 for . and .., one for dev, and one so that it can never be removed
+        assert_eq!(statdata2.st_nlink, 6); //2 for . and .., one each for dev, tmp and proc, and one so that it can never be removed
 
         //ensure that there is no associated size
         assert_eq!(statdata2.st_size, 0);
@@ -687,8 +749,9 @@ pub mod fs_tests {
         let path = "/null";
 
         //now we are going to mknod /dev/null with create, read and write flags and permissions
-        //and then makr sure that it exists
-        assert_eq!(cage.mknod_syscall(path, S_IFCHR as u32, dev), 0);
+        //and then makr sure that it exists; opening a node is checked against its permission
+        //bits like any other file, so it needs some
+        assert_eq!(cage.mknod_syscall(path, S_IFCHR as u32 | 0o666, dev), 0);
         let fd = cage.open_syscall(path, O_RDWR, S_IRWXA);
 
         //checking the metadata of the file:
@@ -711,7 +774,7 @@ pub mod fs_tests {
         let path2 = "/random";
 
         //making the node and then making sure that it exists
-        assert_eq!(cage.mknod_syscall(path2, S_IFCHR as u32, dev2), 0);
+        assert_eq!(cage.mknod_syscall(path2, S_IFCHR as u32 | 0o666, dev2), 0);
         let fd2 = cage.open_syscall(path2, O_RDWR, S_IRWXA);
 
         let mut buf2 = sizecbuf(4);
@@ -1006,7 +1069,7 @@ pub mod fs_tests {
         assert_eq!(cage.fstat_syscall(fd1, &mut uselessstatdata), 0);
         assert_eq!(cage.fstat_syscall(fd2, &mut uselessstatdata), 0);
 
-        assert_eq!(cage.exec_syscall(2), 0);
+        assert_eq!(cage.exec_syscall(2, None), 0);
 
         let execcage = interface::cagetable_getref(2);
         assert_eq!(
@@ -1082,161 +1145,4250 @@ pub mod fs_tests {
         lindrustfinalize();
     }
 
-    pub fn ut_lind_fs_sem_fork() {
+    pub fn ut_lind_fs_waitpid() {
         lindrustinit(0);
-        let cage = interface::cagetable_getref(1);
-        let key = 31337;
-        // Create a shared memory region
-        let shmid = cage.shmget_syscall(key, 1024, 0666 | IPC_CREAT);
-        // Attach the shared memory region
-        let shmatret = cage.shmat_syscall(shmid, 0xfffff000 as *mut u8, 0);
-        assert_ne!(shmatret, -1);
-        // Initialize the semaphore with shared between process
-        let ret_init = cage.sem_init_syscall(shmatret as u32, 1, 1);
-        assert_eq!(ret_init, 0);
-        assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), 1);
-        // Fork child process
-        assert_eq!(cage.fork_syscall(2), 0);
-        // Child process
-        let thread_child = interface::helper_thread(move || {
-            let cage1 = interface::cagetable_getref(2);
-            // Child waits for the semaphore
-            assert_eq!(cage1.sem_wait_syscall(shmatret as u32), 0);
-            interface::sleep(interface::RustDuration::from_millis(40));
-            // Release the semaphore
-            assert_eq!(cage1.sem_post_syscall(shmatret as u32), 0);
-            cage1.exit_syscall(EXIT_SUCCESS);
-        });
-        //Parent processes
-        let thread_parent = interface::helper_thread(move || {
-            // Parents waits for the semaphore
-            assert_eq!(cage.sem_wait_syscall(shmatret as u32), 0);
-            assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), 0);
+        let cage1 = interface::cagetable_getref(1);
+        let mut status = 0;
+
+        assert_eq!(
+            cage1.waitpid_syscall(-1, Some(&mut status), 0),
+            -(Errno::ECHILD as i32)
+        );
+        assert_eq!(cage1.fork_syscall(2), 0);
+        assert_eq!(cage1.fork_syscall(3), 0);
+        assert_eq!(
+            cage1.waitpid_syscall(-1, None, 0x100),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage1.waitpid_syscall(-1, Some(&mut status), WNOHANG), 0);
+
+        //waiting on a specific child blocks until it exits, and reports its exit status
+        let child = std::thread::spawn(move || {
+            let cage2 = interface::cagetable_getref(2);
             interface::sleep(interface::RustDuration::from_millis(100));
-            // Parents release the semaphore
-            assert_eq!(cage.sem_post_syscall(shmatret as u32), 0);
-            assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), 1);
-            // Destroy the semaphore
-            assert_eq!(cage.sem_destroy_syscall(shmatret as u32), 0);
-            // mark the shared memory to be rmoved
-            let shmctlret2 = cage.shmctl_syscall(shmid, IPC_RMID, None);
-            assert_eq!(shmctlret2, 0);
-            //detach from shared memory
-            let shmdtret = cage.shmdt_syscall(0xfffff000 as *mut u8);
-            assert_eq!(shmdtret, shmid);
-            cage.exit_syscall(EXIT_SUCCESS);
+            assert_eq!(cage2.exit_syscall(3), 3);
         });
-        thread_child.join().unwrap();
-        thread_parent.join().unwrap();
+        assert_eq!(cage1.waitpid_syscall(2, Some(&mut status), 0), 2);
+        child.join().unwrap();
+        assert_eq!(status & 0x7f, 0);
+        assert_eq!((status >> 8) & 0xff, 3);
+
+        //a cage ended by a signal reports the signal instead
+        interface::cagetable_getref(3).exit_by_signal(SIGKILL);
+        assert_eq!(cage1.wait_syscall(Some(&mut status)), 3);
+        assert_eq!(status & 0x7f, SIGKILL);
+        assert_eq!(
+            cage1.waitpid_syscall(3, Some(&mut status), 0),
+            -(Errno::ECHILD as i32)
+        );
+
+        //wait4 matches on the process group, and hands back zeroed resource usage
+        assert_eq!(cage1.fork_syscall(4), 0);
+        assert_eq!(interface::cagetable_getref(4).exit_syscall(0), 0);
+        let mut rusage = Rusage::default();
+        rusage.ru_maxrss = 1;
+        assert_eq!(
+            cage1.wait4_syscall(-5, Some(&mut status), 0, Some(&mut rusage)),
+            -(Errno::ECHILD as i32)
+        );
+        assert_eq!(rusage.ru_maxrss, 1);
+        assert_eq!(
+            cage1.wait4_syscall(0, Some(&mut status), WNOHANG, Some(&mut rusage)),
+            4
+        );
+        assert_eq!(status, 0);
+        assert_eq!(rusage.ru_maxrss, 0);
+
+        //a child that execs is still the parent's to wait for under its new id
+        assert_eq!(cage1.fork_syscall(5), 0);
+        assert_eq!(interface::cagetable_getref(5).exec_syscall(6, None), 0);
+        assert_eq!(interface::cagetable_getref(6).exit_syscall(1), 1);
+        assert_eq!(cage1.waitpid_syscall(6, Some(&mut status), 0), 6);
+        assert_eq!((status >> 8) & 0xff, 1);
+
+        assert_eq!(cage1.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_pgid_sid() {
+        lindrustinit(0);
+        let cage1 = interface::cagetable_getref(1);
+
+        //the init cage leads its own group and session, which children inherit
+        assert_eq!(cage1.getpgid_syscall(0), 1);
+        assert_eq!(cage1.getsid_syscall(0), 1);
+        assert_eq!(cage1.setsid_syscall(), -(Errno::EPERM as i32));
+        assert_eq!(cage1.getpgid_syscall(50), -(Errno::ESRCH as i32));
+        assert_eq!(cage1.fork_syscall(2), 0);
+        let cage2 = interface::cagetable_getref(2);
+        assert_eq!(cage1.getpgid_syscall(2), 1);
+
+        //a cage that leaves for a new session leads it and a new group in it
+        assert_eq!(cage2.setsid_syscall(), 2);
+        assert_eq!(cage2.getpgid_syscall(0), 2);
+        assert_eq!(cage2.getsid_syscall(0), 2);
+        assert_eq!(cage2.setsid_syscall(), -(Errno::EPERM as i32));
+        assert_eq!(cage1.setpgid_syscall(2, 0), -(Errno::EPERM as i32));
+
+        //a parent moves a child between groups of its own session
+        assert_eq!(cage1.fork_syscall(3), 0);
+        let cage3 = interface::cagetable_getref(3);
+        assert_eq!(cage1.setpgid_syscall(3, 0), 0);
+        assert_eq!(cage3.getpgid_syscall(0), 3);
+        assert_eq!(cage1.setpgid_syscall(3, 1), 0);
+        assert_eq!(cage1.getpgid_syscall(3), 1);
+        assert_eq!(cage1.setpgid_syscall(3, 2), -(Errno::EPERM as i32));
+        assert_eq!(cage1.setpgid_syscall(3, 99), -(Errno::EPERM as i32));
+        assert_eq!(cage1.setpgid_syscall(3, -1), -(Errno::EINVAL as i32));
+        assert_eq!(cage2.setpgid_syscall(3, 0), -(Errno::ESRCH as i32));
+        assert_eq!(cage3.setpgid_syscall(0, 0), 0);
+        assert_eq!(cage3.getpgid_syscall(0), 3);
+
+        //children join their parent's group, and a parent can wait on the group
+        assert_eq!(cage3.fork_syscall(4), 0);
+        assert_eq!(cage1.getpgid_syscall(4), 3);
+        assert_eq!(interface::cagetable_getref(4).exit_syscall(0), 0);
+        assert_eq!(
+            cage3.waitpid_syscall(-9, None, WNOHANG),
+            -(Errno::ECHILD as i32)
+        );
+        assert_eq!(cage3.waitpid_syscall(0, None, 0), 4);
+
+        //signalling an empty group fails, as does signalling every cage
+        assert_eq!(cage1.kill_syscall(-77, SIGUSR1), -(Errno::ESRCH as i32));
+        assert_eq!(cage1.kill_syscall(-1, SIGUSR1), -(Errno::EINVAL as i32));
+
+        //a group follows its leader across exec
+        assert_eq!(cage3.exec_syscall(5, None), 0);
+        assert_eq!(cage1.getpgid_syscall(5), 5);
+        assert_eq!(cage1.getsid_syscall(5), 1);
+
+        assert_eq!(
+            interface::cagetable_getref(5).exit_syscall(EXIT_SUCCESS),
+            EXIT_SUCCESS
+        );
+        assert_eq!(cage2.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(cage1.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_priority() {
+        lindrustinit(0);
+        let cage1 = interface::cagetable_getref(1);
+
+        //getpriority reports 20 - nice, so it never looks like an error
+        assert_eq!(cage1.getpriority_syscall(PRIO_PROCESS, 0), 20);
+        assert_eq!(cage1.nice_syscall(5), 5);
+        assert_eq!(cage1.getpriority_syscall(PRIO_PROCESS, 0), 15);
+        assert_eq!(cage1.nice_syscall(-1), -(Errno::EPERM as i32));
+
+        //children inherit the nice value, and may be made nicer but not less nice
+        assert_eq!(cage1.fork_syscall(2), 0);
+        assert_eq!(cage1.getpriority_syscall(PRIO_PROCESS, 2), 15);
+        assert_eq!(cage1.setpriority_syscall(PRIO_PROCESS, 2, 10), 0);
+        assert_eq!(cage1.getpriority_syscall(PRIO_PROCESS, 2), 10);
+        assert_eq!(
+            cage1.setpriority_syscall(PRIO_PROCESS, 2, 0),
+            -(Errno::EACCES as i32)
+        );
+        assert_eq!(cage1.setpriority_syscall(PRIO_PROCESS, 2, 100), 0);
+        assert_eq!(
+            cage1.getpriority_syscall(PRIO_PROCESS, 2),
+            20 - (PRIO_MAX - 1)
+        );
+
+        //a group or user reports the highest priority among its cages
+        assert_eq!(cage1.getpriority_syscall(PRIO_PGRP, 0), 15);
+        assert_eq!(cage1.getpriority_syscall(PRIO_USER, 0), 15);
+        assert_eq!(
+            cage1.getpriority_syscall(PRIO_USER, 1234),
+            -(Errno::ESRCH as i32)
+        );
+        assert_eq!(
+            cage1.getpriority_syscall(PRIO_PROCESS, 50),
+            -(Errno::ESRCH as i32)
+        );
+        assert_eq!(cage1.getpriority_syscall(7, 0), -(Errno::EINVAL as i32));
+        assert_eq!(cage1.nice_syscall(100), PRIO_MAX - 1);
+
+        assert_eq!(
+            interface::cagetable_getref(2).exit_syscall(EXIT_SUCCESS),
+            EXIT_SUCCESS
+        );
+        assert_eq!(cage1.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_clone() {
+        lindrustinit(0);
+        let cage1 = interface::cagetable_getref(1);
+
+        //only the sharing flags and an exit signal are understood
+        assert_eq!(cage1.clone_syscall(0x100, 2), -(Errno::EINVAL as i32));
+
+        //with everything shared, what one cage changes the other sees
+        assert_eq!(
+            cage1.clone_syscall(CLONE_FILES | CLONE_FS | CLONE_SIGHAND | SIGCHLD, 2),
+            0
+        );
+        let cage2 = interface::cagetable_getref(2);
+        let fd = cage2.open_syscall("/clonefile", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(cage1.write_syscall(fd, str2cbuf("hi"), 2), 2);
+        assert_eq!(cage1.mkdir_syscall("/clonedir", S_IRWXA), 0);
+        assert_eq!(cage1.chdir_syscall("/clonedir"), 0);
+        let relfd = cage2.open_syscall("relfile", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(relfd >= 0);
+        assert_eq!(cage1.access_syscall("/clonedir/relfile", F_OK), 0);
+        let mut act = interface::SigactionStruct::default();
+        act.sa_handler = 1234;
+        let mut oact = interface::SigactionStruct::default();
+        assert_eq!(cage1.sigaction_syscall(SIGUSR1, Some(&act), None), 0);
+        assert_eq!(cage2.sigaction_syscall(SIGUSR1, None, Some(&mut oact)), 0);
+        assert_eq!(oact.sa_handler, 1234);
+
+        //a plain clone gets its own copies, like fork
+        assert_eq!(cage1.clone_syscall(SIGCHLD, 3), 0);
+        let cage3 = interface::cagetable_getref(3);
+        assert_eq!(cage3.close_syscall(fd), 0);
+        assert_eq!(cage1.lseek_syscall(fd, 0, SEEK_SET), 0);
+        assert_eq!(cage3.chdir_syscall("/"), 0);
+        assert_eq!(cage1.access_syscall("relfile", F_OK), 0);
+        assert_eq!(cage3.sigaction_syscall(SIGUSR2, Some(&act), None), 0);
+        assert_eq!(cage1.sigaction_syscall(SIGUSR2, None, Some(&mut oact)), 0);
+        assert_eq!(oact.sa_handler, 0);
+
+        //a shared table outlives the cages that leave it, and exec stops sharing it
+        assert_eq!(cage2.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(cage1.lseek_syscall(fd, 0, SEEK_SET), 0);
+        assert_eq!(cage1.clone_syscall(CLONE_FILES, 4), 0);
+        assert_eq!(interface::cagetable_getref(4).exec_syscall(5, None), 0);
+        let cage5 = interface::cagetable_getref(5);
+        assert_eq!(cage5.close_syscall(fd), 0);
+        assert_eq!(cage1.lseek_syscall(fd, 0, SEEK_SET), 0);
+
+        assert_eq!(cage5.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(cage3.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(cage1.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_environ() {
+        lindrustinit(0);
+        let cage1 = interface::cagetable_getref(1);
+        let mut buf = vec![0u8; 64];
+        let bufptr: *mut u8 = &mut buf[0];
+
+        //cages start out with an empty environment rather than the host's
+        assert_eq!(cage1.getenviron_syscall(None, 0), 0);
+        assert_eq!(
+            cage1.getenv_syscall("HOME", bufptr, 64),
+            -(Errno::ENOENT as i32)
+        );
+
+        assert_eq!(cage1.setenv_syscall("HOME", "/home/lind", 1), 0);
+        assert_eq!(cage1.setenv_syscall("HOME", "/root", 0), 0);
+        assert_eq!(cage1.getenv_syscall("HOME", bufptr, 64), 10);
+        assert_eq!(cbuf2str(&buf[0..11]), "/home/lind\0");
+        assert_eq!(
+            cage1.getenv_syscall("HOME", bufptr, 10),
+            -(Errno::ERANGE as i32)
+        );
+        assert_eq!(cage1.setenv_syscall("HOM", "x", 1), 0);
+        assert_eq!(cage1.setenv_syscall("A=B", "x", 1), -(Errno::EINVAL as i32));
+        assert_eq!(cage1.unsetenv_syscall("HOM"), 0);
+        assert_eq!(
+            cage1.getenv_syscall("HOM", bufptr, 64),
+            -(Errno::ENOENT as i32)
+        );
+
+        //children inherit a copy of the environment
+        assert_eq!(cage1.fork_syscall(2), 0);
+        let cage2 = interface::cagetable_getref(2);
+        assert_eq!(cage2.setenv_syscall("PATH", "/bin", 1), 0);
+        assert_eq!(
+            cage1.getenv_syscall("PATH", bufptr, 64),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(cage2.getenviron_syscall(None, 0), 26);
+        assert_eq!(cage2.getenviron_syscall(Some(bufptr), 64), 26);
+        assert_eq!(cbuf2str(&buf[0..26]), "HOME=/home/lind\0PATH=/bin\0");
+
+        //exec keeps the environment unless it is handed a new one
+        assert_eq!(cage2.exec_syscall(3, None), 0);
+        let cage3 = interface::cagetable_getref(3);
+        assert_eq!(cage3.getenv_syscall("PATH", bufptr, 64), 4);
+        assert_eq!(cage3.exec_syscall(4, Some(vec!["LANG=C"])), 0);
+        let cage4 = interface::cagetable_getref(4);
+        assert_eq!(
+            cage4.getenv_syscall("PATH", bufptr, 64),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(cage4.getenv_syscall("LANG", bufptr, 64), 1);
+
+        assert_eq!(cage4.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(cage1.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_setuid() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        //let's get the initial -1s out of the way
+        cage.getuid_syscall();
+        cage.geteuid_syscall();
+
+        //an ordinary cage can't make itself root
+        assert_eq!(cage.setuid_syscall(0), -(Errno::EPERM as i32));
+        assert_eq!(cage.seteuid_syscall(0), -(Errno::EPERM as i32));
+        assert_eq!(cage.setgid_syscall(0), -(Errno::EPERM as i32));
+        assert_eq!(cage.setuid_syscall(u32::MAX), -(Errno::EINVAL as i32));
+
+        //as root, make a file only root may read
+        *cage.cred.write() = Credentials::new(0, 0);
+        let path = "/setuidfile";
+        let fd = cage.open_syscall(path, O_CREAT | O_EXCL | O_WRONLY, 0o600);
+        assert!(fd >= 0);
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //dropping only the effective uid keeps root as the real and saved uid
+        assert_eq!(cage.seteuid_syscall(DEFAULT_UID), 0);
+        assert_eq!(cage.getuid_syscall(), 0);
+        assert_eq!(cage.geteuid_syscall() as u32, DEFAULT_UID);
+        assert_eq!(
+            cage.open_syscall(path, O_RDONLY, 0),
+            -(Errno::EACCES as i32)
+        );
+        assert_eq!(cage.chmod_syscall(path, 0o666), -(Errno::EPERM as i32));
+        //access checks against the real uid, which is still root
+        assert_eq!(cage.access_syscall(path, R_OK), 0);
+
+        //the saved uid lets it become root again
+        assert_eq!(cage.seteuid_syscall(0), 0);
+        let fd = cage.open_syscall(path, O_RDONLY, 0);
+        assert!(fd >= 0);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall(path), 0);
+
+        //setuid from root drops all three for good
+        assert_eq!(cage.setuid_syscall(DEFAULT_UID), 0);
+        assert_eq!(cage.getuid_syscall() as u32, DEFAULT_UID);
+        assert_eq!(cage.seteuid_syscall(0), -(Errno::EPERM as i32));
+
+        //a socketpair's peer is the cage that made it
+        let mut socketpair = interface::SockPair::default();
+        assert_eq!(
+            Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut socketpair),
+            0
+        );
+        let mut peercred = interface::UCred::default();
+        assert_eq!(cage.getpeercred_syscall(socketpair.sock1, &mut peercred), 0);
+        assert_eq!(peercred.pid, 1);
+        assert_eq!(peercred.uid, DEFAULT_UID);
+        //setuid leaves the group ids as root had them
+        assert_eq!(peercred.gid, 0);
+        assert_eq!(cage.close_syscall(socketpair.sock1), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock2), 0);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_groups() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        //a cage starts with no supplementary groups, and can't give itself any
+        let mut list = [0u32; 4];
+        assert_eq!(cage.getgroups_syscall(&mut []), 0);
+        assert_eq!(cage.setgroups_syscall(&[50]), -(Errno::EPERM as i32));
+
+        *cage.cred.write() = Credentials::new(0, 0);
+        assert_eq!(cage.setgroups_syscall(&[50, 60]), 0);
+        assert_eq!(cage.getgroups_syscall(&mut []), 2);
+        assert_eq!(
+            cage.getgroups_syscall(&mut list[..1]),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.getgroups_syscall(&mut list), 2);
+        assert_eq!(list[..2], [50, 60]);
+
+        //a file owned by root that only group 50 may also read
+        let path = "/groupsfile";
+        assert_eq!(cage.setegid_syscall(50), 0);
+        let fd = cage.open_syscall(path, O_CREAT | O_EXCL | O_WRONLY, 0o640);
+        assert!(fd >= 0);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.setegid_syscall(0), 0);
+
+        //membership through a supplementary group grants the group bits
+        assert_eq!(cage.seteuid_syscall(DEFAULT_UID), 0);
+        let fd = cage.open_syscall(path, O_RDONLY, 0);
+        assert!(fd >= 0);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(
+            cage.open_syscall(path, O_WRONLY, 0),
+            -(Errno::EACCES as i32)
+        );
+
+        //and without it only the other bits are left
+        assert_eq!(cage.seteuid_syscall(0), 0);
+        assert_eq!(cage.setgroups_syscall(&[60]), 0);
+        assert_eq!(cage.seteuid_syscall(DEFAULT_UID), 0);
+        assert_eq!(
+            cage.open_syscall(path, O_RDONLY, 0),
+            -(Errno::EACCES as i32)
+        );
+
+        assert_eq!(cage.seteuid_syscall(0), 0);
+        assert_eq!(cage.unlink_syscall(path), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_capabilities() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        //an ordinary cage holds no capabilities
+        let mut header = interface::CapUserHeader { version: 0, pid: 0 };
+        let mut data = [interface::CapUserData::default(); _LINUX_CAPABILITY_U32S_3];
+        assert_eq!(cage.capget_syscall(&mut header, None), 0);
+        assert_eq!(header.version, _LINUX_CAPABILITY_VERSION_3);
+        assert_eq!(cage.capget_syscall(&mut header, Some(&mut data)), 0);
+        assert_eq!(data[0].permitted, 0);
+        assert_eq!(data[0].effective, 0);
+
+        //so raw sockets, privileged ports and giving files away are all refused
+        assert_eq!(
+            cage.socket_syscall(AF_INET, SOCK_RAW, 0),
+            -(Errno::EPERM as i32)
+        );
+        let sockfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        let socket = interface::GenSockaddr::V4(interface::SockaddrV4 {
+            sin_family: AF_INET as u16,
+            sin_port: 80u16.to_be(),
+            sin_addr: interface::V4Addr {
+                s_addr: u32::from_ne_bytes([127, 0, 0, 1]),
+            },
+            padding: 0,
+        });
+        assert_eq!(cage.bind_syscall(sockfd, &socket), -(Errno::EACCES as i32));
+        assert_eq!(cage.close_syscall(sockfd), 0);
+
+        let path = "/chownfile";
+        let fd = cage.open_syscall(path, O_CREAT | O_EXCL | O_WRONLY, 0o644);
+        assert!(fd >= 0);
+        assert_eq!(
+            cage.chown_syscall(path, 0, u32::MAX),
+            -(Errno::EPERM as i32)
+        );
+        assert_eq!(
+            cage.fchown_syscall(fd, u32::MAX, 50),
+            -(Errno::EPERM as i32)
+        );
+        //but the owner may still pass it to one of its own groups
+        assert_eq!(cage.fchown_syscall(fd, u32::MAX, DEFAULT_GID), 0);
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //root can, until it gives up CAP_CHOWN
+        *cage.cred.write() = Credentials::new(0, 0);
+        assert_eq!(cage.chown_syscall(path, 0, 50), 0);
+        let mut statdata = StatData::default();
+        assert_eq!(cage.stat_syscall(path, &mut statdata), 0);
+        assert_eq!((statdata.st_uid, statdata.st_gid), (0, 50));
+
+        assert_eq!(cage.capget_syscall(&mut header, Some(&mut data)), 0);
+        data[0].effective &= !(1 << CAP_CHOWN);
+        assert_eq!(cage.capset_syscall(&mut header, &data), 0);
+        assert_eq!(
+            cage.chown_syscall(path, DEFAULT_UID, u32::MAX),
+            -(Errno::EPERM as i32)
+        );
+        //it is still permitted, so it can be raised again, unlike one dropped from permitted
+        data[0].effective |= 1 << CAP_CHOWN;
+        data[0].permitted &= !(1 << CAP_NET_RAW);
+        assert_eq!(
+            cage.capset_syscall(&mut header, &data),
+            -(Errno::EPERM as i32)
+        );
+        data[0].effective &= !(1 << CAP_NET_RAW);
+        assert_eq!(cage.capset_syscall(&mut header, &data), 0);
+        assert_eq!(cage.chown_syscall(path, DEFAULT_UID, DEFAULT_GID), 0);
+        data[0].permitted |= 1 << CAP_NET_RAW;
+        assert_eq!(
+            cage.capset_syscall(&mut header, &data),
+            -(Errno::EPERM as i32)
+        );
+
+        //the bounding set can only shrink, and only with CAP_SETPCAP
+        assert_eq!(cage.prctl_syscall(PR_CAPBSET_READ, CAP_SYS_ADMIN as u64), 1);
+        assert_eq!(cage.prctl_syscall(PR_CAPBSET_DROP, CAP_SYS_ADMIN as u64), 0);
+        assert_eq!(cage.prctl_syscall(PR_CAPBSET_READ, CAP_SYS_ADMIN as u64), 0);
+        assert_eq!(
+            cage.prctl_syscall(PR_CAPBSET_READ, 64),
+            -(Errno::EINVAL as i32)
+        );
+
+        //keepcaps holds on to the permitted set when every uid leaves root
+        assert_eq!(cage.prctl_syscall(PR_SET_KEEPCAPS, 1), 0);
+        assert_eq!(cage.prctl_syscall(PR_GET_KEEPCAPS, 0), 1);
+        assert_eq!(cage.setuid_syscall(DEFAULT_UID), 0);
+        assert_eq!(cage.capget_syscall(&mut header, Some(&mut data)), 0);
+        assert_eq!(data[0].effective, 0);
+        assert_ne!(data[0].permitted & (1 << CAP_CHOWN), 0);
+        assert_eq!(
+            cage.chown_syscall(path, 0, u32::MAX),
+            -(Errno::EPERM as i32)
+        );
+        data[0].effective = 1 << CAP_CHOWN;
+        assert_eq!(cage.capset_syscall(&mut header, &data), 0);
+        assert_eq!(cage.chown_syscall(path, 0, u32::MAX), 0);
+
+        assert_eq!(cage.unlink_syscall(path), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_syscall_filter() {
+        lindrustinit(0);
+        let cage1 = interface::cagetable_getref(1);
+        //get the initial -1 of getuid out of the way before the children copy it
+        cage1.getuid_syscall();
+        assert_eq!(cage1.fork_syscall(2), 0);
+
+        //the dispatcher's numbers for the calls used here
+        let getppid = 29;
+        let getpid = 31;
+        let getuid = 50;
+        let noarg = Arg { dispatch_long: 0 };
+        let call = |cageid: u64, callnum: i32| {
+            dispatcher(cageid, callnum, noarg, noarg, noarg, noarg, noarg, noarg)
+        };
+
+        //bad actions and missing cages are refused
+        assert_eq!(
+            lindsetsyscallfilter(2, getpid, 99, 0),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            lindsetsyscallfilter(2, getpid, FILTER_ERRNO, 100000),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            lindsetsyscallfilter(99, getpid, FILTER_DENY, 0),
+            -(Errno::ESRCH as i32)
+        );
+
+        assert_eq!(lindsetsyscallfilter(2, getpid, FILTER_DENY, 0), 0);
+        assert_eq!(
+            lindsetsyscallfilter(2, getppid, FILTER_ERRNO, Errno::EACCES as i32),
+            0
+        );
+        assert_eq!(lindsetsyscallfilter(2, getuid, FILTER_LOG, 0), 0);
+        assert_eq!(call(2, getpid), -(Errno::EPERM as i32));
+        assert_eq!(call(2, getppid), -(Errno::EACCES as i32));
+        assert_eq!(call(2, getuid), DEFAULT_UID as i32);
+        //other cages are left alone
+        assert_eq!(call(1, getpid), 1);
+
+        //a child starts out with its parent's filter, and can be held to an allow list
+        let cage2 = interface::cagetable_getref(2);
+        assert_eq!(cage2.fork_syscall(3), 0);
+        assert_eq!(call(3, getpid), -(Errno::EPERM as i32));
+        assert_eq!(lindsetsyscallfilterdefault(3, FILTER_DENY, 0), 0);
+        assert_eq!(lindsetsyscallfilter(3, getppid, FILTER_ALLOW, 0), 0);
+        assert_eq!(call(3, getppid), 2);
+        assert_eq!(call(3, getuid), DEFAULT_UID as i32);
+        //not even exit is on the list
+        assert_eq!(call(3, 30), -(Errno::EPERM as i32));
+
+        //a kill rule ends the cage as SIGSYS would
+        assert_eq!(lindsetsyscallfilter(3, getppid, FILTER_KILL, 0), 0);
+        assert_eq!(call(3, getppid), -(Errno::ENOSYS as i32));
+        assert!(interface::cagetable_getref_opt(3).is_none());
+        let mut status = 0;
+        assert_eq!(cage2.waitpid_syscall(3, Some(&mut status), 0), 3);
+        assert_eq!(status & 0x7f, SIGSYS);
+
+        assert_eq!(cage2.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(cage1.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_syscall_metrics() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        metrics::reset_syscall_metrics();
+
+        //the dispatcher's numbers for the calls used here
+        let close = 11;
+        let getpid = 31;
+        let noarg = Arg { dispatch_long: 0 };
+        let call = |callnum: i32, arg1: Arg| {
+            dispatcher(1, callnum, arg1, noarg, noarg, noarg, noarg, noarg)
+        };
+
+        //nothing is counted until metrics are turned on
+        assert_eq!(call(getpid, noarg), 1);
+        assert!(metrics::cage_syscall_metrics(1).is_empty());
+
+        lindsetsyscallmetrics(1);
+        for _ in 0..3 {
+            assert_eq!(call(getpid, noarg), 1);
+        }
+        assert_eq!(
+            call(close, Arg { dispatch_long: 999 }),
+            -(Errno::EBADF as i32)
+        );
+        lindsetsyscallmetrics(0);
+        assert_eq!(call(getpid, noarg), 1);
+
+        let stats = metrics::cage_syscall_metrics(1);
+        assert_eq!(stats.len(), 2);
+        let getpidstats = stats.iter().find(|m| m.callnum == getpid).unwrap();
+        assert_eq!(getpidstats.calls, 3);
+        assert_eq!(getpidstats.error_count(), 0);
+        assert!(getpidstats.p50 <= getpidstats.p90 && getpidstats.p90 <= getpidstats.p99);
+        let closestats = stats.iter().find(|m| m.callnum == close).unwrap();
+        assert_eq!(closestats.calls, 1);
+        assert_eq!(closestats.errors.get(&(Errno::EBADF as i32)), Some(&1));
+
+        //the same figures can be read from procfs
+        let fd = cage.open_syscall("/proc/lind/stats", O_RDONLY, 0);
+        assert!(fd >= 0);
+        let mut statsbuf = sizecbuf(1024);
+        let statslen = cage.read_syscall(fd, statsbuf.as_mut_ptr(), 1024);
+        assert!(statslen > 0);
+        let text = std::str::from_utf8(&statsbuf[..statslen as usize]).unwrap();
+        assert!(text.lines().any(|line| line.starts_with("1\t31\t3\t0\t")));
+        assert!(text
+            .lines()
+            .any(|line| line.starts_with("1\t11\t1\t1\t") && line.ends_with("\t9:1")));
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        metrics::reset_syscall_metrics();
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_debug_dump() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        assert_eq!(cage.mkdir_syscall("/dumpdir", S_IRWXA), 0);
+        assert_eq!(cage.chdir_syscall("/dumpdir"), 0);
+        let filefd = cage.open_syscall("dumpfile", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.write_syscall(filefd, str2cbuf("hello"), 5), 5);
+        let sockfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        let mut pipefds = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+
+        assert_eq!(cage.fork_syscall(2), 0);
+        let child = interface::cagetable_getref(2);
+
+        let dump = cage.debug_dump();
+        assert_eq!(dump.cageid, 1);
+        assert_eq!(dump.cwd, Some(interface::RustPathBuf::from("/dumpdir")));
+        assert_eq!(dump.children, vec![2]);
+        assert_eq!(dump.zombies, Some(vec![]));
+        assert_eq!(dump.sharedpending, 0);
+
+        let fdkind = |fd: i32| {
+            dump.fds
+                .iter()
+                .find(|entry| entry.fd == fd)
+                .and_then(|entry| entry.desc.clone())
+                .map(|desc| desc.kind)
+        };
+        match fdkind(filefd) {
+            Some(dump::FdKindDump::File { position, .. }) => assert_eq!(position, 5),
+            other => panic!("unexpected dump of a file: {:?}", other),
+        }
+        match fdkind(sockfd) {
+            Some(dump::FdKindDump::Socket {
+                domain,
+                handle: Some(handle),
+                ..
+            }) => {
+                assert_eq!(domain, AF_INET);
+                assert_eq!(handle.socktype, SOCK_STREAM);
+                assert_eq!(handle.state, ConnState::NOTCONNECTED);
+                assert!(handle.localaddr.is_none());
+            }
+            other => panic!("unexpected dump of a socket: {:?}", other),
+        }
+        assert!(matches!(
+            fdkind(pipefds.readfd),
+            Some(dump::FdKindDump::Pipe { .. })
+        ));
+        assert!(matches!(
+            fdkind(0),
+            Some(dump::FdKindDump::Stream { stream: 0, .. })
+        ));
+
+        //a descriptor held by a syscall in progress is reported without waiting for it
+        {
+            let _held = cage.filedescriptortable[filefd as usize].write();
+            let dump = cage.debug_dump();
+            let entry = dump.fds.iter().find(|entry| entry.fd == filefd).unwrap();
+            assert!(entry.desc.is_none());
+        }
+
+        //the child shares the open files, and once it exits it is a zombie of its parent
+        assert_eq!(child.debug_dump().parent, 1);
+        assert_eq!(child.debug_dump().fds.len(), dump.fds.len());
+        assert_eq!(child.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        let dump = cage.debug_dump();
+        assert!(dump.children.is_empty());
+        assert_eq!(dump.zombies, Some(vec![2]));
+
+        let all = dump::dump_all_cages();
+        assert!(all.iter().any(|c| c.cageid == 1));
+        assert!(!all.iter().any(|c| c.cageid == 2));
+
+        for fd in [filefd, sockfd, pipefds.readfd, pipefds.writefd].iter() {
+            assert_eq!(cage.close_syscall(*fd), 0);
+        }
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_corrupted_inode() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        interface::clear_corruption_reports();
+
+        let fd = cage.open_syscall("/corruptfile", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("hello"), 5), 5);
+
+        //lose the inode behind the open file, as a bug elsewhere might
+        let mut statdata = StatData::default();
+        assert_eq!(cage.fstat_syscall(fd, &mut statdata), 0);
+        let inodenum = statdata.st_ino as usize;
+        let (_, inodeobj) = filesystem::FS_METADATA
+            .inodetable
+            .remove(&inodenum)
+            .unwrap();
+
+        //the calls fail rather than taking every cage down, and each one is reported
+        let mut readbuf = sizecbuf(5);
+        assert_eq!(
+            cage.read_syscall(fd, readbuf.as_mut_ptr(), 5),
+            -(Errno::EIO as i32)
+        );
+        assert_eq!(cage.fstat_syscall(fd, &mut statdata), -(Errno::EIO as i32));
+        assert_eq!(
+            cage.stat_syscall("/corruptfile", &mut statdata),
+            -(Errno::EIO as i32)
+        );
+        let reports = interface::corruption_reports();
+        assert_eq!(reports.len(), 3);
+        assert!(reports[0].starts_with("read:"));
+
+        //a corrupted descriptor can still be closed
+        assert_eq!(cage.close_syscall(fd), -(Errno::EIO as i32));
+        assert_eq!(cage.close_syscall(fd), -(Errno::EBADF as i32));
+
+        filesystem::FS_METADATA
+            .inodetable
+            .insert(inodenum, inodeobj);
+        if let filesystem::Inode::File(ref mut f) = *filesystem::FS_METADATA
+            .inodetable
+            .get_mut(&inodenum)
+            .unwrap()
+        {
+            f.refcount = 0;
+        }
+        assert_eq!(cage.unlink_syscall("/corruptfile"), 0);
+        interface::clear_corruption_reports();
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_affinity() {
+        lindrustinit(0);
+        let cage1 = interface::cagetable_getref(1);
+        let size = interface::CPU_MASK_BYTES;
+        let processmask = interface::lind_process_cpumask();
+        let mut mask = vec![0u8; size];
+
+        //a cage starts out on every cpu the process has
+        assert_eq!(cage1.sched_getaffinity_syscall(0, &mut mask), size as i32);
+        assert_eq!(mask, processmask);
+        assert_eq!(
+            cage1.sched_getaffinity_syscall(0, &mut mask[..3]),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage1.sched_getaffinity_syscall(-1, &mut mask),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage1.sched_getaffinity_syscall(99, &mut mask),
+            -(Errno::ESRCH as i32)
+        );
+
+        //pin it to the first of them
+        let first = processmask.iter().position(|byte| *byte != 0).unwrap();
+        let mut pinned = vec![0u8; size];
+        pinned[first] = processmask[first] & processmask[first].wrapping_neg();
+        assert_eq!(cage1.sched_setaffinity_syscall(0, &pinned), 0);
+        assert_eq!(cage1.sched_getaffinity_syscall(0, &mut mask), size as i32);
+        assert_eq!(mask, pinned);
+        let none = vec![0u8; size];
+        assert_eq!(
+            cage1.sched_setaffinity_syscall(0, &none),
+            -(Errno::EINVAL as i32)
+        );
+
+        //a child's main thread starts on the cpus of the thread that forked it
+        assert_eq!(cage1.fork_syscall(2), 0);
+        let cage2 = interface::cagetable_getref(2);
+        assert_eq!(cage1.sched_getaffinity_syscall(2, &mut mask), size as i32);
+        assert_eq!(mask, pinned);
+
+        assert_eq!(cage1.sched_setaffinity_syscall(0, &processmask), 0);
+        assert_eq!(cage2.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(cage1.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    static LIFECYCLE_EVENTS: std::sync::Mutex<Vec<(i32, u64, u64, i32)>> =
+        std::sync::Mutex::new(vec![]);
+
+    extern "C" fn record_lifecycle_event(event: i32, cageid: u64, relatedid: u64, status: i32) {
+        LIFECYCLE_EVENTS
+            .lock()
+            .unwrap()
+            .push((event, cageid, relatedid, status));
+    }
+
+    pub fn ut_lind_fs_lifecycle_hooks() {
+        LIFECYCLE_EVENTS.lock().unwrap().clear();
+        let handle = lindregisterlifecyclehook(record_lifecycle_event);
+        lindrustinit(0);
+        let cage1 = interface::cagetable_getref(1);
+
+        assert_eq!(cage1.fork_syscall(2), 0);
+        let cage2 = interface::cagetable_getref(2);
+        assert_eq!(cage2.exec_syscall(3, None), 0);
+        let cage3 = interface::cagetable_getref(3);
+        assert_eq!(cage3.exit_syscall(5), 5);
+
+        assert_eq!(
+            *LIFECYCLE_EVENTS.lock().unwrap(),
+            vec![
+                (LIFECYCLE_CREATE, 0, 0, 0),
+                (LIFECYCLE_CREATE, 1, 1, 0),
+                (LIFECYCLE_FORK, 2, 1, 0),
+                (LIFECYCLE_EXEC, 3, 2, 0),
+                (LIFECYCLE_EXIT, 3, 1, 5 << 8),
+            ]
+        );
+
+        //nothing is heard once the hook is gone
+        assert_eq!(lindunregisterlifecyclehook(handle), 0);
+        assert_eq!(lindunregisterlifecyclehook(handle), -(Errno::EINVAL as i32));
+        assert_eq!(cage1.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(LIFECYCLE_EVENTS.lock().unwrap().len(), 5);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_signal_delivery() {
+        lindrustinit(0);
+        rustposix_thread_init(1, 0);
+        let cage = interface::cagetable_getref(1);
+        let pthreadid = interface::get_pthreadid();
+        assert_eq!(cage.fork_syscall(2), 0);
+
+        let mut act = interface::SigactionStruct {
+            sa_handler: 0x1000,
+            sa_mask: interface::lind_sigaddset(0, SIGUSR2),
+            sa_flags: 0,
+        };
+        assert_eq!(cage.sigaction_syscall(SIGUSR1, Some(&act), None), 0);
+        act.sa_flags = SA_RESTART;
+        assert_eq!(cage.sigaction_syscall(SIGUSR2, Some(&act), None), 0);
+
+        //a blocked wait gives up on a pending signal, and is only restarted under SA_RESTART
+        assert_eq!(cage.kill_syscall(1, SIGUSR1), 0);
+        assert_eq!(cage.waitpid_syscall(2, None, 0), -(Errno::EINTR as i32));
+        assert_eq!(cage.poll_syscall(&mut [], None), -(Errno::EINTR as i32));
+
+        let mut action = interface::SigactionStruct::default();
+        let mut info = interface::SiginfoStruct::default();
+        let mut oldmask: interface::SigsetType = 0;
+        assert_eq!(
+            unsafe { lindnextsignal(1, &mut action, &mut info, &mut oldmask) },
+            SIGUSR1
+        );
+        assert_eq!(action.sa_handler, 0x1000);
+        assert_eq!(oldmask, 0);
+        //the handler runs with its own signal and its sa_mask blocked
+        let handlermask = interface::lind_sigaddset(interface::lind_sigaddset(0, SIGUSR1), SIGUSR2);
+        assert_eq!(cage.blocked_signals(pthreadid), handlermask);
+        assert_eq!(
+            unsafe { lindnextsignal(1, &mut action, &mut info, &mut oldmask) },
+            0
+        );
+
+        //a signal sent while the handler runs waits for it to return
+        assert_eq!(cage.kill_syscall(1, SIGUSR2), 0);
+        assert_eq!(cage.deliverable_signals(pthreadid), 0);
+        assert_eq!(lindsigreturn(1, oldmask), 0);
+        assert_eq!(cage.blocked_signals(pthreadid), 0);
+        assert_eq!(cage.waitpid_syscall(2, None, 0), -(Errno::ERESTART as i32));
+        assert_eq!(cage.poll_syscall(&mut [], None), -(Errno::EINTR as i32));
+        assert_eq!(
+            unsafe { lindnextsignal(1, &mut action, &mut info, &mut oldmask) },
+            SIGUSR2
+        );
+        assert_eq!(lindsigreturn(1, oldmask), 0);
+
+        //blocked signals stay pending until unblocked
+        let set = interface::lind_sigaddset(0, SIGUSR1);
+        assert_eq!(cage.sigprocmask_syscall(SIG_BLOCK, Some(&set), None), 0);
+        assert_eq!(cage.kill_syscall(1, SIGUSR1), 0);
+        assert_eq!(
+            unsafe { lindnextsignal(1, &mut action, &mut info, &mut oldmask) },
+            0
+        );
+        assert_eq!(cage.sigprocmask_syscall(SIG_UNBLOCK, Some(&set), None), 0);
+        assert_eq!(
+            unsafe { lindnextsignal(1, &mut action, &mut info, &mut oldmask) },
+            SIGUSR1
+        );
+        assert_eq!(lindsigreturn(1, oldmask), 0);
+
+        //ignoring a signal throws away what was pending, and what is sent later
+        assert_eq!(cage.sigprocmask_syscall(SIG_BLOCK, Some(&set), None), 0);
+        assert_eq!(cage.kill_syscall(1, SIGUSR1), 0);
+        act.sa_handler = SIG_IGN;
+        assert_eq!(cage.sigaction_syscall(SIGUSR1, Some(&act), None), 0);
+        assert_eq!(cage.pending_signals(pthreadid), 0);
+        assert_eq!(cage.kill_syscall(1, SIGUSR1), 0);
+        assert_eq!(cage.pending_signals(pthreadid), 0);
+        assert_eq!(cage.kill_syscall(1, 0), 0);
+        assert_eq!(
+            cage.kill_syscall(1, SIGNAL_MAX + 1),
+            -(Errno::EINVAL as i32)
+        );
+
+        //with no handler a terminating signal ends the cage when it is delivered
+        assert_eq!(cage.kill_syscall(2, SIGTERM), 0);
+        assert_eq!(
+            unsafe { lindnextsignal(2, &mut action, &mut info, &mut oldmask) },
+            SIGTERM
+        );
+        assert_eq!(action.sa_handler, SIG_DFL);
+        let mut status = 0;
+        assert_eq!(cage.waitpid_syscall(2, Some(&mut status), 0), 2);
+        assert_eq!(status & 0x7f, SIGTERM);
+
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_rt_signals() {
+        lindrustinit(0);
+        rustposix_thread_init(1, 0);
+        let cage = interface::cagetable_getref(1);
+        let act = interface::SigactionStruct {
+            sa_handler: 0x1000,
+            sa_mask: 0,
+            sa_flags: SA_SIGINFO,
+        };
+        for sig in [SIGUSR1, SIGRTMIN, SIGRTMIN + 1] {
+            assert_eq!(cage.sigaction_syscall(sig, Some(&act), None), 0);
+        }
+
+        //stands in for the runtime, as if each handler ran and returned at once
+        let deliver = |cageid: u64| {
+            let mut action = interface::SigactionStruct::default();
+            let mut info = interface::SiginfoStruct::default();
+            let mut oldmask: interface::SigsetType = 0;
+            let sig = unsafe { lindnextsignal(cageid, &mut action, &mut info, &mut oldmask) };
+            assert_eq!(lindsigreturn(cageid, oldmask), 0);
+            (sig, info.si_code, info.si_pid, info.si_value)
+        };
+
+        //every real-time signal is queued with its value, while a standard one collapses, and
+        //the lowest numbered signal goes first
+        assert_eq!(cage.sigqueue_syscall(1, SIGRTMIN + 1, 7), 0);
+        assert_eq!(cage.sigqueue_syscall(1, SIGRTMIN, 5), 0);
+        assert_eq!(cage.sigqueue_syscall(1, SIGRTMIN, 6), 0);
+        assert_eq!(cage.kill_syscall(1, SIGRTMIN), 0);
+        assert_eq!(cage.sigqueue_syscall(1, SIGUSR1, 1), 0);
+        assert_eq!(cage.sigqueue_syscall(1, SIGUSR1, 2), 0);
+        assert_eq!(deliver(1), (SIGUSR1, SI_QUEUE, 1, 1));
+        assert_eq!(deliver(1), (SIGRTMIN, SI_QUEUE, 1, 5));
+        assert_eq!(deliver(1), (SIGRTMIN, SI_QUEUE, 1, 6));
+        assert_eq!(deliver(1), (SIGRTMIN, SI_USER, 1, 0));
+        assert_eq!(deliver(1), (SIGRTMIN + 1, SI_QUEUE, 1, 7));
+        assert_eq!(deliver(1).0, 0);
+
+        assert_eq!(cage.sigqueue_syscall(1, 0, 0), 0);
+        assert_eq!(
+            cage.sigqueue_syscall(1, SIGRTMAX + 1, 0),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.sigqueue_syscall(9, SIGRTMIN, 0),
+            -(Errno::ESRCH as i32)
+        );
+
+        //only so many real-time signals may wait at once
+        let set = interface::lind_sigaddset(0, SIGRTMIN);
+        assert_eq!(cage.sigprocmask_syscall(SIG_BLOCK, Some(&set), None), 0);
+        for value in 0..SIGQUEUE_MAX {
+            assert_eq!(cage.sigqueue_syscall(1, SIGRTMIN, value as u64), 0);
+        }
+        assert_eq!(
+            cage.sigqueue_syscall(1, SIGRTMIN, 0),
+            -(Errno::EAGAIN as i32)
+        );
+        let ignore = interface::SigactionStruct {
+            sa_handler: SIG_IGN,
+            ..act
+        };
+        assert_eq!(cage.sigaction_syscall(SIGRTMIN, Some(&ignore), None), 0);
+        assert_eq!(cage.pending_signals(interface::get_pthreadid()), 0);
+        assert_eq!(cage.sigaction_syscall(SIGRTMIN, Some(&act), None), 0);
+        assert_eq!(cage.sigprocmask_syscall(SIG_UNBLOCK, Some(&set), None), 0);
+
+        //a message queue notification carries its sigev_value
+        let mqd = cage.mq_open_syscall("/rtmq", O_RDWR | O_CREAT, 0o600, None);
+        assert!(mqd >= 0);
+        let sev = interface::SigeventStruct {
+            sigev_notify: SIGEV_SIGNAL,
+            sigev_signo: SIGRTMIN + 1,
+            sigev_value: 42,
+            ..Default::default()
+        };
+        assert_eq!(cage.mq_notify_syscall(mqd, Some(&sev)), 0);
+        assert_eq!(cage.mq_send_syscall(mqd, b"ping", 0), 0);
+        assert_eq!(deliver(1), (SIGRTMIN + 1, SI_MESGQ, 1, 42));
+        assert_eq!(cage.close_syscall(mqd), 0);
+        assert_eq!(cage.mq_unlink_syscall("/rtmq"), 0);
+
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_sigchld() {
+        lindrustinit(0);
+        rustposix_thread_init(1, 0);
+        let cage = interface::cagetable_getref(1);
+        let mut act = interface::SigactionStruct {
+            sa_handler: 0x1000,
+            sa_mask: 0,
+            sa_flags: SA_SIGINFO,
+        };
+        assert_eq!(cage.sigaction_syscall(SIGCHLD, Some(&act), None), 0);
+        let deliver = || {
+            let mut action = interface::SigactionStruct::default();
+            let mut info = interface::SiginfoStruct::default();
+            let mut oldmask: interface::SigsetType = 0;
+            let sig = unsafe { lindnextsignal(1, &mut action, &mut info, &mut oldmask) };
+            assert_eq!(lindsigreturn(1, oldmask), 0);
+            (sig, info.si_code, info.si_pid, info.si_status)
+        };
+
+        //the parent hears how its child ended, then reaps it
+        assert_eq!(cage.fork_syscall(2), 0);
+        interface::cagetable_getref(2).exit_syscall(3);
+        assert_eq!(deliver(), (SIGCHLD, CLD_EXITED, 2, 3));
+        let mut status = 0;
+        assert_eq!(cage.waitpid_syscall(2, Some(&mut status), 0), 2);
+        assert_eq!(status, 3 << 8);
+
+        assert_eq!(cage.fork_syscall(3), 0);
+        interface::cagetable_getref(3).exit_by_signal(SIGKILL);
+        assert_eq!(deliver(), (SIGCHLD, CLD_KILLED, 3, SIGKILL));
+        assert_eq!(cage.waitpid_syscall(3, Some(&mut status), 0), 3);
+        assert_eq!(deliver().0, 0);
+
+        //under SA_NOCLDWAIT children are reaped for the parent, which still gets SIGCHLD
+        act.sa_flags = SA_NOCLDWAIT;
+        assert_eq!(cage.sigaction_syscall(SIGCHLD, Some(&act), None), 0);
+        assert_eq!(cage.fork_syscall(4), 0);
+        interface::cagetable_getref(4).exit_syscall(EXIT_SUCCESS);
+        assert_eq!(deliver(), (SIGCHLD, CLD_EXITED, 4, 0));
+        assert_eq!(cage.waitpid_syscall(-1, None, 0), -(Errno::ECHILD as i32));
+
+        //ignoring SIGCHLD reaps them too, and a blocked wait gives up once the last one is gone
+        act.sa_handler = SIG_IGN;
+        act.sa_flags = 0;
+        assert_eq!(cage.sigaction_syscall(SIGCHLD, Some(&act), None), 0);
+        assert_eq!(cage.fork_syscall(5), 0);
+        let child = interface::helper_thread(move || {
+            interface::sleep(interface::RustDuration::from_millis(50));
+            interface::cagetable_getref(5).exit_syscall(EXIT_SUCCESS);
+        });
+        assert_eq!(cage.waitpid_syscall(-1, None, 0), -(Errno::ECHILD as i32));
+        child.join().unwrap();
+        assert_eq!(deliver().0, 0);
+
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_cpu_timers() {
+        lindrustinit(0);
+        rustposix_thread_init(1, 0);
+        let cage = interface::cagetable_getref(1);
+        let pthreadid = interface::get_pthreadid();
+        let noarg = Arg { dispatch_long: 0 };
+        let getpid = || dispatcher(1, 31, noarg, noarg, noarg, noarg, noarg, noarg);
+        let burn = |millis: u64| {
+            let start = interface::thread_cputime();
+            while interface::thread_cputime() - start < interface::RustDuration::from_millis(millis)
+            {
+            }
+        };
+        let millisecond = interface::ITimerVal {
+            it_interval: interface::TimeVal {
+                tv_sec: 0,
+                tv_usec: 1000,
+            },
+            it_value: interface::TimeVal {
+                tv_sec: 0,
+                tv_usec: 1000,
+            },
+        };
+
+        assert_eq!(
+            cage.setitimer_syscall(3, Some(&millisecond), None),
+            -(Errno::EINVAL as i32)
+        );
+        let mut badvalue = millisecond;
+        badvalue.it_value.tv_usec = 1000000;
+        assert_eq!(
+            cage.setitimer_syscall(ITIMER_VIRTUAL, Some(&badvalue), None),
+            -(Errno::EINVAL as i32)
+        );
+
+        //time spent running counts against both timers once the cage makes a syscall
+        let mut old = interface::ITimerVal::default();
+        assert_eq!(
+            cage.setitimer_syscall(ITIMER_VIRTUAL, Some(&millisecond), Some(&mut old)),
+            0
+        );
+        assert_eq!(old.it_value.tv_usec, 0);
+        assert_eq!(
+            cage.setitimer_syscall(ITIMER_PROF, Some(&millisecond), None),
+            0
+        );
+        getpid();
+        burn(5);
+        getpid();
+        let vtalrm = interface::lind_sigaddset(0, SIGVTALRM);
+        let prof = interface::lind_sigaddset(0, SIGPROF);
+        assert_eq!(cage.pending_signals(pthreadid), vtalrm | prof);
+        assert!(cage.cpu_usage.user_time() >= interface::RustDuration::from_millis(5));
+
+        //a timer re-arms with its interval, and stops for good once set to zero
+        assert_eq!(
+            cage.setitimer_syscall(ITIMER_VIRTUAL, None, Some(&mut old)),
+            0
+        );
+        assert_eq!(old.it_interval.tv_usec, 1000);
+        assert!(old.it_value.tv_usec <= 1000);
+        let zero = interface::ITimerVal::default();
+        assert_eq!(cage.setitimer_syscall(ITIMER_VIRTUAL, Some(&zero), None), 0);
+        assert_eq!(cage.setitimer_syscall(ITIMER_PROF, Some(&zero), None), 0);
+        assert_eq!(cage.setitimer_syscall(ITIMER_PROF, None, Some(&mut old)), 0);
+        assert_eq!(old.it_interval.tv_usec, 0);
+
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_posix_timers() {
+        lindrustinit(0);
+        rustposix_thread_init(1, 0);
+        let cage = interface::cagetable_getref(1);
+        let act = interface::SigactionStruct {
+            sa_handler: 0x1000,
+            sa_mask: 0,
+            sa_flags: SA_SIGINFO,
+        };
+        assert_eq!(cage.sigaction_syscall(SIGRTMIN, Some(&act), None), 0);
+        assert_eq!(cage.sigaction_syscall(SIGALRM, Some(&act), None), 0);
+        let deliver = |cageid: u64| {
+            let mut action = interface::SigactionStruct::default();
+            let mut info = interface::SiginfoStruct::default();
+            let mut oldmask: interface::SigsetType = 0;
+            let sig = unsafe { lindnextsignal(cageid, &mut action, &mut info, &mut oldmask) };
+            assert_eq!(lindsigreturn(cageid, oldmask), 0);
+            (sig, info.si_code, info.si_timerid, info.si_value)
+        };
+        let timespec = |nanos: u64| {
+            let duration = interface::RustDuration::from_nanos(nanos);
+            interface::TimeSpec {
+                tv_sec: duration.as_secs() as i64,
+                tv_nsec: duration.subsec_nanos() as i64,
+            }
+        };
+        let millis = 1000000;
+
+        let sev = interface::SigeventStruct {
+            sigev_notify: SIGEV_SIGNAL,
+            sigev_signo: SIGRTMIN,
+            sigev_value: 42,
+            ..Default::default()
+        };
+        let mut timerid = -1;
+        assert_eq!(
+            cage.timer_create_syscall(3, Some(&sev), Some(&mut timerid)),
+            -(Errno::EINVAL as i32)
+        );
+        let threadsev = interface::SigeventStruct {
+            sigev_notify: SIGEV_THREAD,
+            ..sev
+        };
+        assert_eq!(
+            cage.timer_create_syscall(CLOCK_MONOTONIC, Some(&threadsev), Some(&mut timerid)),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.timer_create_syscall(CLOCK_MONOTONIC, Some(&sev), Some(&mut timerid)),
+            0
+        );
+        assert_eq!(timerid, 0);
+
+        //while the timer's signal is blocked, each further expiry is counted as an overrun
+        let set = interface::lind_sigaddset(0, SIGRTMIN);
+        assert_eq!(cage.sigprocmask_syscall(SIG_BLOCK, Some(&set), None), 0);
+        let periodic = interface::ITimerSpec {
+            it_interval: timespec(5 * millis),
+            it_value: timespec(5 * millis),
+        };
+        assert_eq!(cage.timer_settime_syscall(0, 0, Some(&periodic), None), 0);
+        interface::sleep(interface::RustDuration::from_millis(60));
+        let mut curr = interface::ITimerSpec::default();
+        assert_eq!(cage.timer_gettime_syscall(0, Some(&mut curr)), 0);
+        assert_eq!(curr.it_interval.tv_nsec, 5 * millis as i64);
+        assert!(curr.it_value.tv_nsec <= 5 * millis as i64);
+        let disarm = interface::ITimerSpec::default();
+        let mut old = interface::ITimerSpec::default();
+        assert_eq!(
+            cage.timer_settime_syscall(0, 0, Some(&disarm), Some(&mut old)),
+            0
+        );
+        assert_eq!(old.it_interval.tv_nsec, 5 * millis as i64);
+        assert_eq!(cage.sigprocmask_syscall(SIG_UNBLOCK, Some(&set), None), 0);
+        assert_eq!(deliver(1), (SIGRTMIN, SI_TIMER, 0, 42));
+        assert!(cage.timer_getoverrun_syscall(0) >= 5);
+        assert_eq!(deliver(1).0, 0);
+
+        //without a sigevent a timer sends SIGALRM carrying its id, here at a time on its clock
+        assert_eq!(
+            cage.timer_create_syscall(CLOCK_REALTIME, None, Some(&mut timerid)),
+            0
+        );
+        assert_eq!(timerid, 1);
+        let now = interface::clock_time(libc::CLOCK_REALTIME);
+        let oneshot = interface::ITimerSpec {
+            it_interval: timespec(0),
+            it_value: timespec((now.as_nanos() + 10 * millis as u128) as u64),
+        };
+        assert_eq!(
+            cage.timer_settime_syscall(1, TIMER_ABSTIME, Some(&oneshot), None),
+            0
+        );
+        interface::sleep(interface::RustDuration::from_millis(50));
+        assert_eq!(deliver(1), (SIGALRM, SI_TIMER, 1, 1));
+        assert_eq!(cage.timer_getoverrun_syscall(1), 0);
+        assert_eq!(cage.timer_gettime_syscall(1, Some(&mut curr)), 0);
+        assert_eq!((curr.it_value.tv_sec, curr.it_value.tv_nsec), (0, 0));
+
+        let mut badvalue = periodic;
+        badvalue.it_value.tv_nsec = 1000000000;
+        assert_eq!(
+            cage.timer_settime_syscall(1, 0, Some(&badvalue), None),
+            -(Errno::EINVAL as i32)
+        );
+
+        //a deleted timer's id is handed out again
+        assert_eq!(cage.timer_delete_syscall(0), 0);
+        assert_eq!(cage.timer_delete_syscall(0), -(Errno::EINVAL as i32));
+        assert_eq!(
+            cage.timer_gettime_syscall(0, Some(&mut curr)),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.timer_getoverrun_syscall(7), -(Errno::EINVAL as i32));
+        assert_eq!(
+            cage.timer_create_syscall(CLOCK_MONOTONIC, Some(&sev), Some(&mut timerid)),
+            0
+        );
+        assert_eq!(timerid, 0);
+
+        //a child's timers are neither inherited nor left behind when it exits
+        assert_eq!(cage.fork_syscall(2), 0);
+        let child = interface::cagetable_getref(2);
+        assert_eq!(
+            child.timer_gettime_syscall(0, Some(&mut curr)),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            child.timer_create_syscall(CLOCK_MONOTONIC, None, Some(&mut timerid)),
+            0
+        );
+        assert_eq!(child.timer_settime_syscall(0, 0, Some(&periodic), None), 0);
+        child.exit_syscall(EXIT_SUCCESS);
+        assert!(child.posix_timers.read().is_empty());
+        let mut status = 0;
+        assert_eq!(cage.waitpid_syscall(2, Some(&mut status), 0), 2);
+
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_clocks() {
+        lindrustinit(0);
+        rustposix_thread_init(1, 0);
+        let cage = interface::cagetable_getref(1);
+        let read = |clockid: i32| {
+            let mut tp = interface::TimeSpec::default();
+            assert_eq!(cage.clock_gettime_syscall(clockid, Some(&mut tp)), 0);
+            interface::RustDuration::new(tp.tv_sec as u64, tp.tv_nsec as u32)
+        };
+
+        assert!(read(CLOCK_REALTIME).as_secs() >= interface::timestamp() - 1);
+        let before = read(CLOCK_MONOTONIC);
+        assert!(read(CLOCK_MONOTONIC) >= before);
+        assert!(read(CLOCK_MONOTONIC_RAW) > interface::RustDuration::ZERO);
+        assert!(read(CLOCK_THREAD_CPUTIME_ID) > interface::RustDuration::ZERO);
+        read(CLOCK_PROCESS_CPUTIME_ID);
+        let mut tp = interface::TimeSpec::default();
+        assert_eq!(
+            cage.clock_gettime_syscall(9, Some(&mut tp)),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.clock_gettime_syscall(CLOCK_REALTIME, None),
+            -(Errno::EFAULT as i32)
+        );
+        assert_eq!(cage.clock_getres_syscall(CLOCK_MONOTONIC, Some(&mut tp)), 0);
+        assert_eq!((tp.tv_sec, tp.tv_nsec), (0, 1));
+
+        //relative and absolute sleeps last until their time is up, and one already up returns
+        let twenty = interface::TimeSpec {
+            tv_sec: 0,
+            tv_nsec: 20000000,
+        };
+        let start = interface::starttimer();
+        assert_eq!(cage.nanosleep_syscall(Some(&twenty), None), 0);
+        assert!(interface::readtimer(start) >= interface::RustDuration::from_millis(20));
+        let deadline = read(CLOCK_MONOTONIC) + interface::RustDuration::from_millis(20);
+        let abstime = interface::TimeSpec {
+            tv_sec: deadline.as_secs() as i64,
+            tv_nsec: deadline.subsec_nanos() as i64,
+        };
+        assert_eq!(
+            cage.clock_nanosleep_syscall(CLOCK_MONOTONIC, TIMER_ABSTIME, Some(&abstime), None),
+            0
+        );
+        assert!(read(CLOCK_MONOTONIC) >= deadline);
+        assert_eq!(
+            cage.clock_nanosleep_syscall(CLOCK_REALTIME, TIMER_ABSTIME, Some(&twenty), None),
+            0
+        );
+
+        assert_eq!(
+            cage.clock_nanosleep_syscall(CLOCK_THREAD_CPUTIME_ID, 0, Some(&twenty), None),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.clock_nanosleep_syscall(CLOCK_PROCESS_CPUTIME_ID, 0, Some(&twenty), None),
+            -(Errno::EOPNOTSUPP as i32)
+        );
+        let badtime = interface::TimeSpec {
+            tv_sec: 0,
+            tv_nsec: 1000000000,
+        };
+        assert_eq!(
+            cage.nanosleep_syscall(Some(&badtime), None),
+            -(Errno::EINVAL as i32)
+        );
+
+        //a signal cuts a sleep short, and a relative one gives back the time it had left
+        let act = interface::SigactionStruct {
+            sa_handler: 0x1000,
+            sa_mask: 0,
+            sa_flags: SA_RESTART,
+        };
+        assert_eq!(cage.sigaction_syscall(SIGUSR1, Some(&act), None), 0);
+        assert_eq!(cage.kill_syscall(1, SIGUSR1), 0);
+        let second = interface::TimeSpec {
+            tv_sec: 1,
+            tv_nsec: 0,
+        };
+        let mut remain = interface::TimeSpec::default();
+        assert_eq!(
+            cage.nanosleep_syscall(Some(&second), Some(&mut remain)),
+            -(Errno::EINTR as i32)
+        );
+        assert!(remain.tv_sec == 1 || remain.tv_nsec > 900000000);
+        let later = read(CLOCK_MONOTONIC) + interface::RustDuration::from_secs(1);
+        let abstime = interface::TimeSpec {
+            tv_sec: later.as_secs() as i64,
+            tv_nsec: later.subsec_nanos() as i64,
+        };
+        assert_eq!(
+            cage.clock_nanosleep_syscall(CLOCK_MONOTONIC, TIMER_ABSTIME, Some(&abstime), None),
+            -(Errno::EINTR as i32)
+        );
+        let mut action = interface::SigactionStruct::default();
+        let mut info = interface::SiginfoStruct::default();
+        let mut oldmask: interface::SigsetType = 0;
+        assert_eq!(
+            unsafe { lindnextsignal(1, &mut action, &mut info, &mut oldmask) },
+            SIGUSR1
+        );
+        assert_eq!(lindsigreturn(1, oldmask), 0);
+
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_user_buffers() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let fd = cage.open_syscall("/userbuffers", O_CREAT | O_TRUNC | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        let noarg = Arg { dispatch_long: 0 };
+        let rw = |callnum: i32, buf: *mut u8, len: usize| {
+            dispatcher(
+                1,
+                callnum,
+                Arg {
+                    dispatch_long: fd as i64,
+                },
+                Arg {
+                    dispatch_mutcbuf: buf,
+                },
+                Arg {
+                    dispatch_usize: len,
+                },
+                noarg,
+                noarg,
+                noarg,
+            )
+        };
+        let efault = -(Errno::EFAULT as i32);
+
+        //once the cage's memory is known, buffers have to lie wholly inside it
+        let mut memory = [0u8; 64];
+        let base = memory.as_mut_ptr();
+        let mut outside = [0u8; 8];
+        assert_eq!(lindsetmemoryregion(1, base as usize, memory.len()), 0);
+        memory[..5].copy_from_slice(b"hello");
+        assert_eq!(rw(13, base, 5), 5);
+        assert_eq!(rw(13, base.wrapping_add(60), 5), efault);
+        assert_eq!(rw(13, outside.as_mut_ptr(), 8), efault);
+        assert_eq!(rw(13, base, usize::MAX), efault);
+        assert_eq!(cage.lseek_syscall(fd, 0, SEEK_SET), 0);
+        assert_eq!(rw(12, base.wrapping_add(32), 5), 5);
+        assert_eq!(&memory[32..37], b"hello");
+        assert_eq!(quick_read(fd, outside.as_mut_ptr(), 8, 1), efault);
+
+        //arrays are checked for their full size, and so is each buffer an iovec points to
+        let groups = dispatcher(
+            1,
+            224,
+            Arg { dispatch_long: 100 },
+            Arg {
+                dispatch_uintptr: base as *mut u32,
+            },
+            noarg,
+            noarg,
+            noarg,
+            noarg,
+        );
+        assert_eq!(groups, efault);
+        let iovec = interface::IovecStruct {
+            iov_base: outside.as_mut_ptr() as *mut libc::c_void,
+            iov_len: outside.len(),
+        };
+        let iovecptr = base.wrapping_add(40) as *mut interface::IovecStruct;
+        unsafe { std::ptr::write_unaligned(iovecptr, iovec) };
+        let writev = dispatcher(
+            1,
+            170,
+            Arg {
+                dispatch_long: fd as i64,
+            },
+            Arg {
+                dispatch_constiovecstruct: iovecptr,
+            },
+            Arg { dispatch_long: 1 },
+            noarg,
+            noarg,
+            noarg,
+        );
+        assert_eq!(writev, efault);
+
+        //and so are the paths, structs and addresses syscalls take
+        let call = |callnum: i32, arg1: Arg, arg2: Arg| {
+            dispatcher(1, callnum, arg1, arg2, noarg, noarg, noarg, noarg)
+        };
+        let fdarg = Arg {
+            dispatch_long: fd as i64,
+        };
+        let cstr = |ptr: *mut u8| Arg {
+            dispatch_cstr: ptr as *const i8,
+        };
+        memory[..13].copy_from_slice(b"/userbuffers\0");
+        memory[52..].copy_from_slice(b"/userbuffers");
+        assert_eq!(call(2, cstr(base), noarg), 0);
+        assert_eq!(call(2, cstr(base.wrapping_add(52)), noarg), efault);
+        assert_eq!(call(2, cstr(str2cbuf("/userbuffers\0")), noarg), efault);
+        let stat = |ptr: *mut u8| Arg {
+            dispatch_statdatastruct: ptr as *mut StatData,
+        };
+        assert_eq!(call(17, fdarg, stat(base.wrapping_add(60))), efault);
+        assert_eq!(call(17, fdarg, stat(outside.as_mut_ptr())), efault);
+        let pipearray = Arg {
+            dispatch_pipearray: outside.as_mut_ptr().wrapping_add(4) as *mut PipeArray,
+        };
+        assert_eq!(call(66, pipearray, noarg), efault);
+        let sockaddr = Arg {
+            dispatch_constsockaddrstruct: outside.as_ptr() as *const interface::SockaddrDummy,
+        };
+        let bind = dispatcher(
+            1,
+            33,
+            fdarg,
+            sockaddr,
+            Arg { dispatch_long: 16 },
+            noarg,
+            noarg,
+            noarg,
+        );
+        assert_eq!(bind, efault);
+
+        //without a region any buffer that is there is taken
+        assert_eq!(lindsetmemoryregion(1, 0, 0), 0);
+        assert_eq!(rw(13, outside.as_mut_ptr(), 8), 8);
+        assert_eq!(
+            lindsetmemoryregion(9, base as usize, memory.len()),
+            -(Errno::ESRCH as i32)
+        );
+
+        assert_eq!(cage.close_syscall(fd), 0);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_statx() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let fd = cage.open_syscall("/statxfile", O_CREAT | O_TRUNC | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("hello"), 5), 5);
+
+        let mut statxbuf = StatxStruct::default();
+        assert_eq!(
+            cage.statx_syscall(AT_FDCWD, "/statxfile", 0, STATX_BASIC_STATS, &mut statxbuf),
+            0
+        );
+        assert_eq!(statxbuf.stx_mask & STATX_BASIC_STATS, STATX_BASIC_STATS);
+        assert_ne!(statxbuf.stx_mask & STATX_BTIME, 0);
+        assert_ne!(statxbuf.stx_mask & STATX_MNT_ID, 0);
+        assert_eq!(statxbuf.stx_size, 5);
+        assert_eq!(statxbuf.stx_blksize, STATX_BLOCK_SIZE);
+        assert_eq!(statxbuf.stx_blocks, 1);
+        assert_eq!(statxbuf.stx_mode as u32, S_IFREG as u32 | S_IRWXA);
+        assert_eq!(statxbuf.stx_mnt_id, filesystem::ROOTMOUNTID as u64);
+        assert_eq!(statxbuf.stx_attributes & STATX_ATTR_MOUNT_ROOT, 0);
+        assert!(statxbuf.stx_btime.tv_sec > 0);
+
+        //statx and stat agree on what they share
+        let mut statdata = StatData::default();
+        assert_eq!(cage.stat_syscall("/statxfile", &mut statdata), 0);
+        assert_eq!(statxbuf.stx_ino, statdata.st_ino as u64);
+        assert_eq!(statxbuf.stx_nlink, statdata.st_nlink);
+        assert_eq!(statxbuf.stx_uid, statdata.st_uid);
+
+        //the birth time stays put when the file changes
+        let btime = statxbuf.stx_btime;
+        assert_eq!(cage.chmod_syscall("/statxfile", S_IRUSR | S_IWUSR), 0);
+        let mut fdstatxbuf = StatxStruct::default();
+        assert_eq!(
+            cage.statx_syscall(fd, "", AT_EMPTY_PATH, STATX_ALL, &mut fdstatxbuf),
+            0
+        );
+        assert_eq!(fdstatxbuf.stx_btime, btime);
+        assert_eq!(
+            fdstatxbuf.stx_mode as u32,
+            S_IFREG as u32 | S_IRUSR | S_IWUSR
+        );
+
+        //the root directory is a mount root
+        assert_eq!(
+            cage.statx_syscall(AT_FDCWD, "/", 0, STATX_BASIC_STATS, &mut statxbuf),
+            0
+        );
+        assert_ne!(statxbuf.stx_attributes & STATX_ATTR_MOUNT_ROOT, 0);
+
+        //pipes have no inode, so get no times
+        let mut pipefds = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        assert_eq!(
+            cage.statx_syscall(pipefds.readfd, "", AT_EMPTY_PATH, STATX_ALL, &mut statxbuf),
+            0
+        );
+        assert_eq!(statxbuf.stx_mask & STATX_MTIME, 0);
+        assert_ne!(statxbuf.stx_mask & STATX_TYPE, 0);
+
+        assert_eq!(
+            cage.statx_syscall(AT_FDCWD, "/statxfile", 0, STATX__RESERVED, &mut statxbuf),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.statx_syscall(AT_FDCWD, "/statxfile", AT_STATX_SYNC_TYPE, 0, &mut statxbuf),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.statx_syscall(AT_FDCWD, "/nostatxfile", 0, 0, &mut statxbuf),
+            -(Errno::ENOENT as i32)
+        );
+
+        //32-bit guests get the same information laid out as struct stat64
+        assert_eq!(std::mem::size_of::<Stat64Struct>(), 96);
+        let mut stat64buf = Stat64Struct::default();
+        assert_eq!(cage.fstat64_syscall(fd, &mut stat64buf), 0);
+        let (size, ino) = (stat64buf.st_size, stat64buf.st_ino);
+        assert_eq!(size, 5);
+        assert_eq!(ino, statdata.st_ino as u64);
+        assert_eq!(cage.stat64_syscall("/statxfile", &mut stat64buf), 0);
+        let blocks = stat64buf.st_blocks;
+        assert_eq!(blocks, 1);
+
+        assert_eq!(cage.close_syscall(pipefds.readfd), 0);
+        assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall("/statxfile"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_pty() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        //ptys can't be opened until a devpts is mounted to hold their slaves
+        assert_eq!(
+            cage.posix_openpt_syscall(O_RDWR | O_NOCTTY),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(cage.mkdir_syscall("/ptstest", S_IRWXA), 0);
+        assert_eq!(
+            cage.mount_syscall("devpts", "/ptstest", "devpts", 0, None),
+            0
+        );
+
+        let master = cage.posix_openpt_syscall(O_RDWR | O_NOCTTY);
+        assert!(master >= 0);
+        assert_eq!(cage.isatty_syscall(master), 1);
+        assert_eq!(cage.grantpt_syscall(master), 0);
+
+        let mut ptn: i32 = -1;
+        assert_eq!(
+            cage.ioctl_syscall(master, TIOCGPTN, IoctlPtrUnion { int_ptr: &mut ptn }),
+            0
+        );
+        let slavepath = format!("/ptstest/{}", ptn);
+        let mut namebuf = [0u8; 32];
+        assert_eq!(
+            cage.ptsname_syscall(master, namebuf.as_mut_ptr(), 2),
+            -(Errno::ERANGE as i32)
+        );
+        assert_eq!(
+            cage.ptsname_syscall(master, namebuf.as_mut_ptr(), namebuf.len()),
+            0
+        );
+        assert_eq!(
+            &namebuf[..slavepath.len() + 1],
+            format!("{}\0", slavepath).as_bytes()
+        );
+
+        //the slave can't be opened until it is unlocked
+        assert_eq!(
+            cage.open_syscall(&slavepath, O_RDWR, 0),
+            -(Errno::EIO as i32)
+        );
+        assert_eq!(cage.unlockpt_syscall(master), 0);
+        let slave = cage.open_syscall(&slavepath, O_RDWR, 0);
+        assert!(slave >= 0);
+        assert_eq!(cage.isatty_syscall(slave), 1);
+
+        //what is typed at the master is edited into lines for the slave and echoed back
+        let mut buf = [0u8; 64];
+        assert_eq!(cage.write_syscall(master, str2cbuf("lx\x7fs\r"), 5), 5);
+        assert_eq!(cage.read_syscall(slave, buf.as_mut_ptr(), 64), 3);
+        assert_eq!(&buf[..3], b"ls\n");
+        assert_eq!(cage.read_syscall(master, buf.as_mut_ptr(), 64), 8);
+        assert_eq!(&buf[..8], b"lx\x08 \x08s\r\n");
+
+        //what the slave writes has its newlines turned into carriage return newlines
+        assert_eq!(cage.write_syscall(slave, str2cbuf("out\n"), 4), 4);
+        assert_eq!(cage.read_syscall(master, buf.as_mut_ptr(), 64), 5);
+        assert_eq!(&buf[..5], b"out\r\n");
+
+        //without ICANON and ECHO input is passed along as it arrives and not echoed
+        let mut termios = interface::Termios::default();
+        assert_eq!(
+            cage.ioctl_syscall(
+                slave,
+                TCGETS,
+                IoctlPtrUnion {
+                    termios_ptr: &mut termios
+                }
+            ),
+            0
+        );
+        assert_ne!(termios.c_lflag & ICANON, 0);
+        termios.c_lflag &= !(ICANON | ECHO);
+        assert_eq!(
+            cage.ioctl_syscall(
+                slave,
+                TCSETS,
+                IoctlPtrUnion {
+                    termios_ptr: &mut termios
+                }
+            ),
+            0
+        );
+        assert_eq!(cage.write_syscall(master, str2cbuf("ab"), 2), 2);
+        assert_eq!(cage.read_syscall(slave, buf.as_mut_ptr(), 64), 2);
+        assert_eq!(&buf[..2], b"ab");
+        assert_eq!(cage.fcntl_syscall(master, F_SETFL, O_NONBLOCK), 0);
+        assert_eq!(
+            cage.read_syscall(master, buf.as_mut_ptr(), 64),
+            -(Errno::EAGAIN as i32)
+        );
+
+        //both ends share one window size
+        let mut winsize = interface::WinSize::default();
+        assert_eq!(
+            cage.ioctl_syscall(
+                slave,
+                TIOCGWINSZ,
+                IoctlPtrUnion {
+                    winsize_ptr: &mut winsize
+                }
+            ),
+            0
+        );
+        assert_eq!((winsize.ws_row, winsize.ws_col), (24, 80));
+        winsize.ws_row = 50;
+        assert_eq!(
+            cage.ioctl_syscall(
+                master,
+                TIOCSWINSZ,
+                IoctlPtrUnion {
+                    winsize_ptr: &mut winsize
+                }
+            ),
+            0
+        );
+        let mut newsize = interface::WinSize::default();
+        assert_eq!(
+            cage.ioctl_syscall(
+                slave,
+                TIOCGWINSZ,
+                IoctlPtrUnion {
+                    winsize_ptr: &mut newsize
+                }
+            ),
+            0
+        );
+        assert_eq!(newsize.ws_row, 50);
+
+        //the stream descriptors are the console, while regular files are no terminals at all
+        assert_eq!(cage.isatty_syscall(0), 1);
+        let fd = cage.open_syscall("/ptsfile", O_CREAT | O_TRUNC | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(cage.isatty_syscall(fd), -(Errno::ENOTTY as i32));
+        assert_eq!(
+            cage.ioctl_syscall(
+                fd,
+                TCGETS,
+                IoctlPtrUnion {
+                    termios_ptr: &mut termios
+                }
+            ),
+            -(Errno::ENOTTY as i32)
+        );
+        assert_eq!(cage.grantpt_syscall(fd), -(Errno::EINVAL as i32));
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall("/ptsfile"), 0);
+
+        //closing the master hangs the slave up and removes its name
+        assert_eq!(cage.umount_syscall("/ptstest"), -(Errno::EBUSY as i32));
+        assert_eq!(cage.close_syscall(master), 0);
+        assert_eq!(cage.read_syscall(slave, buf.as_mut_ptr(), 64), 0);
+        assert_eq!(
+            cage.write_syscall(slave, str2cbuf("x"), 1),
+            -(Errno::EIO as i32)
+        );
+        assert_eq!(
+            cage.access_syscall(&slavepath, F_OK),
+            -(Errno::ENOENT as i32)
+        );
+        assert!(tty::PTY_TABLE.contains_key(&(ptn as u32)));
+        assert_eq!(cage.close_syscall(slave), 0);
+        //and the pty is only freed once the slave is closed too
+        assert!(!tty::PTY_TABLE.contains_key(&(ptn as u32)));
+        assert_eq!(cage.umount_syscall("/ptstest"), 0);
+        assert_eq!(cage.rmdir_syscall("/ptstest"), 0);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_uname() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let field = |value: &[u8]| {
+            let len = value.iter().position(|&b| b == 0).unwrap();
+            std::str::from_utf8(&value[..len]).unwrap().to_string()
+        };
+        let mut utsname: interface::UtsName = unsafe { std::mem::zeroed() };
+        assert_eq!(cage.uname_syscall(&mut utsname), 0);
+        assert_eq!(field(&utsname.sysname), DEFAULT_SYSNAME);
+        assert_eq!(field(&utsname.nodename), "Lind");
+        assert_eq!(field(&utsname.release), DEFAULT_RELEASE);
+        assert_eq!(field(&utsname.machine), std::env::consts::ARCH);
+
+        //the embedder can change the fields it is given, and overlong values are cut to fit
+        let release = std::ffi::CString::new("5.10.0-custom").unwrap();
+        assert_eq!(lindsetutsname(UTS_RELEASE, release.as_ptr()), 0);
+        let machine = std::ffi::CString::new("m".repeat(100)).unwrap();
+        assert_eq!(lindsetutsname(UTS_MACHINE, machine.as_ptr()), 0);
+        assert_eq!(
+            lindsetutsname(42, release.as_ptr()),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.uname_syscall(&mut utsname), 0);
+        assert_eq!(field(&utsname.release), "5.10.0-custom");
+        assert_eq!(field(&utsname.machine), "m".repeat(64));
+
+        let release = std::ffi::CString::new(DEFAULT_RELEASE).unwrap();
+        assert_eq!(lindsetutsname(UTS_RELEASE, release.as_ptr()), 0);
+        let machine = std::ffi::CString::new(DEFAULT_MACHINE).unwrap();
+        assert_eq!(lindsetutsname(UTS_MACHINE, machine.as_ptr()), 0);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_sysinfo() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let mut info = interface::SysInfo::default();
+        assert_eq!(cage.sysinfo_syscall(&mut info), 0);
+        assert!(info.uptime > 0);
+        assert_eq!(info.mem_unit, 1);
+        assert!(info.totalram >= info.freeram && info.freeram > 0);
+        let procs = info.procs;
+
+        //a forked cage is one more process
+        assert_eq!(cage.fork_syscall(2), 0);
+        assert_eq!(cage.sysinfo_syscall(&mut info), 0);
+        assert_eq!(info.procs, procs + 1);
+        let cage2 = interface::cagetable_getref(2);
+        assert_eq!(cage2.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_sem_fork() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let key = 31337;
+        // Create a shared memory region
+        let shmid = cage.shmget_syscall(key, 1024, 0666 | IPC_CREAT);
+        // Attach the shared memory region
+        let shmatret = cage.shmat_syscall(shmid, 0xfffff000 as *mut u8, 0);
+        assert_ne!(shmatret, -1);
+        // Initialize the semaphore with shared between process
+        let ret_init = cage.sem_init_syscall(shmatret as u32, 1, 1);
+        assert_eq!(ret_init, 0);
+        assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), 1);
+        // Fork child process
+        assert_eq!(cage.fork_syscall(2), 0);
+        // Child process
+        let thread_child = interface::helper_thread(move || {
+            let cage1 = interface::cagetable_getref(2);
+            // Child waits for the semaphore
+            assert_eq!(cage1.sem_wait_syscall(shmatret as u32), 0);
+            interface::sleep(interface::RustDuration::from_millis(40));
+            // Release the semaphore
+            assert_eq!(cage1.sem_post_syscall(shmatret as u32), 0);
+            cage1.exit_syscall(EXIT_SUCCESS);
+        });
+        //Parent processes
+        let thread_parent = interface::helper_thread(move || {
+            // Parents waits for the semaphore
+            assert_eq!(cage.sem_wait_syscall(shmatret as u32), 0);
+            assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), 0);
+            interface::sleep(interface::RustDuration::from_millis(100));
+            // Parents release the semaphore
+            assert_eq!(cage.sem_post_syscall(shmatret as u32), 0);
+            assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), 1);
+            // Destroy the semaphore
+            assert_eq!(cage.sem_destroy_syscall(shmatret as u32), 0);
+            // mark the shared memory to be rmoved
+            let shmctlret2 = cage.shmctl_syscall(shmid, IPC_RMID, None);
+            assert_eq!(shmctlret2, 0);
+            //detach from shared memory
+            let shmdtret = cage.shmdt_syscall(0xfffff000 as *mut u8);
+            assert_eq!(shmdtret, shmid);
+            cage.exit_syscall(EXIT_SUCCESS);
+        });
+        thread_child.join().unwrap();
+        thread_parent.join().unwrap();
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_sem_trytimed() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let key = 31337;
+        // Create a shared memory region
+        let shmid = cage.shmget_syscall(key, 1024, 0666 | IPC_CREAT);
+        // Attach the shared memory region
+        let shmatret = cage.shmat_syscall(shmid, 0xfffff000 as *mut u8, 0);
+        assert_ne!(shmatret, -1);
+        // Initialize the semaphore with shared between process
+        let ret_init = cage.sem_init_syscall(shmatret as u32, 1, 1);
+        // assert_eq!(shmatret as u32, 0);
+        assert_eq!(ret_init, 0);
+        assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), 1);
+        // Fork child process
+        assert_eq!(cage.fork_syscall(2), 0);
+        // Child process
+        let thread_child = interface::helper_thread(move || {
+            let cage1 = interface::cagetable_getref(2);
+            // Child waits for the semaphore
+            assert_eq!(cage1.sem_trywait_syscall(shmatret as u32), 0);
+            // Wait
+            interface::sleep(interface::RustDuration::from_millis(20));
+            // Release the semaphore
+            assert_eq!(cage1.sem_post_syscall(shmatret as u32), 0);
+            cage1.exit_syscall(EXIT_SUCCESS);
+        });
+        //Parent processes
+        let thread_parent = interface::helper_thread(move || {
+            // Parents waits for the semaphore
+            assert_eq!(
+                cage.sem_timedwait_syscall(
+                    shmatret as u32,
+                    interface::RustDuration::from_millis(100)
+                ),
+                0
+            );
+            assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), 0);
+            interface::sleep(interface::RustDuration::from_millis(10));
+            // Parents release the semaphore
+            assert_eq!(cage.sem_post_syscall(shmatret as u32), 0);
+            assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), 1);
+            // Destroy the semaphore
+            assert_eq!(cage.sem_destroy_syscall(shmatret as u32), 0);
+            // mark the shared memory to be rmoved
+            let shmctlret2 = cage.shmctl_syscall(shmid, IPC_RMID, None);
+            assert_eq!(shmctlret2, 0);
+            //detach from shared memory
+            let shmdtret = cage.shmdt_syscall(0xfffff000 as *mut u8);
+            assert_eq!(shmdtret, shmid);
+            cage.exit_syscall(EXIT_SUCCESS);
+        });
+        thread_child.join().unwrap();
+        thread_parent.join().unwrap();
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_sem_test() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let key = 31337;
+        // Create a shared memory region
+        let shmid = cage.shmget_syscall(key, 1024, 0666 | IPC_CREAT);
+        // Attach the shared memory region
+        let shmatret = cage.shmat_syscall(shmid, 0xfffff000 as *mut u8, 0);
+        assert_ne!(shmatret, -1);
+        assert_eq!(cage.sem_destroy_syscall(shmatret as u32), -22);
+        assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), -22);
+        assert_eq!(cage.sem_post_syscall(shmatret as u32), -22);
+        // Initialize the semaphore with shared between process
+        let ret_init = cage.sem_init_syscall(shmatret as u32, 1, 0);
+        assert_eq!(ret_init, 0);
+        // Should return errno
+        assert_eq!(
+            cage.sem_timedwait_syscall(shmatret as u32, interface::RustDuration::from_millis(100)),
+            -110
+        );
+        assert_eq!(cage.sem_trywait_syscall(shmatret as u32), -11);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_tmp_file_test() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        // Check if /tmp is there
+        assert_eq!(cage.access_syscall("/tmp", F_OK), 0);
+
+        // Open  file in /tmp
+        let file_path = "/tmp/testfile";
+        let fd = cage.open_syscall(file_path, O_CREAT | O_TRUNC | O_RDWR, S_IRWXA);
+
+        assert_eq!(cage.write_syscall(fd, str2cbuf("Hello world"), 6), 6);
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        lindrustfinalize();
+
+        // Init again
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        // Check if /tmp is there
+        assert_eq!(cage.access_syscall("/tmp", F_OK), 0);
+        // Check if file is still there (it shouldn't be, assert no)
+        assert_eq!(cage.access_syscall(file_path, F_OK), -2);
+
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_inotify() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        assert_eq!(cage.mkdir_syscall("/inotifydir", S_IRWXA), 0);
+        let ifd = cage.inotify_init1_syscall(IN_NONBLOCK);
+        assert!(ifd >= 0);
+
+        let wd = cage.inotify_add_watch_syscall(ifd, "/inotifydir", IN_CREATE | IN_DELETE);
+        assert!(wd > 0);
+
+        //nothing has happened yet
+        let mut readbuf = sizecbuf(256);
+        assert_eq!(
+            cage.read_syscall(ifd, readbuf.as_mut_ptr(), 256),
+            -(Errno::EAGAIN as i32)
+        );
+
+        let fd = cage.open_syscall("/inotifydir/file", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        let filewd = cage.inotify_add_watch_syscall(ifd, "/inotifydir/file", IN_MODIFY);
+        assert!(filewd > 0 && filewd != wd);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("hello"), 5), 5);
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //the create event carries the name, padded to 4 bytes with a null terminator
+        let mut pollfds = vec![PollStruct {
+            fd: ifd,
+            events: POLLIN,
+            revents: 0,
+        }];
+        assert_eq!(
+            cage.poll_syscall(&mut pollfds, Some(interface::RustDuration::ZERO)),
+            1
+        );
+        assert_eq!(pollfds[0].revents & POLLIN, POLLIN);
+
+        let ret = cage.read_syscall(ifd, readbuf.as_mut_ptr(), 256);
+        assert_eq!(ret, 24 + 16);
+        let field = |buf: &[u8], off: usize| {
+            u32::from_ne_bytes([buf[off], buf[off + 1], buf[off + 2], buf[off + 3]])
+        };
+        assert_eq!(field(&readbuf, 0) as i32, wd);
+        assert_eq!(field(&readbuf, 4), IN_CREATE);
+        assert_eq!(field(&readbuf, 12), 8);
+        assert_eq!(&readbuf[16..20], "file".as_bytes());
+        assert_eq!(field(&readbuf, 24) as i32, filewd);
+        assert_eq!(field(&readbuf, 28), IN_MODIFY);
+        assert_eq!(field(&readbuf, 36), 0);
+
+        assert_eq!(cage.unlink_syscall("/inotifydir/file"), 0);
+        let ret = cage.read_syscall(ifd, readbuf.as_mut_ptr(), 256);
+        assert!(ret >= 24);
+        assert_eq!(field(&readbuf, 0) as i32, wd);
+        assert_eq!(field(&readbuf, 4), IN_DELETE);
+
+        //a oneshot watch goes away once it fires, and says so with IN_IGNORED
+        let fd = cage.open_syscall("/inotifydir/once", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert!(cage.read_syscall(ifd, readbuf.as_mut_ptr(), 256) > 0);
+        let oncewd =
+            cage.inotify_add_watch_syscall(ifd, "/inotifydir/once", IN_MODIFY | IN_ONESHOT);
+        assert!(oncewd > 0);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("a"), 1), 1);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("b"), 1), 1);
+        assert_eq!(cage.read_syscall(ifd, readbuf.as_mut_ptr(), 256), 32);
+        assert_eq!(field(&readbuf, 0) as i32, oncewd);
+        assert_eq!(field(&readbuf, 4), IN_MODIFY);
+        assert_eq!(field(&readbuf, 16) as i32, oncewd);
+        assert_eq!(field(&readbuf, 20), IN_IGNORED);
+        assert_eq!(
+            cage.inotify_rm_watch_syscall(ifd, oncewd),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall("/inotifydir/once"), 0);
+
+        assert_eq!(cage.inotify_rm_watch_syscall(ifd, wd), 0);
+        assert_eq!(
+            cage.inotify_rm_watch_syscall(ifd, wd),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.close_syscall(ifd), 0);
+        assert_eq!(cage.rmdir_syscall("/inotifydir"), 0);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_mmap_shared() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let fd = cage.open_syscall("/mmapfile", O_CREAT | O_TRUNC | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("hello world"), 11), 11);
+        assert_eq!(cage.ftruncate_syscall(fd, 4096), 0);
+
+        //a read only descriptor can't back a writable shared mapping
+        let rdfd = cage.open_syscall("/mmapfile", O_RDONLY, S_IRWXA);
+        assert!(rdfd >= 0);
+        //reserve a page first so MAP_FIXED can't land on a mapping the test process already has
+        let mapaddr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                4096,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(mapaddr, libc::MAP_FAILED);
+        let mapaddr = mapaddr as *mut u8;
+        assert_eq!(
+            cage.mmap_syscall(
+                mapaddr,
+                4096,
+                PROT_READ | PROT_WRITE,
+                MAP_SHARED | MAP_FIXED,
+                rdfd,
+                0
+            ),
+            -(Errno::EACCES as i32)
+        );
+
+        let mapret = cage.mmap_syscall(
+            mapaddr,
+            4096,
+            PROT_READ | PROT_WRITE,
+            MAP_SHARED | MAP_FIXED,
+            fd,
+            0,
+        );
+        assert_eq!(mapret as u32, mapaddr as usize as u32);
+
+        //stores through the mapping are visible to read() on another descriptor
+        let mapped = unsafe { std::slice::from_raw_parts_mut(mapaddr, 4096) };
+        assert_eq!(&mapped[0..5], "hello".as_bytes());
+        mapped[0] = b'j';
+        let mut read_buf = sizecbuf(5);
+        assert_eq!(cage.pread_syscall(rdfd, read_buf.as_mut_ptr(), 5, 0), 5);
+        assert_eq!(cbuf2str(&read_buf), "jello");
+
+        //and writes to the file are visible through the mapping
+        assert_eq!(cage.pwrite_syscall(fd, str2cbuf("W"), 1, 6), 1);
+        assert_eq!(mapped[6], b'W');
+
+        assert_eq!(
+            cage.msync_syscall(mapaddr, 4096, MS_SYNC | MS_ASYNC),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.msync_syscall(mapaddr, 4096, MS_SYNC), 0);
+        assert_eq!(cage.file_mappings.lock().len(), 1);
+        //the mapping is tracked by its full host address, not the 32 bits mmap hands back
+        assert_eq!(cage.file_mappings.lock()[0].addr, mapaddr as usize);
+        cage.munmap_syscall(mapaddr, 4096);
+        assert_eq!(cage.file_mappings.lock().len(), 0);
+
+        assert_eq!(cage.close_syscall(rdfd), 0);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_mount() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        assert_eq!(cage.mkdir_syscall("/mnttmp", S_IRWXA), 0);
+        assert_eq!(
+            cage.mount_syscall("none", "/mnttmp", "ext4", 0, None),
+            -(Errno::ENODEV as i32)
+        );
+        assert_eq!(cage.mount_syscall("none", "/mnttmp", "tmpfs", 0, None), 0);
+        assert_eq!(
+            cage.mount_syscall("none", "/mnttmp", "tmpfs", 0, None),
+            -(Errno::EBUSY as i32)
+        );
+
+        //files created under a tmpfs are kept out of the metadata log
+        let fd = cage.open_syscall("/mnttmp/scratch", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("data"), 4), 4);
+        assert_eq!(cage.close_syscall(fd), 0);
+        let inodenum = filesystem::metawalk(interface::RustPath::new("/mnttmp/scratch")).unwrap();
+        assert!(!filesystem::inode_is_persistent(inodenum));
+        assert_eq!(
+            cage.link_syscall("/mnttmp/scratch", "/scratchlink"),
+            -(Errno::EXDEV as i32)
+        );
+        assert_eq!(cage.rmdir_syscall("/mnttmp"), -(Errno::EBUSY as i32));
+
+        //a mount can't be detached while it is a working directory
+        assert_eq!(cage.chdir_syscall("/mnttmp"), 0);
+        assert_eq!(cage.umount_syscall("/mnttmp"), -(Errno::EBUSY as i32));
+        assert_eq!(cage.chdir_syscall("/"), 0);
+        assert_eq!(cage.umount_syscall("/mnttmp"), 0);
+        assert_eq!(cage.umount_syscall("/mnttmp"), -(Errno::EINVAL as i32));
+        assert_eq!(
+            cage.access_syscall("/mnttmp/scratch", F_OK),
+            -(Errno::ENOENT as i32)
+        );
+        assert!(filesystem::FS_METADATA.inodetable.get(&inodenum).is_none());
+
+        //a bind mount exposes an existing tree, and can be remounted read only
+        assert_eq!(cage.mkdir_syscall("/bindsrc", S_IRWXA), 0);
+        let fd = cage.open_syscall("/bindsrc/file", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(
+            cage.mount_syscall("/bindsrc", "/mnttmp", "", MS_BIND, None),
+            0
+        );
+        assert_eq!(cage.access_syscall("/mnttmp/file", F_OK), 0);
+        assert_eq!(
+            cage.mount_syscall("", "/mnttmp", "", MS_REMOUNT | MS_RDONLY, None),
+            0
+        );
+        assert_eq!(
+            cage.open_syscall("/mnttmp/file", O_WRONLY, S_IRWXA),
+            -(Errno::EROFS as i32)
+        );
+        assert_eq!(
+            cage.access_syscall("/mnttmp/file", W_OK),
+            -(Errno::EROFS as i32)
+        );
+        let fd = cage.open_syscall("/mnttmp/file", O_RDONLY, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.umount_syscall("/mnttmp"), 0);
+        assert_eq!(cage.access_syscall("/bindsrc/file", F_OK), 0);
+
+        assert_eq!(cage.unlink_syscall("/bindsrc/file"), 0);
+        assert_eq!(cage.rmdir_syscall("/bindsrc"), 0);
+        assert_eq!(cage.rmdir_syscall("/mnttmp"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_tmpfs() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        //files in /tmp live in memory and never get a backing data file
+        let mut fsdata = FSData::default();
+        assert_eq!(cage.statfs_syscall("/tmp", &mut fsdata), 0);
+        assert_eq!(fsdata.f_type, TMPFS_MAGIC);
+        let fd = cage.open_syscall("/tmp/memfile", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("hello"), 5), 5);
+        assert_eq!(cage.close_syscall(fd), 0);
+        let inodenum = filesystem::metawalk(interface::RustPath::new("/tmp/memfile")).unwrap();
+        assert!(!interface::pathexists(format!(
+            "{}{}",
+            FILEDATAPREFIX, inodenum
+        )));
+
+        //the data survives closing the last descriptor
+        let fd = cage.open_syscall("/tmp/memfile", O_RDONLY, S_IRWXA);
+        let mut read_buf = sizecbuf(5);
+        assert_eq!(cage.read_syscall(fd, read_buf.as_mut_ptr(), 5), 5);
+        assert_eq!(cbuf2str(&read_buf), "hello");
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall("/tmp/memfile"), 0);
+
+        //a size limited tmpfs refuses to grow past its limit
+        assert_eq!(cage.mkdir_syscall("/smalltmp", S_IRWXA), 0);
+        assert_eq!(
+            cage.mount_syscall("tmpfs", "/smalltmp", "tmpfs", 0, Some("size=bogus")),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.mount_syscall("tmpfs", "/smalltmp", "tmpfs", 0, Some("size=8k")),
+            0
+        );
+        let fd = cage.open_syscall("/smalltmp/big", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        let block = vec![b'x'; 4096];
+        assert_eq!(cage.write_syscall(fd, block.as_ptr(), 4096), 4096);
+        assert_eq!(cage.write_syscall(fd, block.as_ptr(), 4096), 4096);
+        assert_eq!(
+            cage.write_syscall(fd, block.as_ptr(), 1),
+            -(Errno::ENOSPC as i32)
+        );
+        assert_eq!(cage.fstatfs_syscall(fd, &mut fsdata), 0);
+        assert_eq!(fsdata.f_blocks, 2);
+        assert_eq!(fsdata.f_bfree, 0);
+        assert_eq!(
+            cage.mount_syscall("", "/smalltmp", "", MS_REMOUNT, Some("size=4k")),
+            -(Errno::EINVAL as i32)
+        );
+
+        //shrinking the file gives the space back
+        assert_eq!(cage.ftruncate_syscall(fd, 4096), 0);
+        assert_eq!(cage.statfs_syscall("/smalltmp", &mut fsdata), 0);
+        assert_eq!(fsdata.f_bfree, 1);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall("/smalltmp/big"), 0);
+        assert_eq!(cage.statfs_syscall("/smalltmp", &mut fsdata), 0);
+        assert_eq!(fsdata.f_bfree, 2);
+
+        assert_eq!(cage.umount_syscall("/smalltmp"), 0);
+        assert_eq!(cage.rmdir_syscall("/smalltmp"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_procfs() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let fd = cage.open_syscall("/proc/meminfo", O_RDONLY, 0);
+        assert!(fd >= 0);
+        let mut read_buf = sizecbuf(9);
+        assert_eq!(cage.read_syscall(fd, read_buf.as_mut_ptr(), 9), 9);
+        assert_eq!(cbuf2str(&read_buf), "MemTotal:");
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.access_syscall("/proc/cpuinfo", R_OK), 0);
+
+        //self resolves to the calling cage
+        let fd = cage.open_syscall("/proc/self/status", O_RDONLY, 0);
+        assert!(fd >= 0);
+        let mut statusbuf = sizecbuf(256);
+        let statuslen = cage.read_syscall(fd, statusbuf.as_mut_ptr(), 256);
+        assert!(statuslen > 0);
+        let status = std::str::from_utf8(&statusbuf[..statuslen as usize]).unwrap();
+        assert!(status.contains("\nPid:\t1\n"));
+        assert!(status.contains("\nPPid:\t1\n"));
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //cage directories come and go with the cages
+        assert_eq!(
+            cage.access_syscall("/proc/2", F_OK),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(cage.fork_syscall(2), 0);
+        assert_eq!(cage.access_syscall("/proc/2/status", F_OK), 0);
+        let child = interface::cagetable_getref(2);
+        assert_eq!(child.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(
+            cage.access_syscall("/proc/2", F_OK),
+            -(Errno::ENOENT as i32)
+        );
+
+        //and the fd listing follows the descriptor table
+        let fd = cage.open_syscall("/procfdfile", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        let fdpath = format!("/proc/self/fd/{}", fd);
+        assert_eq!(cage.access_syscall(&fdpath, F_OK), 0);
+        let mut statdata = StatData::default();
+        assert_eq!(cage.stat_syscall(&fdpath, &mut statdata), 0);
+        assert_eq!(statdata.st_mode as i32 & S_FILETYPEFLAGS, S_IFLNK);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.access_syscall(&fdpath, F_OK), -(Errno::ENOENT as i32));
+        assert_eq!(cage.unlink_syscall("/procfdfile"), 0);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_procfs_fds() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let readlink = |path: &str| {
+            let mut linkbuf = sizecbuf(64);
+            let len = cage.readlink_syscall(path, linkbuf.as_mut_ptr(), 64);
+            assert!(len > 0, "readlink of {} failed with {}", path, len);
+            String::from_utf8(linkbuf[..len as usize].to_vec()).unwrap()
+        };
+
+        assert_eq!(cage.mkdir_syscall("/procfds", S_IRWXA), 0);
+        let filefd = cage.open_syscall("/procfds/file", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.write_syscall(filefd, str2cbuf("hello"), 5), 5);
+        let dirfd = cage.open_syscall("/procfds", O_RDONLY, 0);
+        let mut pipefds = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        let sockfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+
+        //the fd entries read as links to what the descriptors refer to
+        assert_eq!(readlink(&format!("/proc/1/fd/{}", filefd)), "/procfds/file");
+        assert_eq!(readlink(&format!("/proc/self/fd/{}", dirfd)), "/procfds");
+        assert_eq!(readlink("/proc/1/fd/1"), "/dev/tty");
+        let readend = readlink(&format!("/proc/1/fd/{}", pipefds.readfd));
+        assert!(readend.starts_with("pipe:["));
+        assert_eq!(
+            readlink(&format!("/proc/1/fd/{}", pipefds.writefd)),
+            readend
+        );
+        assert!(readlink(&format!("/proc/1/fd/{}", sockfd)).starts_with("socket:["));
+
+        //a link is cut short to fit the buffer, and only links can be read as one
+        let mut shortbuf = sizecbuf(4);
+        assert_eq!(
+            cage.readlink_syscall(&format!("/proc/1/fd/{}", filefd), shortbuf.as_mut_ptr(), 4),
+            4
+        );
+        assert_eq!(cbuf2str(&shortbuf), "/pro");
+        assert_eq!(
+            cage.readlink_syscall("/procfds/file", shortbuf.as_mut_ptr(), 4),
+            -(Errno::EINVAL as i32)
+        );
+
+        //and fdinfo has the offset and flags
+        let infofd = cage.open_syscall(&format!("/proc/1/fdinfo/{}", filefd), O_RDONLY, 0);
+        assert!(infofd >= 0);
+        let mut infobuf = sizecbuf(128);
+        let infolen = cage.read_syscall(infofd, infobuf.as_mut_ptr(), 128);
+        assert!(infolen > 0);
+        let info = std::str::from_utf8(&infobuf[..infolen as usize]).unwrap();
+        assert!(info.starts_with("pos:\t5\nflags:\t"));
+        let flags = info.lines().nth(1).unwrap()["flags:\t".len()..].to_string();
+        assert_eq!(
+            i32::from_str_radix(&flags, 8).unwrap() & O_RDWRFLAGS,
+            O_RDWR
+        );
+        assert_eq!(cage.close_syscall(infofd), 0);
+
+        //a file that has been unlinked is marked as deleted
+        assert_eq!(cage.unlink_syscall("/procfds/file"), 0);
+        assert!(readlink(&format!("/proc/1/fd/{}", filefd)).ends_with(" (deleted)"));
+
+        for fd in [filefd, dirfd, pipefds.readfd, pipefds.writefd, sockfd].iter() {
+            assert_eq!(cage.close_syscall(*fd), 0);
+        }
+        assert_eq!(
+            cage.access_syscall(&format!("/proc/1/fdinfo/{}", filefd), F_OK),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(cage.rmdir_syscall("/procfds"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_devices() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        //writes to /dev/full always fail while reads return zeros
+        let fd = cage.open_syscall("/dev/full", O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(
+            cage.write_syscall(fd, str2cbuf("data"), 4),
+            -(Errno::ENOSPC as i32)
+        );
+        let mut read_buf = vec![1u8; 4];
+        assert_eq!(cage.read_syscall(fd, read_buf.as_mut_ptr(), 4), 4);
+        assert_eq!(read_buf, vec![0u8; 4]);
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //writes to /dev/tty go to the controlling stream
+        let fd = cage.open_syscall("/dev/tty", O_WRONLY, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("tty\n"), 4), 4);
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //opening /dev/fd/N duplicates descriptor N
+        let fd = cage.open_syscall("/devfdfile", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("hello"), 5), 5);
+        let dupfd = cage.open_syscall(&format!("/dev/fd/{}", fd), O_RDONLY, 0);
+        assert!(dupfd >= 0 && dupfd != fd);
+        //the duplicate shares the file offset
+        assert_eq!(cage.lseek_syscall(dupfd, 0, SEEK_CUR), 5);
+        assert_eq!(cage.close_syscall(dupfd), 0);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(
+            cage.open_syscall(&format!("/dev/fd/{}", fd), O_RDONLY, 0),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(cage.unlink_syscall("/devfdfile"), 0);
+
+        //and /dev/shm is a tmpfs mount
+        let mut fsdata = FSData::default();
+        assert_eq!(cage.statfs_syscall("/dev/shm", &mut fsdata), 0);
+        assert_eq!(fsdata.f_type, TMPFS_MAGIC);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    //a console that hands back whatever was last written to it
+    struct EchoDevice {
+        pending: interface::Mutex<Vec<u8>>,
+    }
+
+    impl crate::safeposix::devices::CharDevice for EchoDevice {
+        fn read(&self, buf: *mut u8, count: usize) -> i32 {
+            let mut pending = self.pending.lock();
+            let len = interface::rust_min(count, pending.len());
+            unsafe { std::ptr::copy_nonoverlapping(pending.as_ptr(), buf, len) };
+            pending.drain(..len);
+            len as i32
+        }
+
+        fn write(&self, buf: *const u8, count: usize) -> i32 {
+            let data = unsafe { std::slice::from_raw_parts(buf, count) };
+            self.pending.lock().extend_from_slice(data);
+            count as i32
+        }
+
+        fn ioctl(&self, _request: u32, ptrunion: IoctlPtrUnion) -> i32 {
+            //report the number of pending bytes for any request
+            unsafe { *ptrunion.int_ptr = self.pending.lock().len() as i32 };
+            0
+        }
+
+        fn poll(&self, events: i16) -> i16 {
+            let readable = if self.pending.lock().is_empty() {
+                0
+            } else {
+                POLLIN
+            };
+            events & (readable | POLLOUT)
+        }
+    }
+
+    pub fn ut_lind_fs_chardev_registry() {
+        use crate::safeposix::devices::*;
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let echodev = DevNo {
+            major: 240,
+            minor: 0,
+        };
+        assert!(register_char_device(
+            echodev,
+            interface::RustRfc::new(EchoDevice {
+                pending: interface::Mutex::new(vec![]),
+            })
+        )
+        .is_none());
+        assert_eq!(
+            cage.mknod_syscall("/echodev", S_IFCHR as u32 | 0o666, makedev(&echodev)),
+            0
+        );
+        let fd = cage.open_syscall("/echodev", O_RDWR, 0);
+        assert!(fd >= 0);
+
+        //nothing to read yet, so only writes are ready
+        let mut pollfds = vec![PollStruct {
+            fd: fd,
+            events: POLLIN | POLLOUT,
+            revents: 0,
+        }];
+        assert_eq!(
+            cage.poll_syscall(&mut pollfds, Some(interface::RustDuration::ZERO)),
+            1
+        );
+        assert_eq!(pollfds[0].revents, POLLOUT);
+
+        assert_eq!(cage.write_syscall(fd, str2cbuf("echo"), 4), 4);
+        let mut pending = 0;
+        assert_eq!(
+            cage.ioctl_syscall(
+                fd,
+                0x541B,
+                IoctlPtrUnion {
+                    int_ptr: &mut pending
+                }
+            ),
+            0
+        );
+        assert_eq!(pending, 4);
+        pollfds[0].revents = 0;
+        assert_eq!(
+            cage.poll_syscall(&mut pollfds, Some(interface::RustDuration::ZERO)),
+            1
+        );
+        assert_eq!(pollfds[0].revents, POLLIN | POLLOUT);
+        let mut read_buf = sizecbuf(4);
+        assert_eq!(cage.read_syscall(fd, read_buf.as_mut_ptr(), 4), 4);
+        assert_eq!(cbuf2str(&read_buf), "echo");
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //once unregistered the device file no longer does anything
+        assert!(unregister_char_device(&echodev).is_some());
+        let fd = cage.open_syscall("/echodev", O_RDWR, 0);
+        assert_eq!(
+            cage.write_syscall(fd, str2cbuf("echo"), 4),
+            -(Errno::EOPNOTSUPP as i32)
+        );
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall("/echodev"), 0);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_chroot() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        assert_eq!(cage.mkdir_syscall("/jail", S_IRWXA), 0);
+        assert_eq!(cage.mkdir_syscall("/jail/sub", S_IRWXA), 0);
+        let fd = cage.open_syscall("/jail/inner", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.close_syscall(fd), 0);
+        let fd = cage.open_syscall("/outer", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        assert_eq!(cage.chroot_syscall("/nonexistent"), -(Errno::ENOENT as i32));
+        assert_eq!(cage.chroot_syscall("/outer"), -(Errno::ENOTDIR as i32));
+
+        assert_eq!(cage.fork_syscall(2), 0);
+        let child = interface::cagetable_getref(2);
+        assert_eq!(child.chroot_syscall("/jail"), 0);
+
+        //paths now resolve inside the jail and .. can't climb out of it
+        assert_eq!(child.access_syscall("/inner", F_OK), 0);
+        assert_eq!(
+            child.access_syscall("/outer", F_OK),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(
+            child.access_syscall("/sub/../../../outer", F_OK),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(child.chdir_syscall("sub"), 0);
+        assert_eq!(child.access_syscall("../../inner", F_OK), 0);
+
+        //the cwd is reported relative to the new root
+        let mut cwdbuf = sizecbuf(16);
+        assert_eq!(child.getcwd_syscall(cwdbuf.as_mut_ptr(), 16), 0);
+        assert_eq!(&cwdbuf[..5], b"/sub\0");
+
+        //the jail is inherited by forked cages
+        assert_eq!(child.fork_syscall(3), 0);
+        let grandchild = interface::cagetable_getref(3);
+        assert_eq!(grandchild.access_syscall("/inner", F_OK), 0);
+        assert_eq!(
+            grandchild.access_syscall("/outer", F_OK),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(grandchild.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(child.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+
+        //the parent was never jailed
+        assert_eq!(cage.access_syscall("/outer", F_OK), 0);
+        assert_eq!(cage.unlink_syscall("/outer"), 0);
+        assert_eq!(cage.unlink_syscall("/jail/inner"), 0);
+        assert_eq!(cage.rmdir_syscall("/jail/sub"), 0);
+        assert_eq!(cage.rmdir_syscall("/jail"), 0);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_quota() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        assert_eq!(cage.fork_syscall(2), 0);
+        let child = interface::cagetable_getref(2);
+        child.set_disk_quota(Some(10), Some(2));
+
+        //bytes written to files the cage created count against it
+        let fd = child.open_syscall("/quotafile", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(child.write_syscall(fd, str2cbuf("12345678"), 8), 8);
+        assert_eq!(
+            child.write_syscall(fd, str2cbuf("9012"), 4),
+            -(Errno::EDQUOT as i32)
+        );
+        assert_eq!(child.ftruncate_syscall(fd, 16), -(Errno::EDQUOT as i32));
+        assert_eq!(child.close_syscall(fd), 0);
+
+        //and so do the inodes it creates
+        assert_eq!(child.mkdir_syscall("/quotadir", S_IRWXA), 0);
+        assert_eq!(
+            child.open_syscall("/quotafile2", O_CREAT | O_RDWR, S_IRWXA),
+            -(Errno::EDQUOT as i32)
+        );
+        let usage = child.disk_quota_usage().unwrap();
+        assert_eq!((usage.bytes, usage.inodes), (8, 2));
+
+        //whoever truncates or removes the files refunds the cage that owns them
+        assert_eq!(cage.truncate_syscall("/quotafile", 2), 0);
+        assert_eq!(child.disk_quota_usage().unwrap().bytes, 2);
+        assert_eq!(cage.unlink_syscall("/quotafile"), 0);
+        assert_eq!(cage.rmdir_syscall("/quotadir"), 0);
+        let usage = child.disk_quota_usage().unwrap();
+        assert_eq!((usage.bytes, usage.inodes), (0, 0));
+
+        //the parent is not limited by its child's quota
+        let fd = cage.open_syscall("/quotafile", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("123456789012"), 12), 12);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall("/quotafile"), 0);
+        assert!(cage.disk_quota_usage().is_none());
+
+        assert_eq!(child.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_dentry_cache() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        assert_eq!(cage.mkdir_syscall("/dc", S_IRWXA), 0);
+        assert_eq!(cage.mkdir_syscall("/dc/a", S_IRWXA), 0);
+        assert_eq!(cage.mkdir_syscall("/dc/a/b", S_IRWXA), 0);
+        let fd = cage.open_syscall("/dc/a/b/file", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //a walk leaves its result behind in the cache
+        let mut statdata = StatData::default();
+        assert_eq!(cage.stat_syscall("/dc/a/b/file", &mut statdata), 0);
+        let inodenum = statdata.st_ino as usize;
+        let cachedpath = interface::RustPath::new("/dc/a/b/file");
+        assert_eq!(
+            filesystem::DENTRY_CACHE.get(cachedpath).unwrap().inodenum,
+            inodenum
+        );
+
+        //unlinking and recreating the file must not resolve to the old inode
+        assert_eq!(cage.unlink_syscall("/dc/a/b/file"), 0);
+        assert!(filesystem::DENTRY_CACHE.get(cachedpath).is_none());
+        assert_eq!(
+            cage.stat_syscall("/dc/a/b/file", &mut statdata),
+            -(Errno::ENOENT as i32)
+        );
+        let fd = cage.open_syscall("/dc/a/b/file", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.stat_syscall("/dc/a/b/file", &mut statdata), 0);
+        assert_ne!(statdata.st_ino as usize, inodenum);
+        let inodenum = statdata.st_ino as usize;
+
+        //renaming a directory moves everything cached beneath it
+        assert_eq!(cage.rename_syscall("/dc/a", "/dc/z"), 0);
+        assert_eq!(
+            cage.stat_syscall("/dc/a/b/file", &mut statdata),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(cage.stat_syscall("/dc/z/b/file", &mut statdata), 0);
+        assert_eq!(statdata.st_ino as usize, inodenum);
+
+        //a file unlinked through a bind mount is gone from the other path as well
+        assert_eq!(cage.mkdir_syscall("/dcbind", S_IRWXA), 0);
+        assert_eq!(
+            cage.mount_syscall("/dc/z/b", "/dcbind", "", MS_BIND, None),
+            0
+        );
+        assert_eq!(cage.stat_syscall("/dc/z/b/file", &mut statdata), 0);
+        assert_eq!(cage.unlink_syscall("/dcbind/file"), 0);
+        assert_eq!(
+            cage.stat_syscall("/dc/z/b/file", &mut statdata),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(cage.umount_syscall("/dcbind"), 0);
+        assert_eq!(cage.rmdir_syscall("/dcbind"), 0);
+
+        assert_eq!(cage.rmdir_syscall("/dc/z/b"), 0);
+        assert_eq!(cage.rmdir_syscall("/dc/z"), 0);
+        assert_eq!(cage.rmdir_syscall("/dc"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_log_compaction() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let threshold = 4096;
+        filesystem::set_log_compact_threshold(threshold);
+
+        //enough metadata changes to cross the threshold several times over
+        assert_eq!(cage.mkdir_syscall("/compact", S_IRWXA), 0);
+        for i in 0..64 {
+            let path = format!("/compact/file{}", i);
+            let fd = cage.open_syscall(&path, O_CREAT | O_RDWR, S_IRWXA);
+            assert_eq!(cage.write_syscall(fd, str2cbuf("hello"), 5), 5);
+            assert_eq!(cage.close_syscall(fd), 0);
+        }
+
+        //the log is folded into the snapshot in the background and starts over
+        let mut waited = 0;
+        while filesystem::log_size().unwrap() > threshold {
+            assert!(waited < 500, "log was never compacted");
+            interface::sleep(interface::RustDuration::from_millis(10));
+            waited += 1;
+        }
+        drop(filesystem::LOG_COMPACT_LOCK.lock());
+        assert!(!interface::pathexists(
+            filesystem::OLDLOGFILENAME.to_string()
+        ));
+
+        //compacting on demand leaves an empty log
+        let memfd = cage.memfd_create_syscall("compactmem", 0);
+        assert!(memfd >= 0);
+        let mut memstat = StatData::default();
+        assert_eq!(cage.fstat_syscall(memfd, &mut memstat), 0);
+        filesystem::compact_log();
+        assert_eq!(filesystem::log_size(), Some(0));
+        let mut statdata = StatData::default();
+        assert_eq!(cage.stat_syscall("/compact/file63", &mut statdata), 0);
+        assert_eq!(statdata.st_size, 5);
+
+        //and a snapshot only has what outlives a restart, which a memfd doesn't
+        let ondisk = filesystem::FilesystemMetadata::init_fs_metadata();
+        assert!(ondisk.inodetable.contains_key(&(statdata.st_ino as usize)));
+        assert!(!ondisk.inodetable.contains_key(&(memstat.st_ino as usize)));
+        assert_eq!(cage.close_syscall(memfd), 0);
+
+        filesystem::set_log_compact_threshold(filesystem::DEFAULT_LOG_COMPACT_THRESHOLD);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+
+        //everything compacted away is still there once the filesystem is loaded again
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let mut statdata = StatData::default();
+        for i in 0..64 {
+            let path = format!("/compact/file{}", i);
+            assert_eq!(cage.stat_syscall(&path, &mut statdata), 0);
+            assert_eq!(cage.unlink_syscall(&path), 0);
+        }
+        assert_eq!(cage.rmdir_syscall("/compact"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_log_batching() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        filesystem::compact_log();
+
+        //creating a file changes it and its directory, which go in as one record
+        let fd = cage.open_syscall("/batched0", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.close_syscall(fd), 0);
+        let logbytes = std::fs::read(filesystem::LOGFILENAME).unwrap();
+        let (entries, intact) = interface::read_log_entries(&logbytes);
+        assert!(intact);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            filesystem::log_size(),
+            Some(
+                interface::LOGENTRYHEADERSIZE + entries.iter().map(|e| 4 + e.len()).sum::<usize>()
+            )
+        );
+
+        //entries logged from several threads at once all make it into the log
+        filesystem::set_log_batch_window(2000);
+        let mut threads = Vec::new();
+        for i in 1..8 {
+            let cage2 = cage.clone();
+            threads.push(interface::helper_thread(move || {
+                let path = format!("/batched{}", i);
+                let fd = cage2.open_syscall(&path, O_CREAT | O_RDWR, S_IRWXA);
+                assert_eq!(cage2.close_syscall(fd), 0);
+            }));
+        }
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        filesystem::set_log_batch_window(filesystem::DEFAULT_LOG_BATCH_WINDOW_US);
+        let logbytes = std::fs::read(filesystem::LOGFILENAME).unwrap();
+        let (entries, intact) = interface::read_log_entries(&logbytes);
+        assert!(intact);
+        assert_eq!(entries.len(), 16);
+        for entry in entries {
+            let (_, inode): (usize, Option<filesystem::Inode>) =
+                interface::deserialize_metadata(entry).unwrap();
+            assert!(inode.is_some());
+        }
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let mut statdata = StatData::default();
+        for i in 0..8 {
+            let path = format!("/batched{}", i);
+            assert_eq!(cage.stat_syscall(&path, &mut statdata), 0);
+            assert_eq!(cage.unlink_syscall(&path), 0);
+        }
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_log_entries() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let fd = cage.open_syscall("/logentries", O_CREAT | O_RDWR, S_IRWXA);
+        filesystem::compact_log();
+        let logged_inodes = || {
+            let logbytes = std::fs::read(filesystem::LOGFILENAME).unwrap();
+            let (entries, intact) = interface::read_log_entries(&logbytes);
+            assert!(intact);
+            entries
+                .into_iter()
+                .map(|entry| interface::deserialize_metadata(entry).unwrap())
+                .collect::<Vec<(usize, Option<filesystem::Inode>)>>()
+        };
+
+        //each change is logged as it left the inode
+        assert_eq!(cage.write_syscall(fd, str2cbuf("hello"), 5), 5);
+        assert_eq!(cage.ftruncate_syscall(fd, 3), 0);
+        assert_eq!(cage.chmod_syscall("/logentries", S_IRUSR | S_IWUSR), 0);
+        let inodes = logged_inodes();
+        assert_eq!(inodes.len(), 3);
+        let inodenum = inodes[0].0;
+        let logged_file = |index: usize| match &inodes[index] {
+            (num, Some(filesystem::Inode::File(f))) if *num == inodenum => f,
+            _ => panic!("expected the file's inode"),
+        };
+        assert_eq!(logged_file(0).size, 5);
+        assert_eq!(logged_file(1).size, 3);
+        assert_eq!(logged_file(2).mode & S_IRWXA, S_IRUSR | S_IWUSR);
+
+        //a file unlinked while open is logged as gone once its last descriptor is closed
+        assert_eq!(cage.unlink_syscall("/logentries"), 0);
+        filesystem::compact_log();
+        assert_eq!(cage.close_syscall(fd), 0);
+        let inodes = logged_inodes();
+        assert_eq!(inodes.len(), 1);
+        assert_eq!(inodes[0].0, inodenum);
+        assert!(inodes[0].1.is_none());
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_periodic_persist() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        filesystem::set_persist_interval(20);
+
+        assert_eq!(cage.mkdir_syscall("/flushtmp", S_IRWXA), 0);
+        assert_eq!(cage.mount_syscall("none", "/flushtmp", "tmpfs", 0, None), 0);
+        let fd = cage.open_syscall("/flushtmp/scratch", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.close_syscall(fd), 0);
+        let mut tmpstat = StatData::default();
+        assert_eq!(cage.stat_syscall("/flushtmp/scratch", &mut tmpstat), 0);
+        let fd = cage.open_syscall("/persisted", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.close_syscall(fd), 0);
+        let mut statdata = StatData::default();
+        assert_eq!(cage.stat_syscall("/persisted", &mut statdata), 0);
+
+        //the flusher picks up the change and leaves an empty log behind
+        let mut waited = 0;
+        while filesystem::log_size().unwrap() > 0 {
+            assert!(waited < 500, "metadata was never flushed");
+            interface::sleep(interface::RustDuration::from_millis(10));
+            waited += 1;
+        }
+        drop(filesystem::LOG_COMPACT_LOCK.lock());
+        let ondisk = filesystem::FilesystemMetadata::init_fs_metadata();
+        assert!(ondisk.inodetable.contains_key(&(statdata.st_ino as usize)));
+        //while what is on the tmpfs stays in memory
+        assert!(!ondisk.inodetable.contains_key(&(tmpstat.st_ino as usize)));
+        assert_eq!(cage.unlink_syscall("/flushtmp/scratch"), 0);
+        assert_eq!(cage.umount_syscall("/flushtmp"), 0);
+        assert_eq!(cage.rmdir_syscall("/flushtmp"), 0);
+
+        //with periodic snapshots off, changes stay in the log
+        filesystem::set_persist_interval(0);
+        assert_eq!(cage.unlink_syscall("/persisted"), 0);
+        interface::sleep(interface::RustDuration::from_millis(100));
+        assert!(filesystem::log_size().unwrap() > 0);
+
+        filesystem::set_persist_interval(filesystem::DEFAULT_PERSIST_INTERVAL_MS);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_metadata_format() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        //snapshots are written in the configured format
+        interface::set_metadata_format(interface::MetadataFormat::Binary);
+        let fd = cage.open_syscall("/binaryfile", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.close_syscall(fd), 0);
+        let mut statdata = StatData::default();
+        assert_eq!(cage.stat_syscall("/binaryfile", &mut statdata), 0);
+        filesystem::compact_log();
+        let ondiskbytes = std::fs::read(filesystem::METADATAFILENAME).unwrap();
+        assert_eq!(
+            interface::metadata_format_of(&ondiskbytes),
+            interface::MetadataFormat::Binary
+        );
+        let ondisk = filesystem::FilesystemMetadata::init_fs_metadata();
+        assert!(ondisk.inodetable.contains_key(&(statdata.st_ino as usize)));
+
+        //switching back to CBOR reads the binary snapshot and rewrites it
+        interface::set_metadata_format(interface::MetadataFormat::Cbor);
+        assert_eq!(cage.unlink_syscall("/binaryfile"), 0);
+        filesystem::compact_log();
+        let ondiskbytes = std::fs::read(filesystem::METADATAFILENAME).unwrap();
+        assert_eq!(
+            interface::metadata_format_of(&ondiskbytes),
+            interface::MetadataFormat::Cbor
+        );
+        let ondisk = filesystem::FilesystemMetadata::init_fs_metadata();
+        assert!(!ondisk.inodetable.contains_key(&(statdata.st_ino as usize)));
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_fsck_gc() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let fd = cage.open_syscall("/kept", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("hello"), 5), 5);
+        assert_eq!(cage.close_syscall(fd), 0);
+        let keptinode = filesystem::metawalk(interface::RustPath::new("/kept")).unwrap();
+
+        //a data file left behind by an inode that no longer exists
+        let orphaninode = filesystem::FS_METADATA
+            .nextinode
+            .fetch_add(1, interface::RustAtomicOrdering::Relaxed);
+        let orphanname = format!("{}{}", FILEDATAPREFIX, orphaninode);
+        std::fs::write(&orphanname, b"orphaned data").unwrap();
+
+        let report = filesystem::fsck();
+        assert_eq!(report.datafiles, 1);
+        assert_eq!(report.bytes, 13);
+        assert!(!interface::pathexists(orphanname));
+        assert!(interface::pathexists(format!(
+            "{}{}",
+            FILEDATAPREFIX, keptinode
+        )));
+
+        //nothing left to reclaim the second time around
+        assert_eq!(filesystem::fsck().datafiles, 0);
+
+        assert_eq!(cage.unlink_syscall("/kept"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_tar() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        //a small image, with a hard link, a device and a name too long for a plain ustar header
+        assert_eq!(cage.mkdir_syscall("/tarsrc", S_IRWXA), 0);
+        assert_eq!(cage.mkdir_syscall("/tarsrc/etc", 0o755), 0);
+        let fd = cage.open_syscall("/tarsrc/etc/passwd", O_CREAT | O_WRONLY, 0o644);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("root:x:0:0"), 10), 10);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(
+            cage.link_syscall("/tarsrc/etc/passwd", "/tarsrc/etc/passwd.bak"),
+            0
+        );
+        let longname = format!("/tarsrc/{}", "x".repeat(120));
+        let fd = cage.open_syscall(&longname, O_CREAT | O_WRONLY, 0o600);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(
+            cage.mknod_syscall("/tarsrc/null", S_IFCHR as u32 | 0o666, makedev(&NULLDEVNO)),
+            0
+        );
+        assert_eq!(cage.chmod_syscall("/tarsrc/etc", 0o750), 0);
+        //an id with more digits than the octal header field holds
+        *cage.cred.write() = Credentials::new(0, 0);
+        assert_eq!(
+            cage.chown_syscall("/tarsrc/etc/passwd", 0o10000000, 0o70000000),
+            0
+        );
+
+        let mut archive: Vec<u8> = vec![];
+        assert_eq!(cage.export_tar("/tarsrc", &mut archive), 0);
+        assert_eq!(archive.len() % 512, 0);
+        assert_eq!(&archive[257..262], b"ustar");
+
+        assert_eq!(cage.mkdir_syscall("/tardst", S_IRWXA), 0);
+        assert_eq!(cage.import_tar("/tardst", &mut archive.as_slice()), 0);
+
+        let mut srcstat = StatData::default();
+        let mut dststat = StatData::default();
+        for path in ["etc", "etc/passwd", "null"] {
+            assert_eq!(
+                cage.stat_syscall(&format!("/tarsrc/{}", path), &mut srcstat),
+                0
+            );
+            assert_eq!(
+                cage.stat_syscall(&format!("/tardst/{}", path), &mut dststat),
+                0
+            );
+            assert_eq!(srcstat.st_mode, dststat.st_mode);
+            assert_eq!(srcstat.st_size, dststat.st_size);
+            assert_eq!(srcstat.st_rdev, dststat.st_rdev);
+        }
+        let srcinode = filesystem::metawalk(interface::RustPath::new("/tarsrc/etc")).unwrap();
+        let dstinode = filesystem::metawalk(interface::RustPath::new("/tardst/etc")).unwrap();
+        match (
+            &*filesystem::FS_METADATA.inodetable.get(&srcinode).unwrap(),
+            &*filesystem::FS_METADATA.inodetable.get(&dstinode).unwrap(),
+        ) {
+            (filesystem::Inode::Dir(src), filesystem::Inode::Dir(dst)) => {
+                assert_eq!(src.mtime, dst.mtime)
+            }
+            _ => panic!("etc is not a directory"),
+        }
+
+        //the hard link still shares its inode, and the contents came along
+        assert_eq!(cage.stat_syscall("/tardst/etc/passwd", &mut srcstat), 0);
+        assert_eq!(cage.stat_syscall("/tardst/etc/passwd.bak", &mut dststat), 0);
+        assert_eq!(srcstat.st_ino, dststat.st_ino);
+        assert_eq!(srcstat.st_nlink, 2);
+        assert_eq!(srcstat.st_uid, 0o10000000);
+        assert_eq!(srcstat.st_gid, 0o70000000);
+        let fd = cage.open_syscall("/tardst/etc/passwd", O_RDONLY, 0);
+        let mut read_buf = sizecbuf(10);
+        assert_eq!(cage.read_syscall(fd, read_buf.as_mut_ptr(), 10), 10);
+        assert_eq!(cbuf2str(&read_buf), "root:x:0:0");
+        assert_eq!(cage.close_syscall(fd), 0);
+        let longdest = format!("/tardst/{}", "x".repeat(120));
+        assert_eq!(cage.stat_syscall(&longdest, &mut dststat), 0);
+
+        //anything that is not an archive is refused
+        let garbage = vec![1u8; 1024];
+        assert_eq!(
+            cage.import_tar("/tardst", &mut garbage.as_slice()),
+            -(Errno::EINVAL as i32)
+        );
+
+        for root in ["/tarsrc", "/tardst"] {
+            assert_eq!(cage.unlink_syscall(&format!("{}/etc/passwd", root)), 0);
+            assert_eq!(cage.unlink_syscall(&format!("{}/etc/passwd.bak", root)), 0);
+            assert_eq!(cage.rmdir_syscall(&format!("{}/etc", root)), 0);
+            assert_eq!(
+                cage.unlink_syscall(&format!("{}/{}", root, "x".repeat(120))),
+                0
+            );
+            assert_eq!(cage.unlink_syscall(&format!("{}/null", root)), 0);
+            assert_eq!(cage.rmdir_syscall(root), 0);
+        }
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_dir_index() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        //read the names returned by one getdents call, a small buffer only fits a few at a time
+        let readnames = |fd: i32| -> Vec<String> {
+            let mut buf = vec![0u8; 128];
+            let baseptr: *mut u8 = &mut buf[0];
+            let written = cage.getdents_syscall(fd, baseptr, 128);
+            assert!(written >= 0);
+            let mut names = vec![];
+            let mut offset = 0;
+            while offset < written as isize {
+                unsafe {
+                    let dirent = baseptr.wrapping_offset(offset) as *mut interface::ClippedDirent;
+                    let nameptr =
+                        baseptr.wrapping_offset(offset + interface::CLIPPED_DIRENT_SIZE as isize);
+                    let name = interface::RustCStr::from_ptr(nameptr as *const _);
+                    names.push(name.to_str().unwrap().to_string());
+                    offset += (*dirent).d_reclen as isize;
+                }
+            }
+            names
+        };
+
+        assert_eq!(cage.mkdir_syscall("/dirindex", S_IRWXA), 0);
+        for i in 0..10 {
+            let fd = cage.open_syscall(&format!("/dirindex/f{}", i), O_CREAT, S_IRWXA);
+            assert_eq!(cage.close_syscall(fd), 0);
+        }
+
+        //entries are listed in the order they were created
+        let fd = cage.open_syscall("/dirindex", O_RDONLY, 0);
+        let mut listed = vec![];
+        loop {
+            let names = readnames(fd);
+            if names.is_empty() {
+                break;
+            }
+            listed.extend(names);
+        }
+        let mut expected = vec![".".to_string(), "..".to_string()];
+        expected.extend((0..10).map(|i| format!("f{}", i)));
+        assert_eq!(listed, expected);
+
+        //changing the directory midway through a listing neither skips nor repeats the entries
+        //that were left alone
+        assert_eq!(cage.lseek_syscall(fd, 0, SEEK_SET), 0);
+        let mut listed = readnames(fd);
+        assert!(listed.len() > 1 && listed.len() < 6);
+        let seen = listed.last().unwrap().clone();
+        assert_eq!(cage.unlink_syscall(&format!("/dirindex/{}", seen)), 0);
+        assert_eq!(cage.unlink_syscall("/dirindex/f8"), 0);
+        let newfd = cage.open_syscall("/dirindex/g", O_CREAT, S_IRWXA);
+        assert_eq!(cage.close_syscall(newfd), 0);
+        loop {
+            let names = readnames(fd);
+            if names.is_empty() {
+                break;
+            }
+            listed.extend(names);
+        }
+        let mut expected = vec![".".to_string(), "..".to_string()];
+        expected.extend((0..10).filter(|i| *i != 8).map(|i| format!("f{}", i)));
+        expected.push("g".to_string());
+        assert_eq!(listed, expected);
+
+        //a renamed entry is listed as a new one
+        assert_eq!(cage.rename_syscall("/dirindex/f0", "/dirindex/h"), 0);
+        assert_eq!(cage.lseek_syscall(fd, 0, SEEK_SET), 0);
+        let mut listed = vec![];
+        loop {
+            let names = readnames(fd);
+            if names.is_empty() {
+                break;
+            }
+            listed.extend(names);
+        }
+        expected.retain(|name| name != &seen && name != "f0");
+        expected.push("h".to_string());
+        assert_eq!(listed, expected);
+
+        assert_eq!(cage.close_syscall(fd), 0);
+        for name in listed.iter().skip(2) {
+            assert_eq!(cage.unlink_syscall(&format!("/dirindex/{}", name)), 0);
+        }
+        assert_eq!(cage.rmdir_syscall("/dirindex"), 0);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_at_syscalls() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        assert_eq!(cage.mkdir_syscall("/atdir", S_IRWXA), 0);
+        let dirfd = cage.open_syscall("/atdir", O_RDONLY, 0);
+        assert!(dirfd >= 0);
+
+        //relative paths start from the directory dirfd refers to
+        assert_eq!(cage.mkdirat_syscall(dirfd, "sub", S_IRWXA), 0);
+        assert_eq!(cage.access_syscall("/atdir/sub", F_OK), 0);
+        let fd = cage.openat_syscall(dirfd, "sub/file", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("hi"), 2), 2);
+
+        let mut statdata = StatData::default();
+        assert_eq!(cage.fstatat_syscall(dirfd, "sub/file", &mut statdata, 0), 0);
+        assert_eq!(statdata.st_size, 2);
+        assert_eq!(
+            cage.fstatat_syscall(dirfd, "sub/file", &mut statdata, AT_SYMLINK_NOFOLLOW),
+            0
+        );
+
+        //an empty path needs AT_EMPTY_PATH, and then names dirfd itself
+        assert_eq!(
+            cage.fstatat_syscall(dirfd, "", &mut statdata, 0),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(
+            cage.fstatat_syscall(dirfd, "", &mut statdata, AT_EMPTY_PATH),
+            0
+        );
+        assert_eq!(statdata.st_mode & S_IFDIR as u32, S_IFDIR as u32);
+        assert_eq!(
+            cage.fstatat_syscall(fd, "", &mut statdata, AT_EMPTY_PATH),
+            0
+        );
+        assert_eq!(statdata.st_size, 2);
+        assert_eq!(
+            cage.fstatat_syscall(dirfd, "sub", &mut statdata, 0x8000),
+            -(Errno::EINVAL as i32)
+        );
+
+        //the two paths of linkat and renameat resolve independently
+        assert_eq!(
+            cage.linkat_syscall(dirfd, "sub/file", AT_FDCWD, "/atdir/sub/link", 0),
+            0
+        );
+        assert_eq!(
+            cage.renameat_syscall(dirfd, "sub/link", AT_FDCWD, "/atdir/sub/renamed"),
+            0
+        );
+        assert_eq!(cage.access_syscall("/atdir/sub/renamed", F_OK), 0);
+
+        //AT_FDCWD resolves from the working directory, and absolute paths ignore dirfd
+        assert_eq!(cage.chdir_syscall("/atdir/sub"), 0);
+        let cwdfd = cage.openat_syscall(AT_FDCWD, "renamed", O_RDONLY, 0);
+        assert!(cwdfd >= 0);
+        assert_eq!(cage.close_syscall(cwdfd), 0);
+        assert_eq!(cage.chdir_syscall("/"), 0);
+        let absfd = cage.openat_syscall(fd, "/atdir/sub/renamed", O_RDONLY, 0);
+        assert!(absfd >= 0);
+        assert_eq!(cage.close_syscall(absfd), 0);
+
+        //relative paths need dirfd to be an open directory
+        assert_eq!(
+            cage.openat_syscall(fd, "renamed", O_RDONLY, 0),
+            -(Errno::ENOTDIR as i32)
+        );
+        assert_eq!(
+            cage.openat_syscall(900, "renamed", O_RDONLY, 0),
+            -(Errno::EBADF as i32)
+        );
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //AT_REMOVEDIR makes unlinkat remove a directory instead
+        assert_eq!(
+            cage.unlinkat_syscall(dirfd, "sub/renamed", AT_REMOVEDIR),
+            -(Errno::ENOTDIR as i32)
+        );
+        assert_eq!(cage.unlinkat_syscall(dirfd, "sub/renamed", 0), 0);
+        assert_eq!(cage.unlinkat_syscall(dirfd, "sub/file", 0), 0);
+        assert_eq!(
+            cage.unlinkat_syscall(dirfd, "sub", 0x8000),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.unlinkat_syscall(dirfd, "sub", AT_REMOVEDIR), 0);
+        assert_eq!(
+            cage.access_syscall("/atdir/sub", F_OK),
+            -(Errno::ENOENT as i32)
+        );
+
+        assert_eq!(cage.close_syscall(dirfd), 0);
+        assert_eq!(cage.rmdir_syscall("/atdir"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_open_dir_path_flags() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        assert_eq!(cage.mkdir_syscall("/openflags", S_IRWXA), 0);
+        let fd = cage.open_syscall("/openflags/file", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("data"), 4), 4);
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //O_DIRECTORY only opens directories, and never creates anything
+        assert_eq!(
+            cage.open_syscall("/openflags/file", O_RDONLY | O_DIRECTORY, 0),
+            -(Errno::ENOTDIR as i32)
+        );
+        assert_eq!(
+            cage.open_syscall("/openflags/new", O_CREAT | O_DIRECTORY, S_IRWXA),
+            -(Errno::EINVAL as i32)
+        );
+        let dirfd = cage.open_syscall("/openflags", O_RDONLY | O_DIRECTORY, 0);
+        assert!(dirfd >= 0);
+        assert_eq!(cage.close_syscall(dirfd), 0);
+
+        //there are no symbolic links, so O_NOFOLLOW opens files as usual
+        let fd = cage.open_syscall("/openflags/file", O_RDONLY | O_NOFOLLOW, 0);
+        assert!(fd >= 0);
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //an O_PATH descriptor can be stat'd, but not read, written or otherwise used
+        let pathfd = cage.open_syscall("/openflags/file", O_PATH | O_RDWR | O_TRUNC, 0);
+        assert!(pathfd >= 0);
+        let mut statdata = StatData::default();
+        assert_eq!(cage.fstat_syscall(pathfd, &mut statdata), 0);
+        assert_eq!(statdata.st_size, 4);
+        let mut read_buf = sizecbuf(4);
+        assert_eq!(
+            cage.read_syscall(pathfd, read_buf.as_mut_ptr(), 4),
+            -(Errno::EBADF as i32)
+        );
+        assert_eq!(
+            cage.pread_syscall(pathfd, read_buf.as_mut_ptr(), 4, 0),
+            -(Errno::EBADF as i32)
+        );
+        assert_eq!(
+            cage.write_syscall(pathfd, str2cbuf("data"), 4),
+            -(Errno::EBADF as i32)
+        );
+        assert_eq!(
+            cage.lseek_syscall(pathfd, 0, SEEK_SET),
+            -(Errno::EBADF as i32)
+        );
+        assert_eq!(cage.fchmod_syscall(pathfd, S_IRWXU), -(Errno::EBADF as i32));
+        assert_eq!(cage.flock_syscall(pathfd, LOCK_EX), -(Errno::EBADF as i32));
+        assert_eq!(cage.close_syscall(pathfd), 0);
+
+        //O_PATH ignores O_CREAT, so it only finds existing files
+        assert_eq!(
+            cage.open_syscall("/openflags/missing", O_PATH | O_CREAT, S_IRWXA),
+            -(Errno::ENOENT as i32)
+        );
+
+        //an O_PATH directory still works for resolving *at paths
+        let pathdirfd = cage.open_syscall("/openflags", O_PATH | O_DIRECTORY, 0);
+        assert!(pathdirfd >= 0);
+        let mut dentbuf = vec![0u8; 128];
+        assert_eq!(
+            cage.getdents_syscall(pathdirfd, dentbuf.as_mut_ptr(), 128),
+            -(Errno::EBADF as i32)
+        );
+        let fd = cage.openat_syscall(pathdirfd, "file", O_RDONLY, 0);
+        assert!(fd >= 0);
+        assert_eq!(cage.read_syscall(fd, read_buf.as_mut_ptr(), 4), 4);
+        assert_eq!(cbuf2str(&read_buf), "data");
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.close_syscall(pathdirfd), 0);
+
+        assert_eq!(cage.unlink_syscall("/openflags/file"), 0);
+        assert_eq!(cage.rmdir_syscall("/openflags"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_fchdir() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let mut buf = vec![0u8; 32];
+        let bufptr: *mut u8 = &mut buf[0];
+
+        assert_eq!(cage.mkdir_syscall("/fchdir", S_IRWXA), 0);
+        assert_eq!(cage.mkdir_syscall("/fchdir/sub", S_IRWXA), 0);
+        let filefd = cage.open_syscall("/fchdir/file", O_CREAT | O_RDWR, S_IRWXA);
+
+        //save the cwd by fd, move elsewhere, and come back
+        let savedfd = cage.open_syscall(".", O_RDONLY, 0);
+        let subfd = cage.open_syscall("/fchdir/sub", O_RDONLY, 0);
+        assert_eq!(cage.fchdir_syscall(subfd), 0);
+        assert_eq!(cage.getcwd_syscall(bufptr, 32), 0);
+        assert_eq!(
+            interface::RustCStr::from_bytes_until_nul(&buf)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "/fchdir/sub"
+        );
+        assert_eq!(cage.access_syscall("../file", F_OK), 0);
+        assert_eq!(cage.fchdir_syscall(savedfd), 0);
+        assert_eq!(cage.getcwd_syscall(bufptr, 32), 0);
+        assert_eq!(
+            interface::RustCStr::from_bytes_until_nul(&buf)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "/"
+        );
+
+        //the cwd holds its own reference, so the descriptor can be closed independently of it
+        assert_eq!(cage.fchdir_syscall(subfd), 0);
+        assert_eq!(cage.close_syscall(subfd), 0);
+        assert_eq!(cage.access_syscall("../file", F_OK), 0);
+        let pathfd = cage.open_syscall("/fchdir", O_PATH, 0);
+        assert_eq!(cage.fchdir_syscall(pathfd), 0);
+        assert_eq!(cage.access_syscall("sub", F_OK), 0);
+        assert_eq!(cage.close_syscall(pathfd), 0);
+        assert_eq!(cage.fchdir_syscall(savedfd), 0);
+        assert_eq!(cage.close_syscall(savedfd), 0);
+
+        assert_eq!(cage.fchdir_syscall(filefd), -(Errno::ENOTDIR as i32));
+        assert_eq!(cage.fchdir_syscall(900), -(Errno::EBADF as i32));
+        assert_eq!(cage.close_syscall(filefd), 0);
+
+        assert_eq!(cage.unlink_syscall("/fchdir/file"), 0);
+        assert_eq!(cage.rmdir_syscall("/fchdir/sub"), 0);
+        assert_eq!(cage.rmdir_syscall("/fchdir"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_readonly() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let erofs = -(Errno::EROFS as i32);
+
+        assert_eq!(cage.mkdir_syscall("/rofs", S_IRWXA), 0);
+        assert_eq!(cage.mkdir_syscall("/rofs/dir", S_IRWXA), 0);
+        let fd = cage.open_syscall("/rofs/file", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("base"), 4), 4);
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        filesystem::set_fs_readonly(true);
+
+        //reads are still served
+        let fd = cage.open_syscall("/rofs/file", O_RDONLY, 0);
+        assert!(fd >= 0);
+        let mut read_buf = sizecbuf(4);
+        assert_eq!(cage.read_syscall(fd, read_buf.as_mut_ptr(), 4), 4);
+        assert_eq!(cbuf2str(&read_buf), "base");
+        let mut statdata = StatData::default();
+        assert_eq!(cage.stat_syscall("/rofs/file", &mut statdata), 0);
+
+        //but nothing may change
+        assert_eq!(cage.open_syscall("/rofs/file", O_RDWR, 0), erofs);
+        assert_eq!(cage.open_syscall("/rofs/file", O_TRUNC, 0), erofs);
+        assert_eq!(cage.open_syscall("/rofs/new", O_CREAT, S_IRWXA), erofs);
+        assert_eq!(cage.creat_syscall("/rofs/new", S_IRWXA), erofs);
+        assert_eq!(cage.mkdir_syscall("/rofs/newdir", S_IRWXA), erofs);
+        assert_eq!(
+            cage.mkdirat_syscall(AT_FDCWD, "/rofs/newdir", S_IRWXA),
+            erofs
+        );
+        assert_eq!(
+            cage.mknod_syscall("/rofs/dev", S_IFCHR as u32 | 0o666, makedev(&NULLDEVNO)),
+            erofs
+        );
+        assert_eq!(cage.link_syscall("/rofs/file", "/rofs/link"), erofs);
+        assert_eq!(cage.rename_syscall("/rofs/file", "/rofs/moved"), erofs);
+        assert_eq!(cage.unlink_syscall("/rofs/file"), erofs);
+        assert_eq!(cage.rmdir_syscall("/rofs/dir"), erofs);
+        assert_eq!(cage.chmod_syscall("/rofs/file", S_IRWXU), erofs);
+        assert_eq!(cage.fchmod_syscall(fd, S_IRWXU), erofs);
+        assert_eq!(cage.truncate_syscall("/rofs/file", 0), erofs);
+        assert_eq!(cage.access_syscall("/rofs/file", W_OK), erofs);
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //scratch space that isn't part of the persistent filesystem stays writable
+        let fd = cage.open_syscall("/tmp/scratch", O_CREAT | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("temp"), 4), 4);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall("/tmp/scratch"), 0);
+
+        filesystem::set_fs_readonly(false);
+
+        //a single mount can also be read-only from the start
+        assert_eq!(
+            cage.mount_syscall("none", "/rofs/dir", "tmpfs", MS_RDONLY, None),
+            0
+        );
+        assert_eq!(cage.mkdir_syscall("/rofs/dir/sub", S_IRWXA), erofs);
+        assert_eq!(cage.mkdir_syscall("/rofs/sub", S_IRWXA), 0);
+        assert_eq!(cage.umount_syscall("/rofs/dir"), 0);
+
+        assert_eq!(cage.rmdir_syscall("/rofs/sub"), 0);
+        assert_eq!(cage.unlink_syscall("/rofs/file"), 0);
+        assert_eq!(cage.rmdir_syscall("/rofs/dir"), 0);
+        assert_eq!(cage.rmdir_syscall("/rofs"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_sync_flags() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let mut buf = vec![0u8; 8];
+        let bufptr: *mut u8 = &mut buf[0];
+
+        //O_SYNC and O_DSYNC stay on the descriptor and are reported by F_GETFL
+        let syncfd = cage.open_syscall("/syncfile", O_CREAT | O_RDWR | O_SYNC, S_IRWXA);
+        assert!(syncfd >= 0);
+        assert_eq!(cage.fcntl_syscall(syncfd, F_GETFL, 0) & O_SYNC, O_SYNC);
+        let dsyncfd = cage.open_syscall("/syncfile", O_RDWR | O_DSYNC, 0);
+        assert!(dsyncfd >= 0);
+        let dsyncflags = cage.fcntl_syscall(dsyncfd, F_GETFL, 0);
+        assert_eq!(dsyncflags & O_DSYNC, O_DSYNC);
+        assert_ne!(dsyncflags & O_SYNC, O_SYNC);
+        let plainfd = cage.open_syscall("/syncfile", O_RDONLY, 0);
+        assert_eq!(cage.fcntl_syscall(plainfd, F_GETFL, 0) & O_DSYNC, 0);
+
+        //writes through either descriptor behave like ordinary writes
+        assert_eq!(cage.write_syscall(syncfd, str2cbuf("sync"), 4), 4);
+        assert_eq!(cage.pwrite_syscall(dsyncfd, str2cbuf("data"), 4, 4), 4);
+        assert_eq!(cage.read_syscall(plainfd, bufptr, 8), 8);
+        assert_eq!(cbuf2str(&buf), "syncdata");
+
+        assert_eq!(cage.close_syscall(syncfd), 0);
+        assert_eq!(cage.close_syscall(dsyncfd), 0);
+        assert_eq!(cage.close_syscall(plainfd), 0);
+        assert_eq!(cage.unlink_syscall("/syncfile"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_sync_syncfs() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let mut buf = vec![0u8; 4];
+        let bufptr: *mut u8 = &mut buf[0];
+
+        let fd = cage.open_syscall("/syncfsfile", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("sync"), 4), 4);
+        assert_eq!(cage.sync_syscall(), 0);
+        assert_eq!(cage.syncfs_syscall(fd), 0);
+        assert_eq!(cage.pread_syscall(fd, bufptr, 4, 0), 4);
+        assert_eq!(cbuf2str(&buf), "sync");
+
+        //a file on a non-persistent mount and a descriptor that isn't a file can be synced too
+        let tmpfd = cage.open_syscall("/tmp/syncfsfile", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.write_syscall(tmpfd, str2cbuf("temp"), 4), 4);
+        assert_eq!(cage.syncfs_syscall(tmpfd), 0);
+        let mut pipefds = PipeArray {
+            readfd: -1,
+            writefd: -1,
+        };
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        assert_eq!(cage.syncfs_syscall(pipefds.readfd), 0);
+
+        assert_eq!(cage.close_syscall(pipefds.readfd), 0);
+        assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+        assert_eq!(cage.close_syscall(tmpfd), 0);
+        assert_eq!(cage.syncfs_syscall(tmpfd), -(Errno::EBADF as i32));
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall("/tmp/syncfsfile"), 0);
+        assert_eq!(cage.unlink_syscall("/syncfsfile"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
         lindrustfinalize();
     }
 
-    pub fn ut_lind_fs_sem_trytimed() {
+    pub fn ut_lind_fs_dup3() {
         lindrustinit(0);
         let cage = interface::cagetable_getref(1);
-        let key = 31337;
-        // Create a shared memory region
-        let shmid = cage.shmget_syscall(key, 1024, 0666 | IPC_CREAT);
-        // Attach the shared memory region
-        let shmatret = cage.shmat_syscall(shmid, 0xfffff000 as *mut u8, 0);
-        assert_ne!(shmatret, -1);
-        // Initialize the semaphore with shared between process
-        let ret_init = cage.sem_init_syscall(shmatret as u32, 1, 1);
-        // assert_eq!(shmatret as u32, 0);
-        assert_eq!(ret_init, 0);
-        assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), 1);
-        // Fork child process
+
+        let fd = cage.open_syscall("/dup3file", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(cage.fcntl_syscall(fd, F_GETFD, 0), 0);
+
+        //close-on-exec is set on the new descriptor alone
+        assert_eq!(cage.dup3_syscall(fd, 20, O_CLOEXEC), 20);
+        assert_eq!(cage.fcntl_syscall(20, F_GETFD, 0), O_CLOEXEC);
+        assert_eq!(cage.fcntl_syscall(fd, F_GETFD, 0), 0);
+        assert_eq!(cage.dup3_syscall(20, 21, 0), 21);
+        assert_eq!(cage.fcntl_syscall(21, F_GETFD, 0), 0);
+        assert_eq!(cage.fcntl_syscall(20, F_GETFD, 0), O_CLOEXEC);
+
+        //dup3 replaces whatever was open at the new descriptor
+        assert_eq!(cage.dup3_syscall(fd, 20, 0), 20);
+        assert_eq!(cage.fcntl_syscall(20, F_GETFD, 0), 0);
+
+        let dupfd = cage.fcntl_syscall(fd, F_DUPFD_CLOEXEC, 30);
+        assert!(dupfd >= 30);
+        assert_eq!(cage.fcntl_syscall(dupfd, F_GETFD, 0), O_CLOEXEC);
+        assert_eq!(cage.fcntl_syscall(fd, F_GETFD, 0), 0);
+        assert_eq!(cage.write_syscall(dupfd, str2cbuf("dup3"), 4), 4);
+
+        assert_eq!(cage.dup3_syscall(fd, fd, 0), -(Errno::EINVAL as i32));
+        assert_eq!(
+            cage.dup3_syscall(fd, 22, O_NONBLOCK),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.dup3_syscall(fd, MAXFD, 0), -(Errno::EBADF as i32));
+        assert_eq!(cage.dup3_syscall(22, 23, 0), -(Errno::EBADF as i32));
+
+        for closefd in [fd, 20, 21, dupfd] {
+            assert_eq!(cage.close_syscall(closefd), 0);
+        }
+        assert_eq!(cage.unlink_syscall("/dup3file"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_fd_status_flags() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let mut buf = vec![0u8; 8];
+        let bufptr: *mut u8 = &mut buf[0];
+
+        let fd = cage.open_syscall("/statusflagsfile", O_CREAT | O_RDWR, S_IRWXA);
+        let dupfd = cage.dup_syscall(fd, None);
+
+        //status flags are shared with the duplicate, close-on-exec is not
+        assert_eq!(cage.fcntl_syscall(fd, F_SETFL, O_NONBLOCK), 0);
+        assert_eq!(cage.fcntl_syscall(fd, F_SETFD, O_CLOEXEC), 0);
+        assert_eq!(cage.fcntl_syscall(dupfd, F_GETFL, 0), O_RDWR | O_NONBLOCK);
+        assert_eq!(cage.fcntl_syscall(dupfd, F_GETFD, 0), 0);
+
+        //F_SETFL replaces the status flags but leaves the access mode alone
+        assert_eq!(cage.fcntl_syscall(dupfd, F_SETFL, O_APPEND | O_RDONLY), 0);
+        assert_eq!(cage.fcntl_syscall(fd, F_GETFL, 0), O_RDWR | O_APPEND);
+        assert_eq!(cage.fcntl_syscall(fd, F_GETFD, 0), O_CLOEXEC);
+
+        //with O_APPEND writes go to the end of the file wherever the offset is
+        assert_eq!(cage.write_syscall(fd, str2cbuf("hello"), 5), 5);
+        assert_eq!(cage.lseek_syscall(fd, 0, SEEK_SET), 0);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("!"), 1), 1);
+        assert_eq!(cage.pread_syscall(fd, bufptr, 8, 0), 6);
+        assert_eq!(cbuf2str(&buf[..6]), "hello!");
+
+        //a forked child shares the open file description with its parent
         assert_eq!(cage.fork_syscall(2), 0);
-        // Child process
-        let thread_child = interface::helper_thread(move || {
-            let cage1 = interface::cagetable_getref(2);
-            // Child waits for the semaphore
-            assert_eq!(cage1.sem_trywait_syscall(shmatret as u32), 0);
-            // Wait
-            interface::sleep(interface::RustDuration::from_millis(20));
-            // Release the semaphore
-            assert_eq!(cage1.sem_post_syscall(shmatret as u32), 0);
-            cage1.exit_syscall(EXIT_SUCCESS);
-        });
-        //Parent processes
-        let thread_parent = interface::helper_thread(move || {
-            // Parents waits for the semaphore
+        let child = interface::cagetable_getref(2);
+        assert_eq!(child.fcntl_syscall(fd, F_SETFL, O_NONBLOCK), 0);
+        assert_eq!(cage.fcntl_syscall(dupfd, F_GETFL, 0), O_RDWR | O_NONBLOCK);
+        assert_eq!(child.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+
+        //the same holds for the ends of a pipe
+        let mut pipefds = PipeArray {
+            readfd: -1,
+            writefd: -1,
+        };
+        assert_eq!(cage.pipe2_syscall(&mut pipefds, O_NONBLOCK), 0);
+        let dupreadfd = cage.dup_syscall(pipefds.readfd, None);
+        assert_eq!(cage.fcntl_syscall(dupreadfd, F_SETFL, 0), 0);
+        assert_eq!(cage.fcntl_syscall(pipefds.readfd, F_GETFL, 0), O_RDONLY);
+        assert_eq!(
+            cage.fcntl_syscall(pipefds.writefd, F_GETFL, 0),
+            O_WRONLY | O_NONBLOCK
+        );
+
+        for closefd in [fd, dupfd, pipefds.readfd, pipefds.writefd, dupreadfd] {
+            assert_eq!(cage.close_syscall(closefd), 0);
+        }
+        assert_eq!(cage.unlink_syscall("/statusflagsfile"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_exec_cloexec_fd_kinds() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let mut pipefds = PipeArray {
+            readfd: -1,
+            writefd: -1,
+        };
+        assert_eq!(cage.pipe2_syscall(&mut pipefds, O_CLOEXEC), 0);
+        let sockfd = cage.socket_syscall(AF_INET, SOCK_STREAM | SOCK_CLOEXEC, 0);
+        let epfd = cage.epoll_create1_syscall(EPOLL_CLOEXEC);
+        let keptepfd = cage.epoll_create1_syscall(0);
+        let keptfd = cage.open_syscall("/execcloexec", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(
+            cage.epoll_create1_syscall(O_NONBLOCK),
+            -(Errno::EINVAL as i32)
+        );
+
+        let event = EpollEvent {
+            events: EPOLLIN as u32,
+            fd: pipefds.readfd,
+        };
+        assert_eq!(
+            cage.epoll_ctl_syscall(keptepfd, EPOLL_CTL_ADD, pipefds.readfd, &event),
+            0
+        );
+
+        assert_eq!(cage.exec_syscall(2, None), 0);
+        let execcage = interface::cagetable_getref(2);
+
+        //sockets, epoll instances and pipe ends marked close-on-exec are all gone
+        for closedfd in [sockfd, epfd, pipefds.readfd, pipefds.writefd] {
             assert_eq!(
-                cage.sem_timedwait_syscall(
-                    shmatret as u32,
-                    interface::RustDuration::from_millis(100)
-                ),
-                0
+                execcage.fcntl_syscall(closedfd, F_GETFD, 0),
+                -(Errno::EBADF as i32)
             );
-            assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), 0);
-            interface::sleep(interface::RustDuration::from_millis(10));
-            // Parents release the semaphore
-            assert_eq!(cage.sem_post_syscall(shmatret as u32), 0);
-            assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), 1);
-            // Destroy the semaphore
-            assert_eq!(cage.sem_destroy_syscall(shmatret as u32), 0);
-            // mark the shared memory to be rmoved
-            let shmctlret2 = cage.shmctl_syscall(shmid, IPC_RMID, None);
-            assert_eq!(shmctlret2, 0);
-            //detach from shared memory
-            let shmdtret = cage.shmdt_syscall(0xfffff000 as *mut u8);
-            assert_eq!(shmdtret, shmid);
-            cage.exit_syscall(EXIT_SUCCESS);
-        });
-        thread_child.join().unwrap();
-        thread_parent.join().unwrap();
+        }
+        assert_eq!(execcage.fcntl_syscall(keptfd, F_GETFD, 0), 0);
+        assert_eq!(execcage.fcntl_syscall(keptepfd, F_GETFD, 0), 0);
+
+        //the surviving epoll instance no longer holds the closed pipe end, so a file reusing its
+        //number can be registered
+        let reusedfd = execcage.open_syscall("/execcloexec", O_RDONLY, 0);
+        assert_eq!(reusedfd, pipefds.readfd);
+        let event = EpollEvent {
+            events: EPOLLIN as u32,
+            fd: reusedfd,
+        };
+        assert_eq!(
+            execcage.epoll_ctl_syscall(keptepfd, EPOLL_CTL_ADD, pipefds.readfd, &event),
+            0
+        );
+
+        assert_eq!(execcage.close_syscall(reusedfd), 0);
+        assert_eq!(execcage.close_syscall(keptfd), 0);
+        assert_eq!(execcage.close_syscall(keptepfd), 0);
+        assert_eq!(execcage.unlink_syscall("/execcloexec"), 0);
+        assert_eq!(execcage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
         lindrustfinalize();
     }
 
-    pub fn ut_lind_fs_sem_test() {
+    pub fn ut_lind_fs_memfd_create() {
         lindrustinit(0);
         let cage = interface::cagetable_getref(1);
-        let key = 31337;
-        // Create a shared memory region
-        let shmid = cage.shmget_syscall(key, 1024, 0666 | IPC_CREAT);
-        // Attach the shared memory region
-        let shmatret = cage.shmat_syscall(shmid, 0xfffff000 as *mut u8, 0);
-        assert_ne!(shmatret, -1);
-        assert_eq!(cage.sem_destroy_syscall(shmatret as u32), -22);
-        assert_eq!(cage.sem_getvalue_syscall(shmatret as u32), -22);
-        assert_eq!(cage.sem_post_syscall(shmatret as u32), -22);
-        // Initialize the semaphore with shared between process
-        let ret_init = cage.sem_init_syscall(shmatret as u32, 1, 0);
-        assert_eq!(ret_init, 0);
-        // Should return errno
+
         assert_eq!(
-            cage.sem_timedwait_syscall(shmatret as u32, interface::RustDuration::from_millis(100)),
-            -110
+            cage.memfd_create_syscall("badflags", 0x100),
+            -(Errno::EINVAL as i32)
         );
-        assert_eq!(cage.sem_trywait_syscall(shmatret as u32), -11);
+
+        //a plain memfd reads and writes like a file but cannot take any seals
+        let fd = cage.memfd_create_syscall("plain", 0);
+        assert!(fd >= 0);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("hello"), 5), 5);
+        assert_eq!(cage.lseek_syscall(fd, 0, SEEK_SET), 0);
+        let mut read_buf = sizecbuf(5);
+        assert_eq!(cage.read_syscall(fd, read_buf.as_mut_ptr(), 5), 5);
+        assert_eq!(cbuf2str(&read_buf), "hello");
+        assert_eq!(cage.ftruncate_syscall(fd, 100), 0);
+        let mut statdata = StatData::default();
+        assert_eq!(cage.fstat_syscall(fd, &mut statdata), 0);
+        assert_eq!(statdata.st_size, 100);
+        assert_eq!(statdata.st_nlink, 0);
+        assert_eq!(cage.fcntl_syscall(fd, F_GETFD, 0), 0);
+        assert_eq!(cage.fcntl_syscall(fd, F_GET_SEALS, 0), F_SEAL_SEAL);
+        assert_eq!(
+            cage.fcntl_syscall(fd, F_ADD_SEALS, F_SEAL_GROW),
+            -(Errno::EPERM as i32)
+        );
+
+        //closing the last descriptor frees the anonymous inode
+        let inodenum = statdata.st_ino;
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert!(!filesystem::FS_METADATA.inodetable.contains_key(&inodenum));
+
+        let fd = cage.memfd_create_syscall("sealed", MFD_CLOEXEC | MFD_ALLOW_SEALING);
+        assert!(fd >= 0);
+        assert_eq!(cage.fcntl_syscall(fd, F_GETFD, 0), O_CLOEXEC);
+        assert_eq!(cage.fcntl_syscall(fd, F_GET_SEALS, 0), 0);
+        assert_eq!(cage.write_syscall(fd, str2cbuf("0123456789"), 10), 10);
+        assert_eq!(
+            cage.fcntl_syscall(fd, F_ADD_SEALS, F_SEAL_GROW | F_SEAL_SHRINK),
+            0
+        );
+        assert_eq!(cage.ftruncate_syscall(fd, 20), -(Errno::EPERM as i32));
+        assert_eq!(cage.ftruncate_syscall(fd, 5), -(Errno::EPERM as i32));
+        assert_eq!(cage.ftruncate_syscall(fd, 10), 0);
+        assert_eq!(
+            cage.pwrite_syscall(fd, str2cbuf("ab"), 2, 9),
+            -(Errno::EPERM as i32)
+        );
+        assert_eq!(cage.pwrite_syscall(fd, str2cbuf("ab"), 2, 0), 2);
+
+        assert_eq!(cage.fcntl_syscall(fd, F_ADD_SEALS, F_SEAL_WRITE), 0);
+        assert_eq!(
+            cage.pwrite_syscall(fd, str2cbuf("ab"), 2, 0),
+            -(Errno::EPERM as i32)
+        );
+        assert_eq!(
+            cage.fcntl_syscall(fd, F_GET_SEALS, 0),
+            F_SEAL_GROW | F_SEAL_SHRINK | F_SEAL_WRITE
+        );
+        assert_eq!(
+            cage.fcntl_syscall(fd, F_ADD_SEALS, 0x100),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.close_syscall(fd), 0);
+
+        //sealing only applies to memfds
+        let filefd = cage.open_syscall("/sealfile", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(
+            cage.fcntl_syscall(filefd, F_GET_SEALS, 0),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.close_syscall(filefd), 0);
+        assert_eq!(cage.unlink_syscall("/sealfile"), 0);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
         lindrustfinalize();
     }
 
-    pub fn ut_lind_fs_tmp_file_test() {
+    pub fn ut_lind_fs_leases() {
         lindrustinit(0);
         let cage = interface::cagetable_getref(1);
+        let sigio_pending =
+            || interface::lind_sigismember(cage.pending_signals(interface::get_pthreadid()), SIGIO);
+
+        //nothing else has the file open, so it can be leased for writing
+        let path = "/leasefile";
+        let fd = cage.open_syscall(path, O_CREAT | O_RDONLY, S_IRWXA);
+        assert_eq!(cage.fcntl_syscall(fd, F_GETLEASE, 0), F_UNLCK);
+        assert_eq!(cage.fcntl_syscall(fd, F_SETLEASE, F_WRLCK), 0);
+        assert_eq!(cage.fcntl_syscall(fd, F_GETLEASE, 0), F_WRLCK);
+        assert_eq!(
+            cage.fcntl_syscall(fd, F_SETLEASE, 7),
+            -(Errno::EINVAL as i32)
+        );
 
-        // Check if /tmp is there
-        assert_eq!(cage.access_syscall("/tmp", F_OK), 0);
+        //leases of other open file descriptions have to be compatible with it
+        let fd2 = cage.open_syscall(path, O_RDONLY, 0);
+        assert_eq!(
+            cage.fcntl_syscall(fd2, F_SETLEASE, F_RDLCK),
+            -(Errno::EAGAIN as i32)
+        );
+        assert_eq!(cage.fcntl_syscall(fd, F_SETLEASE, F_RDLCK), 0);
+        assert_eq!(cage.fcntl_syscall(fd2, F_SETLEASE, F_RDLCK), 0);
+        assert_eq!(
+            cage.fcntl_syscall(fd2, F_SETLEASE, F_WRLCK),
+            -(Errno::EAGAIN as i32)
+        );
+        assert_eq!(cage.close_syscall(fd2), 0);
+        assert_eq!(
+            cage.fcntl_syscall(fd2, F_GETLEASE, 0),
+            -(Errno::EBADF as i32)
+        );
+        assert_eq!(cage.fcntl_syscall(fd, F_GETLEASE, 0), F_RDLCK);
 
-        // Open  file in /tmp
-        let file_path = "/tmp/testfile";
-        let fd = cage.open_syscall(file_path, O_CREAT | O_TRUNC | O_RDWR, S_IRWXA);
+        //another cage can read the file under a read lease, but writing it breaks the lease
+        assert_eq!(cage.fork_syscall(2), 0);
+        let cage2 = interface::cagetable_getref(2);
+        let rfd = cage2.open_syscall(path, O_RDONLY, 0);
+        assert!(rfd >= 0);
+        assert_eq!(cage2.close_syscall(rfd), 0);
+        assert!(!sigio_pending());
+        assert_eq!(
+            cage2.open_syscall(path, O_WRONLY | O_NONBLOCK, 0),
+            -(Errno::EAGAIN as i32)
+        );
+        assert!(sigio_pending());
+        assert_eq!(cage.fcntl_syscall(fd, F_GETLEASE, 0), F_UNLCK);
+        assert_eq!(
+            cage.fcntl_syscall(fd, F_SETLEASE, F_RDLCK),
+            -(Errno::EAGAIN as i32)
+        );
 
-        assert_eq!(cage.write_syscall(fd, str2cbuf("Hello world"), 6), 6);
+        //and a blocking open waits for the holder to give it up
+        let opened = interface::RustRfc::new(interface::RustAtomicBool::new(false));
+        let openedclone = opened.clone();
+        let child = std::thread::spawn(move || {
+            let cage2 = interface::cagetable_getref(2);
+            let wfd = cage2.open_syscall(path, O_WRONLY, 0);
+            assert!(wfd >= 0);
+            openedclone.store(true, interface::RustAtomicOrdering::SeqCst);
+            assert_eq!(cage2.close_syscall(wfd), 0);
+            assert_eq!(cage2.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        });
+        interface::sleep(interface::RustDuration::from_millis(50));
+        assert!(!opened.load(interface::RustAtomicOrdering::SeqCst));
+        assert_eq!(cage.fcntl_syscall(fd, F_SETLEASE, F_UNLCK), 0);
+        child.join().unwrap();
+        assert!(opened.load(interface::RustAtomicOrdering::SeqCst));
+        let inodenum = filesystem::metawalk(interface::RustPath::new(path)).unwrap();
+        assert!(filesystem::FILE_LEASES.get(&inodenum).is_none());
+
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall(path), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_attr_changes() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let path = "/attrfile";
+        let fd = cage.open_syscall(path, O_CREAT | O_EXCL | O_RDWR, S_IRWXA);
+        assert!(fd >= 0);
+        let inodenum = filesystem::metawalk(interface::RustPath::new(path)).unwrap();
+        let clear_ctime = || match *filesystem::FS_METADATA
+            .inodetable
+            .get_mut(&inodenum)
+            .unwrap()
+        {
+            filesystem::Inode::File(ref mut f) => f.ctime = 0,
+            _ => panic!("not a regular file"),
+        };
+        let ctime = || {
+            let mut statxbuf = StatxStruct::default();
+            assert_eq!(
+                cage.statx_syscall(AT_FDCWD, path, 0, STATX_CTIME, &mut statxbuf),
+                0
+            );
+            statxbuf.stx_ctime.tv_sec
+        };
+        let ifd = cage.inotify_init1_syscall(IN_NONBLOCK);
+        assert!(cage.inotify_add_watch_syscall(ifd, path, IN_ATTRIB) > 0);
+        let mut readbuf = sizecbuf(256);
+
+        //changing the mode or the owner is a change to the inode, which watchers hear about
+        clear_ctime();
+        assert_eq!(cage.chmod_syscall(path, 0o600), 0);
+        assert_ne!(ctime(), 0);
+        assert_eq!(cage.read_syscall(ifd, readbuf.as_mut_ptr(), 256), 16);
+        clear_ctime();
+        assert_eq!(cage.fchmod_syscall(fd, 0o644), 0);
+        assert_ne!(ctime(), 0);
+        assert_eq!(cage.read_syscall(ifd, readbuf.as_mut_ptr(), 256), 16);
+        clear_ctime();
+        assert_eq!(cage.fchown_syscall(fd, u32::MAX, DEFAULT_GID), 0);
+        assert_ne!(ctime(), 0);
+        assert_eq!(cage.read_syscall(ifd, readbuf.as_mut_ptr(), 256), 16);
+
+        //a file someone else owns can't have its mode changed, or be given away without CAP_CHOWN
+        *cage.cred.write() = Credentials::new(0, 0);
+        assert_eq!(cage.chown_syscall(path, 2000, 2000), 0);
+        assert_eq!(cage.read_syscall(ifd, readbuf.as_mut_ptr(), 256), 16);
+        assert_eq!(cage.seteuid_syscall(DEFAULT_UID), 0);
+        clear_ctime();
+        assert_eq!(cage.chmod_syscall(path, S_IRWXA), -(Errno::EPERM as i32));
+        assert_eq!(cage.fchmod_syscall(fd, S_IRWXA), -(Errno::EPERM as i32));
+        assert_eq!(
+            cage.chown_syscall(path, DEFAULT_UID, u32::MAX),
+            -(Errno::EPERM as i32)
+        );
+        assert_eq!(ctime(), 0);
+        assert_eq!(
+            cage.read_syscall(ifd, readbuf.as_mut_ptr(), 256),
+            -(Errno::EAGAIN as i32)
+        );
+        assert_eq!(
+            cage.fchmod_syscall(MAXFD + 1, S_IRWXA),
+            -(Errno::EBADF as i32)
+        );
+        assert_eq!(cage.seteuid_syscall(0), 0);
+
+        assert_eq!(cage.close_syscall(ifd), 0);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall(path), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_contexts() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        assert!(std::ptr::eq(cage.context, context::default_context()));
+        assert_eq!(cage.mkdir_syscall("/outside", S_IRWXA), 0);
+        let ifd = cage.inotify_init1_syscall(IN_NONBLOCK);
+        assert!(cage.inotify_add_watch_syscall(ifd, "/outside", IN_CREATE) > 0);
+        let mut readbuf = sizecbuf(256);
+
+        //a new machine has a filesystem of its own, kept in a directory of its own
+        let root = interface::lind_root().join("contexttest");
+        let machine = context::new_context(Some(&root));
+        {
+            let _context = context::enter_context(machine);
+            filesystem::load_fs();
+            assert!(root.join(filesystem::METADATAFILENAME).exists());
+            assert!(filesystem::metawalk(interface::RustPath::new("/dev/null")).is_some());
+            assert!(filesystem::metawalk(interface::RustPath::new("/outside")).is_none());
+            assert_eq!(cage.mkdir_syscall("/inside", S_IRWXA), 0);
+            assert!(filesystem::metawalk(interface::RustPath::new("/inside")).is_some());
+
+            //along with its own watches, quota charges and procfs
+            assert!(inotify::INOTIFY_WATCHES.is_empty());
+            assert_eq!(cage.mkdir_syscall("/outside", S_IRWXA), 0);
+            let fd = cage.creat_syscall("/outside/file", S_IRWXA);
+            assert_eq!(cage.close_syscall(fd), 0);
+            assert_eq!(
+                cage.read_syscall(ifd, readbuf.as_mut_ptr(), 256),
+                -(Errno::EAGAIN as i32)
+            );
+
+            cage.set_disk_quota(None, None);
+            let fd = cage.creat_syscall("/inside/charged", S_IRWXA);
+            assert_eq!(cage.close_syscall(fd), 0);
+            assert_eq!(cage.disk_quota_usage().unwrap().inodes, 1);
+            assert_eq!(quota::QUOTA_OWNERS.len(), 1);
+
+            assert!(procfs::PROC_NODES.is_empty());
+            assert_eq!(cage.mkdir_syscall("/proc", S_IRWXA), 0);
+            assert_eq!(cage.mount_syscall("proc", "/proc", "proc", 0, None), 0);
+            assert_eq!(cage.access_syscall("/proc/self/status", F_OK), 0);
+            assert_eq!(cage.umount_syscall("/proc"), 0);
+            assert!(procfs::PROC_NODES.is_empty());
+
+            //and whether it is read-only
+            filesystem::set_fs_readonly(true);
+            assert_eq!(
+                cage.mkdir_syscall("/readonly", S_IRWXA),
+                -(Errno::EROFS as i32)
+            );
+            filesystem::set_fs_readonly(false);
+
+            //its flusher snapshots it into its own directory
+            filesystem::set_persist_interval(20);
+            filesystem::start_metadata_flusher();
+            let mut waited = 0;
+            while filesystem::log_size().unwrap() > 0 {
+                assert!(waited < 500, "metadata was never flushed");
+                interface::sleep(interface::RustDuration::from_millis(10));
+                waited += 1;
+            }
+            filesystem::stop_metadata_flusher();
+            filesystem::set_persist_interval(filesystem::DEFAULT_PERSIST_INTERVAL_MS);
+
+            filesystem::LOGMAP.write().take().unwrap().close().unwrap();
+        }
+
+        //and nothing done in it shows up in the default one
+        assert!(filesystem::metawalk(interface::RustPath::new("/inside")).is_none());
+        assert!(filesystem::metawalk(interface::RustPath::new("/outside")).is_some());
+        assert!(!filesystem::fs_readonly());
+        assert!(quota::QUOTA_OWNERS.is_empty());
+        assert_eq!(cage.access_syscall("/proc/self/status", F_OK), 0);
+        let rootdict = |metadata: &filesystem::FilesystemMetadata, name: &str| match &*metadata
+            .inodetable
+            .get(&ROOTDIRECTORYINODE)
+            .unwrap()
+        {
+            filesystem::Inode::Dir(dir) => dir.filename_to_inode_dict.contains_key(name),
+            _ => panic!("root is not a directory"),
+        };
+        {
+            let _context = context::enter_context(machine);
+            assert!(rootdict(
+                &filesystem::FilesystemMetadata::init_fs_metadata(),
+                "inside"
+            ));
+        }
+        filesystem::flush_metadata();
+        assert!(!rootdict(
+            &filesystem::FilesystemMetadata::init_fs_metadata(),
+            "inside"
+        ));
+        std::fs::remove_dir_all(&root).unwrap();
+
+        //the watch still sees what happens in its own machine
+        let fd = cage.creat_syscall("/outside/file", S_IRWXA);
         assert_eq!(cage.close_syscall(fd), 0);
+        assert!(cage.read_syscall(ifd, readbuf.as_mut_ptr(), 256) > 0);
+        assert_eq!(cage.unlink_syscall("/outside/file"), 0);
+        assert_eq!(cage.close_syscall(ifd), 0);
 
+        assert_eq!(cage.rmdir_syscall("/outside"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
         lindrustfinalize();
+    }
 
-        // Init again
+    pub fn ut_lind_fs_fdtable_growth() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let mut rlimit = Rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+
+        assert_eq!(cage.getrlimit(RLIMIT_NOFILE, &mut rlimit), 0);
+        assert_eq!(rlimit.rlim_cur, NOFILE_CUR);
+        assert_eq!(rlimit.rlim_max, NOFILE_MAX);
+
+        //looking up an fd past the allocated slots finds it closed, and allocates nothing
+        let allocated = cage.filedescriptortable.allocated();
+        assert_eq!(cage.close_syscall(MAXFD - 1), -(Errno::EBADF as i32));
+        assert_eq!(
+            cage.fcntl_syscall(MAXFD - 1, F_GETFD, 0),
+            -(Errno::EBADF as i32)
+        );
+        assert_eq!(cage.filedescriptortable.allocated(), allocated);
+
+        //fds are handed out up to the soft limit, and no further
+        let fd = cage.open_syscall("/fdtablefile", O_CREAT | O_RDWR, S_IRWXA);
+        let mut lastfd = fd;
+        loop {
+            let dupfd = cage.dup_syscall(fd, None);
+            if dupfd < 0 {
+                assert_eq!(dupfd, -(Errno::EMFILE as i32));
+                break;
+            }
+            lastfd = dupfd;
+        }
+        assert_eq!(lastfd, NOFILE_CUR as i32 - 1);
+        assert_eq!(
+            cage.dup2_syscall(fd, NOFILE_CUR as i32),
+            -(Errno::EBADF as i32)
+        );
+
+        //raising the limit grows the table, and a forked child gets the same limit
+        assert_eq!(
+            cage.setrlimit(RLIMIT_NOFILE, NOFILE_MAX + 1),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.setrlimit(RLIMIT_NOFILE, 2 * NOFILE_CUR), 0);
+        let mut pipefds = PipeArray {
+            readfd: -1,
+            writefd: -1,
+        };
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        assert_eq!(pipefds.readfd, NOFILE_CUR as i32);
+        assert_eq!(
+            cage.dup2_syscall(pipefds.readfd, 2 * NOFILE_CUR as i32 - 1),
+            2 * NOFILE_CUR as i32 - 1
+        );
+        assert_eq!(
+            cage.fcntl_syscall(fd, F_DUPFD, 2 * NOFILE_CUR as i32),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.fork_syscall(2), 0);
+        let cage2 = interface::cagetable_getref(2);
+        assert_eq!(cage2.getrlimit(RLIMIT_NOFILE, &mut rlimit), 0);
+        assert_eq!(rlimit.rlim_cur, 2 * NOFILE_CUR);
+        assert_eq!(cage2.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+
+        //poll looks at fds past what an fd_set holds
+        assert_eq!(cage.write_syscall(pipefds.writefd, str2cbuf("x"), 1), 1);
+        let mut pollfds = vec![PollStruct {
+            fd: 2 * NOFILE_CUR as i32 - 1,
+            events: POLLIN,
+            revents: 0,
+        }];
+        assert_eq!(
+            cage.poll_syscall(&mut pollfds, Some(interface::RustDuration::ZERO)),
+            1
+        );
+        assert_eq!(pollfds[0].revents, POLLIN);
+
+        //fds past the limit stay open when it is lowered, but no new ones are handed out there
+        assert_eq!(cage.setrlimit(RLIMIT_NOFILE, NOFILE_CUR), 0);
+        assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+        assert_eq!(cage.dup_syscall(fd, None), -(Errno::EMFILE as i32));
+        assert_eq!(cage.close_syscall(2 * NOFILE_CUR as i32 - 1), 0);
+
+        assert_eq!(cage.unlink_syscall("/fdtablefile"), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_fs_cancel_blocked() {
         lindrustinit(0);
         let cage = interface::cagetable_getref(1);
+        let mut buf = vec![0u8; 8];
+        let bufptr = buf.as_mut_ptr() as usize;
+        let mut pipefds = PipeArray {
+            readfd: -1,
+            writefd: -1,
+        };
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        let readfd = pipefds.readfd;
+        let mutex = cage.mutex_create_syscall();
+        let cv = cage.cond_create_syscall();
+
+        //a blocked read and a blocked condvar wait are both woken by cancelling the cage
+        let reader = std::thread::spawn(move || {
+            let cage = interface::cagetable_getref(1);
+            cage.read_syscall(readfd, bufptr as *mut u8, 8)
+        });
+        let waiter = std::thread::spawn(move || {
+            let cage = interface::cagetable_getref(1);
+            assert_eq!(cage.mutex_lock_syscall(mutex), 0);
+            let ret = cage.cond_wait_syscall(cv, mutex);
+            assert_eq!(cage.mutex_unlock_syscall(mutex), 0);
+            ret
+        });
+        interface::sleep(interface::RustDuration::from_millis(100));
+        lindcancelinit(1);
+        assert_eq!(reader.join().unwrap(), -(Errno::ECANCELED as i32));
+        assert_eq!(waiter.join().unwrap(), -(Errno::ECANCELED as i32));
 
-        // Check if /tmp is there
-        assert_eq!(cage.access_syscall("/tmp", F_OK), 0);
-        // Check if file is still there (it shouldn't be, assert no)
-        assert_eq!(cage.access_syscall(file_path, F_OK), -2);
+        //from then on calls give up rather than block, and those that don't block carry on
+        assert_eq!(
+            cage.read_syscall(readfd, buf.as_mut_ptr(), 8),
+            -(Errno::ECANCELED as i32)
+        );
+        assert_eq!(cage.write_syscall(pipefds.writefd, str2cbuf("x"), 1), 1);
+        assert_eq!(cage.read_syscall(readfd, buf.as_mut_ptr(), 8), 1);
 
+        assert_eq!(cage.close_syscall(readfd), 0);
+        assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
         lindrustfinalize();
     }
 }
@@ -12,6 +12,15 @@ pub mod ipc_tests {
         ut_lind_ipc_pipe();
         ut_lind_ipc_domain_socket();
         ut_lind_ipc_socketpair();
+        ut_lind_ipc_pipe_size();
+        ut_lind_ipc_pipe_reuse();
+        ut_lind_ipc_sysv_sem();
+        ut_lind_ipc_sysv_msg();
+        ut_lind_ipc_posix_mqueue();
+        ut_lind_ipc_shm_open();
+        ut_lind_ipc_futex();
+        ut_lind_ipc_shmctl();
+        ut_lind_ipc_mutex_cond_errors();
     }
 
     pub fn ut_lind_ipc_pipe() {
@@ -339,4 +348,815 @@ pub mod ipc_tests {
         assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
         lindrustfinalize();
     }
+
+    pub fn ut_lind_ipc_pipe_size() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let mut pipefds = PipeArray {
+            readfd: -1,
+            writefd: -1,
+        };
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        assert_eq!(
+            cage.fcntl_syscall(pipefds.readfd, F_GETPIPE_SZ, 0),
+            PIPE_CAPACITY as i32
+        );
+
+        //sizes are rounded up to a power of two number of pages within the allowed range
+        assert_eq!(cage.fcntl_syscall(pipefds.writefd, F_SETPIPE_SZ, 1), 4096);
+        assert_eq!(cage.fcntl_syscall(pipefds.readfd, F_GETPIPE_SZ, 0), 4096);
+        assert_eq!(
+            cage.fcntl_syscall(pipefds.writefd, F_SETPIPE_SZ, 2 * PIPE_MAX_CAPACITY as i32),
+            -(Errno::EPERM as i32)
+        );
+        assert_eq!(
+            cage.fcntl_syscall(pipefds.writefd, F_SETPIPE_SZ, -1),
+            -(Errno::EINVAL as i32)
+        );
+
+        //a writer blocked on the full pipe carries on once the pipe is grown
+        let writefd = pipefds.writefd;
+        let writer = std::thread::spawn(move || {
+            let cage2 = interface::cagetable_getref(1);
+            let data = vec![b'x'; 6000];
+            assert_eq!(cage2.write_syscall(writefd, data.as_ptr(), 6000), 6000);
+        });
+        interface::sleep(interface::RustDuration::from_millis(100));
+        assert!(!writer.is_finished());
+        assert_eq!(
+            cage.fcntl_syscall(pipefds.writefd, F_SETPIPE_SZ, 5000),
+            8192
+        );
+        writer.join().unwrap();
+
+        //the pipe can't shrink below what it holds, and resizing keeps its contents
+        assert_eq!(
+            cage.fcntl_syscall(pipefds.writefd, F_SETPIPE_SZ, 4096),
+            -(Errno::EBUSY as i32)
+        );
+        assert_eq!(
+            cage.fcntl_syscall(pipefds.writefd, F_SETPIPE_SZ, 16384),
+            16384
+        );
+        let mut buf = sizecbuf(8192);
+        assert_eq!(
+            cage.read_syscall(pipefds.readfd, buf.as_mut_ptr(), 8192),
+            6000
+        );
+        assert!(buf[..6000].iter().all(|byte| *byte == b'x'));
+
+        //only pipes have a capacity
+        let fd = cage.open_syscall("/pipesizefile", O_CREAT | O_RDWR, S_IRWXA);
+        assert_eq!(
+            cage.fcntl_syscall(fd, F_GETPIPE_SZ, 0),
+            -(Errno::EBADF as i32)
+        );
+
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.unlink_syscall("/pipesizefile"), 0);
+        assert_eq!(cage.close_syscall(pipefds.readfd), 0);
+        assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_ipc_pipe_reuse() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        //a pipe closed with data still in it gives its buffer up to the next pipe made
+        for _ in 0..4 {
+            let mut pipefds = PipeArray::default();
+            assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+            assert_eq!(cage.write_syscall(pipefds.writefd, str2cbuf("stale"), 5), 5);
+            assert_eq!(cage.close_syscall(pipefds.readfd), 0);
+            assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+        }
+
+        //the next pipe takes its buffer from the pool rather than allocating one, but none of
+        //what was left in it
+        let pooled = interface::pooled_buffers(PIPE_CAPACITY);
+        assert!(pooled > 0);
+        let mut pipefds = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        assert_eq!(interface::pooled_buffers(PIPE_CAPACITY), pooled - 1);
+        assert_eq!(cage.write_syscall(pipefds.writefd, str2cbuf("fresh"), 5), 5);
+        let mut buf = sizecbuf(16);
+        assert_eq!(cage.read_syscall(pipefds.readfd, buf.as_mut_ptr(), 16), 5);
+        assert_eq!(&buf[..5], b"fresh");
+
+        assert_eq!(cage.close_syscall(pipefds.readfd), 0);
+        assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+
+        //a unix socket connection takes its buffers, sized by SO_SNDBUF and SO_RCVBUF, from the
+        //pool as well
+        let probefd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+        let (mut sndbuf, mut rcvbuf) = (0, 0);
+        assert_eq!(
+            cage.getsockopt_syscall(probefd, SOL_SOCKET, SO_SNDBUF, &mut sndbuf),
+            0
+        );
+        assert_eq!(
+            cage.getsockopt_syscall(probefd, SOL_SOCKET, SO_RCVBUF, &mut rcvbuf),
+            0
+        );
+        assert_eq!(cage.close_syscall(probefd), 0);
+        let mut capacities = vec![
+            unix_sockpipe_capacity(sndbuf),
+            unix_sockpipe_capacity(rcvbuf),
+        ];
+        capacities.sort();
+        capacities.dedup();
+        let pooled = || -> usize {
+            capacities
+                .iter()
+                .map(|capacity| interface::pooled_buffers(*capacity))
+                .sum()
+        };
+        let mut socketpair = interface::SockPair::default();
+        for _ in 0..2 {
+            assert_eq!(
+                Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut socketpair),
+                0
+            );
+            assert_eq!(cage.close_syscall(socketpair.sock1), 0);
+            assert_eq!(cage.close_syscall(socketpair.sock2), 0);
+        }
+        let before = pooled();
+        assert_eq!(
+            Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut socketpair),
+            0
+        );
+        assert_eq!(pooled(), before - 2);
+        assert_eq!(cage.close_syscall(socketpair.sock1), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock2), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_ipc_sysv_sem() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let key = 3860;
+
+        assert_eq!(cage.semget_syscall(key, 2, 0), -(Errno::ENOENT as i32));
+        assert_eq!(
+            cage.semget_syscall(key, 0, IPC_CREAT),
+            -(Errno::EINVAL as i32)
+        );
+        let semid = cage.semget_syscall(key, 2, IPC_CREAT | 0o666);
+        assert!(semid > 0);
+        assert_eq!(cage.semget_syscall(key, 2, 0), semid);
+        assert_eq!(cage.semget_syscall(key, 3, 0), -(Errno::EINVAL as i32));
+        assert_eq!(
+            cage.semget_syscall(key, 2, IPC_CREAT | IPC_EXCL),
+            -(Errno::EEXIST as i32)
+        );
+
+        assert_eq!(cage.semctl_syscall(semid, 0, SETVAL, 1), 0);
+        assert_eq!(cage.semctl_syscall(semid, 0, GETVAL, 0), 1);
+        assert_eq!(cage.semctl_syscall(semid, 1, GETVAL, 0), 0);
+        assert_eq!(
+            cage.semctl_syscall(semid, 2, GETVAL, 0),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.semctl_syscall(semid, 0, SETVAL, SEMVMX + 1),
+            -(Errno::ERANGE as i32)
+        );
+
+        //the whole array applies at once, so a failing second operation leaves the first undone
+        let sops = [
+            SembufStruct {
+                sem_num: 0,
+                sem_op: -1,
+                sem_flg: 0,
+            },
+            SembufStruct {
+                sem_num: 1,
+                sem_op: -1,
+                sem_flg: IPC_NOWAIT as i16,
+            },
+        ];
+        assert_eq!(cage.semop_syscall(semid, &sops), -(Errno::EAGAIN as i32));
+        assert_eq!(cage.semctl_syscall(semid, 0, GETVAL, 0), 1);
+        let outofrange = [SembufStruct {
+            sem_num: 2,
+            sem_op: 1,
+            sem_flg: 0,
+        }];
+        assert_eq!(
+            cage.semop_syscall(semid, &outofrange),
+            -(Errno::EFBIG as i32)
+        );
+
+        //a decrement blocks until another operation makes it possible
+        let waiter = std::thread::spawn(move || {
+            let cage2 = interface::cagetable_getref(1);
+            let wait = [SembufStruct {
+                sem_num: 1,
+                sem_op: -1,
+                sem_flg: 0,
+            }];
+            assert_eq!(cage2.semop_syscall(semid, &wait), 0);
+        });
+        interface::sleep(interface::RustDuration::from_millis(100));
+        assert!(!waiter.is_finished());
+        let post = [SembufStruct {
+            sem_num: 1,
+            sem_op: 1,
+            sem_flg: 0,
+        }];
+        assert_eq!(cage.semop_syscall(semid, &post), 0);
+        waiter.join().unwrap();
+        assert_eq!(cage.semctl_syscall(semid, 1, GETVAL, 0), 0);
+
+        //SEM_UNDO operations are reversed when the cage that made them exits
+        assert_eq!(cage.fork_syscall(2), 0);
+        let cage2 = interface::cagetable_getref(2);
+        let undoable = [SembufStruct {
+            sem_num: 0,
+            sem_op: -1,
+            sem_flg: SEM_UNDO as i16,
+        }];
+        assert_eq!(cage2.semop_syscall(semid, &undoable), 0);
+        assert_eq!(cage.semctl_syscall(semid, 0, GETVAL, 0), 0);
+        assert_eq!(cage2.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(cage.semctl_syscall(semid, 0, GETVAL, 0), 1);
+
+        //removing the set wakes anyone still waiting on it
+        let waiter = std::thread::spawn(move || {
+            let cage2 = interface::cagetable_getref(1);
+            let wait = [SembufStruct {
+                sem_num: 1,
+                sem_op: -1,
+                sem_flg: 0,
+            }];
+            assert_eq!(cage2.semop_syscall(semid, &wait), -(Errno::EIDRM as i32));
+        });
+        interface::sleep(interface::RustDuration::from_millis(100));
+        assert_eq!(cage.semctl_syscall(semid, 0, IPC_RMID, 0), 0);
+        waiter.join().unwrap();
+        assert_eq!(
+            cage.semctl_syscall(semid, 0, GETVAL, 0),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.semget_syscall(key, 2, 0), -(Errno::ENOENT as i32));
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_ipc_sysv_msg() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let key = 3861;
+
+        //a message buffer is a long message type followed by the message text
+        fn msgbuf(mtype: i64, text: &[u8]) -> Vec<u8> {
+            let mut buf = mtype.to_ne_bytes().to_vec();
+            buf.extend_from_slice(text);
+            buf
+        }
+
+        assert_eq!(cage.msgget_syscall(key, 0), -(Errno::ENOENT as i32));
+        let msqid = cage.msgget_syscall(key, IPC_CREAT | 0o600);
+        assert!(msqid > 0);
+        assert_eq!(cage.msgget_syscall(key, IPC_CREAT), msqid);
+        assert_eq!(
+            cage.msgget_syscall(key, IPC_CREAT | IPC_EXCL),
+            -(Errno::EEXIST as i32)
+        );
+
+        let bad = msgbuf(0, b"x");
+        assert_eq!(cage.msgsnd_syscall(msqid, &bad, 0), -(Errno::EINVAL as i32));
+        for (mtype, text) in [(3, &b"three"[..]), (1, b"one"), (2, b"two")] {
+            let buf = msgbuf(mtype, text);
+            assert_eq!(cage.msgsnd_syscall(msqid, &buf, 0), 0);
+        }
+
+        let mut stat = MsqidsStruct::default();
+        assert_eq!(cage.msgctl_syscall(msqid, IPC_STAT, Some(&mut stat)), 0);
+        assert_eq!(stat.msg_qnum, 3);
+        assert_eq!(stat.msg_cbytes, 11);
+        assert_eq!(stat.msg_qbytes, MSGMNB as u64);
+        assert_eq!(stat.msg_perm.mode, 0o600);
+
+        //receive by exact type, by lowest type up to a bound, and excluding a type
+        let mut rbuf = vec![0u8; 8 + 16];
+        assert_eq!(cage.msgrcv_syscall(msqid, &mut rbuf, 2, 0), 3);
+        assert_eq!(&rbuf[..11], &msgbuf(2, b"two")[..]);
+        assert_eq!(cage.msgrcv_syscall(msqid, &mut rbuf, -3, 0), 3);
+        assert_eq!(&rbuf[..11], &msgbuf(1, b"one")[..]);
+        assert_eq!(
+            cage.msgrcv_syscall(msqid, &mut rbuf, 3, MSG_EXCEPT | IPC_NOWAIT),
+            -(Errno::ENOMSG as i32)
+        );
+
+        //a message too long for the buffer stays queued unless truncation is allowed
+        assert_eq!(
+            cage.msgrcv_syscall(msqid, &mut rbuf[..8 + 2], 0, 0),
+            -(Errno::EBIG as i32)
+        );
+        assert_eq!(
+            cage.msgrcv_syscall(msqid, &mut rbuf[..8 + 2], 0, MSG_NOERROR),
+            2
+        );
+        assert_eq!(&rbuf[..10], &msgbuf(3, b"th")[..]);
+        assert_eq!(
+            cage.msgrcv_syscall(msqid, &mut rbuf, 0, IPC_NOWAIT),
+            -(Errno::ENOMSG as i32)
+        );
+
+        //a receiver blocks until a matching message arrives
+        let receiver = std::thread::spawn(move || {
+            let cage2 = interface::cagetable_getref(1);
+            let mut rbuf = vec![0u8; 8 + 16];
+            assert_eq!(cage2.msgrcv_syscall(msqid, &mut rbuf, 7, 0), 5);
+            assert_eq!(&rbuf[..13], &msgbuf(7, b"seven")[..]);
+        });
+        interface::sleep(interface::RustDuration::from_millis(100));
+        assert!(!receiver.is_finished());
+        let buf = msgbuf(7, b"seven");
+        assert_eq!(cage.msgsnd_syscall(msqid, &buf, 0), 0);
+        receiver.join().unwrap();
+
+        //a sender blocks while the queue is over its byte limit
+        stat.msg_qbytes = 8;
+        assert_eq!(cage.msgctl_syscall(msqid, IPC_SET, Some(&mut stat)), 0);
+        let buf = msgbuf(1, b"12345678");
+        assert_eq!(cage.msgsnd_syscall(msqid, &buf, 0), 0);
+        assert_eq!(
+            cage.msgsnd_syscall(msqid, &buf[..8 + 1], IPC_NOWAIT),
+            -(Errno::EAGAIN as i32)
+        );
+        let sender = std::thread::spawn(move || {
+            let cage2 = interface::cagetable_getref(1);
+            let buf = msgbuf(1, b"late");
+            assert_eq!(cage2.msgsnd_syscall(msqid, &buf, 0), 0);
+        });
+        interface::sleep(interface::RustDuration::from_millis(100));
+        assert!(!sender.is_finished());
+        assert_eq!(cage.msgrcv_syscall(msqid, &mut rbuf, 0, 0), 8);
+        sender.join().unwrap();
+        assert_eq!(cage.msgrcv_syscall(msqid, &mut rbuf, 0, 0), 4);
+
+        //removing the queue wakes anyone still waiting on it
+        let receiver = std::thread::spawn(move || {
+            let cage2 = interface::cagetable_getref(1);
+            let mut rbuf = vec![0u8; 8 + 16];
+            assert_eq!(
+                cage2.msgrcv_syscall(msqid, &mut rbuf, 0, 0),
+                -(Errno::EIDRM as i32)
+            );
+        });
+        interface::sleep(interface::RustDuration::from_millis(100));
+        assert_eq!(cage.msgctl_syscall(msqid, IPC_RMID, None), 0);
+        receiver.join().unwrap();
+        assert_eq!(
+            cage.msgctl_syscall(msqid, IPC_STAT, Some(&mut stat)),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.msgget_syscall(key, 0), -(Errno::ENOENT as i32));
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_ipc_posix_mqueue() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        assert_eq!(
+            cage.mq_open_syscall("noslash", O_RDWR | O_CREAT, 0o600, None),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.mq_open_syscall("/a/b", O_RDWR | O_CREAT, 0o600, None),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.mq_open_syscall("/lindmq", O_RDWR, 0, None),
+            -(Errno::ENOENT as i32)
+        );
+
+        let attr = MqAttrStruct {
+            mq_maxmsg: 2,
+            mq_msgsize: 16,
+            ..Default::default()
+        };
+        let mqd = cage.mq_open_syscall("/lindmq", O_RDWR | O_CREAT, 0o600, Some(&attr));
+        assert!(mqd >= 0);
+        assert_eq!(
+            cage.mq_open_syscall("/lindmq", O_RDWR | O_CREAT | O_EXCL, 0o600, None),
+            -(Errno::EEXIST as i32)
+        );
+        let wrmqd = cage.mq_open_syscall("/lindmq", O_WRONLY, 0, None);
+        assert!(wrmqd >= 0);
+
+        //messages come out highest priority first
+        assert_eq!(cage.mq_send_syscall(wrmqd, b"low", 1), 0);
+        assert_eq!(cage.mq_send_syscall(wrmqd, b"high", 5), 0);
+        assert_eq!(
+            cage.mq_send_syscall(wrmqd, &[0u8; 17], 1),
+            -(Errno::EMSGSIZE as i32)
+        );
+
+        let mut oldattr = MqAttrStruct::default();
+        assert_eq!(cage.mq_getsetattr_syscall(mqd, None, Some(&mut oldattr)), 0);
+        assert_eq!(oldattr.mq_maxmsg, 2);
+        assert_eq!(oldattr.mq_msgsize, 16);
+        assert_eq!(oldattr.mq_curmsgs, 2);
+        assert_eq!(oldattr.mq_flags, 0);
+
+        //a full queue is readable but not writable, and won't take more without blocking
+        let mut pollfds = vec![PollStruct {
+            fd: mqd,
+            events: POLLIN | POLLOUT,
+            revents: 0,
+        }];
+        assert_eq!(
+            cage.poll_syscall(&mut pollfds, Some(interface::RustDuration::ZERO)),
+            1
+        );
+        assert_eq!(pollfds[0].revents, POLLIN);
+        let nonblock = MqAttrStruct {
+            mq_flags: O_NONBLOCK as i64,
+            ..Default::default()
+        };
+        assert_eq!(cage.mq_getsetattr_syscall(wrmqd, Some(&nonblock), None), 0);
+        assert_eq!(
+            cage.mq_send_syscall(wrmqd, b"more", 1),
+            -(Errno::EAGAIN as i32)
+        );
+
+        let mut buf = sizecbuf(16);
+        let mut prio = 0;
+        assert_eq!(
+            cage.mq_receive_syscall(mqd, &mut buf[..8], Some(&mut prio)),
+            -(Errno::EMSGSIZE as i32)
+        );
+        assert_eq!(
+            cage.mq_receive_syscall(wrmqd, &mut buf, Some(&mut prio)),
+            -(Errno::EBADF as i32)
+        );
+        assert_eq!(cage.mq_receive_syscall(mqd, &mut buf, Some(&mut prio)), 4);
+        assert_eq!((&buf[..4], prio), (&b"high"[..], 5));
+        assert_eq!(cage.mq_receive_syscall(mqd, &mut buf, Some(&mut prio)), 3);
+        assert_eq!((&buf[..3], prio), (&b"low"[..], 1));
+
+        //a receiver blocks until a message arrives
+        let receiver = std::thread::spawn(move || {
+            let cage2 = interface::cagetable_getref(1);
+            let mut buf = sizecbuf(16);
+            assert_eq!(cage2.mq_receive_syscall(mqd, &mut buf, None), 5);
+            assert_eq!(&buf[..5], b"later");
+        });
+        interface::sleep(interface::RustDuration::from_millis(100));
+        assert!(!receiver.is_finished());
+        assert_eq!(cage.mq_send_syscall(wrmqd, b"later", 0), 0);
+        receiver.join().unwrap();
+
+        //only one registration at a time, used up by a message arriving on the empty queue
+        let sev = interface::SigeventStruct {
+            sigev_notify: SIGEV_NONE,
+            ..Default::default()
+        };
+        assert_eq!(cage.mq_notify_syscall(mqd, Some(&sev)), 0);
+        assert_eq!(
+            cage.mq_notify_syscall(wrmqd, Some(&sev)),
+            -(Errno::EBUSY as i32)
+        );
+        assert_eq!(cage.mq_send_syscall(wrmqd, b"ping", 0), 0);
+        assert_eq!(cage.mq_notify_syscall(wrmqd, Some(&sev)), 0);
+        assert_eq!(cage.close_syscall(wrmqd), 0);
+        assert_eq!(cage.mq_notify_syscall(mqd, Some(&sev)), 0);
+
+        //an unlinked queue stays usable through descriptors that are still open
+        assert_eq!(cage.mq_unlink_syscall("/lindmq"), 0);
+        assert_eq!(cage.mq_unlink_syscall("/lindmq"), -(Errno::ENOENT as i32));
+        assert_eq!(
+            cage.mq_open_syscall("/lindmq", O_RDWR, 0, None),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(cage.mq_receive_syscall(mqd, &mut buf, None), 4);
+        assert_eq!(&buf[..4], b"ping");
+
+        assert_eq!(cage.close_syscall(mqd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_ipc_shm_open() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        assert_eq!(
+            cage.shm_open_syscall("/a/b", O_RDWR | O_CREAT, 0o600),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.shm_open_syscall("/lindshm", O_WRONLY | O_CREAT, 0o600),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.shm_open_syscall("/lindshm", O_RDWR, 0),
+            -(Errno::ENOENT as i32)
+        );
+
+        let fd = cage.shm_open_syscall("/lindshm", O_RDWR | O_CREAT | O_EXCL, 0o600);
+        assert!(fd >= 0);
+        assert_eq!(cage.fcntl_syscall(fd, F_GETFD, 0), O_CLOEXEC);
+        assert_eq!(
+            cage.shm_open_syscall("/lindshm", O_RDWR | O_CREAT | O_EXCL, 0o600),
+            -(Errno::EEXIST as i32)
+        );
+        assert_eq!(cage.ftruncate_syscall(fd, 4096), 0);
+
+        //the object is an ordinary file under /dev/shm
+        let mut statdata = StatData::default();
+        assert_eq!(cage.stat_syscall("/dev/shm/lindshm", &mut statdata), 0);
+        assert_eq!(statdata.st_size, 4096);
+
+        //another cage opening the same name maps the same memory
+        assert_eq!(cage.fork_syscall(2), 0);
+        let cage2 = interface::cagetable_getref(2);
+        let fd2 = cage2.shm_open_syscall("/lindshm", O_RDWR, 0);
+        assert!(fd2 >= 0);
+
+        //reserve host pages first so MAP_FIXED can't land on a mapping the test process already has
+        let reserve = || {
+            let addr = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    4096,
+                    libc::PROT_NONE,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                    -1,
+                    0,
+                )
+            };
+            assert_ne!(addr, libc::MAP_FAILED);
+            addr as *mut u8
+        };
+        let mapaddr1 = reserve();
+        let mapaddr2 = reserve();
+        let prot = PROT_READ | PROT_WRITE;
+        assert_eq!(
+            cage.mmap_syscall(mapaddr1, 4096, prot, MAP_SHARED | MAP_FIXED, fd, 0) as u32,
+            mapaddr1 as usize as u32
+        );
+        assert_eq!(
+            cage2.mmap_syscall(mapaddr2, 4096, prot, MAP_SHARED | MAP_FIXED, fd2, 0) as u32,
+            mapaddr2 as usize as u32
+        );
+        let mapped1 = unsafe { std::slice::from_raw_parts_mut(mapaddr1, 4096) };
+        let mapped2 = unsafe { std::slice::from_raw_parts_mut(mapaddr2, 4096) };
+        mapped2[100..105].copy_from_slice(b"hello");
+        assert_eq!(&mapped1[100..105], b"hello");
+
+        //unlinking removes the name but not memory that is still mapped
+        assert_eq!(cage.shm_unlink_syscall("/lindshm"), 0);
+        assert_eq!(cage.shm_unlink_syscall("/lindshm"), -(Errno::ENOENT as i32));
+        assert_eq!(
+            cage.shm_open_syscall("/lindshm", O_RDWR, 0),
+            -(Errno::ENOENT as i32)
+        );
+        mapped1[0] = b'x';
+        assert_eq!(mapped2[0], b'x');
+
+        cage2.munmap_syscall(mapaddr2, 4096);
+        assert_eq!(cage2.close_syscall(fd2), 0);
+        assert_eq!(cage2.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        cage.munmap_syscall(mapaddr1, 4096);
+        assert_eq!(cage.close_syscall(fd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_ipc_futex() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let word: &'static interface::RustAtomicU32 =
+            Box::leak(Box::new(interface::RustAtomicU32::new(0)));
+        let uaddr = word.as_ptr() as usize;
+        let wait = FUTEX_WAIT | FUTEX_PRIVATE_FLAG;
+        let wake = FUTEX_WAKE | FUTEX_PRIVATE_FLAG;
+
+        //waiting only sleeps while the word still holds the expected value
+        assert_eq!(
+            cage.futex_syscall(uaddr as *mut u32, wait, 1, 0, None, None),
+            -(Errno::EAGAIN as i32)
+        );
+        let timeout = Some(interface::RustDuration::from_millis(50));
+        assert_eq!(
+            cage.futex_syscall(uaddr as *mut u32, wait, 0, 0, timeout, None),
+            -(Errno::ETIMEDOUT as i32)
+        );
+        assert_eq!(
+            cage.futex_syscall(uaddr as *mut u32, wake, 1, 0, None, None),
+            0
+        );
+        assert_eq!(
+            cage.futex_syscall((uaddr + 1) as *mut u32, wait, 0, 0, None, None),
+            -(Errno::EINVAL as i32)
+        );
+
+        //a waiter sleeps until the word changes and it is woken
+        let waiter = std::thread::spawn(move || {
+            let cage = interface::cagetable_getref(1);
+            assert_eq!(
+                cage.futex_syscall(uaddr as *mut u32, wait, 0, 0, None, None),
+                0
+            );
+        });
+        interface::sleep(interface::RustDuration::from_millis(100));
+        assert!(!waiter.is_finished());
+        word.store(1, interface::RustAtomicOrdering::SeqCst);
+        assert_eq!(
+            cage.futex_syscall(uaddr as *mut u32, wake, 1, 0, None, None),
+            1
+        );
+        waiter.join().unwrap();
+
+        //requeueing wakes some waiters and moves the rest to another word without waking them
+        let word2: &'static interface::RustAtomicU32 =
+            Box::leak(Box::new(interface::RustAtomicU32::new(0)));
+        let uaddr2 = word2.as_ptr() as usize;
+        let waiters: Vec<_> = (0..3)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    let cage = interface::cagetable_getref(1);
+                    assert_eq!(
+                        cage.futex_syscall(uaddr as *mut u32, wait, 1, 0, None, None),
+                        0
+                    );
+                })
+            })
+            .collect();
+        interface::sleep(interface::RustDuration::from_millis(100));
+        assert_eq!(
+            cage.futex_syscall(
+                uaddr as *mut u32,
+                FUTEX_REQUEUE | FUTEX_PRIVATE_FLAG,
+                1,
+                2,
+                None,
+                Some(uaddr2 as *mut u32)
+            ),
+            1
+        );
+        assert_eq!(
+            cage.futex_syscall(uaddr as *mut u32, wake, 3, 0, None, None),
+            0
+        );
+        assert_eq!(
+            cage.futex_syscall(uaddr2 as *mut u32, wake, 3, 0, None, None),
+            2
+        );
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+        assert_eq!(
+            cage.futex_syscall(uaddr as *mut u32, FUTEX_REQUEUE, 1, 1, None, None),
+            -(Errno::EFAULT as i32)
+        );
+
+        //a word in shared memory wakes waiters in any cage that has the segment attached
+        let shmid = cage.shmget_syscall(3865, 1024, 0o666 | IPC_CREAT);
+        let shmaddr = cage.shmat_syscall(shmid, 0xfffff000 as *mut u8, 0);
+        assert_ne!(shmaddr, -1);
+        let shmword = shmaddr as u32 as usize;
+        assert_eq!(cage.fork_syscall(2), 0);
+        let child = std::thread::spawn(move || {
+            let cage2 = interface::cagetable_getref(2);
+            assert_eq!(
+                cage2.futex_syscall(shmword as *mut u32, FUTEX_WAIT, 0, 0, None, None),
+                0
+            );
+            assert_eq!(cage2.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        });
+        interface::sleep(interface::RustDuration::from_millis(100));
+        //a private wake only looks at the waking cage's own queue
+        assert_eq!(
+            cage.futex_syscall(shmword as *mut u32, wake, 1, 0, None, None),
+            0
+        );
+        unsafe { *(shmword as *mut u32) = 1 };
+        assert_eq!(
+            cage.futex_syscall(shmword as *mut u32, FUTEX_WAKE, 1, 0, None, None),
+            1
+        );
+        child.join().unwrap();
+        assert_eq!(cage.shmctl_syscall(shmid, IPC_RMID, None), 0);
+        assert_eq!(cage.shmdt_syscall(0xfffff000 as *mut u8), shmid);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_ipc_shmctl() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let key = 3866;
+        let shmaddr = 0xfffff000 as *mut u8;
+
+        let shmid = cage.shmget_syscall(key, 4096, IPC_CREAT | 0o640);
+        assert!(shmid > 0);
+        let mut stat = ShmidsStruct::default();
+        assert_eq!(cage.shmctl_syscall(shmid, IPC_STAT, Some(&mut stat)), 0);
+        assert_eq!(stat.shm_segsz, 4096);
+        assert_eq!(stat.shm_nattch, 0);
+        assert_eq!(stat.shm_cpid, 1);
+        assert_eq!(stat.shm_lpid, 0);
+        assert_eq!(stat.shm_perm.__key, key);
+        assert_eq!(stat.shm_perm.mode, 0o640);
+        assert_eq!(
+            cage.shmctl_syscall(shmid, IPC_STAT, None),
+            -(Errno::EFAULT as i32)
+        );
+
+        //attaching and detaching show up in the live state
+        assert_ne!(cage.shmat_syscall(shmid, shmaddr, 0), -1);
+        assert_eq!(cage.fork_syscall(2), 0);
+        let cage2 = interface::cagetable_getref(2);
+        assert_eq!(cage.shmctl_syscall(shmid, IPC_STAT, Some(&mut stat)), 0);
+        assert_eq!(stat.shm_nattch, 2);
+        assert_eq!(stat.shm_lpid, 1);
+        assert_ne!(stat.shm_atime, 0);
+        assert_eq!(cage2.shmdt_syscall(shmaddr), shmid);
+        assert_eq!(cage.shmctl_syscall(shmid, IPC_STAT, Some(&mut stat)), 0);
+        assert_eq!(stat.shm_nattch, 1);
+        assert_eq!(stat.shm_lpid, 2);
+        assert_ne!(stat.shm_dtime, 0);
+
+        //IPC_SET changes the owner and permission bits only
+        let mut newstat = stat;
+        newstat.shm_perm.uid = 5;
+        newstat.shm_perm.mode = 0o600;
+        newstat.shm_segsz = 1;
+        assert_eq!(cage.shmctl_syscall(shmid, IPC_SET, Some(&mut newstat)), 0);
+        assert_eq!(cage.shmctl_syscall(shmid, IPC_STAT, Some(&mut stat)), 0);
+        assert_eq!(stat.shm_perm.uid, 5);
+        assert_eq!(stat.shm_perm.cuid, DEFAULT_UID);
+        assert_eq!(stat.shm_perm.mode, 0o600);
+        assert_eq!(stat.shm_segsz, 4096);
+
+        //a segment marked for removal lives on while attached, but its key is released
+        assert_eq!(cage.shmctl_syscall(shmid, IPC_RMID, None), 0);
+        assert_eq!(cage.shmctl_syscall(shmid, IPC_STAT, Some(&mut stat)), 0);
+        assert_eq!(stat.shm_perm.mode, 0o600 | SHM_DEST as u16);
+        assert_eq!(stat.shm_perm.__key, IPC_PRIVATE);
+        assert_eq!(cage.shmget_syscall(key, 4096, 0), -(Errno::ENOENT as i32));
+        let newshmid = cage.shmget_syscall(key, 4096, IPC_CREAT | 0o600);
+        assert!(newshmid > 0);
+        assert_ne!(newshmid, shmid);
+
+        //the last detach destroys it without touching the new segment under the same key
+        assert_eq!(cage.shmdt_syscall(shmaddr), shmid);
+        assert_eq!(
+            cage.shmctl_syscall(shmid, IPC_STAT, Some(&mut stat)),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.shmget_syscall(key, 4096, 0), newshmid);
+        assert_eq!(cage.shmctl_syscall(newshmid, IPC_RMID, None), 0);
+        assert_eq!(
+            cage.shmctl_syscall(newshmid, IPC_STAT, Some(&mut stat)),
+            -(Errno::EINVAL as i32)
+        );
+
+        assert_eq!(cage2.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_ipc_mutex_cond_errors() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        //the pthread calls hand back their error numbers, which come out as -errno like any other
+        let mutex = cage.mutex_create_syscall();
+        assert!(mutex >= 0);
+        assert_eq!(cage.mutex_lock_syscall(mutex), 0);
+        assert_eq!(cage.mutex_trylock_syscall(mutex), -(Errno::EBUSY as i32));
+        assert_eq!(cage.mutex_unlock_syscall(mutex), 0);
+        assert_eq!(cage.mutex_trylock_syscall(mutex), 0);
+
+        let cv = cage.cond_create_syscall();
+        assert!(cv >= 0);
+        //a deadline long gone times out straight away
+        assert_eq!(
+            cage.cond_timedwait_syscall(cv, mutex, interface::RustDuration::from_secs(1)),
+            -(Errno::ETIMEDOUT as i32)
+        );
+        assert_eq!(cage.cond_signal_syscall(cv), 0);
+        assert_eq!(cage.mutex_unlock_syscall(mutex), 0);
+
+        assert_eq!(cage.cond_destroy_syscall(cv), 0);
+        assert_eq!(cage.mutex_destroy_syscall(mutex), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
 }
@@ -25,6 +25,10 @@ mod main_tests {
         lindrustinit(0);
         {
             let cage = interface::cagetable_getref(1);
+            //the /dev/shm mount would keep /dev from being removed
+            assert_eq!(cage.umount_syscall("/dev/shm"), 0);
+            //and procfs regenerates whatever is deleted from it, so the walk would never finish
+            assert_eq!(cage.umount_syscall("/proc"), 0);
             crate::lib_fs_utils::lind_deltree(&cage, "/");
             assert_eq!(cage.mkdir_syscall("/dev", S_IRWXA), 0);
             assert_eq!(
@@ -59,6 +63,22 @@ mod main_tests {
                 ),
                 0
             );
+            assert_eq!(
+                cage.mknod_syscall(
+                    "/dev/full",
+                    S_IFCHR as u32 | 0o777,
+                    makedev(&DevNo { major: 1, minor: 7 })
+                ),
+                0
+            );
+            assert_eq!(
+                cage.mknod_syscall(
+                    "/dev/tty",
+                    S_IFCHR as u32 | 0o777,
+                    makedev(&DevNo { major: 5, minor: 0 })
+                ),
+                0
+            );
             assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
         }
         lindrustfinalize();
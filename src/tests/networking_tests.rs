@@ -2,7 +2,12 @@
 pub mod net_tests {
     use super::super::*;
     use crate::interface;
-    use crate::safeposix::{cage::*, dispatcher::*, filesystem};
+    use crate::safeposix::{
+        cage::*,
+        dispatcher::*,
+        filesystem,
+        net::{mux_port, NET_DEVICES, NET_METADATA, TCPPORT},
+    };
     use libc::c_void;
     use std::mem::size_of;
     use std::sync::{Arc, Barrier};
@@ -30,6 +35,30 @@ pub mod net_tests {
         ut_lind_net_domain_socket();
         ut_lind_net_epoll();
         ut_lind_net_writev();
+        ut_lind_net_msghdr();
+        ut_lind_net_sockaddr_abi();
+        ut_lind_net_fionread();
+        ut_lind_net_ifreq_ioctls();
+        ut_lind_net_fcntl_nonblocking();
+        ut_lind_net_blocked_recv_leaves_fd_free();
+        ut_lind_net_full_duplex();
+        ut_lind_net_select_poll_sleep_until_ready();
+        ut_lind_net_poll_many();
+        ut_lind_net_select_socket_set_changes();
+        ut_lind_net_socket_addresses();
+        ut_lind_net_select_nfds();
+        ut_lind_net_poll_ignored_and_invalid();
+        ut_lind_net_epoll_close_deregisters();
+        ut_lind_net_send_partial();
+        ut_lind_net_unix_stale_path();
+        ut_lind_net_async_signals();
+        ut_lind_net_restart_signals();
+        ut_lind_net_connect_timeout();
+        ut_lind_net_accept_wakeup();
+        ut_lind_net_watched_fds_limit();
+        ut_lind_net_listener_close_teardown();
+        ut_lind_net_time_wait_reuseaddr();
+        ut_lind_net_shutdown_read();
     }
 
     pub fn ut_lind_net_bind() {
@@ -1276,7 +1305,15 @@ pub mod net_tests {
             assert_eq!(cage2.netshutdown_syscall(fd, SHUT_RD), 0);
             assert_eq!(cage2.send_syscall(fd, str2cbuf("random string"), 13, 0), 13);
             assert_eq!(cage2.netshutdown_syscall(fd, SHUT_RDWR), 0);
+            //the descriptor stays open, at the end of the stream and no longer writable
+            let mut buf = [0u8; 16];
+            assert_eq!(cage2.recv_syscall(fd, buf.as_mut_ptr(), 16, 0), 0);
+            assert_eq!(
+                cage2.send_syscall(fd, str2cbuf("random string"), 13, MSG_NOSIGNAL),
+                -(Errno::EPIPE as i32)
+            );
             assert_ne!(cage2.netshutdown_syscall(fd, SHUT_RDWR), 0); //should fail
+            assert_eq!(cage2.close_syscall(fd), 0);
 
             assert_eq!(cage2.close_syscall(serversockfd), 0);
             assert_eq!(cage2.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
@@ -2355,4 +2392,1723 @@ pub mod net_tests {
         assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
         lindrustfinalize();
     }
+
+    pub fn ut_lind_net_msghdr() {
+        lindrustinit(0);
+
+        let mut name = interface::SockaddrStorage::default();
+        let mut control = [0u8; 64];
+        let mut data = [0u8; 8];
+        let mut iov = interface::IovecStruct {
+            iov_base: data.as_mut_ptr() as *mut c_void,
+            iov_len: data.len(),
+        };
+        let mut msghdr = interface::MsghdrStruct {
+            msg_name: &mut name as *mut interface::SockaddrStorage as *mut u8,
+            msg_namelen: size_of::<interface::SockaddrStorage>() as u32,
+            msg_iov: &mut iov,
+            msg_iovlen: 1,
+            msg_control: control.as_mut_ptr(),
+            msg_controllen: control.len(),
+            msg_flags: 0,
+        };
+        let arg = Arg {
+            dispatch_msghdrstruct: &mut msghdr,
+        };
+        assert_eq!(interface::get_msghdr(arg).unwrap().msg_iovlen, 1);
+        assert_eq!(interface::get_msghdr_iovecs(&msghdr).len(), 1);
+        let nullarg = Arg {
+            dispatch_msghdrstruct: std::ptr::null_mut(),
+        };
+        assert_eq!(
+            interface::get_msghdr(nullarg).err(),
+            Some(-(Errno::EFAULT as i32))
+        );
+
+        //what recvmsg copies out reads back the same through the sendmsg side
+        let sockaddr = interface::GenSockaddr::V4(interface::SockaddrV4 {
+            sin_family: AF_INET as u16,
+            sin_port: 50131u16.to_be(),
+            sin_addr: interface::V4Addr {
+                s_addr: u32::from_ne_bytes([127, 0, 0, 1]),
+            },
+            padding: 0,
+        });
+        let cmsgs = vec![
+            (SOL_SOCKET, 1, vec![1, 2, 3, 4]),
+            (SOL_SOCKET, 2, vec![5; 12]),
+        ];
+        interface::copy_out_msghdr(&mut msghdr, Some(sockaddr), &cmsgs, 0);
+        assert_eq!(msghdr.msg_flags, 0);
+        assert_eq!(
+            msghdr.msg_namelen,
+            size_of::<interface::SockaddrV4>() as u32
+        );
+        assert_eq!(
+            msghdr.msg_controllen,
+            interface::cmsg_space(4) + interface::cmsg_space(12)
+        );
+        assert_eq!(
+            interface::get_msghdr_sockaddr(&msghdr).unwrap(),
+            Some(sockaddr)
+        );
+        let parsed = interface::iterate_cmsgs(&msghdr).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].0.cmsg_len, interface::cmsg_len(4));
+        assert_eq!((parsed[0].0.cmsg_type, parsed[0].1), (1, &[1, 2, 3, 4][..]));
+        assert_eq!((parsed[1].0.cmsg_type, parsed[1].1), (2, &[5; 12][..]));
+
+        //control messages that don't fit are dropped and flagged
+        msghdr.msg_controllen = interface::cmsg_space(4) + interface::cmsg_len(4);
+        interface::copy_out_msghdr(&mut msghdr, None, &cmsgs, 0);
+        assert_eq!(msghdr.msg_flags, MSG_CTRUNC);
+        assert_eq!(msghdr.msg_namelen, 0);
+        assert_eq!(msghdr.msg_controllen, interface::cmsg_space(4));
+        assert_eq!(interface::iterate_cmsgs(&msghdr).unwrap().len(), 1);
+
+        //a control message claiming more than the buffer holds is rejected
+        let badcmsg = interface::CmsghdrStruct {
+            cmsg_len: 100,
+            cmsg_level: SOL_SOCKET,
+            cmsg_type: 1,
+        };
+        unsafe {
+            std::ptr::write_unaligned(
+                control.as_mut_ptr() as *mut interface::CmsghdrStruct,
+                badcmsg,
+            )
+        };
+        msghdr.msg_controllen = control.len();
+        assert_eq!(
+            interface::iterate_cmsgs(&msghdr).err(),
+            Some(-(Errno::EINVAL as i32))
+        );
+
+        msghdr.msg_iovlen = interface::IOV_MAX + 1;
+        let arg = Arg {
+            dispatch_msghdrstruct: &mut msghdr,
+        };
+        assert_eq!(
+            interface::get_msghdr(arg).err(),
+            Some(-(Errno::EMSGSIZE as i32))
+        );
+
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_sockaddr_abi() {
+        lindrustinit(0);
+
+        let mut v6 = interface::SockaddrV6 {
+            sin6_family: 30,
+            sin6_port: 50132u16.to_be(),
+            ..Default::default()
+        };
+        let v6len = size_of::<interface::SockaddrV6>() as u32;
+        let inarg = |addr: &interface::SockaddrV6| Arg {
+            dispatch_constsockaddrstruct: addr as *const interface::SockaddrV6
+                as *const interface::SockaddrDummy,
+        };
+
+        //a cage built for macOS numbers AF_INET6 30, and gets the same number back
+        interface::set_sockaddr_abi(interface::SockaddrAbi::Darwin);
+        let addr = interface::get_sockaddr(inarg(&v6), v6len).unwrap();
+        assert_eq!(addr.get_family(), AF_INET6 as u16);
+        let mut out = interface::SockaddrV6::default();
+        let mut outlen = v6len;
+        interface::copy_out_sockaddr(
+            Arg {
+                dispatch_sockaddrstruct: &mut out as *mut interface::SockaddrV6
+                    as *mut interface::SockaddrDummy,
+            },
+            Arg {
+                dispatch_socklen_t_ptr: &mut outlen,
+            },
+            addr,
+        );
+        assert_eq!(out.sin6_family, 30);
+        assert_eq!(out.sin6_port, v6.sin6_port);
+
+        //while one built for Linux uses its own number, and 30 means nothing to it
+        interface::set_sockaddr_abi(interface::SockaddrAbi::Linux);
+        assert_eq!(
+            interface::get_sockaddr(inarg(&v6), v6len).err(),
+            Some(-(Errno::EOPNOTSUPP as i32))
+        );
+        v6.sin6_family = AF_INET6 as u16;
+        let addr = interface::get_sockaddr(inarg(&v6), v6len).unwrap();
+        assert_eq!(addr.get_family(), AF_INET6 as u16);
+
+        interface::set_sockaddr_abi(interface::HOST_SOCKADDR_ABI);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_fionread() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let mut readable: i32 = -1;
+        let readableptr = IoctlPtrUnion {
+            int_ptr: &mut readable,
+        };
+
+        //a unix socket counts what its peer wrote, including bytes already peeked at
+        let mut socketpair = interface::SockPair::default();
+        assert_eq!(
+            Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut socketpair),
+            0
+        );
+        assert_eq!(
+            cage.ioctl_syscall(socketpair.sock2, FIONREAD, readableptr),
+            0
+        );
+        assert_eq!(readable, 0);
+        assert_eq!(
+            cage.send_syscall(socketpair.sock1, str2cbuf("hello"), 5, 0),
+            5
+        );
+        assert_eq!(
+            cage.ioctl_syscall(socketpair.sock2, FIONREAD, readableptr),
+            0
+        );
+        assert_eq!(readable, 5);
+        let mut buf = sizecbuf(5);
+        assert_eq!(
+            cage.recv_syscall(socketpair.sock2, buf.as_mut_ptr(), 2, MSG_PEEK),
+            2
+        );
+        assert_eq!(
+            cage.ioctl_syscall(socketpair.sock2, FIONREAD, readableptr),
+            0
+        );
+        assert_eq!(readable, 5);
+        assert_eq!(
+            cage.recv_syscall(socketpair.sock2, buf.as_mut_ptr(), 3, 0),
+            3
+        );
+        assert_eq!(
+            cage.ioctl_syscall(socketpair.sock2, FIONREAD, readableptr),
+            0
+        );
+        assert_eq!(readable, 2);
+
+        //an inet socket asks the kernel, which gives the size of the next datagram
+        let udpfd = cage.socket_syscall(AF_INET, SOCK_DGRAM, 0);
+        let socket = interface::GenSockaddr::V4(interface::SockaddrV4 {
+            sin_family: AF_INET as u16,
+            sin_port: 50177_u16.to_be(),
+            sin_addr: interface::V4Addr {
+                s_addr: u32::from_ne_bytes([127, 0, 0, 1]),
+            },
+            padding: 0,
+        });
+        assert_eq!(cage.bind_syscall(udpfd, &socket), 0);
+        assert_eq!(
+            cage.sendto_syscall(udpfd, str2cbuf("ping"), 4, 0, &socket),
+            4
+        );
+        assert_eq!(cage.ioctl_syscall(udpfd, FIONREAD, readableptr), 0);
+        assert_eq!(readable, 4);
+
+        //a pipe counts what is buffered in it
+        let mut pipefds = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        assert_eq!(cage.write_syscall(pipefds.writefd, str2cbuf("abc"), 3), 3);
+        assert_eq!(cage.ioctl_syscall(pipefds.readfd, FIONREAD, readableptr), 0);
+        assert_eq!(readable, 3);
+        assert_eq!(
+            cage.ioctl_syscall(
+                pipefds.readfd,
+                FIONREAD,
+                IoctlPtrUnion {
+                    int_ptr: std::ptr::null_mut()
+                }
+            ),
+            -(Errno::EFAULT as i32)
+        );
+
+        assert_eq!(cage.close_syscall(pipefds.readfd), 0);
+        assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+        assert_eq!(cage.close_syscall(udpfd), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock1), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock2), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_ifreq_ioctls() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let sockfd = cage.socket_syscall(AF_INET, SOCK_DGRAM, 0);
+        assert!(sockfd >= 0);
+        let mut ifreq: interface::IfreqStruct = unsafe { std::mem::zeroed() };
+        let ifreqptr = IoctlPtrUnion {
+            ifreq_ptr: &mut ifreq,
+        };
+
+        //every interface getifaddrs lists can be found by its index, then asked about by name
+        for (position, device) in NET_DEVICES.iter().enumerate() {
+            let index = position as i32 + 1;
+            ifreq.ifr_ifru.ifru_ivalue = index;
+            assert_eq!(cage.ioctl_syscall(sockfd, SIOCGIFNAME, ifreqptr), 0);
+            assert_eq!(ifreq.name(), Some(device.name.as_str()));
+
+            assert_eq!(cage.ioctl_syscall(sockfd, SIOCGIFINDEX, ifreqptr), 0);
+            assert_eq!(unsafe { ifreq.ifr_ifru.ifru_ivalue }, index);
+            assert_eq!(cage.ioctl_syscall(sockfd, SIOCGIFFLAGS, ifreqptr), 0);
+            assert_eq!(unsafe { ifreq.ifr_ifru.ifru_flags }, device.flags as i16);
+            assert_eq!(cage.ioctl_syscall(sockfd, SIOCGIFMTU, ifreqptr), 0);
+            assert_eq!(unsafe { ifreq.ifr_ifru.ifru_mtu }, device.mtu());
+            assert_eq!(cage.ioctl_syscall(sockfd, SIOCGIFADDR, ifreqptr), 0);
+            let addr = unsafe { ifreq.ifr_ifru.ifru_addr };
+            assert_eq!(addr.sin_family, AF_INET as u16);
+            assert_eq!(addr.sin_addr, device.addr);
+            assert_eq!(cage.ioctl_syscall(sockfd, SIOCGIFNETMASK, ifreqptr), 0);
+            assert_eq!(unsafe { ifreq.ifr_ifru.ifru_addr }.sin_addr, device.netmask);
+        }
+
+        ifreq.set_name("nosuchif0");
+        assert_eq!(
+            cage.ioctl_syscall(sockfd, SIOCGIFADDR, ifreqptr),
+            -(Errno::ENODEV as i32)
+        );
+        ifreq.ifr_ifru.ifru_ivalue = 0;
+        assert_eq!(
+            cage.ioctl_syscall(sockfd, SIOCGIFNAME, ifreqptr),
+            -(Errno::ENODEV as i32)
+        );
+        //a failed lookup leaves the request as it was
+        assert_eq!(unsafe { ifreq.ifr_ifru.ifru_ivalue }, 0);
+        assert_eq!(
+            cage.ioctl_syscall(
+                sockfd,
+                SIOCGIFADDR,
+                IoctlPtrUnion {
+                    ifreq_ptr: std::ptr::null_mut()
+                }
+            ),
+            -(Errno::EFAULT as i32)
+        );
+
+        assert_eq!(cage.close_syscall(sockfd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_fcntl_nonblocking() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let serversockfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        let clientsockfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        let socket = interface::GenSockaddr::V4(interface::SockaddrV4 {
+            sin_family: AF_INET as u16,
+            sin_port: 50178_u16.to_be(),
+            sin_addr: interface::V4Addr {
+                s_addr: u32::from_ne_bytes([127, 0, 0, 1]),
+            },
+            padding: 0,
+        });
+
+        //a socket is open for reading and writing, and O_NONBLOCK can be set before it is bound
+        assert_eq!(cage.fcntl_syscall(serversockfd, F_GETFL, 0), O_RDWR);
+        assert_eq!(cage.fcntl_syscall(serversockfd, F_SETFL, O_NONBLOCK), 0);
+        assert_eq!(
+            cage.fcntl_syscall(serversockfd, F_GETFL, 0),
+            O_RDWR | O_NONBLOCK
+        );
+        assert_eq!(cage.bind_syscall(serversockfd, &socket), 0);
+        assert_eq!(cage.listen_syscall(serversockfd, 10), 0);
+
+        let mut addr = interface::GenSockaddr::V4(interface::SockaddrV4::default());
+        assert_eq!(
+            cage.accept_syscall(serversockfd, &mut addr),
+            -(Errno::EAGAIN as i32)
+        );
+        assert_eq!(cage.connect_syscall(clientsockfd, &socket), 0);
+        interface::sleep(interface::RustDuration::from_millis(100));
+        let connfd = cage.accept_syscall(serversockfd, &mut addr);
+        assert!(connfd > 0);
+
+        //the accepted socket does not inherit O_NONBLOCK, but picks it up from a later fcntl
+        assert_eq!(cage.fcntl_syscall(connfd, F_GETFL, 0), O_RDWR);
+        assert_eq!(cage.fcntl_syscall(connfd, F_SETFL, O_NONBLOCK), 0);
+        let mut buf = sizecbuf(4);
+        assert_eq!(
+            cage.recv_syscall(connfd, buf.as_mut_ptr(), 4, 0),
+            -(Errno::EAGAIN as i32)
+        );
+        assert_eq!(cage.fcntl_syscall(connfd, F_SETFL, 0), 0);
+        assert_eq!(cage.send_syscall(clientsockfd, str2cbuf("ping"), 4, 0), 4);
+        assert_eq!(cage.recv_syscall(connfd, buf.as_mut_ptr(), 4, 0), 4);
+        assert_eq!(cbuf2str(&buf), "ping");
+
+        //FD_CLOEXEC is accepted alongside O_CLOEXEC, and F_DUPFD must start below the fd limit
+        assert_eq!(cage.fcntl_syscall(connfd, F_SETFD, FD_CLOEXEC), 0);
+        assert_eq!(cage.fcntl_syscall(connfd, F_GETFD, 0), O_CLOEXEC);
+        assert_eq!(
+            cage.fcntl_syscall(connfd, F_DUPFD, MAXFD),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.fcntl_syscall(connfd, F_DUPFD, -1),
+            -(Errno::EINVAL as i32)
+        );
+
+        assert_eq!(cage.close_syscall(connfd), 0);
+        assert_eq!(cage.close_syscall(clientsockfd), 0);
+        assert_eq!(cage.close_syscall(serversockfd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_blocked_recv_leaves_fd_free() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let mut socketpair = interface::SockPair::default();
+        assert_eq!(
+            Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut socketpair),
+            0
+        );
+        let cage2 = cage.clone();
+
+        let thread = interface::helper_thread(move || {
+            let mut buf = sizecbuf(4);
+            assert_eq!(
+                cage2.recv_syscall(socketpair.sock2, buf.as_mut_ptr(), 4, 0),
+                4
+            );
+            assert_eq!(cbuf2str(&buf), "ping");
+        });
+
+        //while the recv waits, its descriptor can still be looked at and dup'd
+        interface::sleep(interface::RustDuration::from_millis(100));
+        assert_eq!(cage.fcntl_syscall(socketpair.sock2, F_GETFL, 0), O_RDWR);
+        let dupfd = cage.dup_syscall(socketpair.sock2, None);
+        assert!(dupfd > 0);
+        assert_eq!(
+            cage.send_syscall(socketpair.sock1, str2cbuf("ping"), 4, 0),
+            4
+        );
+        thread.join().unwrap();
+
+        assert_eq!(cage.close_syscall(dupfd), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock1), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock2), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_full_duplex() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let mut socketpair = interface::SockPair::default();
+        assert_eq!(
+            Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut socketpair),
+            0
+        );
+        let cage2 = cage.clone();
+
+        //one thread blocks reading sock1 until the peer answers
+        let thread = interface::helper_thread(move || {
+            let mut buf = sizecbuf(4);
+            assert_eq!(
+                cage2.recv_syscall(socketpair.sock1, buf.as_mut_ptr(), 4, 0),
+                4
+            );
+            assert_eq!(cbuf2str(&buf), "pong");
+        });
+
+        //meanwhile sock1 is written to from this thread without waiting on that recv
+        interface::sleep(interface::RustDuration::from_millis(100));
+        let start = interface::starttimer();
+        assert_eq!(
+            cage.send_syscall(socketpair.sock1, str2cbuf("ping"), 4, 0),
+            4
+        );
+        assert!(interface::readtimer(start) < interface::RustDuration::from_millis(500));
+
+        let mut buf = sizecbuf(4);
+        assert_eq!(
+            cage.recv_syscall(socketpair.sock2, buf.as_mut_ptr(), 4, 0),
+            4
+        );
+        assert_eq!(cbuf2str(&buf), "ping");
+        assert_eq!(
+            cage.send_syscall(socketpair.sock2, str2cbuf("pong"), 4, 0),
+            4
+        );
+        thread.join().unwrap();
+
+        assert_eq!(cage.close_syscall(socketpair.sock1), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock2), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_select_poll_sleep_until_ready() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        //long enough never to be reached, so a wakeup that is lost fails rather than hangs
+        let timeout = Some(interface::RustDuration::from_secs(5));
+
+        //a select on an empty pipe wakes once another thread writes to it
+        let mut pipefds = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        let cage2 = cage.clone();
+        let writer = interface::helper_thread(move || {
+            interface::sleep(interface::RustDuration::from_millis(100));
+            assert_eq!(cage2.write_syscall(pipefds.writefd, str2cbuf("x"), 1), 1);
+        });
+        let readfds = &mut interface::FdSet::new();
+        readfds.set(pipefds.readfd);
+        assert_eq!(
+            cage.select_syscall(pipefds.readfd + 1, Some(&mut *readfds), None, None, timeout),
+            1
+        );
+        assert!(readfds.is_set(pipefds.readfd));
+        writer.join().unwrap();
+        let mut buf = sizecbuf(1);
+        assert_eq!(cage.read_syscall(pipefds.readfd, buf.as_mut_ptr(), 1), 1);
+
+        //a poll on an inet socket wakes once a datagram reaches it
+        let udpfd = cage.socket_syscall(AF_INET, SOCK_DGRAM, 0);
+        let socket = interface::GenSockaddr::V4(interface::SockaddrV4 {
+            sin_family: AF_INET as u16,
+            sin_port: 50179_u16.to_be(),
+            sin_addr: interface::V4Addr {
+                s_addr: u32::from_ne_bytes([127, 0, 0, 1]),
+            },
+            padding: 0,
+        });
+        assert_eq!(cage.bind_syscall(udpfd, &socket), 0);
+        let cage2 = cage.clone();
+        let sender = interface::helper_thread(move || {
+            interface::sleep(interface::RustDuration::from_millis(100));
+            let senderfd = cage2.socket_syscall(AF_INET, SOCK_DGRAM, 0);
+            assert_eq!(
+                cage2.sendto_syscall(senderfd, str2cbuf("ping"), 4, 0, &socket),
+                4
+            );
+            assert_eq!(cage2.close_syscall(senderfd), 0);
+        });
+        let mut pollfds = [interface::PollStruct {
+            fd: udpfd,
+            events: POLLIN,
+            revents: 0,
+        }];
+        assert_eq!(cage.poll_syscall(&mut pollfds, timeout), 1);
+        assert_eq!(pollfds[0].revents & POLLIN, POLLIN);
+        sender.join().unwrap();
+
+        //with nothing to wake it, a poll still returns once its timeout is up
+        let start = interface::starttimer();
+        assert_eq!(
+            cage.poll_syscall(
+                &mut [interface::PollStruct {
+                    fd: pipefds.writefd,
+                    events: POLLIN,
+                    revents: 0,
+                }],
+                Some(interface::RustDuration::from_millis(50))
+            ),
+            0
+        );
+        assert!(interface::readtimer(start) >= interface::RustDuration::from_millis(50));
+
+        assert_eq!(cage.close_syscall(udpfd), 0);
+        assert_eq!(cage.close_syscall(pipefds.readfd), 0);
+        assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_poll_many() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let mut emptypipe = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut emptypipe), 0);
+        let mut fullpipe = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut fullpipe), 0);
+        assert_eq!(cage.write_syscall(fullpipe.writefd, str2cbuf("x"), 1), 1);
+        let mut socketpair = interface::SockPair::default();
+        assert_eq!(
+            Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut socketpair),
+            0
+        );
+
+        //every fd is looked at in the one pass, and a negative fd is skipped
+        let mut pollfds = [
+            interface::PollStruct {
+                fd: emptypipe.readfd,
+                events: POLLIN,
+                revents: 0,
+            },
+            interface::PollStruct {
+                fd: fullpipe.readfd,
+                events: POLLIN,
+                revents: 0,
+            },
+            interface::PollStruct {
+                fd: socketpair.sock1,
+                events: POLLIN | POLLOUT,
+                revents: 0,
+            },
+            interface::PollStruct {
+                fd: -1,
+                events: POLLIN,
+                revents: 0,
+            },
+        ];
+        assert_eq!(
+            cage.poll_syscall(&mut pollfds, Some(interface::RustDuration::ZERO)),
+            2
+        );
+        assert_eq!(pollfds[0].revents, 0);
+        assert_eq!(pollfds[1].revents, POLLIN);
+        assert_eq!(pollfds[2].revents, POLLOUT);
+        assert_eq!(pollfds[3].revents, 0);
+
+        assert_eq!(cage.close_syscall(emptypipe.readfd), 0);
+        assert_eq!(cage.close_syscall(emptypipe.writefd), 0);
+        assert_eq!(cage.close_syscall(fullpipe.readfd), 0);
+        assert_eq!(cage.close_syscall(fullpipe.writefd), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock1), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock2), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_select_socket_set_changes() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let udpaddr = |port: u16| {
+            interface::GenSockaddr::V4(interface::SockaddrV4 {
+                sin_family: AF_INET as u16,
+                sin_port: port.to_be(),
+                sin_addr: interface::V4Addr {
+                    s_addr: u32::from_ne_bytes([127, 0, 0, 1]),
+                },
+                padding: 0,
+            })
+        };
+        //select only watches udp sockets that are connected, each of these to the sender
+        let connected = || {
+            let fd = cage.socket_syscall(AF_INET, SOCK_DGRAM, 0);
+            assert_eq!(cage.connect_syscall(fd, &udpaddr(50140)), 0);
+            let mut addr = interface::GenSockaddr::V4(interface::SockaddrV4::default());
+            assert_eq!(cage.getsockname_syscall(fd, &mut addr), 0);
+            (fd, addr)
+        };
+        let select_read = |fds: &[i32]| {
+            let readfds = &mut interface::FdSet::new();
+            for fd in fds {
+                readfds.set(*fd);
+            }
+            let ret = cage.select_syscall(
+                fds.iter().max().unwrap() + 1,
+                Some(readfds),
+                None,
+                None,
+                Some(interface::RustDuration::ZERO),
+            );
+            let ready: Vec<i32> = fds
+                .iter()
+                .cloned()
+                .filter(|fd| readfds.is_set(*fd))
+                .collect();
+            (ret, ready)
+        };
+
+        let (sockfd1, _) = connected();
+        let (sockfd2, sockaddr2) = connected();
+        let senderfd = cage.socket_syscall(AF_INET, SOCK_DGRAM, 0);
+        assert_eq!(cage.bind_syscall(senderfd, &udpaddr(50140)), 0);
+        assert_eq!(select_read(&[sockfd1, sockfd2]), (0, vec![]));
+
+        assert_eq!(
+            cage.sendto_syscall(senderfd, str2cbuf("test"), 4, 0, &sockaddr2),
+            4
+        );
+        assert_eq!(select_read(&[sockfd1, sockfd2]), (1, vec![sockfd2]));
+        //dropping a socket from the set stops it being reported
+        assert_eq!(select_read(&[sockfd1]), (0, vec![]));
+        assert_eq!(select_read(&[sockfd1, sockfd2]), (1, vec![sockfd2]));
+
+        //a socket opened after another was closed may reuse its host fd, and is still watched
+        assert_eq!(cage.close_syscall(sockfd1), 0);
+        let (sockfd3, sockaddr3) = connected();
+        assert_eq!(
+            cage.sendto_syscall(senderfd, str2cbuf("test"), 4, 0, &sockaddr3),
+            4
+        );
+        assert_eq!(
+            select_read(&[sockfd2, sockfd3]),
+            (2, vec![sockfd2, sockfd3])
+        );
+
+        let mut buf = sizecbuf(4);
+        assert_eq!(cage.recv_syscall(sockfd2, buf.as_mut_ptr(), 4, 0), 4);
+        assert_eq!(select_read(&[sockfd2, sockfd3]), (1, vec![sockfd3]));
+
+        assert_eq!(cage.close_syscall(sockfd2), 0);
+        assert_eq!(cage.close_syscall(sockfd3), 0);
+        assert_eq!(cage.close_syscall(senderfd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_socket_addresses() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let inetaddr = |octets: [u8; 4], port: u16| {
+            interface::GenSockaddr::V4(interface::SockaddrV4 {
+                sin_family: AF_INET as u16,
+                sin_port: port.to_be(),
+                sin_addr: interface::V4Addr {
+                    s_addr: u32::from_ne_bytes(octets),
+                },
+                padding: 0,
+            })
+        };
+        let sockname = |fd: i32| {
+            let mut addr = interface::GenSockaddr::V4(interface::SockaddrV4::default());
+            assert_eq!(cage.getsockname_syscall(fd, &mut addr), 0);
+            addr
+        };
+        let peername = |fd: i32| {
+            let mut addr = interface::GenSockaddr::V4(interface::SockaddrV4::default());
+            assert_eq!(cage.getpeername_syscall(fd, &mut addr), 0);
+            addr
+        };
+
+        //the connection is queued by the host, so one thread can connect and then accept
+        let listenaddr = inetaddr([127, 0, 0, 1], 50181);
+        let serverfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        assert_eq!(cage.bind_syscall(serverfd, &listenaddr), 0);
+        assert_eq!(cage.listen_syscall(serverfd, 4), 0);
+        let clientfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        assert_eq!(cage.connect_syscall(clientfd, &listenaddr), 0);
+        let mut acceptaddr = interface::GenSockaddr::V4(interface::SockaddrV4::default());
+        let acceptedfd = cage.accept_syscall(serverfd, &mut acceptaddr);
+        assert!(acceptedfd > 0);
+
+        //each end names itself as the other end names it, and the accepted end has the listener's
+        let clientaddr = sockname(clientfd);
+        assert_ne!(clientaddr.port(), 0);
+        assert_eq!(acceptaddr, clientaddr);
+        assert_eq!(peername(acceptedfd), clientaddr);
+        assert_eq!(sockname(acceptedfd), listenaddr);
+        assert_eq!(peername(clientfd), listenaddr);
+
+        //closing the accepted end leaves the listener's port with the listener
+        assert_eq!(cage.close_syscall(acceptedfd), 0);
+        let otherfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        assert_eq!(
+            cage.bind_syscall(otherfd, &listenaddr),
+            -(Errno::EADDRINUSE as i32)
+        );
+        assert_eq!(cage.close_syscall(otherfd), 0);
+        assert_eq!(cage.close_syscall(clientfd), 0);
+        assert_eq!(cage.close_syscall(serverfd), 0);
+
+        //a listener on the wildcard address accepts at the address the peer connected to
+        let serverfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        assert_eq!(
+            cage.bind_syscall(serverfd, &inetaddr([0, 0, 0, 0], 50182)),
+            0
+        );
+        assert_eq!(cage.listen_syscall(serverfd, 4), 0);
+        let clientfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        assert_eq!(
+            cage.connect_syscall(clientfd, &inetaddr([127, 0, 0, 1], 50182)),
+            0
+        );
+        let acceptedfd = cage.accept_syscall(serverfd, &mut acceptaddr);
+        assert!(acceptedfd > 0);
+        assert_eq!(sockname(acceptedfd), inetaddr([127, 0, 0, 1], 50182));
+        assert_eq!(sockname(acceptedfd), peername(clientfd));
+        assert_eq!(peername(acceptedfd), sockname(clientfd));
+        assert_eq!(cage.close_syscall(acceptedfd), 0);
+        assert_eq!(cage.close_syscall(clientfd), 0);
+        assert_eq!(cage.close_syscall(serverfd), 0);
+
+        //the ends of a socketpair are unnamed, and leave nothing in the filesystem
+        let rootentries = || match &*filesystem::FS_METADATA
+            .inodetable
+            .get(&ROOTDIRECTORYINODE)
+            .unwrap()
+        {
+            filesystem::Inode::Dir(dir) => dir.filename_to_inode_dict.len(),
+            _ => unreachable!(),
+        };
+        let before = rootentries();
+        let mut socketpair = interface::SockPair::default();
+        assert_eq!(
+            Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut socketpair),
+            0
+        );
+        for fd in [socketpair.sock1, socketpair.sock2].iter() {
+            let name = sockname(*fd);
+            assert_eq!(name.get_family(), AF_UNIX as u16);
+            assert_eq!(name.path(), "");
+            assert_eq!(peername(*fd), name);
+        }
+        assert_eq!(rootentries(), before);
+
+        //and an unnamed address is copied out as just its family
+        let mut out = interface::SockaddrUnix {
+            sun_family: 0,
+            sun_path: [b'x'; 108],
+        };
+        let mut outlen = size_of::<interface::SockaddrUnix>() as u32;
+        interface::copy_out_sockaddr(
+            Arg {
+                dispatch_sockaddrstruct: &mut out as *mut interface::SockaddrUnix
+                    as *mut interface::SockaddrDummy,
+            },
+            Arg {
+                dispatch_socklen_t_ptr: &mut outlen,
+            },
+            sockname(socketpair.sock1),
+        );
+        assert_eq!(outlen, size_of::<u16>() as u32);
+
+        assert_eq!(cage.close_syscall(socketpair.sock1), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock2), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_select_nfds() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        //with nfds 0 a select looks at nothing, and just sleeps out its timeout
+        let start = interface::starttimer();
+        assert_eq!(
+            cage.select_syscall(
+                0,
+                None,
+                None,
+                None,
+                Some(interface::RustDuration::from_millis(50))
+            ),
+            0
+        );
+        assert!(interface::readtimer(start) >= interface::RustDuration::from_millis(50));
+
+        //a small nfds only looks at the descriptors below it, even when later ones are set
+        let mut pipefds = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        assert_eq!(cage.write_syscall(pipefds.writefd, str2cbuf("x"), 1), 1);
+        let readfds = &mut interface::FdSet::new();
+        readfds.set(pipefds.readfd);
+        assert_eq!(
+            cage.select_syscall(
+                1,
+                Some(&mut *readfds),
+                None,
+                None,
+                Some(interface::RustDuration::ZERO)
+            ),
+            0
+        );
+        assert!(!readfds.is_set(pipefds.readfd));
+
+        //and an nfds covering the whole fd_set is fine too
+        readfds.set(pipefds.readfd);
+        assert_eq!(
+            cage.select_syscall(FD_SET_MAX_FD, Some(&mut *readfds), None, None, None),
+            1
+        );
+        assert!(readfds.is_set(pipefds.readfd));
+        assert_eq!(
+            cage.select_syscall(-1, None, None, None, None),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.select_syscall(MAX_WATCHED_FDS + 1, None, None, None, None),
+            -(Errno::EINVAL as i32)
+        );
+
+        assert_eq!(cage.close_syscall(pipefds.readfd), 0);
+        assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_poll_ignored_and_invalid() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let mut pipefds = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        assert_eq!(cage.write_syscall(pipefds.writefd, str2cbuf("x"), 1), 1);
+        let closedfd = cage.dup_syscall(pipefds.readfd, None);
+        assert_eq!(cage.close_syscall(closedfd), 0);
+
+        //a negative fd is skipped and a closed one reported, without failing the whole poll
+        let pollfd = |fd: i32| interface::PollStruct {
+            fd: fd,
+            events: POLLIN,
+            revents: -1,
+        };
+        let mut polled = vec![pollfd(-1), pollfd(closedfd), pollfd(pipefds.readfd)];
+        assert_eq!(
+            cage.poll_syscall(&mut polled, Some(interface::RustDuration::ZERO)),
+            2
+        );
+        assert_eq!(polled[0].revents, 0);
+        assert_eq!(polled[1].revents, POLLNVAL);
+        assert_eq!(polled[2].revents, POLLIN);
+
+        //an invalid fd counts as ready, so a poll on one doesn't wait
+        let mut polled = vec![pollfd(MAXFD + 5)];
+        assert_eq!(cage.poll_syscall(&mut polled, None), 1);
+        assert_eq!(polled[0].revents, POLLNVAL);
+
+        //and with only negative fds there is nothing to report
+        let mut polled = vec![pollfd(-1), pollfd(-7)];
+        assert_eq!(
+            cage.poll_syscall(&mut polled, Some(interface::RustDuration::from_millis(10))),
+            0
+        );
+        assert!(polled.iter().all(|structpoll| structpoll.revents == 0));
+
+        assert_eq!(cage.close_syscall(pipefds.readfd), 0);
+        assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_epoll_close_deregisters() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let registered = |epfd: i32, fd: i32| match &*cage.get_filedescriptor(epfd).unwrap().read()
+        {
+            Some(FileDescriptor::Epoll(epollfdobj)) => epollfdobj.registered_fds.contains_key(&fd),
+            _ => panic!("not an epoll fd"),
+        };
+        let ready = |epfd: i32| {
+            let mut events = vec![EpollEvent { events: 0, fd: 0 }; 4];
+            cage.epoll_wait_syscall(epfd, &mut events, 4, Some(interface::RustDuration::ZERO))
+        };
+        let event = |fd: i32| EpollEvent {
+            events: EPOLLIN as u32,
+            fd: fd,
+        };
+
+        //one fd can be watched by two epolls at once
+        let epfd1 = cage.epoll_create_syscall(1);
+        let epfd2 = cage.epoll_create_syscall(1);
+        let mut pipefds = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        let readfd = pipefds.readfd;
+        for epfd in [epfd1, epfd2].iter() {
+            assert_eq!(
+                cage.epoll_ctl_syscall(*epfd, EPOLL_CTL_ADD, readfd, &event(readfd)),
+                0
+            );
+        }
+        assert_eq!(cage.write_syscall(pipefds.writefd, str2cbuf("x"), 1), 1);
+        assert_eq!(ready(epfd1), 1);
+        assert_eq!(ready(epfd2), 1);
+
+        //closing it takes it out of both straight away, so a new fd of the same number isn't
+        //taken for it
+        assert_eq!(cage.close_syscall(readfd), 0);
+        assert!(!registered(epfd1, readfd));
+        assert!(!registered(epfd2, readfd));
+        assert!(cage.epoll_registrations.get(&readfd).is_none());
+        let mut pipefds2 = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut pipefds2), 0);
+        assert_eq!(pipefds2.readfd, readfd);
+        assert_eq!(cage.write_syscall(pipefds2.writefd, str2cbuf("x"), 1), 1);
+        assert_eq!(ready(epfd1), 0);
+        assert_eq!(
+            cage.epoll_ctl_syscall(epfd1, EPOLL_CTL_DEL, readfd, &event(readfd)),
+            -(Errno::ENOENT as i32)
+        );
+
+        //a close doesn't wait for an epoll_wait on the fd to finish
+        let mut pipefds3 = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut pipefds3), 0);
+        let idlefd = pipefds3.readfd;
+        assert_eq!(
+            cage.epoll_ctl_syscall(epfd1, EPOLL_CTL_ADD, idlefd, &event(idlefd)),
+            0
+        );
+        let cage2 = cage.clone();
+        let waiter = interface::helper_thread(move || {
+            let mut events = vec![EpollEvent { events: 0, fd: 0 }; 4];
+            cage2.epoll_wait_syscall(
+                epfd1,
+                &mut events,
+                4,
+                Some(interface::RustDuration::from_millis(1000)),
+            )
+        });
+        interface::sleep(interface::RustDuration::from_millis(100));
+        let start = interface::starttimer();
+        assert_eq!(cage.close_syscall(idlefd), 0);
+        assert!(interface::readtimer(start) < interface::RustDuration::from_millis(500));
+        assert_eq!(waiter.join().unwrap(), 0);
+        assert!(!registered(epfd1, idlefd));
+        assert_eq!(cage.close_syscall(pipefds3.writefd), 0);
+
+        //and closing an epoll drops what it watched from the bookkeeping
+        assert_eq!(
+            cage.epoll_ctl_syscall(epfd2, EPOLL_CTL_ADD, readfd, &event(readfd)),
+            0
+        );
+        assert_eq!(cage.close_syscall(epfd2), 0);
+        assert!(cage.epoll_registrations.get(&readfd).is_none());
+
+        assert_eq!(cage.close_syscall(readfd), 0);
+        assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+        assert_eq!(cage.close_syscall(pipefds2.writefd), 0);
+        assert_eq!(cage.close_syscall(epfd1), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_send_partial() {
+        lindrustinit(0);
+        rustposix_thread_init(1, 0);
+        let cage = interface::cagetable_getref(1);
+        let pthreadid = interface::get_pthreadid();
+        let act = interface::SigactionStruct {
+            sa_handler: 0x1000,
+            sa_mask: 0,
+            sa_flags: 0,
+        };
+        assert_eq!(cage.sigaction_syscall(SIGUSR1, Some(&act), None), 0);
+
+        let big = vec![b'x'; 2 * UDSOCK_CAPACITY];
+        let mut readbuf = vec![0u8; UDSOCK_CAPACITY];
+        let mut socketpair = interface::SockPair::default();
+        assert_eq!(
+            Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut socketpair),
+            0
+        );
+        let (sock1, sock2) = (socketpair.sock1, socketpair.sock2);
+
+        //a nonblocking send takes what fits and says how much that was
+        assert_eq!(cage.fcntl_syscall(sock1, F_SETFL, O_NONBLOCK), 0);
+        assert_eq!(
+            cage.send_syscall(sock1, big.as_ptr(), big.len(), 0),
+            UDSOCK_CAPACITY as i32
+        );
+        assert_eq!(
+            cage.send_syscall(sock1, big.as_ptr(), 1, 0),
+            -(Errno::EAGAIN as i32)
+        );
+        assert_eq!(cage.recv_syscall(sock2, readbuf.as_mut_ptr(), 100, 0), 100);
+        assert_eq!(cage.send_syscall(sock1, big.as_ptr(), big.len(), 0), 100);
+
+        //a blocking send cut short by a signal returns what it got through, or EINTR if nothing
+        assert_eq!(cage.fcntl_syscall(sock1, F_SETFL, 0), 0);
+        let mut drained = 0;
+        while drained < UDSOCK_CAPACITY {
+            let count =
+                cage.recv_syscall(sock2, readbuf.as_mut_ptr(), UDSOCK_CAPACITY - drained, 0);
+            assert!(count > 0);
+            drained += count as usize;
+        }
+        assert_eq!(cage.kill_syscall(1, SIGUSR1), 0);
+        assert_eq!(
+            cage.send_syscall(sock1, big.as_ptr(), big.len(), 0),
+            UDSOCK_CAPACITY as i32
+        );
+        assert_eq!(
+            cage.send_syscall(sock1, big.as_ptr(), 1, 0),
+            -(Errno::EINTR as i32)
+        );
+        let mut action = interface::SigactionStruct::default();
+        let mut info = interface::SiginfoStruct::default();
+        let mut oldmask: interface::SigsetType = 0;
+        assert_eq!(
+            unsafe { lindnextsignal(1, &mut action, &mut info, &mut oldmask) },
+            SIGUSR1
+        );
+        assert_eq!(lindsigreturn(1, oldmask), 0);
+
+        //a send to a closed peer fails with EPIPE, and raises SIGPIPE unless MSG_NOSIGNAL is given
+        assert_eq!(cage.close_syscall(sock2), 0);
+        assert_eq!(
+            cage.send_syscall(sock1, big.as_ptr(), 1, MSG_NOSIGNAL),
+            -(Errno::EPIPE as i32)
+        );
+        assert!(!interface::lind_sigismember(
+            cage.pending_signals(pthreadid),
+            SIGPIPE
+        ));
+        assert_eq!(
+            cage.send_syscall(sock1, big.as_ptr(), 1, 0),
+            -(Errno::EPIPE as i32)
+        );
+        assert!(interface::lind_sigismember(
+            cage.pending_signals(pthreadid),
+            SIGPIPE
+        ));
+        assert_eq!(cage.close_syscall(sock1), 0);
+
+        //the same goes for tcp, where what fits is up to the host's socket buffers
+        let mut listenaddr = interface::GenSockaddr::V4(interface::SockaddrV4::default());
+        listenaddr.set_family(AF_INET as u16);
+        listenaddr.set_addr(interface::GenIpaddr::V4(interface::V4Addr {
+            s_addr: u32::from_ne_bytes([127, 0, 0, 1]),
+        }));
+        listenaddr.set_port(50183);
+        let serverfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        assert_eq!(cage.bind_syscall(serverfd, &listenaddr), 0);
+        assert_eq!(cage.listen_syscall(serverfd, 4), 0);
+        let clientfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        assert_eq!(cage.connect_syscall(clientfd, &listenaddr), 0);
+        let mut acceptaddr = interface::GenSockaddr::V4(interface::SockaddrV4::default());
+        let acceptedfd = cage.accept_syscall(serverfd, &mut acceptaddr);
+        assert!(acceptedfd > 0);
+
+        let huge = vec![b'x'; 64 * 1024 * 1024];
+        assert_eq!(cage.fcntl_syscall(clientfd, F_SETFL, O_NONBLOCK), 0);
+        let first = cage.send_syscall(clientfd, huge.as_ptr(), huge.len(), 0);
+        assert!(first > 0 && (first as usize) < huge.len());
+        let mut tries = 0;
+        loop {
+            let count = cage.send_syscall(clientfd, huge.as_ptr(), huge.len(), 0);
+            if count == -(Errno::EAGAIN as i32) {
+                break;
+            }
+            assert!(count > 0);
+            tries += 1;
+            assert!(tries < 100);
+        }
+        assert_eq!(cage.fcntl_syscall(clientfd, F_SETFL, 0), 0);
+        assert_eq!(cage.kill_syscall(1, SIGUSR1), 0);
+        assert_eq!(
+            cage.send_syscall(clientfd, huge.as_ptr(), huge.len(), 0),
+            -(Errno::EINTR as i32)
+        );
+        assert_eq!(
+            unsafe { lindnextsignal(1, &mut action, &mut info, &mut oldmask) },
+            SIGUSR1
+        );
+        assert_eq!(lindsigreturn(1, oldmask), 0);
+
+        assert_eq!(cage.close_syscall(acceptedfd), 0);
+        assert_eq!(cage.close_syscall(clientfd), 0);
+        assert_eq!(cage.close_syscall(serverfd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_unix_stale_path() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let path = "/stale.sock";
+        let addr = interface::GenSockaddr::Unix(interface::new_sockaddr_unix(
+            AF_UNIX as u16,
+            path.as_bytes(),
+        ));
+        let serverfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+        assert_eq!(cage.bind_syscall(serverfd, &addr), 0);
+        assert_eq!(cage.listen_syscall(serverfd, 1), 0);
+        let inodenum = filesystem::metawalk(interface::RustPath::new(path)).unwrap();
+
+        //closing the socket leaves its path behind, but nothing answers there anymore
+        assert_eq!(cage.close_syscall(serverfd), 0);
+        assert_eq!(cage.access_syscall(path, F_OK), 0);
+        let clientfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+        assert_eq!(
+            cage.connect_syscall(clientfd, &addr),
+            -(Errno::ECONNREFUSED as i32)
+        );
+
+        //and it can't be bound again until it is unlinked
+        let serverfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+        assert_eq!(
+            cage.bind_syscall(serverfd, &addr),
+            -(Errno::EADDRINUSE as i32)
+        );
+        assert_eq!(cage.unlink_syscall(path), 0);
+        assert!(filesystem::FS_METADATA.inodetable.get(&inodenum).is_none());
+        assert_eq!(
+            cage.connect_syscall(clientfd, &addr),
+            -(Errno::ENOENT as i32)
+        );
+        assert_eq!(cage.bind_syscall(serverfd, &addr), 0);
+
+        //unlinking a path still bound keeps the inode until the socket is closed
+        let newinodenum = filesystem::metawalk(interface::RustPath::new(path)).unwrap();
+        assert_eq!(cage.unlink_syscall(path), 0);
+        assert!(filesystem::FS_METADATA
+            .inodetable
+            .get(&newinodenum)
+            .is_some());
+        assert_eq!(cage.close_syscall(serverfd), 0);
+        assert!(filesystem::FS_METADATA
+            .inodetable
+            .get(&newinodenum)
+            .is_none());
+
+        assert_eq!(cage.close_syscall(clientfd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_async_signals() {
+        lindrustinit(0);
+        rustposix_thread_init(1, 0);
+        let cage = interface::cagetable_getref(1);
+        let act = interface::SigactionStruct {
+            sa_handler: 0x1000,
+            sa_mask: 0,
+            sa_flags: 0,
+        };
+        assert_eq!(cage.sigaction_syscall(SIGIO, Some(&act), None), 0);
+        assert_eq!(cage.sigaction_syscall(SIGUSR1, Some(&act), None), 0);
+        let mut action = interface::SigactionStruct::default();
+        let mut info = interface::SiginfoStruct::default();
+        let mut oldmask: interface::SigsetType = 0;
+        //the signals come from a helper thread, so give it a moment
+        let mut next_signal = || {
+            for _ in 0..100 {
+                let sig = unsafe { lindnextsignal(1, &mut action, &mut info, &mut oldmask) };
+                if sig > 0 {
+                    assert_eq!(lindsigreturn(1, oldmask), 0);
+                    return (sig, info.si_code);
+                }
+                interface::sleep(interface::RustDuration::from_millis(10));
+            }
+            (0, 0)
+        };
+
+        //the owner and signal belong to the open file description
+        let mut pipefds = PipeArray::default();
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        assert_eq!(cage.fcntl_syscall(pipefds.readfd, F_GETOWN, 0), 0);
+        assert_eq!(cage.fcntl_syscall(pipefds.readfd, F_SETOWN, 1), 0);
+        let dupfd = cage.dup_syscall(pipefds.readfd, None);
+        assert_eq!(cage.fcntl_syscall(dupfd, F_GETOWN, 0), 1);
+        assert_eq!(cage.fcntl_syscall(dupfd, F_GETSIG, 0), 0);
+        assert_eq!(
+            cage.fcntl_syscall(dupfd, F_SETSIG, SIGNAL_MAX + 1),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(cage.close_syscall(dupfd), 0);
+
+        //with O_ASYNC set, data arriving sends the owner SIGIO
+        assert_eq!(cage.fcntl_syscall(pipefds.readfd, F_SETFL, O_ASYNC), 0);
+        assert_eq!(cage.fcntl_syscall(pipefds.readfd, F_GETFL, 0), O_ASYNC);
+        assert_eq!(cage.write_syscall(pipefds.writefd, str2cbuf("x"), 1), 1);
+        assert_eq!(next_signal(), (SIGIO, SI_KERNEL));
+
+        //and once O_ASYNC is cleared nothing is sent
+        let mut buf = [0u8; 4];
+        assert_eq!(cage.read_syscall(pipefds.readfd, buf.as_mut_ptr(), 4), 1);
+        assert_eq!(cage.fcntl_syscall(pipefds.readfd, F_SETFL, 0), 0);
+        assert_eq!(cage.write_syscall(pipefds.writefd, str2cbuf("x"), 1), 1);
+        interface::sleep(interface::RustDuration::from_millis(200));
+        assert_eq!(next_signal(), (0, 0));
+
+        //a socket with F_SETSIG set sends that signal instead, saying that input is possible
+        let mut socketpair = interface::SockPair::default();
+        assert_eq!(
+            Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut socketpair),
+            0
+        );
+        assert_eq!(cage.fcntl_syscall(socketpair.sock2, F_SETOWN, 1), 0);
+        assert_eq!(cage.fcntl_syscall(socketpair.sock2, F_SETSIG, SIGUSR1), 0);
+        assert_eq!(cage.fcntl_syscall(socketpair.sock2, F_SETFL, O_ASYNC), 0);
+        assert_eq!(cage.send_syscall(socketpair.sock1, str2cbuf("x"), 1, 0), 1);
+        assert_eq!(next_signal(), (SIGUSR1, POLL_IN));
+
+        assert_eq!(cage.close_syscall(socketpair.sock1), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock2), 0);
+        assert_eq!(cage.close_syscall(pipefds.readfd), 0);
+        assert_eq!(cage.close_syscall(pipefds.writefd), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_restart_signals() {
+        lindrustinit(0);
+        rustposix_thread_init(1, 0);
+        let cage = interface::cagetable_getref(1);
+        let mut act = interface::SigactionStruct {
+            sa_handler: 0x1000,
+            sa_mask: 0,
+            sa_flags: 0,
+        };
+        assert_eq!(cage.sigaction_syscall(SIGUSR1, Some(&act), None), 0);
+        act.sa_flags = SA_RESTART;
+        assert_eq!(cage.sigaction_syscall(SIGUSR2, Some(&act), None), 0);
+        let mut action = interface::SigactionStruct::default();
+        let mut info = interface::SiginfoStruct::default();
+        let mut oldmask: interface::SigsetType = 0;
+        let mut handle_signal = |sig: i32| {
+            assert_eq!(
+                unsafe { lindnextsignal(1, &mut action, &mut info, &mut oldmask) },
+                sig
+            );
+            assert_eq!(lindsigreturn(1, oldmask), 0);
+        };
+
+        let mut socketpair = interface::SockPair::default();
+        assert_eq!(
+            Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut socketpair),
+            0
+        );
+        let path = "/restart.sock";
+        let addr = interface::GenSockaddr::Unix(interface::new_sockaddr_unix(
+            AF_UNIX as u16,
+            path.as_bytes(),
+        ));
+        let serverfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+        assert_eq!(cage.bind_syscall(serverfd, &addr), 0);
+        assert_eq!(cage.listen_syscall(serverfd, 1), 0);
+        let mut buf = sizecbuf(4);
+        let mut remote = interface::GenSockaddr::Unix(interface::new_sockaddr_unix(
+            AF_UNIX as u16,
+            "".as_bytes(),
+        ));
+        let ms = interface::TimeSpec {
+            tv_sec: 0,
+            tv_nsec: 1000000,
+        };
+
+        //without SA_RESTART every blocked call gives up with EINTR
+        assert_eq!(cage.kill_syscall(1, SIGUSR1), 0);
+        assert_eq!(
+            cage.recv_syscall(socketpair.sock1, buf.as_mut_ptr(), 4, 0),
+            -(Errno::EINTR as i32)
+        );
+        assert_eq!(
+            cage.accept_syscall(serverfd, &mut remote),
+            -(Errno::EINTR as i32)
+        );
+        handle_signal(SIGUSR1);
+
+        //with it, recv and accept are restarted once the handler returns
+        assert_eq!(cage.kill_syscall(1, SIGUSR2), 0);
+        assert_eq!(
+            cage.recv_syscall(socketpair.sock1, buf.as_mut_ptr(), 4, 0),
+            -(Errno::ERESTART as i32)
+        );
+        assert_eq!(
+            cage.accept_syscall(serverfd, &mut remote),
+            -(Errno::ERESTART as i32)
+        );
+        //but calls that wait with a timeout, and connect, never are
+        let mut readfds = interface::FdSet::new();
+        readfds.set(socketpair.sock1);
+        assert_eq!(
+            cage.select_syscall(socketpair.sock1 + 1, Some(&mut readfds), None, None, None),
+            -(Errno::EINTR as i32)
+        );
+        let mut pollfds = [interface::PollStruct {
+            fd: socketpair.sock1,
+            events: POLLIN,
+            revents: 0,
+        }];
+        assert_eq!(
+            cage.poll_syscall(&mut pollfds, None),
+            -(Errno::EINTR as i32)
+        );
+        assert_eq!(
+            cage.nanosleep_syscall(Some(&ms), None),
+            -(Errno::EINTR as i32)
+        );
+
+        //an interrupted connect is left for the listener to accept
+        let clientfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+        assert_eq!(
+            cage.connect_syscall(clientfd, &addr),
+            -(Errno::EINTR as i32)
+        );
+        handle_signal(SIGUSR2);
+        let acceptedfd = cage.accept_syscall(serverfd, &mut remote);
+        assert!(acceptedfd > 0);
+        assert_eq!(cage.send_syscall(clientfd, str2cbuf("ping"), 4, 0), 4);
+        assert_eq!(cage.recv_syscall(acceptedfd, buf.as_mut_ptr(), 4, 0), 4);
+        assert_eq!(cbuf2str(&buf), "ping");
+
+        assert_eq!(cage.close_syscall(acceptedfd), 0);
+        assert_eq!(cage.close_syscall(clientfd), 0);
+        assert_eq!(cage.close_syscall(serverfd), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock1), 0);
+        assert_eq!(cage.close_syscall(socketpair.sock2), 0);
+        assert_eq!(cage.unlink_syscall(path), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_connect_timeout() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let path = "/connecttimeout.sock";
+        let addr = interface::GenSockaddr::Unix(interface::new_sockaddr_unix(
+            AF_UNIX as u16,
+            path.as_bytes(),
+        ));
+        let serverfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+        assert_eq!(cage.bind_syscall(serverfd, &addr), 0);
+        assert_eq!(cage.listen_syscall(serverfd, 1), 0);
+
+        let clientfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+        let mut timeout = interface::TimeVal::default();
+        assert_eq!(cage.getsndtimeo_syscall(clientfd, &mut timeout), 0);
+        assert_eq!((timeout.tv_sec, timeout.tv_usec), (0, 0));
+        let badtimeout = interface::TimeVal {
+            tv_sec: 0,
+            tv_usec: 1000000,
+        };
+        assert_eq!(
+            cage.setsndtimeo_syscall(clientfd, &badtimeout),
+            -(Errno::EDOM as i32)
+        );
+        let shorttimeout = interface::TimeVal {
+            tv_sec: 0,
+            tv_usec: 100000,
+        };
+        assert_eq!(cage.setsndtimeo_syscall(clientfd, &shorttimeout), 0);
+        assert_eq!(cage.getsndtimeo_syscall(clientfd, &mut timeout), 0);
+        assert_eq!((timeout.tv_sec, timeout.tv_usec), (0, 100000));
+
+        //nobody accepts, so the connect gives up once the timeout runs out, and the connection is
+        //left for the listener to accept later
+        let start = interface::starttimer();
+        assert_eq!(
+            cage.connect_syscall(clientfd, &addr),
+            -(Errno::EINPROGRESS as i32)
+        );
+        assert!(interface::readtimer(start) >= interface::RustDuration::from_millis(100));
+        let mut remote = interface::GenSockaddr::Unix(interface::new_sockaddr_unix(
+            AF_UNIX as u16,
+            "".as_bytes(),
+        ));
+        let acceptedfd = cage.accept_syscall(serverfd, &mut remote);
+        assert!(acceptedfd > 0);
+        assert_eq!(cage.send_syscall(clientfd, str2cbuf("ping"), 4, 0), 4);
+        let mut buf = sizecbuf(4);
+        assert_eq!(cage.recv_syscall(acceptedfd, buf.as_mut_ptr(), 4, 0), 4);
+        assert_eq!(cbuf2str(&buf), "ping");
+
+        for fd in [clientfd, acceptedfd, serverfd] {
+            assert_eq!(cage.close_syscall(fd), 0);
+        }
+        assert_eq!(cage.unlink_syscall(path), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_accept_wakeup() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let path = "/acceptwakeup.sock";
+        let addr = interface::GenSockaddr::Unix(interface::new_sockaddr_unix(
+            AF_UNIX as u16,
+            path.as_bytes(),
+        ));
+        let serverfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+        assert_eq!(cage.bind_syscall(serverfd, &addr), 0);
+        assert_eq!(cage.listen_syscall(serverfd, 1), 0);
+
+        //the accept sleeps until the connect below queues a connection for it
+        let acceptor = interface::helper_thread(move || {
+            let cage = interface::cagetable_getref(1);
+            let mut remote = interface::GenSockaddr::Unix(interface::new_sockaddr_unix(
+                AF_UNIX as u16,
+                "".as_bytes(),
+            ));
+            let acceptedfd = cage.accept_syscall(serverfd, &mut remote);
+            assert!(acceptedfd > 0);
+            let mut buf = sizecbuf(4);
+            assert_eq!(cage.recv_syscall(acceptedfd, buf.as_mut_ptr(), 4, 0), 4);
+            assert_eq!(cbuf2str(&buf), "ping");
+            assert_eq!(cage.close_syscall(acceptedfd), 0);
+        });
+        interface::sleep(interface::RustDuration::from_millis(100));
+
+        let clientfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+        assert_eq!(cage.connect_syscall(clientfd, &addr), 0);
+        assert_eq!(cage.send_syscall(clientfd, str2cbuf("ping"), 4, 0), 4);
+        acceptor.join().unwrap();
+
+        assert_eq!(cage.close_syscall(clientfd), 0);
+        assert_eq!(cage.close_syscall(serverfd), 0);
+        assert_eq!(cage.unlink_syscall(path), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_watched_fds_limit() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let mut pipefds = PipeArray {
+            readfd: -1,
+            writefd: -1,
+        };
+        assert_eq!(cage.pipe_syscall(&mut pipefds), 0);
+        assert_eq!(cage.write_syscall(pipefds.writefd, str2cbuf("x"), 1), 1);
+
+        //select looks at fds past what libc's fd_set holds
+        assert_eq!(cage.setrlimit(RLIMIT_NOFILE, NOFILE_MAX), 0);
+        let highfd = 3000;
+        assert_eq!(cage.dup2_syscall(pipefds.readfd, highfd), highfd);
+        let readfds = &mut interface::FdSet::new();
+        readfds.set(highfd);
+        assert_eq!(
+            cage.select_syscall(highfd + 1, Some(&mut *readfds), None, None, None),
+            1
+        );
+        assert!(readfds.is_set(highfd));
+
+        assert_eq!(lindsetmaxwatchedfds(1, 0), -(Errno::EINVAL as i32));
+        assert_eq!(lindsetmaxwatchedfds(99, 1), -(Errno::ESRCH as i32));
+        assert_eq!(lindsetmaxwatchedfds(1, 1), 0);
+        assert_eq!(
+            cage.select_syscall(highfd + 1, Some(&mut *readfds), None, None, None),
+            -(Errno::EINVAL as i32)
+        );
+        let mut pollfds = vec![
+            interface::PollStruct {
+                fd: pipefds.readfd,
+                events: POLLIN,
+                revents: 0,
+            },
+            interface::PollStruct {
+                fd: highfd,
+                events: POLLIN,
+                revents: 0,
+            },
+        ];
+        assert_eq!(
+            cage.poll_syscall(&mut pollfds, Some(interface::RustDuration::ZERO)),
+            -(Errno::EINVAL as i32)
+        );
+        assert_eq!(
+            cage.poll_syscall(&mut pollfds[..1], Some(interface::RustDuration::ZERO)),
+            1
+        );
+        let epfd = cage.epoll_create_syscall(1);
+        assert!(epfd > 0);
+        let mut events = vec![interface::EpollEvent { events: 0, fd: 0 }; 2];
+        assert_eq!(
+            cage.epoll_wait_syscall(epfd, &mut events, 2, Some(interface::RustDuration::ZERO)),
+            -(Errno::EINVAL as i32)
+        );
+
+        //a forked cage keeps the limit
+        assert_eq!(cage.fork_syscall(2), 0);
+        let child = interface::cagetable_getref(2);
+        assert_eq!(child.max_watched_fds(), 1);
+        assert_eq!(child.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+
+        for fd in [epfd, highfd, pipefds.readfd, pipefds.writefd] {
+            assert_eq!(cage.close_syscall(fd), 0);
+        }
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_listener_close_teardown() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        //closing an inet listener stops it listening, so the port can be listened on again
+        let addr = interface::GenSockaddr::V4(interface::SockaddrV4 {
+            sin_family: AF_INET as u16,
+            sin_port: 50199u16.to_be(),
+            sin_addr: interface::V4Addr {
+                s_addr: u32::from_ne_bytes([127, 0, 0, 1]),
+            },
+            padding: 0,
+        });
+        let porttuple = mux_port(addr.addr(), addr.port(), AF_INET, TCPPORT);
+        for _ in 0..2 {
+            let listenfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+            assert_eq!(cage.bind_syscall(listenfd, &addr), 0);
+            assert_eq!(cage.listen_syscall(listenfd, 1), 0);
+            assert!(NET_METADATA.listening_port_set.contains(&porttuple));
+            assert_eq!(cage.close_syscall(listenfd), 0);
+            assert!(!NET_METADATA.listening_port_set.contains(&porttuple));
+            assert!(!NET_METADATA.pending_conn_table.contains_key(&porttuple));
+        }
+
+        //closing a unix listener gives up its path and the connections it never accepted
+        let path = "/listenerclose.sock";
+        let unixaddr = interface::GenSockaddr::Unix(interface::new_sockaddr_unix(
+            AF_UNIX as u16,
+            path.as_bytes(),
+        ));
+        let truepath = interface::RustPathBuf::from(path);
+        let listenfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+        assert_eq!(cage.bind_syscall(listenfd, &unixaddr), 0);
+        assert_eq!(cage.listen_syscall(listenfd, 1), 0);
+        let clientfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM | SOCK_NONBLOCK, 0);
+        assert_eq!(cage.connect_syscall(clientfd, &unixaddr), 0);
+        assert!(NET_METADATA.domsock_accept_table.contains_key(&truepath));
+
+        //a blocking connect waiting on the listener is refused once it is closed
+        let waiter = interface::helper_thread(move || {
+            let cage = interface::cagetable_getref(1);
+            let waitfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+            assert_eq!(
+                cage.connect_syscall(waitfd, &unixaddr),
+                -(Errno::ECONNREFUSED as i32)
+            );
+            assert_eq!(cage.close_syscall(waitfd), 0);
+        });
+        //the waiting connect takes the place of the first in the accept table
+        interface::sleep(interface::RustDuration::from_millis(100));
+        assert_eq!(cage.close_syscall(listenfd), 0);
+        waiter.join().unwrap();
+        assert!(!NET_METADATA.domsock_accept_table.contains_key(&truepath));
+        assert!(!NET_METADATA.domsock_paths.contains_key(&truepath));
+
+        let otherfd = cage.socket_syscall(AF_UNIX, SOCK_STREAM, 0);
+        assert_eq!(
+            cage.connect_syscall(otherfd, &unixaddr),
+            -(Errno::ECONNREFUSED as i32)
+        );
+
+        for fd in [clientfd, otherfd] {
+            assert_eq!(cage.close_syscall(fd), 0);
+        }
+        assert_eq!(cage.unlink_syscall(path), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
+
+    pub fn ut_lind_net_time_wait_reuseaddr() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+        let addr = interface::GenSockaddr::V4(interface::SockaddrV4 {
+            sin_family: AF_INET as u16,
+            sin_port: 50198u16.to_be(),
+            sin_addr: interface::V4Addr {
+                s_addr: u32::from_ne_bytes([127, 0, 0, 1]),
+            },
+            padding: 0,
+        });
+
+        //the host may still hold the port from an earlier run, so the first server reuses it too
+        let listenfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        assert_eq!(
+            cage.setsockopt_syscall(listenfd, SOL_SOCKET, SO_REUSEADDR, 1),
+            0
+        );
+        assert_eq!(cage.bind_syscall(listenfd, &addr), 0);
+        assert_eq!(cage.listen_syscall(listenfd, 1), 0);
+        let clientfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        assert_eq!(cage.connect_syscall(clientfd, &addr), 0);
+        let mut remote = interface::GenSockaddr::V4(interface::SockaddrV4::default());
+        let acceptedfd = cage.accept_syscall(listenfd, &mut remote);
+        assert!(acceptedfd > 0);
+        let mut clientaddr = interface::GenSockaddr::V4(interface::SockaddrV4::default());
+        assert_eq!(cage.getsockname_syscall(clientfd, &mut clientaddr), 0);
+
+        //the server closes first, so only its end of the connection keeps the port for a while
+        assert_eq!(cage.close_syscall(acceptedfd), 0);
+        let mut buf = [0u8; 4];
+        assert_eq!(cage.recv_syscall(clientfd, buf.as_mut_ptr(), 4, 0), 0);
+        for fd in [clientfd, listenfd] {
+            assert_eq!(cage.close_syscall(fd), 0);
+        }
+        assert!(NET_METADATA.time_wait_ports.contains_key(&mux_port(
+            addr.addr(),
+            addr.port(),
+            AF_INET,
+            TCPPORT
+        )));
+        assert!(!NET_METADATA
+            .time_wait_ports
+            .iter()
+            .any(|entry| entry.key().1 == clientaddr.port()));
+
+        //so restarting the server needs SO_REUSEADDR
+        let refusedfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        assert_eq!(
+            cage.bind_syscall(refusedfd, &addr),
+            -(Errno::EADDRINUSE as i32)
+        );
+        assert_eq!(cage.close_syscall(refusedfd), 0);
+        let restartfd = cage.socket_syscall(AF_INET, SOCK_STREAM, 0);
+        assert_eq!(
+            cage.setsockopt_syscall(restartfd, SOL_SOCKET, SO_REUSEADDR, 1),
+            0
+        );
+        assert_eq!(cage.bind_syscall(restartfd, &addr), 0);
+        assert_eq!(cage.listen_syscall(restartfd, 1), 0);
+        assert_eq!(cage.close_syscall(restartfd), 0);
+
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+        //tearing the machine down ends every wait
+        assert!(NET_METADATA.time_wait_ports.is_empty());
+    }
+
+    pub fn ut_lind_net_shutdown_read() {
+        lindrustinit(0);
+        let cage = interface::cagetable_getref(1);
+
+        let mut socketpair = interface::SockPair::default();
+        assert_eq!(
+            Cage::socketpair_syscall(cage.clone(), AF_UNIX, SOCK_STREAM, 0, &mut socketpair),
+            0
+        );
+        let (sock1, sock2) = (socketpair.sock1, socketpair.sock2);
+        let mut readbuf = [0u8; 16];
+
+        //what is waiting when reads are shut down is thrown away, and recv finds the end at once
+        assert_eq!(cage.send_syscall(sock1, str2cbuf("hello"), 5, 0), 5);
+        assert_eq!(cage.netshutdown_syscall(sock2, SHUT_RD), 0);
+        assert_eq!(cage.recv_syscall(sock2, readbuf.as_mut_ptr(), 16, 0), 0);
+        assert_eq!(
+            cage.recv_syscall(sock2, readbuf.as_mut_ptr(), 16, MSG_PEEK),
+            0
+        );
+        //and the peer can no longer send to it
+        assert_eq!(
+            cage.send_syscall(sock1, str2cbuf("hello"), 5, MSG_NOSIGNAL),
+            -(Errno::EPIPE as i32)
+        );
+
+        //the other direction still works until writes are shut down too
+        assert_eq!(cage.send_syscall(sock2, str2cbuf("world"), 5, 0), 5);
+        assert_eq!(cage.netshutdown_syscall(sock2, SHUT_WR), 0);
+        assert_eq!(cage.recv_syscall(sock1, readbuf.as_mut_ptr(), 16, 0), 5);
+        assert_eq!(&readbuf[..5], b"world");
+        assert_eq!(cage.recv_syscall(sock1, readbuf.as_mut_ptr(), 16, 0), 0);
+
+        //a send after SHUT_WR fails with EPIPE
+        assert_eq!(cage.netshutdown_syscall(sock1, SHUT_WR), 0);
+        assert_eq!(
+            cage.send_syscall(sock1, str2cbuf("again"), 5, MSG_NOSIGNAL),
+            -(Errno::EPIPE as i32)
+        );
+
+        //with both halves shut down, in either order, recv finds the end and send fails with EPIPE
+        assert_eq!(cage.netshutdown_syscall(sock1, SHUT_RD), 0);
+        for sock in [sock1, sock2] {
+            assert_eq!(cage.recv_syscall(sock, readbuf.as_mut_ptr(), 16, 0), 0);
+            assert_eq!(
+                cage.send_syscall(sock, str2cbuf("again"), 5, MSG_NOSIGNAL),
+                -(Errno::EPIPE as i32)
+            );
+        }
+
+        assert_eq!(cage.close_syscall(sock1), 0);
+        assert_eq!(cage.close_syscall(sock2), 0);
+        assert_eq!(cage.exit_syscall(EXIT_SUCCESS), EXIT_SUCCESS);
+        lindrustfinalize();
+    }
 }
@@ -120,24 +120,51 @@ fn main() {
     let mut args = env::args();
     let utilcage = Cage {
         cageid: 0,
-        cwd: interface::RustLock::new(interface::RustRfc::new(interface::RustPathBuf::from("/"))),
+        cwd: interface::RustRfc::new(interface::RustLock::new(interface::RustRfc::new(
+            interface::RustPathBuf::from("/"),
+        ))),
         parent: 0,
+        pgid: interface::RustAtomicU64::new(0),
+        sid: interface::RustAtomicU64::new(0),
+        nice: interface::RustAtomicI32::new(0),
+        cpu_affinity: interface::RustHashMap::new(),
         filedescriptortable: init_fdtable(),
-        cancelstatus: interface::RustAtomicBool::new(false),
+        fdtable_users: interface::RustRfc::new(interface::RustAtomicUsize::new(1)),
+        epoll_registrations: interface::RustRfc::new(interface::RustHashMap::new()),
+        cancel_registry: CancelRegistry::new(),
         getgid: interface::RustAtomicI32::new(-1),
         getuid: interface::RustAtomicI32::new(-1),
         getegid: interface::RustAtomicI32::new(-1),
         geteuid: interface::RustAtomicI32::new(-1),
+        cred: interface::RustLock::new(Credentials::new(DEFAULT_UID, DEFAULT_GID)),
         rev_shm: interface::Mutex::new(vec![]),
         mutex_table: interface::RustLock::new(vec![]),
         cv_table: interface::RustLock::new(vec![]),
         sem_table: interface::RustHashMap::new(),
         thread_table: interface::RustHashMap::new(),
-        signalhandler: interface::RustHashMap::new(),
+        signalhandler: interface::RustRfc::new(interface::RustHashMap::new()),
         sigset: interface::RustHashMap::new(),
         pendingsigset: interface::RustHashMap::new(),
+        sharedpendingsigset: interface::RustAtomicU64::new(0),
+        sigqueue: interface::Mutex::new(interface::RustBTreeMap::new()),
         main_threadid: interface::RustAtomicU64::new(0),
         interval_timer: interface::IntervalTimer::new(0),
+        cpu_usage: interface::CpuUsage::new(),
+        memory_region: interface::RustLock::new(None),
+        posix_timers: interface::RustLock::new(vec![]),
+        file_mappings: interface::Mutex::new(vec![]),
+        rootdir: interface::RustRfc::new(interface::RustLock::new(interface::RustRfc::new(
+            interface::RustPathBuf::from("/"),
+        ))),
+        fs_users: interface::RustRfc::new(interface::RustAtomicUsize::new(1)),
+        environ: interface::RustLock::new(vec![]),
+        syscall_filter: interface::RustLock::new(SyscallFilter::new()),
+        quota: interface::RustLock::new(None),
+        zombies: interface::Mutex::new(vec![]),
+        zombie_cv: interface::Condvar::new(),
+        select_epoll: interface::Mutex::new(interface::KernelEpollCache::new()),
+        max_watched_fds: interface::RustAtomicI32::new(MAX_WATCHED_FDS),
+        context: safeposix::context::current_context(),
     };
 
     args.next(); //first arg is executable, we don't care